@@ -0,0 +1,280 @@
+//! `stakk graph check --against-remote`: whether GitHub still matches the
+//! local stacks, and which command brings it back in line.
+//!
+//! Every bookmark of a stack should have an open PR based on the bookmark
+//! below it, a remote branch at its local commit, and (when stacks are
+//! shown) a stack comment listing the stack's current PRs. `stakk submit`
+//! fixes all of these; `stakk comment refresh` fixes the stack comments
+//! alone without pushing.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use stakk_core::forge::PullRequest;
+use stakk_core::submit::base_chain::BaseChain;
+
+/// What GitHub shows of one bookmark of a stack.
+#[derive(Debug, Clone, Default)]
+pub struct RemoteView {
+    pub pr: Option<PullRequest>,
+    /// Whether the remote branch is at the local bookmark's commit.
+    pub in_sync: bool,
+    /// The bookmarks the PR's stack comment or body section lists, if it
+    /// has one.
+    pub listed: Option<Vec<String>>,
+}
+
+/// A way the remote has drifted from a stack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    MissingPr {
+        bookmark: String,
+    },
+    WrongBase {
+        bookmark: String,
+        pr_number: u64,
+        base: String,
+        expected: String,
+    },
+    BranchOutOfSync {
+        bookmark: String,
+    },
+    MissingStack {
+        pr_number: u64,
+    },
+    StaleStack {
+        pr_number: u64,
+        listed: Vec<String>,
+    },
+}
+
+impl Problem {
+    /// Whether `stakk comment refresh` fixes this, rather than only
+    /// `stakk submit`.
+    fn fixed_by_refresh(&self) -> bool {
+        matches!(self, Self::MissingStack { .. } | Self::StaleStack { .. })
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingPr { bookmark } => write!(f, "{bookmark} has no open PR"),
+            Self::WrongBase {
+                bookmark,
+                pr_number,
+                base,
+                expected,
+            } => write!(
+                f,
+                "PR #{pr_number} ({bookmark}) is based on {base}, not {expected}"
+            ),
+            Self::BranchOutOfSync { bookmark } => {
+                write!(
+                    f,
+                    "the remote branch {bookmark} is not at the local bookmark"
+                )
+            }
+            Self::MissingStack { pr_number } => {
+                write!(f, "PR #{pr_number} does not list its stack")
+            }
+            Self::StaleStack { pr_number, listed } => write!(
+                f,
+                "PR #{pr_number} lists an outdated stack: {}",
+                listed.join(", ")
+            ),
+        }
+    }
+}
+
+/// The drift of one stack, named by its leaf bookmark.
+#[derive(Debug, Clone)]
+pub struct StackAudit {
+    pub leaf: String,
+    pub problems: Vec<Problem>,
+}
+
+impl StackAudit {
+    /// The command that fixes every problem of the stack, if it has any.
+    pub fn fix(&self) -> Option<String> {
+        if self.problems.is_empty() {
+            None
+        } else if self.problems.iter().all(Problem::fixed_by_refresh) {
+            Some(format!("stakk comment refresh {}", self.leaf))
+        } else {
+            Some(format!("stakk submit {}", self.leaf))
+        }
+    }
+}
+
+/// Compare a stack's bookmarks, trunk to leaf, with what the remote shows
+/// of each. `known` holds every bookmark in the change graph: a stack
+/// comment may list the bookmarks of other stacks through a shared PR, but
+/// not ones that are gone. With `check_stacks` off, stack comments are not
+/// looked at.
+pub fn audit_stack(
+    bookmarks: &[String],
+    default_branch: &str,
+    views: &[RemoteView],
+    known: &HashSet<&str>,
+    check_stacks: bool,
+) -> StackAudit {
+    let chain = BaseChain::submitted(default_branch, bookmarks.iter().map(String::as_str));
+    let with_pr: Vec<&str> = bookmarks
+        .iter()
+        .zip(views)
+        .filter(|(_, view)| view.pr.is_some())
+        .map(|(bookmark, _)| bookmark.as_str())
+        .collect();
+
+    let mut problems = Vec::new();
+    for ((bookmark, expected), view) in chain.bases().into_iter().zip(views) {
+        if !view.in_sync {
+            problems.push(Problem::BranchOutOfSync {
+                bookmark: bookmark.to_string(),
+            });
+        }
+        let Some(pr) = &view.pr else {
+            problems.push(Problem::MissingPr {
+                bookmark: bookmark.to_string(),
+            });
+            continue;
+        };
+        if pr.base_ref != expected {
+            problems.push(Problem::WrongBase {
+                bookmark: bookmark.to_string(),
+                pr_number: pr.number,
+                base: pr.base_ref.clone(),
+                expected: expected.to_string(),
+            });
+        }
+        if !check_stacks {
+            continue;
+        }
+        match &view.listed {
+            None if with_pr.len() > 1 => problems.push(Problem::MissingStack {
+                pr_number: pr.number,
+            }),
+            None => {}
+            Some(listed) => {
+                let missing = with_pr.iter().any(|b| !listed.iter().any(|l| l == b));
+                let gone = listed.iter().any(|l| {
+                    !known.contains(l.as_str())
+                        || (bookmarks.contains(l) && !with_pr.contains(&l.as_str()))
+                });
+                if with_pr.len() == 1 || missing || gone {
+                    problems.push(Problem::StaleStack {
+                        pr_number: pr.number,
+                        listed: listed.clone(),
+                    });
+                }
+            }
+        }
+    }
+    StackAudit {
+        leaf: bookmarks.last().cloned().unwrap_or_default(),
+        problems,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use stakk_core::forge::PrState;
+
+    use super::*;
+
+    fn view(number: u64, head: &str, base: &str, listed: &[&str]) -> RemoteView {
+        RemoteView {
+            pr: Some(PullRequest {
+                number,
+                html_url: format!("https://github.com/o/r/pull/{number}"),
+                title: head.to_string(),
+                head_ref: head.to_string(),
+                base_ref: base.to_string(),
+                state: PrState::Open,
+                body: None,
+                draft: false,
+            }),
+            in_sync: true,
+            listed: Some(listed.iter().map(ToString::to_string).collect()),
+        }
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn consistent_stack_needs_no_fix() {
+        let bookmarks = names(&["a", "b"]);
+        let known = HashSet::from(["a", "b", "other"]);
+        let views = [
+            view(1, "a", "main", &["a", "b"]),
+            // A shared PR may list another stack's bookmarks too.
+            view(2, "b", "a", &["a", "b", "other"]),
+        ];
+        let audit = audit_stack(&bookmarks, "main", &views, &known, true);
+        assert!(audit.problems.is_empty());
+        assert_eq!(audit.fix(), None);
+    }
+
+    #[test]
+    fn stale_comments_are_fixed_by_refresh() {
+        let bookmarks = names(&["a", "b"]);
+        let known = HashSet::from(["a", "b"]);
+        let views = [
+            view(1, "a", "main", &["merged", "a", "b"]),
+            RemoteView {
+                listed: None,
+                ..view(2, "b", "a", &[])
+            },
+        ];
+        let audit = audit_stack(&bookmarks, "main", &views, &known, true);
+        assert_eq!(
+            audit.problems,
+            [
+                Problem::StaleStack {
+                    pr_number: 1,
+                    listed: names(&["merged", "a", "b"]),
+                },
+                Problem::MissingStack { pr_number: 2 },
+            ]
+        );
+        assert_eq!(audit.fix().as_deref(), Some("stakk comment refresh b"));
+        assert!(
+            audit_stack(&bookmarks, "main", &views, &known, false)
+                .problems
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn missing_prs_and_wrong_bases_need_a_submit() {
+        let bookmarks = names(&["a", "b", "c"]);
+        let known = HashSet::from(["a", "b", "c"]);
+        let views = [
+            view(1, "a", "main", &["a", "c"]),
+            RemoteView::default(),
+            view(3, "c", "main", &["a", "c"]),
+        ];
+        let audit = audit_stack(&bookmarks, "main", &views, &known, true);
+        assert_eq!(
+            audit.problems,
+            [
+                Problem::BranchOutOfSync {
+                    bookmark: "b".to_string()
+                },
+                Problem::MissingPr {
+                    bookmark: "b".to_string()
+                },
+                Problem::WrongBase {
+                    bookmark: "c".to_string(),
+                    pr_number: 3,
+                    base: "main".to_string(),
+                    expected: "b".to_string(),
+                },
+            ]
+        );
+        assert_eq!(audit.fix().as_deref(), Some("stakk submit c"));
+    }
+}
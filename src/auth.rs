@@ -1,30 +1,53 @@
 //! GitHub authentication token resolution.
 //!
 //! Resolves a token in priority order:
-//! 1. `gh auth token` (GitHub CLI)
-//! 2. `GITHUB_TOKEN` environment variable
-//! 3. `GH_TOKEN` environment variable
+//! 1. The system keyring (stored by `stakk auth login`)
+//! 2. `gh auth token` (GitHub CLI)
+//! 3. `GITHUB_TOKEN` environment variable
+//! 4. `GH_TOKEN` environment variable
+//!
+//! With `transport = "gh"` in the `[forge]` config table, no token is
+//! resolved: every request goes through `gh api` instead.
+
+use std::sync::OnceLock;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
 
 use miette::Diagnostic;
+use octocrab::Octocrab;
+use octocrab::auth::DeviceCodes;
+use octocrab::auth::OAuth;
+use secrecy::ExposeSecret;
+use secrecy::SecretString;
+use serde::Deserialize;
+use serde::Serialize;
+use stakk_core::forge::github::Credentials;
+use stakk_core::forge::github::Transport;
 use thiserror::Error;
 
 /// How the token was obtained.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TokenSource {
+    /// From the system keyring, stored by `stakk auth login`.
+    Keyring,
     /// From `gh auth token`.
     GitHubCli,
     /// From `GITHUB_TOKEN` environment variable.
     GitHubTokenEnv,
     /// From `GH_TOKEN` environment variable.
     GhTokenEnv,
+    /// No token: requests go through `gh api` (`transport = "gh"`).
+    GhApi,
 }
 
 impl std::fmt::Display for TokenSource {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            Self::Keyring => write!(f, "system keyring (stakk auth login)"),
             Self::GitHubCli => write!(f, "GitHub CLI (gh auth token)"),
             Self::GitHubTokenEnv => write!(f, "GITHUB_TOKEN environment variable"),
             Self::GhTokenEnv => write!(f, "GH_TOKEN environment variable"),
+            Self::GhApi => write!(f, "GitHub CLI (gh api)"),
         }
     }
 }
@@ -36,13 +59,29 @@ pub struct AuthToken {
     pub source: TokenSource,
 }
 
+/// What requests to GitHub authenticate with, and where it came from.
+#[derive(Debug, Clone)]
+pub struct Login {
+    pub credentials: Credentials,
+    pub source: TokenSource,
+}
+
+/// How the GitHub API is reached, from the `[forge]` config table. Set
+/// once at startup.
+static TRANSPORT: OnceLock<Transport> = OnceLock::new();
+
+/// Reach the GitHub API through `transport` from now on.
+pub fn set_transport(transport: Transport) {
+    let _ = TRANSPORT.set(transport);
+}
+
 /// Errors from authentication resolution.
 #[derive(Debug, Error, Diagnostic)]
 pub enum AuthError {
     #[error("no GitHub authentication found")]
     #[diagnostic(
         code(stakk::auth::no_token),
-        help("run `gh auth login` or set GITHUB_TOKEN/GH_TOKEN")
+        help("run `stakk auth login` or `gh auth login`, or set GITHUB_TOKEN/GH_TOKEN")
     )]
     NoAuthFound,
 
@@ -52,17 +91,208 @@ pub enum AuthError {
         help("install the `gh` CLI, or set GITHUB_TOKEN/GH_TOKEN to skip it")
     )]
     GhCliError(std::io::Error),
+
+    #[error("could not access the system keyring: {0}")]
+    #[diagnostic(
+        code(stakk::auth::keyring_error),
+        help(
+            "make sure a keyring service (e.g. GNOME Keyring or KWallet) is running and unlocked, \
+             or set GITHUB_TOKEN/GH_TOKEN instead"
+        )
+    )]
+    KeyringError(keyring::Error),
+
+    #[error("no token provided")]
+    #[diagnostic(
+        code(stakk::auth::empty_token),
+        help("paste a GitHub personal access token with `repo` scope")
+    )]
+    EmptyToken,
+
+    #[error("no OAuth client ID for the device flow")]
+    #[diagnostic(
+        code(stakk::auth::missing_client_id),
+        help(
+            "register a GitHub OAuth app with device flow enabled and pass its client ID via \
+             --client-id or STAKK_GITHUB_CLIENT_ID"
+        )
+    )]
+    MissingClientId,
+
+    #[error("GitHub device flow login failed: {0}")]
+    #[diagnostic(
+        code(stakk::auth::device_flow_failed),
+        help("check the client ID and that device flow is enabled for the OAuth app, then retry")
+    )]
+    DeviceFlowFailed(octocrab::Error),
+
+    #[error("could not refresh the stored GitHub token: {0}")]
+    #[diagnostic(
+        code(stakk::auth::refresh_failed),
+        help("run `stakk auth login --device` again")
+    )]
+    RefreshFailed(octocrab::Error),
+
+    #[error("GitHub refused to refresh the stored token: {0}")]
+    #[diagnostic(
+        code(stakk::auth::refresh_rejected),
+        help(
+            "run `stakk auth login --device` again, with --client-secret if the app requires one"
+        )
+    )]
+    RefreshRejected(String),
+}
+
+/// Keyring service name under which `stakk auth login` stores the token.
+const KEYRING_SERVICE: &str = "stakk";
+/// Keyring user name for the stored token.
+const KEYRING_USER: &str = "github.com";
+/// Refresh device-flow tokens this many seconds before they expire.
+const REFRESH_MARGIN_SECS: u64 = 300;
+
+/// A token stored in the keyring by `stakk auth login`.
+///
+/// Personal access tokens are stored as the bare token. Device-flow tokens are
+/// stored as JSON, together with what is needed to refresh them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct StoredToken {
+    access_token: String,
+    refresh_token: Option<String>,
+    /// Unix time (seconds) at which `access_token` expires.
+    expires_at: Option<u64>,
+    /// OAuth app the token was issued to.
+    client_id: Option<String>,
+    /// Secret of that app, for apps that require it to refresh tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    client_secret: Option<String>,
+}
+
+impl StoredToken {
+    /// Parse a keyring secret, treating anything that is not a stored
+    /// device-flow token as a bare personal access token.
+    fn parse(secret: &str) -> Self {
+        serde_json::from_str(secret).unwrap_or_else(|_| Self {
+            access_token: secret.to_string(),
+            refresh_token: None,
+            expires_at: None,
+            client_id: None,
+            client_secret: None,
+        })
+    }
+
+    fn from_oauth(oauth: &OAuth, client_id: &str, client_secret: Option<&str>, now: u64) -> Self {
+        Self {
+            access_token: oauth.access_token.expose_secret().to_string(),
+            refresh_token: oauth
+                .refresh_token
+                .as_ref()
+                .map(|t| t.expose_secret().to_string()),
+            expires_at: oauth.expires_in.map(|secs| now + secs as u64),
+            client_id: Some(client_id.to_string()),
+            client_secret: client_secret.map(ToString::to_string),
+        }
+    }
+
+    /// Whether the token expires within [`REFRESH_MARGIN_SECS`] and can be
+    /// refreshed.
+    fn needs_refresh(&self, now: u64) -> bool {
+        self.refresh_token.is_some()
+            && self.client_id.is_some()
+            && self
+                .expires_at
+                .is_some_and(|at| now + REFRESH_MARGIN_SECS >= at)
+    }
+}
+
+/// A device flow login in progress: the user must enter
+/// [`user_code`](Self::user_code) at
+/// [`verification_uri`](Self::verification_uri) before
+/// [`finish`](Self::finish) returns.
+pub struct DeviceLogin {
+    crab: Octocrab,
+    client_id: SecretString,
+    client_secret: Option<SecretString>,
+    codes: DeviceCodes,
+}
+
+impl DeviceLogin {
+    /// Request device and user codes for the OAuth app `client_id`. The
+    /// app's `client_secret`, if given, is kept with the token to refresh
+    /// it.
+    pub async fn start(client_id: &str, client_secret: Option<&str>) -> Result<Self, AuthError> {
+        let crab = oauth_client().map_err(AuthError::DeviceFlowFailed)?;
+        let client_id = SecretString::from(client_id.to_string());
+        let codes = crab
+            .authenticate_as_device(&client_id, ["repo"])
+            .await
+            .map_err(AuthError::DeviceFlowFailed)?;
+        Ok(Self {
+            crab,
+            client_id,
+            client_secret: client_secret.map(|s| SecretString::from(s.to_string())),
+            codes,
+        })
+    }
+
+    pub fn user_code(&self) -> &str {
+        &self.codes.user_code
+    }
+
+    pub fn verification_uri(&self) -> &str {
+        &self.codes.verification_uri
+    }
+
+    /// Wait for the user to authorize the app, then store the token in the
+    /// system keyring.
+    pub async fn finish(self) -> Result<(), AuthError> {
+        let oauth = self
+            .codes
+            .poll_until_available(&self.crab, &self.client_id)
+            .await
+            .map_err(AuthError::DeviceFlowFailed)?;
+        let stored = StoredToken::from_oauth(
+            &oauth,
+            self.client_id.expose_secret(),
+            self.client_secret.as_ref().map(ExposeSecret::expose_secret),
+            unix_now(),
+        );
+        save_stored_token(&stored).await
+    }
+}
+
+/// Resolve what to authenticate GitHub requests with: gh's own login when
+/// the transport is `gh`, otherwise a token from [`resolve_token`].
+pub async fn resolve_login() -> Result<Login, AuthError> {
+    if TRANSPORT.get().copied().unwrap_or_default() == Transport::Gh {
+        return Ok(Login {
+            credentials: Credentials::GhCli,
+            source: TokenSource::GhApi,
+        });
+    }
+    let token = resolve_token().await?;
+    Ok(Login {
+        credentials: Credentials::Token(token.token),
+        source: token.source,
+    })
 }
 
 /// Resolve a GitHub authentication token.
 ///
-/// Tries sources in order: gh CLI, `GITHUB_TOKEN` env, `GH_TOKEN` env.
-/// Returns the first token found, or `AuthError::NoAuthFound`.
+/// Tries sources in order: system keyring, gh CLI, `GITHUB_TOKEN` env,
+/// `GH_TOKEN` env. Returns the first token found, or `AuthError::NoAuthFound`.
 ///
 /// This does NOT validate the token against the GitHub API.
 /// Use `Forge::get_authenticated_user()` to validate.
 pub async fn resolve_token() -> Result<AuthToken, AuthError> {
-    // 1. Try `gh auth token`
+    // 1. Try the system keyring
+    if let Some(token) = try_keyring().await? {
+        return Ok(AuthToken {
+            token,
+            source: TokenSource::Keyring,
+        });
+    }
+
+    // 2. Try `gh auth token`
     if let Some(token) = try_gh_cli().await? {
         return Ok(AuthToken {
             token,
@@ -70,7 +300,7 @@ pub async fn resolve_token() -> Result<AuthToken, AuthError> {
         });
     }
 
-    // 2. Try GITHUB_TOKEN
+    // 3. Try GITHUB_TOKEN
     if let Ok(token) = std::env::var("GITHUB_TOKEN")
         && !token.is_empty()
     {
@@ -80,7 +310,7 @@ pub async fn resolve_token() -> Result<AuthToken, AuthError> {
         });
     }
 
-    // 3. Try GH_TOKEN
+    // 4. Try GH_TOKEN
     if let Ok(token) = std::env::var("GH_TOKEN")
         && !token.is_empty()
     {
@@ -93,6 +323,142 @@ pub async fn resolve_token() -> Result<AuthToken, AuthError> {
     Err(AuthError::NoAuthFound)
 }
 
+/// Store `token` in the system keyring, replacing any previously stored one.
+pub async fn store_token(token: &str) -> Result<(), AuthError> {
+    let token = token.trim().to_string();
+    if token.is_empty() {
+        return Err(AuthError::EmptyToken);
+    }
+    with_keyring_entry(move |entry| entry.set_password(&token))
+        .await
+        .map_err(AuthError::KeyringError)
+}
+
+/// Try to get the token stored by `stakk auth login`, refreshing it first if
+/// it is a device-flow token about to expire.
+///
+/// Returns `Ok(None)` when nothing is stored or the keyring is unavailable
+/// (e.g. a headless machine without a secret service), so that resolution
+/// falls through to the other sources. So does a refresh that fails once
+/// the token has expired, with a warning; before that, the old token is
+/// still used.
+async fn try_keyring() -> Result<Option<String>, AuthError> {
+    let Some(secret) = with_keyring_entry(keyring::Entry::get_password)
+        .await
+        .ok()
+        .filter(|secret| !secret.is_empty())
+    else {
+        return Ok(None);
+    };
+
+    let stored = StoredToken::parse(&secret);
+    if !stored.needs_refresh(unix_now()) {
+        return Ok(Some(stored.access_token));
+    }
+
+    let now = unix_now();
+    let refreshed = match refresh_token(&stored).await {
+        Ok(refreshed) => refreshed,
+        Err(e) => {
+            eprintln!("Warning: {e}; run `stakk auth login --device` again.");
+            let unexpired = stored.expires_at.is_some_and(|at| now < at);
+            return Ok(unexpired.then_some(stored.access_token));
+        }
+    };
+    // GitHub refresh tokens are single-use, so the new pair must be saved.
+    save_stored_token(&refreshed).await?;
+    Ok(Some(refreshed.access_token))
+}
+
+/// Exchange the refresh token of `stored` for a new access token.
+async fn refresh_token(stored: &StoredToken) -> Result<StoredToken, AuthError> {
+    #[derive(Serialize)]
+    struct RefreshRequest<'a> {
+        client_id: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        client_secret: Option<&'a str>,
+        grant_type: &'a str,
+        refresh_token: &'a str,
+    }
+
+    let client_id = stored.client_id.as_deref().unwrap_or_default();
+    let client_secret = stored.client_secret.as_deref();
+    let crab = oauth_client().map_err(AuthError::RefreshFailed)?;
+    let reply: serde_json::Value = crab
+        .post(
+            "/login/oauth/access_token",
+            Some(&RefreshRequest {
+                client_id,
+                client_secret,
+                grant_type: "refresh_token",
+                refresh_token: stored.refresh_token.as_deref().unwrap_or_default(),
+            }),
+        )
+        .await
+        .map_err(AuthError::RefreshFailed)?;
+    let oauth = oauth_reply(reply)?;
+    Ok(StoredToken::from_oauth(
+        &oauth,
+        client_id,
+        client_secret,
+        unix_now(),
+    ))
+}
+
+/// The token in a reply from GitHub's token endpoint, which reports errors
+/// such as an expired refresh token as `200 OK` with an `error` field.
+fn oauth_reply(reply: serde_json::Value) -> Result<OAuth, AuthError> {
+    if let Some(error) = reply.get("error").and_then(serde_json::Value::as_str) {
+        let description = reply
+            .get("error_description")
+            .and_then(serde_json::Value::as_str);
+        return Err(AuthError::RefreshRejected(match description {
+            Some(description) => format!("{description} ({error})"),
+            None => error.to_string(),
+        }));
+    }
+    serde_json::from_value(reply).map_err(|e| AuthError::RefreshRejected(e.to_string()))
+}
+
+/// An unauthenticated client for GitHub's OAuth endpoints, which live on
+/// github.com rather than the API host and answer JSON only when asked to.
+fn oauth_client() -> Result<Octocrab, octocrab::Error> {
+    Octocrab::builder()
+        .base_uri("https://github.com")?
+        .add_header(http::header::ACCEPT, "application/json".to_string())
+        .build()
+}
+
+async fn save_stored_token(stored: &StoredToken) -> Result<(), AuthError> {
+    let secret = serde_json::to_string(stored).expect("StoredToken serializes to JSON");
+    with_keyring_entry(move |entry| entry.set_password(&secret))
+        .await
+        .map_err(AuthError::KeyringError)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Run a blocking keyring operation on the stakk entry.
+///
+/// Keyring calls run on a blocking thread: the secret-service backend drives
+/// its own async I/O and can deadlock on a tokio worker thread.
+async fn with_keyring_entry<T, F>(op: F) -> Result<T, keyring::Error>
+where
+    T: Send + 'static,
+    F: FnOnce(&keyring::Entry) -> Result<T, keyring::Error> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)?;
+        op(&entry)
+    })
+    .await
+    .map_err(|e| keyring::Error::PlatformFailure(Box::new(e)))?
+}
+
 /// Try to get a token from the GitHub CLI.
 ///
 /// Returns `Ok(None)` if gh is not installed or not authenticated.
@@ -122,6 +488,14 @@ async fn try_gh_cli() -> Result<Option<String>, AuthError> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn token_source_display_keyring() {
+        assert_eq!(
+            TokenSource::Keyring.to_string(),
+            "system keyring (stakk auth login)"
+        );
+    }
+
     #[test]
     fn token_source_display_github_cli() {
         assert_eq!(
@@ -154,8 +528,88 @@ mod tests {
         // Actionable advice is in the miette diagnostic help.
         let help = miette::Diagnostic::help(&err).expect("NoAuthFound should have diagnostic help");
         let help_text = help.to_string();
+        assert!(help_text.contains("stakk auth login"));
         assert!(help_text.contains("gh auth login"));
         assert!(help_text.contains("GITHUB_TOKEN"));
         assert!(help_text.contains("GH_TOKEN"));
     }
+
+    #[test]
+    fn stored_token_parses_bare_pat() {
+        let stored = StoredToken::parse("ghp_abc");
+        assert_eq!(stored.access_token, "ghp_abc");
+        assert!(!stored.needs_refresh(u64::MAX - REFRESH_MARGIN_SECS));
+    }
+
+    #[test]
+    fn stored_token_round_trips_device_token() {
+        let stored = StoredToken {
+            access_token: "ghu_abc".to_string(),
+            refresh_token: Some("ghr_def".to_string()),
+            expires_at: Some(1_000),
+            client_id: Some("Iv1.client".to_string()),
+            client_secret: Some("s3cret".to_string()),
+        };
+        let secret = serde_json::to_string(&stored).unwrap();
+        assert_eq!(StoredToken::parse(&secret), stored);
+    }
+
+    #[test]
+    fn stored_token_refreshes_shortly_before_expiry() {
+        let stored = StoredToken {
+            access_token: "ghu_abc".to_string(),
+            refresh_token: Some("ghr_def".to_string()),
+            expires_at: Some(10_000),
+            client_id: Some("Iv1.client".to_string()),
+            client_secret: None,
+        };
+        assert!(!stored.needs_refresh(10_000 - REFRESH_MARGIN_SECS - 1));
+        assert!(stored.needs_refresh(10_000 - REFRESH_MARGIN_SECS));
+
+        let no_refresh_token = StoredToken {
+            refresh_token: None,
+            ..stored
+        };
+        assert!(!no_refresh_token.needs_refresh(20_000));
+    }
+
+    #[test]
+    fn stored_token_without_a_secret_still_parses() {
+        let stored = StoredToken::parse(
+            r#"{"access_token":"ghu_abc","refresh_token":"ghr_def","expires_at":1,"client_id":"Iv1.c"}"#,
+        );
+        assert_eq!(stored.refresh_token.as_deref(), Some("ghr_def"));
+        assert_eq!(stored.client_secret, None);
+    }
+
+    #[test]
+    fn oauth_error_replies_are_rejections() {
+        let Err(err) = oauth_reply(serde_json::json!({
+            "error": "bad_refresh_token",
+            "error_description": "The refresh token passed is incorrect or expired.",
+        })) else {
+            panic!("an error reply must not parse as a token");
+        };
+        assert_eq!(
+            err.to_string(),
+            "GitHub refused to refresh the stored token: The refresh token passed is incorrect or \
+             expired. (bad_refresh_token)"
+        );
+
+        let oauth = oauth_reply(serde_json::json!({
+            "access_token": "ghu_new",
+            "token_type": "bearer",
+            "scope": "",
+            "refresh_token": "ghr_new",
+            "expires_in": 28_800,
+        }))
+        .unwrap();
+        assert_eq!(oauth.access_token.expose_secret(), "ghu_new");
+    }
+
+    #[tokio::test]
+    async fn store_token_rejects_empty_input() {
+        let err = store_token("  \n").await.unwrap_err();
+        assert!(matches!(err, AuthError::EmptyToken));
+    }
 }
@@ -0,0 +1,96 @@
+//! Warning for commits authored under an email the GitHub account has not
+//! verified.
+//!
+//! GitHub attributes a commit to an account by its author email. Commits
+//! under an unverified email do not count toward the account's
+//! contributions and fail DCO checks that match sign-offs to accounts.
+
+use stakk_core::forge::Forge;
+use stakk_core::submit::SubmissionAnalysis;
+
+/// The domain of GitHub's private commit email addresses.
+const NOREPLY_DOMAIN: &str = "@users.noreply.github.com";
+
+/// The author emails in `authors` that are neither one of `verified` nor a
+/// private commit address of `login`, deduplicated and sorted. Emails
+/// compare case-insensitively.
+pub fn unverified_authors<'a>(
+    authors: impl IntoIterator<Item = &'a str>,
+    verified: &[String],
+    login: &str,
+) -> Vec<&'a str> {
+    let mut unverified: Vec<&str> = authors
+        .into_iter()
+        .filter(|email| {
+            !verified.iter().any(|v| v.eq_ignore_ascii_case(email)) && !is_noreply_for(email, login)
+        })
+        .collect();
+    unverified.sort_unstable();
+    unverified.dedup();
+    unverified
+}
+
+/// Whether `email` is `login`'s private commit address, with or without
+/// the account ID prefix (`123+login@users.noreply.github.com`).
+fn is_noreply_for(email: &str, login: &str) -> bool {
+    let email = email.to_ascii_lowercase();
+    let Some(local) = email.strip_suffix(NOREPLY_DOMAIN) else {
+        return false;
+    };
+    let name = match local.split_once('+') {
+        Some((id, name)) if id.bytes().all(|b| b.is_ascii_digit()) => name,
+        _ => local,
+    };
+    name.eq_ignore_ascii_case(login)
+}
+
+/// Warn if any commit in `analysis` is authored under an email that is not
+/// verified on the authenticated account. A token that may not read the
+/// account's emails skips the check.
+pub async fn warn_unverified_authors<F: Forge>(forge: &F, analysis: &SubmissionAnalysis) {
+    let (Ok(login), Ok(verified)) =
+        futures::future::join(forge.get_authenticated_user(), forge.get_user_emails()).await
+    else {
+        return;
+    };
+    let authors = analysis
+        .segments
+        .iter()
+        .flat_map(|segment| &segment.commits)
+        .map(|commit| commit.author.email.as_str());
+    let unverified = unverified_authors(authors, &verified, &login);
+    if unverified.is_empty() {
+        return;
+    }
+    eprintln!(
+        "Warning: commits are authored as {}, which is not a verified email of the GitHub account \
+         '{login}'. They will not count toward its contributions.",
+        unverified.join(", ")
+    );
+    eprintln!(
+        "  Set user.email in your jj config, or silence this with --no-author-check or \
+         `author_check = false` in the repository's stakk.toml."
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verified_and_noreply_emails_pass() {
+        let verified = vec!["Me@Example.com".to_string()];
+        let authors = [
+            "me@example.com",
+            "me@work.example",
+            "42+me@users.noreply.github.com",
+            "me@users.noreply.github.com",
+            "42+other@users.noreply.github.com",
+            "me@work.example",
+        ];
+        assert_eq!(
+            unverified_authors(authors, &verified, "me"),
+            ["42+other@users.noreply.github.com", "me@work.example"]
+        );
+    }
+}
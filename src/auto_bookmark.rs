@@ -0,0 +1,187 @@
+//! `stakk submit --auto-bookmark` and `--per-commit`: bookmark a stack
+//! without the TUI.
+//!
+//! Every segment from trunk up to the target that has no bookmark (with
+//! `--per-commit`, every commit) gets one named after its tip commit's
+//! summary, through a pattern such as `jo/{slug}`. The result has the same
+//! shape as the TUI's, so submit creates the new bookmarks the same way
+//! either way.
+
+use std::collections::HashSet;
+
+use stakk_core::graph::types::BookmarkSegment;
+
+use crate::select::BookmarkAssignment;
+use crate::select::SelectionResult;
+use crate::select::bookmark_gen;
+use crate::select::bookmark_gen::BookmarkGenError;
+
+/// Longest slug taken from a commit summary, in bytes.
+const MAX_SLUG_LENGTH: usize = 50;
+
+/// The summary's ASCII words, lowercased and joined with hyphens, cut at a
+/// word boundary to at most [`MAX_SLUG_LENGTH`] bytes.
+pub fn slugify(summary: &str) -> String {
+    let mut slug = String::new();
+    let words = summary
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty());
+    for word in words {
+        if !slug.is_empty() {
+            if slug.len() + 1 + word.len() > MAX_SLUG_LENGTH {
+                break;
+            }
+            slug.push('-');
+        }
+        slug.push_str(&word.to_ascii_lowercase());
+    }
+    slug.truncate(MAX_SLUG_LENGTH);
+    slug
+}
+
+/// The bookmark name `pattern` gives `segment`: `{slug}` is replaced with
+/// the slug of the tip commit's summary and `{change_id}` with the first 12
+/// characters of its change ID. A summary without a slug falls back to the
+/// change ID.
+pub fn name_from_pattern(pattern: &str, segment: &BookmarkSegment) -> String {
+    let change_id = &segment.change_id[..segment.change_id.len().min(12)];
+    let summary = segment
+        .commits
+        .first()
+        .and_then(|commit| commit.description.lines().next())
+        .unwrap_or_default();
+    let slug = slugify(summary);
+    let slug = if slug.is_empty() { change_id } else { &slug };
+    pattern
+        .replace("{slug}", slug)
+        .replace("{change_id}", change_id)
+}
+
+/// Assign a bookmark to each of `segments` (trunk to leaf): a segment's
+/// first existing bookmark, or a new one from `pattern`. New names that are
+/// in `taken` or already assigned get a `-2`, `-3`, ... suffix.
+pub fn assign_bookmarks(
+    segments: &[BookmarkSegment],
+    pattern: &str,
+    taken: &HashSet<String>,
+) -> Result<SelectionResult, BookmarkGenError> {
+    let mut used = taken.clone();
+    let mut assignments = Vec::with_capacity(segments.len());
+    for segment in segments {
+        if let Some(existing) = segment.bookmark_names.first() {
+            assignments.push(BookmarkAssignment {
+                change_id: segment.change_id.clone(),
+                bookmark_name: existing.clone(),
+                is_new: false,
+            });
+            continue;
+        }
+
+        let base = name_from_pattern(pattern, segment);
+        let mut name = base.clone();
+        let mut suffix = 2;
+        while used.contains(&name) {
+            name = format!("{base}-{suffix}");
+            suffix += 1;
+        }
+        bookmark_gen::validate_bookmark_name(&name)?;
+        used.insert(name.clone());
+        assignments.push(BookmarkAssignment {
+            change_id: segment.change_id.clone(),
+            bookmark_name: name,
+            is_new: true,
+        });
+    }
+    Ok(SelectionResult { assignments })
+}
+
+#[cfg(test)]
+mod tests {
+    use stakk_core::graph::types::SegmentCommit;
+    use stakk_core::jj::types::Signature;
+
+    use super::*;
+
+    fn segment(bookmark: Option<&str>, change_id: &str, description: &str) -> BookmarkSegment {
+        let signature = Signature {
+            name: String::new(),
+            email: String::new(),
+            timestamp: String::new(),
+        };
+        BookmarkSegment {
+            bookmark_names: bookmark.into_iter().map(ToString::to_string).collect(),
+            change_id: change_id.to_string(),
+            commits: vec![SegmentCommit {
+                commit_id: format!("c_{change_id}"),
+                change_id: change_id.to_string(),
+                description: description.to_string(),
+                author: signature.clone(),
+                committer: signature,
+                short_change_id: change_id[..4].to_string(),
+                files: vec![],
+                is_immutable: false,
+                local_bookmark_names: vec![],
+            }],
+        }
+    }
+
+    #[test]
+    fn slugify_joins_lowercased_words() {
+        assert_eq!(
+            slugify("feat: Add the login page!"),
+            "feat-add-the-login-page"
+        );
+        assert_eq!(slugify("  ---  "), "");
+        assert_eq!(slugify(&"word ".repeat(20)).len(), 49);
+        assert_eq!(slugify(&"x".repeat(80)), "x".repeat(50));
+    }
+
+    #[test]
+    fn name_from_pattern_fills_placeholders() {
+        let seg = segment(None, "qpvuntsmwlqtzyxk", "Fix cache eviction\n\nDetails.");
+        assert_eq!(
+            name_from_pattern("jo/{slug}", &seg),
+            "jo/fix-cache-eviction"
+        );
+        assert_eq!(
+            name_from_pattern("{slug}-{change_id}", &seg),
+            "fix-cache-eviction-qpvuntsmwlqt"
+        );
+        let undescribed = segment(None, "qpvuntsmwlqtzyxk", "");
+        assert_eq!(
+            name_from_pattern("jo/{slug}", &undescribed),
+            "jo/qpvuntsmwlqt"
+        );
+    }
+
+    #[test]
+    fn assign_keeps_existing_and_deduplicates_new_names() {
+        let segments = [
+            segment(Some("base"), "aaaaaaaaaaaaaaaa", "add base"),
+            segment(None, "bbbbbbbbbbbbbbbb", "add parser"),
+            segment(None, "cccccccccccccccc", "add parser"),
+        ];
+        let taken = HashSet::from(["base".to_string(), "add-parser".to_string()]);
+        let result = assign_bookmarks(&segments, "{slug}", &taken).unwrap();
+        let names: Vec<(&str, bool)> = result
+            .assignments
+            .iter()
+            .map(|a| (a.bookmark_name.as_str(), a.is_new))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("base", false),
+                ("add-parser-2", true),
+                ("add-parser-3", true)
+            ]
+        );
+        assert_eq!(result.assignments[1].change_id, "bbbbbbbbbbbbbbbb");
+    }
+
+    #[test]
+    fn assign_rejects_invalid_pattern() {
+        let segments = [segment(None, "aaaaaaaaaaaaaaaa", "add base")];
+        assert!(assign_bookmarks(&segments, "my branch/{slug}", &HashSet::new()).is_err());
+    }
+}
@@ -0,0 +1,450 @@
+//! `stakk backport`: copy a stack onto a release branch.
+//!
+//! The stack's commits are duplicated onto the release branch as it is on
+//! the remote, keeping their order, and the copy of each segment gets a
+//! bookmark named `backport/<branch>/<bookmark>`. The copies are then
+//! submitted as a stack of their own based on the release branch, and each
+//! new PR's body starts by naming the PR it backports.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+
+use miette::Diagnostic;
+use stakk_core::forge::Forge;
+use stakk_core::forge::ForgeError;
+use stakk_core::graph::types::BookmarkSegment;
+use stakk_core::jj::Jj;
+use stakk_core::jj::JjError;
+use stakk_core::jj::runner::JjRunner;
+use stakk_core::jj::types::LogEntry;
+use stakk_core::submit::SubmissionPlan;
+use thiserror::Error;
+
+use crate::segment_test::label;
+
+/// Errors from `stakk backport`.
+#[derive(Debug, Error, Diagnostic)]
+pub enum BackportError {
+    /// Failed to look up the PR of a bookmark being backported.
+    #[error("failed to look up the PR for '{bookmark}'")]
+    #[diagnostic(
+        code(stakk::backport::pr_lookup_failed),
+        help("check your network connection and GitHub token permissions")
+    )]
+    PrLookupFailed {
+        bookmark: String,
+        #[source]
+        source: ForgeError,
+    },
+
+    /// The bookmark a backport would create already exists.
+    #[error("bookmark '{bookmark}' already exists")]
+    #[diagnostic(
+        code(stakk::backport::bookmark_exists),
+        help(
+            "the stack may already be backported; delete the bookmark with `jj bookmark delete` \
+             to backport it again"
+        )
+    )]
+    BookmarkExists { bookmark: String },
+
+    /// `jj duplicate` failed.
+    #[error("failed to copy the stack onto '{onto}'")]
+    #[diagnostic(
+        code(stakk::backport::duplicate_failed),
+        help("check that '{onto}' exists on the remote")
+    )]
+    DuplicateFailed {
+        onto: String,
+        #[source]
+        source: JjError,
+    },
+
+    /// The copies made by `jj duplicate` could not be told apart from the
+    /// release branch's other changes.
+    #[error("could not find the copies of the stack on '{onto}'")]
+    #[diagnostic(
+        code(stakk::backport::copies_not_found),
+        help(
+            "the copies may still be on the release branch; look for them with `jj log` and \
+             abandon them before trying again"
+        )
+    )]
+    CopiesNotFound { onto: String },
+
+    /// Failed to look up or bookmark the copies.
+    #[error("failed to bookmark the copies of the stack")]
+    #[diagnostic(
+        code(stakk::backport::bookmark_failed),
+        help("the copies are on the release branch; look for them with `jj log`")
+    )]
+    BookmarkFailed {
+        #[source]
+        source: JjError,
+    },
+}
+
+/// One segment of the stack and the bookmark its copy gets.
+#[derive(Debug)]
+pub struct BackportEntry {
+    /// The segment being backported.
+    pub segment: BookmarkSegment,
+    /// The bookmark of its copy.
+    pub backport: String,
+    /// The number of the segment's open PR, if it has one.
+    pub pr_number: Option<u64>,
+}
+
+/// What `stakk backport` will do.
+#[derive(Debug)]
+pub struct BackportPlan {
+    /// The release branch.
+    pub onto: String,
+    /// The remote the release branch is read from.
+    pub remote: String,
+    /// The segments to copy, trunk to leaf.
+    pub entries: Vec<BackportEntry>,
+}
+
+impl BackportPlan {
+    /// The release branch as a revset: its remote bookmark.
+    pub fn destination(&self) -> String {
+        let quote = |s: &str| serde_json::to_string(s).expect("strings serialize to JSON");
+        format!("{}@{}", quote(&self.onto), quote(&self.remote))
+    }
+
+    /// Every commit to copy, oldest first.
+    pub fn commit_ids(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .flat_map(|entry| entry.segment.commits.iter().rev())
+            .map(|commit| commit.commit_id.clone())
+            .collect()
+    }
+}
+
+impl fmt::Display for BackportPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Backport plan ({} bookmark(s) onto {}@{}):",
+            self.entries.len(),
+            self.onto,
+            self.remote
+        )?;
+        for entry in &self.entries {
+            let pr = entry
+                .pr_number
+                .map(|n| format!(", PR #{n}"))
+                .unwrap_or_default();
+            writeln!(
+                f,
+                "  {} -> {} ({} commit(s){pr})",
+                label(&entry.segment),
+                entry.backport,
+                entry.segment.commits.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The bookmark the copy of `bookmark` on `onto` gets.
+pub fn backport_name(onto: &str, bookmark: &str) -> String {
+    format!("backport/{onto}/{bookmark}")
+}
+
+/// Look up the open PR of each of `segments`, by its first bookmark.
+pub async fn find_original_prs<F: Forge>(
+    segments: &[BookmarkSegment],
+    forge: &F,
+) -> Result<HashMap<String, u64>, BackportError> {
+    let mut prs = HashMap::new();
+    for name in segments.iter().filter_map(|seg| seg.bookmark_names.first()) {
+        let pr = forge.find_pr_for_branch(name).await.map_err(|source| {
+            BackportError::PrLookupFailed {
+                bookmark: name.clone(),
+                source,
+            }
+        })?;
+        if let Some(pr) = pr {
+            prs.insert(name.clone(), pr.number);
+        }
+    }
+    Ok(prs)
+}
+
+/// Plan copying `segments` (trunk to leaf) onto `onto`. Fails if a
+/// backport bookmark would take a name in `taken`.
+pub fn plan_backport(
+    segments: Vec<BookmarkSegment>,
+    onto: &str,
+    remote: &str,
+    taken: &HashSet<String>,
+    prs: &HashMap<String, u64>,
+) -> Result<BackportPlan, BackportError> {
+    let entries = segments
+        .into_iter()
+        .map(|segment| {
+            let name = label(&segment);
+            let backport = backport_name(onto, &name);
+            if taken.contains(&backport) {
+                return Err(BackportError::BookmarkExists { bookmark: backport });
+            }
+            Ok(BackportEntry {
+                pr_number: prs.get(&name).copied(),
+                segment,
+                backport,
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(BackportPlan {
+        onto: onto.to_string(),
+        remote: remote.to_string(),
+        entries,
+    })
+}
+
+/// The segments of the copies, trunk to leaf, from `copies` (the log of
+/// the duplicated commits, newest first). The copies line up with the
+/// originals one to one, so each keeps its original's files.
+pub fn copy_segments(
+    plan: &BackportPlan,
+    copies: &[LogEntry],
+) -> Result<Vec<BookmarkSegment>, BackportError> {
+    let expected: usize = plan.entries.iter().map(|e| e.segment.commits.len()).sum();
+    if copies.len() != expected {
+        return Err(BackportError::CopiesNotFound {
+            onto: plan.onto.clone(),
+        });
+    }
+
+    let mut copies = copies.iter().rev();
+    let mut segments = Vec::with_capacity(plan.entries.len());
+    for entry in &plan.entries {
+        let mut commits: Vec<_> = entry
+            .segment
+            .commits
+            .iter()
+            .rev()
+            .zip(copies.by_ref())
+            .map(|(original, copy)| {
+                let mut commit = original.clone();
+                commit.commit_id.clone_from(&copy.commit_id);
+                commit.change_id.clone_from(&copy.change_id);
+                commit.short_change_id.clone_from(&copy.short_change_id);
+                commit.committer = copy.committer.clone();
+                commit.is_immutable = false;
+                commit.local_bookmark_names = vec![entry.backport.clone()];
+                commit
+            })
+            .collect();
+        commits.reverse();
+        for commit in commits.iter_mut().skip(1) {
+            commit.local_bookmark_names.clear();
+        }
+        segments.push(BookmarkSegment {
+            bookmark_names: vec![entry.backport.clone()],
+            change_id: commits
+                .first()
+                .map(|c| c.change_id.clone())
+                .unwrap_or_default(),
+            commits,
+        });
+    }
+    Ok(segments)
+}
+
+/// Copy the stack onto the release branch and bookmark the copies,
+/// returning their segments, trunk to leaf.
+pub async fn duplicate_stack<R: JjRunner>(
+    plan: &BackportPlan,
+    jj: &Jj<R>,
+) -> Result<Vec<BookmarkSegment>, BackportError> {
+    let duplicate_failed = |source| BackportError::DuplicateFailed {
+        onto: plan.onto.clone(),
+        source,
+    };
+    // The copies are the only new children of the release branch.
+    let children = format!("children({})", plan.destination());
+    let before = jj
+        .get_commit_ids(&children)
+        .await
+        .map_err(duplicate_failed)?;
+    jj.duplicate(&plan.commit_ids(), &plan.destination())
+        .await
+        .map_err(duplicate_failed)?;
+
+    let bookmark_failed = |source| BackportError::BookmarkFailed { source };
+    let after = jj
+        .get_commit_ids(&children)
+        .await
+        .map_err(bookmark_failed)?;
+    let roots: Vec<&String> = after.difference(&before).collect();
+    let [root] = roots[..] else {
+        return Err(BackportError::CopiesNotFound {
+            onto: plan.onto.clone(),
+        });
+    };
+    let copies = jj
+        .get_log(&format!("{root}::"))
+        .await
+        .map_err(bookmark_failed)?;
+    let segments = copy_segments(plan, &copies)?;
+
+    for segment in &segments {
+        if let Some(tip) = segment.commits.first() {
+            jj.create_bookmark(&segment.bookmark_names[0], &tip.commit_id)
+                .await
+                .map_err(bookmark_failed)?;
+        }
+    }
+    Ok(segments)
+}
+
+/// Start the body of each PR the submission creates with the PR (or, for a
+/// segment without one, the bookmark) it backports.
+pub fn link_originals(submission: &mut SubmissionPlan, plan: &BackportPlan) {
+    for bp in &mut submission.bookmark_plans {
+        let Some(entry) = plan
+            .entries
+            .iter()
+            .find(|entry| entry.backport == bp.bookmark_name)
+        else {
+            continue;
+        };
+        if !bp.needs_create {
+            continue;
+        }
+        let line = match entry.pr_number {
+            Some(number) => format!("Backport of #{number} to `{}`.", plan.onto),
+            None => format!(
+                "Backport of `{}` to `{}`.",
+                label(&entry.segment),
+                plan.onto
+            ),
+        };
+        bp.body = Some(match bp.body.take() {
+            Some(body) if !body.is_empty() => format!("{line}\n\n{body}"),
+            _ => line,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use stakk_core::graph::types::SegmentCommit;
+    use stakk_core::jj::types::Signature;
+
+    use super::*;
+
+    fn signature() -> Signature {
+        Signature {
+            name: "Test".to_string(),
+            email: "test@example.com".to_string(),
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+        }
+    }
+
+    fn commit(id: &str) -> SegmentCommit {
+        SegmentCommit {
+            commit_id: format!("c_{id}"),
+            change_id: format!("ch_{id}"),
+            description: format!("change {id}"),
+            author: signature(),
+            committer: signature(),
+            short_change_id: id.to_string(),
+            files: vec![format!("{id}.rs")],
+            is_immutable: false,
+            local_bookmark_names: vec![],
+        }
+    }
+
+    /// A segment whose commits are given oldest first.
+    fn segment(bookmark: &str, ids: &[&str]) -> BookmarkSegment {
+        BookmarkSegment {
+            bookmark_names: vec![bookmark.to_string()],
+            change_id: format!("ch_{}", ids.last().unwrap()),
+            commits: ids.iter().rev().map(|id| commit(id)).collect(),
+        }
+    }
+
+    fn copy(id: &str) -> LogEntry {
+        LogEntry {
+            commit_id: format!("c_{id}"),
+            change_id: format!("ch_{id}"),
+            description: String::new(),
+            parents: vec![],
+            author: signature(),
+            committer: signature(),
+            local_bookmark_names: vec![],
+            remote_bookmark_names: vec![],
+            immutable: false,
+            short_change_id: id.to_string(),
+        }
+    }
+
+    fn plan() -> BackportPlan {
+        plan_backport(
+            vec![segment("feat-a", &["a1", "a2"]), segment("feat-b", &["b1"])],
+            "release/1.x",
+            "origin",
+            &HashSet::new(),
+            &HashMap::from([("feat-a".to_string(), 12)]),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn plan_names_the_copies_after_the_release_branch() {
+        let plan = plan();
+        assert_eq!(plan.destination(), r#""release/1.x"@"origin""#);
+        assert_eq!(plan.commit_ids(), ["c_a1", "c_a2", "c_b1"]);
+        assert_eq!(
+            plan.to_string(),
+            "Backport plan (2 bookmark(s) onto release/1.x@origin):\n  feat-a -> \
+             backport/release/1.x/feat-a (2 commit(s), PR #12)\n  feat-b -> \
+             backport/release/1.x/feat-b (1 commit(s))\n"
+        );
+
+        let taken = HashSet::from(["backport/release/1.x/feat-b".to_string()]);
+        let err = plan_backport(
+            vec![segment("feat-b", &["b1"])],
+            "release/1.x",
+            "origin",
+            &taken,
+            &HashMap::new(),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            BackportError::BookmarkExists { bookmark } if bookmark == "backport/release/1.x/feat-b"
+        ));
+    }
+
+    #[test]
+    fn copies_line_up_with_the_originals() {
+        let plan = plan();
+        let copies = [copy("y3"), copy("y2"), copy("y1")];
+        let segments = copy_segments(&plan, &copies).unwrap();
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].bookmark_names, ["backport/release/1.x/feat-a"]);
+        assert_eq!(segments[0].change_id, "ch_y2");
+        let ids: Vec<&str> = segments[0]
+            .commits
+            .iter()
+            .map(|c| c.commit_id.as_str())
+            .collect();
+        assert_eq!(ids, ["c_y2", "c_y1"]);
+        assert_eq!(segments[0].commits[1].description, "change a1");
+        assert_eq!(segments[0].commits[1].files, ["a1.rs"]);
+        assert_eq!(segments[1].change_id, "ch_y3");
+
+        assert!(matches!(
+            copy_segments(&plan, &copies[1..]),
+            Err(BackportError::CopiesNotFound { .. })
+        ));
+    }
+}
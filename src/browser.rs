@@ -0,0 +1,67 @@
+//! Opening URLs in the user's default browser.
+//!
+//! Shells out to the platform's opener (`open` on macOS, `cmd /C start` on
+//! Windows, `xdg-open` elsewhere) rather than linking a browser crate.
+
+/// Return the program and arguments used to open `url` on this platform.
+fn opener_command(url: &str) -> (&'static str, Vec<String>) {
+    if cfg!(target_os = "macos") {
+        ("open", vec![url.to_string()])
+    } else if cfg!(windows) {
+        // The empty string is the window title `start` expects before the
+        // target; without it a quoted URL would be taken as the title.
+        (
+            "cmd",
+            vec![
+                "/C".to_string(),
+                "start".to_string(),
+                String::new(),
+                url.to_string(),
+            ],
+        )
+    } else {
+        ("xdg-open", vec![url.to_string()])
+    }
+}
+
+/// Open `url` in the default browser.
+///
+/// Waits for the opener to exit (openers hand off to the browser and return
+/// immediately). A non-zero exit status is reported as an error.
+pub async fn open_url(url: &str) -> Result<(), std::io::Error> {
+    let (program, args) = opener_command(url);
+    let status = tokio::process::Command::new(program)
+        .args(&args)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .await?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!(
+            "{program} exited with {status}"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opener_command_passes_url_last() {
+        let (_, args) = opener_command("https://github.com/o/r/pull/1");
+        assert_eq!(
+            args.last().map(String::as_str),
+            Some("https://github.com/o/r/pull/1")
+        );
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn opener_command_uses_xdg_open_on_linux() {
+        let (program, _) = opener_command("https://example.com");
+        assert_eq!(program, "xdg-open");
+    }
+}
@@ -0,0 +1,180 @@
+//! Local cache of submitted pull requests.
+//!
+//! `stakk submit` records the PR number and stack position of every bookmark
+//! it submits in `.jj/stakk/prs.json` under the workspace root, and `delete`,
+//! `abandon` and `sync` drop the bookmarks they remove. Commands that
+//! only need to display PR state (e.g. `stakk jj-template`) read the cache
+//! instead of querying GitHub.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use miette::Diagnostic;
+use serde::Deserialize;
+use serde::Serialize;
+use stakk_core::forge::markup::StackEntry;
+use thiserror::Error;
+
+/// Errors from reading or writing the PR cache.
+#[derive(Debug, Error, Diagnostic)]
+pub enum CacheError {
+    #[error("failed to read PR cache {path}")]
+    #[diagnostic(code(stakk::cache::read_failed), help("check file permissions"))]
+    Read {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("failed to parse PR cache {path}")]
+    #[diagnostic(
+        code(stakk::cache::parse_failed),
+        help("delete the file; it is rebuilt on the next `stakk submit`")
+    )]
+    Parse {
+        path: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error("failed to write PR cache {path}")]
+    #[diagnostic(code(stakk::cache::write_failed), help("check file permissions"))]
+    Write {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+/// A submitted bookmark's PR and its place in the stack.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedPr {
+    pub number: u64,
+    pub url: String,
+    /// 1-based position in the stack, counted from trunk.
+    pub position: usize,
+    pub stack_size: usize,
+}
+
+/// PRs keyed by bookmark name.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrCache {
+    pub prs: BTreeMap<String, CachedPr>,
+}
+
+impl PrCache {
+    /// Path of the cache file in the workspace rooted at `workspace_root`.
+    pub fn path(workspace_root: &Path) -> PathBuf {
+        workspace_root.join(".jj").join("stakk").join("prs.json")
+    }
+
+    /// Load the cache, or an empty one if the file does not exist yet.
+    pub fn load(path: &Path) -> Result<Self, CacheError> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(source) => {
+                return Err(CacheError::Read {
+                    path: path.display().to_string(),
+                    source,
+                });
+            }
+        };
+        serde_json::from_str(&contents).map_err(|source| CacheError::Parse {
+            path: path.display().to_string(),
+            source,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), CacheError> {
+        let write_failed = |source| CacheError::Write {
+            path: path.display().to_string(),
+            source,
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(write_failed)?;
+        }
+        let json = serde_json::to_string_pretty(self).expect("PrCache serializes to JSON");
+        std::fs::write(path, json).map_err(write_failed)
+    }
+
+    /// Record a submitted stack, ordered trunk-to-leaf. Entries for other
+    /// bookmarks are kept.
+    pub fn record_stack(&mut self, stack: &[StackEntry]) {
+        for (i, entry) in stack.iter().enumerate() {
+            self.prs.insert(
+                entry.bookmark_name.clone(),
+                CachedPr {
+                    number: entry.pr_number,
+                    url: entry.pr_url.clone(),
+                    position: i + 1,
+                    stack_size: stack.len(),
+                },
+            );
+        }
+    }
+
+    /// Drop the entries of bookmarks that no longer exist.
+    pub fn forget<'a>(&mut self, bookmarks: impl IntoIterator<Item = &'a str>) {
+        for bookmark in bookmarks {
+            self.prs.remove(bookmark);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, number: u64) -> StackEntry {
+        StackEntry {
+            bookmark_name: name.to_string(),
+            pr_url: format!("https://github.com/o/r/pull/{number}"),
+            pr_number: number,
+            repo: None,
+        }
+    }
+
+    #[test]
+    fn record_stack_sets_positions_and_keeps_others() {
+        let mut cache = PrCache::default();
+        cache.record_stack(&[entry("other", 1)]);
+        cache.record_stack(&[entry("a", 10), entry("b", 11)]);
+
+        assert_eq!(cache.prs["other"].position, 1);
+        assert_eq!(cache.prs["a"].position, 1);
+        assert_eq!(cache.prs["b"].position, 2);
+        assert_eq!(cache.prs["b"].stack_size, 2);
+        assert_eq!(cache.prs["b"].number, 11);
+    }
+
+    #[test]
+    fn forget_drops_only_the_named_bookmarks() {
+        let mut cache = PrCache::default();
+        cache.record_stack(&[entry("a", 10), entry("b", 11)]);
+        cache.forget(["a", "gone"]);
+
+        assert_eq!(cache.prs.keys().collect::<Vec<_>>(), ["b"]);
+    }
+
+    #[test]
+    fn load_missing_file_is_empty() {
+        let cache = PrCache::load(Path::new("/nonexistent/.jj/stakk/prs.json")).unwrap();
+        assert_eq!(cache, PrCache::default());
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let dir = std::env::temp_dir().join(format!("stakk-cache-test-{}", std::process::id()));
+        let path = PrCache::path(&dir);
+        let mut cache = PrCache::default();
+        cache.record_stack(&[entry("a", 10)]);
+
+        cache.save(&path).unwrap();
+        let loaded = PrCache::load(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(loaded, cache);
+    }
+}
@@ -0,0 +1,60 @@
+use clap::ArgGroup;
+use clap::Args;
+use clap_complete::ArgValueCompleter;
+
+use crate::cli::complete;
+use crate::cli::graph::GraphArgs;
+
+/// Arguments for the `amend-title` subcommand.
+#[derive(Debug, Args)]
+#[command(group(
+    ArgGroup::new("edits")
+        .required(true)
+        .multiple(true)
+        .args(["title", "body", "labels", "draft", "ready"])
+))]
+pub struct AmendTitleArgs {
+    /// The bookmark whose pull request to edit.
+    #[arg(add = ArgValueCompleter::new(complete::bookmarks))]
+    pub bookmark: String,
+
+    /// New title of the pull request.
+    #[arg(long)]
+    pub title: Option<String>,
+
+    /// New body of the pull request. The sections stakk manages in it (the
+    /// stack, links, and notes) are kept.
+    #[arg(long, value_name = "MARKDOWN")]
+    pub body: Option<String>,
+
+    /// Add a label to the pull request, keeping the ones it has. Repeat for
+    /// several labels.
+    #[arg(long = "label", value_name = "LABEL")]
+    pub labels: Vec<String>,
+
+    /// Turn the pull request into a draft.
+    #[arg(long, conflicts_with = "ready")]
+    pub draft: bool,
+
+    /// Mark the pull request ready for review.
+    #[arg(long)]
+    pub ready: bool,
+
+    /// Also write the new title and body into the description of the
+    /// bookmarked change, keeping its trailers. The change is not pushed;
+    /// the next `stakk submit` pushes it.
+    #[arg(long)]
+    pub sync_description: bool,
+
+    /// Git remote whose GitHub repository is searched for pull requests.
+    #[arg(long, default_value = "origin", env = "STAKK_REMOTE")]
+    pub remote: String,
+
+    /// Git remote whose GitHub repository receives pull requests, when it
+    /// differs from --remote (e.g. `upstream` when pushing to a fork).
+    #[arg(long, value_name = "REMOTE", env = "STAKK_PR_REMOTE")]
+    pub pr_remote: Option<String>,
+
+    #[command(flatten)]
+    pub graph: GraphArgs,
+}
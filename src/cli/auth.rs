@@ -10,6 +10,31 @@ pub struct AuthArgs {
 
 #[derive(Debug, Subcommand)]
 pub enum AuthCommands {
+    /// Store a GitHub token in the system keyring.
+    ///
+    /// Prompts for a personal access token without echoing it, or with
+    /// `--device` authorizes through GitHub's device flow. The stored token
+    /// takes precedence over the GitHub CLI and environment variables.
+    Login {
+        /// Read the token from standard input instead of prompting.
+        #[arg(long, conflicts_with = "device")]
+        with_token: bool,
+
+        /// Log in with GitHub's device flow instead of a personal access
+        /// token.
+        #[arg(long)]
+        device: bool,
+
+        /// Client ID of the GitHub OAuth app used by `--device`. stakk
+        /// ships none: register your own app with device flow enabled.
+        #[arg(long, env = "STAKK_GITHUB_CLIENT_ID")]
+        client_id: Option<String>,
+
+        /// Client secret of that app, stored with the token to refresh it
+        /// when it expires, for apps that require one.
+        #[arg(long, env = "STAKK_GITHUB_CLIENT_SECRET", requires = "device")]
+        client_secret: Option<String>,
+    },
     /// Test that authentication is working.
     Test,
     /// Print instructions for setting up authentication.
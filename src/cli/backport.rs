@@ -0,0 +1,38 @@
+use clap::Args;
+use clap_complete::ArgValueCompleter;
+
+use crate::cli::complete;
+use crate::cli::graph::GraphArgs;
+
+/// Arguments for the `backport` subcommand.
+#[derive(Debug, Args)]
+pub struct BackportArgs {
+    /// Backport the stack up to this bookmark.
+    #[arg(add = ArgValueCompleter::new(complete::bookmarks))]
+    pub bookmark: String,
+
+    /// The release branch to backport onto, as it is on --remote.
+    #[arg(long, value_name = "BRANCH")]
+    pub onto: String,
+
+    /// Backport only the bookmark's own segment, not the stack below it.
+    #[arg(long)]
+    pub only: bool,
+
+    /// Show what would be done without fetching or changing anything.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Git remote to fetch the release branch from and push the backports
+    /// to.
+    #[arg(long, default_value = "origin", env = "STAKK_REMOTE")]
+    pub remote: String,
+
+    /// Git remote whose GitHub repository receives pull requests, when it
+    /// differs from --remote (e.g. `upstream` when pushing to a fork).
+    #[arg(long, value_name = "REMOTE", env = "STAKK_PR_REMOTE")]
+    pub pr_remote: Option<String>,
+
+    #[command(flatten)]
+    pub graph: GraphArgs,
+}
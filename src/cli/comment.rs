@@ -0,0 +1,73 @@
+use clap::Args;
+use clap::Subcommand;
+use clap_complete::ArgValueCompleter;
+use stakk_core::forge::markup::CommentStyle;
+use stakk_core::forge::markup::StackPlacement;
+
+use crate::cli::complete;
+use crate::cli::graph::GraphArgs;
+
+/// Arguments for the `comment` subcommand.
+#[derive(Debug, Args)]
+pub struct CommentArgs {
+    #[command(subcommand)]
+    pub command: CommentCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CommentCommands {
+    /// Re-render the stack comments of a stack's pull requests from the
+    /// current change graph and GitHub, without pushing or changing bases.
+    Refresh(CommentRefreshArgs),
+}
+
+/// Arguments for the `comment refresh` subcommand.
+#[derive(Debug, Args)]
+pub struct CommentRefreshArgs {
+    /// A bookmark of the stack to refresh (default: the working copy's
+    /// stack).
+    #[arg(add = ArgValueCompleter::new(complete::bookmarks))]
+    pub bookmark: Option<String>,
+
+    /// Git remote whose GitHub repository is searched for pull requests.
+    #[arg(long, default_value = "origin", env = "STAKK_REMOTE")]
+    pub remote: String,
+
+    /// Git remote whose GitHub repository receives pull requests, when it
+    /// differs from --remote (e.g. `upstream` when pushing to a fork).
+    #[arg(long, value_name = "REMOTE", env = "STAKK_PR_REMOTE")]
+    pub pr_remote: Option<String>,
+
+    /// Path to a custom minijinja template for stack comments; pass the
+    /// same template as for `stakk submit`.
+    #[arg(long, env = "STAKK_TEMPLATE")]
+    pub template: Option<String>,
+
+    /// Wording of the built-in stack comment template; pass the same style
+    /// as for `stakk submit`.
+    #[arg(
+        long,
+        env = "STAKK_COMMENT_STYLE",
+        default_value = "default",
+        value_enum
+    )]
+    pub comment_style: CommentStyle,
+
+    /// Markdown replacing the footer line of the built-in stack comment
+    /// template (the "Created with stakk" link); empty to leave it out.
+    #[arg(long, env = "STAKK_COMMENT_FOOTER", value_name = "MARKDOWN")]
+    pub comment_footer: Option<String>,
+
+    /// Where the stack is shown: a separate comment or a section of the PR
+    /// description; pass the same placement as for `stakk submit`.
+    #[arg(
+        long,
+        env = "STAKK_STACK_PLACEMENT",
+        default_value = "comment",
+        value_enum
+    )]
+    pub stack_placement: StackPlacement,
+
+    #[command(flatten)]
+    pub graph: GraphArgs,
+}
@@ -0,0 +1,62 @@
+//! Dynamic shell completion of argument values.
+//!
+//! Completers run synchronously inside the shell's completion request, so
+//! they call jj directly and complete nothing on any failure.
+
+use std::ffi::OsStr;
+use std::process::Command;
+
+use clap_complete::CompletionCandidate;
+
+/// Complete local bookmark names from `jj bookmark list`.
+pub fn bookmarks(current: &OsStr) -> Vec<CompletionCandidate> {
+    let Some(prefix) = current.to_str() else {
+        return Vec::new();
+    };
+    // Skip the working-copy snapshot: completion must stay fast and must not
+    // create jj operations.
+    let Ok(output) = Command::new("jj")
+        .args([
+            "--ignore-working-copy",
+            "--color",
+            "never",
+            "bookmark",
+            "list",
+            "-T",
+            r#"if(!remote, name ++ "\n")"#,
+        ])
+        .output()
+    else {
+        return Vec::new();
+    };
+    if !output.status.success() {
+        return Vec::new();
+    }
+    matching_names(&String::from_utf8_lossy(&output.stdout), prefix)
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// The distinct names in `list` (one per line) that start with `prefix`.
+fn matching_names<'a>(list: &'a str, prefix: &str) -> Vec<&'a str> {
+    let mut names: Vec<&str> = list
+        .lines()
+        .map(str::trim)
+        .filter(|name| !name.is_empty() && name.starts_with(prefix))
+        .collect();
+    names.dedup();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matching_names_filters_by_prefix() {
+        let list = "feat-a\nfeat-b\nfeat-b\nfix-c\n\n";
+        assert_eq!(matching_names(list, "feat"), ["feat-a", "feat-b"]);
+        assert_eq!(matching_names(list, ""), ["feat-a", "feat-b", "fix-c"]);
+    }
+}
@@ -0,0 +1,52 @@
+use clap::Args;
+use clap_complete::ArgValueCompleter;
+use stakk_core::forge::markup::CommentStyle;
+
+use crate::cli::complete;
+use crate::cli::graph::GraphArgs;
+
+/// Arguments for the `delete` and `abandon` subcommands.
+#[derive(Debug, Args)]
+pub struct DeleteArgs {
+    /// The bookmark to delete or abandon.
+    #[arg(add = ArgValueCompleter::new(complete::bookmarks))]
+    pub bookmark: String,
+
+    /// Show what would be done without actually doing it.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Git remote to delete the branch from.
+    #[arg(long, default_value = "origin", env = "STAKK_REMOTE")]
+    pub remote: String,
+
+    /// Git remote whose GitHub repository receives pull requests, when it
+    /// differs from --remote (e.g. `upstream` when pushing to a fork).
+    #[arg(long, value_name = "REMOTE", env = "STAKK_PR_REMOTE")]
+    pub pr_remote: Option<String>,
+
+    /// Path to a custom minijinja template for stack comments.
+    ///
+    /// Used to re-render the stack comments of the remaining PRs; pass the
+    /// same template as for `stakk submit`.
+    #[arg(long, env = "STAKK_TEMPLATE")]
+    pub template: Option<String>,
+
+    /// Wording of the built-in stack comment template; pass the same style
+    /// as for `stakk submit`.
+    #[arg(
+        long,
+        env = "STAKK_COMMENT_STYLE",
+        default_value = "default",
+        value_enum
+    )]
+    pub comment_style: CommentStyle,
+
+    /// Markdown replacing the footer line of the built-in stack comment
+    /// template (the "Created with stakk" link); empty to leave it out.
+    #[arg(long, env = "STAKK_COMMENT_FOOTER", value_name = "MARKDOWN")]
+    pub comment_footer: Option<String>,
+
+    #[command(flatten)]
+    pub graph: GraphArgs,
+}
@@ -0,0 +1,30 @@
+use clap::Args;
+
+use crate::cli::submit::SubmitArgs;
+
+/// Arguments for the edit subcommand. The submit options apply to the
+/// submit that `--submit` runs once editing is done.
+#[derive(Debug, Args)]
+#[command(mut_arg("bookmark", |arg| {
+    arg.required(true)
+        .help("The bookmark whose segment to edit")
+}))]
+pub struct EditArgs {
+    /// Start a new change right after the bookmark (`jj new
+    /// --insert-after`) instead of editing the bookmark's change itself.
+    #[arg(long)]
+    pub new: bool,
+
+    /// Wait until the working copy leaves the edited change (watching the
+    /// jj operation log), then submit every stack that contains the
+    /// bookmark.
+    #[arg(long)]
+    pub submit: bool,
+
+    /// Seconds between looks at the operation log while waiting.
+    #[arg(long, default_value_t = 2, value_name = "SECONDS", requires = "submit")]
+    pub poll_interval: u64,
+
+    #[command(flatten)]
+    pub submit_args: SubmitArgs,
+}
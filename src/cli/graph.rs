@@ -1,4 +1,14 @@
+use std::time::Duration;
+
 use clap::Args;
+use clap::Subcommand;
+use clap_complete::ArgValueCompleter;
+use stakk_core::forge::markup::StackPlacement;
+use stakk_core::graph::DEFAULT_BOOKMARKS_REVSET;
+use stakk_core::graph::DEFAULT_HEADS_REVSET;
+use stakk_core::jj::runner::RealJjRunner;
+
+use crate::cli::complete;
 
 /// Arguments controlling graph discovery revsets.
 #[derive(Debug, Args)]
@@ -11,7 +21,7 @@ pub struct GraphArgs {
     /// toward trunk to build the full commit chain.
     #[arg(
         long,
-        default_value = "mine() ~ trunk() ~ immutable()",
+        default_value = DEFAULT_BOOKMARKS_REVSET,
         env = "STAKK_BOOKMARKS_REVSET",
         verbatim_doc_comment
     )]
@@ -26,9 +36,148 @@ pub struct GraphArgs {
     /// `heads(...)`) to avoid redundant traversals.
     #[arg(
         long,
-        default_value = "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+        default_value = DEFAULT_HEADS_REVSET,
         env = "STAKK_HEADS_REVSET",
         verbatim_doc_comment
     )]
     pub heads_revset: String,
+
+    /// Revset of private changes that must never be pushed, e.g.
+    /// `description(glob:"private:*")`.
+    ///
+    /// Graph traversal stops at these changes: bookmarks at or above
+    /// one are left out of the stacks, and submitting one fails.
+    #[arg(long, env = "STAKK_PRIVATE_REVSET", verbatim_doc_comment)]
+    pub private_revset: Option<String>,
+
+    /// Revset of the commit stacks are based on, instead of jj's
+    /// `trunk()`, e.g. `release/1.2` for stacks on a release branch.
+    ///
+    /// It must resolve to one commit with a remote bookmark: that
+    /// bookmark is the base of the bottom PR of every stack.
+    #[arg(long, value_name = "REVSET", env = "STAKK_TRUNK", verbatim_doc_comment)]
+    pub trunk: Option<String>,
+
+    /// Glob of further long-lived branches stacks may be based on, e.g.
+    /// `develop` or `release/*`; repeat or separate with commas for
+    /// several.
+    ///
+    /// A stack forking off a matching branch of the remote ends there,
+    /// and its bottom PR targets that branch. When a stack could attach to
+    /// several, the branches nearest its fork point count, and of those
+    /// the one matching the earliest pattern.
+    #[arg(
+        long = "trunk-pattern",
+        value_name = "GLOB",
+        value_delimiter = ',',
+        env = "STAKK_TRUNK_PATTERNS",
+        verbatim_doc_comment
+    )]
+    pub trunk_patterns: Vec<String>,
+
+    /// Glob of paths to scope the stacks to, e.g. `services/foo` or
+    /// `*.proto`; repeat or separate with commas for several.
+    ///
+    /// Only stacks with a commit that changes a matching file (or a
+    /// file below a matching directory) are shown and submitted.
+    #[arg(
+        long,
+        value_name = "GLOB",
+        value_delimiter = ',',
+        env = "STAKK_PATHS",
+        verbatim_doc_comment
+    )]
+    pub paths: Vec<String>,
+
+    /// Don't read or write the cache of jj queries and PR lookups in
+    /// `.jj/stakk-cache/`.
+    ///
+    /// The cache is keyed by the jj operation log head and the bookmark
+    /// targets, so it is discarded automatically whenever the repo
+    /// changes; use this flag to rule it out when debugging.
+    #[arg(long, env = "STAKK_NO_CACHE", verbatim_doc_comment)]
+    pub no_cache: bool,
+
+    /// Abort a jj command whose output exceeds this many MiB.
+    #[arg(
+        long,
+        value_name = "MIB",
+        default_value_t = RealJjRunner::DEFAULT_MAX_OUTPUT_MIB,
+        env = "STAKK_JJ_MAX_OUTPUT"
+    )]
+    pub jj_max_output: u64,
+
+    /// Abort a jj command that runs longer than this many seconds.
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = RealJjRunner::DEFAULT_TIMEOUT_SECS,
+        env = "STAKK_JJ_TIMEOUT"
+    )]
+    pub jj_timeout: u64,
+
+    /// Check the change graph for internal consistency after building it,
+    /// and fail with a dump of the graph if it is broken. For tracking
+    /// down bookmarks that go missing from the stacks.
+    #[arg(long, env = "STAKK_PARANOID")]
+    pub paranoid: bool,
+}
+
+impl GraphArgs {
+    /// A jj runner with the configured output and time limits.
+    pub fn jj_runner(&self) -> RealJjRunner {
+        RealJjRunner::new(self.jj_max_output, Duration::from_secs(self.jj_timeout))
+    }
+}
+
+/// Arguments for the `graph` subcommand.
+#[derive(Debug, Args)]
+pub struct GraphCommandArgs {
+    #[command(subcommand)]
+    pub command: GraphCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum GraphCommands {
+    /// Check the change graph for internal consistency and, with
+    /// --against-remote, that GitHub matches the stacks.
+    Check(GraphCheckArgs),
+}
+
+/// Arguments for the `graph check` subcommand.
+#[derive(Debug, Args)]
+pub struct GraphCheckArgs {
+    /// Check only the stacks containing this bookmark (default: every
+    /// stack).
+    #[arg(add = ArgValueCompleter::new(complete::bookmarks))]
+    pub bookmark: Option<String>,
+
+    /// Also check every stack against the remote: each bookmark has an open
+    /// PR on the expected base, its remote branch is at the local commit,
+    /// and its stack comment lists the current stack. Prints the command
+    /// that fixes each stack that differs.
+    #[arg(long)]
+    pub against_remote: bool,
+
+    /// Git remote to compare against.
+    #[arg(long, default_value = "origin", env = "STAKK_REMOTE")]
+    pub remote: String,
+
+    /// Git remote whose GitHub repository receives pull requests, when it
+    /// differs from --remote (e.g. `upstream` when pushing to a fork).
+    #[arg(long, value_name = "REMOTE", env = "STAKK_PR_REMOTE")]
+    pub pr_remote: Option<String>,
+
+    /// Where the stacks are expected to be shown, as for `stakk submit`;
+    /// with `none`, stack comments are not checked.
+    #[arg(
+        long,
+        env = "STAKK_STACK_PLACEMENT",
+        default_value = "comment",
+        value_enum
+    )]
+    pub stack_placement: StackPlacement,
+
+    #[command(flatten)]
+    pub graph: GraphArgs,
 }
@@ -0,0 +1,77 @@
+use clap::Args;
+use clap::Subcommand;
+
+use crate::cli::graph::GraphArgs;
+
+/// Arguments for the `pr` subcommand.
+#[derive(Debug, Args)]
+pub struct PrArgs {
+    #[command(subcommand)]
+    pub command: PrCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PrCommands {
+    /// Open the pull request for the working-copy change in the browser.
+    Open(PrOpenArgs),
+    /// Show the CI checks of every pull request in the working-copy
+    /// change's stack.
+    Checks(PrChecksArgs),
+}
+
+/// Arguments for the `pr open` subcommand.
+#[derive(Debug, Args)]
+pub struct PrOpenArgs {
+    /// Open every pull request in the working-copy change's stack instead
+    /// of only the one for its own bookmark.
+    #[arg(long)]
+    pub all: bool,
+
+    /// Open the compare view of each bookmark against the bookmark below it
+    /// (or trunk) instead of its pull request, e.g. to look over a branch
+    /// before `stakk submit`.
+    #[arg(long)]
+    pub compare: bool,
+
+    /// Git remote whose GitHub repository is searched for pull requests.
+    #[arg(long, default_value = "origin", env = "STAKK_REMOTE")]
+    pub remote: String,
+
+    /// Git remote whose GitHub repository receives pull requests, when it
+    /// differs from --remote (e.g. `upstream` when pushing to a fork).
+    #[arg(long, value_name = "REMOTE", env = "STAKK_PR_REMOTE")]
+    pub pr_remote: Option<String>,
+
+    #[command(flatten)]
+    pub graph: GraphArgs,
+}
+
+/// Arguments for the `pr checks` subcommand.
+#[derive(Debug, Args)]
+pub struct PrChecksArgs {
+    /// Wait until every check has finished and exit nonzero if any of them
+    /// fail or are still running when --checks-timeout runs out.
+    #[arg(long)]
+    pub wait: bool,
+
+    /// Seconds to wait for CI checks with --wait.
+    #[arg(
+        long,
+        env = "STAKK_CHECKS_TIMEOUT",
+        default_value = "1800",
+        value_name = "SECONDS"
+    )]
+    pub checks_timeout: u64,
+
+    /// Git remote whose GitHub repository is queried for checks.
+    #[arg(long, default_value = "origin", env = "STAKK_REMOTE")]
+    pub remote: String,
+
+    /// Git remote whose GitHub repository receives pull requests, when it
+    /// differs from --remote (e.g. `upstream` when pushing to a fork).
+    #[arg(long, value_name = "REMOTE", env = "STAKK_PR_REMOTE")]
+    pub pr_remote: Option<String>,
+
+    #[command(flatten)]
+    pub graph: GraphArgs,
+}
@@ -0,0 +1,14 @@
+use clap::Args;
+
+use crate::cli::graph::GraphArgs;
+
+/// Arguments for the `prompt` subcommand.
+#[derive(Debug, Args)]
+pub struct PromptArgs {
+    /// Git remote to compare local bookmarks against.
+    #[arg(long, default_value = "origin", env = "STAKK_REMOTE")]
+    pub remote: String,
+
+    #[command(flatten)]
+    pub graph: GraphArgs,
+}
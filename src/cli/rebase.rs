@@ -0,0 +1,20 @@
+use clap::Args;
+
+use crate::cli::submit::SubmitArgs;
+
+/// Arguments for the rebase subcommand. `--dry-run` only shows the
+/// rebases; the other submit options apply to the submit that `--submit`
+/// runs afterwards.
+#[derive(Debug, Args)]
+#[command(mut_arg("bookmark", |arg| {
+    arg.help("The bookmark that moved on to new commits (default: the working copy's segment)")
+}))]
+pub struct RebaseArgs {
+    /// Submit every stack that contains the bookmark once the branches are
+    /// rebased onto it.
+    #[arg(long)]
+    pub submit: bool,
+
+    #[command(flatten)]
+    pub submit_args: SubmitArgs,
+}
@@ -0,0 +1,25 @@
+use clap::Args;
+use clap_complete::ArgValueCompleter;
+
+use crate::cli::complete;
+use crate::cli::graph::GraphArgs;
+
+/// Arguments for the `status` subcommand.
+#[derive(Debug, Args)]
+pub struct StatusArgs {
+    /// A bookmark in the stack to show.
+    #[arg(add = ArgValueCompleter::new(complete::bookmarks))]
+    pub bookmark: String,
+
+    /// Git remote to compare local bookmarks against.
+    #[arg(long, default_value = "origin", env = "STAKK_REMOTE")]
+    pub remote: String,
+
+    /// Git remote whose GitHub repository receives pull requests, when it
+    /// differs from --remote (e.g. `upstream` when pushing to a fork).
+    #[arg(long, value_name = "REMOTE", env = "STAKK_PR_REMOTE")]
+    pub pr_remote: Option<String>,
+
+    #[command(flatten)]
+    pub graph: GraphArgs,
+}
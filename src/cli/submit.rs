@@ -1,96 +1,100 @@
 use clap::Args;
-use clap::ValueEnum;
+use clap_complete::ArgValueCompleter;
+use stakk_core::forge::MergeMethod;
+use stakk_core::forge::markup::CommentStyle;
+use stakk_core::forge::markup::StackPlacement;
+use stakk_core::jj::PushOptions;
+use stakk_core::submit::options::PrMode;
+use stakk_core::submit::options::PrTemplateMerge;
+use stakk_core::submit::options::SyncPrContent;
+use stakk_core::submit::options::TitleSource;
+use stakk_core::submit::options::TrailerHandling;
 
+use crate::cli::complete;
 use crate::cli::graph::GraphArgs;
-use crate::forge::comment::StackPlacement;
-
-/// Whether new pull requests are created as regular or draft PRs.
-///
-/// This only affects newly created PRs. Existing PRs keep their
-/// current draft/ready state.
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, clap::ValueEnum)]
-#[serde(rename_all = "kebab-case")]
-pub enum PrMode {
-    /// Create pull requests as regular (non-draft) PRs.
-    #[default]
-    Regular,
-    /// Create pull requests as drafts.
-    Draft,
-}
-
-impl std::fmt::Display for PrMode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let pv = self
-            .to_possible_value()
-            .expect("all variants have possible values");
-        f.write_str(pv.get_name())
-    }
-}
-
-/// Controls whether existing PR titles and/or bodies are updated from
-/// commit descriptions on every submit.
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, clap::ValueEnum)]
-#[serde(rename_all = "kebab-case")]
-pub enum SyncPrContent {
-    /// Do not sync. Title and body are only set on PR creation.
-    #[default]
-    None,
-    /// Sync only the PR title from the first line of the commit description.
-    Title,
-    /// Sync only the PR body from the commit description.
-    Body,
-    /// Sync both the PR title and body.
-    All,
-}
-
-impl std::fmt::Display for SyncPrContent {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let pv = self
-            .to_possible_value()
-            .expect("all variants have possible values");
-        f.write_str(pv.get_name())
-    }
-}
-
-/// Controls whether git commit trailers are stripped from PR bodies.
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, clap::ValueEnum)]
-#[serde(rename_all = "kebab-case")]
-pub enum TrailerHandling {
-    /// Leave trailers in the PR body verbatim.
-    #[default]
-    Keep,
-    /// Strip the trailer block (Signed-off-by, Co-authored-by, Refs, etc.)
-    /// from the PR body.
-    Strip,
-}
-
-impl std::fmt::Display for TrailerHandling {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let pv = self
-            .to_possible_value()
-            .expect("all variants have possible values");
-        f.write_str(pv.get_name())
-    }
-}
 
 /// Arguments for the submit subcommand.
 #[derive(Debug, Args)]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "independent CLI flags, not a state machine"
+)]
 pub struct SubmitArgs {
     /// The bookmark to submit as a pull request. If omitted, shows an
     /// interactive selection.
+    #[arg(add = ArgValueCompleter::new(complete::bookmarks))]
     pub bookmark: Option<String>,
 
-    /// Show what would be done without actually doing it.
+    /// Show what would be done without actually doing it. Bookmarks that
+    /// need pushing are checked with `jj git push --dry-run`.
     #[arg(long)]
     pub dry_run: bool,
 
+    /// If a push, base update, or PR creation fails partway, undo the
+    /// changes already made: close the PRs this run created and restore
+    /// the bases it changed. Pushed branches stay on the remote.
+    #[arg(long, env = "STAKK_ATOMIC")]
+    pub atomic: bool,
+
+    /// Before submitting, show the PR bodies and stack comments the submit
+    /// would write, rendered as Markdown, and ask whether to go ahead. With
+    /// `--dry-run`, show them after the list of changes.
+    #[arg(long, env = "STAKK_PREVIEW_BODIES")]
+    pub preview_bodies: bool,
+
+    /// Carry out the rest of the last submit, which stopped partway, from
+    /// its saved plan: steps it completed (pushes, created PRs, base
+    /// updates) are skipped, and nothing is re-planned.
+    #[arg(long = "continue", conflicts_with_all = ["bookmark", "dry_run"])]
+    pub continue_submit: bool,
+
+    /// Submit even if the stack contains conflicted bookmarks (ones that
+    /// point to several commits). They stay unpushed, and their commits go
+    /// into the PR of the bookmark above them.
+    #[arg(long)]
+    pub force: bool,
+
     #[command(flatten)]
     pub graph: GraphArgs,
 
+    /// Leave creating branches the remote does not have yet to jj's
+    /// git.push-new-bookmarks setting instead of always allowing it.
+    #[arg(long, env = "STAKK_NO_ALLOW_NEW")]
+    no_allow_new: bool,
+
+    /// Push even if it drops commits from a remote branch that the local
+    /// bookmark has no version of (e.g. fixups someone else pushed), or if a
+    /// remote branch moved since the submission was planned. jj still
+    /// refuses to overwrite a branch that moved since the last fetch.
+    #[arg(long, env = "STAKK_FORCE_PUSH")]
+    pub force_push: bool,
+
+    /// Do not run `jj bookmark track` after pushing a bookmark for the
+    /// first time; leave tracking to jj's configuration.
+    #[arg(long, env = "STAKK_NO_TRACK")]
+    no_track: bool,
+
+    /// Do not warn when commits are authored under an email that is not
+    /// verified on the GitHub account.
+    #[arg(long, env = "STAKK_NO_AUTHOR_CHECK")]
+    pub no_author_check: bool,
+
+    /// Refuse to submit commits without a Signed-off-by trailer for their
+    /// author, for projects that require a DCO sign-off.
+    #[arg(long, env = "STAKK_REQUIRE_SIGNOFF")]
+    pub require_signoff: bool,
+
+    /// Append a Signed-off-by trailer for the author to each submitted
+    /// commit that lacks one (with `jj describe`) before submitting.
+    #[arg(long, conflicts_with = "dry_run")]
+    pub fix_signoff: bool,
+
     /// Whether new pull requests are created as regular or draft PRs.
     ///
     /// This only affects newly created PRs. Existing PRs keep their
-    /// current draft/ready state. Overridden by --draft.
+    /// current draft/ready state, except that draft-above-first marks a
+    /// draft PR ready for review once the PRs below it have merged.
+    /// Overridden by --draft.
     #[arg(
         long,
         env = "STAKK_PR_MODE",
@@ -108,6 +112,11 @@ pub struct SubmitArgs {
     #[arg(long, default_value = "origin", env = "STAKK_REMOTE")]
     pub remote: String,
 
+    /// Git remote whose GitHub repository receives pull requests, when it
+    /// differs from --remote (e.g. `upstream` when pushing to a fork).
+    #[arg(long, value_name = "REMOTE", env = "STAKK_PR_REMOTE")]
+    pub pr_remote: Option<String>,
+
     /// Path to a custom minijinja template for stack comments.
     ///
     /// The template is rendered with minijinja and receives the following
@@ -118,12 +127,15 @@ pub struct SubmitArgs {
     ///   default_branch    — name of the trunk branch (e.g. "main")
     ///   current_bookmark  — the bookmark being submitted
     ///   stakk_url         — URL to the stakk project
+    ///   style             — wording of --comment-style (intro,
+    ///                       current_marker, show_titles, outro, footer)
     ///
     /// Each entry in stack has:
     ///
     ///   bookmark_name  — bookmark name
     ///   pr_url         — full URL to the pull request
     ///   pr_number      — PR number
+    ///   pr_ref         — "#12", or "owner/repo#12" for another repo
     ///   title          — PR title
     ///   base           — base branch name
     ///   is_draft       — whether the PR is a draft
@@ -143,8 +155,73 @@ pub struct SubmitArgs {
     #[arg(long, env = "STAKK_TEMPLATE", verbatim_doc_comment)]
     pub template: Option<String>,
 
+    /// Wording of the built-in stack comment template.
+    ///
+    /// default — numbered list with 👈 at the current PR
+    /// minimal — numbered list only, no footer
+    /// verbose — PR titles and a note on merge order
+    /// corporate — like verbose, without emoji
+    ///
+    /// A custom --template can read the chosen wording as `style`.
+    #[arg(
+        long,
+        env = "STAKK_COMMENT_STYLE",
+        default_value = "default",
+        value_enum,
+        verbatim_doc_comment
+    )]
+    pub comment_style: CommentStyle,
+
+    /// Markdown replacing the footer line of the built-in stack comment
+    /// template (the "Created with stakk" link); empty to leave it out.
+    #[arg(long, env = "STAKK_COMMENT_FOOTER", value_name = "MARKDOWN")]
+    pub comment_footer: Option<String>,
+
+    /// Minijinja template for PR titles (inline string).
+    ///
+    /// Receives the following context:
+    ///
+    ///   bookmark       — the PR's bookmark (head branch)
+    ///   base           — the PR's base branch
+    ///   commit_count   — number of commits in the PR
+    ///   first_commit   — the bookmarked commit (see below)
+    ///   commits        — all commits in the PR, oldest first
+    ///   default_title  — the title stakk would use without a template
+    ///   default_body   — the body stakk would use without a template
+    ///
+    /// Each commit has summary, body, description, change_id,
+    /// short_change_id, commit_id, author_name and author_email.
+    ///
+    /// Example: "[{{ bookmark }}] {{ first_commit.summary }}"
+    ///
+    /// An empty result falls back to the default title.
+    #[arg(long, env = "STAKK_TITLE_TEMPLATE", verbatim_doc_comment)]
+    pub title_template: Option<String>,
+
+    /// Where PR titles come from without --title-template: the first
+    /// line of the bookmarked commit (tip), of the segment's oldest
+    /// commit (root), or the prettified bookmark name (bookmark). Also
+    /// available to templates as default_title.
+    #[arg(long, env = "STAKK_TITLE_SOURCE", default_value = "tip", value_enum)]
+    pub title_source: TitleSource,
+
+    /// Path to a minijinja template for PR bodies.
+    ///
+    /// Receives the same context as --title-template. Example:
+    ///
+    ///   {% for c in commits %}- {{ c.summary }}
+    ///   {% endfor %}
+    ///
+    /// An empty result creates the PR without a body.
+    #[arg(long, env = "STAKK_BODY_TEMPLATE", verbatim_doc_comment)]
+    pub body_template: Option<String>,
+
     /// Where to place the stack comment on each pull request.
     ///
+    /// In none mode no stack information is written, for repositories
+    /// that forbid bot-style comments; existing stack comments and body
+    /// sections are left as they are.
+    ///
     /// In body mode the stack is written inside a fenced section
     /// (STAKK_BODY_START / STAKK_BODY_END) that is appended to the PR
     /// description. Content you write outside the fences is preserved.
@@ -180,6 +257,54 @@ pub struct SubmitArgs {
     )]
     pub sync_pr_content: SyncPrContent,
 
+    /// Shortcut for --stack-placement=none: skip stack comments
+    /// entirely. Overrides --stack-placement if both are given.
+    #[arg(long, env = "STAKK_NO_COMMENT")]
+    no_comment: bool,
+
+    /// Keep a section in each PR body linking the PR it depends on and
+    /// the PR that follows it ("Depends on #N", "Followed by #M"), for
+    /// review tools and merge checks that read dependency links. Text
+    /// outside the section is left alone.
+    #[arg(long, env = "STAKK_LINK_PRS")]
+    pub link_prs: bool,
+
+    /// Prefix each PR title with its position in the stack, as `[2/4]`.
+    /// Positions are recounted on every submit, so titles follow the stack
+    /// as it grows or shrinks.
+    #[arg(long, env = "STAKK_NUMBER_TITLES")]
+    pub number_titles: bool,
+
+    /// Write each change's local note (see `stakk note`) into a section of
+    /// its PR body. Text outside the section is left alone.
+    #[arg(long, env = "STAKK_INCLUDE_NOTES")]
+    pub include_notes: bool,
+
+    /// After submitting, add a `PR: <url>` trailer to each bookmarked
+    /// change's description, so local history links to its review, and
+    /// push the rewritten bookmarks. Changes that already link their PR,
+    /// and immutable ones, are left alone.
+    #[arg(long, env = "STAKK_PR_TRAILER")]
+    pub pr_trailer: bool,
+
+    /// Keep a tracking issue that lists every PR of the stack in merge
+    /// order. The first submit opens it; later submits of the stack update
+    /// it.
+    #[arg(long, env = "STAKK_UMBRELLA")]
+    pub umbrella: bool,
+
+    /// Link each PR to the issues its commits reference as `#N`, so every
+    /// referenced issue lists the PRs of the stack that touch it. Links
+    /// already made by earlier submits are left alone.
+    #[arg(long, env = "STAKK_LINK_ISSUES")]
+    pub link_issues: bool,
+
+    /// Shortcut for --sync-pr-content=all: propagate amended commit
+    /// messages to existing PR titles and bodies. Overrides
+    /// --sync-pr-content if both are given.
+    #[arg(long, env = "STAKK_UPDATE_METADATA", verbatim_doc_comment)]
+    update_metadata: bool,
+
     /// Whether to keep or strip git commit trailers in PR bodies.
     ///
     /// Trailers are key/value lines at the end of a commit message such as
@@ -193,6 +318,33 @@ pub struct SubmitArgs {
     )]
     pub trailers: TrailerHandling,
 
+    /// Build PR bodies on the repository's pull request template, for
+    /// repos whose conventions require it.
+    ///
+    /// The template is looked up where GitHub looks for it
+    /// (.github/, the repo root, docs/). With `fill`, the generated body
+    /// replaces the placeholder text of the --pr-template-section
+    /// section, or is appended when the template has no such section.
+    #[arg(
+        long,
+        env = "STAKK_PR_TEMPLATE",
+        default_value = "ignore",
+        value_enum,
+        verbatim_doc_comment
+    )]
+    pub pr_template: PrTemplateMerge,
+
+    /// Heading of the PR template section that --pr-template=fill
+    /// puts the generated body in (matched case-insensitively, without
+    /// the leading #s).
+    #[arg(
+        long,
+        env = "STAKK_PR_TEMPLATE_SECTION",
+        default_value = "Description",
+        verbatim_doc_comment
+    )]
+    pub pr_template_section: String,
+
     /// Prefix for auto-generated bookmark names.
     ///
     /// When set, the prefix is prepended to names produced by the [~]auto
@@ -292,6 +444,72 @@ pub struct SubmitArgs {
     ///     | head -c 50
     #[arg(long, env = "STAKK_BOOKMARK_COMMAND", verbatim_doc_comment)]
     pub bookmark_command: Option<String>,
+
+    /// Submit the stack up to the working copy without the interactive
+    /// selection, bookmarking every change in it that has none with a name
+    /// from --auto-bookmark-pattern.
+    #[arg(long, env = "STAKK_AUTO_BOOKMARK", conflicts_with = "bookmark")]
+    pub auto_bookmark: bool,
+
+    /// Submit every commit between trunk and the bookmark (or the working
+    /// copy, without one) as its own PR, bookmarking the commits that have
+    /// no bookmark with a name from --auto-bookmark-pattern.
+    #[arg(long, env = "STAKK_PER_COMMIT")]
+    pub per_commit: bool,
+
+    /// Pattern for the bookmark names --auto-bookmark and --per-commit
+    /// create.
+    ///
+    /// {slug} is replaced with the change's summary, lowercased with
+    /// non-alphanumeric runs turned into hyphens (at most 50 bytes), and
+    /// {change_id} with the first 12 characters of its change ID. For
+    /// example, jo/{slug} names "Fix cache eviction" jo/fix-cache-eviction.
+    #[arg(
+        long,
+        env = "STAKK_AUTO_BOOKMARK_PATTERN",
+        default_value = "{slug}",
+        verbatim_doc_comment
+    )]
+    pub auto_bookmark_pattern: String,
+
+    /// After submitting, wait for the CI checks of every submitted PR and
+    /// exit nonzero if any of them fail or are still running when
+    /// --checks-timeout runs out.
+    #[arg(long, env = "STAKK_WAIT_CHECKS")]
+    pub wait_checks: bool,
+
+    /// Seconds to wait for CI checks with --wait-checks.
+    #[arg(
+        long,
+        env = "STAKK_CHECKS_TIMEOUT",
+        default_value = "1800",
+        value_name = "SECONDS"
+    )]
+    pub checks_timeout: u64,
+
+    /// Enable auto-merge on the PRs based on trunk, so each one merges
+    /// with --merge-method as soon as its reviews and checks pass.
+    ///
+    /// PRs stacked on another PR are left alone: they would merge into
+    /// its branch. They get auto-merge from a later submit, once they
+    /// are based on trunk. The repository must allow auto-merge, and
+    /// PRs only wait if branch protection requires reviews or checks.
+    #[arg(long, env = "STAKK_AUTO_MERGE", verbatim_doc_comment)]
+    pub auto_merge: bool,
+
+    /// How PRs are merged when --auto-merge fires.
+    #[arg(long, env = "STAKK_MERGE_METHOD", default_value = "merge", value_enum)]
+    pub merge_method: MergeMethod,
+
+    /// Put new PRs in the open milestone with this title.
+    #[arg(long, env = "STAKK_MILESTONE", value_name = "TITLE")]
+    pub milestone: Option<String>,
+
+    /// Add new PRs to this GitHub Project, by its number in the project URL
+    /// (7 in github.com/orgs/acme/projects/7). The project must belong to
+    /// the repository's owner, and the token needs the `project` scope.
+    #[arg(long, env = "STAKK_PROJECT", value_name = "NUMBER")]
+    pub project: Option<u64>,
 }
 
 impl SubmitArgs {
@@ -303,4 +521,35 @@ impl SubmitArgs {
             self.pr_mode
         }
     }
+
+    /// Effective stack placement. `--no-comment` forces
+    /// `StackPlacement::None`.
+    pub fn stack_placement(&self) -> StackPlacement {
+        if self.no_comment {
+            StackPlacement::None
+        } else {
+            self.stack_placement
+        }
+    }
+
+    /// Effective PR content sync mode. `--update-metadata` forces
+    /// `SyncPrContent::All`.
+    pub fn sync_pr_content(&self) -> SyncPrContent {
+        if self.update_metadata {
+            SyncPrContent::All
+        } else {
+            self.sync_pr_content
+        }
+    }
+
+    /// How bookmarks are pushed, from `--no-allow-new`, `--force-push`,
+    /// and `--no-track`.
+    pub fn push_options(&self) -> PushOptions {
+        PushOptions {
+            allow_new: !self.no_allow_new,
+            force: self.force_push,
+            dry_run: false,
+            track: !self.no_track,
+        }
+    }
 }
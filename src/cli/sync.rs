@@ -0,0 +1,23 @@
+use clap::Args;
+
+use crate::cli::graph::GraphArgs;
+
+/// Arguments for the `sync` subcommand.
+#[derive(Debug, Args)]
+pub struct SyncArgs {
+    /// Show what would be done without fetching or changing anything.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Git remote to fetch from.
+    #[arg(long, default_value = "origin", env = "STAKK_REMOTE")]
+    pub remote: String,
+
+    /// Git remote whose GitHub repository receives pull requests, when it
+    /// differs from --remote (e.g. `upstream` when pushing to a fork).
+    #[arg(long, value_name = "REMOTE", env = "STAKK_PR_REMOTE")]
+    pub pr_remote: Option<String>,
+
+    #[command(flatten)]
+    pub graph: GraphArgs,
+}
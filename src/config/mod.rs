@@ -2,11 +2,15 @@ use std::path::Path;
 use std::path::PathBuf;
 
 use serde::Deserialize;
-
-use crate::cli::submit::PrMode;
-use crate::cli::submit::SyncPrContent;
-use crate::cli::submit::TrailerHandling;
-use crate::forge::comment::StackPlacement;
+use stakk_core::forge::MergeMethod;
+use stakk_core::forge::github::Transport;
+use stakk_core::forge::markup::CommentStyle;
+use stakk_core::forge::markup::StackPlacement;
+use stakk_core::submit::options::PrMode;
+use stakk_core::submit::options::PrTemplateMerge;
+use stakk_core::submit::options::SyncPrContent;
+use stakk_core::submit::options::TitleSource;
+use stakk_core::submit::options::TrailerHandling;
 
 /// Pre-parse the config file path from raw CLI args or environment, before clap
 /// runs.
@@ -48,15 +52,56 @@ pub struct Config {
     #[serde(default = "default_true")]
     pub inherit: bool,
     pub remote: Option<String>,
+    pub pr_remote: Option<String>,
     pub pr_mode: Option<PrMode>,
     pub template: Option<String>,
+    pub comment_style: Option<CommentStyle>,
+    pub comment_footer: Option<String>,
+    pub title_template: Option<String>,
+    pub title_source: Option<TitleSource>,
+    pub body_template: Option<String>,
     pub stack_placement: Option<StackPlacement>,
     pub sync_pr_content: Option<SyncPrContent>,
+    pub update_metadata: Option<bool>,
     pub trailers: Option<TrailerHandling>,
+    pub pr_template: Option<PrTemplateMerge>,
+    pub pr_template_section: Option<String>,
     pub auto_prefix: Option<String>,
     pub bookmark_command: Option<String>,
+    pub auto_bookmark_pattern: Option<String>,
+    pub checks_timeout: Option<u64>,
+    pub test_command: Option<String>,
+    pub review_tool: Option<String>,
+    pub merge_method: Option<MergeMethod>,
+    pub milestone: Option<String>,
+    pub project: Option<u64>,
+    pub author_check: Option<bool>,
+    pub require_signoff: Option<bool>,
+    pub pr_trailer: Option<bool>,
+    pub link_prs: Option<bool>,
+    pub number_titles: Option<bool>,
+    pub include_notes: Option<bool>,
+    pub wait_checks: Option<bool>,
+    pub umbrella: Option<bool>,
+    pub link_issues: Option<bool>,
     pub bookmarks_revset: Option<String>,
     pub heads_revset: Option<String>,
+    pub private_revset: Option<String>,
+    pub trunk: Option<String>,
+    pub trunk_patterns: Option<Vec<String>>,
+    pub paths: Option<Vec<String>>,
+    pub jj_max_output: Option<u64>,
+    pub jj_timeout: Option<u64>,
+    #[serde(default)]
+    pub forge: ForgeConfig,
+}
+
+/// The `[forge]` table: how stakk talks to the forge.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ForgeConfig {
+    /// `gh` to send every API request through `gh api`.
+    pub transport: Option<Transport>,
 }
 
 impl Default for Config {
@@ -64,15 +109,47 @@ impl Default for Config {
         Self {
             inherit: true,
             remote: None,
+            pr_remote: None,
             pr_mode: None,
             template: None,
+            comment_style: None,
+            comment_footer: None,
+            title_template: None,
+            title_source: None,
+            body_template: None,
             stack_placement: None,
             sync_pr_content: None,
+            update_metadata: None,
             trailers: None,
+            pr_template: None,
+            pr_template_section: None,
             auto_prefix: None,
             bookmark_command: None,
+            auto_bookmark_pattern: None,
+            checks_timeout: None,
+            test_command: None,
+            review_tool: None,
+            merge_method: None,
+            milestone: None,
+            project: None,
+            author_check: None,
+            require_signoff: None,
+            pr_trailer: None,
+            link_prs: None,
+            number_titles: None,
+            include_notes: None,
+            wait_checks: None,
+            umbrella: None,
+            link_issues: None,
             bookmarks_revset: None,
             heads_revset: None,
+            private_revset: None,
+            trunk: None,
+            trunk_patterns: None,
+            paths: None,
+            jj_max_output: None,
+            jj_timeout: None,
+            forge: ForgeConfig::default(),
         }
     }
 }
@@ -132,15 +209,51 @@ impl Config {
         Self {
             inherit: self.inherit,
             remote: self.remote.or(fallback.remote),
+            pr_remote: self.pr_remote.or(fallback.pr_remote),
             pr_mode: self.pr_mode.or(fallback.pr_mode),
             template: self.template.or(fallback.template),
+            comment_style: self.comment_style.or(fallback.comment_style),
+            comment_footer: self.comment_footer.or(fallback.comment_footer),
+            title_template: self.title_template.or(fallback.title_template),
+            title_source: self.title_source.or(fallback.title_source),
+            body_template: self.body_template.or(fallback.body_template),
             stack_placement: self.stack_placement.or(fallback.stack_placement),
             sync_pr_content: self.sync_pr_content.or(fallback.sync_pr_content),
+            update_metadata: self.update_metadata.or(fallback.update_metadata),
             trailers: self.trailers.or(fallback.trailers),
+            pr_template: self.pr_template.or(fallback.pr_template),
+            pr_template_section: self.pr_template_section.or(fallback.pr_template_section),
             auto_prefix: self.auto_prefix.or(fallback.auto_prefix),
             bookmark_command: self.bookmark_command.or(fallback.bookmark_command),
+            auto_bookmark_pattern: self
+                .auto_bookmark_pattern
+                .or(fallback.auto_bookmark_pattern),
+            checks_timeout: self.checks_timeout.or(fallback.checks_timeout),
+            test_command: self.test_command.or(fallback.test_command),
+            review_tool: self.review_tool.or(fallback.review_tool),
+            merge_method: self.merge_method.or(fallback.merge_method),
+            milestone: self.milestone.or(fallback.milestone),
+            project: self.project.or(fallback.project),
+            author_check: self.author_check.or(fallback.author_check),
+            require_signoff: self.require_signoff.or(fallback.require_signoff),
+            pr_trailer: self.pr_trailer.or(fallback.pr_trailer),
+            link_prs: self.link_prs.or(fallback.link_prs),
+            number_titles: self.number_titles.or(fallback.number_titles),
+            include_notes: self.include_notes.or(fallback.include_notes),
+            wait_checks: self.wait_checks.or(fallback.wait_checks),
+            umbrella: self.umbrella.or(fallback.umbrella),
+            link_issues: self.link_issues.or(fallback.link_issues),
             bookmarks_revset: self.bookmarks_revset.or(fallback.bookmarks_revset),
             heads_revset: self.heads_revset.or(fallback.heads_revset),
+            private_revset: self.private_revset.or(fallback.private_revset),
+            trunk: self.trunk.or(fallback.trunk),
+            trunk_patterns: self.trunk_patterns.or(fallback.trunk_patterns),
+            paths: self.paths.or(fallback.paths),
+            jj_max_output: self.jj_max_output.or(fallback.jj_max_output),
+            jj_timeout: self.jj_timeout.or(fallback.jj_timeout),
+            forge: ForgeConfig {
+                transport: self.forge.transport.or(fallback.forge.transport),
+            },
         }
     }
 }
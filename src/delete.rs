@@ -0,0 +1,1050 @@
+//! Deleting a bookmark and splicing it out of its stack.
+//!
+//! `stakk delete` retargets the PRs stacked on the bookmark to the bookmark's
+//! own base, closes its PR with an explanatory comment, rewrites the stack
+//! comments (or body fences) of the remaining PRs without it, and finally
+//! deletes the bookmark locally and on the remote.
+//!
+//! `stakk abandon` does the same and additionally abandons the bookmark's
+//! changes, so jj rebases the rest of the stack onto the bookmark's parent.
+//!
+//! Retargeting must happen before the remote branch is deleted: GitHub closes
+//! every PR whose base branch disappears.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use miette::Diagnostic;
+use stakk_core::forge::Forge;
+use stakk_core::forge::ForgeError;
+use stakk_core::forge::ForgeRepoRef;
+use stakk_core::forge::PullRequest;
+use stakk_core::forge::markup::STAKK_REPO_URL;
+use stakk_core::forge::markup::StackCommentContext;
+use stakk_core::forge::markup::StackCommentData;
+use stakk_core::forge::markup::StackEntryContext;
+use stakk_core::forge::markup::find_stack_comment;
+use stakk_core::forge::markup::format_stack_comment;
+use stakk_core::forge::markup::parse_stack_comment;
+use stakk_core::forge::markup::parse_stack_in_body;
+use stakk_core::forge::markup::splice_stack_into_body;
+use stakk_core::forge::markup::strip_stack_from_body;
+use stakk_core::forge::markup::with_comment_preamble;
+use stakk_core::graph::types::ChangeGraph;
+use stakk_core::jj::Jj;
+use stakk_core::jj::JjError;
+use stakk_core::jj::PushOptions;
+use stakk_core::jj::runner::JjRunner;
+use stakk_core::submit::SubmitError;
+use thiserror::Error;
+
+/// Errors from `stakk delete`.
+#[derive(Debug, Error, Diagnostic)]
+pub enum DeleteError {
+    /// The bookmark is not part of any stack.
+    #[error("bookmark '{bookmark}' not found in any stack")]
+    #[diagnostic(
+        code(stakk::delete::bookmark_not_found),
+        help(
+            "run `stakk show` to see available stacks; use `jj bookmark delete` for bookmarks \
+             outside a stack"
+        )
+    )]
+    BookmarkNotFound { bookmark: String },
+
+    /// Abandoning the bookmark's change would also remove other bookmarks.
+    #[error("change of '{bookmark}' also carries bookmark(s): {}", .others.join(", "))]
+    #[diagnostic(
+        code(stakk::delete::shared_change),
+        help("move or delete the other bookmarks first, or use `stakk delete` to keep the change")
+    )]
+    SharedChange {
+        bookmark: String,
+        others: Vec<String>,
+    },
+
+    /// Failed to look up a PR.
+    #[error("failed to check for existing PR for '{bookmark}'")]
+    #[diagnostic(
+        code(stakk::delete::pr_lookup_failed),
+        help("check your network connection and GitHub token permissions")
+    )]
+    PrLookupFailed {
+        bookmark: String,
+        #[source]
+        source: ForgeError,
+    },
+
+    /// Failed to retarget a PR stacked on the deleted bookmark.
+    #[error("failed to retarget PR #{pr_number} for '{bookmark}' onto '{new_base}'")]
+    #[diagnostic(
+        code(stakk::delete::base_update_failed),
+        help(
+            "nothing has been deleted yet — check your token permissions and re-run `stakk delete`"
+        )
+    )]
+    BaseUpdateFailed {
+        pr_number: u64,
+        bookmark: String,
+        new_base: String,
+        #[source]
+        source: ForgeError,
+    },
+
+    /// Failed to comment on or close the deleted bookmark's PR.
+    #[error("failed to close PR #{pr_number}")]
+    #[diagnostic(
+        code(stakk::delete::close_failed),
+        help("check your token permissions, then close the PR by hand or re-run `stakk delete`")
+    )]
+    CloseFailed {
+        pr_number: u64,
+        #[source]
+        source: ForgeError,
+    },
+
+    /// Failed to rewrite a remaining PR's stack comment or body.
+    #[error("failed to update stack info on PR #{pr_number}")]
+    #[diagnostic(
+        code(stakk::delete::stack_update_failed),
+        help("re-run `stakk submit` on the stack to rewrite its stack comments")
+    )]
+    StackUpdateFailed {
+        pr_number: u64,
+        #[source]
+        source: ForgeError,
+    },
+
+    /// Failed to abandon the bookmark's changes.
+    #[error("failed to abandon the changes of '{bookmark}'")]
+    #[diagnostic(
+        code(stakk::delete::abandon_failed),
+        help("the bookmark is gone; finish with `jj abandon` and `jj git push --deleted`")
+    )]
+    AbandonFailed {
+        bookmark: String,
+        #[source]
+        source: JjError,
+    },
+
+    /// Failed to delete the bookmark locally or on the remote.
+    #[error("failed to delete bookmark '{bookmark}'")]
+    #[diagnostic(
+        code(stakk::delete::bookmark_delete_failed),
+        help("the PR side is done; finish with `jj bookmark delete` and `jj git push --deleted`")
+    )]
+    BookmarkDeleteFailed {
+        bookmark: String,
+        #[source]
+        source: JjError,
+    },
+
+    /// Failed to render a stack comment.
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Render(#[from] SubmitError),
+}
+
+/// What `stakk delete` will do.
+#[derive(Debug)]
+pub struct DeletePlan {
+    /// The bookmark being deleted.
+    pub bookmark: String,
+    /// The remote to delete the branch from.
+    pub remote: String,
+    /// The repository of the affected PRs.
+    pub repo: ForgeRepoRef,
+    /// The bookmark's open PR, if any.
+    pub pr: Option<PullRequest>,
+    /// Where PRs stacked on the bookmark are retargeted: the deleted PR's
+    /// base, or the bookmark's parent in the stack when it has no PR.
+    pub new_base: String,
+    /// Open PRs based on the deleted bookmark, with their bookmarks.
+    pub children: Vec<(String, PullRequest)>,
+    /// Open PRs of every other bookmark in the deleted bookmark's stacks.
+    /// Their stack comments may list the deleted bookmark.
+    pub stack_prs: Vec<(String, PullRequest)>,
+    /// The default branch name (e.g., "main").
+    pub default_branch: String,
+    /// Commits to abandon along with the bookmark (`stakk abandon`), newest
+    /// first. Empty for `stakk delete`.
+    pub abandon: Vec<String>,
+}
+
+/// Locate `bookmark` in the change graph and look up the PRs affected by
+/// deleting it. With `abandon`, the commits of the bookmark's segment are
+/// planned for abandoning as well.
+pub async fn create_delete_plan<F: Forge>(
+    bookmark: &str,
+    change_graph: &ChangeGraph,
+    default_branch: &str,
+    remote: &str,
+    forge: &F,
+    abandon: bool,
+) -> Result<DeletePlan, DeleteError> {
+    let mut parent = None;
+    let mut members: Vec<String> = Vec::new();
+    let mut seen = HashSet::new();
+    let mut found = false;
+    let mut abandon_commits = Vec::new();
+    for stack in &change_graph.stacks {
+        let Some(idx) = stack
+            .segments
+            .iter()
+            .position(|seg| seg.bookmark_names.iter().any(|n| n == bookmark))
+        else {
+            continue;
+        };
+        found = true;
+        if abandon && abandon_commits.is_empty() {
+            let seg = &stack.segments[idx];
+            let others: Vec<String> = seg
+                .bookmark_names
+                .iter()
+                .filter(|n| *n != bookmark)
+                .cloned()
+                .collect();
+            if !others.is_empty() {
+                return Err(DeleteError::SharedChange {
+                    bookmark: bookmark.to_string(),
+                    others,
+                });
+            }
+            abandon_commits = seg.commits.iter().map(|c| c.commit_id.clone()).collect();
+        }
+        if idx > 0 {
+            parent = stack.segments[idx - 1].bookmark_names.first().cloned();
+        }
+        for seg in &stack.segments {
+            if let Some(name) = seg.bookmark_names.first()
+                && name != bookmark
+                && seen.insert(name.clone())
+            {
+                members.push(name.clone());
+            }
+        }
+    }
+    if !found {
+        return Err(DeleteError::BookmarkNotFound {
+            bookmark: bookmark.to_string(),
+        });
+    }
+
+    let lookup = |name: String| async move {
+        let pr = forge.find_pr_for_branch(&name).await.map_err(|source| {
+            DeleteError::PrLookupFailed {
+                bookmark: name.clone(),
+                source,
+            }
+        })?;
+        Ok::<_, DeleteError>((name, pr))
+    };
+    let (own, others) = futures::future::join(
+        lookup(bookmark.to_string()),
+        futures::future::join_all(members.into_iter().map(lookup)),
+    )
+    .await;
+    let (_, pr) = own?;
+    let mut stack_prs = Vec::new();
+    for result in others {
+        if let (name, Some(pr)) = result? {
+            stack_prs.push((name, pr));
+        }
+    }
+
+    let new_base = pr
+        .as_ref()
+        .map(|pr| pr.base_ref.clone())
+        .or(parent)
+        .unwrap_or_else(|| default_branch.to_string());
+    let children = stack_prs
+        .iter()
+        .filter(|(_, pr)| pr.base_ref == bookmark)
+        .cloned()
+        .collect();
+
+    Ok(DeletePlan {
+        bookmark: bookmark.to_string(),
+        remote: remote.to_string(),
+        repo: forge.repo_ref(),
+        pr,
+        new_base,
+        children,
+        stack_prs,
+        default_branch: default_branch.to_string(),
+        abandon: abandon_commits,
+    })
+}
+
+impl fmt::Display for DeletePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verb = if self.abandon.is_empty() {
+            "Deleting"
+        } else {
+            "Abandoning"
+        };
+        writeln!(f, "{verb} bookmark '{}':", self.bookmark)?;
+        for (name, pr) in &self.children {
+            writeln!(
+                f,
+                "  - retarget PR #{} ({name}): {} -> {}",
+                pr.number, pr.base_ref, self.new_base
+            )?;
+        }
+        match &self.pr {
+            Some(pr) => writeln!(f, "  - close PR #{} with a comment", pr.number)?,
+            None => writeln!(f, "  - no open PR to close")?,
+        }
+        if !self.stack_prs.is_empty() {
+            writeln!(
+                f,
+                "  - remove it from the stack info of {} PR(s)",
+                self.stack_prs.len()
+            )?;
+        }
+        writeln!(f, "  - delete the bookmark locally and on {}", self.remote)?;
+        if !self.abandon.is_empty() {
+            writeln!(
+                f,
+                "  - abandon its {} commit(s), rebasing descendants onto its parent",
+                self.abandon.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Execute the delete plan.
+pub async fn execute_delete_plan<R: JjRunner, F: Forge>(
+    plan: &DeletePlan,
+    jj: &Jj<R>,
+    forge: &F,
+    comment_env: &minijinja::Environment<'_>,
+) -> Result<(), DeleteError> {
+    // Must be checked before the local bookmark is gone.
+    let has_remote = jj
+        .has_remote_bookmark(&plan.bookmark, &plan.remote)
+        .await
+        .map_err(|source| DeleteError::BookmarkDeleteFailed {
+            bookmark: plan.bookmark.clone(),
+            source,
+        })?;
+
+    // 1. Retarget children before anything is deleted.
+    for (name, pr) in &plan.children {
+        forge
+            .update_pr_base(pr.number, &plan.new_base)
+            .await
+            .map_err(|source| DeleteError::BaseUpdateFailed {
+                pr_number: pr.number,
+                bookmark: name.clone(),
+                new_base: plan.new_base.clone(),
+                source,
+            })?;
+    }
+
+    // 2. Explain and close the deleted bookmark's PR.
+    if let Some(pr) = &plan.pr {
+        let message = close_message(plan);
+        forge
+            .create_comment(pr.number, &message)
+            .await
+            .map_err(|source| DeleteError::CloseFailed {
+                pr_number: pr.number,
+                source,
+            })?;
+        forge
+            .close_pr(pr.number)
+            .await
+            .map_err(|source| DeleteError::CloseFailed {
+                pr_number: pr.number,
+                source,
+            })?;
+    }
+
+    // 3. Splice the deleted entry out of the remaining PRs' stack info.
+    let template = comment_env.get_template("stack_comment").map_err(|e| {
+        SubmitError::TemplateRenderFailed {
+            message: e.to_string(),
+        }
+    })?;
+    let updates = plan
+        .stack_prs
+        .iter()
+        .map(|(name, pr)| splice_out_of_stack_info(plan, name, pr, forge, &template));
+    for result in futures::future::join_all(updates).await {
+        result?;
+    }
+
+    // 4. Delete the bookmark, then push the deletion.
+    // The bookmark is deleted before abandoning: depending on the jj
+    // version, `jj abandon` either deletes it or moves it to the parent.
+    jj.delete_bookmark(&plan.bookmark).await.map_err(|source| {
+        DeleteError::BookmarkDeleteFailed {
+            bookmark: plan.bookmark.clone(),
+            source,
+        }
+    })?;
+    if !plan.abandon.is_empty() {
+        jj.abandon(&plan.abandon)
+            .await
+            .map_err(|source| DeleteError::AbandonFailed {
+                bookmark: plan.bookmark.clone(),
+                source,
+            })?;
+    }
+    if has_remote {
+        // Pushing a deletion creates no branch.
+        let options = PushOptions {
+            allow_new: false,
+            ..PushOptions::default()
+        };
+        jj.push_bookmark(&plan.bookmark, &plan.remote, options)
+            .await
+            .map_err(|source| DeleteError::BookmarkDeleteFailed {
+                bookmark: plan.bookmark.clone(),
+                source,
+            })?;
+    }
+
+    Ok(())
+}
+
+fn close_message(plan: &DeletePlan) -> String {
+    let what = if plan.abandon.is_empty() {
+        format!("the bookmark `{}` was deleted", plan.bookmark)
+    } else {
+        format!("the change behind `{}` was abandoned", plan.bookmark)
+    };
+    let mut message = format!("Closed by [stakk]({STAKK_REPO_URL}): {what}.");
+    if !plan.children.is_empty() {
+        let numbers: Vec<String> = plan
+            .children
+            .iter()
+            .map(|(_, pr)| format!("#{}", pr.number))
+            .collect();
+        let verb = if numbers.len() == 1 {
+            "targets"
+        } else {
+            "target"
+        };
+        message = format!(
+            "{message}\n\n{} now {verb} `{}`.",
+            numbers.join(", "),
+            plan.new_base
+        );
+    }
+    message
+}
+
+/// Remove the deleted bookmark from one PR's stack comment and/or body
+/// fence. Stacks that shrink to a single PR lose their stack info entirely,
+/// as `stakk submit` does for single-bookmark submissions.
+async fn splice_out_of_stack_info<F: Forge>(
+    plan: &DeletePlan,
+    bookmark: &str,
+    pr: &PullRequest,
+    forge: &F,
+    template: &minijinja::Template<'_, '_>,
+) -> Result<(), DeleteError> {
+    let pr_number = pr.number;
+    let stack_err = |source| DeleteError::StackUpdateFailed { pr_number, source };
+
+    let comments = forge.list_comments(pr_number).await.map_err(stack_err)?;
+    if let Some(comment) = find_stack_comment(&comments)
+        && let Some(data) = parse_stack_comment(&comment.body)
+        && let Some(remaining) = without_deleted(plan, &data)
+    {
+        if remaining.stack.len() <= 1 {
+            forge.delete_comment(comment.id).await.map_err(stack_err)?;
+        } else {
+            let ctx = stack_context(plan, bookmark, &remaining);
+            let body = with_comment_preamble(&format_stack_comment(&remaining, &ctx, template)?);
+            forge
+                .update_comment(comment.id, &body)
+                .await
+                .map_err(stack_err)?;
+        }
+    }
+
+    let pr_body = pr.body.as_deref().unwrap_or_default();
+    if let Some(data) = parse_stack_in_body(pr_body)
+        && let Some(remaining) = without_deleted(plan, &data)
+    {
+        let new_body = if remaining.stack.len() <= 1 {
+            strip_stack_from_body(pr_body)
+        } else {
+            let ctx = stack_context(plan, bookmark, &remaining);
+            splice_stack_into_body(pr_body, &format_stack_comment(&remaining, &ctx, template)?)
+        };
+        forge
+            .update_pr_body(pr_number, &new_body)
+            .await
+            .map_err(stack_err)?;
+    }
+
+    Ok(())
+}
+
+/// The stack data without the deleted bookmark, or `None` if it was not
+/// listed.
+fn without_deleted(plan: &DeletePlan, data: &StackCommentData) -> Option<StackCommentData> {
+    if !data.stack.iter().any(|e| e.bookmark_name == plan.bookmark) {
+        return None;
+    }
+    Some(StackCommentData {
+        version: data.version,
+        stack: data
+            .stack
+            .iter()
+            .filter(|e| e.bookmark_name != plan.bookmark)
+            .cloned()
+            .collect(),
+    })
+}
+
+/// Build the template context for `current`'s stack comment from recorded
+/// stack data, filling titles and bases from the PRs looked up for the plan.
+fn stack_context(plan: &DeletePlan, current: &str, data: &StackCommentData) -> StackCommentContext {
+    let mut previous = plan.default_branch.as_str();
+    let stack: Vec<StackEntryContext> = data
+        .stack
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let known = plan
+                .stack_prs
+                .iter()
+                .find(|(name, _)| *name == entry.bookmark_name)
+                .map(|(_, pr)| pr);
+            let base = if plan
+                .children
+                .iter()
+                .any(|(name, _)| *name == entry.bookmark_name)
+            {
+                plan.new_base.clone()
+            } else {
+                known.map_or_else(|| previous.to_string(), |pr| pr.base_ref.clone())
+            };
+            previous = &entry.bookmark_name;
+            StackEntryContext {
+                bookmark_name: entry.bookmark_name.clone(),
+                pr_url: entry.pr_url.clone(),
+                pr_number: entry.pr_number,
+                pr_ref: entry.pr_ref(&plan.repo),
+                title: known.map_or_else(|| entry.bookmark_name.clone(), |pr| pr.title.clone()),
+                base,
+                is_draft: false,
+                position: i + 1,
+                is_current: entry.bookmark_name == current,
+            }
+        })
+        .collect();
+    StackCommentContext {
+        stack_size: stack.len(),
+        default_branch: plan.default_branch.clone(),
+        current_bookmark: current.to_string(),
+        stakk_url: STAKK_REPO_URL.to_string(),
+        stack,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use stakk_core::forge::CheckRun;
+    use stakk_core::forge::Comment;
+    use stakk_core::forge::CreatePrParams;
+    use stakk_core::forge::Issue;
+    use stakk_core::forge::MergeMethod;
+    use stakk_core::forge::MergedPullRequest;
+    use stakk_core::forge::PrDetails;
+    use stakk_core::forge::PrState;
+    use stakk_core::forge::Review;
+    use stakk_core::forge::markup::CommentStyle;
+    use stakk_core::forge::markup::StackEntry;
+    use stakk_core::forge::markup::build_comment_env;
+    use stakk_core::graph::types::BookmarkSegment;
+    use stakk_core::graph::types::BranchStack;
+    use stakk_core::graph::types::SegmentCommit;
+    use stakk_core::jj::types::Signature;
+
+    use super::*;
+
+    type Log = Arc<Mutex<Vec<String>>>;
+
+    /// Records every mutating forge call, in order, into a shared log.
+    struct MockForge {
+        prs: HashMap<String, PullRequest>,
+        comments: HashMap<u64, Vec<Comment>>,
+        log: Log,
+    }
+
+    impl Forge for MockForge {
+        fn repo_ref(&self) -> ForgeRepoRef {
+            ForgeRepoRef::new("test", "repo")
+        }
+
+        async fn get_authenticated_user(&self) -> Result<String, ForgeError> {
+            Ok("test-user".to_string())
+        }
+
+        async fn get_user_emails(&self) -> Result<Vec<String>, ForgeError> {
+            Ok(vec![])
+        }
+
+        fn find_pr_for_branch(
+            &self,
+            head: &str,
+        ) -> impl std::future::Future<Output = Result<Option<PullRequest>, ForgeError>> + Send
+        {
+            let pr = self.prs.get(head).cloned();
+            async move { Ok(pr) }
+        }
+
+        async fn find_merged_pr_for_branch(
+            &self,
+            _head: &str,
+        ) -> Result<Option<MergedPullRequest>, ForgeError> {
+            Ok(None)
+        }
+
+        async fn get_pr(&self, _number: u64) -> Result<PullRequest, ForgeError> {
+            unreachable!("delete never looks up PRs by number")
+        }
+
+        async fn list_review_requests(&self) -> Result<Vec<PrDetails>, ForgeError> {
+            unreachable!("delete never lists review requests")
+        }
+
+        async fn find_pr_for_change(
+            &self,
+            _change_id: &str,
+        ) -> Result<Option<PullRequest>, ForgeError> {
+            Ok(None)
+        }
+
+        async fn rename_branch(&self, _from: &str, _to: &str) -> Result<(), ForgeError> {
+            unreachable!("delete never renames branches")
+        }
+
+        async fn create_pr(&self, _params: CreatePrParams) -> Result<PullRequest, ForgeError> {
+            unreachable!("delete never creates PRs")
+        }
+
+        fn update_pr_base(
+            &self,
+            pr_number: u64,
+            new_base: &str,
+        ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("base #{pr_number} {new_base}"));
+            async { Ok(()) }
+        }
+
+        async fn update_pr(
+            &self,
+            _pr_number: u64,
+            _title: Option<&str>,
+            _body: Option<&str>,
+        ) -> Result<(), ForgeError> {
+            Ok(())
+        }
+
+        fn close_pr(
+            &self,
+            pr_number: u64,
+        ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send {
+            self.log.lock().unwrap().push(format!("close #{pr_number}"));
+            async { Ok(()) }
+        }
+
+        async fn list_own_issues(&self) -> Result<Vec<Issue>, ForgeError> {
+            Ok(vec![])
+        }
+
+        async fn create_issue(&self, _title: &str, _body: &str) -> Result<Issue, ForgeError> {
+            unreachable!("delete never creates issues")
+        }
+
+        async fn update_issue_body(&self, _number: u64, _body: &str) -> Result<(), ForgeError> {
+            Ok(())
+        }
+
+        async fn link_pr_to_issue(&self, _issue: u64, _pr: u64) -> Result<bool, ForgeError> {
+            Ok(false)
+        }
+
+        fn list_comments(
+            &self,
+            pr_number: u64,
+        ) -> impl std::future::Future<Output = Result<Vec<Comment>, ForgeError>> + Send {
+            let comments = self.comments.get(&pr_number).cloned().unwrap_or_default();
+            async move { Ok(comments) }
+        }
+
+        fn create_comment(
+            &self,
+            pr_number: u64,
+            body: &str,
+        ) -> impl std::future::Future<Output = Result<Comment, ForgeError>> + Send {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("comment #{pr_number} {body}"));
+            let comment = Comment {
+                id: pr_number * 1000,
+                node_id: None,
+                body: body.to_string(),
+            };
+            async move { Ok(comment) }
+        }
+
+        fn update_comment(
+            &self,
+            comment_id: u64,
+            body: &str,
+        ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("edit {comment_id} {body}"));
+            async { Ok(()) }
+        }
+
+        fn update_pr_body(
+            &self,
+            pr_number: u64,
+            body: &str,
+        ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("body #{pr_number} {body}"));
+            async { Ok(()) }
+        }
+
+        fn delete_comment(
+            &self,
+            comment_id: u64,
+        ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("delete comment {comment_id}"));
+            async { Ok(()) }
+        }
+
+        async fn branch_exists(&self, _branch: &str) -> Result<bool, ForgeError> {
+            Ok(true)
+        }
+
+        async fn list_check_runs(&self, _branch: &str) -> Result<Vec<CheckRun>, ForgeError> {
+            Ok(Vec::new())
+        }
+
+        async fn list_reviews(&self, _pr_number: u64) -> Result<Vec<Review>, ForgeError> {
+            Ok(Vec::new())
+        }
+
+        async fn request_reviewers(
+            &self,
+            _pr_number: u64,
+            _reviewers: &[String],
+        ) -> Result<(), ForgeError> {
+            Ok(())
+        }
+
+        async fn add_labels(&self, _pr_number: u64, _labels: &[String]) -> Result<(), ForgeError> {
+            Ok(())
+        }
+
+        async fn add_assignees(
+            &self,
+            _pr_number: u64,
+            _assignees: &[String],
+        ) -> Result<(), ForgeError> {
+            unreachable!("delete never assigns PRs")
+        }
+
+        async fn enable_auto_merge(
+            &self,
+            _pr_number: u64,
+            _method: MergeMethod,
+        ) -> Result<(), ForgeError> {
+            Ok(())
+        }
+
+        async fn mark_ready_for_review(&self, _pr_number: u64) -> Result<(), ForgeError> {
+            Ok(())
+        }
+
+        async fn convert_to_draft(&self, _pr_number: u64) -> Result<(), ForgeError> {
+            Ok(())
+        }
+    }
+
+    /// Logs jj mutations; reports every bookmark as present on the remote.
+    struct MockJjRunner {
+        log: Log,
+    }
+
+    impl JjRunner for MockJjRunner {
+        fn run_jj(
+            &self,
+            args: &[&str],
+        ) -> impl std::future::Future<Output = Result<String, JjError>> + Send {
+            let output = if let ["bookmark", "list", "--remote", _, name, ..] = args {
+                format!("{name}\n")
+            } else {
+                self.log
+                    .lock()
+                    .unwrap()
+                    .push(format!("jj {}", args.join(" ")));
+                String::new()
+            };
+            async move { Ok(output) }
+        }
+    }
+
+    fn segment(name: &str) -> BookmarkSegment {
+        BookmarkSegment {
+            bookmark_names: vec![name.to_string()],
+            change_id: format!("ch_{name}"),
+            commits: vec![],
+        }
+    }
+
+    fn commit(commit_id: &str) -> SegmentCommit {
+        let sig = Signature {
+            name: "Test".to_string(),
+            email: "test@example.com".to_string(),
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+        };
+        SegmentCommit {
+            commit_id: commit_id.to_string(),
+            change_id: format!("ch_{commit_id}"),
+            description: String::new(),
+            author: sig.clone(),
+            committer: sig,
+            short_change_id: commit_id.to_string(),
+            files: vec![],
+            is_immutable: false,
+            local_bookmark_names: vec![],
+        }
+    }
+
+    fn graph(stacks: &[&[&str]]) -> ChangeGraph {
+        ChangeGraph {
+            adjacency_list: HashMap::new(),
+            stack_leaves: HashSet::new(),
+            stack_roots: HashSet::new(),
+            segments: HashMap::new(),
+            tainted_change_ids: HashSet::new(),
+            excluded_bookmark_count: 0,
+            tainted_bookmarks: vec![],
+            private_bookmarks: HashMap::new(),
+            conflicted_bookmarks: vec![],
+            root_trunks: HashMap::new(),
+            stacks: stacks
+                .iter()
+                .map(|names| BranchStack {
+                    segments: names.iter().map(|n| segment(n)).collect(),
+                })
+                .collect(),
+        }
+    }
+
+    fn pr(number: u64, head: &str, base: &str) -> PullRequest {
+        PullRequest {
+            number,
+            html_url: format!("https://github.com/test/repo/pull/{number}"),
+            title: format!("PR for {head}"),
+            head_ref: head.to_string(),
+            base_ref: base.to_string(),
+            state: PrState::Open,
+            body: None,
+            draft: false,
+        }
+    }
+
+    fn stack_comment(id: u64, names: &[(&str, u64)]) -> Comment {
+        let data = StackCommentData {
+            version: 0,
+            stack: names
+                .iter()
+                .map(|(name, number)| StackEntry {
+                    bookmark_name: (*name).to_string(),
+                    pr_url: format!("https://github.com/test/repo/pull/{number}"),
+                    pr_number: *number,
+                    repo: None,
+                })
+                .collect(),
+        };
+        let ctx = StackCommentContext {
+            stack: vec![],
+            stack_size: names.len(),
+            default_branch: "main".to_string(),
+            current_bookmark: String::new(),
+            stakk_url: STAKK_REPO_URL.to_string(),
+        };
+        let env = build_comment_env(None, CommentStyle::Default).unwrap();
+        let template = env.get_template("stack_comment").unwrap();
+        Comment {
+            id,
+            node_id: None,
+            body: with_comment_preamble(&format_stack_comment(&data, &ctx, &template).unwrap()),
+        }
+    }
+
+    /// `a <- b <- c` and `a <- b <- d`, all with open PRs.
+    fn forked_stack_forge(log: &Log) -> MockForge {
+        MockForge {
+            prs: HashMap::from([
+                ("a".to_string(), pr(1, "a", "main")),
+                ("b".to_string(), pr(2, "b", "a")),
+                ("c".to_string(), pr(3, "c", "b")),
+                ("d".to_string(), pr(4, "d", "b")),
+            ]),
+            comments: HashMap::from([
+                (1, vec![stack_comment(10, &[("a", 1), ("b", 2)])]),
+                (3, vec![stack_comment(30, &[("a", 1), ("b", 2), ("c", 3)])]),
+            ]),
+            log: Arc::clone(log),
+        }
+    }
+
+    #[tokio::test]
+    async fn plan_retargets_children_onto_deleted_base() {
+        let log = Log::default();
+        let forge = forked_stack_forge(&log);
+        let graph = graph(&[&["a", "b", "c"], &["a", "b", "d"]]);
+
+        let plan = create_delete_plan("b", &graph, "main", "origin", &forge, false)
+            .await
+            .unwrap();
+
+        assert_eq!(plan.pr.as_ref().map(|p| p.number), Some(2));
+        assert_eq!(plan.new_base, "a");
+        let children: Vec<_> = plan.children.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(children, vec!["c", "d"]);
+        let stack: Vec<_> = plan.stack_prs.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(stack, vec!["a", "c", "d"]);
+    }
+
+    #[tokio::test]
+    async fn plan_unknown_bookmark_is_error() {
+        let log = Log::default();
+        let forge = forked_stack_forge(&log);
+        let graph = graph(&[&["a", "b"]]);
+
+        let result = create_delete_plan("zzz", &graph, "main", "origin", &forge, false).await;
+        assert!(matches!(result, Err(DeleteError::BookmarkNotFound { .. })));
+    }
+
+    #[tokio::test]
+    async fn execute_retargets_before_closing_and_deleting() {
+        let log = Log::default();
+        let forge = forked_stack_forge(&log);
+        let graph = graph(&[&["a", "b", "c"], &["a", "b", "d"]]);
+        let plan = create_delete_plan("b", &graph, "main", "origin", &forge, false)
+            .await
+            .unwrap();
+        let jj = Jj::new(MockJjRunner {
+            log: Arc::clone(&log),
+        });
+        let env = build_comment_env(None, CommentStyle::Default).unwrap();
+
+        execute_delete_plan(&plan, &jj, &forge, &env).await.unwrap();
+
+        let log = log.lock().unwrap();
+        let position = |prefix: &str| {
+            log.iter()
+                .position(|l| l.starts_with(prefix))
+                .unwrap_or_else(|| panic!("no '{prefix}' in {log:?}"))
+        };
+        assert!(position("base #3 a") < position("close #2"));
+        assert!(position("base #4 a") < position("close #2"));
+        assert!(position("comment #2") < position("close #2"));
+        assert!(position("close #2") < position("jj bookmark delete b"));
+        assert!(position("jj bookmark delete b") < position("jj git push"));
+
+        // a's stack shrinks to one PR, so its comment goes away; c's comment
+        // keeps a and c.
+        assert!(log.iter().any(|l| l == "delete comment 10"));
+        let edit = &log[position("edit 30")];
+        let data = parse_stack_comment(edit.trim_start_matches("edit 30 ")).unwrap();
+        let names: Vec<_> = data
+            .stack
+            .iter()
+            .map(|e| e.bookmark_name.as_str())
+            .collect();
+        assert_eq!(names, vec!["a", "c"]);
+    }
+
+    #[test]
+    fn close_message_lists_retargeted_prs() {
+        let plan = DeletePlan {
+            bookmark: "b".to_string(),
+            remote: "origin".to_string(),
+            repo: ForgeRepoRef::new("test", "repo"),
+            pr: Some(pr(2, "b", "a")),
+            new_base: "a".to_string(),
+            children: vec![("c".to_string(), pr(3, "c", "b"))],
+            stack_prs: vec![],
+            default_branch: "main".to_string(),
+            abandon: vec![],
+        };
+        let message = close_message(&plan);
+        assert!(message.contains("`b` was deleted"));
+        assert!(message.contains("#3 now targets `a`"));
+    }
+
+    #[tokio::test]
+    async fn abandon_deletes_bookmark_then_abandons_its_commits() {
+        let log = Log::default();
+        let forge = forked_stack_forge(&log);
+        let mut graph = graph(&[&["a", "b", "c"]]);
+        graph.stacks[0].segments[1].commits = vec![commit("b2"), commit("b1")];
+        let plan = create_delete_plan("b", &graph, "main", "origin", &forge, true)
+            .await
+            .unwrap();
+        assert_eq!(plan.abandon, vec!["b2", "b1"]);
+        let jj = Jj::new(MockJjRunner {
+            log: Arc::clone(&log),
+        });
+        let env = build_comment_env(None, CommentStyle::Default).unwrap();
+
+        execute_delete_plan(&plan, &jj, &forge, &env).await.unwrap();
+
+        let log = log.lock().unwrap();
+        let position = |entry: &str| log.iter().position(|l| l == entry).unwrap();
+        assert!(position("base #3 a") < position("close #2"));
+        assert!(position("jj bookmark delete b") < position("jj abandon b2 b1"));
+        assert!(
+            log.iter()
+                .any(|l| l.starts_with("comment #2") && l.contains("abandoned"))
+        );
+    }
+
+    #[tokio::test]
+    async fn abandon_change_with_other_bookmarks_is_error() {
+        let log = Log::default();
+        let forge = forked_stack_forge(&log);
+        let mut graph = graph(&[&["a", "b"]]);
+        graph.stacks[0].segments[1]
+            .bookmark_names
+            .push("b-alias".to_string());
+
+        let result = create_delete_plan("b", &graph, "main", "origin", &forge, true).await;
+        assert!(matches!(
+            result,
+            Err(DeleteError::SharedChange { ref others, .. }) if others == &["b-alias"]
+        ));
+    }
+}
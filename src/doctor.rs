@@ -0,0 +1,180 @@
+//! Checks for `stakk doctor`: whether jj, the repository, and GitHub are set
+//! up the way stakk needs, each with a fix for what is not.
+
+use std::fmt;
+use std::path::Path;
+
+use stakk_core::jj::JjError;
+use stakk_core::jj::version::JjVersion;
+use stakk_core::jj::version::MIN_SUPPORTED_JJ_VERSION;
+
+use crate::output;
+
+/// How a check came out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Ok,
+    /// stakk works, but something will likely surprise the user.
+    Warn,
+    /// stakk cannot work until this is fixed.
+    Fail,
+}
+
+/// The outcome of one check.
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    pub level: Level,
+    pub detail: String,
+    /// What to do about a warning or failure.
+    pub fix: Option<String>,
+}
+
+impl Check {
+    pub fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            level: Level::Ok,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    pub fn warn(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            level: Level::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    pub fn fail(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            level: Level::Fail,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self.level {
+            Level::Ok => output::symbol("✓", "ok"),
+            Level::Warn => output::symbol("!", "warn"),
+            Level::Fail => output::symbol("✗", "FAIL"),
+        };
+        writeln!(f, "{symbol} {}: {}", self.name, self.detail)?;
+        if let Some(fix) = &self.fix {
+            writeln!(f, "    {fix}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether the installed jj is new enough.
+pub fn jj_version(version: &Result<Option<JjVersion>, JjError>) -> Check {
+    match version {
+        Err(e) => Check::fail(
+            "jj",
+            format!("could not run jj: {e}"),
+            "Install jj and make sure it is on your PATH.",
+        ),
+        Ok(None) => Check::warn(
+            "jj",
+            "could not read the jj version",
+            "stakk will try its templates anyway; if commands fail, install a released jj.",
+        ),
+        Ok(Some(version)) if *version < MIN_SUPPORTED_JJ_VERSION => Check::warn(
+            "jj",
+            format!("jj {version} is older than the minimum supported {MIN_SUPPORTED_JJ_VERSION}"),
+            "Upgrade jj; older versions are untested.",
+        ),
+        Ok(Some(version)) => Check::ok("jj", format!("jj {version}")),
+    }
+}
+
+/// Whether the workspace at `root` is colocated with a git repository.
+pub fn colocation(root: &Path) -> Check {
+    if root.join(".git").exists() {
+        Check::ok(
+            "colocation",
+            format!("{} is a colocated git repo", root.display()),
+        )
+    } else {
+        Check::warn(
+            "colocation",
+            format!("{} is not colocated with a git repo", root.display()),
+            "git tools (and `gh`) will not see your changes; run `jj git colocation enable`.",
+        )
+    }
+}
+
+/// Bookmarks that are left out of every stack: `problem` describes why, and
+/// `fix` what to do about it.
+pub fn excluded_bookmarks(
+    name: &'static str,
+    bookmarks: &[String],
+    problem: &str,
+    fix: &str,
+) -> Check {
+    if bookmarks.is_empty() {
+        return Check::ok(name, "none");
+    }
+    Check::warn(
+        name,
+        format!("{} {problem}: {}", bookmarks.len(), bookmarks.join(", ")),
+        fix,
+    )
+}
+
+/// The number of checks that failed.
+pub fn failures(checks: &[Check]) -> usize {
+    checks.iter().filter(|c| c.level == Level::Fail).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn old_jj_warns_with_upgrade_hint() {
+        let old = JjVersion {
+            major: 0,
+            minor: 20,
+            patch: 0,
+        };
+        let check = jj_version(&Ok(Some(old)));
+        assert_eq!(check.level, Level::Warn);
+        assert_eq!(
+            check.fix.as_deref(),
+            Some("Upgrade jj; older versions are untested.")
+        );
+        assert_eq!(
+            jj_version(&Ok(Some(MIN_SUPPORTED_JJ_VERSION))).level,
+            Level::Ok
+        );
+        assert_eq!(jj_version(&Ok(None)).level, Level::Warn);
+    }
+
+    #[test]
+    fn excluded_bookmarks_are_listed() {
+        let check = excluded_bookmarks(
+            "conflicted bookmarks",
+            &["a".to_string(), "b".to_string()],
+            "point to several commits",
+            "Run `stakk resolve-bookmark <name>`.",
+        );
+        assert_eq!(check.level, Level::Warn);
+        assert_eq!(check.detail, "2 point to several commits: a, b");
+        assert_eq!(
+            excluded_bookmarks("x", &[], "", "")
+                .to_string()
+                .lines()
+                .count(),
+            1
+        );
+    }
+}
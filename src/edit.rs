@@ -0,0 +1,132 @@
+//! `stakk edit`: put the working copy on a bookmark's segment, wait for the
+//! user to finish with it, and submit what the edit touched.
+//!
+//! Editing is done with jj itself, in another terminal or editor. stakk
+//! watches the operation log and takes the working copy moving off the
+//! edited change (`jj new`, `jj edit` elsewhere, ...) as the sign that the
+//! edit is done.
+
+use std::ops::ControlFlow;
+
+use stakk_core::graph::types::ChangeGraph;
+
+/// The leaf bookmarks of the stacks that contain `bookmark`, in stack
+/// order. Submitting each of them covers the bookmark and everything
+/// stacked on it.
+pub fn affected_leaves(graph: &ChangeGraph, bookmark: &str) -> Vec<String> {
+    let mut leaves: Vec<String> = Vec::new();
+    for stack in &graph.stacks {
+        let contains = stack
+            .segments
+            .iter()
+            .any(|segment| segment.bookmark_names.iter().any(|b| b == bookmark));
+        let leaf = stack
+            .segments
+            .last()
+            .and_then(|segment| segment.bookmark_names.first());
+        if let Some(leaf) = leaf.filter(|_| contains)
+            && !leaves.contains(leaf)
+        {
+            leaves.push(leaf.clone());
+        }
+    }
+    leaves
+}
+
+/// Watches the operation log for the working copy leaving one change.
+#[derive(Debug)]
+pub struct EditWatch {
+    change_id: String,
+    operation: String,
+}
+
+impl EditWatch {
+    /// Watch for the working copy leaving `change_id`, starting after
+    /// `operation`.
+    pub fn new(change_id: String, operation: String) -> Self {
+        Self {
+            change_id,
+            operation,
+        }
+    }
+
+    /// Look at the latest operation and the working copy's change as of it.
+    /// Breaks with the operation that moved the working copy away.
+    pub fn observe(&mut self, operation: String, change_id: &str) -> ControlFlow<String> {
+        if operation == self.operation {
+            return ControlFlow::Continue(());
+        }
+        if change_id != self.change_id {
+            return ControlFlow::Break(operation);
+        }
+        self.operation = operation;
+        ControlFlow::Continue(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::collections::HashSet;
+
+    use stakk_core::graph::types::BookmarkSegment;
+    use stakk_core::graph::types::BranchStack;
+
+    use super::*;
+
+    fn graph_of(stacks: &[&[&str]]) -> ChangeGraph {
+        ChangeGraph {
+            adjacency_list: HashMap::new(),
+            stack_leaves: HashSet::new(),
+            stack_roots: HashSet::new(),
+            segments: HashMap::new(),
+            tainted_change_ids: HashSet::new(),
+            excluded_bookmark_count: 0,
+            tainted_bookmarks: vec![],
+            private_bookmarks: HashMap::new(),
+            conflicted_bookmarks: vec![],
+            root_trunks: HashMap::new(),
+            stacks: stacks
+                .iter()
+                .map(|names| BranchStack {
+                    segments: names
+                        .iter()
+                        .map(|name| BookmarkSegment {
+                            bookmark_names: vec![(*name).to_string()],
+                            change_id: format!("ch_{name}"),
+                            commits: vec![],
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn every_stack_through_the_bookmark_is_affected() {
+        let graph = graph_of(&[&["a", "b", "c"], &["a", "b", "d"], &["a", "e"], &["f"]]);
+        assert_eq!(affected_leaves(&graph, "b"), ["c", "d"]);
+        assert_eq!(affected_leaves(&graph, "a"), ["c", "d", "e"]);
+        assert_eq!(affected_leaves(&graph, "f"), ["f"]);
+        assert!(affected_leaves(&graph, "gone").is_empty());
+    }
+
+    #[test]
+    fn edit_is_done_when_the_working_copy_moves() {
+        let mut watch = EditWatch::new("edited".into(), "op1".into());
+        assert_eq!(
+            watch.observe("op1".into(), "edited"),
+            ControlFlow::Continue(())
+        );
+        // Snapshots and squashes that keep `@` on the change are still part of
+        // the edit.
+        assert_eq!(
+            watch.observe("op2".into(), "edited"),
+            ControlFlow::Continue(())
+        );
+        assert_eq!(
+            watch.observe("op3".into(), "other"),
+            ControlFlow::Break("op3".into())
+        );
+    }
+}
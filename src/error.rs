@@ -1,12 +1,20 @@
 use miette::Diagnostic;
+use stakk_core::forge::ForgeError;
+use stakk_core::graph::invariants::InvariantError;
+use stakk_core::jj::JjError;
+use stakk_core::journal::JournalError;
+use stakk_core::query_cache::QueryCacheError;
+use stakk_core::submit::SubmitError;
 use thiserror::Error;
 
 use crate::auth::AuthError;
+use crate::backport::BackportError;
+use crate::cache::CacheError;
 use crate::config::ConfigError;
-use crate::forge::ForgeError;
-use crate::jj::JjError;
+use crate::delete::DeleteError;
+use crate::notes::NotesError;
 use crate::select::bookmark_gen::BookmarkGenError;
-use crate::submit::SubmitError;
+use crate::sync::SyncError;
 
 /// Errors that can occur in stakk.
 #[derive(Debug, Error, Diagnostic)]
@@ -26,11 +34,51 @@ pub enum StakkError {
     #[diagnostic(transparent)]
     Auth(#[from] AuthError),
 
+    /// The change graph failed the `--paranoid` self-check.
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    GraphInvariant(#[from] InvariantError),
+
     /// An error from the submission pipeline.
     #[error(transparent)]
     #[diagnostic(transparent)]
     Submit(#[from] SubmitError),
 
+    /// An error reading or writing the local PR cache.
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Cache(#[from] CacheError),
+
+    /// An error reading, writing, or editing local notes.
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Notes(#[from] NotesError),
+
+    /// An error reading or writing the journal of failed steps.
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Journal(#[from] JournalError),
+
+    /// An error writing the cache of jj queries and PR lookups.
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    QueryCache(#[from] QueryCacheError),
+
+    /// An error from `stakk delete`.
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Delete(#[from] DeleteError),
+
+    /// An error from `stakk sync`.
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Sync(#[from] SyncError),
+
+    /// An error from `stakk backport`.
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Backport(#[from] BackportError),
+
     /// An error from the bookmark name generation command.
     #[error(transparent)]
     #[diagnostic(transparent)]
@@ -73,6 +121,124 @@ pub enum StakkError {
     )]
     TemplateLoadFailed { path: String, reason: String },
 
+    /// The working-copy commit is not part of any bookmark stack.
+    #[error("working copy ({change_id}) is not part of any bookmark stack")]
+    #[diagnostic(
+        code(stakk::pr::not_in_stack),
+        help("run `stakk show` to see the stacks stakk knows about")
+    )]
+    WorkingCopyNotInStack { change_id: String },
+
+    /// The working copy's segment has no bookmark, so it has no PR.
+    #[error("working copy ({change_id}) is in a segment without a bookmark")]
+    #[diagnostic(
+        code(stakk::pr::no_bookmark),
+        help("run `stakk submit` to assign bookmarks and create pull requests")
+    )]
+    WorkingCopyUnbookmarked { change_id: String },
+
+    /// No open pull request exists for the bookmark.
+    #[error("no open pull request for bookmark '{bookmark}'")]
+    #[diagnostic(
+        code(stakk::pr::not_found),
+        help("run `stakk submit {bookmark}` to create it")
+    )]
+    NoPullRequest { bookmark: String },
+
+    /// `stakk status` was given a bookmark that is in no stack.
+    #[error("bookmark '{bookmark}' is not part of any stack")]
+    #[diagnostic(
+        code(stakk::status::not_in_stack),
+        help("run `stakk show` to see the stacks stakk knows about")
+    )]
+    BookmarkNotInStack { bookmark: String },
+
+    /// `stakk open-stack` was given something other than a PR.
+    #[error("'{reference}' is not a pull request number or URL")]
+    #[diagnostic(
+        code(stakk::open_stack::invalid_reference),
+        help("pass a PR number such as `12`, or its URL")
+    )]
+    InvalidPrReference { reference: String },
+
+    /// `stakk open-stack` was given a PR of a repository other than the
+    /// remote's.
+    #[error("pull request {reference} is not in {repo}")]
+    #[diagnostic(
+        code(stakk::open_stack::other_repo),
+        help("pass --remote (or --pr-remote) naming a remote of that repository")
+    )]
+    PrInOtherRepo { reference: String, repo: String },
+
+    /// None of the branches of the stack are on the remote any more.
+    #[error("no branch of the stack of #{number} is on the remote")]
+    #[diagnostic(
+        code(stakk::open_stack::nothing_to_check_out),
+        help("the stack may have been merged or its branches deleted")
+    )]
+    StackBranchesGone { number: u64 },
+
+    /// The browser could not be launched.
+    #[error("failed to open {url} in a browser")]
+    #[diagnostic(
+        code(stakk::pr::browser_failed),
+        help("open the URL manually, or install xdg-open (Linux)")
+    )]
+    BrowserOpenFailed {
+        url: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// `stakk test` has no command to run.
+    #[error("no test command given")]
+    #[diagnostic(
+        code(stakk::test::no_command),
+        help("pass `--command <CMD>`, or set `test_command` in stakk.toml")
+    )]
+    NoTestCommand,
+
+    /// The test command could not be started.
+    #[error("failed to run test command '{command}'")]
+    #[diagnostic(code(stakk::test::spawn_failed))]
+    TestCommandFailed {
+        command: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// The test command failed on some segments of the stack.
+    #[error("{failed} of {total} segment(s) failed")]
+    #[diagnostic(code(stakk::test::failed))]
+    SegmentTestsFailed { failed: usize, total: usize },
+
+    /// `stakk doctor` found problems stakk cannot work around.
+    #[error("{failed} check(s) failed")]
+    #[diagnostic(code(stakk::doctor::failed))]
+    DoctorFailed { failed: usize },
+
+    /// `stakk graph check --against-remote` found stacks that GitHub does
+    /// not match.
+    #[error("{stacks} stack(s) differ from the remote")]
+    #[diagnostic(
+        code(stakk::graph::drift),
+        help("run the commands listed above to bring the remote in line")
+    )]
+    GraphDrift { stacks: usize },
+
+    /// `stakk resolve-bookmark` was given a bookmark that is not conflicted.
+    #[error("bookmark '{name}' is not conflicted")]
+    #[diagnostic(
+        code(stakk::resolve::not_conflicted),
+        help("`stakk show` lists the conflicted bookmarks")
+    )]
+    BookmarkNotConflicted { name: String },
+
+    /// The chosen target is not one of the listed ones.
+    #[error("invalid choice '{choice}': expected a number from 1 to {count}")]
+    #[diagnostic(code(stakk::resolve::invalid_choice))]
+    InvalidTargetChoice { choice: String, count: usize },
+
     /// A CLI argument parsing error.
     #[error("{0}")]
     #[diagnostic(code(stakk::cli))]
@@ -87,7 +253,10 @@ pub enum StakkError {
     #[error("interactive mode requires a terminal")]
     #[diagnostic(
         code(stakk::not_interactive),
-        help("pass the bookmark name explicitly: stakk submit <BOOKMARK>")
+        help(
+            "pass the choice explicitly: `stakk submit <BOOKMARK>`, or `stakk resolve-bookmark \
+             <NAME> --pick <N>`"
+        )
     )]
     NotInteractive,
 
@@ -1,173 +1,1068 @@
-//! GitHub implementation of the Forge trait using octocrab.
+//! GitHub implementation of the Forge trait.
+//!
+//! Requests go to the REST and GraphQL APIs either through octocrab, with a
+//! token, or through `gh api` (see [`Transport`]); responses are decoded
+//! into octocrab's models either way. Octocrab clients, and the number of
+//! requests in flight, are shared through a [`ClientPool`].
 
+use std::fmt::Write as _;
+use std::process::Stdio;
+use std::sync::Arc;
+
+use clap::ValueEnum;
+use http::Method;
 use octocrab::Octocrab;
-use octocrab::models::CommentId;
 use octocrab::models::IssueState;
+use serde::Deserialize;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde::de::IgnoredAny;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Semaphore;
 
+use super::CheckRun;
+use super::CheckState;
 use super::Comment;
+use super::CommentEdit;
 use super::CreatePrParams;
 use super::Forge;
 use super::ForgeError;
+use super::ForgeRepoRef;
+use super::Issue;
+use super::MergeMethod;
+use super::MergedPullRequest;
+use super::PrDetails;
 use super::PrState;
 use super::PullRequest;
+use super::Review;
+use super::ReviewState;
+use super::markup::format_issue_links;
+use super::markup::parse_change_id;
+use super::markup::parse_issue_links;
+use super::pool;
+use super::pool::ClientPool;
+use super::pool::GITHUB_API;
+
+/// Maximum number of aliased fields sent in one GraphQL request.
+///
+/// GitHub limits the cost of a single request; small batches keep each one
+/// well under the limit while still collapsing tall stacks into a handful of
+/// round-trips.
+const GRAPHQL_BATCH_SIZE: usize = 20;
+
+/// How stakk reaches the GitHub API.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Transport {
+    /// HTTP requests authenticated with a token stakk resolves itself.
+    #[default]
+    Api,
+    /// `gh api`, authenticated by gh. For environments where gh is the only
+    /// approved way to reach GitHub.
+    Gh,
+}
+
+/// What a [`GitHubForge`] authenticates with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Credentials {
+    /// A GitHub token, sent by stakk's own HTTP client.
+    Token(String),
+    /// Whatever `gh` is logged in with; requests go through `gh api`.
+    GhCli,
+}
+
+/// The client requests are sent with.
+enum Client {
+    Octocrab(Octocrab),
+    GhCli,
+}
 
 /// GitHub implementation of the `Forge` trait.
+///
+/// PRs are opened on `owner/repo`. Their head branches live in
+/// `head_owner/head_repo`, which is the same repository unless the branches
+/// are pushed to a fork.
 pub struct GitHubForge {
-    client: Octocrab,
+    client: Client,
+    /// Requests in flight, shared with the other forges of the pool.
+    budget: Arc<Semaphore>,
     owner: String,
     repo: String,
+    head_owner: String,
+    head_repo: String,
 }
 
 impl GitHubForge {
-    /// Create a new `GitHubForge` for the given repository.
-    pub fn new(token: &str, owner: String, repo: String) -> Result<Self, ForgeError> {
-        let client = Octocrab::builder()
-            .personal_token(token.to_string())
-            .build()
-            .map_err(|e| {
-                let message = format!("failed to create GitHub client: {e}");
-                ForgeError::Api {
-                    message,
-                    source: Box::new(e),
-                }
-            })?;
+    /// Create a new `GitHubForge` for the given repository, with a client
+    /// from the process-wide [`ClientPool`].
+    pub fn new(credentials: &Credentials, owner: String, repo: String) -> Result<Self, ForgeError> {
+        Self::new_in(ClientPool::shared(), credentials, owner, repo)
+    }
+
+    /// Create a new `GitHubForge` for the given repository, with a client
+    /// from `pool` and a share of its request budget.
+    pub fn new_in(
+        pool: &ClientPool,
+        credentials: &Credentials,
+        owner: String,
+        repo: String,
+    ) -> Result<Self, ForgeError> {
+        let client = match credentials {
+            Credentials::Token(token) => Client::Octocrab(pool.client(GITHUB_API, token)?),
+            Credentials::GhCli => Client::GhCli,
+        };
 
         Ok(Self {
             client,
+            budget: pool.budget(),
+            head_owner: owner.clone(),
+            head_repo: repo.clone(),
             owner,
             repo,
         })
     }
+
+    /// Look for head branches in `owner/repo` (a fork) instead of the
+    /// repository PRs are opened on.
+    #[must_use]
+    pub fn with_head_repo(mut self, owner: String, repo: String) -> Self {
+        self.head_owner = owner;
+        self.head_repo = repo;
+        self
+    }
+
+    /// `head` as the PR API expects it: qualified with the fork's owner when
+    /// the branch lives in a fork.
+    fn qualified_head(&self, head: &str) -> String {
+        if self.head_owner == self.owner {
+            head.to_string()
+        } else {
+            format!("{}:{head}", self.head_owner)
+        }
+    }
+
+    /// Send a REST request (or a GraphQL one, to `/graphql`) and decode the
+    /// JSON response; an empty response decodes as `null`. `Ok(None)` means
+    /// GitHub answered 404.
+    async fn request<R: DeserializeOwned>(
+        &self,
+        method: Method,
+        route: &str,
+        query: &[(&str, &str)],
+        body: Option<&serde_json::Value>,
+    ) -> Result<Option<R>, ForgeError> {
+        let route = with_query(route, query);
+        let _permit = pool::acquire(&self.budget).await;
+        let text = match &self.client {
+            Client::Octocrab(client) => {
+                let response = match method {
+                    Method::GET => client._get(route.as_str()).await,
+                    Method::POST => client._post(route.as_str(), body).await,
+                    Method::PATCH => client._patch(route.as_str(), body).await,
+                    Method::DELETE => client._delete(route.as_str(), body).await,
+                    _ => unreachable!("stakk sends no {method} requests"),
+                };
+                let response = match response {
+                    Ok(response) => octocrab::map_github_error(response).await,
+                    Err(e) => Err(e),
+                };
+                match response {
+                    Ok(response) => client
+                        .body_to_string(response)
+                        .await
+                        .map_err(map_octocrab_error)?,
+                    Err(octocrab::Error::GitHub { source, .. })
+                        if source.status_code == http::StatusCode::NOT_FOUND =>
+                    {
+                        return Ok(None);
+                    }
+                    Err(e) => return Err(map_octocrab_error(e)),
+                }
+            }
+            Client::GhCli => match gh_api(&method, &route, body).await? {
+                Some(text) => text,
+                None => return Ok(None),
+            },
+        };
+        let text = if text.trim().is_empty() {
+            "null"
+        } else {
+            &text
+        };
+        serde_json::from_str(text)
+            .map(Some)
+            .map_err(|e| ForgeError::Api {
+                message: format!("unexpected response from {route}: {e}"),
+                source: Box::new(e),
+            })
+    }
+
+    /// Send a request whose resource must exist.
+    async fn send<R: DeserializeOwned>(
+        &self,
+        method: Method,
+        route: &str,
+        query: &[(&str, &str)],
+        body: Option<&serde_json::Value>,
+    ) -> Result<R, ForgeError> {
+        self.request(method, route, query, body)
+            .await?
+            .ok_or_else(|| api_error(format!("{route}: not found")))
+    }
+
+    async fn get<R: DeserializeOwned>(
+        &self,
+        route: &str,
+        query: &[(&str, &str)],
+    ) -> Result<R, ForgeError> {
+        self.send(Method::GET, route, query, None).await
+    }
+
+    async fn post<R: DeserializeOwned>(
+        &self,
+        route: &str,
+        body: &serde_json::Value,
+    ) -> Result<R, ForgeError> {
+        self.send(Method::POST, route, &[], Some(body)).await
+    }
+
+    async fn patch(&self, route: &str, body: &serde_json::Value) -> Result<(), ForgeError> {
+        self.send::<IgnoredAny>(Method::PATCH, route, &[], Some(body))
+            .await
+            .map(|_| ())
+    }
+
+    /// Every page of a list endpoint, 100 items at a time.
+    async fn get_all<R: DeserializeOwned>(
+        &self,
+        route: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Vec<R>, ForgeError> {
+        let mut items = Vec::new();
+        for page in 1.. {
+            let page = page.to_string();
+            let mut paged = query.to_vec();
+            paged.extend([("per_page", "100"), ("page", page.as_str())]);
+            let batch: Vec<R> = self.get(route, &paged).await?;
+            let last = batch.len() < 100;
+            items.extend(batch);
+            if last {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    /// Route of `path` under the repository PRs are opened on.
+    fn repo_route(&self, path: &str) -> String {
+        format!("/repos/{}/{}/{path}", self.owner, self.repo)
+    }
+
+    /// The repository the head repository was forked from, or `None` if it
+    /// is not a fork.
+    pub async fn fork_parent(&self) -> Result<Option<ForgeRepoRef>, ForgeError> {
+        let repo: serde_json::Value = self
+            .get(
+                &format!("/repos/{}/{}", self.head_owner, self.head_repo),
+                &[],
+            )
+            .await?;
+        Ok(fork_parent_of(&repo))
+    }
+
+    async fn ref_exists(&self, owner: &str, repo: &str, branch: &str) -> Result<bool, ForgeError> {
+        let found: Option<IgnoredAny> = self
+            .request(
+                Method::GET,
+                &format!("/repos/{owner}/{repo}/git/ref/heads/{branch}"),
+                &[],
+                None,
+            )
+            .await?;
+        Ok(found.is_some())
+    }
+
+    /// The GraphQL node ID of a PR. GraphQL mutations address PRs by node
+    /// ID, which only the REST representation hands out.
+    async fn pr_node_id(&self, pr_number: u64) -> Result<String, ForgeError> {
+        let pr: octocrab::models::pulls::PullRequest = self
+            .get(&self.repo_route(&format!("pulls/{pr_number}")), &[])
+            .await?;
+        pr.node_id
+            .ok_or(ForgeError::MalformedResponse { field: "node_id" })
+    }
+
+    /// Run a GraphQL request, turning an `errors` array into an error.
+    async fn graphql(&self, payload: &serde_json::Value) -> Result<serde_json::Value, ForgeError> {
+        let response: serde_json::Value = self.post("/graphql", payload).await?;
+        match graphql_error_message(&response) {
+            Some(message) => Err(api_error(message)),
+            None => Ok(response),
+        }
+    }
+
+    /// Run a GraphQL mutation, turning an `errors` array into an error.
+    async fn mutate(&self, payload: &serde_json::Value) -> Result<(), ForgeError> {
+        self.graphql(payload).await.map(|_| ())
+    }
+
+    /// The number of the open milestone titled `title`.
+    async fn milestone_number(&self, title: &str) -> Result<u64, ForgeError> {
+        let milestones: serde_json::Value = self
+            .get(
+                &self.repo_route("milestones"),
+                &[("state", "open"), ("per_page", "100")],
+            )
+            .await?;
+        find_milestone(&milestones, title).ok_or_else(|| {
+            api_error(format!(
+                "no open milestone titled '{title}' in {}/{}",
+                self.owner, self.repo
+            ))
+        })
+    }
+
+    /// The node ID of project `number` of the repository's owner.
+    async fn project_id(&self, number: u64) -> Result<String, ForgeError> {
+        let response = self.graphql(&project_id_query(&self.owner, number)).await?;
+        response
+            .pointer("/data/repositoryOwner/projectV2/id")
+            .and_then(serde_json::Value::as_str)
+            .map(ToString::to_string)
+            .ok_or_else(|| api_error(format!("no project #{number} owned by {}", self.owner)))
+    }
+
+    /// PRs in `state` whose head is branch `head` of the head repository,
+    /// most recently updated first.
+    async fn list_prs_for_head(
+        &self,
+        head: &str,
+        state: &str,
+    ) -> Result<Vec<octocrab::models::pulls::PullRequest>, ForgeError> {
+        let qualified_head = format!("{}:{head}", self.head_owner);
+        self.get(
+            &self.repo_route("pulls"),
+            &[
+                ("head", qualified_head.as_str()),
+                ("state", state),
+                ("sort", "updated"),
+                ("direction", "desc"),
+            ],
+        )
+        .await
+    }
 }
 
 impl Forge for GitHubForge {
+    fn repo_ref(&self) -> ForgeRepoRef {
+        ForgeRepoRef::new(&self.owner, &self.repo)
+    }
+
+    fn head_repo_ref(&self) -> ForgeRepoRef {
+        ForgeRepoRef::new(&self.head_owner, &self.head_repo)
+    }
+
     async fn get_authenticated_user(&self) -> Result<String, ForgeError> {
-        let user = self
-            .client
-            .current()
-            .user()
-            .await
-            .map_err(map_octocrab_error)?;
+        let user: octocrab::models::Author = self.get("/user", &[]).await?;
         Ok(user.login)
     }
 
+    async fn get_user_emails(&self) -> Result<Vec<String>, ForgeError> {
+        let emails: serde_json::Value = self.get("/user/emails", &[]).await?;
+        Ok(verified_emails(&emails))
+    }
+
     async fn find_pr_for_branch(&self, head: &str) -> Result<Option<PullRequest>, ForgeError> {
-        let qualified_head = format!("{}:{head}", self.owner);
-        let pulls = self
-            .client
-            .pulls(&self.owner, &self.repo)
-            .list()
-            .head(qualified_head)
-            .state(octocrab::params::State::Open)
-            .send()
-            .await
-            .map_err(map_octocrab_error)?;
+        let pulls = self.list_prs_for_head(head, "open").await?;
+        pulls.into_iter().next().map(convert_pr).transpose()
+    }
 
-        pulls.items.into_iter().next().map(convert_pr).transpose()
+    async fn find_prs_for_branches(
+        &self,
+        heads: &[String],
+    ) -> Result<Vec<Option<PrDetails>>, ForgeError> {
+        let mut found = Vec::with_capacity(heads.len());
+        for chunk in heads.chunks(GRAPHQL_BATCH_SIZE) {
+            let response = self
+                .graphql(&pr_lookup_query(&self.owner, &self.repo, chunk))
+                .await?;
+            for i in 0..chunk.len() {
+                let nodes = response
+                    .pointer(&format!("/data/repository/h{i}/nodes"))
+                    .and_then(serde_json::Value::as_array)
+                    .ok_or(ForgeError::MalformedResponse {
+                        field: "pullRequests",
+                    })?;
+                let details = pr_from_owner(nodes, &self.head_owner)
+                    .map(convert_pr_details)
+                    .transpose()?;
+                found.push(details);
+            }
+        }
+        Ok(found)
     }
 
-    async fn create_pr(&self, params: CreatePrParams) -> Result<PullRequest, ForgeError> {
-        let pulls = self.client.pulls(&self.owner, &self.repo);
-        let mut builder = pulls.create(&params.title, &params.head, &params.base);
+    async fn list_review_requests(&self) -> Result<Vec<PrDetails>, ForgeError> {
+        let response = self
+            .graphql(&review_requests_query(&self.owner, &self.repo))
+            .await?;
+        response
+            .pointer("/data/search/nodes")
+            .and_then(serde_json::Value::as_array)
+            .ok_or(ForgeError::MalformedResponse { field: "search" })?
+            .iter()
+            .map(convert_pr_details)
+            .collect()
+    }
 
+    async fn find_merged_pr_for_branch(
+        &self,
+        head: &str,
+    ) -> Result<Option<MergedPullRequest>, ForgeError> {
+        let pulls = self.list_prs_for_head(head, "closed").await?;
+        pulls
+            .into_iter()
+            .find(|pr| pr.merged_at.is_some())
+            .map(|pr| {
+                let head_sha = pr
+                    .head
+                    .as_ref()
+                    .map(|head| head.sha.clone())
+                    .ok_or(ForgeError::MalformedResponse { field: "head" })?;
+                Ok(MergedPullRequest {
+                    pr: convert_pr(pr)?,
+                    head_sha,
+                })
+            })
+            .transpose()
+    }
+
+    async fn get_pr(&self, number: u64) -> Result<PullRequest, ForgeError> {
+        let pr: octocrab::models::pulls::PullRequest = self
+            .get(&self.repo_route(&format!("pulls/{number}")), &[])
+            .await?;
+        convert_pr(pr)
+    }
+
+    async fn find_pr_for_change(&self, change_id: &str) -> Result<Option<PullRequest>, ForgeError> {
+        let pulls: Vec<octocrab::models::pulls::PullRequest> = self
+            .get_all(&self.repo_route("pulls"), &[("state", "open")])
+            .await?;
+        pulls
+            .into_iter()
+            .find(|pr| pr.body.as_deref().and_then(parse_change_id) == Some(change_id))
+            .map(convert_pr)
+            .transpose()
+    }
+
+    async fn rename_branch(&self, from: &str, to: &str) -> Result<(), ForgeError> {
+        let route = format!(
+            "/repos/{}/{}/branches/{from}/rename",
+            self.head_owner, self.head_repo
+        );
+        let _: IgnoredAny = self
+            .post(&route, &serde_json::json!({ "new_name": to }))
+            .await?;
+        Ok(())
+    }
+
+    async fn create_pr(&self, params: CreatePrParams) -> Result<PullRequest, ForgeError> {
+        let mut request = serde_json::json!({
+            "title": params.title,
+            "head": self.qualified_head(&params.head),
+            "base": params.base,
+            "draft": params.draft,
+        });
         if let Some(body) = &params.body {
-            builder = builder.body(body);
+            request["body"] = serde_json::Value::from(body.as_str());
         }
 
-        if params.draft {
-            builder = builder.draft(true);
-        }
+        // Resolved up front, so a typo fails before the PR exists.
+        let milestone = match &params.milestone {
+            Some(title) => Some(self.milestone_number(title).await?),
+            None => None,
+        };
+        let project_id = match params.project {
+            Some(number) => Some(self.project_id(number).await?),
+            None => None,
+        };
 
-        let pr = builder.send().await.map_err(map_octocrab_error)?;
+        let pr: octocrab::models::pulls::PullRequest =
+            self.post(&self.repo_route("pulls"), &request).await?;
+
+        if let Some(milestone) = milestone
+            && let Some(number) = pr.number
+        {
+            self.patch(
+                &self.repo_route(&format!("issues/{number}")),
+                &serde_json::json!({ "milestone": milestone }),
+            )
+            .await?;
+        }
+        if let Some(project_id) = project_id {
+            let node_id = pr
+                .node_id
+                .as_deref()
+                .ok_or(ForgeError::MalformedResponse { field: "node_id" })?;
+            self.mutate(&add_to_project_mutation(&project_id, node_id))
+                .await?;
+        }
 
         convert_pr(pr)
     }
 
     async fn update_pr_base(&self, pr_number: u64, new_base: &str) -> Result<(), ForgeError> {
-        self.client
-            .pulls(&self.owner, &self.repo)
-            .update(pr_number)
-            .base(new_base)
-            .send()
-            .await
-            .map_err(map_octocrab_error)?;
-        Ok(())
+        self.patch(
+            &self.repo_route(&format!("pulls/{pr_number}")),
+            &serde_json::json!({ "base": new_base }),
+        )
+        .await
     }
 
-    async fn update_pr_title(&self, pr_number: u64, title: &str) -> Result<(), ForgeError> {
-        self.client
-            .pulls(&self.owner, &self.repo)
-            .update(pr_number)
-            .title(title)
-            .send()
-            .await
-            .map_err(map_octocrab_error)?;
-        Ok(())
+    async fn update_pr(
+        &self,
+        pr_number: u64,
+        title: Option<&str>,
+        body: Option<&str>,
+    ) -> Result<(), ForgeError> {
+        let mut update = serde_json::Map::new();
+        if let Some(title) = title {
+            update.insert("title".to_string(), title.into());
+        }
+        if let Some(body) = body {
+            update.insert("body".to_string(), body.into());
+        }
+        self.patch(
+            &self.repo_route(&format!("pulls/{pr_number}")),
+            &serde_json::Value::Object(update),
+        )
+        .await
+    }
+
+    async fn close_pr(&self, pr_number: u64) -> Result<(), ForgeError> {
+        self.patch(
+            &self.repo_route(&format!("pulls/{pr_number}")),
+            &serde_json::json!({ "state": "closed" }),
+        )
+        .await
+    }
+
+    async fn list_own_issues(&self) -> Result<Vec<Issue>, ForgeError> {
+        let login = self.get_authenticated_user().await?;
+        let issues: Vec<octocrab::models::issues::Issue> = self
+            .get(
+                &self.repo_route("issues"),
+                &[
+                    ("creator", login.as_str()),
+                    ("state", "open"),
+                    ("per_page", "100"),
+                ],
+            )
+            .await?;
+        // The issues API lists pull requests too.
+        Ok(issues
+            .into_iter()
+            .filter(|issue| issue.pull_request.is_none())
+            .map(convert_issue)
+            .collect())
+    }
+
+    async fn create_issue(&self, title: &str, body: &str) -> Result<Issue, ForgeError> {
+        let issue: octocrab::models::issues::Issue = self
+            .post(
+                &self.repo_route("issues"),
+                &serde_json::json!({ "title": title, "body": body }),
+            )
+            .await?;
+        Ok(convert_issue(issue))
+    }
+
+    async fn update_issue_body(&self, number: u64, body: &str) -> Result<(), ForgeError> {
+        self.patch(
+            &self.repo_route(&format!("issues/{number}")),
+            &serde_json::json!({ "body": body }),
+        )
+        .await
+    }
+    // GitHub's API can only put a PR in an issue's Development section
+    // through a closing keyword in the PR body, which would also close the
+    // issue on merge. Instead, one comment on the issue lists the PRs; each
+    // mention shows as a cross-reference in the issue's and PR's timelines.
+    async fn link_pr_to_issue(
+        &self,
+        issue_number: u64,
+        pr_number: u64,
+    ) -> Result<bool, ForgeError> {
+        let comments = self.list_comments(issue_number).await?;
+        let existing = comments
+            .iter()
+            .find_map(|c| Some((c.id, parse_issue_links(&c.body)?)));
+        match existing {
+            Some((_, prs)) if prs.contains(&pr_number) => Ok(false),
+            Some((id, mut prs)) => {
+                prs.push(pr_number);
+                self.update_comment(id, &format_issue_links(&prs)).await?;
+                Ok(true)
+            }
+            None => {
+                self.create_comment(issue_number, &format_issue_links(&[pr_number]))
+                    .await?;
+                Ok(true)
+            }
+        }
     }
 
     async fn list_comments(&self, pr_number: u64) -> Result<Vec<Comment>, ForgeError> {
-        let comments = self
-            .client
-            .issues(&self.owner, &self.repo)
-            .list_comments(pr_number)
-            .send()
-            .await
-            .map_err(map_octocrab_error)?;
+        let comments: Vec<octocrab::models::issues::Comment> = self
+            .get(
+                &self.repo_route(&format!("issues/{pr_number}/comments")),
+                &[],
+            )
+            .await?;
 
         Ok(comments
-            .items
             .into_iter()
             .map(|c| Comment {
                 id: c.id.into_inner(),
+                node_id: Some(c.node_id),
                 body: c.body.unwrap_or_default(),
             })
             .collect())
     }
 
     async fn create_comment(&self, pr_number: u64, body: &str) -> Result<Comment, ForgeError> {
-        let comment = self
-            .client
-            .issues(&self.owner, &self.repo)
-            .create_comment(pr_number, body)
-            .await
-            .map_err(map_octocrab_error)?;
+        let comment: octocrab::models::issues::Comment = self
+            .post(
+                &self.repo_route(&format!("issues/{pr_number}/comments")),
+                &serde_json::json!({ "body": body }),
+            )
+            .await?;
 
         Ok(Comment {
             id: comment.id.into_inner(),
+            node_id: Some(comment.node_id),
             body: comment.body.unwrap_or_default(),
         })
     }
 
     async fn update_comment(&self, comment_id: u64, body: &str) -> Result<(), ForgeError> {
-        self.client
-            .issues(&self.owner, &self.repo)
-            .update_comment(CommentId::from(comment_id), body)
-            .await
-            .map_err(map_octocrab_error)?;
-        Ok(())
+        self.patch(
+            &self.repo_route(&format!("issues/comments/{comment_id}")),
+            &serde_json::json!({ "body": body }),
+        )
+        .await
     }
 
     async fn update_pr_body(&self, pr_number: u64, body: &str) -> Result<(), ForgeError> {
-        self.client
-            .pulls(&self.owner, &self.repo)
-            .update(pr_number)
-            .body(body)
-            .send()
-            .await
-            .map_err(map_octocrab_error)?;
-        Ok(())
+        self.update_pr(pr_number, None, Some(body)).await
     }
 
     async fn delete_comment(&self, comment_id: u64) -> Result<(), ForgeError> {
-        self.client
-            .issues(&self.owner, &self.repo)
-            .delete_comment(CommentId::from(comment_id))
+        self.send::<IgnoredAny>(
+            Method::DELETE,
+            &self.repo_route(&format!("issues/comments/{comment_id}")),
+            &[],
+            None,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn branch_exists(&self, branch: &str) -> Result<bool, ForgeError> {
+        self.ref_exists(&self.owner, &self.repo, branch).await
+    }
+
+    async fn head_branch_exists(&self, branch: &str) -> Result<bool, ForgeError> {
+        self.ref_exists(&self.head_owner, &self.head_repo, branch)
             .await
-            .map_err(map_octocrab_error)?;
+    }
+
+    async fn list_check_runs(&self, branch: &str) -> Result<Vec<CheckRun>, ForgeError> {
+        let runs: octocrab::models::checks::ListCheckRuns = self
+            .get(
+                &format!(
+                    "/repos/{}/{}/commits/{branch}/check-runs",
+                    self.head_owner, self.head_repo
+                ),
+                &[("per_page", "100")],
+            )
+            .await?;
+
+        Ok(runs
+            .check_runs
+            .into_iter()
+            .map(|run| CheckRun {
+                state: map_check_conclusion(run.conclusion.as_deref()),
+                name: run.name,
+            })
+            .collect())
+    }
+
+    async fn list_reviews(&self, pr_number: u64) -> Result<Vec<Review>, ForgeError> {
+        let reviews: Vec<octocrab::models::pulls::Review> = self
+            .get(
+                &self.repo_route(&format!("pulls/{pr_number}/reviews")),
+                &[("per_page", "100")],
+            )
+            .await?;
+
+        Ok(reviews
+            .into_iter()
+            .filter_map(|review| {
+                Some(Review {
+                    author: review.user?.login,
+                    state: map_review_state(review.state?)?,
+                })
+            })
+            .collect())
+    }
+
+    async fn request_reviewers(
+        &self,
+        pr_number: u64,
+        reviewers: &[String],
+    ) -> Result<(), ForgeError> {
+        let (teams, users): (Vec<&String>, Vec<&String>) =
+            reviewers.iter().partition(|r| r.contains('/'));
+        let team_slugs: Vec<&str> = teams
+            .iter()
+            .filter_map(|t| t.split_once('/').map(|(_, slug)| slug))
+            .collect();
+        let _: IgnoredAny = self
+            .post(
+                &self.repo_route(&format!("pulls/{pr_number}/requested_reviewers")),
+                &serde_json::json!({
+                    "reviewers": users,
+                    "team_reviewers": team_slugs,
+                }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn add_labels(&self, pr_number: u64, labels: &[String]) -> Result<(), ForgeError> {
+        let _: IgnoredAny = self
+            .post(
+                &self.repo_route(&format!("issues/{pr_number}/labels")),
+                &serde_json::json!({ "labels": labels }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn add_assignees(&self, pr_number: u64, assignees: &[String]) -> Result<(), ForgeError> {
+        let _: IgnoredAny = self
+            .post(
+                &self.repo_route(&format!("issues/{pr_number}/assignees")),
+                &serde_json::json!({ "assignees": assignees }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn enable_auto_merge(
+        &self,
+        pr_number: u64,
+        method: MergeMethod,
+    ) -> Result<(), ForgeError> {
+        let node_id = self.pr_node_id(pr_number).await?;
+        self.mutate(&auto_merge_mutation(&node_id, method)).await
+    }
+
+    async fn mark_ready_for_review(&self, pr_number: u64) -> Result<(), ForgeError> {
+        let node_id = self.pr_node_id(pr_number).await?;
+        self.mutate(&ready_for_review_mutation(&node_id)).await
+    }
+
+    async fn convert_to_draft(&self, pr_number: u64) -> Result<(), ForgeError> {
+        let node_id = self.pr_node_id(pr_number).await?;
+        self.mutate(&convert_to_draft_mutation(&node_id)).await
+    }
+
+    fn supports_batch_mutations(&self) -> bool {
+        true
+    }
+
+    async fn update_comments_batch(&self, edits: &[CommentEdit]) -> Result<(), ForgeError> {
+        for chunk in edits.chunks(GRAPHQL_BATCH_SIZE) {
+            self.mutate(&comment_batch_mutation(chunk)?).await?;
+        }
         Ok(())
     }
 }
 
+/// `route` with `query` appended as a percent-encoded query string.
+fn with_query(route: &str, query: &[(&str, &str)]) -> String {
+    let mut route = route.to_string();
+    for (i, (key, value)) in query.iter().enumerate() {
+        route.push(if i == 0 { '?' } else { '&' });
+        route.push_str(&percent_encode(key));
+        route.push('=');
+        route.push_str(&percent_encode(value));
+    }
+    route
+}
+
+/// Percent-encode everything but RFC 3986's unreserved characters.
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(char::from(byte));
+        } else {
+            let _ = write!(encoded, "%{byte:02X}");
+        }
+    }
+    encoded
+}
+
+/// Send a request through `gh api` and return the response body, or `None`
+/// if GitHub answered 404. gh adds its own authentication.
+async fn gh_api(
+    method: &Method,
+    route: &str,
+    body: Option<&serde_json::Value>,
+) -> Result<Option<String>, ForgeError> {
+    let mut command = tokio::process::Command::new("gh");
+    command
+        .args(["api", "--method", method.as_str(), route])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if body.is_some() {
+        command.args(["--input", "-"]);
+    }
+    let spawn_failed = |e: std::io::Error| ForgeError::Api {
+        message: format!("failed to run `gh api`: {e}"),
+        source: Box::new(e),
+    };
+    let mut child = command.spawn().map_err(spawn_failed)?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    if let Some(body) = body {
+        stdin
+            .write_all(body.to_string().as_bytes())
+            .await
+            .map_err(spawn_failed)?;
+    }
+    drop(stdin);
+    let output = child.wait_with_output().await.map_err(spawn_failed)?;
+    if output.status.success() {
+        return Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()));
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    match gh_http_status(&stderr) {
+        Some(404) => Ok(None),
+        Some(401 | 403) => Err(ForgeError::AuthFailed {
+            source: stderr.clone().into(),
+            message: stderr,
+        }),
+        _ => Err(api_error(stderr)),
+    }
+}
+
+/// The HTTP status in a `gh api` error, which gh ends with `(HTTP 404)`.
+fn gh_http_status(stderr: &str) -> Option<u16> {
+    let (_, rest) = stderr.rsplit_once("(HTTP ")?;
+    rest.split_once(')')?.0.parse().ok()
+}
+
+/// Build one GraphQL request that edits every comment in `edits` through
+/// aliased `updateIssueComment` mutations (`c0`, `c1`, ...).
+///
+/// IDs and bodies travel as variables so comment text never needs escaping
+/// inside the query string.
+fn comment_batch_mutation(edits: &[CommentEdit]) -> Result<serde_json::Value, ForgeError> {
+    let mut params = Vec::with_capacity(edits.len());
+    let mut fields = Vec::with_capacity(edits.len());
+    let mut variables = serde_json::Map::new();
+    for (i, edit) in edits.iter().enumerate() {
+        let node_id = edit
+            .node_id
+            .as_ref()
+            .ok_or(ForgeError::MalformedResponse { field: "node_id" })?;
+        params.push(format!("$id{i}: ID!, $body{i}: String!"));
+        fields.push(format!(
+            "c{i}: updateIssueComment(input: {{id: $id{i}, body: $body{i}}}) {{ clientMutationId \
+             }}"
+        ));
+        variables.insert(format!("id{i}"), serde_json::Value::from(node_id.as_str()));
+        variables.insert(
+            format!("body{i}"),
+            serde_json::Value::from(edit.body.as_str()),
+        );
+    }
+    let query = format!("mutation({}) {{ {} }}", params.join(", "), fields.join(" "));
+    Ok(serde_json::json!({ "query": query, "variables": variables }))
+}
+
+/// Build the `enablePullRequestAutoMerge` request for the PR with `node_id`.
+fn auto_merge_mutation(node_id: &str, method: MergeMethod) -> serde_json::Value {
+    let method = match method {
+        MergeMethod::Merge => "MERGE",
+        MergeMethod::Squash => "SQUASH",
+        MergeMethod::Rebase => "REBASE",
+    };
+    serde_json::json!({
+        "query": "mutation($id: ID!, $method: PullRequestMergeMethod!) { \
+                  enablePullRequestAutoMerge(input: {pullRequestId: $id, mergeMethod: $method}) \
+                  { clientMutationId } }",
+        "variables": { "id": node_id, "method": method },
+    })
+}
+
+/// Build the `markPullRequestReadyForReview` request for the PR with
+/// `node_id`. The REST API cannot take a PR out of draft.
+fn ready_for_review_mutation(node_id: &str) -> serde_json::Value {
+    serde_json::json!({
+        "query": "mutation($id: ID!) { \
+                  markPullRequestReadyForReview(input: {pullRequestId: $id}) \
+                  { clientMutationId } }",
+        "variables": { "id": node_id },
+    })
+}
+
+/// Build the `convertPullRequestToDraft` request for the PR with `node_id`.
+fn convert_to_draft_mutation(node_id: &str) -> serde_json::Value {
+    serde_json::json!({
+        "query": "mutation($id: ID!) { \
+                  convertPullRequestToDraft(input: {pullRequestId: $id}) \
+                  { clientMutationId } }",
+        "variables": { "id": node_id },
+    })
+}
+
+/// Build one GraphQL query for the open PRs of every branch in `heads`
+/// through aliased `pullRequests` fields (`h0`, `h1`, ...), each with its
+/// reviews and the check runs on its head commit.
+fn pr_lookup_query(owner: &str, repo: &str, heads: &[String]) -> serde_json::Value {
+    let mut params = vec!["$owner: String!".to_string(), "$repo: String!".to_string()];
+    let mut fields = Vec::with_capacity(heads.len());
+    let mut variables = serde_json::Map::new();
+    variables.insert("owner".to_string(), owner.into());
+    variables.insert("repo".to_string(), repo.into());
+    for (i, head) in heads.iter().enumerate() {
+        params.push(format!("$h{i}: String!"));
+        fields.push(format!(
+            "h{i}: pullRequests(headRefName: $h{i}, states: OPEN, first: 10) {{ nodes {{ \
+             ...details }} }}"
+        ));
+        variables.insert(format!("h{i}"), head.as_str().into());
+    }
+    let query = format!(
+        "query({}) {{ repository(owner: $owner, name: $repo) {{ {} }} }} {PR_DETAILS_FRAGMENT}",
+        params.join(", "),
+        fields.join(" ")
+    );
+    serde_json::json!({ "query": query, "variables": variables })
+}
+
+/// The fields of an open PR that [`convert_pr_details`] reads, as the
+/// GraphQL fragment `details`.
+const PR_DETAILS_FRAGMENT: &str =
+    "fragment details on PullRequest { number url title headRefName baseRefName body isDraft \
+     headRepositoryOwner { login } reviews(first: 100) { nodes { author { login } state } } \
+     commits(last: 1) { nodes { commit { statusCheckRollup { contexts(first: 100) { nodes { ... \
+     on CheckRun { name conclusion } } } } } } } }";
+
+/// Build the search for the open PRs of `owner/repo` that request a review
+/// from the authenticated user.
+fn review_requests_query(owner: &str, repo: &str) -> serde_json::Value {
+    let search = format!("repo:{owner}/{repo} is:pr is:open review-requested:@me");
+    serde_json::json!({
+        "query": format!(
+            "query($q: String!) {{ search(query: $q, type: ISSUE, first: 100) {{ nodes {{ \
+             ... on PullRequest {{ ...details }} }} }} }} {PR_DETAILS_FRAGMENT}"
+        ),
+        "variables": { "q": search },
+    })
+}
+
+/// Build the query for the node ID of project `number` of `owner`, which may
+/// be a user or an organization.
+fn project_id_query(owner: &str, number: u64) -> serde_json::Value {
+    serde_json::json!({
+        "query": "query($owner: String!, $number: Int!) { \
+                  repositoryOwner(login: $owner) { \
+                  ... on ProjectV2Owner { projectV2(number: $number) { id } } } }",
+        "variables": { "owner": owner, "number": number },
+    })
+}
+
+/// Build the `addProjectV2ItemById` request adding the PR with `node_id` to
+/// the project with `project_id`.
+fn add_to_project_mutation(project_id: &str, node_id: &str) -> serde_json::Value {
+    serde_json::json!({
+        "query": "mutation($project: ID!, $content: ID!) { \
+                  addProjectV2ItemById(input: {projectId: $project, contentId: $content}) \
+                  { item { id } } }",
+        "variables": { "project": project_id, "content": node_id },
+    })
+}
+
+/// The number of the milestone titled `title` in a milestone listing.
+fn find_milestone(milestones: &serde_json::Value, title: &str) -> Option<u64> {
+    milestones
+        .as_array()?
+        .iter()
+        .find(|m| m.get("title").and_then(serde_json::Value::as_str) == Some(title))?
+        .get("number")?
+        .as_u64()
+}
+
+/// The first of the PRs in `nodes` whose head branch lives in a repository
+/// of `owner`. GitHub logins are case-insensitive, and the owner taken from
+/// a remote URL may be cased differently from the login.
+fn pr_from_owner<'a>(nodes: &'a [serde_json::Value], owner: &str) -> Option<&'a serde_json::Value> {
+    nodes.iter().find(|pr| {
+        pr.pointer("/headRepositoryOwner/login")
+            .and_then(serde_json::Value::as_str)
+            .is_some_and(|login| login.eq_ignore_ascii_case(owner))
+    })
+}
+
+/// The verified addresses in a listing of the user's emails.
+fn verified_emails(emails: &serde_json::Value) -> Vec<String> {
+    emails
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|e| e.get("verified").and_then(serde_json::Value::as_bool) == Some(true))
+        .filter_map(|e| e.get("email").and_then(serde_json::Value::as_str))
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// An API error carrying only a message.
+fn api_error(message: String) -> ForgeError {
+    ForgeError::Api {
+        source: message.clone().into(),
+        message,
+    }
+}
+
+/// Join the messages of a GraphQL response's `errors` array, if any.
+///
+/// GitHub reports mutation failures (e.g. auto-merge disabled for the
+/// repository) with a 200 status and an `errors` array, so they never reach
+/// `map_octocrab_error`.
+fn graphql_error_message(response: &serde_json::Value) -> Option<String> {
+    let errors = response.get("errors")?.as_array()?;
+    if errors.is_empty() {
+        return None;
+    }
+    Some(
+        errors
+            .iter()
+            .map(|e| {
+                e.get("message")
+                    .and_then(|m| m.as_str())
+                    .unwrap_or("unknown error")
+            })
+            .collect::<Vec<_>>()
+            .join("; "),
+    )
+}
+
 /// Convert an octocrab pull request into the forge-agnostic type.
 ///
 /// octocrab 0.53 made `number`, `head`, and `base` optional; GitHub always
@@ -191,9 +1086,78 @@ fn convert_pr(pr: octocrab::models::pulls::PullRequest) -> Result<PullRequest, F
         base_ref: base.ref_field,
         state: map_pr_state(pr.state.as_ref(), pr.merged_at.is_some()),
         body: pr.body,
+        draft: pr.draft.unwrap_or(false),
+    })
+}
+
+/// Convert an open PR from a [`pr_lookup_query`] response.
+fn convert_pr_details(pr: &serde_json::Value) -> Result<PrDetails, ForgeError> {
+    let str_field = |field: &'static str| {
+        pr.get(field)
+            .and_then(serde_json::Value::as_str)
+            .map(ToString::to_string)
+            .ok_or(ForgeError::MalformedResponse { field })
+    };
+    let nodes = |pointer: &str| {
+        pr.pointer(pointer)
+            .and_then(serde_json::Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    };
+    let reviews = nodes("/reviews/nodes")
+        .iter()
+        .filter_map(|review| {
+            Some(Review {
+                author: review.pointer("/author/login")?.as_str()?.to_string(),
+                state: map_graphql_review_state(review.get("state")?.as_str()?)?,
+            })
+        })
+        .collect();
+    // Status contexts other than check runs come back as empty objects.
+    let checks = nodes("/commits/nodes/0/commit/statusCheckRollup/contexts/nodes")
+        .iter()
+        .filter_map(|run| {
+            let conclusion = run
+                .get("conclusion")
+                .and_then(serde_json::Value::as_str)
+                .map(str::to_ascii_lowercase);
+            Some(CheckRun {
+                name: run.get("name")?.as_str()?.to_string(),
+                state: map_check_conclusion(conclusion.as_deref()),
+            })
+        })
+        .collect();
+
+    Ok(PrDetails {
+        pr: PullRequest {
+            number: pr
+                .get("number")
+                .and_then(serde_json::Value::as_u64)
+                .ok_or(ForgeError::MalformedResponse { field: "number" })?,
+            html_url: str_field("url")?,
+            title: str_field("title")?,
+            head_ref: str_field("headRefName")?,
+            base_ref: str_field("baseRefName")?,
+            state: PrState::Open,
+            body: str_field("body").ok().filter(|body| !body.is_empty()),
+            draft: pr
+                .get("isDraft")
+                .and_then(serde_json::Value::as_bool)
+                .unwrap_or(false),
+        },
+        reviews,
+        checks,
     })
 }
 
+fn convert_issue(issue: octocrab::models::issues::Issue) -> Issue {
+    Issue {
+        number: issue.number,
+        html_url: issue.html_url.to_string(),
+        body: issue.body,
+    }
+}
+
 fn map_octocrab_error(e: octocrab::Error) -> ForgeError {
     let is_auth_error = matches!(
         &e,
@@ -218,6 +1182,42 @@ fn map_octocrab_error(e: octocrab::Error) -> ForgeError {
     }
 }
 
+/// Map a check run conclusion to a [`CheckState`]. Runs that have not
+/// completed have no conclusion yet.
+fn map_check_conclusion(conclusion: Option<&str>) -> CheckState {
+    match conclusion {
+        None => CheckState::Pending,
+        Some("success" | "neutral" | "skipped") => CheckState::Passed,
+        Some(_) => CheckState::Failed,
+    }
+}
+
+/// Map a GitHub review state to a [`ReviewState`]; `None` for states that
+/// carry no verdict.
+fn map_review_state(state: octocrab::models::pulls::ReviewState) -> Option<ReviewState> {
+    use octocrab::models::pulls::ReviewState as GitHubReviewState;
+    match state {
+        GitHubReviewState::Approved => Some(ReviewState::Approved),
+        GitHubReviewState::ChangesRequested => Some(ReviewState::ChangesRequested),
+        GitHubReviewState::Commented => Some(ReviewState::Commented),
+        GitHubReviewState::Dismissed => Some(ReviewState::Dismissed),
+        GitHubReviewState::Pending => Some(ReviewState::Pending),
+        _ => None,
+    }
+}
+
+/// Map a GraphQL `PullRequestReviewState` to a [`ReviewState`].
+fn map_graphql_review_state(state: &str) -> Option<ReviewState> {
+    match state {
+        "APPROVED" => Some(ReviewState::Approved),
+        "CHANGES_REQUESTED" => Some(ReviewState::ChangesRequested),
+        "COMMENTED" => Some(ReviewState::Commented),
+        "DISMISSED" => Some(ReviewState::Dismissed),
+        "PENDING" => Some(ReviewState::Pending),
+        _ => None,
+    }
+}
+
 fn map_pr_state(state: Option<&IssueState>, has_merged_at: bool) -> PrState {
     if has_merged_at {
         PrState::Merged
@@ -227,3 +1227,360 @@ fn map_pr_state(state: Option<&IssueState>, has_merged_at: bool) -> PrState {
         PrState::Open
     }
 }
+
+/// The `parent` of a REST repository object.
+fn fork_parent_of(repo: &serde_json::Value) -> Option<ForgeRepoRef> {
+    let parent = repo.get("parent")?;
+    Some(ForgeRepoRef::new(
+        parent.pointer("/owner/login")?.as_str()?,
+        parent.get("name")?.as_str()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_values_are_percent_encoded() {
+        assert_eq!(
+            with_query(
+                "/repos/o/r/pulls",
+                &[("head", "o:feat/a b"), ("state", "open")]
+            ),
+            "/repos/o/r/pulls?head=o%3Afeat%2Fa%20b&state=open"
+        );
+        assert_eq!(with_query("/user", &[]), "/user");
+    }
+
+    #[test]
+    fn gh_http_status_reads_the_last_status() {
+        assert_eq!(gh_http_status("gh: Not Found (HTTP 404)\n"), Some(404));
+        assert_eq!(
+            gh_http_status("gh: Bad credentials (HTTP 401)\n"),
+            Some(401)
+        );
+        assert_eq!(gh_http_status("error connecting to api.github.com"), None);
+    }
+
+    #[test]
+    fn fork_parent_of_reads_parent_owner_and_name() {
+        let fork = serde_json::json!({
+            "name": "stakk",
+            "owner": { "login": "me" },
+            "parent": { "name": "stakk", "owner": { "login": "glennib" } },
+        });
+        assert_eq!(
+            fork_parent_of(&fork),
+            Some(ForgeRepoRef::new("glennib", "stakk"))
+        );
+        assert_eq!(
+            fork_parent_of(&serde_json::json!({ "name": "stakk" })),
+            None
+        );
+    }
+
+    fn edit(id: u64, node_id: Option<&str>, body: &str) -> CommentEdit {
+        CommentEdit {
+            comment_id: id,
+            node_id: node_id.map(ToString::to_string),
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn auto_merge_mutation_passes_method_as_variable() {
+        let payload = auto_merge_mutation("PR_kw1", MergeMethod::Squash);
+        assert_eq!(payload["variables"]["id"], "PR_kw1");
+        assert_eq!(payload["variables"]["method"], "SQUASH");
+        assert!(
+            payload["query"]
+                .as_str()
+                .unwrap()
+                .contains("enablePullRequestAutoMerge")
+        );
+    }
+
+    #[test]
+    fn project_requests_pass_ids_as_variables() {
+        let query = project_id_query("acme", 7);
+        assert_eq!(query["variables"]["owner"], "acme");
+        assert_eq!(query["variables"]["number"], 7);
+        let mutation = add_to_project_mutation("PVT_kw1", "PR_kw1");
+        assert_eq!(mutation["variables"]["project"], "PVT_kw1");
+        assert_eq!(mutation["variables"]["content"], "PR_kw1");
+        assert!(
+            mutation["query"]
+                .as_str()
+                .unwrap()
+                .contains("addProjectV2ItemById")
+        );
+    }
+
+    #[test]
+    fn find_milestone_matches_the_title() {
+        let milestones = serde_json::json!([
+            { "number": 3, "title": "Sprint 41" },
+            { "number": 5, "title": "Sprint 42" },
+        ]);
+        assert_eq!(find_milestone(&milestones, "Sprint 42"), Some(5));
+        assert_eq!(find_milestone(&milestones, "sprint 42"), None);
+    }
+
+    #[test]
+    fn verified_emails_skips_unverified() {
+        let emails = serde_json::json!([
+            { "email": "me@example.com", "verified": true, "primary": true },
+            { "email": "old@example.com", "verified": false, "primary": false },
+        ]);
+        assert_eq!(verified_emails(&emails), ["me@example.com"]);
+    }
+
+    #[test]
+    fn ready_for_review_mutation_passes_node_id() {
+        let payload = ready_for_review_mutation("PR_kw1");
+        assert_eq!(payload["variables"]["id"], "PR_kw1");
+        assert!(
+            payload["query"]
+                .as_str()
+                .unwrap()
+                .contains("markPullRequestReadyForReview")
+        );
+        let payload = convert_to_draft_mutation("PR_kw1");
+        assert_eq!(payload["variables"]["id"], "PR_kw1");
+        assert!(
+            payload["query"]
+                .as_str()
+                .unwrap()
+                .contains("convertPullRequestToDraft")
+        );
+    }
+
+    #[test]
+    fn graphql_error_message_joins_errors() {
+        let ok = serde_json::json!({ "data": { "enablePullRequestAutoMerge": null } });
+        assert_eq!(graphql_error_message(&ok), None);
+
+        let failed = serde_json::json!({
+            "errors": [
+                { "message": "Auto merge is not allowed for this repository" },
+                { "message": "second" },
+            ]
+        });
+        assert_eq!(
+            graphql_error_message(&failed).as_deref(),
+            Some("Auto merge is not allowed for this repository; second")
+        );
+    }
+
+    /// A forge talking to a one-shot server on localhost that answers the
+    /// first request with `200 OK` and the JSON `body`.
+    fn forge_answering(body: &serde_json::Value) -> GitHubForge {
+        use std::io::BufRead;
+        use std::io::Read;
+        use std::io::Write;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let body = body.to_string();
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = std::io::BufReader::new(stream);
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':')
+                    && name.eq_ignore_ascii_case("content-length")
+                {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+            reader.read_exact(&mut vec![0; length]).unwrap();
+            let reply = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: \
+                 {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            reader.get_mut().write_all(reply.as_bytes()).unwrap();
+        });
+
+        let client = Octocrab::builder()
+            .base_uri(url)
+            .unwrap()
+            .personal_token("t".to_string())
+            .build()
+            .unwrap();
+        GitHubForge {
+            client: Client::Octocrab(client),
+            budget: Arc::new(Semaphore::new(1)),
+            owner: "o".to_string(),
+            repo: "r".to_string(),
+            head_owner: "o".to_string(),
+            head_repo: "r".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn update_comments_batch_fails_on_graphql_errors() {
+        let forge = forge_answering(&serde_json::json!({
+            "data": { "c0": null },
+            "errors": [{ "message": "Could not resolve to a node with the global id" }],
+        }));
+
+        let result = forge
+            .update_comments_batch(&[edit(1, Some("IC_gone"), "one")])
+            .await;
+
+        let Err(ForgeError::Api { message, .. }) = result else {
+            panic!("expected an API error, got {result:?}");
+        };
+        assert!(message.contains("Could not resolve"), "{message}");
+    }
+
+    #[test]
+    fn comment_batch_mutation_aliases_each_edit() {
+        let payload =
+            comment_batch_mutation(&[edit(1, Some("IC_a"), "one"), edit(2, Some("IC_b"), "two")])
+                .unwrap();
+        let query = payload["query"].as_str().unwrap();
+        assert!(query.starts_with("mutation($id0: ID!, $body0: String!, $id1: ID!"));
+        assert!(query.contains("c0: updateIssueComment(input: {id: $id0, body: $body0})"));
+        assert!(query.contains("c1: updateIssueComment(input: {id: $id1, body: $body1})"));
+        assert_eq!(payload["variables"]["id1"], "IC_b");
+        assert_eq!(payload["variables"]["body0"], "one");
+    }
+
+    #[test]
+    fn pr_lookup_query_aliases_each_head() {
+        let heads = ["feat-a".to_string(), "feat-b".to_string()];
+        let payload = pr_lookup_query("o", "r", &heads);
+        let query = payload["query"].as_str().unwrap();
+        assert!(query.starts_with("query($owner: String!, $repo: String!, $h0: String!"));
+        assert!(query.contains("h1: pullRequests(headRefName: $h1, states: OPEN"));
+        assert!(!query.contains("feat-a"));
+        assert_eq!(payload["variables"]["h1"], "feat-b");
+        assert_eq!(payload["variables"]["owner"], "o");
+        assert!(query.ends_with(PR_DETAILS_FRAGMENT));
+    }
+
+    #[test]
+    fn pr_from_owner_ignores_login_case() {
+        let nodes = [
+            serde_json::json!({ "number": 1, "headRepositoryOwner": { "login": "someone" } }),
+            serde_json::json!({ "number": 2, "headRepositoryOwner": { "login": "glennib" } }),
+        ];
+        assert_eq!(pr_from_owner(&nodes, "Glennib").unwrap()["number"], 2);
+        assert!(pr_from_owner(&nodes, "other").is_none());
+    }
+
+    #[test]
+    fn review_requests_query_searches_the_repository() {
+        let payload = review_requests_query("o", "r");
+        assert_eq!(
+            payload["variables"]["q"],
+            "repo:o/r is:pr is:open review-requested:@me"
+        );
+        assert!(
+            payload["query"]
+                .as_str()
+                .unwrap()
+                .contains("... on PullRequest { ...details }")
+        );
+    }
+
+    #[test]
+    fn convert_pr_details_reads_reviews_and_check_runs() {
+        let pr = serde_json::json!({
+            "number": 12,
+            "url": "https://github.com/o/r/pull/12",
+            "title": "Add feature",
+            "headRefName": "feat-b",
+            "baseRefName": "feat-a",
+            "body": "",
+            "isDraft": true,
+            "headRepositoryOwner": { "login": "o" },
+            "reviews": { "nodes": [
+                { "author": { "login": "alice" }, "state": "APPROVED" },
+                { "author": null, "state": "COMMENTED" },
+            ] },
+            "commits": { "nodes": [ { "commit": { "statusCheckRollup": { "contexts": {
+                "nodes": [
+                    { "name": "build", "conclusion": "SUCCESS" },
+                    { "name": "test", "conclusion": null },
+                    {},
+                ]
+            } } } } ] },
+        });
+        let details = convert_pr_details(&pr).unwrap();
+        assert_eq!(details.pr.number, 12);
+        assert_eq!(details.pr.base_ref, "feat-a");
+        assert_eq!(details.pr.body, None);
+        assert!(details.pr.draft);
+        assert_eq!(details.reviews.len(), 1);
+        assert_eq!(details.reviews[0].state, ReviewState::Approved);
+        let checks: Vec<_> = details.checks.iter().map(|c| (&*c.name, c.state)).collect();
+        assert_eq!(
+            checks,
+            [("build", CheckState::Passed), ("test", CheckState::Pending)]
+        );
+    }
+
+    #[test]
+    fn convert_pr_details_without_checks() {
+        let pr = serde_json::json!({
+            "number": 3,
+            "url": "https://github.com/o/r/pull/3",
+            "title": "Fix",
+            "headRefName": "fix",
+            "baseRefName": "main",
+            "body": "Details",
+            "isDraft": false,
+            "reviews": { "nodes": [] },
+            "commits": { "nodes": [ { "commit": { "statusCheckRollup": null } } ] },
+        });
+        let details = convert_pr_details(&pr).unwrap();
+        assert_eq!(details.pr.body.as_deref(), Some("Details"));
+        assert!(details.checks.is_empty());
+    }
+
+    #[test]
+    fn comment_batch_mutation_keeps_bodies_out_of_query() {
+        let payload =
+            comment_batch_mutation(&[edit(1, Some("IC_a"), "quote \" and } brace")]).unwrap();
+        let query = payload["query"].as_str().unwrap();
+        assert!(!query.contains("brace"));
+        assert_eq!(payload["variables"]["body0"], "quote \" and } brace");
+    }
+
+    #[test]
+    fn comment_batch_mutation_requires_node_id() {
+        let result = comment_batch_mutation(&[edit(1, None, "body")]);
+        assert!(matches!(
+            result,
+            Err(ForgeError::MalformedResponse { field: "node_id" })
+        ));
+    }
+
+    #[test]
+    fn check_conclusion_mapping() {
+        assert_eq!(map_check_conclusion(None), CheckState::Pending);
+        assert_eq!(map_check_conclusion(Some("success")), CheckState::Passed);
+        assert_eq!(map_check_conclusion(Some("skipped")), CheckState::Passed);
+        assert_eq!(map_check_conclusion(Some("failure")), CheckState::Failed);
+        assert_eq!(map_check_conclusion(Some("timed_out")), CheckState::Failed);
+    }
+
+    #[test]
+    fn review_state_mapping() {
+        use octocrab::models::pulls::ReviewState as GitHubReviewState;
+        assert_eq!(
+            map_review_state(GitHubReviewState::ChangesRequested),
+            Some(ReviewState::ChangesRequested)
+        );
+        assert_eq!(map_review_state(GitHubReviewState::Open), None);
+    }
+}
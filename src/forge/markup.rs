@@ -0,0 +1,1127 @@
+//! Stack comment formatting and parsing, and the regions of PR bodies that
+//! stakk manages.
+//!
+//! Comments include base64-encoded metadata on the first line so that
+//! future runs can identify and update the same comment idempotently. In PR
+//! bodies, stakk only writes inside [`ManagedRegion`]s, so the rest of the
+//! body is left as the author wrote it.
+
+use std::fmt::Write as _;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use clap::ValueEnum;
+use minijinja::Environment;
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::Comment;
+use super::ForgeRepoRef;
+use crate::submit::SubmitError;
+
+/// Where stack metadata is placed on a pull request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StackPlacement {
+    /// Place the stack comment as a separate PR comment (issue comment).
+    #[default]
+    Comment,
+    /// Place the stack content in a fenced section of the PR body.
+    Body,
+    /// Write no stack information at all.
+    None,
+}
+
+impl std::fmt::Display for StackPlacement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pv = self
+            .to_possible_value()
+            .expect("all variants have possible values");
+        f.write_str(pv.get_name())
+    }
+}
+
+/// Built-in wording of the default stack comment template.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CommentStyle {
+    /// The stakk wording: a numbered list with 👈 at the current PR.
+    #[default]
+    Default,
+    /// Just the numbered list, with no footer.
+    Minimal,
+    /// PR titles next to each entry and a note on merge order.
+    Verbose,
+    /// Like verbose, with plain-text markers and no emoji.
+    Corporate,
+}
+
+impl CommentStyle {
+    /// The phrases the default template fills in for this style.
+    pub fn wording(self) -> CommentWording {
+        match self {
+            Self::Default => CommentWording {
+                intro: "This PR is part of a stack that merges into",
+                current_marker: " \u{1f448}",
+                show_titles: false,
+                outro: None,
+                footer: Some("Created with"),
+            },
+            Self::Minimal => CommentWording {
+                intro: "Stack into",
+                current_marker: " \u{2190}",
+                show_titles: false,
+                outro: None,
+                footer: None,
+            },
+            Self::Verbose => CommentWording {
+                intro: "This PR is part of a stack of dependent pull requests that merges into",
+                current_marker: " \u{1f448} (this PR)",
+                show_titles: true,
+                outro: Some(
+                    "Each PR builds on the one above it. Review and merge them in order, starting \
+                     from 1.",
+                ),
+                footer: Some("Created with"),
+            },
+            Self::Corporate => CommentWording {
+                intro: "This pull request is part of a series that merges into",
+                current_marker: " (this pull request)",
+                show_titles: true,
+                outro: Some("Please review and merge the pull requests in the order listed."),
+                footer: Some("Managed with"),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for CommentStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pv = self
+            .to_possible_value()
+            .expect("all variants have possible values");
+        f.write_str(pv.get_name())
+    }
+}
+
+/// The phrases of a [`CommentStyle`], available to stack comment templates
+/// as `style`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CommentWording {
+    /// Text before the default branch in the first line.
+    pub intro: &'static str,
+    /// Appended to the entry of the PR the comment is on.
+    pub current_marker: &'static str,
+    /// Whether each entry is followed by its PR title.
+    pub show_titles: bool,
+    /// Paragraph after the list.
+    pub outro: Option<&'static str>,
+    /// Text before the stakk link at the bottom; no link when `None`.
+    pub footer: Option<&'static str>,
+}
+
+/// Start fence for stack content embedded in a PR body.
+const BODY_FENCE_START: &str = "<!-- STAKK_BODY_START -->";
+/// End fence for stack content embedded in a PR body.
+const BODY_FENCE_END: &str = "<!-- STAKK_BODY_END -->";
+
+/// The stack section of a PR body, written in body placement mode.
+pub const STACK_REGION: ManagedRegion = ManagedRegion::new(BODY_FENCE_START, BODY_FENCE_END);
+
+/// The dependency links of `--link-prs` in a PR body.
+pub const LINKS_REGION: ManagedRegion =
+    ManagedRegion::new("<!-- STAKK_LINKS_START -->", "<!-- STAKK_LINKS_END -->");
+
+/// The jj change ID a PR was opened for, so the PR can be found again
+/// after its bookmark is renamed.
+pub const CHANGE_ID_REGION: ManagedRegion = ManagedRegion::new("<!-- STAKK_CHANGE_ID", "-->");
+
+/// The local note on a change (`stakk note`), written by
+/// `--include-notes`.
+pub const NOTES_REGION: ManagedRegion =
+    ManagedRegion::new("<!-- STAKK_NOTES_START -->", "<!-- STAKK_NOTES_END -->");
+
+/// Every region stakk manages in PR bodies.
+pub const MANAGED_REGIONS: [ManagedRegion; 4] =
+    [STACK_REGION, LINKS_REGION, CHANGE_ID_REGION, NOTES_REGION];
+
+/// Prefix for the metadata HTML comment.
+const COMMENT_DATA_PREFIX: &str = "<!--- STAKK_STACK: ";
+/// Prefix of the metadata written before stakk was renamed from jack.
+/// Comments carrying it are still recognized, and get the current prefix
+/// the next time they are updated.
+const LEGACY_COMMENT_DATA_PREFIX: &str = "<!--- JACK_STACK: ";
+const COMMENT_DATA_POSTFIX: &str = " --->";
+
+/// Prefix for the metadata HTML comment of a stack's tracking issue.
+const UMBRELLA_DATA_PREFIX: &str = "<!--- STAKK_UMBRELLA: ";
+
+/// First line of the comment linking an issue to the PRs that reference it.
+const ISSUE_LINKS_MARKER: &str = "<!--- STAKK_ISSUE_LINKS --->";
+
+const DEFAULT_TEMPLATE: &str = include_str!("default_comment.md.jinja");
+
+/// Public URL of the stakk repository.
+pub const STAKK_REPO_URL: &str = "https://github.com/glennib/stakk";
+
+/// Metadata embedded in stack comments as base64-encoded JSON.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StackCommentData {
+    pub version: u32,
+    pub stack: Vec<StackEntry>,
+}
+
+/// One entry in the stack comment metadata.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StackEntry {
+    /// The jj bookmark name.
+    pub bookmark_name: String,
+    /// Full URL to the pull request.
+    pub pr_url: String,
+    /// PR number.
+    pub pr_number: u64,
+    /// Repository of the PR. `None` in metadata written before stacks could
+    /// span repositories, meaning the repository of the commented PR.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<ForgeRepoRef>,
+}
+
+impl StackEntry {
+    /// Reference to this entry's PR as written from a PR in `viewer` (see
+    /// [`ForgeRepoRef::pr_ref`]).
+    pub fn pr_ref(&self, viewer: &ForgeRepoRef) -> String {
+        self.repo.as_ref().map_or_else(
+            || format!("#{}", self.pr_number),
+            |repo| repo.pr_ref(self.pr_number, viewer),
+        )
+    }
+}
+
+/// Template rendering context for a full stack comment.
+#[derive(Debug, Clone, Serialize)]
+pub struct StackCommentContext {
+    pub stack: Vec<StackEntryContext>,
+    pub stack_size: usize,
+    pub default_branch: String,
+    pub current_bookmark: String,
+    pub stakk_url: String,
+}
+
+/// Template rendering context for a single entry in the stack.
+#[derive(Debug, Clone, Serialize)]
+pub struct StackEntryContext {
+    pub bookmark_name: String,
+    pub pr_url: String,
+    pub pr_number: u64,
+    /// `#12`, or `owner/repo#12` for a PR in another repository.
+    pub pr_ref: String,
+    pub title: String,
+    pub base: String,
+    pub is_draft: bool,
+    pub position: usize,
+    pub is_current: bool,
+}
+
+/// Build a minijinja environment with the stack comment template loaded.
+///
+/// If `custom_template` is `Some`, it is used instead of the built-in
+/// default. Either template sees the wording of `style` as `style`, and the
+/// footer line (see [`set_comment_footer`]) as `footer`.
+pub fn build_comment_env(
+    custom_template: Option<&str>,
+    style: CommentStyle,
+) -> Result<Environment<'static>, SubmitError> {
+    let mut env = Environment::new();
+    let wording = style.wording();
+    let footer = wording
+        .footer
+        .map(|text| format!("<sub>{text} [stakk]({STAKK_REPO_URL})</sub>"));
+    env.add_global("footer", minijinja::Value::from(footer));
+    env.add_global("style", minijinja::Value::from_serialize(wording));
+    let source = match custom_template {
+        Some(s) => s.to_string(),
+        None => DEFAULT_TEMPLATE.to_string(),
+    };
+    env.add_template("stack_comment", Box::leak(source.into_boxed_str()))
+        .map_err(|e| SubmitError::TemplateRenderFailed {
+            message: format!("failed to compile template: {e}"),
+        })?;
+    Ok(env)
+}
+
+/// Replace the footer line of the built-in template with `footer`
+/// (Markdown). An empty footer leaves the line out.
+pub fn set_comment_footer(env: &mut Environment<'static>, footer: &str) {
+    let footer = (!footer.is_empty()).then(|| footer.to_string());
+    env.add_global("footer", minijinja::Value::from(footer));
+}
+
+/// Format a stack comment body for a specific PR in the stack.
+///
+/// Returns the metadata line (`<!--- STAKK_STACK: ... --->`) followed by the
+/// rendered template. Callers are responsible for adding any placement-specific
+/// preamble (e.g. warning lines) between the metadata and the rendered content.
+pub fn format_stack_comment(
+    data: &StackCommentData,
+    context: &StackCommentContext,
+    template: &minijinja::Template<'_, '_>,
+) -> Result<String, SubmitError> {
+    let encoded = BASE64.encode(serde_json::to_string(data).expect("serialization cannot fail"));
+    let metadata_line = format!("{COMMENT_DATA_PREFIX}{encoded}{COMMENT_DATA_POSTFIX}");
+
+    let rendered = template
+        .render(context)
+        .map_err(|e| SubmitError::TemplateRenderFailed {
+            message: e.to_string(),
+        })?;
+
+    Ok(format!("{metadata_line}\n{rendered}"))
+}
+
+/// Warning preamble for comment-mode stack comments.
+pub const COMMENT_WARNING: &str =
+    "<!-- This comment is managed by stakk. Manual edits will be overwritten. -->";
+
+/// Warning preamble for body-mode fenced stack sections.
+pub const BODY_WARNING: &str =
+    "<!-- This section is managed by stakk. Manual edits will be overwritten. -->";
+
+/// Insert the comment-mode warning preamble after the metadata line.
+///
+/// The preamble is inserted between line 1 (metadata) and the rest of the
+/// rendered content.
+pub fn with_comment_preamble(formatted: &str) -> String {
+    let mut lines = formatted.splitn(2, '\n');
+    let metadata = lines.next().unwrap_or("");
+    let rest = lines.next().unwrap_or("");
+    format!("{metadata}\n{COMMENT_WARNING}\n<!-- {STAKK_REPO_URL} -->\n{rest}")
+}
+
+/// Find the existing stack comment among a list of comments.
+///
+/// Detects by the `STAKK_STACK` metadata prefix on the first line.
+pub fn find_stack_comment(comments: &[Comment]) -> Option<&Comment> {
+    comments.iter().find(|c| {
+        c.body.contains(COMMENT_DATA_PREFIX) || c.body.contains(LEGACY_COMMENT_DATA_PREFIX)
+    })
+}
+
+/// Parse stack comment metadata from a comment body.
+///
+/// Returns `None` if the comment does not contain valid metadata.
+pub fn parse_stack_comment(body: &str) -> Option<StackCommentData> {
+    parse_metadata(body, COMMENT_DATA_PREFIX)
+        .or_else(|| parse_metadata(body, LEGACY_COMMENT_DATA_PREFIX))
+}
+
+/// Decode the metadata after `prefix` on the first line of `body`.
+fn parse_metadata(body: &str, prefix: &str) -> Option<StackCommentData> {
+    let first_line = body.lines().next()?;
+    let start = first_line.find(prefix)? + prefix.len();
+    let end = first_line[start..].find(COMMENT_DATA_POSTFIX)? + start;
+    let encoded = &first_line[start..end];
+    let decoded = BASE64.decode(encoded).ok()?;
+    let json_str = std::str::from_utf8(&decoded).ok()?;
+    serde_json::from_str(json_str).ok()
+}
+
+/// Format the body of a stack's tracking issue: metadata listing the
+/// stack's PRs, then each PR with its title, in merge order. `titles` are
+/// the PR titles, one per entry of `data`.
+pub fn format_umbrella_body(
+    data: &StackCommentData,
+    titles: &[String],
+    repo: &ForgeRepoRef,
+    default_branch: &str,
+) -> String {
+    let encoded = BASE64.encode(serde_json::to_string(data).expect("serialization cannot fail"));
+    let mut body = format!(
+        "{UMBRELLA_DATA_PREFIX}{encoded}{COMMENT_DATA_POSTFIX}\n<!-- This issue is managed by \
+         stakk. Manual edits will be overwritten. -->\n\nTracking issue for a stack of {} pull \
+         requests, to be merged into `{default_branch}` in this order:\n\n",
+        data.stack.len()
+    );
+    for (i, (entry, title)) in data.stack.iter().zip(titles).enumerate() {
+        let _ = writeln!(
+            body,
+            "{}. {} {title} (`{}`)",
+            i + 1,
+            entry.pr_ref(repo),
+            entry.bookmark_name
+        );
+    }
+    let _ = writeln!(
+        body,
+        "\n---\n<sub>Kept up to date by [stakk]({STAKK_REPO_URL}).</sub>"
+    );
+    body
+}
+
+/// Parse the metadata of a tracking issue body.
+///
+/// Returns `None` if the body was not written by [`format_umbrella_body`].
+pub fn parse_umbrella_body(body: &str) -> Option<StackCommentData> {
+    parse_metadata(body, UMBRELLA_DATA_PREFIX)
+}
+
+/// Format the comment stakk keeps on an issue referenced by commits of a
+/// stack, listing the PRs that carry those commits.
+pub fn format_issue_links(prs: &[u64]) -> String {
+    let mut body = format!(
+        "{ISSUE_LINKS_MARKER}\n<!-- This comment is managed by stakk. -->\n\nReferenced by these \
+         stacked pull requests:\n\n"
+    );
+    for pr in prs {
+        let _ = writeln!(body, "- #{pr}");
+    }
+    body
+}
+
+/// The PR numbers listed in a comment made by [`format_issue_links`], or
+/// `None` if `body` is not one.
+pub fn parse_issue_links(body: &str) -> Option<Vec<u64>> {
+    let mut lines = body.lines();
+    if lines.next()? != ISSUE_LINKS_MARKER {
+        return None;
+    }
+    Some(
+        lines
+            .filter_map(|line| line.strip_prefix("- #")?.parse().ok())
+            .collect(),
+    )
+}
+
+/// A block of a PR body that stakk owns, between two HTML comment markers
+/// that do not show when the body is rendered.
+///
+/// Everything outside the markers belongs to the author and survives every
+/// rewrite of the block: stakk only ever replaces, appends, or removes the
+/// block itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ManagedRegion {
+    pub start: &'static str,
+    pub end: &'static str,
+}
+
+impl ManagedRegion {
+    pub const fn new(start: &'static str, end: &'static str) -> Self {
+        Self { start, end }
+    }
+
+    /// Find the byte range of the block in `body`.
+    ///
+    /// Returns `Some((start, end))` where `start` is the byte offset of the
+    /// start marker and `end` is the byte offset just past the end marker
+    /// (including its trailing newline if present).
+    pub fn find(&self, body: &str) -> Option<(usize, usize)> {
+        let start = body.find(self.start)?;
+        let end_marker_start = body[start..].find(self.end)? + start;
+        let mut end = end_marker_start + self.end.len();
+        // Consume one trailing newline if present.
+        if body[end..].starts_with('\n') {
+            end += 1;
+        }
+        Some((start, end))
+    }
+
+    /// The content of the block, between its markers.
+    pub fn extract<'a>(&self, body: &'a str) -> Option<&'a str> {
+        let (start, _) = self.find(body)?;
+        let content_start = start + self.start.len();
+        let content_end = body[content_start..].find(self.end)? + content_start;
+        let content = &body[content_start..content_end];
+        Some(content.strip_prefix('\n').unwrap_or(content))
+    }
+
+    /// Replace the block with `content`, or append it if `body` has none
+    /// (after a blank line if the body is non-empty).
+    pub fn replace(&self, body: &str, content: &str) -> String {
+        let block = format!("{}\n{content}{}\n", self.start, self.end);
+        if let Some((start, end)) = self.find(body) {
+            let mut result = String::with_capacity(body.len() + block.len());
+            result.push_str(&body[..start]);
+            result.push_str(&block);
+            result.push_str(&body[end..]);
+            result
+        } else if body.is_empty() {
+            block
+        } else {
+            format!("{body}\n\n{block}")
+        }
+    }
+
+    /// Remove the block, and any trailing blank lines left behind.
+    pub fn strip(&self, body: &str) -> String {
+        if let Some((start, end)) = self.find(body) {
+            let mut result = String::with_capacity(body.len());
+            result.push_str(&body[..start]);
+            result.push_str(&body[end..]);
+            result.trim_end().to_string()
+        } else {
+            body.to_string()
+        }
+    }
+
+    /// Copy the block from `from` into `into`, for rewriting a PR body
+    /// without losing what stakk manages in it. `into` is returned as is if
+    /// `from` has no block.
+    pub fn reinsert(&self, from: &str, into: &str) -> String {
+        match self.extract(from) {
+            Some(content) => self.replace(into, content),
+            None => into.to_string(),
+        }
+    }
+}
+
+/// Remove every region stakk manages from a PR body, leaving the author's
+/// text.
+pub fn strip_managed_regions(body: &str) -> String {
+    MANAGED_REGIONS
+        .iter()
+        .fold(body.to_string(), |body, region| region.strip(&body))
+}
+
+/// Parse stack metadata from the fenced stack section of a PR body.
+///
+/// Returns `None` if the body has no fenced section or its metadata is
+/// invalid.
+pub fn parse_stack_in_body(body: &str) -> Option<StackCommentData> {
+    let section = STACK_REGION.extract(body)?;
+    let offset = section
+        .find(COMMENT_DATA_PREFIX)
+        .or_else(|| section.find(LEGACY_COMMENT_DATA_PREFIX))?;
+    parse_stack_comment(&section[offset..])
+}
+
+/// Find the byte range of a fenced stack section in a PR body. See
+/// [`ManagedRegion::find`].
+pub fn find_stack_in_body(body: &str) -> Option<(usize, usize)> {
+    STACK_REGION.find(body)
+}
+
+/// Replace or append a fenced stack section in a PR body.
+///
+/// If the body already contains a fenced section, it is replaced in-place.
+/// Otherwise, the fenced section is appended (with a blank line separator
+/// if the body is non-empty).
+pub fn splice_stack_into_body(existing_body: &str, stack_content: &str) -> String {
+    STACK_REGION.replace(
+        existing_body,
+        &format!("{BODY_WARNING}\n<!-- {STAKK_REPO_URL} -->\n\n---\n\n{stack_content}\n"),
+    )
+}
+
+/// Remove the fenced stack section from a PR body.
+///
+/// Strips any trailing blank lines left behind after removal.
+pub fn strip_stack_from_body(body: &str) -> String {
+    STACK_REGION.strip(body)
+}
+
+/// The dependency links of a PR in a stack: the PR it is stacked on and
+/// the PR stacked on it, as Markdown list items. Empty when it has neither.
+pub fn format_pr_links(depends_on: Option<&str>, followed_by: Option<&str>) -> String {
+    let mut links = String::new();
+    if let Some(parent) = depends_on {
+        links.push_str("- Depends on ");
+        links.push_str(parent);
+        links.push('\n');
+    }
+    if let Some(child) = followed_by {
+        links.push_str("- Followed by ");
+        links.push_str(child);
+        links.push('\n');
+    }
+    links
+}
+
+/// The PR reference on the "Followed by" line of a body's links section.
+pub fn parse_followed_by(body: &str) -> Option<&str> {
+    LINKS_REGION
+        .extract(body)?
+        .lines()
+        .find_map(|line| line.strip_prefix("- Followed by "))
+        .map(str::trim)
+}
+
+/// Replace, append, or remove the links section of a PR body: empty
+/// `links` remove it instead of writing an empty one.
+pub fn splice_links_into_body(existing_body: &str, links: &str) -> String {
+    if links.is_empty() {
+        LINKS_REGION.strip(existing_body)
+    } else {
+        LINKS_REGION.replace(existing_body, links)
+    }
+}
+
+/// The change ID recorded in a PR body, if any.
+pub fn parse_change_id(body: &str) -> Option<&str> {
+    CHANGE_ID_REGION
+        .extract(body)
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+}
+
+/// Record `change_id` in a PR body, replacing any change ID already there.
+pub fn splice_change_id_into_body(existing_body: &str, change_id: &str) -> String {
+    CHANGE_ID_REGION.replace(existing_body, &format!("{change_id}\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_data() -> StackCommentData {
+        StackCommentData {
+            version: 0,
+            stack: vec![
+                StackEntry {
+                    bookmark_name: "feat-a".to_string(),
+                    pr_url: "https://github.com/owner/repo/pull/1".to_string(),
+                    pr_number: 1,
+                    repo: None,
+                },
+                StackEntry {
+                    bookmark_name: "feat-b".to_string(),
+                    pr_url: "https://github.com/owner/repo/pull/2".to_string(),
+                    pr_number: 2,
+                    repo: None,
+                },
+            ],
+        }
+    }
+
+    fn sample_context(current_index: usize) -> StackCommentContext {
+        let entries = vec![
+            StackEntryContext {
+                bookmark_name: "feat-a".to_string(),
+                pr_url: "https://github.com/owner/repo/pull/1".to_string(),
+                pr_number: 1,
+                pr_ref: "#1".to_string(),
+                title: "feature a".to_string(),
+                base: "main".to_string(),
+                is_draft: false,
+                position: 1,
+                is_current: current_index == 0,
+            },
+            StackEntryContext {
+                bookmark_name: "feat-b".to_string(),
+                pr_url: "https://github.com/owner/repo/pull/2".to_string(),
+                pr_number: 2,
+                pr_ref: "#2".to_string(),
+                title: "feature b".to_string(),
+                base: "feat-a".to_string(),
+                is_draft: false,
+                position: 2,
+                is_current: current_index == 1,
+            },
+        ];
+        StackCommentContext {
+            stack_size: entries.len(),
+            current_bookmark: entries[current_index].bookmark_name.clone(),
+            stack: entries,
+            default_branch: "main".to_string(),
+            stakk_url: STAKK_REPO_URL.to_string(),
+        }
+    }
+
+    fn default_env() -> Environment<'static> {
+        build_comment_env(None, CommentStyle::Default).unwrap()
+    }
+
+    #[test]
+    fn format_and_parse_roundtrip() {
+        let data = sample_data();
+        let ctx = sample_context(0);
+        let env = default_env();
+        let tmpl = env.get_template("stack_comment").unwrap();
+        let body = format_stack_comment(&data, &ctx, &tmpl).unwrap();
+        let parsed = parse_stack_comment(&body).unwrap();
+        assert_eq!(parsed, data);
+    }
+
+    #[test]
+    fn umbrella_body_roundtrip() {
+        let data = sample_data();
+        let titles = vec!["Add a".to_string(), "Add b".to_string()];
+        let repo = ForgeRepoRef::new("owner", "repo");
+        let body = format_umbrella_body(&data, &titles, &repo, "main");
+        assert_eq!(parse_umbrella_body(&body), Some(data));
+        assert!(parse_stack_comment(&body).is_none());
+        assert!(body.contains("1. #1 Add a (`feat-a`)\n"));
+    }
+
+    #[test]
+    fn issue_links_roundtrip() {
+        let body = format_issue_links(&[3, 5]);
+        assert_eq!(parse_issue_links(&body), Some(vec![3, 5]));
+        assert_eq!(parse_issue_links("- #3\n"), None);
+    }
+
+    #[test]
+    fn format_highlights_current_pr() {
+        let data = sample_data();
+        let ctx = sample_context(1);
+        let env = default_env();
+        let tmpl = env.get_template("stack_comment").unwrap();
+        let body = format_stack_comment(&data, &ctx, &tmpl).unwrap();
+        // Second PR should be highlighted with pointing finger.
+        assert!(
+            body.contains("\u{1f448}"),
+            "expected pointing finger emoji in body: {body}"
+        );
+    }
+
+    #[test]
+    fn format_includes_default_branch() {
+        let data = sample_data();
+        let ctx = sample_context(0);
+        let env = default_env();
+        let tmpl = env.get_template("stack_comment").unwrap();
+        let body = format_stack_comment(&data, &ctx, &tmpl).unwrap();
+        assert!(body.contains("`main`"));
+    }
+
+    #[test]
+    fn find_stack_comment_matches() {
+        let data = sample_data();
+        let ctx = sample_context(0);
+        let env = default_env();
+        let tmpl = env.get_template("stack_comment").unwrap();
+        let comments = vec![
+            Comment {
+                id: 1,
+                node_id: None,
+                body: "Some unrelated comment".to_string(),
+            },
+            Comment {
+                id: 2,
+                node_id: None,
+                body: format_stack_comment(&data, &ctx, &tmpl).unwrap(),
+            },
+        ];
+        let found = find_stack_comment(&comments);
+        assert_eq!(found.unwrap().id, 2);
+    }
+
+    #[test]
+    fn legacy_jack_comments_are_recognized() {
+        let data = sample_data();
+        let encoded = BASE64.encode(serde_json::to_string(&data).unwrap());
+        let body = format!("{LEGACY_COMMENT_DATA_PREFIX}{encoded}{COMMENT_DATA_POSTFIX}\nold text");
+        let comments = vec![Comment {
+            id: 3,
+            node_id: None,
+            body: body.clone(),
+        }];
+        assert_eq!(find_stack_comment(&comments).unwrap().id, 3);
+        assert_eq!(parse_stack_comment(&body), Some(data));
+    }
+
+    #[test]
+    fn find_stack_comment_none_when_absent() {
+        let comments = vec![Comment {
+            id: 1,
+            node_id: None,
+            body: "Nothing here".to_string(),
+        }];
+        assert!(find_stack_comment(&comments).is_none());
+    }
+
+    #[test]
+    fn parse_with_different_body_text() {
+        // Parse metadata even when the body text around it differs.
+        let data = sample_data();
+        let encoded = BASE64.encode(serde_json::to_string(&data).unwrap());
+        let body = format!(
+            "{COMMENT_DATA_PREFIX}{encoded}{COMMENT_DATA_POSTFIX}\nSome different body \
+             text\n\n---\n*Some other footer*"
+        );
+        let parsed = parse_stack_comment(&body).unwrap();
+        assert_eq!(parsed, data);
+    }
+
+    #[test]
+    fn parse_invalid_base64_returns_none() {
+        let body = format!("{COMMENT_DATA_PREFIX}not-valid-base64!!!{COMMENT_DATA_POSTFIX}\nstuff");
+        assert!(parse_stack_comment(&body).is_none());
+    }
+
+    #[test]
+    fn parse_no_metadata_returns_none() {
+        assert!(parse_stack_comment("just a regular comment").is_none());
+    }
+
+    // -- Body fence tests --
+
+    #[test]
+    fn find_stack_in_body_present() {
+        let body =
+            format!("Some PR description\n\n{BODY_FENCE_START}\nstack content\n{BODY_FENCE_END}\n");
+        let (start, end) = find_stack_in_body(&body).unwrap();
+        assert_eq!(
+            &body[start..end],
+            format!("{BODY_FENCE_START}\nstack content\n{BODY_FENCE_END}\n")
+        );
+    }
+
+    #[test]
+    fn find_stack_in_body_absent() {
+        assert!(find_stack_in_body("just a normal body").is_none());
+    }
+
+    #[test]
+    fn splice_into_empty_body() {
+        let result = splice_stack_into_body("", "stack content");
+        assert!(result.contains(BODY_FENCE_START));
+        assert!(result.contains("stack content"));
+        assert!(result.contains(BODY_FENCE_END));
+    }
+
+    #[test]
+    fn splice_appends_to_nonempty_body() {
+        let result = splice_stack_into_body("Existing body", "stack content");
+        assert!(result.starts_with("Existing body\n\n"));
+        assert!(result.contains(BODY_FENCE_START));
+        assert!(result.contains("stack content"));
+    }
+
+    #[test]
+    fn splice_replaces_existing_fence() {
+        let body = format!("Before\n\n{BODY_FENCE_START}\nold content\n{BODY_FENCE_END}\nAfter");
+        let result = splice_stack_into_body(&body, "new content");
+        assert!(result.contains("new content"));
+        assert!(!result.contains("old content"));
+        assert!(result.starts_with("Before\n\n"));
+        assert!(result.contains("After"));
+    }
+
+    #[test]
+    fn splice_roundtrip() {
+        let body = "My PR description\n\nSome details here.";
+        let spliced = splice_stack_into_body(body, "first version");
+        let spliced_again = splice_stack_into_body(&spliced, "second version");
+        assert!(spliced_again.contains("second version"));
+        assert!(!spliced_again.contains("first version"));
+        // Original body text is preserved.
+        assert!(spliced_again.contains("My PR description"));
+    }
+
+    #[test]
+    fn parse_stack_in_body_reads_fenced_metadata() {
+        let data = sample_data();
+        let env = default_env();
+        let tmpl = env.get_template("stack_comment").unwrap();
+        let content = format_stack_comment(&data, &sample_context(0), &tmpl).unwrap();
+        let body = splice_stack_into_body("My PR description", &content);
+        assert_eq!(parse_stack_in_body(&body), Some(data));
+        assert_eq!(parse_stack_in_body("My PR description"), None);
+    }
+
+    #[test]
+    fn strip_removes_fence() {
+        let body = format!("Before\n\n{BODY_FENCE_START}\nstack content\n{BODY_FENCE_END}\n");
+        let result = strip_stack_from_body(&body);
+        assert_eq!(result, "Before");
+    }
+
+    #[test]
+    fn strip_no_fence_is_noop() {
+        let body = "Just a body";
+        assert_eq!(strip_stack_from_body(body), body);
+    }
+
+    #[test]
+    fn format_single_entry_numbered_list() {
+        let data = StackCommentData {
+            version: 0,
+            stack: vec![StackEntry {
+                bookmark_name: "solo".to_string(),
+                pr_url: "https://github.com/o/r/pull/1".to_string(),
+                pr_number: 1,
+                repo: None,
+            }],
+        };
+        let ctx = StackCommentContext {
+            stack: vec![StackEntryContext {
+                bookmark_name: "solo".to_string(),
+                pr_url: "https://github.com/o/r/pull/1".to_string(),
+                pr_number: 1,
+                pr_ref: "#1".to_string(),
+                title: "solo feature".to_string(),
+                base: "main".to_string(),
+                is_draft: false,
+                position: 1,
+                is_current: true,
+            }],
+            stack_size: 1,
+            default_branch: "main".to_string(),
+            current_bookmark: "solo".to_string(),
+            stakk_url: STAKK_REPO_URL.to_string(),
+        };
+        let env = default_env();
+        let tmpl = env.get_template("stack_comment").unwrap();
+        let body = format_stack_comment(&data, &ctx, &tmpl).unwrap();
+        assert!(
+            body.contains("1. https://github.com/o/r/pull/1"),
+            "expected numbered list entry: {body}"
+        );
+    }
+
+    #[test]
+    fn format_includes_footer() {
+        let data = sample_data();
+        let ctx = sample_context(0);
+        let env = default_env();
+        let tmpl = env.get_template("stack_comment").unwrap();
+        let body = format_stack_comment(&data, &ctx, &tmpl).unwrap();
+        assert!(body.contains("stakk"));
+    }
+
+    #[test]
+    fn format_header_mentions_merges_into() {
+        let data = sample_data();
+        let ctx = sample_context(0);
+        let env = default_env();
+        let tmpl = env.get_template("stack_comment").unwrap();
+        let body = format_stack_comment(&data, &ctx, &tmpl).unwrap();
+        assert!(
+            body.contains("merges into `main`"),
+            "expected merge target in header: {body}"
+        );
+    }
+
+    #[test]
+    fn custom_template_renders() {
+        let data = sample_data();
+        let ctx = sample_context(0);
+        let custom = "Custom: {{ stack_size }} PRs for {{ current_bookmark }}";
+        let env = build_comment_env(Some(custom), CommentStyle::Default).unwrap();
+        let tmpl = env.get_template("stack_comment").unwrap();
+        let body = format_stack_comment(&data, &ctx, &tmpl).unwrap();
+        assert!(body.contains("Custom: 2 PRs for feat-a"));
+    }
+
+    fn render_with_style(style: CommentStyle) -> String {
+        let env = build_comment_env(None, style).unwrap();
+        let tmpl = env.get_template("stack_comment").unwrap();
+        let body = format_stack_comment(&sample_data(), &sample_context(1), &tmpl).unwrap();
+        body.split_once('\n').unwrap().1.to_string()
+    }
+
+    #[test]
+    fn default_style_renders_stakk_wording() {
+        assert_eq!(
+            render_with_style(CommentStyle::Default),
+            "This PR is part of a stack that merges into `main`:\n\n1. \
+             https://github.com/owner/repo/pull/1\n2. https://github.com/owner/repo/pull/2 \
+             \u{1f448}\n\n<sub>Created with [stakk](https://github.com/glennib/stakk)</sub>"
+        );
+    }
+
+    #[test]
+    fn footer_can_be_replaced_or_removed() {
+        let render = |footer: &str| {
+            let mut env = build_comment_env(None, CommentStyle::Default).unwrap();
+            set_comment_footer(&mut env, footer);
+            let tmpl = env.get_template("stack_comment").unwrap();
+            format_stack_comment(&sample_data(), &sample_context(1), &tmpl).unwrap()
+        };
+        assert!(
+            render("_Stacked by the platform team_")
+                .ends_with("\n\n_Stacked by the platform team_")
+        );
+        assert!(render("").ends_with("pull/2 \u{1f448}"));
+    }
+
+    #[test]
+    fn minimal_style_has_no_footer() {
+        assert_eq!(
+            render_with_style(CommentStyle::Minimal),
+            "Stack into `main`:\n\n1. https://github.com/owner/repo/pull/1\n2. \
+             https://github.com/owner/repo/pull/2 \u{2190}"
+        );
+    }
+
+    #[test]
+    fn verbose_and_corporate_styles_list_titles() {
+        let verbose = render_with_style(CommentStyle::Verbose);
+        assert!(verbose.contains("pull/2: feature b \u{1f448} (this PR)\n\nEach PR"));
+
+        let corporate = render_with_style(CommentStyle::Corporate);
+        assert!(corporate.contains("pull/1: feature a\n"));
+        assert!(corporate.contains("pull/2: feature b (this pull request)\n"));
+        assert!(corporate.contains("<sub>Managed with [stakk]("));
+        assert!(!corporate.chars().any(|c| u32::from(c) > 0x2000));
+    }
+
+    #[test]
+    fn custom_template_sees_style() {
+        let env = build_comment_env(Some("{{ style.intro }}"), CommentStyle::Minimal).unwrap();
+        let tmpl = env.get_template("stack_comment").unwrap();
+        let body = format_stack_comment(&sample_data(), &sample_context(0), &tmpl).unwrap();
+        assert!(body.ends_with("\nStack into"));
+    }
+
+    #[test]
+    fn invalid_template_returns_error() {
+        let result = build_comment_env(Some("{{ unclosed"), CommentStyle::Default);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn format_renders_numbered_entries() {
+        let data = sample_data();
+        let ctx = sample_context(0);
+        let env = default_env();
+        let tmpl = env.get_template("stack_comment").unwrap();
+        let body = format_stack_comment(&data, &ctx, &tmpl).unwrap();
+        // Should contain numbered list entries with PR URLs
+        assert!(
+            body.contains("1. https://github.com/owner/repo/pull/1"),
+            "expected entry 1: {body}"
+        );
+        assert!(
+            body.contains("2. https://github.com/owner/repo/pull/2"),
+            "expected entry 2: {body}"
+        );
+    }
+
+    #[test]
+    fn with_comment_preamble_inserts_warning() {
+        let data = sample_data();
+        let ctx = sample_context(0);
+        let env = default_env();
+        let tmpl = env.get_template("stack_comment").unwrap();
+        let formatted = format_stack_comment(&data, &ctx, &tmpl).unwrap();
+        let body = with_comment_preamble(&formatted);
+        assert!(
+            body.contains(COMMENT_WARNING),
+            "expected warning line in comment: {body}"
+        );
+        assert!(
+            body.contains(&format!("<!-- {STAKK_REPO_URL} -->")),
+            "expected repo URL line in comment: {body}"
+        );
+        // Metadata is still on the first line.
+        assert!(body.starts_with(COMMENT_DATA_PREFIX));
+    }
+
+    #[test]
+    fn format_stack_comment_has_no_warning() {
+        let data = sample_data();
+        let ctx = sample_context(0);
+        let env = default_env();
+        let tmpl = env.get_template("stack_comment").unwrap();
+        let body = format_stack_comment(&data, &ctx, &tmpl).unwrap();
+        assert!(
+            !body.contains(COMMENT_WARNING),
+            "format_stack_comment should not include comment warning: {body}"
+        );
+        assert!(
+            !body.contains(BODY_WARNING),
+            "format_stack_comment should not include body warning: {body}"
+        );
+    }
+
+    #[test]
+    fn splice_includes_warning_preamble() {
+        let result = splice_stack_into_body("", "stack content");
+        assert!(
+            result.contains(BODY_WARNING),
+            "expected warning line in fenced block: {result}"
+        );
+        assert!(
+            result.contains(&format!("<!-- {STAKK_REPO_URL} -->")),
+            "expected repo URL line in fenced block: {result}"
+        );
+    }
+
+    #[test]
+    fn managed_region_extract_replace_reinsert() {
+        let region = ManagedRegion::new("<!-- A -->", "<!-- /A -->");
+        let body = region.replace("Intro", "managed\n");
+        assert_eq!(body, "Intro\n\n<!-- A -->\nmanaged\n<!-- /A -->\n");
+        assert_eq!(region.extract(&body), Some("managed\n"));
+
+        // Text after the block survives a replacement.
+        let body = format!("{body}Outro");
+        let body = region.replace(&body, "changed\n");
+        assert_eq!(body, "Intro\n\n<!-- A -->\nchanged\n<!-- /A -->\nOutro");
+
+        assert_eq!(
+            region.reinsert(&body, "New text"),
+            "New text\n\n<!-- A -->\nchanged\n<!-- /A -->\n"
+        );
+        assert_eq!(region.reinsert("no block", "New text"), "New text");
+        assert_eq!(region.strip(&body), "Intro\n\nOutro");
+        assert_eq!(region.extract("Intro"), None);
+    }
+
+    #[test]
+    fn strip_managed_regions_leaves_author_text() {
+        let body = splice_stack_into_body("Author text", "stack");
+        let body = splice_links_into_body(&body, "- Depends on #1\n");
+        assert_eq!(strip_managed_regions(&body), "Author text");
+    }
+
+    #[test]
+    fn links_section_spliced_replaced_and_removed() {
+        let links = format_pr_links(Some("#1"), Some("#3"));
+        assert_eq!(links, "- Depends on #1\n- Followed by #3\n");
+
+        let body = splice_links_into_body("User text", &links);
+        assert_eq!(
+            body,
+            "User text\n\n<!-- STAKK_LINKS_START -->\n- Depends on #1\n- Followed by #3\n<!-- \
+             STAKK_LINKS_END -->\n"
+        );
+        assert_eq!(parse_followed_by(&body), Some("#3"));
+
+        let body = splice_links_into_body(&body, &format_pr_links(Some("#2"), None));
+        assert!(body.contains("- Depends on #2\n<!-- STAKK_LINKS_END -->"));
+        assert_eq!(parse_followed_by(&body), None);
+
+        // A stack section next to the links is left alone.
+        let with_stack = splice_stack_into_body(&body, "stack");
+        let stripped = LINKS_REGION.strip(&with_stack);
+        assert!(!stripped.contains("STAKK_LINKS"));
+        assert!(find_stack_in_body(&stripped).is_some());
+        assert_eq!(strip_stack_from_body(&stripped), "User text");
+    }
+
+    #[test]
+    fn change_id_recorded_and_replaced() {
+        let body = splice_change_id_into_body("User text", "qpvuntsm");
+        assert_eq!(body, "User text\n\n<!-- STAKK_CHANGE_ID\nqpvuntsm\n-->\n");
+        assert_eq!(parse_change_id(&body), Some("qpvuntsm"));
+
+        // The stack section's markers end in `-->` too.
+        let body = splice_stack_into_body(&body, "stack");
+        let body = splice_change_id_into_body(&body, "zzzzzzzz");
+        assert_eq!(parse_change_id(&body), Some("zzzzzzzz"));
+        assert!(find_stack_in_body(&body).is_some());
+        assert_eq!(strip_managed_regions(&body), "User text");
+        assert_eq!(parse_change_id("User text"), None);
+    }
+
+    #[test]
+    fn pr_ref_qualifies_other_repositories() {
+        let here = ForgeRepoRef::new("owner", "service");
+        let mut entry = sample_data().stack[0].clone();
+        assert_eq!(entry.pr_ref(&here), "#1");
+
+        entry.repo = Some(here.clone());
+        assert_eq!(entry.pr_ref(&here), "#1");
+
+        entry.repo = Some(ForgeRepoRef::new("owner", "proto"));
+        assert_eq!(entry.pr_ref(&here), "owner/proto#1");
+    }
+
+    #[test]
+    fn metadata_without_repo_still_parses() {
+        let json = r#"{"version":0,"stack":[{"bookmark_name":"a","pr_url":"u","pr_number":3}]}"#;
+        let data: StackCommentData = serde_json::from_str(json).unwrap();
+        assert_eq!(data.stack[0].repo, None);
+    }
+}
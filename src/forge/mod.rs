@@ -3,10 +3,18 @@
 //! All forge interaction (GitHub, etc.) goes through the `Forge` trait. The
 //! core submission logic never imports forge-specific types directly.
 
-pub mod comment;
 pub mod github;
+pub mod markup;
+pub mod pool;
+pub mod recording;
+pub mod urls;
 
+use std::fmt;
+
+use clap::ValueEnum;
 use miette::Diagnostic;
+use serde::Deserialize;
+use serde::Serialize;
 use thiserror::Error;
 
 /// Errors from forge operations.
@@ -42,8 +50,46 @@ pub enum ForgeError {
     MalformedResponse { field: &'static str },
 }
 
+/// A repository on a forge, identified by owner and name.
+///
+/// Stack entries carry the repository of their PR so a stack can span
+/// repositories (e.g. a proto repo and the service built on it) and comments
+/// can cross-link PRs unambiguously.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ForgeRepoRef {
+    pub owner: String,
+    pub repo: String,
+}
+
+impl ForgeRepoRef {
+    pub fn new(owner: impl Into<String>, repo: impl Into<String>) -> Self {
+        Self {
+            owner: owner.into(),
+            repo: repo.into(),
+        }
+    }
+
+    /// Reference to PR `number` in this repository as written from a PR in
+    /// `viewer`: `#12` within the same repository, `owner/repo#12` across
+    /// repositories.
+    pub fn pr_ref(&self, number: u64, viewer: &Self) -> String {
+        if self == viewer {
+            format!("#{number}")
+        } else {
+            format!("{self}#{number}")
+        }
+    }
+}
+
+impl fmt::Display for ForgeRepoRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.owner, self.repo)
+    }
+}
+
 /// State of a pull request.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum PrState {
     Open,
     Closed,
@@ -51,30 +97,62 @@ pub enum PrState {
 }
 
 /// A pull request, forge-agnostic.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PullRequest {
     pub number: u64,
     pub html_url: String,
     pub title: String,
-    #[expect(
-        dead_code,
-        reason = "part of PR data model, not yet consumed by submission logic"
-    )]
     pub head_ref: String,
     pub base_ref: String,
-    #[expect(
-        dead_code,
-        reason = "part of PR data model, not yet consumed by submission logic"
-    )]
     pub state: PrState,
     /// The PR body/description text.
     pub body: Option<String>,
+    /// Whether the PR is a draft.
+    #[serde(default)]
+    pub draft: bool,
+}
+
+/// An open pull request with its reviews, oldest first, and the CI checks
+/// on its head commit.
+#[derive(Debug, Clone)]
+pub struct PrDetails {
+    pub pr: PullRequest,
+    pub reviews: Vec<Review>,
+    pub checks: Vec<CheckRun>,
+}
+
+/// A merged pull request, with the commit its head branch pointed at when
+/// it merged. A squash or rebase merge puts different commits on the base
+/// branch, so the head commit is what ties the PR to local changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergedPullRequest {
+    pub pr: PullRequest,
+    pub head_sha: String,
+}
+
+/// An issue, forge-agnostic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub number: u64,
+    pub html_url: String,
+    pub body: Option<String>,
 }
 
 /// A comment on a pull request.
 #[derive(Debug, Clone)]
 pub struct Comment {
     pub id: u64,
+    /// Global node ID, for forges whose batch API addresses objects by node
+    /// rather than by numeric ID (GitHub GraphQL).
+    pub node_id: Option<String>,
+    pub body: String,
+}
+
+/// One comment edit in a batched update.
+#[derive(Debug, Clone)]
+pub struct CommentEdit {
+    pub comment_id: u64,
+    pub node_id: Option<String>,
     pub body: String,
 }
 
@@ -86,6 +164,130 @@ pub struct CreatePrParams {
     pub base: String,
     pub body: Option<String>,
     pub draft: bool,
+    /// Title of an open milestone to put the PR in.
+    pub milestone: Option<String>,
+    /// Number of a GitHub Project (v2) owned by the repository's owner to
+    /// add the PR to.
+    pub project: Option<u64>,
+}
+
+/// How a pull request is merged once auto-merge fires.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MergeMethod {
+    /// Create a merge commit.
+    #[default]
+    Merge,
+    /// Squash all commits into one.
+    Squash,
+    /// Rebase the commits onto the base branch.
+    Rebase,
+}
+
+impl fmt::Display for MergeMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pv = self
+            .to_possible_value()
+            .expect("all variants have possible values");
+        f.write_str(pv.get_name())
+    }
+}
+
+/// Outcome of a CI check run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CheckState {
+    /// Queued or still running.
+    Pending,
+    Passed,
+    Failed,
+}
+
+/// A CI check run on the head commit of a branch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckRun {
+    pub name: String,
+    pub state: CheckState,
+}
+
+/// A reviewer's verdict on a pull request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReviewState {
+    Approved,
+    ChangesRequested,
+    Commented,
+    Dismissed,
+    /// Started but not yet submitted.
+    Pending,
+}
+
+/// A review submitted on a pull request.
+#[derive(Debug, Clone)]
+pub struct Review {
+    pub author: String,
+    pub state: ReviewState,
+}
+
+/// Each reviewer's latest verdict, in order of their first review. A
+/// comment only counts for reviewers who have given no verdict, and pending
+/// (unsubmitted) reviews are ignored.
+pub fn latest_verdicts(reviews: &[Review]) -> Vec<(&str, ReviewState)> {
+    let mut latest: Vec<(&str, ReviewState)> = Vec::new();
+    for review in reviews {
+        if review.state == ReviewState::Pending {
+            continue;
+        }
+        match latest
+            .iter_mut()
+            .find(|(author, _)| *author == review.author)
+        {
+            Some((_, state))
+                if review.state != ReviewState::Commented || *state == ReviewState::Commented =>
+            {
+                *state = review.state;
+            }
+            Some(_) => {}
+            None => latest.push((&review.author, review.state)),
+        }
+    }
+    latest
+}
+
+/// How many reviewers currently approve a PR and how many request changes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReviewSummary {
+    pub approved: usize,
+    pub changes_requested: usize,
+}
+
+impl ReviewSummary {
+    /// Count the reviewers' latest verdicts (see [`latest_verdicts`]).
+    pub fn from_reviews(reviews: &[Review]) -> Self {
+        let verdicts = latest_verdicts(reviews);
+        let count = |wanted| verdicts.iter().filter(|(_, s)| *s == wanted).count();
+        Self {
+            approved: count(ReviewState::Approved),
+            changes_requested: count(ReviewState::ChangesRequested),
+        }
+    }
+
+    /// Whether nobody has approved or requested changes.
+    pub fn is_empty(&self) -> bool {
+        self.approved == 0 && self.changes_requested == 0
+    }
+}
+
+/// `✓2 ✗1`, leaving out zero counts.
+impl fmt::Display for ReviewSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if self.approved > 0 {
+            parts.push(format!("\u{2713}{}", self.approved));
+        }
+        if self.changes_requested > 0 {
+            parts.push(format!("\u{2717}{}", self.changes_requested));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
 }
 
 /// Trait for interacting with a code forge (GitHub, Forgejo, etc.).
@@ -93,17 +295,90 @@ pub struct CreatePrParams {
 /// All methods return forge-agnostic types. Implementations handle the
 /// translation to/from forge-specific APIs.
 pub trait Forge: Send + Sync {
+    /// The repository this forge operates on.
+    fn repo_ref(&self) -> ForgeRepoRef;
+
+    /// The repository head branches are pushed to: a fork of `repo_ref`, or
+    /// `repo_ref` itself.
+    fn head_repo_ref(&self) -> ForgeRepoRef {
+        self.repo_ref()
+    }
+
+    /// Web links into `repo_ref`.
+    fn urls(&self) -> urls::UrlBuilder {
+        urls::UrlBuilder::github(self.repo_ref())
+    }
+
     /// Get the username of the authenticated user.
     fn get_authenticated_user(
         &self,
     ) -> impl std::future::Future<Output = Result<String, ForgeError>> + Send;
 
+    /// Get the verified email addresses of the authenticated user.
+    fn get_user_emails(
+        &self,
+    ) -> impl std::future::Future<Output = Result<Vec<String>, ForgeError>> + Send;
+
     /// Find an open PR with the given head branch.
     fn find_pr_for_branch(
         &self,
         head: &str,
     ) -> impl std::future::Future<Output = Result<Option<PullRequest>, ForgeError>> + Send;
 
+    /// Find the open PRs of several head branches, with their reviews and
+    /// checks, in the order of `heads`.
+    ///
+    /// The default looks each branch up on its own. Forges with a query API
+    /// fetch them all in a few requests.
+    fn find_prs_for_branches(
+        &self,
+        heads: &[String],
+    ) -> impl std::future::Future<Output = Result<Vec<Option<PrDetails>>, ForgeError>> + Send {
+        futures::future::try_join_all(heads.iter().map(|head| async move {
+            let Some(pr) = self.find_pr_for_branch(head).await? else {
+                return Ok(None);
+            };
+            let (reviews, checks) =
+                futures::try_join!(self.list_reviews(pr.number), self.list_check_runs(head))?;
+            Ok(Some(PrDetails {
+                pr,
+                reviews,
+                checks,
+            }))
+        }))
+    }
+
+    /// Find the most recently merged PR with the given head branch.
+    fn find_merged_pr_for_branch(
+        &self,
+        head: &str,
+    ) -> impl std::future::Future<Output = Result<Option<MergedPullRequest>, ForgeError>> + Send;
+
+    /// List the open PRs that request a review from the authenticated user.
+    fn list_review_requests(
+        &self,
+    ) -> impl std::future::Future<Output = Result<Vec<PrDetails>, ForgeError>> + Send;
+
+    /// Find an open PR whose body records `change_id` (see
+    /// [`markup::CHANGE_ID_REGION`]), whatever its head branch is called.
+    fn find_pr_for_change(
+        &self,
+        change_id: &str,
+    ) -> impl std::future::Future<Output = Result<Option<PullRequest>, ForgeError>> + Send;
+
+    /// Fetch PR `number`, whatever its state.
+    fn get_pr(
+        &self,
+        number: u64,
+    ) -> impl std::future::Future<Output = Result<PullRequest, ForgeError>> + Send;
+
+    /// Rename a head branch. Open PRs from it follow the new name.
+    fn rename_branch(
+        &self,
+        from: &str,
+        to: &str,
+    ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send;
+
     /// Create a new pull request.
     fn create_pr(
         &self,
@@ -117,13 +392,48 @@ pub trait Forge: Send + Sync {
         new_base: &str,
     ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send;
 
-    /// Update the title of an existing PR.
-    fn update_pr_title(
+    /// Update the title and/or body of an existing PR in a single request.
+    /// Fields passed as `None` are left unchanged.
+    fn update_pr(
         &self,
         pr_number: u64,
+        title: Option<&str>,
+        body: Option<&str>,
+    ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send;
+
+    /// Close a PR without merging it.
+    fn close_pr(
+        &self,
+        pr_number: u64,
+    ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send;
+
+    /// List the open issues (not PRs) the authenticated user opened.
+    fn list_own_issues(
+        &self,
+    ) -> impl std::future::Future<Output = Result<Vec<Issue>, ForgeError>> + Send;
+
+    /// Open an issue.
+    fn create_issue(
+        &self,
         title: &str,
+        body: &str,
+    ) -> impl std::future::Future<Output = Result<Issue, ForgeError>> + Send;
+
+    /// Replace the body of an issue.
+    fn update_issue_body(
+        &self,
+        number: u64,
+        body: &str,
     ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send;
 
+    /// Link PR `pr_number` to issue `issue_number`, so the issue lists the
+    /// PR. Returns `false` if it was linked already.
+    fn link_pr_to_issue(
+        &self,
+        issue_number: u64,
+        pr_number: u64,
+    ) -> impl std::future::Future<Output = Result<bool, ForgeError>> + Send;
+
     /// List all comments on a PR.
     fn list_comments(
         &self,
@@ -156,4 +466,110 @@ pub trait Forge: Send + Sync {
         &self,
         comment_id: u64,
     ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send;
+
+    /// Whether a branch named `branch` exists on the forge.
+    fn branch_exists(
+        &self,
+        branch: &str,
+    ) -> impl std::future::Future<Output = Result<bool, ForgeError>> + Send;
+
+    /// Whether a head branch named `branch` exists. Differs from
+    /// `branch_exists` when head branches are pushed to a fork.
+    fn head_branch_exists(
+        &self,
+        branch: &str,
+    ) -> impl std::future::Future<Output = Result<bool, ForgeError>> + Send {
+        self.branch_exists(branch)
+    }
+
+    /// List the check runs on the head commit of `branch`.
+    fn list_check_runs(
+        &self,
+        branch: &str,
+    ) -> impl std::future::Future<Output = Result<Vec<CheckRun>, ForgeError>> + Send;
+
+    /// List the reviews on a PR, oldest first.
+    fn list_reviews(
+        &self,
+        pr_number: u64,
+    ) -> impl std::future::Future<Output = Result<Vec<Review>, ForgeError>> + Send;
+
+    /// Count the approvals and change requests on a PR.
+    fn get_reviews(
+        &self,
+        pr_number: u64,
+    ) -> impl std::future::Future<Output = Result<ReviewSummary, ForgeError>> + Send {
+        async move {
+            Ok(ReviewSummary::from_reviews(
+                &self.list_reviews(pr_number).await?,
+            ))
+        }
+    }
+
+    /// Ask `reviewers` to review a PR. An `org/team` entry requests a team.
+    fn request_reviewers(
+        &self,
+        pr_number: u64,
+        reviewers: &[String],
+    ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send;
+
+    /// Add `labels` to a PR, keeping the labels it already has.
+    fn add_labels(
+        &self,
+        pr_number: u64,
+        labels: &[String],
+    ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send;
+
+    /// Assign `assignees` to a PR, keeping the ones it has.
+    fn add_assignees(
+        &self,
+        pr_number: u64,
+        assignees: &[String],
+    ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send;
+
+    /// Mark a draft PR ready for review.
+    fn mark_ready_for_review(
+        &self,
+        pr_number: u64,
+    ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send;
+
+    /// Turn a PR back into a draft.
+    fn convert_to_draft(
+        &self,
+        pr_number: u64,
+    ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send;
+
+    /// Turn on auto-merge for a PR so the forge merges it with `method` once
+    /// its required reviews and checks pass.
+    fn enable_auto_merge(
+        &self,
+        pr_number: u64,
+        method: MergeMethod,
+    ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send;
+
+    /// Whether `update_comments_batch` collapses several edits into fewer
+    /// requests. Forges without a batch API return `false` and callers fall
+    /// back to one `update_comment` call per edit.
+    fn supports_batch_mutations(&self) -> bool {
+        false
+    }
+
+    /// Apply several comment edits in as few requests as possible.
+    ///
+    /// Every edit must carry a `node_id`. Only called when
+    /// `supports_batch_mutations` returns `true`; forges without a batch API
+    /// keep this default, which fails.
+    fn update_comments_batch(
+        &self,
+        edits: &[CommentEdit],
+    ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send {
+        let count = edits.len();
+        async move {
+            let message = format!("this forge cannot batch comment edits ({count} requested)");
+            Err(ForgeError::Api {
+                source: message.clone().into(),
+                message,
+            })
+        }
+    }
 }
@@ -0,0 +1,110 @@
+//! HTTP clients shared by the forges of one process.
+//!
+//! Building an octocrab client builds a connection pool with it, and every
+//! client sends as many requests at once as it is given. A [`ClientPool`]
+//! hands out one client per API host and token instead (clones of an
+//! octocrab client share its connections), and one budget of requests in
+//! flight that every forge taken from the pool draws on, so that forges for
+//! several repositories reuse connections and do not add up to more
+//! concurrent requests than GitHub's secondary rate limits allow.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::LazyLock;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+use octocrab::Octocrab;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+
+use super::ForgeError;
+
+/// Base URI of the github.com REST and GraphQL APIs.
+pub const GITHUB_API: &str = "https://api.github.com";
+
+/// Requests the process-wide pool lets be in flight at once.
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 8;
+
+static SHARED: LazyLock<ClientPool> = LazyLock::new(|| ClientPool::new(DEFAULT_MAX_IN_FLIGHT));
+
+/// API clients keyed by host and token, and the request budget they share.
+pub struct ClientPool {
+    clients: Mutex<HashMap<(String, String), Octocrab>>,
+    budget: Arc<Semaphore>,
+}
+
+impl ClientPool {
+    /// An empty pool that lets at most `max_in_flight` requests run at once.
+    pub fn new(max_in_flight: usize) -> Self {
+        Self {
+            clients: Mutex::new(HashMap::new()),
+            budget: Arc::new(Semaphore::new(max_in_flight.max(1))),
+        }
+    }
+
+    /// The pool every forge of this process uses unless given another.
+    pub fn shared() -> &'static Self {
+        &SHARED
+    }
+
+    /// The client for `host` (an API base URI such as [`GITHUB_API`])
+    /// authenticated with `token`, built on first use.
+    pub(crate) fn client(&self, host: &str, token: &str) -> Result<Octocrab, ForgeError> {
+        let mut clients = self.clients.lock().unwrap_or_else(PoisonError::into_inner);
+        let key = (host.to_string(), token.to_string());
+        if let Some(client) = clients.get(&key) {
+            return Ok(client.clone());
+        }
+        let client = Octocrab::builder()
+            .base_uri(host)
+            .and_then(|builder| builder.personal_token(token.to_string()).build())
+            .map_err(|e| {
+                let message = format!("failed to create GitHub client for {host}: {e}");
+                ForgeError::Api {
+                    message,
+                    source: Box::new(e),
+                }
+            })?;
+        clients.insert(key, client.clone());
+        Ok(client)
+    }
+
+    /// The request budget of this pool, for a forge to hold on to.
+    pub(crate) fn budget(&self) -> Arc<Semaphore> {
+        Arc::clone(&self.budget)
+    }
+}
+
+/// Wait for room in `budget` for one more request.
+pub(crate) async fn acquire(budget: &Arc<Semaphore>) -> OwnedSemaphorePermit {
+    Arc::clone(budget)
+        .acquire_owned()
+        .await
+        .expect("the request budget is never closed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn clients_are_shared_per_host_and_token() {
+        let pool = ClientPool::new(2);
+        pool.client(GITHUB_API, "t1").unwrap();
+        pool.client(GITHUB_API, "t1").unwrap();
+        pool.client(GITHUB_API, "t2").unwrap();
+        pool.client("https://ghe.example.com/api/v3", "t1").unwrap();
+        assert_eq!(pool.clients.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn forges_draw_on_one_budget() {
+        let pool = ClientPool::new(1);
+        let (first, second) = (pool.budget(), pool.budget());
+        let permit = acquire(&first).await;
+        assert!(second.clone().try_acquire_owned().is_err());
+        drop(permit);
+        assert!(second.try_acquire_owned().is_ok());
+    }
+}
@@ -0,0 +1,655 @@
+//! A forge that answers reads from another forge and records writes instead
+//! of making them, for `stakk submit --dry-run`.
+//!
+//! Running a submission against it exercises everything a real run decides
+//! (stack comments, PR bodies, base updates) and leaves the list of
+//! [`Mutation`]s it would have made, with the text of every comment and body
+//! it would have written.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+use super::CheckRun;
+use super::Comment;
+use super::CommentEdit;
+use super::CreatePrParams;
+use super::Forge;
+use super::ForgeError;
+use super::ForgeRepoRef;
+use super::Issue;
+use super::MergeMethod;
+use super::MergedPullRequest;
+use super::PrDetails;
+use super::PrState;
+use super::PullRequest;
+use super::Review;
+use super::urls::UrlBuilder;
+
+/// A write the recording forge was asked to make.
+#[derive(Debug, Clone)]
+pub enum Mutation {
+    RenameBranch {
+        from: String,
+        to: String,
+    },
+    /// `number` is made up: the forge assigns the real one.
+    CreatePr {
+        number: u64,
+        params: CreatePrParams,
+    },
+    UpdateBase {
+        pr_number: u64,
+        base: String,
+    },
+    UpdateTitle {
+        pr_number: u64,
+        title: String,
+    },
+    UpdatePrBody {
+        pr_number: u64,
+        old: String,
+        new: String,
+    },
+    ClosePr {
+        pr_number: u64,
+    },
+    CreateIssue {
+        title: String,
+        body: String,
+    },
+    UpdateIssueBody {
+        number: u64,
+        body: String,
+    },
+    LinkPrToIssue {
+        issue_number: u64,
+        pr_number: u64,
+    },
+    CreateComment {
+        pr_number: u64,
+        body: String,
+    },
+    /// `pr_number` is `None` for a comment not listed before the update.
+    UpdateComment {
+        pr_number: Option<u64>,
+        old: String,
+        new: String,
+    },
+    DeleteComment {
+        pr_number: Option<u64>,
+    },
+    RequestReviewers {
+        pr_number: u64,
+        reviewers: Vec<String>,
+    },
+    AddLabels {
+        pr_number: u64,
+        labels: Vec<String>,
+    },
+    AddAssignees {
+        pr_number: u64,
+        assignees: Vec<String>,
+    },
+    MarkReady {
+        pr_number: u64,
+    },
+    MarkDraft {
+        pr_number: u64,
+    },
+    EnableAutoMerge {
+        pr_number: u64,
+        method: MergeMethod,
+    },
+}
+
+impl Mutation {
+    /// The Markdown this write leaves on the forge for reviewers to read: a
+    /// PR or issue body, or a comment. `None` for writes without text.
+    pub fn markdown(&self) -> Option<&str> {
+        match self {
+            Self::CreatePr { params, .. } => params.body.as_deref(),
+            Self::UpdatePrBody { new, .. } | Self::UpdateComment { new, .. } => Some(new),
+            Self::CreateIssue { body, .. }
+            | Self::UpdateIssueBody { body, .. }
+            | Self::CreateComment { body, .. } => Some(body),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Mutation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let on = |pr_number: &Option<u64>| pr_number.map_or(String::new(), |n| format!(" on #{n}"));
+        match self {
+            Self::RenameBranch { from, to } => writeln!(f, "would rename branch {from} to {to}"),
+            Self::CreatePr { number, params } => {
+                let draft = if params.draft { " draft" } else { "" };
+                writeln!(
+                    f,
+                    "would create{draft} PR #{number} ({} -> {}): \"{}\"",
+                    params.head, params.base, params.title
+                )?;
+                write_indented(f, params.body.as_deref().unwrap_or(""))
+            }
+            Self::UpdateBase { pr_number, base } => {
+                writeln!(f, "would change the base of #{pr_number} to {base}")
+            }
+            Self::UpdateTitle { pr_number, title } => {
+                writeln!(f, "would retitle #{pr_number} to \"{title}\"")
+            }
+            Self::UpdatePrBody {
+                pr_number,
+                old,
+                new,
+            } => {
+                writeln!(f, "would update the body of #{pr_number}:")?;
+                write_diff(f, old, new)
+            }
+            Self::ClosePr { pr_number } => writeln!(f, "would close #{pr_number}"),
+            Self::CreateIssue { title, body } => {
+                writeln!(f, "would open issue \"{title}\":")?;
+                write_indented(f, body)
+            }
+            Self::UpdateIssueBody { number, body } => {
+                writeln!(f, "would update the body of issue #{number}:")?;
+                write_indented(f, body)
+            }
+            Self::LinkPrToIssue {
+                issue_number,
+                pr_number,
+            } => writeln!(f, "would link #{pr_number} to issue #{issue_number}"),
+            Self::CreateComment { pr_number, body } => {
+                writeln!(f, "would comment on #{pr_number}:")?;
+                write_indented(f, body)
+            }
+            Self::UpdateComment {
+                pr_number,
+                old,
+                new,
+            } => {
+                writeln!(f, "would update comment{}:", on(pr_number))?;
+                write_diff(f, old, new)
+            }
+            Self::DeleteComment { pr_number } => {
+                writeln!(f, "would delete comment{}", on(pr_number))
+            }
+            Self::RequestReviewers {
+                pr_number,
+                reviewers,
+            } => writeln!(
+                f,
+                "would request reviews on #{pr_number} from {}",
+                reviewers.join(", ")
+            ),
+            Self::AddLabels { pr_number, labels } => {
+                writeln!(f, "would label #{pr_number} {}", labels.join(", "))
+            }
+            Self::AddAssignees {
+                pr_number,
+                assignees,
+            } => writeln!(f, "would assign #{pr_number} to {}", assignees.join(", ")),
+            Self::MarkReady { pr_number } => {
+                writeln!(f, "would mark #{pr_number} ready for review")
+            }
+            Self::MarkDraft { pr_number } => {
+                writeln!(f, "would convert #{pr_number} to a draft")
+            }
+            Self::EnableAutoMerge { pr_number, method } => {
+                writeln!(f, "would enable auto-merge ({method}) on #{pr_number}")
+            }
+        }
+    }
+}
+
+fn write_indented(f: &mut fmt::Formatter<'_>, text: &str) -> fmt::Result {
+    for line in text.lines() {
+        writeln!(f, "    {line}")?;
+    }
+    Ok(())
+}
+
+/// Write the lines `old` loses (`-`) and gains (`+`), in order, leaving out
+/// the lines both share.
+fn write_diff(f: &mut fmt::Formatter<'_>, old: &str, new: &str) -> fmt::Result {
+    for (sign, line) in line_diff(old, new) {
+        writeln!(f, "    {sign} {line}")?;
+    }
+    Ok(())
+}
+
+/// The lines removed from `old` (`'-'`) and added in `new` (`'+'`), in
+/// order, from a longest common subsequence of their lines.
+fn line_diff<'a>(old: &'a str, new: &'a str) -> Vec<(char, &'a str)> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    // lcs[i][j]: length of the longest common subsequence of old[i..] and
+    // new[j..].
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push(('-', old[i]));
+            i += 1;
+        } else {
+            diff.push(('+', new[j]));
+            j += 1;
+        }
+    }
+    diff
+}
+
+/// What the recording forge knows of the writes it took in.
+#[derive(Debug, Default)]
+struct State {
+    mutations: Vec<Mutation>,
+    /// Latest body of each PR, to diff body updates against.
+    pr_bodies: HashMap<u64, String>,
+    /// Each listed or created comment's PR and latest body, by comment ID.
+    comments: HashMap<u64, (u64, String)>,
+    /// Numbers handed out to PRs, issues, and comments that were never made.
+    next_id: u64,
+    created_prs: HashSet<u64>,
+}
+
+/// A [`Forge`] that reads through `inner` and records every write as a
+/// [`Mutation`] instead of making it.
+pub struct RecordingForge<'a, F> {
+    inner: &'a F,
+    /// Branches taken to exist on the remote: those a real run would have
+    /// pushed first.
+    pushed: HashSet<String>,
+    state: Mutex<State>,
+}
+
+impl<'a, F: Forge> RecordingForge<'a, F> {
+    /// Record writes meant for `inner`. Made-up PR numbers start at
+    /// `first_new_number`.
+    pub fn new(inner: &'a F, first_new_number: u64) -> Self {
+        Self {
+            inner,
+            pushed: HashSet::new(),
+            state: Mutex::new(State {
+                next_id: first_new_number,
+                ..State::default()
+            }),
+        }
+    }
+
+    /// Treat `branches` as present on the remote.
+    #[must_use]
+    pub fn with_pushed_branches(mut self, branches: impl IntoIterator<Item = String>) -> Self {
+        self.pushed.extend(branches);
+        self
+    }
+
+    /// Diff body updates of the given PRs against these bodies.
+    #[must_use]
+    pub fn with_pr_bodies(self, bodies: impl IntoIterator<Item = (u64, String)>) -> Self {
+        self.state().pr_bodies.extend(bodies);
+        self
+    }
+
+    /// The writes recorded so far, in order.
+    pub fn mutations(&self) -> Vec<Mutation> {
+        self.state().mutations.clone()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn record(&self, mutation: Mutation) {
+        self.state().mutations.push(mutation);
+    }
+
+    fn set_pr_body(&self, pr_number: u64, body: &str) {
+        let old = self
+            .state()
+            .pr_bodies
+            .insert(pr_number, body.to_string())
+            .unwrap_or_default();
+        self.record(Mutation::UpdatePrBody {
+            pr_number,
+            old,
+            new: body.to_string(),
+        });
+    }
+}
+
+impl<F: Forge> Forge for RecordingForge<'_, F> {
+    fn repo_ref(&self) -> ForgeRepoRef {
+        self.inner.repo_ref()
+    }
+
+    fn head_repo_ref(&self) -> ForgeRepoRef {
+        self.inner.head_repo_ref()
+    }
+
+    fn urls(&self) -> UrlBuilder {
+        self.inner.urls()
+    }
+
+    async fn get_authenticated_user(&self) -> Result<String, ForgeError> {
+        self.inner.get_authenticated_user().await
+    }
+
+    async fn get_user_emails(&self) -> Result<Vec<String>, ForgeError> {
+        self.inner.get_user_emails().await
+    }
+
+    async fn find_pr_for_branch(&self, head: &str) -> Result<Option<PullRequest>, ForgeError> {
+        self.inner.find_pr_for_branch(head).await
+    }
+
+    async fn find_prs_for_branches(
+        &self,
+        heads: &[String],
+    ) -> Result<Vec<Option<PrDetails>>, ForgeError> {
+        self.inner.find_prs_for_branches(heads).await
+    }
+
+    async fn find_merged_pr_for_branch(
+        &self,
+        head: &str,
+    ) -> Result<Option<MergedPullRequest>, ForgeError> {
+        self.inner.find_merged_pr_for_branch(head).await
+    }
+
+    async fn get_pr(&self, number: u64) -> Result<PullRequest, ForgeError> {
+        self.inner.get_pr(number).await
+    }
+
+    async fn list_review_requests(&self) -> Result<Vec<PrDetails>, ForgeError> {
+        self.inner.list_review_requests().await
+    }
+
+    async fn find_pr_for_change(&self, change_id: &str) -> Result<Option<PullRequest>, ForgeError> {
+        self.inner.find_pr_for_change(change_id).await
+    }
+
+    async fn rename_branch(&self, from: &str, to: &str) -> Result<(), ForgeError> {
+        self.record(Mutation::RenameBranch {
+            from: from.to_string(),
+            to: to.to_string(),
+        });
+        Ok(())
+    }
+
+    async fn create_pr(&self, params: CreatePrParams) -> Result<PullRequest, ForgeError> {
+        let mut state = self.state();
+        let number = state.next_id;
+        state.next_id += 1;
+        state.created_prs.insert(number);
+        if let Some(body) = &params.body {
+            state.pr_bodies.insert(number, body.clone());
+        }
+        let pr = PullRequest {
+            number,
+            html_url: format!("{}#{number}", self.inner.repo_ref()),
+            title: params.title.clone(),
+            head_ref: params.head.clone(),
+            base_ref: params.base.clone(),
+            state: PrState::Open,
+            body: params.body.clone(),
+            draft: params.draft,
+        };
+        state.mutations.push(Mutation::CreatePr { number, params });
+        Ok(pr)
+    }
+
+    async fn update_pr_base(&self, pr_number: u64, new_base: &str) -> Result<(), ForgeError> {
+        self.record(Mutation::UpdateBase {
+            pr_number,
+            base: new_base.to_string(),
+        });
+        Ok(())
+    }
+
+    async fn update_pr(
+        &self,
+        pr_number: u64,
+        title: Option<&str>,
+        body: Option<&str>,
+    ) -> Result<(), ForgeError> {
+        if let Some(title) = title {
+            self.record(Mutation::UpdateTitle {
+                pr_number,
+                title: title.to_string(),
+            });
+        }
+        if let Some(body) = body {
+            self.set_pr_body(pr_number, body);
+        }
+        Ok(())
+    }
+
+    async fn close_pr(&self, pr_number: u64) -> Result<(), ForgeError> {
+        self.record(Mutation::ClosePr { pr_number });
+        Ok(())
+    }
+
+    async fn list_own_issues(&self) -> Result<Vec<Issue>, ForgeError> {
+        self.inner.list_own_issues().await
+    }
+
+    async fn create_issue(&self, title: &str, body: &str) -> Result<Issue, ForgeError> {
+        let number = {
+            let mut state = self.state();
+            state.next_id += 1;
+            state.next_id - 1
+        };
+        self.record(Mutation::CreateIssue {
+            title: title.to_string(),
+            body: body.to_string(),
+        });
+        Ok(Issue {
+            number,
+            html_url: format!("{}#{number}", self.inner.repo_ref()),
+            body: Some(body.to_string()),
+        })
+    }
+
+    async fn update_issue_body(&self, number: u64, body: &str) -> Result<(), ForgeError> {
+        self.record(Mutation::UpdateIssueBody {
+            number,
+            body: body.to_string(),
+        });
+        Ok(())
+    }
+
+    async fn link_pr_to_issue(
+        &self,
+        issue_number: u64,
+        pr_number: u64,
+    ) -> Result<bool, ForgeError> {
+        self.record(Mutation::LinkPrToIssue {
+            issue_number,
+            pr_number,
+        });
+        Ok(true)
+    }
+
+    async fn list_comments(&self, pr_number: u64) -> Result<Vec<Comment>, ForgeError> {
+        if self.state().created_prs.contains(&pr_number) {
+            return Ok(Vec::new());
+        }
+        let comments = self.inner.list_comments(pr_number).await?;
+        let mut state = self.state();
+        for comment in &comments {
+            state
+                .comments
+                .insert(comment.id, (pr_number, comment.body.clone()));
+        }
+        Ok(comments)
+    }
+
+    async fn create_comment(&self, pr_number: u64, body: &str) -> Result<Comment, ForgeError> {
+        let mut state = self.state();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.comments.insert(id, (pr_number, body.to_string()));
+        state.mutations.push(Mutation::CreateComment {
+            pr_number,
+            body: body.to_string(),
+        });
+        Ok(Comment {
+            id,
+            node_id: None,
+            body: body.to_string(),
+        })
+    }
+
+    async fn update_comment(&self, comment_id: u64, body: &str) -> Result<(), ForgeError> {
+        let mut state = self.state();
+        let (pr_number, old) = match state.comments.get_mut(&comment_id) {
+            Some((pr_number, old)) => (Some(*pr_number), std::mem::replace(old, body.to_string())),
+            None => (None, String::new()),
+        };
+        state.mutations.push(Mutation::UpdateComment {
+            pr_number,
+            old,
+            new: body.to_string(),
+        });
+        Ok(())
+    }
+
+    async fn update_pr_body(&self, pr_number: u64, body: &str) -> Result<(), ForgeError> {
+        self.set_pr_body(pr_number, body);
+        Ok(())
+    }
+
+    async fn delete_comment(&self, comment_id: u64) -> Result<(), ForgeError> {
+        let mut state = self.state();
+        let pr_number = state.comments.remove(&comment_id).map(|(pr, _)| pr);
+        state.mutations.push(Mutation::DeleteComment { pr_number });
+        Ok(())
+    }
+
+    async fn branch_exists(&self, branch: &str) -> Result<bool, ForgeError> {
+        if self.pushed.contains(branch) {
+            return Ok(true);
+        }
+        self.inner.branch_exists(branch).await
+    }
+
+    async fn head_branch_exists(&self, branch: &str) -> Result<bool, ForgeError> {
+        if self.pushed.contains(branch) {
+            return Ok(true);
+        }
+        self.inner.head_branch_exists(branch).await
+    }
+
+    async fn list_check_runs(&self, branch: &str) -> Result<Vec<CheckRun>, ForgeError> {
+        self.inner.list_check_runs(branch).await
+    }
+
+    async fn list_reviews(&self, pr_number: u64) -> Result<Vec<Review>, ForgeError> {
+        if self.state().created_prs.contains(&pr_number) {
+            return Ok(Vec::new());
+        }
+        self.inner.list_reviews(pr_number).await
+    }
+
+    async fn request_reviewers(
+        &self,
+        pr_number: u64,
+        reviewers: &[String],
+    ) -> Result<(), ForgeError> {
+        self.record(Mutation::RequestReviewers {
+            pr_number,
+            reviewers: reviewers.to_vec(),
+        });
+        Ok(())
+    }
+
+    async fn add_labels(&self, pr_number: u64, labels: &[String]) -> Result<(), ForgeError> {
+        self.record(Mutation::AddLabels {
+            pr_number,
+            labels: labels.to_vec(),
+        });
+        Ok(())
+    }
+
+    async fn add_assignees(&self, pr_number: u64, assignees: &[String]) -> Result<(), ForgeError> {
+        self.record(Mutation::AddAssignees {
+            pr_number,
+            assignees: assignees.to_vec(),
+        });
+        Ok(())
+    }
+
+    async fn mark_ready_for_review(&self, pr_number: u64) -> Result<(), ForgeError> {
+        self.record(Mutation::MarkReady { pr_number });
+        Ok(())
+    }
+
+    async fn convert_to_draft(&self, pr_number: u64) -> Result<(), ForgeError> {
+        self.record(Mutation::MarkDraft { pr_number });
+        Ok(())
+    }
+
+    async fn enable_auto_merge(
+        &self,
+        pr_number: u64,
+        method: MergeMethod,
+    ) -> Result<(), ForgeError> {
+        self.record(Mutation::EnableAutoMerge { pr_number, method });
+        Ok(())
+    }
+
+    // Comment edits go through `update_comment` one by one, so each is
+    // recorded with its diff.
+    async fn update_comments_batch(&self, edits: &[CommentEdit]) -> Result<(), ForgeError> {
+        for edit in edits {
+            self.update_comment(edit.comment_id, &edit.body).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_lists_removed_and_added_lines() {
+        let old = "# Stack\n- #1\n- #2 👈\n\nfooter";
+        let new = "# Stack\n- #1\n- #2\n- #3 👈\n\nfooter";
+        assert_eq!(
+            line_diff(old, new),
+            [('-', "- #2 👈"), ('+', "- #2"), ('+', "- #3 👈")]
+        );
+        assert!(line_diff("same", "same").is_empty());
+    }
+
+    #[test]
+    fn comment_update_shows_its_pr_and_diff() {
+        let update = Mutation::UpdateComment {
+            pr_number: Some(12),
+            old: "a\nb".to_string(),
+            new: "a\nc".to_string(),
+        };
+        assert_eq!(
+            update.to_string(),
+            "would update comment on #12:\n    - b\n    + c\n"
+        );
+        assert_eq!(update.markdown(), Some("a\nc"));
+        assert_eq!(Mutation::ClosePr { pr_number: 12 }.markdown(), None);
+    }
+}
@@ -0,0 +1,159 @@
+//! Web links into a forge: pull requests, compare views, commits, and
+//! branches.
+//!
+//! Every forge lays these pages out differently (`/pull/12` on GitHub,
+//! `/-/merge_requests/12` on GitLab, `/pulls/12` on Forgejo), so links are
+//! built here from the forge's [`WebFlavor`] and web root instead of being
+//! formatted at each call site.
+
+use super::ForgeRepoRef;
+
+/// The URL layout of a forge's web interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebFlavor {
+    /// github.com and GitHub Enterprise Server.
+    GitHub,
+    /// gitlab.com and self-managed GitLab.
+    GitLab,
+    /// Forgejo and Gitea.
+    Forgejo,
+}
+
+/// Builds web links into one repository of a forge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlBuilder {
+    flavor: WebFlavor,
+    /// Web root of the forge, without a trailing slash, e.g.
+    /// `https://github.com`.
+    root: String,
+    repo: ForgeRepoRef,
+}
+
+impl UrlBuilder {
+    pub fn new(flavor: WebFlavor, root: &str, repo: ForgeRepoRef) -> Self {
+        Self {
+            flavor,
+            root: root.trim_end_matches('/').to_string(),
+            repo,
+        }
+    }
+
+    /// Links into `repo` on github.com.
+    pub fn github(repo: ForgeRepoRef) -> Self {
+        Self::new(WebFlavor::GitHub, "https://github.com", repo)
+    }
+
+    /// The repository's front page.
+    pub fn repo(&self) -> String {
+        format!("{}/{}", self.root, self.repo)
+    }
+
+    /// Pull request (merge request on GitLab) `number`.
+    pub fn pr(&self, number: u64) -> String {
+        match self.flavor {
+            WebFlavor::GitHub => format!("{}/pull/{number}", self.repo()),
+            WebFlavor::GitLab => format!("{}/-/merge_requests/{number}", self.repo()),
+            WebFlavor::Forgejo => format!("{}/pulls/{number}", self.repo()),
+        }
+    }
+
+    /// The changes of branch `head` of `head_repo` on top of `base`, as
+    /// they would appear in a PR. A `head_repo` other than this repository
+    /// (a fork) is named in the link where the forge supports it.
+    pub fn compare(&self, base: &str, head_repo: &ForgeRepoRef, head: &str) -> String {
+        match self.flavor {
+            WebFlavor::GitHub | WebFlavor::Forgejo if *head_repo == self.repo => {
+                format!("{}/compare/{base}...{head}", self.repo())
+            }
+            WebFlavor::GitHub | WebFlavor::Forgejo => format!(
+                "{}/compare/{base}...{}:{head}",
+                self.repo(),
+                head_repo.owner
+            ),
+            // GitLab compares branches of one project, so a fork's branch is
+            // compared from the fork.
+            WebFlavor::GitLab => format!("{}/{head_repo}/-/compare/{base}...{head}", self.root),
+        }
+    }
+
+    /// Commit `id`.
+    pub fn commit(&self, id: &str) -> String {
+        match self.flavor {
+            WebFlavor::GitHub | WebFlavor::Forgejo => format!("{}/commit/{id}", self.repo()),
+            WebFlavor::GitLab => format!("{}/-/commit/{id}", self.repo()),
+        }
+    }
+
+    /// The file tree of branch `name`.
+    pub fn branch(&self, name: &str) -> String {
+        match self.flavor {
+            WebFlavor::GitHub => format!("{}/tree/{name}", self.repo()),
+            WebFlavor::GitLab => format!("{}/-/tree/{name}", self.repo()),
+            WebFlavor::Forgejo => format!("{}/src/branch/{name}", self.repo()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder(flavor: WebFlavor, root: &str) -> UrlBuilder {
+        UrlBuilder::new(flavor, root, ForgeRepoRef::new("o", "r"))
+    }
+
+    #[test]
+    fn links_follow_each_forges_layout() {
+        let github = UrlBuilder::github(ForgeRepoRef::new("o", "r"));
+        assert_eq!(github.pr(12), "https://github.com/o/r/pull/12");
+        assert_eq!(github.commit("abc"), "https://github.com/o/r/commit/abc");
+        assert_eq!(
+            github.branch("feat/a"),
+            "https://github.com/o/r/tree/feat/a"
+        );
+
+        let gitlab = builder(WebFlavor::GitLab, "https://gitlab.example.com/");
+        assert_eq!(
+            gitlab.pr(12),
+            "https://gitlab.example.com/o/r/-/merge_requests/12"
+        );
+        assert_eq!(
+            gitlab.commit("abc"),
+            "https://gitlab.example.com/o/r/-/commit/abc"
+        );
+        assert_eq!(
+            gitlab.branch("a"),
+            "https://gitlab.example.com/o/r/-/tree/a"
+        );
+
+        let forgejo = builder(WebFlavor::Forgejo, "https://codeberg.org");
+        assert_eq!(forgejo.pr(12), "https://codeberg.org/o/r/pulls/12");
+        assert_eq!(forgejo.branch("a"), "https://codeberg.org/o/r/src/branch/a");
+    }
+
+    #[test]
+    fn compare_names_a_fork_head() {
+        let own = ForgeRepoRef::new("o", "r");
+        let fork = ForgeRepoRef::new("me", "r");
+
+        let github = builder(WebFlavor::GitHub, "https://ghe.example.com");
+        assert_eq!(
+            github.compare("main", &own, "feat"),
+            "https://ghe.example.com/o/r/compare/main...feat"
+        );
+        assert_eq!(
+            github.compare("main", &fork, "feat"),
+            "https://ghe.example.com/o/r/compare/main...me:feat"
+        );
+
+        let gitlab = builder(WebFlavor::GitLab, "https://gitlab.com");
+        assert_eq!(
+            gitlab.compare("main", &own, "feat"),
+            "https://gitlab.com/o/r/-/compare/main...feat"
+        );
+        assert_eq!(
+            gitlab.compare("main", &fork, "feat"),
+            "https://gitlab.com/me/r/-/compare/main...feat"
+        );
+    }
+}
@@ -0,0 +1,361 @@
+//! Self-check of a built [`ChangeGraph`], for `--paranoid`.
+//!
+//! Graph construction threads several maps through many traversals, and a
+//! slip in one of them tends to show up as a bookmark that silently goes
+//! missing from `stakk show` rather than as an error. [`check_invariants`]
+//! re-derives what the graph must look like from its own parts and reports
+//! every mismatch along with a dump of the graph.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt::Write as _;
+
+use miette::Diagnostic;
+use thiserror::Error;
+
+use super::types::ChangeGraph;
+use crate::jj::types::Bookmark;
+
+/// The graph broke at least one invariant.
+#[derive(Debug, Error, Diagnostic)]
+#[error(
+    "the change graph failed {} consistency check(s):\n{}\n\n{dump}",
+    violations.len(),
+    violations.iter().map(|v| format!("  - {v}")).collect::<Vec<_>>().join("\n")
+)]
+#[diagnostic(
+    code(stakk::graph::invariant),
+    help("this is a bug in stakk; please report it with the dump above")
+)]
+pub struct InvariantError {
+    /// What is wrong, one entry per broken invariant instance.
+    pub violations: Vec<String>,
+    /// The graph's adjacency, segments, stacks, and exclusions.
+    pub dump: String,
+}
+
+/// Check that `graph`, built from `bookmarks`, is consistent:
+///
+/// - every bookmark is in exactly one segment or one exclusion list, and
+///   segments hold no other bookmarks;
+/// - segments are keyed by their own change ID;
+/// - both ends of every adjacency edge are segments;
+/// - the stacks run from a root to a leaf along adjacency edges, and together
+///   cover every segment, edge, and leaf.
+pub fn check_invariants(graph: &ChangeGraph, bookmarks: &[Bookmark]) -> Result<(), InvariantError> {
+    let mut violations = Vec::new();
+
+    let mut homes: HashMap<&str, Vec<String>> = HashMap::new();
+    let mut segment_ids: Vec<&String> = graph.segments.keys().collect();
+    segment_ids.sort();
+    for id in &segment_ids {
+        for name in &graph.segments[*id].bookmark_names {
+            homes
+                .entry(name)
+                .or_default()
+                .push(format!("segment {}", short(id)));
+        }
+    }
+    for name in &graph.tainted_bookmarks {
+        homes
+            .entry(name)
+            .or_default()
+            .push("merge-tainted list".to_string());
+    }
+    for name in graph.private_bookmarks.keys() {
+        homes
+            .entry(name)
+            .or_default()
+            .push("private list".to_string());
+    }
+    let known: HashSet<&str> = bookmarks.iter().map(|b| b.name.as_str()).collect();
+    for bookmark in bookmarks {
+        match homes.get(bookmark.name.as_str()).map(Vec::as_slice) {
+            None | Some([]) => violations.push(format!(
+                "bookmark '{}' is in no segment and not excluded",
+                bookmark.name
+            )),
+            Some([_]) => {}
+            Some(places) => violations.push(format!(
+                "bookmark '{}' is in more than one place: {}",
+                bookmark.name,
+                places.join(", ")
+            )),
+        }
+    }
+    let mut unknown: Vec<&&str> = homes.keys().filter(|name| !known.contains(*name)).collect();
+    unknown.sort();
+    for name in unknown {
+        violations.push(format!(
+            "bookmark '{name}' is in the graph but was not discovered"
+        ));
+    }
+
+    for id in &segment_ids {
+        let segment = &graph.segments[*id];
+        if segment.change_id != **id {
+            violations.push(format!(
+                "segment {} is keyed by change {}",
+                short(&segment.change_id),
+                short(id)
+            ));
+        }
+    }
+
+    let mut edges: Vec<(&String, &String)> = graph.adjacency_list.iter().collect();
+    edges.sort();
+    for (child, parent) in &edges {
+        for (end, id) in [("child", child), ("parent", parent)] {
+            if !graph.segments.contains_key(*id) {
+                violations.push(format!(
+                    "adjacency edge {} -> {} has no segment for its {end}",
+                    short(child),
+                    short(parent)
+                ));
+            }
+        }
+    }
+
+    let mut stacked: HashSet<&str> = HashSet::new();
+    let mut stacked_edges: HashSet<(&str, &str)> = HashSet::new();
+    let mut stack_leaves: HashSet<&str> = HashSet::new();
+    for (index, stack) in graph.stacks.iter().enumerate() {
+        let (Some(first), Some(last)) = (stack.segments.first(), stack.segments.last()) else {
+            violations.push(format!("stack {index} is empty"));
+            continue;
+        };
+        if let Some(parent) = graph.adjacency_list.get(&first.change_id) {
+            violations.push(format!(
+                "stack {index} starts at {}, which has parent {}",
+                short(&first.change_id),
+                short(parent)
+            ));
+        }
+        if !graph.stack_leaves.contains(&last.change_id) {
+            violations.push(format!(
+                "stack {index} ends at {}, which is not a leaf",
+                short(&last.change_id)
+            ));
+        }
+        stack_leaves.insert(&last.change_id);
+        for pair in stack.segments.windows(2) {
+            let (parent, child) = (&pair[0].change_id, &pair[1].change_id);
+            if graph.adjacency_list.get(child) != Some(parent) {
+                violations.push(format!(
+                    "stack {index} puts {} on {}, which the adjacency does not",
+                    short(child),
+                    short(parent)
+                ));
+            }
+            stacked_edges.insert((child, parent));
+        }
+        stacked.extend(stack.segments.iter().map(|seg| seg.change_id.as_str()));
+    }
+    for id in &segment_ids {
+        if !stacked.contains(id.as_str()) {
+            violations.push(format!("segment {} is in no stack", short(id)));
+        }
+    }
+    for (child, parent) in &edges {
+        if !stacked_edges.contains(&(child.as_str(), parent.as_str())) {
+            violations.push(format!(
+                "adjacency edge {} -> {} is in no stack",
+                short(child),
+                short(parent)
+            ));
+        }
+    }
+    let mut leaves: Vec<&String> = graph.stack_leaves.iter().collect();
+    leaves.sort();
+    for leaf in leaves {
+        if !stack_leaves.contains(leaf.as_str()) {
+            violations.push(format!("leaf {} ends no stack", short(leaf)));
+        }
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(InvariantError {
+            violations,
+            dump: dump(graph),
+        })
+    }
+}
+
+/// The first 12 characters of a change ID.
+fn short(id: &str) -> &str {
+    &id[..id.len().min(12)]
+}
+
+/// A plain-text rendering of everything the checks look at.
+fn dump(graph: &ChangeGraph) -> String {
+    let mut out = String::from("Graph dump:\n  segments:\n");
+    let mut segment_ids: Vec<&String> = graph.segments.keys().collect();
+    segment_ids.sort();
+    for id in segment_ids {
+        let segment = &graph.segments[id];
+        let commits: Vec<&str> = segment
+            .commits
+            .iter()
+            .map(|c| short(&c.commit_id))
+            .collect();
+        let _ = writeln!(
+            out,
+            "    {}: bookmarks [{}], commits [{}]{}{}",
+            short(id),
+            segment.bookmark_names.join(", "),
+            commits.join(", "),
+            graph
+                .adjacency_list
+                .get(id)
+                .map(|parent| format!(", parent {}", short(parent)))
+                .unwrap_or_default(),
+            if graph.stack_leaves.contains(id) {
+                ", leaf"
+            } else {
+                ""
+            },
+        );
+    }
+    out.push_str("  stacks:\n");
+    for (index, stack) in graph.stacks.iter().enumerate() {
+        let ids: Vec<&str> = stack
+            .segments
+            .iter()
+            .map(|seg| short(&seg.change_id))
+            .collect();
+        let _ = writeln!(out, "    {index}: {}", ids.join(" -> "));
+    }
+    let mut private: Vec<(&String, &String)> = graph.private_bookmarks.iter().collect();
+    private.sort();
+    let private: Vec<String> = private
+        .into_iter()
+        .map(|(name, id)| format!("{name} ({})", short(id)))
+        .collect();
+    let _ = write!(
+        out,
+        "  merge-tainted: [{}]\n  private: [{}]\n  conflicted: [{}]",
+        graph.tainted_bookmarks.join(", "),
+        private.join(", "),
+        graph.conflicted_bookmarks.join(", ")
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::types::BookmarkSegment;
+    use crate::graph::types::BranchStack;
+
+    fn segment(name: &str) -> BookmarkSegment {
+        BookmarkSegment {
+            bookmark_names: vec![name.to_string()],
+            change_id: format!("ch_{name}"),
+            commits: vec![],
+        }
+    }
+
+    fn bookmark(name: &str) -> Bookmark {
+        Bookmark {
+            name: name.to_string(),
+            commit_id: format!("c_{name}"),
+            change_id: format!("ch_{name}"),
+            synced: false,
+        }
+    }
+
+    /// `a` <- `b` <- `c` and `a` <- `d`, plus a merge-tainted `m`.
+    fn valid_graph() -> ChangeGraph {
+        let stack = |names: &[&str]| BranchStack {
+            segments: names.iter().map(|name| segment(name)).collect(),
+        };
+        ChangeGraph {
+            adjacency_list: HashMap::from([
+                ("ch_b".to_string(), "ch_a".to_string()),
+                ("ch_c".to_string(), "ch_b".to_string()),
+                ("ch_d".to_string(), "ch_a".to_string()),
+            ]),
+            stack_leaves: HashSet::from(["ch_c".to_string(), "ch_d".to_string()]),
+            stack_roots: HashSet::from(["ch_a".to_string()]),
+            segments: ["a", "b", "c", "d"]
+                .into_iter()
+                .map(|name| (format!("ch_{name}"), segment(name)))
+                .collect(),
+            tainted_change_ids: HashSet::new(),
+            excluded_bookmark_count: 1,
+            tainted_bookmarks: vec!["m".to_string()],
+            private_bookmarks: HashMap::new(),
+            conflicted_bookmarks: vec![],
+            root_trunks: HashMap::new(),
+            stacks: vec![stack(&["a", "b", "c"]), stack(&["a", "d"])],
+        }
+    }
+
+    fn bookmarks() -> Vec<Bookmark> {
+        ["a", "b", "c", "d", "m"]
+            .into_iter()
+            .map(bookmark)
+            .collect()
+    }
+
+    fn violations(graph: &ChangeGraph, bookmarks: &[Bookmark]) -> Vec<String> {
+        check_invariants(graph, bookmarks)
+            .err()
+            .map(|e| e.violations)
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn consistent_graph_passes() {
+        check_invariants(&valid_graph(), &bookmarks()).unwrap();
+    }
+
+    #[test]
+    fn dropped_and_duplicated_bookmarks_are_reported() {
+        let mut graph = valid_graph();
+        graph.tainted_bookmarks.clear();
+        graph
+            .private_bookmarks
+            .insert("b".to_string(), "ch_p".to_string());
+        assert_eq!(
+            violations(&graph, &bookmarks()),
+            [
+                "bookmark 'b' is in more than one place: segment ch_b, private list",
+                "bookmark 'm' is in no segment and not excluded",
+            ]
+        );
+    }
+
+    #[test]
+    fn stacks_must_follow_the_adjacency() {
+        let mut graph = valid_graph();
+        graph.adjacency_list.remove("ch_d");
+        graph
+            .adjacency_list
+            .insert("ch_x".to_string(), "ch_a".to_string());
+        graph.stacks.pop();
+        assert_eq!(
+            violations(&graph, &bookmarks()),
+            [
+                "adjacency edge ch_x -> ch_a has no segment for its child",
+                "segment ch_d is in no stack",
+                "adjacency edge ch_x -> ch_a is in no stack",
+                "leaf ch_d ends no stack",
+            ]
+        );
+    }
+
+    #[test]
+    fn error_includes_a_dump() {
+        let mut graph = valid_graph();
+        graph.stacks[0].segments.swap(1, 2);
+        let error = check_invariants(&graph, &bookmarks()).unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("  - stack 0 puts ch_c on ch_a, which the adjacency does not"));
+        assert!(message.contains("    ch_b: bookmarks [b], commits [], parent ch_a\n"));
+        assert!(message.contains("    0: ch_a -> ch_c -> ch_b\n"));
+        assert!(message.ends_with("  merge-tainted: [m]\n  private: []\n  conflicted: []"));
+    }
+}
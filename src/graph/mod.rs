@@ -3,18 +3,36 @@
 //! Builds `ChangeGraph`, `BookmarkSegment`, and `BranchStack` from jj output to
 //! determine the stacking order of bookmarks for PR submission.
 
+pub mod invariants;
 pub mod types;
 
 use std::collections::HashMap;
 use std::collections::HashSet;
 
+use futures::StreamExt;
+use futures::TryStreamExt;
+
 use self::types::BookmarkSegment;
 use self::types::BranchStack;
 use self::types::ChangeGraph;
 use self::types::SegmentCommit;
-use crate::error::StakkError;
+use self::types::TrunkBranch;
 use crate::jj::Jj;
+use crate::jj::JjError;
 use crate::jj::runner::JjRunner;
+use crate::jj::types::LogEntry;
+
+/// Default revset of the bookmarks the graph is built from.
+pub const DEFAULT_BOOKMARKS_REVSET: &str = "mine() ~ trunk() ~ immutable()";
+
+/// Default revset of the unbookmarked heads the graph is built from.
+pub const DEFAULT_HEADS_REVSET: &str = "heads((mine() ~ empty() ~ immutable()) & trunk()..)";
+
+/// Maximum number of `jj log` traversals in flight while building the graph.
+const MAX_CONCURRENT_TRAVERSALS: usize = 8;
+
+/// Page size of `Jj::get_branch_changes_paginated`.
+const LOG_PAGE_SIZE: usize = 100;
 
 /// Result of traversing from one bookmark toward trunk.
 struct TraversalResult {
@@ -25,6 +43,9 @@ struct TraversalResult {
     already_seen_change_id: Option<String>,
     /// Whether this bookmark was excluded (tainted by a merge commit).
     excluded: bool,
+    /// If traversal stopped at a private change: that change's `change_id`
+    /// and the user bookmarks found at or above it.
+    private_stop: Option<(String, Vec<String>)>,
 }
 
 /// Build the complete change graph from the current jj repo state.
@@ -32,12 +53,48 @@ struct TraversalResult {
 /// Discovers all user bookmarks, traverses each toward trunk to find segments,
 /// builds an adjacency list, detects merge commits, identifies leaves, and
 /// groups segments into stacks.
+///
+/// Traversal stops at changes matching `private_revset`; bookmarks at or above
+/// them are left out of the stacks and recorded in
+/// [`ChangeGraph::private_bookmarks`].
+///
+/// The `jj log` calls behind the traversals run concurrently up front (at
+/// most `MAX_CONCURRENT_TRAVERSALS` at a time); the traversals themselves
+/// then walk the fetched changes in order, since each one depends on what the
+/// previous ones collected.
 pub async fn build_change_graph<R: JjRunner>(
     jj: &Jj<R>,
     bookmarks_revset: &str,
     heads_revset: &str,
-) -> Result<ChangeGraph, StakkError> {
-    let bookmarks = jj.get_my_bookmarks(bookmarks_revset).await?;
+    private_revset: Option<&str>,
+) -> Result<ChangeGraph, JjError> {
+    let private_query = private_revset.map(|revset| format!("{}.. & ({revset})", jj.stack_base()));
+    let (bookmarks, conflicted_bookmarks, heads, private_commit_ids) = futures::try_join!(
+        jj.get_my_bookmarks(bookmarks_revset),
+        jj.get_my_conflicted_bookmarks(bookmarks_revset),
+        jj.get_heads(heads_revset),
+        async {
+            match &private_query {
+                Some(query) => jj.get_commit_ids(query).await,
+                None => Ok(HashSet::new()),
+            }
+        },
+    )?;
+
+    // Unbookmarked heads: changes beyond the last bookmark.
+    let bookmarked_commit_ids: HashSet<String> =
+        bookmarks.iter().map(|b| b.commit_id.clone()).collect();
+    let unbookmarked_heads: Vec<&LogEntry> = heads
+        .iter()
+        .filter(|head| !bookmarked_commit_ids.contains(&head.commit_id))
+        .collect();
+
+    let start_commit_ids: Vec<&str> = bookmarks
+        .iter()
+        .map(|b| b.commit_id.as_str())
+        .chain(unbookmarked_heads.iter().map(|h| h.commit_id.as_str()))
+        .collect();
+    let branch_changes = fetch_branch_changes(jj, &start_commit_ids).await?;
 
     // Collect user bookmark names so traversal can filter out non-user bookmarks
     // that appear on commits (e.g. bookmarks from other users).
@@ -49,23 +106,34 @@ pub async fn build_change_graph<R: JjRunner>(
     let mut stack_roots: HashSet<String> = HashSet::new();
     let mut tainted_change_ids: HashSet<String> = HashSet::new();
     let mut excluded_bookmark_count: usize = 0;
+    let mut tainted_bookmarks: Vec<String> = Vec::new();
+    let mut private_bookmarks: HashMap<String, String> = HashMap::new();
 
     for bookmark in &bookmarks {
-        if fully_collected.contains(&bookmark.name) {
+        if fully_collected.contains(&bookmark.name)
+            || private_bookmarks.contains_key(&bookmark.name)
+        {
             continue;
         }
 
         let result = traverse_and_discover_segments(
-            &bookmark.commit_id,
-            jj,
+            &branch_changes[&bookmark.commit_id],
             &fully_collected,
             &mut tainted_change_ids,
             &user_bookmark_names,
-        )
-        .await?;
+            &private_commit_ids,
+        );
+
+        if let Some((change_id, blocked)) = result.private_stop {
+            for name in blocked {
+                private_bookmarks.insert(name, change_id.clone());
+            }
+            continue;
+        }
 
         if result.excluded {
             excluded_bookmark_count += 1;
+            tainted_bookmarks.push(bookmark.name.clone());
             continue;
         }
 
@@ -78,29 +146,26 @@ pub async fn build_change_graph<R: JjRunner>(
         );
     }
 
-    // Discover unbookmarked heads — changes beyond the last bookmark.
-    let bookmarked_commit_ids: HashSet<String> =
-        bookmarks.iter().map(|b| b.commit_id.clone()).collect();
-
-    let heads = jj.get_heads(heads_revset).await?;
-    for head in &heads {
-        // Skip heads that are at a bookmarked commit (already traversed).
-        if bookmarked_commit_ids.contains(&head.commit_id) {
-            continue;
-        }
+    for head in unbookmarked_heads {
         // Skip heads whose change_id is already in segments.
         if segments.contains_key(&head.change_id) {
             continue;
         }
 
         let result = traverse_and_discover_segments(
-            &head.commit_id,
-            jj,
+            &branch_changes[&head.commit_id],
             &fully_collected,
             &mut tainted_change_ids,
             &user_bookmark_names,
-        )
-        .await?;
+            &private_commit_ids,
+        );
+
+        if let Some((change_id, blocked)) = result.private_stop {
+            for name in blocked {
+                private_bookmarks.insert(name, change_id.clone());
+            }
+            continue;
+        }
 
         if result.excluded {
             excluded_bookmark_count += 1;
@@ -137,6 +202,8 @@ pub async fn build_change_graph<R: JjRunner>(
         }
     }
 
+    let root_trunks = find_root_trunks(jj, &stack_roots, &segments).await?;
+
     Ok(ChangeGraph {
         adjacency_list,
         stack_leaves,
@@ -144,6 +211,10 @@ pub async fn build_change_graph<R: JjRunner>(
         segments,
         tainted_change_ids,
         excluded_bookmark_count,
+        tainted_bookmarks,
+        private_bookmarks,
+        conflicted_bookmarks,
+        root_trunks,
         stacks,
     })
 }
@@ -189,115 +260,153 @@ fn integrate_traversal_result(
     }
 }
 
+/// Fetch the changes between trunk and each of `start_commit_ids`, newest
+/// first, following pagination. Runs up to [`MAX_CONCURRENT_TRAVERSALS`]
+/// `jj log` calls at once.
+async fn fetch_branch_changes<R: JjRunner>(
+    jj: &Jj<R>,
+    start_commit_ids: &[&str],
+) -> Result<HashMap<String, Vec<LogEntry>>, JjError> {
+    let unique: HashSet<&str> = start_commit_ids.iter().copied().collect();
+    let base = jj.stack_base();
+    let base = base.as_str();
+    futures::stream::iter(unique)
+        .map(|start| async move {
+            let mut changes = Vec::new();
+            let mut last_seen: Option<String> = None;
+            loop {
+                let page = jj
+                    .get_branch_changes_paginated(base, start, last_seen.as_deref())
+                    .await?;
+                let last_page = page.len() < LOG_PAGE_SIZE;
+                last_seen = page.last().map(|c| c.commit_id.clone());
+                changes.extend(page);
+                if last_page {
+                    break;
+                }
+            }
+            Ok::<_, JjError>((start.to_string(), changes))
+        })
+        .buffer_unordered(MAX_CONCURRENT_TRAVERSALS)
+        .try_collect()
+        .await
+}
+
 /// Traverse from a starting commit toward trunk, discovering segments along the
 /// way.
 ///
-/// Fetches commits in pages of 100. At each commit, checks for local bookmarks
-/// to determine segment boundaries. Stops when:
+/// `changes` are the commits from the start (a bookmark target or an
+/// unbookmarked head) down to trunk, newest first. At each commit, checks for
+/// local bookmarks to determine segment boundaries. Stops when:
 /// - hitting a commit whose bookmark was already fully collected
 /// - reaching trunk (no more commits in the revset)
 /// - encountering a merge commit (taints this traversal)
-///
-/// `start_commit_id` is the commit to begin traversal from (a bookmark target
-/// or an unbookmarked head).
-async fn traverse_and_discover_segments<R: JjRunner>(
-    start_commit_id: &str,
-    jj: &Jj<R>,
+/// - encountering a private commit (blocks this traversal)
+fn traverse_and_discover_segments(
+    changes: &[LogEntry],
     fully_collected: &HashSet<String>,
     tainted_change_ids: &mut HashSet<String>,
     user_bookmark_names: &HashSet<String>,
-) -> Result<TraversalResult, StakkError> {
+    private_commit_ids: &HashSet<String>,
+) -> TraversalResult {
     let mut segments: Vec<BookmarkSegment> = Vec::new();
     let mut current_segment: Option<BookmarkSegment> = None;
-    let mut last_seen_commit: Option<String> = None;
     let mut already_seen_change_id: Option<String> = None;
     let mut seen_change_ids: Vec<String> = Vec::new();
 
-    'page_loop: loop {
-        let changes = jj
-            .get_branch_changes_paginated("trunk()", start_commit_id, last_seen_commit.as_deref())
-            .await?;
+    for change in changes {
+        seen_change_ids.push(change.change_id.clone());
 
-        if changes.is_empty() {
-            break;
-        }
-
-        for change in &changes {
-            seen_change_ids.push(change.change_id.clone());
-
-            // Detect merge commits or already-tainted changes.
-            if change.parents.len() > 1 || tainted_change_ids.contains(&change.change_id) {
-                for id in &seen_change_ids {
-                    tainted_change_ids.insert(id.clone());
-                }
-                return Ok(TraversalResult {
-                    segments: Vec::new(),
-                    already_seen_change_id: None,
-                    excluded: true,
-                });
-            }
-
-            // Filter to only user-owned bookmarks on this commit.
-            let user_bookmarks: Vec<String> = change
-                .local_bookmark_names
+        // Never build on a private change: everything above it is
+        // unpushable.
+        if private_commit_ids.contains(&change.commit_id) {
+            let blocked = segments
                 .iter()
-                .filter(|name| user_bookmark_names.contains(*name))
+                .chain(&current_segment)
+                .flat_map(|seg| seg.bookmark_names.iter())
+                .chain(
+                    change
+                        .local_bookmark_names
+                        .iter()
+                        .filter(|name| user_bookmark_names.contains(*name)),
+                )
                 .cloned()
                 .collect();
+            return TraversalResult {
+                segments: Vec::new(),
+                already_seen_change_id: None,
+                excluded: false,
+                private_stop: Some((change.change_id.clone(), blocked)),
+            };
+        }
 
-            // Check if this commit has user bookmarks (segment boundary).
-            if !user_bookmarks.is_empty() {
-                // Finish current segment if any.
-                if let Some(seg) = current_segment.take() {
-                    segments.push(seg);
-                }
+        // Detect merge commits or already-tainted changes.
+        if change.parents.len() > 1 || tainted_change_ids.contains(&change.change_id) {
+            for id in &seen_change_ids {
+                tainted_change_ids.insert(id.clone());
+            }
+            return TraversalResult {
+                segments: Vec::new(),
+                already_seen_change_id: None,
+                excluded: true,
+                private_stop: None,
+            };
+        }
 
-                // Check if any bookmark on this change was already collected.
-                if user_bookmarks
-                    .iter()
-                    .any(|name| fully_collected.contains(name))
-                {
-                    already_seen_change_id = Some(change.change_id.clone());
-                    break 'page_loop;
-                }
+        // Filter to only user-owned bookmarks on this commit.
+        let user_bookmarks: Vec<String> = change
+            .local_bookmark_names
+            .iter()
+            .filter(|name| user_bookmark_names.contains(*name))
+            .cloned()
+            .collect();
 
-                // Start new segment.
-                current_segment = Some(BookmarkSegment {
-                    bookmark_names: user_bookmarks,
-                    change_id: change.change_id.clone(),
-                    commits: Vec::new(),
-                });
+        // Check if this commit has user bookmarks (segment boundary).
+        if !user_bookmarks.is_empty() {
+            // Finish current segment if any.
+            if let Some(seg) = current_segment.take() {
+                segments.push(seg);
             }
 
-            // Add commit to current segment. If no segment exists yet
-            // (unbookmarked head), start one with empty bookmark_names.
-            if current_segment.is_none() {
-                current_segment = Some(BookmarkSegment {
-                    bookmark_names: vec![],
-                    change_id: change.change_id.clone(),
-                    commits: Vec::new(),
-                });
-            }
-            if let Some(ref mut seg) = current_segment {
-                seg.commits.push(SegmentCommit {
-                    commit_id: change.commit_id.clone(),
-                    change_id: change.change_id.clone(),
-                    description: change.description.clone(),
-                    author: change.author.clone(),
-                    committer: change.committer.clone(),
-                    short_change_id: change.short_change_id.clone(),
-                    files: vec![],
-                    is_immutable: change.immutable,
-                    local_bookmark_names: change.local_bookmark_names.clone(),
-                });
+            // Check if any bookmark on this change was already collected.
+            if user_bookmarks
+                .iter()
+                .any(|name| fully_collected.contains(name))
+            {
+                already_seen_change_id = Some(change.change_id.clone());
+                break;
             }
-        }
 
-        if changes.len() < 100 {
-            break; // Last page.
+            // Start new segment.
+            current_segment = Some(BookmarkSegment {
+                bookmark_names: user_bookmarks,
+                change_id: change.change_id.clone(),
+                commits: Vec::new(),
+            });
         }
 
-        last_seen_commit = changes.last().map(|c| c.commit_id.clone());
+        // Add commit to current segment. If no segment exists yet
+        // (unbookmarked head), start one with empty bookmark_names.
+        if current_segment.is_none() {
+            current_segment = Some(BookmarkSegment {
+                bookmark_names: vec![],
+                change_id: change.change_id.clone(),
+                commits: Vec::new(),
+            });
+        }
+        if let Some(ref mut seg) = current_segment {
+            seg.commits.push(SegmentCommit {
+                commit_id: change.commit_id.clone(),
+                change_id: change.change_id.clone(),
+                description: change.description.clone(),
+                author: change.author.clone(),
+                committer: change.committer.clone(),
+                short_change_id: change.short_change_id.clone(),
+                files: vec![],
+                is_immutable: change.immutable,
+                local_bookmark_names: change.local_bookmark_names.clone(),
+            });
+        }
     }
 
     // Push final segment.
@@ -305,18 +414,148 @@ async fn traverse_and_discover_segments<R: JjRunner>(
         segments.push(seg);
     }
 
-    Ok(TraversalResult {
+    TraversalResult {
         segments,
         already_seen_change_id,
         excluded: false,
+        private_stop: None,
+    }
+}
+
+/// The branch each of `stack_roots` forks off, for roots on a branch
+/// matching one of `jj`'s trunk patterns (none without patterns).
+///
+/// A root's candidates are the pattern branches descending from its fork
+/// point; of those, the ones nearest the fork point count, and among them
+/// the earliest pattern wins. A root whose nearest candidate is the trunk
+/// itself is left out: it attaches to the default branch.
+async fn find_root_trunks<R: JjRunner>(
+    jj: &Jj<R>,
+    stack_roots: &HashSet<String>,
+    segments: &HashMap<String, BookmarkSegment>,
+) -> Result<HashMap<String, TrunkBranch>, JjError> {
+    if jj.trunk_patterns().is_empty() {
+        return Ok(HashMap::new());
+    }
+    let lookups = stack_roots.iter().filter_map(|change_id| {
+        let oldest = segments.get(change_id)?.commits.last()?;
+        let revset = format!("roots({} & {}-::)", jj.stack_base(), oldest.commit_id);
+        Some(async move {
+            let nearest = jj.get_log(&revset).await?;
+            Ok::<_, JjError>(
+                pattern_branch(&nearest, jj.trunk_patterns(), jj.trunk_remote())
+                    .map(|branch| (change_id.clone(), branch)),
+            )
+        })
+    });
+
+    Ok(futures::future::try_join_all(lookups)
+        .await?
+        .into_iter()
+        .flatten()
+        .collect())
+}
+
+/// The first remote branch of `entries` matching `patterns`, trying the
+/// patterns in order. With `remote`, only branches on that remote count.
+fn pattern_branch(
+    entries: &[LogEntry],
+    patterns: &[String],
+    remote: Option<&str>,
+) -> Option<TrunkBranch> {
+    let branches: Vec<(&str, &str)> = entries
+        .iter()
+        .flat_map(|entry| &entry.remote_bookmark_names)
+        .filter_map(|name| name.rsplit_once('@'))
+        .filter(|&(_, on)| on != "git" && remote.is_none_or(|remote| on == remote))
+        .collect();
+    patterns.iter().find_map(|pattern| {
+        branches
+            .iter()
+            .find(|(branch, _)| glob_matches(pattern, branch))
+            .map(|&(branch, remote)| TrunkBranch {
+                branch: branch.to_string(),
+                remote: remote.to_string(),
+            })
     })
 }
 
+/// Whether `text` matches `pattern`, in which `*` stands for any run of
+/// characters and `?` for any one character.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let (pattern, text): (Vec<char>, Vec<char>) =
+        (pattern.chars().collect(), text.chars().collect());
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was, and the text position it has matched up to.
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    backtrack = Some((star, matched + 1));
+                    p = star + 1;
+                    t = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Keep only the stacks with a commit that changes a file matching one of
+/// the path globs `patterns`, returning how many stacks were dropped. A
+/// pattern matches a file if it matches its path or one of its parent
+/// directories, so `services/foo` covers everything below that directory.
+pub fn retain_stacks_touching(graph: &mut ChangeGraph, patterns: &[String]) -> usize {
+    let touches = |file: &str| {
+        patterns.iter().any(|pattern| {
+            let pattern = pattern.trim_end_matches('/');
+            std::iter::successors(Some(file), |path| path.rsplit_once('/').map(|(dir, _)| dir))
+                .any(|path| glob_matches(pattern, path))
+        })
+    };
+    let before = graph.stacks.len();
+    graph.stacks.retain(|stack| {
+        stack
+            .segments
+            .iter()
+            .flat_map(|segment| &segment.commits)
+            .flat_map(|commit| &commit.files)
+            .any(|file| touches(file))
+    });
+    before - graph.stacks.len()
+}
+
+/// The branch the stack containing `bookmark` forks off, if that is a
+/// branch matching the trunk patterns rather than the default branch.
+pub fn trunk_branch_of<'a>(graph: &'a ChangeGraph, bookmark: &str) -> Option<&'a str> {
+    let stack = graph.stacks.iter().find(|stack| {
+        stack
+            .segments
+            .iter()
+            .any(|seg| seg.bookmark_names.iter().any(|name| name == bookmark))
+    })?;
+    let root = stack.segments.first()?;
+    graph
+        .root_trunks
+        .get(&root.change_id)
+        .map(|trunk| trunk.branch.as_str())
+}
+
 /// Pre-fetch file lists for all commits in all stacks concurrently.
 async fn fetch_file_lists<R: JjRunner>(
     jj: &Jj<R>,
     stacks: &mut [BranchStack],
-) -> Result<(), StakkError> {
+) -> Result<(), JjError> {
     // Collect all (stack_idx, seg_idx, commit_idx, commit_id) tuples.
     let mut tasks: Vec<(usize, usize, usize, String)> = Vec::new();
     for (si, stack) in stacks.iter().enumerate() {
@@ -401,6 +640,119 @@ fn collect_timestamps_desc(stack: &BranchStack) -> Vec<&str> {
     timestamps
 }
 
+/// Locate the stack and segment that contain a commit.
+///
+/// Returns the first stack (in display order) with a segment containing
+/// `commit_id`, together with that segment's index in the stack. Used to map
+/// the working copy onto the graph.
+pub fn locate_commit<'a>(
+    graph: &'a ChangeGraph,
+    commit_id: &str,
+) -> Option<(&'a BranchStack, usize)> {
+    graph.stacks.iter().find_map(|stack| {
+        stack
+            .segments
+            .iter()
+            .position(|seg| seg.commits.iter().any(|c| c.commit_id == commit_id))
+            .map(|idx| (stack, idx))
+    })
+}
+
+/// The conflicted bookmarks of `graph` that sit on a commit of `segments`,
+/// sorted. Pushing those segments would leave the bookmarks behind.
+pub fn conflicted_in<'a>(graph: &'a ChangeGraph, segments: &[BookmarkSegment]) -> Vec<&'a str> {
+    let mut names: Vec<&str> = graph
+        .conflicted_bookmarks
+        .iter()
+        .filter(|name| {
+            segments
+                .iter()
+                .flat_map(|seg| &seg.commits)
+                .any(|commit| commit.local_bookmark_names.contains(name))
+        })
+        .map(String::as_str)
+        .collect();
+    names.sort_unstable();
+    names
+}
+
+/// Split `segments` (trunk to leaf) so that every commit is a segment of its
+/// own, trunk to leaf, for stacking one PR per commit. A segment's bookmarks
+/// stay on its tip commit; the commits below it get segments without
+/// bookmarks.
+pub fn split_into_commits(segments: &[BookmarkSegment]) -> Vec<BookmarkSegment> {
+    segments
+        .iter()
+        .flat_map(|segment| {
+            segment
+                .commits
+                .iter()
+                .enumerate()
+                .rev()
+                .map(|(i, commit)| BookmarkSegment {
+                    bookmark_names: if i == 0 {
+                        segment.bookmark_names.clone()
+                    } else {
+                        Vec::new()
+                    },
+                    change_id: commit.change_id.clone(),
+                    commits: vec![commit.clone()],
+                })
+        })
+        .collect()
+}
+
+/// Order the segments of `graph` for a log-like view, leaves first: every
+/// segment comes before its parent, and the descendants of a segment sit
+/// contiguously right above it.
+///
+/// Siblings keep the order of the first stack containing each, so the path
+/// of the first stack in display order is listed first. Returns change IDs.
+pub fn topological_sort(graph: &ChangeGraph) -> Vec<&str> {
+    fn visit<'a>(id: &'a str, children: &HashMap<&str, Vec<&'a str>>, order: &mut Vec<&'a str>) {
+        for child in children.get(id).into_iter().flatten() {
+            visit(child, children, order);
+        }
+        order.push(id);
+    }
+
+    let mut roots: Vec<&str> = Vec::new();
+    let mut children: HashMap<&str, Vec<&str>> = HashMap::new();
+    for stack in &graph.stacks {
+        for segment in &stack.segments {
+            let id = segment.change_id.as_str();
+            let siblings = match graph.adjacency_list.get(id) {
+                Some(parent) => children.entry(parent.as_str()).or_default(),
+                None => &mut roots,
+            };
+            if !siblings.contains(&id) {
+                siblings.push(id);
+            }
+        }
+    }
+
+    let mut order = Vec::with_capacity(graph.segments.len());
+    for root in roots {
+        visit(root, &children, &mut order);
+    }
+    order
+}
+
+/// The first bookmark of each segment in [`topological_sort`] order, or in
+/// reverse with `roots_first` (every bookmark before those stacked on it).
+/// Segments without a bookmark are skipped.
+pub fn bookmark_order(graph: &ChangeGraph, roots_first: bool) -> Vec<&str> {
+    let mut order: Vec<&str> = topological_sort(graph)
+        .into_iter()
+        .filter_map(|id| graph.segments.get(id)?.bookmark_names.first())
+        .map(String::as_str)
+        .collect();
+    if roots_first {
+        order.reverse();
+    }
+    order
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -424,6 +776,43 @@ mod tests {
 
     // -- Test helpers --
 
+    /// A graph of bare segments from `stacks` of change IDs, trunk to leaf.
+    fn graph_of(stacks: &[&[&str]]) -> ChangeGraph {
+        let segment = |id: &str| BookmarkSegment {
+            bookmark_names: vec![id.to_string()],
+            change_id: id.to_string(),
+            commits: vec![],
+        };
+        let mut adjacency_list = HashMap::new();
+        for stack in stacks {
+            for pair in stack.windows(2) {
+                adjacency_list.insert(pair[1].to_string(), pair[0].to_string());
+            }
+        }
+        ChangeGraph {
+            adjacency_list,
+            stack_leaves: HashSet::new(),
+            stack_roots: HashSet::new(),
+            segments: stacks
+                .iter()
+                .flat_map(|stack| stack.iter())
+                .map(|id| (id.to_string(), segment(id)))
+                .collect(),
+            tainted_change_ids: HashSet::new(),
+            excluded_bookmark_count: 0,
+            tainted_bookmarks: vec![],
+            private_bookmarks: HashMap::new(),
+            conflicted_bookmarks: vec![],
+            root_trunks: HashMap::new(),
+            stacks: stacks
+                .iter()
+                .map(|stack| BranchStack {
+                    segments: stack.iter().map(|id| segment(id)).collect(),
+                })
+                .collect(),
+        }
+    }
+
     /// Build a bookmark list NDJSON line.
     fn bookmark_json(name: &str, commit_id: &str, change_id: &str) -> String {
         format!(
@@ -466,6 +855,67 @@ mod tests {
 
     // -- Tests --
 
+    /// A private change between two bookmarks: trunk -> `bm_a` -> private ->
+    /// `bm_b`.
+    ///
+    /// Traversal from `bm_b` stops at the private change, so `bm_b` is left
+    /// out of the stacks and recorded as private; `bm_a` below it is
+    /// unaffected.
+    #[tokio::test]
+    async fn private_change_blocks_bookmarks_above_it() {
+        let runner = MockJjRunner {
+            handler: |args: &[&str]| {
+                if args[0] == "diff" {
+                    return Ok(String::new());
+                }
+                if args[0] == "bookmark" {
+                    let lines = [
+                        bookmark_json("bm_b", "c_b", "ch_b"),
+                        bookmark_json("bm_a", "c_a", "ch_a"),
+                    ];
+                    return Ok(lines.join("\n"));
+                }
+
+                let revset = args[2];
+                if revset.contains("private") {
+                    return Ok("c_p\n".to_string());
+                }
+                if revset.contains("c_b") {
+                    let lines = [
+                        log_entry_json("c_b", "ch_b", &["c_p"], &["bm_b"]),
+                        log_entry_json("c_p", "ch_p", &["c_a"], &[]),
+                        log_entry_json("c_a", "ch_a", &["trunk_c"], &["bm_a"]),
+                    ];
+                    return Ok(lines.join("\n"));
+                }
+                if revset.contains("c_a") {
+                    return Ok(log_entry_json("c_a", "ch_a", &["trunk_c"], &["bm_a"]));
+                }
+
+                Ok(String::new())
+            },
+        };
+
+        let jj = Jj::new(runner);
+        let graph = build_change_graph(
+            &jj,
+            "mine() ~ trunk() ~ immutable()",
+            "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            Some("description(glob:'private:*')"),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(graph.stacks.len(), 1);
+        assert_eq!(graph.stacks[0].segments.len(), 1);
+        assert_eq!(graph.stacks[0].segments[0].bookmark_names, vec!["bm_a"]);
+        assert_eq!(
+            graph.private_bookmarks,
+            HashMap::from([("bm_b".to_string(), "ch_p".to_string())])
+        );
+        assert_eq!(graph.excluded_bookmark_count, 0);
+    }
+
     /// Simple linear stack: trunk -> `bm_a` -> `bm_b`
     ///
     /// Bookmark list returns [`bm_b`, `bm_a`].
@@ -507,6 +957,7 @@ mod tests {
             &jj,
             "mine() ~ trunk() ~ immutable()",
             "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            None,
         )
         .await
         .unwrap();
@@ -528,6 +979,190 @@ mod tests {
         assert_eq!(stack.segments[1].bookmark_names, vec!["bm_b"]);
     }
 
+    /// With a trunk override, stacks are traversed down to the overriding
+    /// revset rather than `trunk()`.
+    #[tokio::test]
+    async fn traversal_stops_at_the_trunk_override() {
+        let runner = MockJjRunner {
+            handler: |args: &[&str]| {
+                if args[0] == "bookmark" {
+                    return Ok(bookmark_json("bm_a", "c_a", "ch_a"));
+                }
+                match args.get(2) {
+                    Some(&"release/1.2..c_a") => {
+                        Ok(log_entry_json("c_a", "ch_a", &["rel_c"], &["bm_a"]))
+                    }
+                    Some(revset) if revset.contains("trunk()..c_a") => {
+                        panic!("traversed to trunk(): {args:?}")
+                    }
+                    _ => Ok(String::new()),
+                }
+            },
+        };
+
+        let jj = Jj::new(runner).with_trunk("release/1.2");
+        let graph = build_change_graph(
+            &jj,
+            "mine() ~ trunk() ~ immutable()",
+            "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(graph.stacks.len(), 1);
+        assert_eq!(graph.stacks[0].segments[0].bookmark_names, vec!["bm_a"]);
+    }
+
+    /// A log entry for `commit_id` with the given remote bookmarks
+    /// (`name@remote`).
+    fn trunk_entry_json(commit_id: &str, remote_bookmarks: &[&str]) -> String {
+        let refs: Vec<String> = remote_bookmarks
+            .iter()
+            .map(|r| {
+                let (name, remote) = r.split_once('@').unwrap();
+                format!(r#"{{"name":"{name}","remote":"{remote}","target":["{commit_id}"]}}"#)
+            })
+            .collect();
+        log_entry_json(commit_id, &format!("ch_{commit_id}"), &[], &[]).replace(
+            r#""remote_bookmarks":[]"#,
+            &format!(r#""remote_bookmarks":[{}]"#, refs.join(",")),
+        )
+    }
+
+    /// trunk-pattern branch `release/1.2` -> `bm_a`: the stack ends at the
+    /// release branch, and its root records it.
+    #[tokio::test]
+    async fn roots_record_the_trunk_pattern_branch_they_fork_off() {
+        let runner = MockJjRunner {
+            handler: |args: &[&str]| {
+                if args[0] == "bookmark" {
+                    return Ok(bookmark_json("bm_a", "c_a", "ch_a"));
+                }
+                match args.get(2) {
+                    Some(revset) if revset.starts_with("roots(") => {
+                        assert!(revset.ends_with("& c_a-::)"), "{revset}");
+                        Ok(trunk_entry_json(
+                            "rel_c",
+                            &["release/1.2@git", "release/1.2@origin"],
+                        ))
+                    }
+                    Some(revset) if revset.ends_with("..c_a") => {
+                        assert!(revset.contains(r#"remote_bookmarks(glob:"release/*")"#));
+                        Ok(log_entry_json("c_a", "ch_a", &["rel_c"], &["bm_a"]))
+                    }
+                    _ => Ok(String::new()),
+                }
+            },
+        };
+
+        let jj = Jj::new(runner).with_trunk_patterns(vec!["release/*".to_string()]);
+        let graph = build_change_graph(
+            &jj,
+            "mine() ~ trunk() ~ immutable()",
+            "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            graph.root_trunks["ch_a"].revset(),
+            r#""release/1.2"@"origin""#
+        );
+        assert_eq!(trunk_branch_of(&graph, "bm_a"), Some("release/1.2"));
+        assert_eq!(trunk_branch_of(&graph, "gone"), None);
+    }
+
+    #[test]
+    fn earlier_trunk_patterns_win() {
+        let signature = crate::jj::types::Signature {
+            name: "T".to_string(),
+            email: "t@t.t".to_string(),
+            timestamp: "T".to_string(),
+        };
+        let entries = [LogEntry {
+            commit_id: "c".to_string(),
+            change_id: "ch_c".to_string(),
+            description: String::new(),
+            parents: vec![],
+            author: signature.clone(),
+            committer: signature,
+            local_bookmark_names: vec![],
+            remote_bookmark_names: vec![
+                "main@origin".to_string(),
+                "develop@origin".to_string(),
+                "next@fork".to_string(),
+            ],
+            immutable: true,
+            short_change_id: "ch_c".to_string(),
+        }];
+        let patterns = |p: &[&str]| p.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let branch = |p: &[&str]| pattern_branch(&entries, &patterns(p), None).map(|t| t.branch);
+        assert_eq!(branch(&["develop", "ma*"]).as_deref(), Some("develop"));
+        assert_eq!(branch(&["ma?n", "develop"]).as_deref(), Some("main"));
+        assert_eq!(branch(&["next"]).as_deref(), Some("next"));
+        assert_eq!(
+            pattern_branch(&entries, &patterns(&["next", "develop"]), Some("origin")),
+            Some(TrunkBranch {
+                branch: "develop".to_string(),
+                remote: "origin".to_string(),
+            })
+        );
+        assert_eq!(branch(&["release/*"]), None);
+    }
+
+    #[test]
+    fn glob_matching() {
+        assert!(glob_matches("release/*", "release/1.2"));
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("r*e*/?.2", "release/1.2"));
+        assert!(!glob_matches("release/*", "releases/1.2"));
+        assert!(!glob_matches("main", "main2"));
+    }
+
+    #[test]
+    fn stacks_are_kept_if_they_touch_the_paths() {
+        let mut graph = graph_of(&[&["a", "b"], &["c"], &["d"]]);
+        let sig = crate::jj::types::Signature {
+            name: "T".to_string(),
+            email: "t@t.t".to_string(),
+            timestamp: "T".to_string(),
+        };
+        let touching = |files: &[&str]| SegmentCommit {
+            commit_id: "c".to_string(),
+            change_id: "c".to_string(),
+            description: String::new(),
+            author: sig.clone(),
+            committer: sig.clone(),
+            short_change_id: "c".to_string(),
+            files: files.iter().map(ToString::to_string).collect(),
+            is_immutable: false,
+            local_bookmark_names: vec![],
+        };
+        graph.stacks[0].segments[1]
+            .commits
+            .push(touching(&["services/foo/src/lib.rs"]));
+        graph.stacks[1].segments[0]
+            .commits
+            .push(touching(&["services/foobar/x.rs", "README.md"]));
+        graph.stacks[2].segments[0]
+            .commits
+            .push(touching(&["docs/foo.md"]));
+
+        let patterns = ["services/foo/".to_string(), "*.md".to_string()];
+        assert_eq!(retain_stacks_touching(&mut graph, &patterns[..1]), 2);
+        assert_eq!(graph.stacks.len(), 1);
+        assert_eq!(graph.stacks[0].segments[1].change_id, "b");
+
+        let mut graph = graph_of(&[&["a"], &["d"]]);
+        graph.stacks[1].segments[0]
+            .commits
+            .push(touching(&["docs/foo.md"]));
+        assert_eq!(retain_stacks_touching(&mut graph, &patterns), 1);
+        assert_eq!(graph.stacks[0].segments[0].change_id, "d");
+    }
+
     /// Branching: trunk -> `bm_a` -> `bm_b` and trunk -> `bm_a` -> `bm_c`
     ///
     /// Two stacks sharing a common root (`bm_a`).
@@ -574,6 +1209,7 @@ mod tests {
             &jj,
             "mine() ~ trunk() ~ immutable()",
             "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            None,
         )
         .await
         .unwrap();
@@ -597,6 +1233,12 @@ mod tests {
             assert_eq!(stack.segments[0].bookmark_names, vec!["bm_a"]);
             assert_eq!(stack.segments.len(), 2);
         }
+
+        let bookmarks = jj
+            .get_my_bookmarks("mine() ~ trunk() ~ immutable()")
+            .await
+            .unwrap();
+        invariants::check_invariants(&graph, &bookmarks).unwrap();
     }
 
     /// Merge commit exclusion: bookmark points at a merge commit (>1 parent).
@@ -632,15 +1274,31 @@ mod tests {
             &jj,
             "mine() ~ trunk() ~ immutable()",
             "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            None,
         )
         .await
         .unwrap();
 
         assert_eq!(graph.stacks.len(), 0);
         assert_eq!(graph.excluded_bookmark_count, 1);
+        assert_eq!(graph.tainted_bookmarks, ["bm_merge"]);
         assert!(graph.tainted_change_ids.contains("ch_merge"));
     }
 
+    #[test]
+    fn topological_sort_lists_subtrees_above_their_base() {
+        let graph = graph_of(&[&["a", "b"], &["a", "c", "d"], &["a", "c", "e"], &["f"]]);
+        assert_eq!(topological_sort(&graph), ["b", "d", "e", "c", "a", "f"]);
+    }
+
+    #[test]
+    fn bookmark_order_both_directions() {
+        let mut graph = graph_of(&[&["a", "b"], &["a", "c"]]);
+        graph.segments.get_mut("c").unwrap().bookmark_names.clear();
+        assert_eq!(bookmark_order(&graph, false), ["b", "a"]);
+        assert_eq!(bookmark_order(&graph, true), ["a", "b"]);
+    }
+
     /// Taint propagation: a descendant of a merge commit is also tainted.
     ///
     /// trunk -> `bm_a` (merge) -> `bm_b`
@@ -680,6 +1338,7 @@ mod tests {
             &jj,
             "mine() ~ trunk() ~ immutable()",
             "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            None,
         )
         .await
         .unwrap();
@@ -740,6 +1399,7 @@ mod tests {
             &jj,
             "mine() ~ trunk() ~ immutable()",
             "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            None,
         )
         .await
         .unwrap();
@@ -786,6 +1446,7 @@ mod tests {
             &jj,
             "mine() ~ trunk() ~ immutable()",
             "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            None,
         )
         .await
         .unwrap();
@@ -819,6 +1480,7 @@ mod tests {
             &jj,
             "mine() ~ trunk() ~ immutable()",
             "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            None,
         )
         .await
         .unwrap();
@@ -872,6 +1534,7 @@ mod tests {
             &jj,
             "mine() ~ trunk() ~ immutable()",
             "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            None,
         )
         .await
         .unwrap();
@@ -893,6 +1556,23 @@ mod tests {
         let stack = &graph.stacks[0];
         assert_eq!(stack.segments[0].change_id, "ch_a");
         assert_eq!(stack.segments[1].change_id, "ch_b");
+
+        // Split per commit: trunk to leaf, bookmarks kept on the tips.
+        let split = split_into_commits(&stack.segments);
+        let commits: Vec<(&str, &[String])> = split
+            .iter()
+            .map(|seg| (seg.commits[0].commit_id.as_str(), &seg.bookmark_names[..]))
+            .collect();
+        assert_eq!(
+            commits,
+            [
+                ("c1", &[][..]),
+                ("c2", &["bm_a".to_string()][..]),
+                ("c3", &[][..]),
+                ("c4", &["bm_b".to_string()][..]),
+            ]
+        );
+        assert_eq!(split[0].change_id, "ch_1");
     }
 
     /// Already-collected bookmark: second traversal connects to first via
@@ -904,7 +1584,8 @@ mod tests {
     ///
     /// Traversing `bm_b` discovers [`bm_b`, `bm_a`].
     /// Traversing `bm_c` discovers [`bm_c`], stops at `bm_a` (already
-    /// collected). `bm_a` is NOT traversed separately (already collected).
+    /// collected). `bm_a`'s log is prefetched alongside the others, but it is
+    /// NOT traversed separately (already collected).
     #[tokio::test]
     async fn already_collected_early_stop() {
         let runner = MockJjRunner {
@@ -937,13 +1618,15 @@ mod tests {
                     return Ok(lines.join("\n"));
                 }
 
+                if revset.contains("c_a") {
+                    return Ok(log_entry_json("c_a", "ch_a", &["trunk_c"], &["bm_a"]));
+                }
+
                 // Heads query: no unbookmarked heads in this test.
                 if is_heads_query(args) {
                     return Ok(String::new());
                 }
 
-                // Should NOT be called for c_a because bm_a is already
-                // collected.
                 panic!("unexpected revset: {revset}");
             },
         };
@@ -953,6 +1636,7 @@ mod tests {
             &jj,
             "mine() ~ trunk() ~ immutable()",
             "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            None,
         )
         .await
         .unwrap();
@@ -992,6 +1676,7 @@ mod tests {
             &jj,
             "mine() ~ trunk() ~ immutable()",
             "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            None,
         )
         .await
         .unwrap();
@@ -1035,6 +1720,7 @@ mod tests {
             &jj,
             "mine() ~ trunk() ~ immutable()",
             "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            None,
         )
         .await
         .unwrap();
@@ -1137,6 +1823,7 @@ mod tests {
             &jj,
             "mine() ~ trunk() ~ immutable()",
             "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            None,
         )
         .await
         .unwrap();
@@ -1182,6 +1869,7 @@ mod tests {
             &jj,
             "mine() ~ trunk() ~ immutable()",
             "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            None,
         )
         .await
         .unwrap();
@@ -1238,6 +1926,7 @@ mod tests {
             &jj,
             "mine() ~ trunk() ~ immutable()",
             "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            None,
         )
         .await
         .unwrap();
@@ -1253,6 +1942,35 @@ mod tests {
         assert_eq!(seg.commits[1].commit_id, "c_other");
     }
 
+    /// A branch longer than one log page is fetched page by page, each page
+    /// continuing below the last commit of the previous one.
+    #[tokio::test]
+    async fn fetch_branch_changes_follows_pagination() {
+        let runner = MockJjRunner {
+            handler: |args: &[&str]| {
+                let revset = args[2];
+                if revset.contains("~ c_99::") {
+                    return Ok(log_entry_json("c_100", "ch_100", &["trunk_c"], &[]));
+                }
+                let page: Vec<String> = (0..LOG_PAGE_SIZE)
+                    .map(|i| {
+                        let parent = format!("c_{}", i + 1);
+                        log_entry_json(&format!("c_{i}"), &format!("ch_{i}"), &[&parent], &[])
+                    })
+                    .collect();
+                Ok(page.join("\n"))
+            },
+        };
+
+        let jj = Jj::new(runner);
+        let changes = fetch_branch_changes(&jj, &["c_0", "c_0"]).await.unwrap();
+
+        assert_eq!(changes.len(), 1);
+        let branch = &changes["c_0"];
+        assert_eq!(branch.len(), LOG_PAGE_SIZE + 1);
+        assert_eq!(branch.last().unwrap().commit_id, "c_100");
+    }
+
     // -- Unbookmarked head tests --
 
     /// Helper: determines if a `jj log` invocation is a heads query vs
@@ -1303,6 +2021,7 @@ mod tests {
             &jj,
             "mine() ~ trunk() ~ immutable()",
             "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            None,
         )
         .await
         .unwrap();
@@ -1361,6 +2080,7 @@ mod tests {
             &jj,
             "mine() ~ trunk() ~ immutable()",
             "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            None,
         )
         .await
         .unwrap();
@@ -1422,6 +2142,7 @@ mod tests {
             &jj,
             "mine() ~ trunk() ~ immutable()",
             "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            None,
         )
         .await
         .unwrap();
@@ -1495,6 +2216,7 @@ mod tests {
             &jj,
             "mine() ~ trunk() ~ immutable()",
             "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            None,
         )
         .await
         .unwrap();
@@ -1519,4 +2241,104 @@ mod tests {
         assert_eq!(stack.segments[0].change_id, "ch_mid");
         assert_eq!(stack.segments[1].change_id, "ch_head");
     }
+
+    /// `locate_commit` finds the segment containing any commit of the stack,
+    /// not just the bookmarked one.
+    #[tokio::test]
+    async fn locate_commit_finds_segment() {
+        let runner = MockJjRunner {
+            handler: |args: &[&str]| {
+                if args[0] == "diff" {
+                    return Ok(String::new());
+                }
+                if args[0] == "bookmark" {
+                    let lines = [
+                        bookmark_json("bm_b", "c_b2", "ch_b2"),
+                        bookmark_json("bm_a", "c_a", "ch_a"),
+                    ];
+                    return Ok(lines.join("\n"));
+                }
+
+                let revset = args[2];
+                if revset.contains("c_b2") {
+                    let lines = [
+                        log_entry_json("c_b2", "ch_b2", &["c_b1"], &["bm_b"]),
+                        log_entry_json("c_b1", "ch_b1", &["c_a"], &[]),
+                        log_entry_json("c_a", "ch_a", &["trunk_c"], &["bm_a"]),
+                    ];
+                    return Ok(lines.join("\n"));
+                }
+
+                Ok(String::new())
+            },
+        };
+
+        let jj = Jj::new(runner);
+        let graph = build_change_graph(
+            &jj,
+            "mine() ~ trunk() ~ immutable()",
+            "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            None,
+        )
+        .await
+        .unwrap();
+
+        let (stack, idx) = locate_commit(&graph, "c_b1").unwrap();
+        assert_eq!(idx, 1);
+        assert_eq!(stack.segments[idx].bookmark_names, vec!["bm_b"]);
+
+        let (_, idx) = locate_commit(&graph, "c_a").unwrap();
+        assert_eq!(idx, 0);
+
+        assert!(locate_commit(&graph, "unknown").is_none());
+    }
+
+    /// A conflicted bookmark is not a segment boundary, but is listed in the
+    /// graph and found in the segment holding one of its commits.
+    #[tokio::test]
+    async fn conflicted_bookmark_is_listed_and_located() {
+        let runner = MockJjRunner {
+            handler: |args: &[&str]| {
+                if args[0] == "diff" {
+                    return Ok(String::new());
+                }
+                if args[0] == "bookmark" {
+                    let lines = [
+                        bookmark_json("bm_b", "c_b2", "ch_b2"),
+                        r#"{"name":"bm_x","synced":false,"target":null}"#.to_string(),
+                        bookmark_json("bm_a", "c_a", "ch_a"),
+                    ];
+                    return Ok(lines.join("\n"));
+                }
+
+                let revset = args[2];
+                if revset.contains("c_b2") {
+                    let lines = [
+                        log_entry_json("c_b2", "ch_b2", &["c_b1"], &["bm_b"]),
+                        log_entry_json("c_b1", "ch_b1", &["c_a"], &["bm_x"]),
+                        log_entry_json("c_a", "ch_a", &["trunk_c"], &["bm_a"]),
+                    ];
+                    return Ok(lines.join("\n"));
+                }
+
+                Ok(String::new())
+            },
+        };
+
+        let jj = Jj::new(runner);
+        let graph = build_change_graph(
+            &jj,
+            "mine() ~ trunk() ~ immutable()",
+            "heads((mine() ~ empty() ~ immutable()) & trunk()..)",
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(graph.conflicted_bookmarks, ["bm_x"]);
+        let stack = &graph.stacks[0];
+        assert_eq!(stack.segments.len(), 2);
+        assert_eq!(conflicted_in(&graph, &stack.segments), ["bm_x"]);
+        assert!(conflicted_in(&graph, &stack.segments[..1]).is_empty());
+    }
 }
@@ -26,6 +26,23 @@ pub struct SegmentCommit {
     pub local_bookmark_names: Vec<String>,
 }
 
+/// A remote branch matching one of the trunk patterns, as a stack's root
+/// forks off it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrunkBranch {
+    pub branch: String,
+    /// The remote the branch was found on.
+    pub remote: String,
+}
+
+impl TrunkBranch {
+    /// The branch as a revset: its remote bookmark, e.g. `"develop"@"origin"`.
+    pub fn revset(&self) -> String {
+        let quote = |s: &str| serde_json::to_string(s).expect("strings serialize to JSON");
+        format!("{}@{}", quote(&self.branch), quote(&self.remote))
+    }
+}
+
 /// A group of consecutive commits belonging to one or more bookmarks.
 ///
 /// When multiple bookmarks point at the same change, they share one segment.
@@ -56,57 +73,45 @@ pub struct BranchStack {
 pub struct ChangeGraph {
     /// Child `change_id` → parent `change_id` (toward trunk). Each entry
     /// represents a stacking relationship between two bookmarked changes.
-    #[cfg_attr(
-        not(test),
-        expect(
-            dead_code,
-            reason = "populated during graph construction; exposed for diagnostics"
-        )
-    )]
     pub adjacency_list: HashMap<String, String>,
 
     /// Change IDs that are leaf nodes (no children point to them as parent).
     /// Each leaf defines one stack.
-    #[cfg_attr(
-        not(test),
-        expect(
-            dead_code,
-            reason = "populated during graph construction; exposed for diagnostics"
-        )
-    )]
     pub stack_leaves: HashSet<String>,
 
     /// Change IDs closest to trunk with no parent in the adjacency list.
-    #[cfg_attr(
-        not(test),
-        expect(dead_code, reason = "available for interactive mode or diagnostics")
-    )]
     pub stack_roots: HashSet<String>,
 
     /// Map from `change_id` to its `BookmarkSegment`.
-    #[cfg_attr(
-        not(test),
-        expect(
-            dead_code,
-            reason = "used during graph construction; exposed for diagnostics"
-        )
-    )]
     pub segments: HashMap<String, BookmarkSegment>,
 
     /// Change IDs of merge commits and their descendants, excluded from
     /// stacking.
-    #[cfg_attr(
-        not(test),
-        expect(
-            dead_code,
-            reason = "populated during graph construction; exposed for diagnostics"
-        )
-    )]
     pub tainted_change_ids: HashSet<String>,
 
     /// Number of bookmarks excluded due to merge commits in their history.
     pub excluded_bookmark_count: usize,
 
+    /// Names of the bookmarks excluded due to merge commits in their history.
+    /// Unlike `excluded_bookmark_count`, leaves out unbookmarked heads.
+    pub tainted_bookmarks: Vec<String>,
+
+    /// Bookmarks left out of the graph because a private change lies at or
+    /// below them, mapped to that change's `change_id`. Pushing any of them
+    /// would publish the private change.
+    pub private_bookmarks: HashMap<String, String>,
+
+    /// Bookmarks left out of the graph because they point to several
+    /// commits (e.g. after concurrent updates locally and on the remote).
+    /// Their commits still sit in the stacks; see
+    /// [`conflicted_in`](crate::graph::conflicted_in).
+    pub conflicted_bookmarks: Vec<String>,
+
+    /// Root `change_id` → the branch its stack forks off, for roots that
+    /// attach to a branch matching one of the trunk patterns rather than to
+    /// the trunk. See [`trunk_branch_of`](crate::graph::trunk_branch_of).
+    pub root_trunks: HashMap<String, TrunkBranch>,
+
     /// Complete stacks, one per leaf bookmark, ordered trunk-to-leaf.
     pub stacks: Vec<BranchStack>,
 }
@@ -0,0 +1,84 @@
+//! `stakk handoff`: pass a stack on to someone else.
+//!
+//! Every PR of the stack is assigned to the new owner and gets a comment
+//! telling them how to check the stack out; handing the stack over again
+//! rewrites that comment rather than adding another. With `--release`, the
+//! local bookmarks of the stack stop tracking their branches, which stay on
+//! the remote, so the next submit cannot push over the new owner's work.
+
+use stakk_core::forge::Comment;
+
+/// First line of the handoff comment, by which a later handoff finds it.
+const HANDOFF_MARKER: &str = "<!--- STAKK_HANDOFF --->";
+
+/// The comment posted on each PR of a stack handed to `owner`, whose leaf
+/// is PR `leaf`.
+pub fn handoff_comment(owner: &str, leaf: u64) -> String {
+    format!(
+        "{HANDOFF_MARKER}\nThis stack is now owned by @{owner}.\n\nTo continue it locally, check \
+         out every PR of the stack with\n\n```sh\nstakk open-stack {leaf}\n```\n"
+    )
+}
+
+/// How a PR's handoff comment is brought up to date.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CommentWrite {
+    /// The PR has no handoff comment yet.
+    Create,
+    /// The PR's handoff comment with this ID says something else.
+    Update(u64),
+    /// The PR's handoff comment already says `body`.
+    Keep,
+}
+
+/// What to do to leave `body` as the handoff comment among a PR's
+/// `comments`.
+pub fn comment_write(comments: &[Comment], body: &str) -> CommentWrite {
+    match comments
+        .iter()
+        .find(|c| c.body.lines().next() == Some(HANDOFF_MARKER))
+    {
+        None => CommentWrite::Create,
+        Some(c) if c.body == body => CommentWrite::Keep,
+        Some(c) => CommentWrite::Update(c.id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comment_names_the_owner_and_the_checkout() {
+        let comment = handoff_comment("octocat", 14);
+        assert!(comment.contains("\nThis stack is now owned by @octocat."));
+        assert!(comment.contains("\nstakk open-stack 14\n"));
+    }
+
+    fn comment(id: u64, body: &str) -> Comment {
+        Comment {
+            id,
+            node_id: None,
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn an_earlier_handoff_comment_is_rewritten() {
+        let body = handoff_comment("octocat", 14);
+        let other = comment(1, "LGTM");
+        assert_eq!(
+            comment_write(std::slice::from_ref(&other), &body),
+            CommentWrite::Create
+        );
+        let earlier = comment(2, &handoff_comment("someone", 14));
+        assert_eq!(
+            comment_write(&[other.clone(), earlier], &body),
+            CommentWrite::Update(2)
+        );
+        assert_eq!(
+            comment_write(&[other, comment(3, &body)], &body),
+            CommentWrite::Keep
+        );
+    }
+}
@@ -3,22 +3,45 @@
 //! All VCS operations go through this module by shelling out to `jj`. No direct
 //! git calls, no `git2`, no `gix`. Always pass `--config 'ui.paginate=never'`
 //! to avoid pager issues.
+//!
+//! Read-only queries survive a working copy that jj cannot snapshot by
+//! falling back to `--ignore-working-copy`. Commands that change the
+//! repository or push do not, since they would act on stale contents.
 
 pub mod remote;
 pub mod runner;
+pub mod templates;
 pub mod types;
 pub mod version;
+pub mod workspace;
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 
 use miette::Diagnostic;
 use thiserror::Error;
 
 use crate::jj::runner::JjRunner;
+use crate::jj::runner::RealJjRunner;
+use crate::jj::templates::JjTemplates;
 use crate::jj::types::Bookmark;
 use crate::jj::types::BookmarkEntryRaw;
+use crate::jj::types::ConflictedBookmark;
 use crate::jj::types::GitRemote;
 use crate::jj::types::LogEntry;
 use crate::jj::types::LogEntryRaw;
 use crate::jj::version::JjVersion;
+use crate::query_cache::CacheKey;
+use crate::query_cache::QueryCache;
+
+/// The revset stacks are based on unless overridden.
+pub const DEFAULT_TRUNK: &str = "trunk()";
 
 /// Errors from interacting with `jj`.
 #[derive(Debug, Error, Diagnostic)]
@@ -50,30 +73,455 @@ pub enum JjError {
     )]
     NotFound(std::io::Error),
 
+    /// Reading `jj`'s output or waiting for it to exit failed.
+    #[error("I/O error while running {command}: {source}")]
+    #[diagnostic(code(stakk::jj::io))]
+    Io {
+        command: String,
+        source: std::io::Error,
+    },
+
+    /// `jj` wrote more output than stakk is willing to buffer.
+    #[error("jj produced more than {limit_mib} MiB of output: {command}")]
+    #[diagnostic(
+        code(stakk::jj::output_too_large),
+        help(
+            "narrow --bookmarks-revset or --heads-revset, or raise the limit with --jj-max-output"
+        )
+    )]
+    OutputTooLarge { command: String, limit_mib: usize },
+
+    /// `jj` did not finish in time.
+    #[error("jj did not finish within {seconds}s: {command}")]
+    #[diagnostic(
+        code(stakk::jj::timed_out),
+        help("raise the limit with --jj-timeout, or check whether jj is waiting on a lock")
+    )]
+    TimedOut { command: String, seconds: u64 },
+
     /// Could not determine the default branch.
     #[error("could not determine default branch; candidates: {candidates:?}")]
     #[diagnostic(
         code(stakk::jj::no_default_branch),
-        help("ensure your repo has a trunk bookmark tracked from a remote")
+        help(
+            "ensure your repo has a trunk bookmark tracked from a remote, or name the branch to \
+             stack on with --trunk"
+        )
     )]
     NoDefaultBranch { candidates: Vec<String> },
+
+    /// `jj log -r @` returned no commit.
+    #[error("could not resolve the working-copy commit (@)")]
+    #[diagnostic(
+        code(stakk::jj::no_working_copy),
+        help("run stakk from inside a jj workspace")
+    )]
+    NoWorkingCopy,
+
+    /// None of stakk's template sets works with the installed jj.
+    #[error("stakk's jj templates do not work with jj {version}")]
+    #[diagnostic(
+        code(stakk::jj::unsupported_version),
+        help(
+            "stakk supports jj {} and later; upgrade jj, or stakk if jj is newer than it",
+            version::MIN_SUPPORTED_JJ_VERSION
+        )
+    )]
+    UnsupportedVersion { version: String },
+
+    /// A command that needs the working copy snapshotted could not snapshot
+    /// it.
+    #[error("jj could not snapshot the working copy: {command}\n{stderr}")]
+    #[diagnostic(
+        code(stakk::jj::snapshot_failed),
+        help(
+            "this command changes the repository and needs an up-to-date snapshot; fix what jj \
+             reports above (e.g. a file over `snapshot.max-new-file-size`, or a stale working \
+             copy: `jj workspace update-stale`) and retry"
+        )
+    )]
+    SnapshotFailed { command: String, stderr: String },
+}
+
+/// Whether a failed command's stderr says jj could not snapshot the working
+/// copy (or found it stale), rather than that the command itself failed.
+fn is_snapshot_failure(stderr: &str) -> bool {
+    stderr.contains("Failed to snapshot the working copy")
+        || stderr.contains("The working copy is stale")
 }
 
-// Template for `jj bookmark list`: produces one JSON object per line.
-const BOOKMARK_TEMPLATE: &str = r#""{\"name\":" ++ json(self.name()) ++ ",\"synced\":" ++ json(self.synced()) ++ ",\"target\":" ++ json(self.normal_target()) ++ "}\n""#;
+// Template for `jj bookmark list` when computing the query cache key: one
+// "name commit_id" line per local bookmark.
+const BOOKMARK_TARGETS_TEMPLATE: &str = r#"if(remote, "", name ++ " " ++ if(normal_target, normal_target.commit_id(), "conflicted") ++ "\n")"#;
 
-// Template for `jj log`: produces one JSON object per line with commit +
-// bookmarks + shortest unique change ID prefix.
-const LOG_TEMPLATE: &str = r#""{\"commit\":" ++ json(self) ++ ",\"local_bookmarks\":" ++ json(local_bookmarks) ++ ",\"remote_bookmarks\":" ++ json(remote_bookmarks) ++ ",\"immutable\":" ++ immutable ++ ",\"short_change_id\":\"" ++ change_id.shortest() ++ "\"}\n""#;
+// Template for `jj bookmark list --remote`: one "name commit_id" line per
+// remote bookmark with a single target.
+const REMOTE_TARGETS_TEMPLATE: &str =
+    r#"if(remote, if(normal_target, name ++ " " ++ normal_target.commit_id() ++ "\n"))"#;
+
+/// How [`Jj::push_bookmark`] pushes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "independent push settings, not a state machine"
+)]
+pub struct PushOptions {
+    /// Create branches the remote does not have yet, whatever jj's
+    /// `git.push-new-bookmarks` says. Without it, jj's configuration decides.
+    pub allow_new: bool,
+    /// Push even if the remote branch moved since the submission was
+    /// planned. jj still refuses to overwrite a branch that moved since the
+    /// last fetch.
+    pub force: bool,
+    /// Pass `--dry-run`: jj checks the push and reports what it would do
+    /// without pushing.
+    pub dry_run: bool,
+    /// Track the remote branch after a bookmark's first push, so later
+    /// runs see whether it is in sync whatever jj's auto-tracking settings.
+    pub track: bool,
+}
+
+impl Default for PushOptions {
+    fn default() -> Self {
+        Self {
+            allow_new: true,
+            force: false,
+            dry_run: false,
+            track: true,
+        }
+    }
+}
+
+/// What pushing a bookmark would do to its branch on the remote, from
+/// [`Jj::get_push_size`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PushSize {
+    /// Commits the remote has under no branch yet, which the push sends.
+    pub new_commits: usize,
+    /// Commits of the remote branch that the push drops from it, as a
+    /// force-push after a rewrite does.
+    pub replaced_commits: usize,
+}
+
+impl PushOptions {
+    /// The same options, with jj only checking the push.
+    #[must_use]
+    pub fn dry_run(self) -> Self {
+        Self {
+            dry_run: true,
+            ..self
+        }
+    }
+}
 
 /// Main interface for interacting with `jj`.
 pub struct Jj<R: JjRunner> {
     runner: R,
+    /// Output of read-only queries, dropped by the first command that
+    /// changes the repository.
+    cache: Mutex<Option<QueryCache<String>>>,
+    /// The templates for the installed jj; the newest set until
+    /// [`Self::select_templates`] says otherwise.
+    templates: &'static JjTemplates,
+    /// Set once a read-only command fell back to `--ignore-working-copy`.
+    snapshot_skipped: AtomicBool,
+    /// The stakk command recorded in the operations this changes the
+    /// repository with (see [`Self::with_action`]).
+    action: Option<String>,
+    /// Revset of the commit stacks are based on (see [`Self::with_trunk`]).
+    trunk: String,
+    /// Globs of further long-lived branches stacks may be based on (see
+    /// [`Self::with_trunk_patterns`]).
+    trunk_patterns: Vec<String>,
+    /// The remote trunk pattern branches are read from (see
+    /// [`Self::with_trunk_remote`]); any remote when unset.
+    trunk_remote: Option<String>,
 }
 
 impl<R: JjRunner> Jj<R> {
     pub fn new(runner: R) -> Self {
-        Self { runner }
+        Self {
+            runner,
+            cache: Mutex::new(None),
+            templates: templates::ALL[0],
+            snapshot_skipped: AtomicBool::new(false),
+            action: None,
+            trunk: DEFAULT_TRUNK.to_string(),
+            trunk_patterns: Vec::new(),
+            trunk_remote: None,
+        }
+    }
+
+    /// Record `action` (e.g. `submit`) in every operation that changes the
+    /// repository, as a `stakk.action` config value on the command line.
+    /// jj keeps the command line with the operation, so `jj op log` shows
+    /// which operations a stakk run made, to undo them.
+    #[must_use]
+    pub fn with_action(self, action: impl Into<String>) -> Self {
+        Self {
+            action: Some(action.into()),
+            ..self
+        }
+    }
+
+    /// Base stacks on `trunk`, a revset such as `release/1.2`, instead of
+    /// jj's `trunk()`. The revset must resolve to a single commit with a
+    /// remote bookmark: that bookmark is the default branch.
+    #[must_use]
+    pub fn with_trunk(self, trunk: impl Into<String>) -> Self {
+        Self {
+            trunk: trunk.into(),
+            ..self
+        }
+    }
+
+    /// The revset stacks are based on: `trunk()` unless overridden with
+    /// [`Self::with_trunk`].
+    pub fn trunk(&self) -> &str {
+        &self.trunk
+    }
+
+    /// Also base stacks on the remote branches matching `patterns` (globs
+    /// such as `develop` or `release/*`), for repositories with several
+    /// mainlines. A stack then ends where it forks off any of them.
+    #[must_use]
+    pub fn with_trunk_patterns(self, patterns: Vec<String>) -> Self {
+        Self {
+            trunk_patterns: patterns,
+            ..self
+        }
+    }
+
+    /// The globs set with [`Self::with_trunk_patterns`].
+    pub fn trunk_patterns(&self) -> &[String] {
+        &self.trunk_patterns
+    }
+
+    /// Only take the branches matching the trunk patterns from `remote`,
+    /// the one stakk pushes to, so that a stack never targets a branch of
+    /// another remote's repository.
+    #[must_use]
+    pub fn with_trunk_remote(self, remote: impl Into<String>) -> Self {
+        Self {
+            trunk_remote: Some(remote.into()),
+            ..self
+        }
+    }
+
+    /// The remote set with [`Self::with_trunk_remote`].
+    pub fn trunk_remote(&self) -> Option<&str> {
+        self.trunk_remote.as_deref()
+    }
+
+    /// Revset of the commits on the remote branches matching the trunk
+    /// patterns, or `None` without patterns.
+    pub fn pattern_trunks(&self) -> Option<String> {
+        if self.trunk_patterns.is_empty() {
+            return None;
+        }
+        let heads: Vec<String> = self
+            .trunk_patterns
+            .iter()
+            .map(|pattern| match &self.trunk_remote {
+                Some(remote) => format!(
+                    "remote_bookmarks(glob:{}, exact:{})",
+                    revset_string(pattern),
+                    revset_string(remote)
+                ),
+                None => format!("remote_bookmarks(glob:{})", revset_string(pattern)),
+            })
+            .collect();
+        Some(format!("({})", heads.join(" | ")))
+    }
+
+    /// Revset of everything a stack ends at: the trunk and, with trunk
+    /// patterns, the branches matching them.
+    pub fn stack_base(&self) -> String {
+        match self.pattern_trunks() {
+            Some(heads) => format!("({} | {heads})", self.trunk),
+            None => self.trunk.clone(),
+        }
+    }
+
+    /// Render queries with `templates` instead of the newest set.
+    #[must_use]
+    pub fn with_templates(self, templates: &'static JjTemplates) -> Self {
+        Self { templates, ..self }
+    }
+
+    /// The first template set written for `version` that jj accepts, newest
+    /// first.
+    ///
+    /// Each candidate is probed by listing no bookmarks and logging the root
+    /// commit with its templates; a set passes when jj runs them and the log
+    /// output parses. Errors other than a failed or unparseable probe (jj
+    /// missing, timed out) are returned as-is.
+    pub async fn select_templates(
+        &self,
+        version: Option<JjVersion>,
+    ) -> Result<&'static JjTemplates, JjError> {
+        for candidate in templates::candidates(version) {
+            match self.probe_templates(candidate).await {
+                Ok(()) => return Ok(candidate),
+                Err(JjError::CommandFailed { .. } | JjError::ParseError { .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Err(JjError::UnsupportedVersion {
+            version: version.map_or_else(|| "(unknown version)".to_string(), |v| v.to_string()),
+        })
+    }
+
+    async fn probe_templates(&self, templates: &JjTemplates) -> Result<(), JjError> {
+        let list = ["--ignore-working-copy", "bookmark", "list", "-r", "none()"];
+        self.runner
+            .run_jj(&[&list[..], &["-T", templates.bookmark]].concat())
+            .await?;
+        self.runner
+            .run_jj(
+                &[
+                    &list[..],
+                    &["--conflicted", "-T", templates.conflicted_bookmark],
+                ]
+                .concat(),
+            )
+            .await?;
+        let output = self
+            .runner
+            .run_jj(&[
+                "--ignore-working-copy",
+                "log",
+                "-r",
+                "root()",
+                "--no-graph",
+                "-T",
+                templates.log,
+            ])
+            .await?;
+        parse_log_entries(&output)?;
+        Ok(())
+    }
+
+    /// Answer read-only queries from `cache` when it has them.
+    #[must_use]
+    pub fn with_query_cache(self, cache: QueryCache<String>) -> Self {
+        Self {
+            cache: Mutex::new(Some(cache)),
+            ..self
+        }
+    }
+
+    /// The query cache with everything queried so far, or `None` if caching
+    /// is off or the repository has changed since it was loaded.
+    pub fn query_cache(&self) -> Option<QueryCache<String>> {
+        self.cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Key identifying the current repository state: the op log head and
+    /// every local bookmark's target.
+    ///
+    /// The bookmarks are listed first so that jj snapshots the working copy
+    /// before the op log head is read.
+    pub async fn query_cache_key(&self) -> Result<CacheKey, JjError> {
+        let targets = self
+            .run_read(&["bookmark", "list", "-T", BOOKMARK_TARGETS_TEMPLATE])
+            .await?;
+        let operation_id = self
+            .run_read(&[
+                "op",
+                "log",
+                "--no-graph",
+                "--limit",
+                "1",
+                "-T",
+                r#"id ++ "\n""#,
+            ])
+            .await?;
+        Ok(CacheKey {
+            operation_id: operation_id.trim().to_string(),
+            bookmark_targets: targets
+                .lines()
+                .filter_map(|line| line.trim().split_once(' '))
+                .map(|(name, target)| (name.to_string(), target.to_string()))
+                .collect(),
+        })
+    }
+
+    /// Run a read-only `jj` command, through the query cache if there is
+    /// one.
+    async fn query(&self, args: &[&str]) -> Result<String, JjError> {
+        let query = args.join("\0");
+        let cached = self
+            .cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .and_then(|cache| cache.get(&query).cloned());
+        if let Some(output) = cached {
+            return Ok(output);
+        }
+
+        let output = self.run_read(args).await?;
+        if let Some(cache) = self
+            .cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_mut()
+        {
+            cache.insert(query, output.clone());
+        }
+        Ok(output)
+    }
+
+    /// Run a read-only `jj` command. If jj cannot snapshot the working copy
+    /// (a conflicted or oversized file, a stale workspace), the command is
+    /// retried with `--ignore-working-copy` and answered from the last
+    /// snapshot; [`Self::snapshot_skipped`] reports that this happened.
+    async fn run_read(&self, args: &[&str]) -> Result<String, JjError> {
+        match self.runner.run_jj(args).await {
+            Err(JjError::CommandFailed { stderr, .. }) if is_snapshot_failure(&stderr) => {
+                self.snapshot_skipped.store(true, Ordering::Relaxed);
+                self.runner
+                    .run_jj(&[&["--ignore-working-copy"], args].concat())
+                    .await
+            }
+            result => result,
+        }
+    }
+
+    /// Run a `jj` command that needs an up-to-date snapshot of the working
+    /// copy, because it changes the repository or pushes from it. A failed
+    /// snapshot is reported as [`JjError::SnapshotFailed`].
+    async fn run_with_snapshot(&self, args: &[&str]) -> Result<String, JjError> {
+        let tag = self
+            .action
+            .as_ref()
+            .map(|action| format!("stakk.action={}", toml_string(action)));
+        let args = match &tag {
+            Some(tag) => [&["--config", tag.as_str()], args].concat(),
+            None => args.to_vec(),
+        };
+        self.runner.run_jj(&args).await.map_err(|e| match e {
+            JjError::CommandFailed { command, stderr } if is_snapshot_failure(&stderr) => {
+                JjError::SnapshotFailed { command, stderr }
+            }
+            e => e,
+        })
+    }
+
+    /// Whether a read-only command had to skip snapshotting the working
+    /// copy, so that what was read may miss its latest changes.
+    pub fn snapshot_skipped(&self) -> bool {
+        self.snapshot_skipped.load(Ordering::Relaxed)
+    }
+
+    /// Stop caching: the command about to run moves the op log head.
+    fn drop_query_cache(&self) {
+        *self.cache.lock().unwrap_or_else(PoisonError::into_inner) = None;
     }
 
     /// Query and parse `jj --version`.
@@ -88,12 +536,43 @@ impl<R: JjRunner> Jj<R> {
 
     /// List bookmarks matching the given revset.
     pub async fn get_my_bookmarks(&self, revset: &str) -> Result<Vec<Bookmark>, JjError> {
+        parse_bookmarks(&self.list_bookmarks(revset).await?)
+    }
+
+    /// The names of the bookmarks matching the given revset that have
+    /// conflicting targets, which [`Self::get_my_bookmarks`] leaves out.
+    pub async fn get_my_conflicted_bookmarks(&self, revset: &str) -> Result<Vec<String>, JjError> {
+        parse_conflicted_bookmark_names(&self.list_bookmarks(revset).await?)
+    }
+
+    async fn list_bookmarks(&self, revset: &str) -> Result<String, JjError> {
+        self.query(&[
+            "bookmark",
+            "list",
+            "-r",
+            revset,
+            "-T",
+            self.templates.bookmark,
+        ])
+        .await
+    }
+
+    /// List local bookmarks that point to more than one commit.
+    ///
+    /// These have no normal target, so [`Self::get_my_bookmarks`] leaves them
+    /// out of the change graph until they are resolved.
+    pub async fn get_conflicted_bookmarks(&self) -> Result<Vec<ConflictedBookmark>, JjError> {
         let output = self
-            .runner
-            .run_jj(&["bookmark", "list", "-r", revset, "-T", BOOKMARK_TEMPLATE])
+            .query(&[
+                "bookmark",
+                "list",
+                "--conflicted",
+                "-T",
+                self.templates.conflicted_bookmark,
+            ])
             .await?;
 
-        parse_bookmarks(&output)
+        parse_conflicted_bookmarks(&output)
     }
 
     /// Get log entries for a revision range, paginated.
@@ -109,8 +588,7 @@ impl<R: JjRunner> Jj<R> {
         };
 
         let output = self
-            .runner
-            .run_jj(&[
+            .query(&[
                 "log",
                 "-r",
                 &revset,
@@ -118,7 +596,7 @@ impl<R: JjRunner> Jj<R> {
                 "--limit",
                 "100",
                 "-T",
-                LOG_TEMPLATE,
+                self.templates.log,
             ])
             .await?;
 
@@ -127,23 +605,23 @@ impl<R: JjRunner> Jj<R> {
 
     /// List git remotes.
     pub async fn get_git_remote_list(&self) -> Result<Vec<GitRemote>, JjError> {
-        let output = self.runner.run_jj(&["git", "remote", "list"]).await?;
+        let output = self.query(&["git", "remote", "list"]).await?;
         Ok(parse_git_remote_list(&output))
     }
 
-    /// Detect the default branch name from `trunk()`.
+    /// Detect the default branch name from the trunk revset (see
+    /// [`Self::trunk`]).
     pub async fn get_default_branch(&self) -> Result<String, JjError> {
         let output = self
-            .runner
-            .run_jj(&[
+            .query(&[
                 "log",
                 "-r",
-                "trunk()",
+                &self.trunk,
                 "--no-graph",
                 "--limit",
                 "1",
                 "-T",
-                LOG_TEMPLATE,
+                self.templates.log,
             ])
             .await?;
 
@@ -177,18 +655,418 @@ impl<R: JjRunner> Jj<R> {
         }
     }
 
-    /// Push a bookmark to a remote.
-    pub async fn push_bookmark(&self, bookmark: &str, remote: &str) -> Result<(), JjError> {
-        self.runner
-            .run_jj(&["git", "push", "--remote", remote, "--bookmark", bookmark])
+    /// Resolve the working-copy commit (`@`) to its log entry.
+    ///
+    /// The entry carries the change ID, parents, and local bookmarks, which
+    /// callers use to locate the working copy's segment in the change graph.
+    pub async fn get_working_copy(&self) -> Result<LogEntry, JjError> {
+        let output = self
+            .query(&[
+                "log",
+                "-r",
+                "@",
+                "--no-graph",
+                "--limit",
+                "1",
+                "-T",
+                self.templates.log,
+            ])
+            .await?;
+
+        parse_log_entries(&output)?
+            .into_iter()
+            .next()
+            .ok_or(JjError::NoWorkingCopy)
+    }
+
+    /// Root directory of the current workspace (`jj root`).
+    pub async fn get_workspace_root(&self) -> Result<PathBuf, JjError> {
+        let output = self.runner.run_jj(&["root"]).await?;
+        Ok(PathBuf::from(output.trim()))
+    }
+
+    /// Add a workspace named `name` at `path`, with a new empty working-copy
+    /// change on top of `revision`.
+    pub async fn add_workspace(
+        &self,
+        name: &str,
+        path: &Path,
+        revision: &str,
+    ) -> Result<(), JjError> {
+        self.drop_query_cache();
+        let path = path.to_string_lossy();
+        self.run_with_snapshot(&["workspace", "add", "--name", name, "-r", revision, &path])
+            .await?;
+        Ok(())
+    }
+
+    /// Stop tracking a workspace. jj abandons its working-copy change if it
+    /// is still empty; the files on disk are left for the caller to remove.
+    pub async fn forget_workspace(&self, name: &str) -> Result<(), JjError> {
+        self.drop_query_cache();
+        self.run_with_snapshot(&["workspace", "forget", name])
+            .await?;
+        Ok(())
+    }
+
+    /// Push a bookmark to a remote. [`PushOptions::force`] is left to the
+    /// caller, which knows what the remote branch was expected to be.
+    pub async fn push_bookmark(
+        &self,
+        bookmark: &str,
+        remote: &str,
+        options: PushOptions,
+    ) -> Result<(), JjError> {
+        let mut args = Vec::new();
+        if options.allow_new {
+            args.extend(["--config", "git.push-new-bookmarks=true"]);
+        }
+        args.extend(["git", "push", "--remote", remote, "--bookmark", bookmark]);
+        if options.dry_run {
+            args.push("--dry-run");
+        } else {
+            self.drop_query_cache();
+        }
+        self.run_with_snapshot(&args).await?;
+        Ok(())
+    }
+
+    /// Fetch the branches `names` from a remote, or every branch if `names`
+    /// is empty, updating their remote-tracking bookmarks. A branch deleted
+    /// on the remote loses its remote-tracking bookmark.
+    pub async fn fetch_branches(&self, remote: &str, names: &[&str]) -> Result<(), JjError> {
+        self.drop_query_cache();
+        let mut args = vec!["git", "fetch", "--remote", remote];
+        for name in names {
+            args.extend(["--branch", name]);
+        }
+        self.run_with_snapshot(&args).await?;
+        Ok(())
+    }
+
+    /// Track the remote bookmark `name@remote`, so that pushes of the local
+    /// bookmark `name` update it. If the local bookmark points elsewhere it
+    /// becomes conflicted until it is set again.
+    pub async fn track_bookmark(&self, name: &str, remote: &str) -> Result<(), JjError> {
+        self.drop_query_cache();
+        let remote_bookmark = format!("{name}@{remote}");
+        self.run_with_snapshot(&["bookmark", "track", &remote_bookmark])
+            .await?;
+        Ok(())
+    }
+
+    /// Stop tracking the remote bookmark `name@remote`. The local bookmark
+    /// stays, but pushes of it no longer update the branch on the remote,
+    /// and fetches no longer move it.
+    pub async fn untrack_bookmark(&self, name: &str, remote: &str) -> Result<(), JjError> {
+        self.drop_query_cache();
+        let remote_bookmark = format!("{name}@{remote}");
+        self.run_with_snapshot(&["bookmark", "untrack", &remote_bookmark])
+            .await?;
+        Ok(())
+    }
+
+    /// Delete a local bookmark. The deletion reaches the remote on the next
+    /// `push_bookmark` for the same name.
+    pub async fn delete_bookmark(&self, name: &str) -> Result<(), JjError> {
+        self.drop_query_cache();
+        self.run_with_snapshot(&["bookmark", "delete", name])
+            .await?;
+        Ok(())
+    }
+
+    /// Abandon the given commits. jj rebases their descendants onto their
+    /// parents.
+    pub async fn abandon(&self, commit_ids: &[String]) -> Result<(), JjError> {
+        self.drop_query_cache();
+        let mut args = vec!["abandon"];
+        args.extend(commit_ids.iter().map(String::as_str));
+        self.run_with_snapshot(&args).await?;
+        Ok(())
+    }
+
+    /// Rebase `source` and its descendants onto `destination`.
+    pub async fn rebase(&self, source: &str, destination: &str) -> Result<(), JjError> {
+        self.drop_query_cache();
+        self.run_with_snapshot(&["rebase", "-s", source, "-d", destination])
+            .await?;
+        Ok(())
+    }
+
+    /// Replace the description of `revision` with `message`.
+    pub async fn describe(&self, revision: &str, message: &str) -> Result<(), JjError> {
+        self.drop_query_cache();
+        self.run_with_snapshot(&["describe", revision, "-m", message])
+            .await?;
+        Ok(())
+    }
+
+    /// Duplicate `commit_ids` onto `destination`. The copies keep the
+    /// parent relations among the duplicated commits and get new change IDs.
+    pub async fn duplicate(&self, commit_ids: &[String], destination: &str) -> Result<(), JjError> {
+        self.drop_query_cache();
+        let mut args = vec!["duplicate"];
+        args.extend(commit_ids.iter().map(String::as_str));
+        args.extend(["-d", destination]);
+        self.run_with_snapshot(&args).await?;
+        Ok(())
+    }
+
+    /// Make `revision` the working-copy commit (`jj edit`).
+    pub async fn edit(&self, revision: &str) -> Result<(), JjError> {
+        self.drop_query_cache();
+        self.run_with_snapshot(&["edit", revision]).await?;
+        Ok(())
+    }
+
+    /// Start a new working-copy change on top of `revision` (`jj new`).
+    pub async fn new_on(&self, revision: &str) -> Result<(), JjError> {
+        self.drop_query_cache();
+        self.run_with_snapshot(&["new", revision]).await?;
+        Ok(())
+    }
+
+    /// Start a new working-copy change between `revision` and its children
+    /// (`jj new --insert-after`).
+    pub async fn new_after(&self, revision: &str) -> Result<(), JjError> {
+        self.drop_query_cache();
+        self.run_with_snapshot(&["new", "--insert-after", revision])
+            .await?;
+        Ok(())
+    }
+
+    /// The latest operation ID and the change the working copy was on as of
+    /// that operation. Both are read without snapshotting, so that watching
+    /// the operation log never records operations of its own.
+    pub async fn watch_working_copy(&self) -> Result<(String, String), JjError> {
+        let operation = self
+            .runner
+            .run_jj(&[
+                "--ignore-working-copy",
+                "op",
+                "log",
+                "--no-graph",
+                "-n",
+                "1",
+                "-T",
+                r#"id ++ "\n""#,
+            ])
+            .await?;
+        let change = self
+            .runner
+            .run_jj(&[
+                "--ignore-working-copy",
+                "log",
+                "-r",
+                "@",
+                "--no-graph",
+                "-T",
+                r#"change_id ++ "\n""#,
+            ])
+            .await?;
+        Ok((operation.trim().to_string(), change.trim().to_string()))
+    }
+
+    /// The ID of the repository's current operation, for restoring it with
+    /// [`Self::restore_operation`].
+    pub async fn current_operation(&self) -> Result<String, JjError> {
+        let output = self
+            .query(&["op", "log", "--no-graph", "-n", "1", "-T", r#"id ++ "\n""#])
+            .await?;
+        Ok(output.trim().to_string())
+    }
+
+    /// Put the repository back the way it was at operation `id`, undoing
+    /// every operation since.
+    pub async fn restore_operation(&self, id: &str) -> Result<(), JjError> {
+        self.drop_query_cache();
+        self.run_with_snapshot(&["op", "restore", id]).await?;
+        Ok(())
+    }
+
+    /// Log entries for the commits in `revset` that have conflicts, newest
+    /// first.
+    pub async fn get_conflicts(&self, revset: &str) -> Result<Vec<LogEntry>, JjError> {
+        self.get_log(&format!("conflicts() & ({revset})")).await
+    }
+
+    /// The files with conflicts in `revision`.
+    pub async fn get_conflicted_files(&self, revision: &str) -> Result<Vec<String>, JjError> {
+        let output = self.query(&["resolve", "--list", "-r", revision]).await?;
+        Ok(parse_conflicted_files(&output))
+    }
+
+    /// Whether `name` has a remote bookmark on `remote`, i.e. whether there is
+    /// a branch for a push to delete.
+    pub async fn has_remote_bookmark(&self, name: &str, remote: &str) -> Result<bool, JjError> {
+        let output = self
+            .query(&[
+                "bookmark",
+                "list",
+                "--remote",
+                remote,
+                name,
+                "-T",
+                r#"if(remote, name ++ "\n")"#,
+            ])
+            .await?;
+        Ok(output.lines().any(|line| line.trim() == name))
+    }
+
+    /// How far the local bookmark `name` has moved from its branch on
+    /// `remote`: the number of commits only the local side has, and the
+    /// number only the remote side has. `None` if `name` has never been
+    /// pushed to `remote`.
+    pub async fn get_remote_divergence(
+        &self,
+        name: &str,
+        remote: &str,
+    ) -> Result<Option<(usize, usize)>, JjError> {
+        if !self.has_remote_bookmark(name, remote).await? {
+            return Ok(None);
+        }
+        let local = format!("bookmarks(exact:{})", revset_string(name));
+        let remote = format!(
+            "remote_bookmarks(exact:{}, exact:{})",
+            revset_string(name),
+            revset_string(remote)
+        );
+        let (ahead_revset, behind_revset) =
+            (format!("{remote}..{local}"), format!("{local}..{remote}"));
+        let (ahead, behind) = futures::try_join!(
+            self.get_commit_ids(&ahead_revset),
+            self.get_commit_ids(&behind_revset),
+        )?;
+        Ok(Some((ahead.len(), behind.len())))
+    }
+
+    /// What pushing the bookmark `name` to `remote` would do: the commits it
+    /// sends that no branch on `remote` has, and the commits it drops from
+    /// the branch `name` there.
+    pub async fn get_push_size(&self, name: &str, remote: &str) -> Result<PushSize, JjError> {
+        let local = format!("bookmarks(exact:{})", revset_string(name));
+        let on_remote = format!("remote_bookmarks(remote=exact:{})", revset_string(remote));
+        let branch = format!(
+            "remote_bookmarks(exact:{}, exact:{})",
+            revset_string(name),
+            revset_string(remote)
+        );
+        let (new_revset, replaced_revset) = (
+            format!("{on_remote}..{local}"),
+            format!("{local}..{branch}"),
+        );
+        let (new, replaced) = futures::try_join!(
+            self.get_commit_ids(&new_revset),
+            self.get_commit_ids(&replaced_revset),
+        )?;
+        Ok(PushSize {
+            new_commits: new.len(),
+            replaced_commits: replaced.len(),
+        })
+    }
+
+    /// The commits pushing the bookmark `name` to `remote` would drop from
+    /// the branch there that the local bookmark has no version of: commits
+    /// that reached the branch from elsewhere (e.g. someone else's fixups),
+    /// rather than ones rewritten locally since the last push. Newest first.
+    pub async fn get_clobbered_commits(
+        &self,
+        name: &str,
+        remote: &str,
+    ) -> Result<Vec<LogEntry>, JjError> {
+        let local = format!("bookmarks(exact:{})", revset_string(name));
+        let branch = format!(
+            "remote_bookmarks(exact:{}, exact:{})",
+            revset_string(name),
+            revset_string(remote)
+        );
+        let (dropped_revset, local_revset) =
+            (format!("{local}..{branch}"), format!("{branch}..{local}"));
+        let (dropped, local_only) =
+            futures::try_join!(self.get_log(&dropped_revset), self.get_log(&local_revset),)?;
+        let rewritten: HashSet<&str> = local_only.iter().map(|e| e.change_id.as_str()).collect();
+        Ok(dropped
+            .into_iter()
+            .filter(|entry| !rewritten.contains(entry.change_id.as_str()))
+            .collect())
+    }
+
+    /// Names of the bookmarks matching `revset` whose local target is
+    /// already on `remote`, i.e. bookmarks a push would leave unchanged.
+    ///
+    /// `synced` alone is not enough: it is also true for a bookmark that has
+    /// never been pushed, and only covers tracked remotes. The local target is
+    /// therefore compared against the remote bookmark's commit as well.
+    pub async fn get_synced_bookmarks(
+        &self,
+        revset: &str,
+        remote: &str,
+    ) -> Result<HashSet<String>, JjError> {
+        let bookmarks = self.get_my_bookmarks(revset).await?;
+        let remote_targets = self.get_remote_targets(remote).await?;
+
+        Ok(bookmarks
+            .into_iter()
+            .filter(|b| b.synced && remote_targets.get(&b.name) == Some(&b.commit_id))
+            .map(|b| b.name)
+            .collect())
+    }
+
+    /// The commit each bookmark on `remote` points to, by bookmark name, as
+    /// of the last fetch or push. Conflicted remote bookmarks are left out.
+    pub async fn get_remote_targets(
+        &self,
+        remote: &str,
+    ) -> Result<HashMap<String, String>, JjError> {
+        let output = self
+            .query(&[
+                "bookmark",
+                "list",
+                "--remote",
+                remote,
+                "-T",
+                REMOTE_TARGETS_TEMPLATE,
+            ])
+            .await?;
+        Ok(parse_remote_targets(&output))
+    }
+
+    /// The commit the remote-tracking bookmark `name@remote` points to now,
+    /// read past the query cache so that a fetch by another process since
+    /// the cache was loaded is seen. `None` if it does not exist.
+    pub async fn get_current_remote_target(
+        &self,
+        name: &str,
+        remote: &str,
+    ) -> Result<Option<String>, JjError> {
+        let output = self
+            .runner
+            .run_jj(&[
+                "bookmark",
+                "list",
+                "--remote",
+                remote,
+                name,
+                "-T",
+                REMOTE_TARGETS_TEMPLATE,
+            ])
+            .await?;
+        Ok(parse_remote_targets(&output).remove(name))
+    }
+
+    /// Create a bookmark on a specific revision.
+    pub async fn create_bookmark(&self, name: &str, revision: &str) -> Result<(), JjError> {
+        self.drop_query_cache();
+        self.run_with_snapshot(&["bookmark", "create", name, "-r", revision])
             .await?;
         Ok(())
     }
 
-    /// Create a bookmark on a specific revision.
-    pub async fn create_bookmark(&self, name: &str, revision: &str) -> Result<(), JjError> {
-        self.runner
-            .run_jj(&["bookmark", "create", name, "-r", revision])
+    /// Point an existing bookmark at a revision, even if that moves it
+    /// backwards or sideways (as when resolving a conflict).
+    pub async fn set_bookmark(&self, name: &str, revision: &str) -> Result<(), JjError> {
+        self.drop_query_cache();
+        self.run_with_snapshot(&["bookmark", "set", name, "-r", revision, "--allow-backwards"])
             .await?;
         Ok(())
     }
@@ -199,8 +1077,7 @@ impl<R: JjRunner> Jj<R> {
     /// Used to discover unbookmarked changes beyond the last bookmark.
     pub async fn get_heads(&self, revset: &str) -> Result<Vec<LogEntry>, JjError> {
         let output = self
-            .runner
-            .run_jj(&[
+            .query(&[
                 "log",
                 "-r",
                 revset,
@@ -208,17 +1085,46 @@ impl<R: JjRunner> Jj<R> {
                 "--limit",
                 "100",
                 "-T",
-                LOG_TEMPLATE,
+                self.templates.log,
             ])
             .await?;
         parse_log_entries(&output)
     }
 
+    /// Log entries for every commit matching the given revset, newest
+    /// first.
+    pub async fn get_log(&self, revset: &str) -> Result<Vec<LogEntry>, JjError> {
+        let output = self
+            .query(&["log", "-r", revset, "--no-graph", "-T", self.templates.log])
+            .await?;
+        parse_log_entries(&output)
+    }
+
+    /// Commit IDs of all commits matching the given revset.
+    pub async fn get_commit_ids(&self, revset: &str) -> Result<HashSet<String>, JjError> {
+        let output = self
+            .query(&[
+                "log",
+                "-r",
+                revset,
+                "--no-graph",
+                "-T",
+                r#"commit_id ++ "\n""#,
+            ])
+            .await?;
+
+        Ok(output
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(String::from)
+            .collect())
+    }
+
     /// Get the list of files changed by a specific commit.
     pub async fn get_diff_files(&self, commit_id: &str) -> Result<Vec<String>, JjError> {
         let output = self
-            .runner
-            .run_jj(&["diff", "-r", commit_id, "--name-only"])
+            .query(&["diff", "-r", commit_id, "--name-only"])
             .await?;
 
         Ok(output
@@ -230,9 +1136,49 @@ impl<R: JjRunner> Jj<R> {
     }
 }
 
-fn parse_bookmarks(output: &str) -> Result<Vec<Bookmark>, JjError> {
-    let mut seen = std::collections::HashSet::new();
-    let mut bookmarks = Vec::new();
+impl Jj<RealJjRunner> {
+    /// Run `jj resolve` on `revision` in the terminal, so the user can fix
+    /// its conflicts with their merge tool.
+    pub async fn resolve_interactively(&self, revision: &str) -> Result<(), JjError> {
+        self.drop_query_cache();
+        self.runner
+            .run_jj_interactive(&["resolve", "-r", revision])
+            .await
+    }
+
+    /// Show the diff from `from` to `to` in the terminal, with the diff
+    /// `tool` when one is given (`jj diff --tool`).
+    pub async fn diff_interactively(
+        &self,
+        from: &str,
+        to: &str,
+        tool: Option<&str>,
+    ) -> Result<(), JjError> {
+        let mut args = vec!["diff", "--from", from, "--to", to];
+        if let Some(tool) = tool {
+            args.extend(["--tool", tool]);
+        }
+        self.runner.run_jj_interactive(&args).await
+    }
+}
+
+/// `value` as a revset string literal. jj's escapes are a superset of JSON's.
+fn revset_string(value: &str) -> String {
+    serde_json::to_string(value).expect("strings serialize to JSON")
+}
+
+/// `value` as a TOML string, for `--config`. Every JSON escape is valid in
+/// a TOML basic string.
+fn toml_string(value: &str) -> String {
+    serde_json::to_string(value).expect("strings serialize to JSON")
+}
+
+/// The local entries of `jj bookmark list` output. When a bookmark is
+/// unsynced, jj emits separate entries for the local and remote tracking
+/// targets; only the first (local) one is kept.
+fn parse_local_bookmark_entries(output: &str) -> Result<Vec<BookmarkEntryRaw>, JjError> {
+    let mut seen = HashSet::new();
+    let mut entries = Vec::new();
     for line in output.lines() {
         let line = line.trim();
         if line.is_empty() {
@@ -243,22 +1189,60 @@ fn parse_bookmarks(output: &str) -> Result<Vec<Bookmark>, JjError> {
                 context: "bookmark list".to_string(),
                 source: e,
             })?;
-        // When a bookmark is unsynced, jj emits separate entries for the local
-        // and remote tracking targets. Keep only the first (local) entry.
-        if !seen.insert(raw.name.clone()) {
-            continue;
+        if seen.insert(raw.name.clone()) {
+            entries.push(raw);
         }
-        // Skip conflicted bookmarks (no normal target).
-        if let Some(target) = raw.target {
-            bookmarks.push(Bookmark {
+    }
+    Ok(entries)
+}
+
+/// Bookmarks with a normal target. Conflicted bookmarks are listed by
+/// [`parse_conflicted_bookmark_names`] instead.
+fn parse_bookmarks(output: &str) -> Result<Vec<Bookmark>, JjError> {
+    Ok(parse_local_bookmark_entries(output)?
+        .into_iter()
+        .filter_map(|raw| {
+            let target = raw.target?;
+            Some(Bookmark {
                 name: raw.name,
                 commit_id: target.commit_id,
                 change_id: target.change_id,
                 synced: raw.synced,
-            });
-        }
-    }
-    Ok(bookmarks)
+            })
+        })
+        .collect())
+}
+
+/// The names of the bookmarks without a normal target: those pointing to
+/// several commits.
+fn parse_conflicted_bookmark_names(output: &str) -> Result<Vec<String>, JjError> {
+    Ok(parse_local_bookmark_entries(output)?
+        .into_iter()
+        .filter(|raw| raw.target.is_none())
+        .map(|raw| raw.name)
+        .collect())
+}
+
+fn parse_remote_targets(output: &str) -> HashMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| line.trim().split_once(' '))
+        .map(|(name, target)| (name.to_string(), target.to_string()))
+        .collect()
+}
+
+fn parse_conflicted_bookmarks(output: &str) -> Result<Vec<ConflictedBookmark>, JjError> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            serde_json::from_str(line).map_err(|e| JjError::ParseError {
+                context: "conflicted bookmark list".to_string(),
+                source: e,
+            })
+        })
+        .collect()
 }
 
 fn parse_log_entries(output: &str) -> Result<Vec<LogEntry>, JjError> {
@@ -295,6 +1279,22 @@ fn parse_log_entries(output: &str) -> Result<Vec<LogEntry>, JjError> {
     Ok(entries)
 }
 
+/// The paths in `jj resolve --list` output, whose lines are a path, padding,
+/// and a description such as `2-sided conflict`.
+fn parse_conflicted_files(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let path = match line.find("-sided conflict") {
+                Some(end) => line[..end].trim_end_matches(|c: char| c.is_ascii_digit()),
+                None => line,
+            };
+            let path = path.trim();
+            (!path.is_empty()).then(|| path.to_string())
+        })
+        .collect()
+}
+
 fn parse_git_remote_list(output: &str) -> Vec<GitRemote> {
     output
         .lines()
@@ -348,6 +1348,10 @@ mod tests {
         let input = r#"{"name":"conflict","synced":false,"target":null}"#;
         let bookmarks = parse_bookmarks(input).unwrap();
         assert!(bookmarks.is_empty());
+        assert_eq!(
+            parse_conflicted_bookmark_names(input).unwrap(),
+            ["conflict"]
+        );
     }
 
     #[test]
@@ -369,6 +1373,32 @@ mod tests {
         assert_eq!(bookmarks[0].commit_id, "new");
     }
 
+    // -- parse_conflicted_bookmarks tests --
+
+    #[test]
+    fn parse_conflicted_bookmarks_lists_targets() {
+        let input = concat!(
+            r#"{"name":"feat","targets":[{"commit_id":"111","parents":[],"change_id":"aaa","description":"one\n","author":{"name":"A","email":"a@b.c","timestamp":"T"},"committer":{"name":"A","email":"a@b.c","timestamp":"T"}},{"commit_id":"222","parents":[],"change_id":"bbb","description":"two\n","author":{"name":"A","email":"a@b.c","timestamp":"T"},"committer":{"name":"A","email":"a@b.c","timestamp":"T"}}]}"#,
+            "\n",
+        );
+        let conflicted = parse_conflicted_bookmarks(input).unwrap();
+        assert_eq!(conflicted.len(), 1);
+        assert_eq!(conflicted[0].name, "feat");
+        let ids: Vec<_> = conflicted[0].targets.iter().map(|t| &t.commit_id).collect();
+        assert_eq!(ids, ["111", "222"]);
+    }
+
+    #[test]
+    fn parse_conflicted_bookmarks_empty_input() {
+        assert!(parse_conflicted_bookmarks("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn revset_string_quotes_and_escapes() {
+        assert_eq!(revset_string("feat/x"), r#""feat/x""#);
+        assert_eq!(revset_string(r#"a"b"#), r#""a\"b""#);
+    }
+
     // -- parse_log_entries tests --
 
     #[test]
@@ -441,6 +1471,16 @@ mod tests {
         assert_eq!(remotes[1].url, "https://github.com/other/repo.git");
     }
 
+    #[test]
+    fn parse_conflicted_files_strips_descriptions() {
+        let output = "src/main.rs    2-sided conflict\ndocs/a b.md    3-sided conflict including \
+                      1 deletion\n";
+        assert_eq!(
+            parse_conflicted_files(output),
+            ["src/main.rs", "docs/a b.md"]
+        );
+    }
+
     #[test]
     fn parse_git_remote_list_empty() {
         let remotes = parse_git_remote_list("");
@@ -462,6 +1502,76 @@ mod tests {
         }
     }
 
+    fn snapshot_error(args: &[&str]) -> JjError {
+        JjError::CommandFailed {
+            command: args.join(" "),
+            stderr: "Error: Failed to snapshot the working copy\n".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_retry_without_snapshot() {
+        let runner = MockJjRunner {
+            handler: |args: &[&str]| {
+                if args[0] == "--ignore-working-copy" {
+                    Ok("origin https://github.com/o/r.git\n".to_string())
+                } else {
+                    Err(snapshot_error(args))
+                }
+            },
+        };
+        let jj = Jj::new(runner);
+        assert!(!jj.snapshot_skipped());
+        let remotes = jj.get_git_remote_list().await.unwrap();
+        assert_eq!(remotes.len(), 1);
+        assert!(jj.snapshot_skipped());
+    }
+
+    #[tokio::test]
+    async fn mutations_record_the_action() {
+        let runner = MockJjRunner {
+            handler: |args: &[&str]| match args {
+                [
+                    "--config",
+                    r#"stakk.action="submit""#,
+                    "bookmark",
+                    "delete",
+                    "feat-a",
+                ]
+                | ["git", "remote", "list"] => Ok(String::new()),
+                _ => panic!("unexpected jj call: {args:?}"),
+            },
+        };
+        let jj = Jj::new(runner).with_action("submit");
+        jj.delete_bookmark("feat-a").await.unwrap();
+        jj.get_git_remote_list().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn untrack_bookmark_names_the_remote_bookmark() {
+        let runner = MockJjRunner {
+            handler: |args: &[&str]| match args {
+                ["bookmark", "untrack", "feat-a@origin"] => Ok(String::new()),
+                _ => panic!("unexpected jj call: {args:?}"),
+            },
+        };
+        Jj::new(runner)
+            .untrack_bookmark("feat-a", "origin")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn mutations_report_snapshot_failure() {
+        let runner = MockJjRunner {
+            handler: |args: &[&str]| Err(snapshot_error(args)),
+        };
+        let jj = Jj::new(runner);
+        let err = jj.delete_bookmark("feat-a").await.unwrap_err();
+        assert!(matches!(err, JjError::SnapshotFailed { .. }));
+        assert!(!jj.snapshot_skipped());
+    }
+
     #[tokio::test]
     async fn get_my_bookmarks_integration() {
         let runner = MockJjRunner {
@@ -592,6 +1702,85 @@ mod tests {
             .unwrap();
     }
 
+    #[tokio::test]
+    async fn get_working_copy_integration() {
+        let runner = MockJjRunner {
+            handler: |args: &[&str]| {
+                assert_eq!(args[0], "log");
+                assert_eq!(args[2], "@");
+                Ok(r#"{"commit":{"commit_id":"wc","parents":["c_a"],"change_id":"ch_wc","description":"","author":{"name":"A","email":"a@b.c","timestamp":"T"},"committer":{"name":"A","email":"a@b.c","timestamp":"T"}},"local_bookmarks":[],"remote_bookmarks":[],"immutable":false,"short_change_id":"ch_w"}"#.to_string())
+            },
+        };
+        let jj = Jj::new(runner);
+        let wc = jj.get_working_copy().await.unwrap();
+        assert_eq!(wc.commit_id, "wc");
+        assert_eq!(wc.change_id, "ch_wc");
+        assert_eq!(wc.parents, vec!["c_a"]);
+    }
+
+    #[tokio::test]
+    async fn get_working_copy_empty_output_is_error() {
+        let runner = MockJjRunner {
+            handler: |_args: &[&str]| Ok(String::new()),
+        };
+        let jj = Jj::new(runner);
+        let result = jj.get_working_copy().await;
+        assert!(matches!(result, Err(JjError::NoWorkingCopy)));
+    }
+
+    #[tokio::test]
+    async fn has_remote_bookmark_matches_remote_line() {
+        let runner = MockJjRunner {
+            handler: |args: &[&str]| {
+                assert_eq!(
+                    &args[..5],
+                    ["bookmark", "list", "--remote", "origin", "feat"]
+                );
+                // Local bookmarks render as empty lines under the template.
+                Ok("\nfeat\n".to_string())
+            },
+        };
+        let jj = Jj::new(runner);
+        assert!(jj.has_remote_bookmark("feat", "origin").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn has_remote_bookmark_false_when_only_local() {
+        let runner = MockJjRunner {
+            handler: |_args: &[&str]| Ok(String::new()),
+        };
+        let jj = Jj::new(runner);
+        assert!(!jj.has_remote_bookmark("feat", "origin").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn synced_bookmarks_require_matching_remote_target() {
+        let runner = MockJjRunner {
+            handler: |args: &[&str]| {
+                if args.contains(&"-r") {
+                    let bookmark = |name: &str, synced: bool, commit: &str| {
+                        format!(
+                            r#"{{"name":"{name}","synced":{synced},"target":{{"commit_id":"{commit}","parents":[],"change_id":"x","description":"","author":{{"name":"A","email":"a@b.c","timestamp":"T"}},"committer":{{"name":"A","email":"a@b.c","timestamp":"T"}}}}}}"#
+                        )
+                    };
+                    // a: pushed and unchanged; b: moved locally; c: never pushed.
+                    Ok([
+                        bookmark("a", true, "111"),
+                        bookmark("b", false, "222"),
+                        bookmark("c", true, "333"),
+                    ]
+                    .join("\n"))
+                } else {
+                    assert_eq!(&args[..4], ["bookmark", "list", "--remote", "origin"]);
+                    Ok("a 111\nb 999\n".to_string())
+                }
+            },
+        };
+        let jj = Jj::new(runner);
+        let synced = jj.get_synced_bookmarks("mine()", "origin").await.unwrap();
+        assert_eq!(synced, HashSet::from(["a".to_string()]));
+    }
+
     #[tokio::test]
     async fn version_parses_plain() {
         let runner = MockJjRunner {
@@ -648,4 +1837,178 @@ mod tests {
         let jj = Jj::new(runner);
         assert!(jj.version().await.is_err());
     }
+
+    const ROOT_LOG_ENTRY: &str = r#"{"commit":{"commit_id":"000","parents":[],"change_id":"zzz","description":"","author":{"name":"","email":"","timestamp":"T"},"committer":{"name":"","email":"","timestamp":"T"}},"local_bookmarks":[],"remote_bookmarks":[],"immutable":true,"short_change_id":"z"}"#;
+
+    /// A runner for which templates using `json(` fail to parse, as on a jj
+    /// without the `json()` function.
+    fn runner_without_json()
+    -> MockJjRunner<impl Fn(&[&str]) -> Result<String, JjError> + Send + Sync> {
+        MockJjRunner {
+            handler: |args: &[&str]| {
+                let template = args.last().unwrap();
+                if template.replace("escape_json(", "").contains("json(") {
+                    return Err(JjError::CommandFailed {
+                        command: "jj".to_string(),
+                        stderr: "Error: Failed to parse template: Function `json` doesn't exist"
+                            .to_string(),
+                    });
+                }
+                Ok(if args.contains(&"log") {
+                    format!("{ROOT_LOG_ENTRY}\n")
+                } else {
+                    String::new()
+                })
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn select_templates_falls_back_to_older_set() {
+        let jj = Jj::new(runner_without_json());
+        let selected = jj.select_templates(None).await.unwrap();
+        assert_eq!(selected.name, "escaped");
+    }
+
+    #[tokio::test]
+    async fn select_templates_unsupported_version() {
+        let runner = MockJjRunner {
+            handler: |_args: &[&str]| {
+                Ok("Error: Failed to parse template: Method `name` doesn't exist\n".to_string())
+            },
+        };
+        let jj = Jj::new(runner);
+        let err = jj
+            .select_templates(version::parse("jj 0.50.0"))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, JjError::UnsupportedVersion { version } if version == "0.50.0"),
+            "{err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn select_templates_propagates_runner_errors() {
+        let runner = MockJjRunner {
+            handler: |_args: &[&str]| Err(JjError::NotFound(std::io::Error::other("boom"))),
+        };
+        let jj = Jj::new(runner);
+        assert!(matches!(
+            jj.select_templates(None).await,
+            Err(JjError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn query_cache_answers_repeated_queries_until_a_mutation() {
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let runner = MockJjRunner {
+            handler: |_args: &[&str]| {
+                calls.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                Ok("origin git@github.com:glennib/stakk.git\n".to_string())
+            },
+        };
+        let key = CacheKey {
+            operation_id: "op".to_string(),
+            bookmark_targets: std::collections::BTreeMap::new(),
+        };
+        let cache = QueryCache::load(std::path::Path::new("/nonexistent/jj.json"), key);
+        let jj = Jj::new(runner).with_query_cache(cache);
+
+        jj.get_git_remote_list().await.unwrap();
+        jj.get_git_remote_list().await.unwrap();
+        assert_eq!(calls.load(std::sync::atomic::Ordering::Relaxed), 1);
+        assert!(jj.query_cache().is_some());
+
+        jj.delete_bookmark("feat").await.unwrap();
+        jj.get_git_remote_list().await.unwrap();
+        assert_eq!(calls.load(std::sync::atomic::Ordering::Relaxed), 3);
+        assert!(jj.query_cache().is_none());
+    }
+
+    #[tokio::test]
+    async fn get_remote_divergence_counts_both_sides() {
+        let runner = MockJjRunner {
+            handler: |args: &[&str]| match args {
+                ["bookmark", "list", ..] => Ok("feat\n".to_string()),
+                ["log", "-r", revset, ..] if revset.starts_with("remote_bookmarks") => {
+                    Ok("c1\nc2\n".to_string())
+                }
+                ["log", ..] => Ok("c3\n".to_string()),
+                _ => panic!("unexpected jj call: {args:?}"),
+            },
+        };
+        let jj = Jj::new(runner);
+        assert_eq!(
+            jj.get_remote_divergence("feat", "origin").await.unwrap(),
+            Some((2, 1))
+        );
+    }
+
+    #[tokio::test]
+    async fn push_bookmark_passes_options() {
+        let calls = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let recorded = std::sync::Arc::clone(&calls);
+        let runner = MockJjRunner {
+            handler: move |args: &[&str]| {
+                recorded.lock().unwrap().push(args.join(" "));
+                Ok(String::new())
+            },
+        };
+        let jj = Jj::new(runner);
+        jj.push_bookmark("feat", "origin", PushOptions::default())
+            .await
+            .unwrap();
+        let gated = PushOptions {
+            allow_new: false,
+            ..PushOptions::default()
+        };
+        jj.push_bookmark("feat", "origin", gated.dry_run())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [
+                "--config git.push-new-bookmarks=true git push --remote origin --bookmark feat",
+                "git push --remote origin --bookmark feat --dry-run",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn clobbered_commits_leave_out_local_rewrites() {
+        let entry = |commit_id: &str, change_id: &str| {
+            format!(
+                r#"{{"commit":{{"commit_id":"{commit_id}","parents":[],"change_id":"{change_id}","description":"","author":{{"name":"A","email":"a@b.c","timestamp":"T"}},"committer":{{"name":"A","email":"a@b.c","timestamp":"T"}}}},"local_bookmarks":[],"remote_bookmarks":[],"immutable":false,"short_change_id":"{change_id}"}}"#
+            )
+        };
+        let runner = MockJjRunner {
+            handler: move |args: &[&str]| {
+                // `local..branch`: what the push drops from the remote.
+                if args[2].starts_with("bookmarks(") {
+                    Ok([entry("fix", "theirs"), entry("old", "mine")].join("\n"))
+                } else {
+                    Ok(entry("new", "mine"))
+                }
+            },
+        };
+        let jj = Jj::new(runner);
+        let clobbered = jj.get_clobbered_commits("feat", "origin").await.unwrap();
+        let ids: Vec<_> = clobbered.iter().map(|e| e.commit_id.as_str()).collect();
+        assert_eq!(ids, ["fix"]);
+    }
+
+    #[tokio::test]
+    async fn get_remote_divergence_unpushed_is_none() {
+        let runner = MockJjRunner {
+            handler: |_args: &[&str]| Ok(String::new()),
+        };
+        let jj = Jj::new(runner);
+        assert_eq!(
+            jj.get_remote_divergence("feat", "origin").await.unwrap(),
+            None
+        );
+    }
 }
@@ -1,5 +1,13 @@
 //! Command execution for `jj`.
 
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use std::time::Instant;
+
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+
 use crate::jj::JjError;
 
 /// Trait for running `jj` commands. Abstracted for testing.
@@ -11,28 +19,165 @@ pub trait JjRunner: Send + Sync {
 }
 
 /// Runs `jj` commands via `tokio::process::Command`.
-pub struct RealJjRunner;
+///
+/// A command whose stdout grows past `max_output` bytes, or that runs longer
+/// than `timeout`, is killed and reported as an error, so a pathological
+/// template or an enormous history cannot exhaust memory or hang stakk.
+#[derive(Debug, Clone)]
+pub struct RealJjRunner {
+    max_output: usize,
+    timeout: Duration,
+    /// Directory jj runs in; the current directory when `None`.
+    dir: Option<PathBuf>,
+}
+
+impl RealJjRunner {
+    pub const DEFAULT_MAX_OUTPUT_MIB: u64 = 256;
+    pub const DEFAULT_TIMEOUT_SECS: u64 = 300;
+
+    pub fn new(max_output_mib: u64, timeout: Duration) -> Self {
+        Self {
+            max_output: usize::try_from(max_output_mib.saturating_mul(1024 * 1024))
+                .unwrap_or(usize::MAX),
+            timeout,
+            dir: None,
+        }
+    }
+
+    /// Run jj in `dir` instead of the current directory.
+    #[must_use]
+    pub fn in_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = Some(dir.into());
+        self
+    }
+
+    /// Run a `jj` command attached to the terminal, for commands that talk
+    /// to the user (e.g. `jj resolve` starting a merge tool). Neither the
+    /// output limit nor the timeout applies.
+    pub async fn run_jj_interactive(&self, args: &[&str]) -> Result<(), JjError> {
+        tracing::debug!(?args, "running jj interactively");
+        let mut command = tokio::process::Command::new("jj");
+        if let Some(dir) = &self.dir {
+            command.current_dir(dir);
+        }
+        let status = command
+            .args(args)
+            .status()
+            .await
+            .map_err(JjError::NotFound)?;
+        if !status.success() {
+            return Err(JjError::CommandFailed {
+                command: render_command(args),
+                stderr: String::new(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Default for RealJjRunner {
+    fn default() -> Self {
+        Self::new(
+            Self::DEFAULT_MAX_OUTPUT_MIB,
+            Duration::from_secs(Self::DEFAULT_TIMEOUT_SECS),
+        )
+    }
+}
 
 impl JjRunner for RealJjRunner {
     async fn run_jj(&self, args: &[&str]) -> Result<String, JjError> {
-        let output = tokio::process::Command::new("jj")
+        let start = Instant::now();
+        let result = self.run_captured(args).await;
+        let elapsed = start.elapsed();
+        match &result {
+            Ok(output) => {
+                tracing::debug!(command = %render_command(args), ?elapsed, bytes = output.len(), "ran jj");
+                tracing::trace!(%output, "jj output");
+            }
+            Err(error) => {
+                tracing::debug!(command = %render_command(args), ?elapsed, %error, "jj failed");
+            }
+        }
+        result
+    }
+}
+
+impl RealJjRunner {
+    async fn run_captured(&self, args: &[&str]) -> Result<String, JjError> {
+        let mut command = tokio::process::Command::new("jj");
+        if let Some(dir) = &self.dir {
+            command.current_dir(dir);
+        }
+        let mut child = command
             .arg("--config")
             .arg("ui.paginate=never")
             .args(args)
-            .output()
-            .await
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
             .map_err(JjError::NotFound)?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let mut stderr = child.stderr.take().expect("stderr is piped");
+
+        let io_error = |source| JjError::Io {
+            command: render_command(args),
+            source,
+        };
+        let run = async {
+            let mut err = Vec::new();
+            let (out, _, status) = futures::try_join!(
+                read_capped(stdout, self.max_output, args),
+                async { stderr.read_to_end(&mut err).await.map_err(io_error) },
+                async { child.wait().await.map_err(io_error) },
+            )?;
+            Ok::<_, JjError>((out, err, status))
+        };
+        // Returning early drops `child`, which kills jj.
+        let (stdout, stderr, status) =
+            tokio::time::timeout(self.timeout, run)
+                .await
+                .map_err(|_| JjError::TimedOut {
+                    command: render_command(args),
+                    seconds: self.timeout.as_secs(),
+                })??;
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        if !status.success() {
             return Err(JjError::CommandFailed {
                 command: render_command(args),
-                stderr,
+                stderr: String::from_utf8_lossy(&stderr).to_string(),
             });
         }
 
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        Ok(String::from_utf8_lossy(&stdout).to_string())
+    }
+}
+
+/// Read all of `reader`, failing once more than `max` bytes have arrived.
+/// The output is buffered whole rather than streamed; `max` only bounds how
+/// much memory a runaway command can take.
+async fn read_capped(
+    reader: impl AsyncRead + Unpin,
+    max: usize,
+    args: &[&str],
+) -> Result<Vec<u8>, JjError> {
+    let mut out = Vec::new();
+    reader
+        .take(u64::try_from(max).unwrap_or(u64::MAX).saturating_add(1))
+        .read_to_end(&mut out)
+        .await
+        .map_err(|source| JjError::Io {
+            command: render_command(args),
+            source,
+        })?;
+    if out.len() > max {
+        return Err(JjError::OutputTooLarge {
+            command: render_command(args),
+            limit_mib: max / (1024 * 1024),
+        });
     }
+    Ok(out)
 }
 
 /// Render the full jj invocation (including the always-passed `--config`
@@ -73,4 +218,37 @@ mod tests {
             r#"jj --config ui.paginate=never log -T 'json(self) ++ "\n"'"#
         );
     }
+
+    #[tokio::test]
+    async fn read_capped_rejects_oversized_output() {
+        let output = vec![b'x'; 2 * 1024 * 1024];
+        let err = read_capped(&output[..], 1024 * 1024, &["log"])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, JjError::OutputTooLarge { limit_mib: 1, .. }));
+        let ok = read_capped(&output[..], output.len(), &["log"]).await;
+        assert_eq!(ok.unwrap().len(), output.len());
+    }
+
+    /// A reader whose every read fails.
+    struct Broken;
+
+    impl AsyncRead for Broken {
+        fn poll_read(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            _buf: &mut tokio::io::ReadBuf<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            std::task::Poll::Ready(Err(std::io::Error::other("pipe broke")))
+        }
+    }
+
+    #[tokio::test]
+    async fn read_capped_reports_read_errors_as_io() {
+        let err = read_capped(Broken, 1024, &["log"]).await.unwrap_err();
+        assert!(
+            matches!(&err, JjError::Io { command, .. } if command.ends_with(" log")),
+            "{err:?}"
+        );
+    }
 }
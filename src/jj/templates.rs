@@ -0,0 +1,153 @@
+//! Template strings for the jj queries stakk parses, per jj version range.
+//!
+//! jj's template language changes between releases, and a template that no
+//! longer type-checks surfaces as a bare parse error. Each [`JjTemplates`]
+//! set targets a range of jj versions; [`Jj::select_templates`] probes the
+//! sets that claim the installed version, newest first, and keeps the first
+//! one jj accepts.
+//!
+//! [`Jj::select_templates`]: crate::jj::Jj::select_templates
+
+use crate::jj::version::JjVersion;
+
+/// The templates for one range of jj versions.
+#[derive(Debug, PartialEq, Eq)]
+pub struct JjTemplates {
+    /// Short name shown in diagnostics.
+    pub name: &'static str,
+    /// The oldest jj release the set is written for.
+    pub min_version: JjVersion,
+    /// `jj bookmark list`: one JSON object per line.
+    pub bookmark: &'static str,
+    /// `jj bookmark list --conflicted`: one JSON object per local bookmark,
+    /// with the commits it currently points to.
+    pub conflicted_bookmark: &'static str,
+    /// `jj log`: one JSON object per line with commit + bookmarks + shortest
+    /// unique change ID prefix.
+    pub log: &'static str,
+}
+
+/// jj 0.31 and later: serialize with the `json()` function.
+pub const JSON: JjTemplates = JjTemplates {
+    name: "json",
+    min_version: JjVersion {
+        major: 0,
+        minor: 31,
+        patch: 0,
+    },
+    bookmark: r#""{\"name\":" ++ json(self.name()) ++ ",\"synced\":" ++ json(self.synced()) ++ ",\"target\":" ++ json(self.normal_target()) ++ "}\n""#,
+    conflicted_bookmark: r#"if(remote, "", "{\"name\":" ++ json(self.name()) ++ ",\"targets\":[" ++ self.added_targets().map(|c| json(c)).join(",") ++ "]}\n")"#,
+    log: r#""{\"commit\":" ++ json(self) ++ ",\"local_bookmarks\":" ++ json(local_bookmarks) ++ ",\"remote_bookmarks\":" ++ json(remote_bookmarks) ++ ",\"immutable\":" ++ immutable ++ ",\"short_change_id\":\"" ++ change_id.shortest() ++ "\"}\n""#,
+};
+
+/// The JSON for the signature `$commit$method`, as a template expression.
+macro_rules! signature_json {
+    ($commit:literal, $method:literal) => {
+        concat!(
+            r#""{\"name\":" ++ "#,
+            $commit,
+            $method,
+            r#".name().escape_json() ++ ",\"email\":" ++ "#,
+            $commit,
+            $method,
+            r#".email().escape_json() ++ ",\"timestamp\":\"" ++ "#,
+            $commit,
+            $method,
+            r#".timestamp().format("%Y-%m-%dT%H:%M:%S%:z") ++ "\"}""#,
+        )
+    };
+}
+
+/// The JSON `json($commit)` produces, as a template expression.
+macro_rules! commit_json {
+    ($commit:literal) => {
+        concat!(
+            r#""{\"commit_id\":\"" ++ "#,
+            $commit,
+            r#".commit_id() ++ "\",\"parents\":[" ++ "#,
+            $commit,
+            r#".parents().map(|p| "\"" ++ p.commit_id() ++ "\"").join(",") ++ "],\"change_id\":\"" ++ "#,
+            $commit,
+            r#".change_id() ++ "\",\"description\":" ++ "#,
+            $commit,
+            r#".description().escape_json() ++ ",\"author\":" ++ "#,
+            signature_json!($commit, ".author()"),
+            r#" ++ ",\"committer\":" ++ "#,
+            signature_json!($commit, ".committer()"),
+            r#" ++ "}""#,
+        )
+    };
+}
+
+/// jj 0.26 through 0.30, before `json()`: build the same JSON by hand with
+/// `escape_json()`.
+pub const ESCAPED: JjTemplates = JjTemplates {
+    name: "escaped",
+    min_version: JjVersion {
+        major: 0,
+        minor: 26,
+        patch: 0,
+    },
+    bookmark: concat!(
+        r#""{\"name\":" ++ self.name().escape_json() ++ ",\"synced\":" ++ self.synced() ++ ",\"target\":" ++ if(self.normal_target(), "#,
+        commit_json!("self.normal_target()"),
+        r#", "null") ++ "}\n""#,
+    ),
+    conflicted_bookmark: concat!(
+        r#"if(remote, "", "{\"name\":" ++ self.name().escape_json() ++ ",\"targets\":[" ++ self.added_targets().map(|c| "#,
+        commit_json!("c"),
+        r#").join(",") ++ "]}\n")"#,
+    ),
+    log: concat!(
+        r#""{\"commit\":" ++ "#,
+        commit_json!("self"),
+        r#" ++ ",\"local_bookmarks\":[" ++ local_bookmarks.map(|b| "{\"name\":" ++ b.name().escape_json() ++ ",\"target\":" ++ "#,
+        r#""[" ++ b.added_targets().map(|c| "\"" ++ c.commit_id() ++ "\"").join(",") ++ "]""#,
+        r#" ++ "}").join(",") ++ "],\"remote_bookmarks\":[" ++ remote_bookmarks.map(|b| "{\"name\":" ++ b.name().escape_json() ++ ",\"remote\":\"" ++ b.remote() ++ "\",\"target\":" ++ "#,
+        r#""[" ++ b.added_targets().map(|c| "\"" ++ c.commit_id() ++ "\"").join(",") ++ "]""#,
+        r#" ++ "}").join(",") ++ "],\"immutable\":" ++ immutable ++ ",\"short_change_id\":\"" ++ change_id.shortest() ++ "\"}\n""#,
+    ),
+};
+
+/// Every template set, newest first.
+pub const ALL: [&JjTemplates; 2] = [&JSON, &ESCAPED];
+
+/// The template sets written for `version`, newest first. With no known
+/// version (e.g. an unusual dev build), every set is a candidate.
+pub fn candidates(version: Option<JjVersion>) -> impl Iterator<Item = &'static JjTemplates> {
+    ALL.into_iter()
+        .filter(move |t| version.is_none_or(|v| t.min_version <= v))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(minor: u64) -> JjVersion {
+        JjVersion {
+            major: 0,
+            minor,
+            patch: 0,
+        }
+    }
+
+    fn names(version: Option<JjVersion>) -> Vec<&'static str> {
+        candidates(version).map(|t| t.name).collect()
+    }
+
+    #[test]
+    fn candidates_by_version() {
+        assert_eq!(names(Some(v(40))), ["json", "escaped"]);
+        assert_eq!(names(Some(v(28))), ["escaped"]);
+        assert!(names(Some(v(20))).is_empty());
+        assert_eq!(names(None), ["json", "escaped"]);
+    }
+
+    #[test]
+    fn escaped_templates_are_balanced() {
+        for template in [ESCAPED.bookmark, ESCAPED.conflicted_bookmark, ESCAPED.log] {
+            let opens = template.matches('(').count();
+            assert_eq!(opens, template.matches(')').count(), "{template}");
+        }
+    }
+}
@@ -26,26 +26,12 @@ pub struct Signature {
 #[derive(Debug, Clone, Deserialize)]
 pub struct CommitRefData {
     pub name: String,
-    #[cfg_attr(
-        not(test),
-        expect(
-            dead_code,
-            reason = "deserialized for completeness, not yet read in production"
-        )
-    )]
     pub target: Vec<String>,
     #[serde(default)]
     pub remote: Option<String>,
     /// Tracking target commit IDs. Elements are `Option<String>` because jj
     /// serializes absent tracking targets as `[null]` (e.g. when the tracked
     /// commit has been rewritten and the remote bookmark hasn't been updated).
-    #[cfg_attr(
-        not(test),
-        expect(
-            dead_code,
-            reason = "deserialized for completeness, not yet read in production"
-        )
-    )]
     #[serde(default)]
     pub tracking_target: Option<Vec<Option<String>>>,
 }
@@ -72,26 +58,21 @@ pub struct BookmarkEntryRaw {
     pub target: Option<CommitData>,
 }
 
+/// A local bookmark with conflicting targets, from `jj bookmark list
+/// --conflicted`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConflictedBookmark {
+    pub name: String,
+    /// The commits the bookmark points to, in jj's order.
+    pub targets: Vec<CommitData>,
+}
+
 /// Processed bookmark for public API.
 #[derive(Debug, Clone)]
 pub struct Bookmark {
     pub name: String,
     pub commit_id: String,
-    #[cfg_attr(
-        not(test),
-        expect(
-            dead_code,
-            reason = "deserialized for completeness, available for diagnostics"
-        )
-    )]
     pub change_id: String,
-    #[cfg_attr(
-        not(test),
-        expect(
-            dead_code,
-            reason = "available for push optimization (skip synced bookmarks)"
-        )
-    )]
     pub synced: bool,
 }
 
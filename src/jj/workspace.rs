@@ -0,0 +1,140 @@
+//! Temporary jj workspaces.
+//!
+//! Features that need a revision checked out (running tests on each segment,
+//! previewing a restack, simulating conflicts) do it in a [`TempWorkspace`]
+//! of their own, so the user's working copy is never touched. A workspace is
+//! removed with [`TempWorkspace::destroy`]; one that is dropped without it
+//! (an early return, a panic) is cleaned up on drop.
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use crate::jj::Jj;
+use crate::jj::JjError;
+use crate::jj::runner::JjRunner;
+
+/// Workspaces created by this process so far, for unique names.
+static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+/// A jj workspace in the system temp directory, with a new empty
+/// working-copy change on top of a revision.
+pub struct TempWorkspace<'a, R: JjRunner> {
+    jj: &'a Jj<R>,
+    name: String,
+    path: PathBuf,
+    /// Cleared once the workspace is destroyed, so drop has nothing to do.
+    live: bool,
+}
+
+impl<'a, R: JjRunner> TempWorkspace<'a, R> {
+    /// Check out `revision` into a new temporary workspace.
+    pub async fn create(jj: &'a Jj<R>, revision: &str) -> Result<Self, JjError> {
+        let name = format!(
+            "stakk-{}-{}",
+            std::process::id(),
+            CREATED.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = std::env::temp_dir().join(&name);
+        let _ = std::fs::remove_dir_all(&path);
+
+        jj.add_workspace(&name, &path, revision).await?;
+        Ok(Self {
+            jj,
+            name,
+            path,
+            live: true,
+        })
+    }
+
+    /// The jj name of the workspace.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The directory the revision is checked out in.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Forget the workspace and remove its directory. The directory is
+    /// removed even if forgetting fails.
+    pub async fn destroy(mut self) -> Result<(), JjError> {
+        self.live = false;
+        let forgotten = self.jj.forget_workspace(&self.name).await;
+        let _ = std::fs::remove_dir_all(&self.path);
+        forgotten
+    }
+}
+
+impl<R: JjRunner> Drop for TempWorkspace<'_, R> {
+    /// Best-effort cleanup of a workspace that was not destroyed. Drop
+    /// cannot await the runner, so this runs `jj workspace forget` directly,
+    /// from inside the workspace.
+    fn drop(&mut self) {
+        if !self.live {
+            return;
+        }
+        let _ = std::process::Command::new("jj")
+            .args(["workspace", "forget", &self.name])
+            .current_dir(&self.path)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status();
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// Records every jj invocation and succeeds.
+    #[derive(Default)]
+    struct RecordingRunner {
+        calls: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl JjRunner for RecordingRunner {
+        async fn run_jj(&self, args: &[&str]) -> Result<String, JjError> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(args.iter().map(ToString::to_string).collect());
+            Ok(String::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn create_and_destroy_add_and_forget_the_workspace() {
+        let jj = Jj::new(RecordingRunner::default());
+        let workspace = TempWorkspace::create(&jj, "abc123").await.unwrap();
+        let name = workspace.name().to_string();
+        let path = workspace.path().to_string_lossy().into_owned();
+        assert!(name.starts_with("stakk-"));
+        workspace.destroy().await.unwrap();
+
+        assert_eq!(
+            *jj.runner.calls.lock().unwrap(),
+            [
+                vec!["workspace", "add", "--name", &name, "-r", "abc123", &path],
+                vec!["workspace", "forget", &name],
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn workspaces_get_distinct_names() {
+        let jj = Jj::new(RecordingRunner::default());
+        let first = TempWorkspace::create(&jj, "a").await.unwrap();
+        let second = TempWorkspace::create(&jj, "b").await.unwrap();
+        assert_ne!(first.name(), second.name());
+        assert_ne!(first.path(), second.path());
+        first.destroy().await.unwrap();
+        second.destroy().await.unwrap();
+    }
+}
@@ -0,0 +1,76 @@
+//! `jj log` template aliases showing stack PRs inline.
+//!
+//! jj templates cannot read files, so the generated aliases embed the PR
+//! cache as a lookup table. Re-running `stakk jj-template` after a submit
+//! refreshes them.
+
+use std::fmt::Write;
+
+use crate::cache::PrCache;
+
+/// Render a jj config snippet defining the `stakk_*` template aliases.
+///
+/// - `stakk_pr(name)`: `"#12 [1/3] "` for a cached bookmark, `""` otherwise.
+/// - `stakk_prs(commit)`: `stakk_pr` for every local bookmark on `commit`.
+/// - `stakk_log`: `builtin_log_compact` prefixed with the commit's PRs.
+pub fn render(cache: &PrCache) -> String {
+    let mut conditions = String::new();
+    for (name, pr) in &cache.prs {
+        // JSON string escaping is valid jj string-literal syntax.
+        let name = serde_json::to_string(name).expect("strings serialize to JSON");
+        writeln!(
+            conditions,
+            "if(stringify(name) == {name}, \"#{} [{}/{}] \",",
+            pr.number, pr.position, pr.stack_size
+        )
+        .expect("writing to a String cannot fail");
+    }
+    let lookup = format!("{conditions}\"\"{}", ")".repeat(cache.prs.len()));
+
+    format!(
+        r#"# Generated by `stakk jj-template`. Add to your jj config
+# (`jj config edit --repo`) and re-run after `stakk submit` to refresh.
+# Then use `jj log -T stakk_log`, or set `templates.log = "stakk_log"`.
+[template-aliases]
+'stakk_pr(name)' = '''
+{lookup}
+'''
+'stakk_prs(commit)' = 'commit.local_bookmarks().map(|b| stakk_pr(b.name())).join("")'
+'stakk_log' = 'label("stakk", stakk_prs(self)) ++ builtin_log_compact'
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CachedPr;
+
+    #[test]
+    fn render_embeds_each_cached_pr() {
+        let mut cache = PrCache::default();
+        for (name, number, position) in [("feat-a", 12, 1), ("feat-b", 13, 2)] {
+            cache.prs.insert(
+                name.to_string(),
+                CachedPr {
+                    number,
+                    url: format!("https://github.com/o/r/pull/{number}"),
+                    position,
+                    stack_size: 2,
+                },
+            );
+        }
+
+        let snippet = render(&cache);
+        assert!(snippet.contains(r##"if(stringify(name) == "feat-a", "#12 [1/2] ","##));
+        assert!(snippet.contains(r##"if(stringify(name) == "feat-b", "#13 [2/2] ","##));
+        assert!(snippet.contains("\"\"))\n"));
+        assert!(snippet.contains("'stakk_log' ="));
+    }
+
+    #[test]
+    fn render_empty_cache_is_valid() {
+        let snippet = render(&PrCache::default());
+        assert!(snippet.contains("'stakk_pr(name)' = '''\n\"\"\n'''"));
+    }
+}
@@ -0,0 +1,262 @@
+//! Journal of the post-submit steps that failed in the last run.
+//!
+//! Once every PR exists, `stakk submit` keeps going past failed retargets and
+//! stack comment writes. It records each failed step, with everything needed
+//! to replay it, in `.jj/stakk/journal.json` under the workspace root, and
+//! `stakk retry` re-executes just those steps without re-planning or pushing.
+//!
+//! A submit that stops before every PR exists records its plan and what it
+//! had done instead: `stakk submit --continue` carries out the rest of the
+//! plan, and `stakk undo` reverses what was done.
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use miette::Diagnostic;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::forge::ForgeRepoRef;
+use crate::submit::SubmissionPlan;
+
+/// Errors from reading or writing the journal.
+#[derive(Debug, Error, Diagnostic)]
+pub enum JournalError {
+    #[error("failed to read journal {path}")]
+    #[diagnostic(code(stakk::journal::read_failed), help("check file permissions"))]
+    Read {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("failed to parse journal {path}")]
+    #[diagnostic(
+        code(stakk::journal::parse_failed),
+        help("delete the file and re-run `stakk submit` instead of `stakk retry`")
+    )]
+    Parse {
+        path: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error("failed to write journal {path}")]
+    #[diagnostic(code(stakk::journal::write_failed), help("check file permissions"))]
+    Write {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+/// A forge write that can be replayed as-is.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum JournalStep {
+    /// Change the base branch of a PR.
+    Retarget {
+        bookmark: String,
+        pr_number: u64,
+        base: String,
+    },
+    /// Create the stack comment on a PR, or update the existing one.
+    StackComment { pr_number: u64, body: String },
+    /// Replace the body of a PR.
+    PrBody { pr_number: u64, body: String },
+    /// Request reviews on a PR.
+    Reviewers {
+        pr_number: u64,
+        reviewers: Vec<String>,
+    },
+    /// Add labels to a PR.
+    Labels { pr_number: u64, labels: Vec<String> },
+    /// Mark a draft PR ready for review.
+    MarkReady { pr_number: u64 },
+}
+
+impl JournalStep {
+    /// Short description for progress output and summaries.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Retarget {
+                pr_number, base, ..
+            } => format!("retarget PR #{pr_number} onto {base}"),
+            Self::StackComment { pr_number, .. } => format!("stack comment on PR #{pr_number}"),
+            Self::PrBody { pr_number, .. } => format!("body of PR #{pr_number}"),
+            Self::Reviewers {
+                pr_number,
+                reviewers,
+            } => format!("request {} on PR #{pr_number}", reviewers.join(", ")),
+            Self::Labels { pr_number, labels } => {
+                format!("label PR #{pr_number} {}", labels.join(", "))
+            }
+            Self::MarkReady { pr_number } => format!("mark PR #{pr_number} ready for review"),
+        }
+    }
+}
+
+/// A change a submit made before it stopped partway.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum SubmitAction {
+    /// A bookmark was pushed. Undo leaves the branch on the remote.
+    Pushed { bookmark: String, remote: String },
+    /// A PR's base was changed. Undo changes it back to `from`.
+    UpdatedBase {
+        bookmark: String,
+        pr_number: u64,
+        from: String,
+        to: String,
+    },
+    /// A PR was created. Undo closes it.
+    CreatedPr {
+        bookmark: String,
+        pr_number: u64,
+        url: String,
+    },
+}
+
+impl SubmitAction {
+    /// Whether `stakk undo` reverses the action, as opposed to reporting it.
+    pub fn is_reversible(&self) -> bool {
+        !matches!(self, Self::Pushed { .. })
+    }
+
+    /// Short description of what the submit did.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Pushed { bookmark, remote } => format!("pushed {bookmark} to {remote}"),
+            Self::UpdatedBase {
+                pr_number,
+                from,
+                to,
+                ..
+            } => format!("changed base of PR #{pr_number} from {from} to {to}"),
+            Self::CreatedPr {
+                bookmark,
+                pr_number,
+                ..
+            } => format!("created PR #{pr_number} for {bookmark}"),
+        }
+    }
+
+    /// Short description of what undoing the action does.
+    pub fn describe_undo(&self) -> String {
+        match self {
+            Self::Pushed { bookmark, remote } => format!("branch {bookmark} stays on {remote}"),
+            Self::UpdatedBase {
+                pr_number, from, ..
+            } => format!("restore base of PR #{pr_number} to {from}"),
+            Self::CreatedPr {
+                bookmark,
+                pr_number,
+                ..
+            } => format!("close PR #{pr_number} ({bookmark})"),
+        }
+    }
+}
+
+/// The failed steps of the last run and the repository they apply to.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Journal {
+    pub repo: Option<ForgeRepoRef>,
+    pub steps: Vec<JournalStep>,
+    /// What the last submit had done when it stopped partway, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub undo: Vec<SubmitAction>,
+    /// The plan of the submit that stopped partway, for
+    /// `stakk submit --continue`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plan: Option<SubmissionPlan>,
+}
+
+impl Journal {
+    /// Path of the journal file in the workspace rooted at `workspace_root`.
+    pub fn path(workspace_root: &Path) -> PathBuf {
+        workspace_root
+            .join(".jj")
+            .join("stakk")
+            .join("journal.json")
+    }
+
+    /// Load the journal, or an empty one if the file does not exist yet.
+    pub fn load(path: &Path) -> Result<Self, JournalError> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(source) => {
+                return Err(JournalError::Read {
+                    path: path.display().to_string(),
+                    source,
+                });
+            }
+        };
+        serde_json::from_str(&contents).map_err(|source| JournalError::Parse {
+            path: path.display().to_string(),
+            source,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), JournalError> {
+        let write_failed = |source| JournalError::Write {
+            path: path.display().to_string(),
+            source,
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(write_failed)?;
+        }
+        let json = serde_json::to_string_pretty(self).expect("Journal serializes to JSON");
+        std::fs::write(path, json).map_err(write_failed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let dir = std::env::temp_dir().join(format!("stakk-journal-test-{}", std::process::id()));
+        let path = Journal::path(&dir);
+        let journal = Journal {
+            repo: Some(ForgeRepoRef::new("o", "r")),
+            steps: vec![
+                JournalStep::Retarget {
+                    bookmark: "feat-b".to_string(),
+                    pr_number: 2,
+                    base: "main".to_string(),
+                },
+                JournalStep::StackComment {
+                    pr_number: 3,
+                    body: "stack".to_string(),
+                },
+            ],
+            undo: vec![
+                SubmitAction::Pushed {
+                    bookmark: "feat-a".to_string(),
+                    remote: "origin".to_string(),
+                },
+                SubmitAction::CreatedPr {
+                    bookmark: "feat-a".to_string(),
+                    pr_number: 4,
+                    url: "https://github.com/o/r/pull/4".to_string(),
+                },
+            ],
+            plan: None,
+        };
+
+        journal.save(&path).unwrap();
+        let loaded = Journal::load(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(loaded, journal);
+    }
+
+    #[test]
+    fn load_missing_file_is_empty() {
+        let journal = Journal::load(Path::new("/nonexistent/.jj/stakk/journal.json")).unwrap();
+        assert_eq!(journal, Journal::default());
+    }
+}
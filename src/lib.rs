@@ -0,0 +1,21 @@
+//! The stacking logic behind `stakk`, for embedding in other tools.
+//!
+//! - [`jj`] wraps the jj CLI: every VCS operation goes through [`jj::Jj`],
+//!   generic over a [`jj::runner::JjRunner`] so it can be faked in tests.
+//! - [`graph`] builds the [`graph::types::ChangeGraph`] of bookmark stacks.
+//! - [`forge`] defines the [`forge::Forge`] trait and its GitHub
+//!   implementation.
+//! - [`submit`] analyzes, plans, and executes the submission of a stack as pull
+//!   requests.
+//!
+//! Nothing in this crate prints: errors are returned as values, and the steps
+//! of a submission are reported as [`submit::events::SubmitEvent`]s to a
+//! [`submit::events::SubmitObserver`].
+
+pub mod forge;
+pub mod graph;
+pub mod jj;
+pub mod journal;
+pub mod poll;
+pub mod query_cache;
+pub mod submit;
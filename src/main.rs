@@ -1,33 +1,101 @@
+mod audit;
 mod auth;
+mod author_check;
+mod auto_bookmark;
+mod backport;
+mod browser;
+mod cache;
 mod cli;
 mod config;
+mod delete;
+mod doctor;
+mod edit;
 mod error;
-mod forge;
-mod graph;
-mod jj;
+mod handoff;
+mod jj_template;
+mod manpage;
+mod notes;
+mod open_stack;
+mod output;
+mod plan_view;
+mod preview;
+mod prompt;
+mod rebase;
+mod review_queue;
+mod segment_test;
 mod select;
-mod submit;
+mod show;
+mod status;
+mod sync;
+mod triage;
 
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::collections::HashSet;
+use std::path::Path;
+use std::sync::OnceLock;
 
 use clap::CommandFactory;
 use clap::FromArgMatches;
+use stakk_core::forge;
+use stakk_core::forge::Forge;
+use stakk_core::graph;
+use stakk_core::graph::types::BranchStack;
+use stakk_core::graph::types::ChangeGraph;
+use stakk_core::jj::Jj;
+use stakk_core::jj::JjError;
+use stakk_core::jj::remote::parse_github_url;
+use stakk_core::jj::runner::RealJjRunner;
+use stakk_core::jj::templates::JjTemplates;
+use stakk_core::jj::types::LogEntry;
+use stakk_core::jj::version::MIN_SUPPORTED_JJ_VERSION;
+use stakk_core::journal;
+use stakk_core::poll;
+use stakk_core::poll::Polled;
+use stakk_core::poll::Schedule;
+use stakk_core::query_cache::PrLink;
+use stakk_core::query_cache::QueryCache;
+use stakk_core::submit;
+use stakk_core::submit::options::PrTemplateMerge;
 
 use crate::cli::Cli;
 use crate::cli::Commands;
+use crate::cli::DoctorArgs;
+use crate::cli::HandoffArgs;
+use crate::cli::NoteArgs;
+use crate::cli::OpenStackArgs;
+use crate::cli::OrderArgs;
+use crate::cli::ReviewArgs;
+use crate::cli::ReviewQueueArgs;
 use crate::cli::ShowArgs;
+use crate::cli::TestArgs;
+use crate::cli::amend_title::AmendTitleArgs;
 use crate::cli::auth::AuthCommands;
+use crate::cli::backport::BackportArgs;
+use crate::cli::comment::CommentCommands;
+use crate::cli::comment::CommentRefreshArgs;
+use crate::cli::delete::DeleteArgs;
+use crate::cli::edit::EditArgs;
+use crate::cli::graph::GraphArgs;
+use crate::cli::graph::GraphCheckArgs;
+use crate::cli::graph::GraphCommands;
+use crate::cli::pr::PrChecksArgs;
+use crate::cli::pr::PrCommands;
+use crate::cli::pr::PrOpenArgs;
+use crate::cli::prompt::PromptArgs;
+use crate::cli::rebase::RebaseArgs;
+use crate::cli::status::StatusArgs;
 use crate::cli::submit::SubmitArgs;
+use crate::cli::sync::SyncArgs;
 use crate::error::StakkError::Interrupted;
 use crate::error::StakkError::{self};
-use crate::forge::Forge;
-use crate::jj::Jj;
-use crate::jj::remote::parse_github_url;
-use crate::jj::runner::RealJjRunner;
-use crate::jj::version::MIN_SUPPORTED_JJ_VERSION;
+use crate::output::Spinner;
 
 #[tokio::main]
 async fn main() {
+    // Answers dynamic completion requests (`COMPLETE=<shell>` set) and exits.
+    clap_complete::CompleteEnv::with_factory(Cli::command).complete();
+
     if let Err(e) = run().await {
         if matches!(e, Interrupted) {
             std::process::exit(130);
@@ -40,18 +108,39 @@ async fn main() {
 async fn run() -> Result<(), StakkError> {
     let config_path = config::pre_parse_config_path();
     let config = config::Config::load(config_path)?;
+    auth::set_transport(config.forge.transport.unwrap_or_default());
     let cmd = cli::apply_config_defaults(config, Cli::command());
-    let cli = Cli::from_arg_matches(&cmd.get_matches())?;
+    let matches = cmd.get_matches();
+    let cli = Cli::from_arg_matches(&matches)?;
+    let _ = JJ_ACTION.set(matches.subcommand_name().unwrap_or("submit").to_string());
+    output::set_plain(cli.plain);
+    output::set_color(cli.color);
+    init_logging(cli.verbose, cli.plain);
+    if cli.plain || cli.color == output::ColorChoice::Never {
+        let unicode = !cli.plain;
+        miette::set_hook(Box::new(move |_| {
+            Box::new(
+                miette::MietteHandlerOpts::new()
+                    .unicode(unicode)
+                    .color(false)
+                    .build(),
+            )
+        }))
+        .expect("the error report hook is only set once");
+    }
 
-    // Warn about an outdated jj for commands that shell out to it. Commands that
-    // never touch jj (completions, `auth setup`) skip the check.
+    // Warn about an outdated jj and pick the templates it understands, for
+    // commands that shell out to it. Commands that never touch jj
+    // (completions, `auth setup`) skip the check, and so does `prompt`, whose
+    // output lands in the shell prompt.
     let runs_jj = match &cli.command {
-        Some(Commands::Completions { .. }) => false,
+        // Doctor reports on jj itself rather than failing before it starts.
+        Some(Commands::Completions { .. } | Commands::Prompt(_) | Commands::Doctor(_)) => false,
         Some(Commands::Auth(args)) => matches!(args.command, AuthCommands::Test),
         _ => true, // Submit, Show, and None (= submit) all use jj.
     };
     if runs_jj {
-        warn_if_jj_too_old().await;
+        select_jj_templates().await?;
     }
 
     match cli.command {
@@ -59,6 +148,18 @@ async fn run() -> Result<(), StakkError> {
             submit_bookmark(&args).await?;
         }
         Some(Commands::Auth(args)) => match args.command {
+            AuthCommands::Login {
+                with_token,
+                device,
+                client_id,
+                client_secret,
+            } => {
+                if device {
+                    auth_login_device(client_id.as_deref(), client_secret.as_deref()).await?;
+                } else {
+                    auth_login(with_token).await?;
+                }
+            }
             AuthCommands::Test => {
                 auth_test().await?;
             }
@@ -69,8 +170,104 @@ async fn run() -> Result<(), StakkError> {
         Some(Commands::Show(args)) => {
             show_status(&args).await?;
         }
-        Some(Commands::Completions { shell }) => {
-            clap_complete::generate(shell, &mut Cli::command(), "stakk", &mut std::io::stdout());
+        Some(Commands::Order(args)) => {
+            print_bookmark_order(&args).await?;
+        }
+        Some(Commands::Test(args)) => {
+            test_stack(&args).await?;
+        }
+        Some(Commands::Review(args)) => {
+            review_segments(&args).await?;
+        }
+        Some(Commands::ReviewQueue(args)) => {
+            list_review_requests(&args).await?;
+        }
+        Some(Commands::Note(args)) => {
+            edit_note(&args).await?;
+        }
+        Some(Commands::Status(args)) => {
+            stack_status(&args).await?;
+        }
+        Some(Commands::Prompt(args)) => {
+            // A prompt must never show an error: outside a jj workspace, or
+            // if jj fails, print nothing.
+            if let Ok(Some(line)) = prompt_line(&args).await {
+                println!("{line}");
+            }
+        }
+        Some(Commands::Pr(args)) => match args.command {
+            PrCommands::Open(open_args) => {
+                pr_open(&open_args).await?;
+            }
+            PrCommands::Checks(checks_args) => {
+                pr_checks(&checks_args).await?;
+            }
+        },
+        Some(Commands::Comment(args)) => match args.command {
+            CommentCommands::Refresh(refresh_args) => {
+                comment_refresh(&refresh_args).await?;
+            }
+        },
+        Some(Commands::AmendTitle(args)) => {
+            amend_pr(&args).await?;
+        }
+        Some(Commands::OpenStack(args)) => {
+            open_stack(&args).await?;
+        }
+        Some(Commands::Handoff(args)) => {
+            hand_off(&args).await?;
+        }
+        Some(Commands::Graph(args)) => match args.command {
+            GraphCommands::Check(check_args) => {
+                graph_check(&check_args).await?;
+            }
+        },
+        Some(Commands::Delete(args)) => {
+            delete_bookmark(&args, false).await?;
+        }
+        Some(Commands::Abandon(args)) => {
+            delete_bookmark(&args, true).await?;
+        }
+        Some(Commands::Edit(args)) => {
+            edit_segment(args).await?;
+        }
+        Some(Commands::Rebase(args)) => {
+            restack(args).await?;
+        }
+        Some(Commands::Sync(args)) => {
+            sync_stacks(&args).await?;
+        }
+        Some(Commands::Backport(args)) => {
+            backport_stack(&args).await?;
+        }
+        Some(Commands::ResolveBookmark { name, pick }) => {
+            resolve_bookmark(&name, pick).await?;
+        }
+        Some(Commands::Retry) => {
+            retry_failed_steps().await?;
+        }
+        Some(Commands::Undo) => {
+            undo_stopped_submit().await?;
+        }
+        Some(Commands::JjTemplate) => {
+            let root = new_jj(RealJjRunner::default()).get_workspace_root().await?;
+            let cache = cache::PrCache::load(&cache::PrCache::path(&root))?;
+            print!("{}", jj_template::render(&cache));
+        }
+        Some(Commands::Doctor(args)) => {
+            run_doctor(&args).await?;
+        }
+        Some(Commands::Completions { shell, man }) => {
+            if man {
+                manpage::render(Cli::command(), &mut std::io::stdout())?;
+            } else if let Some(shell) = shell {
+                clap_complete::generate(
+                    shell,
+                    &mut Cli::command(),
+                    "stakk",
+                    &mut std::io::stdout(),
+                );
+            }
         }
         None => {
             submit_bookmark(&cli.submit_args).await?;
@@ -80,15 +277,73 @@ async fn run() -> Result<(), StakkError> {
     Ok(())
 }
 
+/// Log to stderr at the level `-v` asks for, or as `STAKK_LOG` filters.
+/// Without either, nothing is logged.
+fn init_logging(verbose: u8, plain: bool) {
+    let filter = match (std::env::var("STAKK_LOG"), verbose) {
+        (Ok(directives), _) => tracing_subscriber::EnvFilter::new(directives),
+        (Err(_), 0) => return,
+        (Err(_), 1) => {
+            tracing_subscriber::EnvFilter::new("stakk=debug,stakk_core=debug,octocrab=debug")
+        }
+        (Err(_), _) => {
+            tracing_subscriber::EnvFilter::new("stakk=trace,stakk_core=trace,octocrab=debug")
+        }
+    };
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(!plain && console::Term::stderr().is_term())
+        .with_timer(tracing_subscriber::fmt::time::uptime())
+        .init();
+}
+
+/// The templates [`select_jj_templates`] settled on for this process.
+static JJ_TEMPLATES: OnceLock<&'static JjTemplates> = OnceLock::new();
+
+/// The subcommand this process runs, recorded in the jj operations it makes.
+static JJ_ACTION: OnceLock<String> = OnceLock::new();
+
+/// A jj interface using the templates selected for the installed jj, that
+/// tags the operations it makes with the running subcommand.
+fn new_jj(runner: RealJjRunner) -> Jj<RealJjRunner> {
+    let mut jj = Jj::new(runner);
+    if let Some(templates) = JJ_TEMPLATES.get() {
+        jj = jj.with_templates(templates);
+    }
+    if let Some(action) = JJ_ACTION.get() {
+        jj = jj.with_action(action);
+    }
+    jj
+}
+
+/// [`new_jj`] with the output and time limits and the trunks of `graph_args`,
+/// taking trunk pattern branches from `remote` when given.
+fn graph_jj(graph_args: &GraphArgs, remote: Option<&str>) -> Jj<RealJjRunner> {
+    let mut jj =
+        new_jj(graph_args.jj_runner()).with_trunk_patterns(graph_args.trunk_patterns.clone());
+    if let Some(remote) = remote {
+        jj = jj.with_trunk_remote(remote);
+    }
+    match &graph_args.trunk {
+        Some(trunk) => jj.with_trunk(trunk),
+        None => jj,
+    }
+}
+
 /// Warn (to stderr) if the installed jj is older than the minimum supported
-/// version.
+/// version, and select the templates it understands.
 ///
-/// Never fails the command: if jj can't be run, or its version output can't be
-/// parsed (e.g. an unusual dev build), this stays silent. A genuine jj problem
-/// surfaces moments later with a more specific diagnostic.
-async fn warn_if_jj_too_old() {
-    let jj = Jj::new(RealJjRunner);
-    if let Ok(Some(version)) = jj.version().await
+/// Fails only when no template set works with the installed jj. If jj can't
+/// be run at all, this stays silent: the problem surfaces moments later with
+/// a more specific diagnostic. Unparseable version output (e.g. an unusual
+/// dev build) skips the warning and probes every template set.
+async fn select_jj_templates() -> Result<(), StakkError> {
+    let jj = Jj::new(RealJjRunner::default());
+    let Ok(version) = jj.version().await else {
+        return Ok(());
+    };
+    if let Some(version) = version
         && version < MIN_SUPPORTED_JJ_VERSION
     {
         eprintln!(
@@ -96,16 +351,218 @@ async fn warn_if_jj_too_old() {
              ({MIN_SUPPORTED_JJ_VERSION}). stakk may not work correctly — consider upgrading jj."
         );
     }
+    match jj.select_templates(version).await {
+        Ok(templates) => {
+            let _ = JJ_TEMPLATES.set(templates);
+            Ok(())
+        }
+        Err(e @ JjError::UnsupportedVersion { .. }) => Err(e.into()),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Run every `stakk doctor` check, printing each as it completes, and fail
+/// if any found a problem stakk cannot work around.
+///
+/// Checks that need an earlier one to pass (a workspace, a GitHub remote, a
+/// token) are skipped when it fails.
+async fn run_doctor(args: &DoctorArgs) -> Result<(), StakkError> {
+    use doctor::Check;
+
+    let mut checks = Vec::new();
+    let mut report = |check: Check| {
+        print!("{check}");
+        checks.push(check);
+    };
+
+    let mut jj = Jj::new(args.graph.jj_runner())
+        .with_trunk_patterns(args.graph.trunk_patterns.clone())
+        .with_trunk_remote(&args.remote);
+    if let Some(trunk) = &args.graph.trunk {
+        jj = jj.with_trunk(trunk);
+    }
+    let version = jj.version().await;
+    report(doctor::jj_version(&version));
+    let Ok(version) = version else {
+        return Err(StakkError::DoctorFailed { failed: 1 });
+    };
+
+    let root = match jj.get_workspace_root().await {
+        Ok(root) => root,
+        Err(e) => {
+            report(Check::fail(
+                "repository",
+                format!("not in a jj workspace: {e}"),
+                "Run stakk inside a jj repository, or create one with `jj git init --colocate`.",
+            ));
+            return Err(StakkError::DoctorFailed {
+                failed: doctor::failures(&checks),
+            });
+        }
+    };
+    report(doctor::colocation(&root));
+
+    let jj = match jj.select_templates(version).await {
+        Ok(templates) => jj.with_templates(templates),
+        Err(e @ JjError::UnsupportedVersion { .. }) => {
+            report(Check::fail(
+                "templates",
+                e.to_string(),
+                "Upgrade jj, or report the version at https://github.com/glennib/stakk/issues.",
+            ));
+            return Err(StakkError::DoctorFailed {
+                failed: doctor::failures(&checks),
+            });
+        }
+        Err(_) => jj,
+    };
+
+    let trunk = match jj.get_default_branch().await {
+        Ok(branch) => {
+            report(Check::ok("trunk", format!("{} is '{branch}'", jj.trunk())));
+            Some(branch)
+        }
+        Err(e) => {
+            report(Check::fail(
+                "trunk",
+                format!("could not find a bookmark at {}: {e}", jj.trunk()),
+                "Fetch the default branch with `jj git fetch`, or set `revset-aliases.'trunk()'` \
+                 in your jj config.",
+            ));
+            None
+        }
+    };
+
+    match jj.get_conflicted_bookmarks().await {
+        Ok(conflicted) => {
+            let names: Vec<String> = conflicted.into_iter().map(|b| b.name).collect();
+            report(doctor::excluded_bookmarks(
+                "conflicted bookmarks",
+                &names,
+                "point to several commits and are left out of stacks",
+                "Pick a target for each with `stakk resolve-bookmark <name>`.",
+            ));
+        }
+        Err(e) => report(Check::fail(
+            "conflicted bookmarks",
+            e.to_string(),
+            "Check that `jj bookmark list --conflicted` works.",
+        )),
+    }
+
+    let bookmark_check =
+        async |name, revset: String, problem, fix| match jj.get_my_bookmarks(&revset).await {
+            Ok(bookmarks) => {
+                let names: Vec<String> = bookmarks.into_iter().map(|b| b.name).collect();
+                doctor::excluded_bookmarks(name, &names, problem, fix)
+            }
+            Err(e) => Check::fail(name, e.to_string(), format!("Check the revset `{revset}`.")),
+        };
+    report(
+        bookmark_check(
+            "divergent bookmarks",
+            format!("({}) & divergent()", args.graph.bookmarks_revset),
+            "are on divergent changes",
+            "Abandon the copies you don't want (`jj log -r 'divergent()'` lists them).",
+        )
+        .await,
+    );
+    report(
+        bookmark_check(
+            "immutable bookmarks",
+            format!("(mine() & immutable()) ~ ::({})", jj.trunk()),
+            "are on immutable commits outside trunk and are left out of stacks",
+            "Check `revset-aliases.'immutable_heads()'` in your jj config; it likely covers these \
+             bookmarks (e.g. through `remote_bookmarks()`).",
+        )
+        .await,
+    );
+
+    let remote = match resolve_github_remote(Some(&args.remote)).await {
+        Ok((_, repo)) => {
+            report(Check::ok(
+                "remote",
+                format!("'{}' is {}/{}", args.remote, repo.owner, repo.repo),
+            ));
+            Some(repo)
+        }
+        Err(e) => {
+            report(Check::fail(
+                "remote",
+                e.to_string(),
+                "Add a GitHub remote with `jj git remote add`, or name it with --remote.",
+            ));
+            None
+        }
+    };
+
+    let token = match auth::resolve_login().await {
+        Ok(token) => Some(token),
+        Err(e) => {
+            report(Check::fail(
+                "auth",
+                e.to_string(),
+                "Log in with `stakk auth login`.",
+            ));
+            None
+        }
+    };
+
+    if let (Some(repo), Some(token)) = (remote, token) {
+        match forge::github::GitHubForge::new(&token.credentials, repo.owner, repo.repo) {
+            Ok(forge) => {
+                match forge.get_authenticated_user().await {
+                    Ok(login) => report(Check::ok(
+                        "auth",
+                        format!("authenticated as {login} ({})", token.source),
+                    )),
+                    Err(e) => report(Check::fail(
+                        "auth",
+                        format!("the token from {} was rejected: {e}", token.source),
+                        "Log in again with `stakk auth login`.",
+                    )),
+                }
+                if let Some(trunk) = &trunk {
+                    report(match forge.branch_exists(trunk).await {
+                        Ok(true) => Check::ok(
+                            "reachability",
+                            format!("'{trunk}' exists on {}", args.remote),
+                        ),
+                        Ok(false) => Check::fail(
+                            "reachability",
+                            format!("'{trunk}' does not exist on {}", args.remote),
+                            "Push trunk, or point trunk() at a branch GitHub has.",
+                        ),
+                        Err(e) => Check::fail(
+                            "reachability",
+                            format!("could not reach GitHub: {e}"),
+                            "Check your network, and that the token can read the repository.",
+                        ),
+                    });
+                }
+            }
+            Err(e) => report(Check::fail(
+                "auth",
+                e.to_string(),
+                "Log in again with `stakk auth login`.",
+            )),
+        }
+    }
+
+    match doctor::failures(&checks) {
+        0 => Ok(()),
+        failed => Err(StakkError::DoctorFailed { failed }),
+    }
 }
 
 async fn auth_test() -> Result<(), StakkError> {
-    let auth_token = auth::resolve_token().await?;
-    println!("Authentication source: {}", auth_token.source);
+    let login = auth::resolve_login().await?;
+    println!("Authentication source: {}", login.source);
 
     let (_, github_repo) = resolve_github_remote(None).await?;
 
     let forge =
-        forge::github::GitHubForge::new(&auth_token.token, github_repo.owner, github_repo.repo)?;
+        forge::github::GitHubForge::new(&login.credentials, github_repo.owner, github_repo.repo)?;
 
     let username = forge.get_authenticated_user().await?;
     println!("Authenticated as: {username}");
@@ -113,13 +570,60 @@ async fn auth_test() -> Result<(), StakkError> {
     Ok(())
 }
 
+async fn auth_login(with_token: bool) -> Result<(), StakkError> {
+    let token = if with_token {
+        let mut token = String::new();
+        std::io::stdin().read_line(&mut token)?;
+        token
+    } else {
+        eprint!("Paste a GitHub personal access token with `repo` scope: ");
+        console::Term::stderr().read_secure_line()?
+    };
+
+    auth::store_token(&token).await?;
+    println!("Token stored in the system keyring.");
+    println!("To verify: run `stakk auth test`");
+
+    Ok(())
+}
+
+async fn auth_login_device(
+    client_id: Option<&str>,
+    client_secret: Option<&str>,
+) -> Result<(), StakkError> {
+    let client_id = client_id.ok_or(auth::AuthError::MissingClientId)?;
+    let login = auth::DeviceLogin::start(client_id, client_secret).await?;
+
+    println!("First copy your one-time code: {}", login.user_code());
+    println!(
+        "Then enter it at {} to authorize stakk.",
+        login.verification_uri()
+    );
+    if browser::open_url(login.verification_uri()).await.is_err() {
+        println!("(Could not open a browser; open the URL manually.)");
+    }
+
+    let pb = Spinner::new();
+    pb.set_message("Waiting for authorization...");
+    let result = login.finish().await;
+    pb.finish_and_clear();
+    result?;
+
+    println!("Token stored in the system keyring.");
+    println!("To verify: run `stakk auth test`");
+
+    Ok(())
+}
+
 fn auth_setup() {
     println!("stakk resolves GitHub authentication in this order:\n");
-    println!("  1. GitHub CLI:    Run `gh auth login` to authenticate.");
+    println!("  1. Keyring:       Run `stakk auth login` to store a personal");
+    println!("                    access token in the system keyring.\n");
+    println!("  2. GitHub CLI:    Run `gh auth login` to authenticate.");
     println!("                    This is the recommended method.\n");
-    println!("  2. GITHUB_TOKEN:  Set the GITHUB_TOKEN environment variable");
+    println!("  3. GITHUB_TOKEN:  Set the GITHUB_TOKEN environment variable");
     println!("                    to a personal access token with `repo` scope.\n");
-    println!("  3. GH_TOKEN:      Set the GH_TOKEN environment variable");
+    println!("  4. GH_TOKEN:      Set the GH_TOKEN environment variable");
     println!("                    (same as GITHUB_TOKEN, alternative name).\n");
     println!("To verify: run `stakk auth test`");
 }
@@ -127,53 +631,45 @@ fn auth_setup() {
 /// Submits a bookmark as a stacked pull request using the three-phase pipeline:
 /// analyze, plan, execute.
 async fn submit_bookmark(args: &SubmitArgs) -> Result<(), StakkError> {
-    let pb = indicatif::ProgressBar::new_spinner();
-    pb.enable_steady_tick(std::time::Duration::from_millis(120));
+    if args.continue_submit {
+        return continue_submission(args).await;
+    }
+
+    let pb = Spinner::new();
 
     pb.set_message("Resolving authentication...");
-    let jj = Jj::new(RealJjRunner);
+    let jj = cached_jj(&args.graph, Some(&args.remote)).await?;
 
     // Resolve auth and remote.
-    let auth_token = auth::resolve_token().await?;
+    let login = auth::resolve_login().await?;
 
     pb.set_message("Resolving GitHub remote...");
-    let (remote_name, github_repo) = resolve_github_remote(Some(&args.remote)).await?;
-
-    let forge = forge::github::GitHubForge::new(
-        &auth_token.token,
-        github_repo.owner.clone(),
-        github_repo.repo.clone(),
-    )?;
+    let (remote_name, forge) =
+        resolve_forge(&login.credentials, &args.remote, args.pr_remote.as_deref()).await?;
 
     // Build the change graph.
     pb.set_message("Building change graph...");
-    let change_graph =
-        graph::build_change_graph(&jj, &args.graph.bookmarks_revset, &args.graph.heads_revset)
-            .await?;
+    let change_graph = build_graph(&jj, &args.graph).await?;
 
     pb.set_message("Detecting default branch...");
     let default_branch = jj.get_default_branch().await?;
+    save_query_cache(&jj)?;
 
     // Resolve bookmark: explicit argument or interactive selection.
     pb.finish_and_clear();
 
     let (bookmark, change_graph, selected_bookmarks) = match &args.bookmark {
-        Some(name) => {
+        Some(name) if !args.per_commit => {
             let selected = HashSet::from([name.clone()]);
             (name.clone(), change_graph, selected)
         }
-        None => match select::resolve_bookmark_interactively(
-            &change_graph,
-            args.bookmark_command.as_deref(),
-            args.auto_prefix.as_deref(),
-        )? {
+        _ => match select_bookmarks(args, &jj, &change_graph).await? {
             Some(result) => {
                 // Create any new bookmarks that were assigned.
                 let has_new = result.assignments.iter().any(|a| a.is_new);
                 for assignment in &result.assignments {
                     if assignment.is_new {
-                        let pb = indicatif::ProgressBar::new_spinner();
-                        pb.enable_steady_tick(std::time::Duration::from_millis(120));
+                        let pb = Spinner::new();
                         pb.set_message(format!(
                             "Creating bookmark {}...",
                             assignment.bookmark_name
@@ -200,15 +696,9 @@ async fn submit_bookmark(args: &SubmitArgs) -> Result<(), StakkError> {
 
                 // Rebuild graph if we created new bookmarks.
                 let graph = if has_new {
-                    let pb = indicatif::ProgressBar::new_spinner();
-                    pb.enable_steady_tick(std::time::Duration::from_millis(120));
+                    let pb = Spinner::new();
                     pb.set_message("Rebuilding change graph...");
-                    let g = graph::build_change_graph(
-                        &jj,
-                        &args.graph.bookmarks_revset,
-                        &args.graph.heads_revset,
-                    )
-                    .await?;
+                    let g = build_graph(&jj, &args.graph).await?;
                     pb.finish_and_clear();
                     g
                 } else {
@@ -221,75 +711,538 @@ async fn submit_bookmark(args: &SubmitArgs) -> Result<(), StakkError> {
         },
     };
 
+    // A stack on a --trunk-pattern branch is submitted against that branch.
+    let default_branch = graph::trunk_branch_of(&change_graph, &bookmark)
+        .map_or(default_branch, ToString::to_string);
+
     // Phase 1: Analyze.
-    let pb = indicatif::ProgressBar::new_spinner();
-    pb.enable_steady_tick(std::time::Duration::from_millis(120));
+    let pb = Spinner::new();
     pb.set_message("Analyzing submission...");
-    let analysis = submit::analyze_submission(
+    let mut analysis = submit::analyze_submission(
         &bookmark,
         &change_graph,
         &default_branch,
         &selected_bookmarks,
     )?;
+    let conflicted = graph::conflicted_in(&change_graph, &analysis.segments);
+    if args.force {
+        if !conflicted.is_empty() {
+            eprintln!(
+                "Warning: submitting past conflicted bookmark(s), which stay unpushed: {}",
+                conflicted.join(", ")
+            );
+        }
+    } else {
+        submit::require_resolved_bookmarks(&conflicted)?;
+    }
+    if args.require_signoff || args.fix_signoff {
+        let unsigned = submit::unsigned_commits(&analysis);
+        if !args.fix_signoff {
+            submit::require_signoffs(&unsigned)?;
+        } else if !unsigned.is_empty() {
+            pb.set_message("Signing off commits...");
+            for commit in unsigned {
+                jj.describe(&commit.change_id, &submit::signed_off_description(commit))
+                    .await?;
+            }
+            let change_graph = build_graph(&jj, &args.graph).await?;
+            analysis = submit::analyze_submission(
+                &bookmark,
+                &change_graph,
+                &default_branch,
+                &selected_bookmarks,
+            )?;
+        }
+    }
+
+    // Load PR title/body templates.
+    let body_template_source = match &args.body_template {
+        Some(path) => {
+            Some(
+                std::fs::read_to_string(path).map_err(|e| StakkError::TemplateLoadFailed {
+                    path: path.clone(),
+                    reason: e.to_string(),
+                })?,
+            )
+        }
+        None => None,
+    };
+    let repo_pr_template = match args.pr_template {
+        PrTemplateMerge::Ignore => None,
+        merge => match submit::content::RepoPrTemplate::find(&jj.get_workspace_root().await?) {
+            Some(path) => {
+                let source =
+                    std::fs::read_to_string(&path).map_err(|e| StakkError::TemplateLoadFailed {
+                        path: path.display().to_string(),
+                        reason: e.to_string(),
+                    })?;
+                Some(submit::content::RepoPrTemplate::new(
+                    source,
+                    merge,
+                    &args.pr_template_section,
+                ))
+            }
+            None => None,
+        },
+    };
+    let pr_templates = submit::content::PrTemplates::new(
+        args.title_template.as_deref(),
+        body_template_source.as_deref(),
+    )?
+    .with_repo_template(repo_pr_template)
+    .with_title_source(args.title_source);
+
+    // Bookmarks whose remote branch already matches need no push.
+    pb.set_message("Checking remote bookmarks...");
+    let synced_bookmarks = jj
+        .get_synced_bookmarks(&args.graph.bookmarks_revset, &remote_name)
+        .await?;
+    let remote_targets = jj.get_remote_targets(&remote_name).await?;
 
     // Phase 2: Plan.
     pb.set_message("Checking for existing pull requests...");
-    let plan = submit::create_submission_plan(
+    let mut plan = submit::create_submission_plan(
         &analysis,
         &forge,
         &remote_name,
         args.pr_mode(),
-        args.sync_pr_content,
+        args.sync_pr_content(),
         args.trailers,
+        &pr_templates,
+        &synced_bookmarks,
+        &remote_targets,
     )
     .await?;
+    plan.push_options = args.push_options();
+    plan.milestone.clone_from(&args.milestone);
+    plan.project = args.project;
+    if args.include_notes {
+        let notes = notes::Notes::load(&notes::Notes::path(&jj.get_workspace_root().await?))?;
+        submit::attach_notes(&mut plan, &notes.notes);
+    }
+    if args.number_titles {
+        submit::number_titles(&mut plan);
+    }
+
+    // How much each push sends, for the plan display only: a failed lookup
+    // is left out.
+    let push_sizes = futures::future::join_all(plan.bookmark_plans.iter().map(|bp| async {
+        if bp.needs_push {
+            jj.get_push_size(&bp.bookmark_name, &remote_name).await.ok()
+        } else {
+            None
+        }
+    }))
+    .await;
+    for (bp, size) in plan.bookmark_plans.iter_mut().zip(push_sizes) {
+        bp.push_size = size;
+    }
+    if !args.force_push {
+        pb.set_message("Checking remote branches for commits a push would drop...");
+        submit::check_remote_clobbers(&jj, &plan).await?;
+    }
 
     pb.finish_and_clear();
 
+    if !args.no_author_check {
+        author_check::warn_unverified_authors(&forge, &analysis).await;
+    }
+
     // Print the plan.
     if args.dry_run {
-        println!("DRY RUN — no changes will be made.\n");
+        println!(
+            "DRY RUN {} no changes will be made.\n",
+            output::symbol("—", "-")
+        );
     }
-    println!("{plan}");
+    println!("{}", plan_view::render(&plan));
 
     if args.dry_run {
+        return simulate_plan(args, &jj, &forge, &plan).await;
+    }
+    if args.preview_bodies && !preview_and_confirm(args, &jj, &forge, &plan).await? {
+        println!("Nothing was submitted.");
         return Ok(());
     }
 
-    // Load template.
-    let template_source = match &args.template {
+    execute_plan(args, &jj, &forge, &plan, Vec::new()).await
+}
+
+/// Load the stack comment template at `path`, or the built-in one with
+/// `footer` in place of its footer line.
+fn load_comment_env(
+    path: Option<&str>,
+    style: forge::markup::CommentStyle,
+    footer: Option<&str>,
+) -> Result<minijinja::Environment<'static>, StakkError> {
+    let template_source = match path {
         Some(path) => {
             Some(
                 std::fs::read_to_string(path).map_err(|e| StakkError::TemplateLoadFailed {
-                    path: path.clone(),
+                    path: path.to_string(),
                     reason: e.to_string(),
                 })?,
             )
         }
         None => None,
     };
-    let comment_env = forge::comment::build_comment_env(template_source.as_deref())?;
+    let mut env = forge::markup::build_comment_env(template_source.as_deref(), style)?;
+    if let Some(footer) = footer {
+        forge::markup::set_comment_footer(&mut env, footer);
+    }
+    Ok(env)
+}
+
+/// Run `plan` for `--dry-run`: jj only checks the pushes, and the forge
+/// records its writes instead of making them. Prints every write a real run
+/// would make, with the comments and bodies it would write.
+async fn simulate_plan(
+    args: &SubmitArgs,
+    jj: &Jj<RealJjRunner>,
+    forge: &forge::github::GitHubForge,
+    plan: &submit::SubmissionPlan,
+) -> Result<(), StakkError> {
+    let (mutations, result) = record_plan(args, jj, forge, plan).await?;
+    if mutations.is_empty() {
+        println!("\nNothing would change on the forge.");
+    } else {
+        println!();
+        for mutation in &mutations {
+            print!("{mutation}");
+        }
+    }
+    if args.preview_bodies {
+        print!("{}", preview::render(&mutations));
+    }
+    if let Some(error) = result.steps.to_error() {
+        print!("\n{}", result.steps);
+        return Err(error.into());
+    }
+    Ok(())
+}
+
+/// With `--preview-bodies`: show the bodies and comments `plan` would write,
+/// rendered, and ask whether to go ahead. Outside a terminal there is no
+/// one to ask, so the submit goes ahead once they are shown.
+async fn preview_and_confirm(
+    args: &SubmitArgs,
+    jj: &Jj<RealJjRunner>,
+    forge: &forge::github::GitHubForge,
+    plan: &submit::SubmissionPlan,
+) -> Result<bool, StakkError> {
+    let (mutations, _) = record_plan(args, jj, forge, plan).await?;
+    let rendered = preview::render(&mutations);
+    if rendered.is_empty() {
+        return Ok(true);
+    }
+    println!("{rendered}");
+
+    let term = console::Term::stderr();
+    if !term.is_term() {
+        return Ok(true);
+    }
+    eprint!("Submit? [y/N]: ");
+    let answer = term.read_line()?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Run `plan` against a recording forge, pushing nothing, and return the
+/// forge writes it would make.
+async fn record_plan(
+    args: &SubmitArgs,
+    jj: &Jj<RealJjRunner>,
+    forge: &forge::github::GitHubForge,
+    plan: &submit::SubmissionPlan,
+) -> Result<(Vec<forge::recording::Mutation>, submit::SubmissionResult), StakkError> {
+    let comment_env = load_comment_env(
+        args.template.as_deref(),
+        args.comment_style,
+        args.comment_footer.as_deref(),
+    )?;
+    let plan = submit::SubmissionPlan {
+        push_options: plan.push_options.dry_run(),
+        ..plan.clone()
+    };
+    let recording = submit::recording_forge(forge, &plan);
+
+    let spinner = Spinner::new();
+    let simulated = async {
+        let result = submit::execute_submission_plan(
+            &plan,
+            jj,
+            &recording,
+            &comment_env,
+            args.stack_placement(),
+            args.link_prs,
+            &spinner,
+        )
+        .await?;
+        if args.umbrella {
+            submit::update_umbrella(&recording, &plan, &result.stack_entries).await?;
+        }
+        if args.link_issues {
+            submit::link_issues(&recording, &plan, &result.stack_entries).await?;
+        }
+        if args.auto_merge {
+            submit::enable_auto_merge(&recording, &plan, &result.stack_entries, args.merge_method)
+                .await?;
+        }
+        Ok::<_, submit::SubmitError>(result)
+    }
+    .await;
+    spinner.finish_and_clear();
+    let result = simulated?;
+    Ok((recording.mutations(), result))
+}
+
+/// The bookmarks to submit when none was named, or with `--per-commit`.
+///
+/// With `--auto-bookmark` or `--per-commit`, these are the stack up to the
+/// named bookmark or the working copy, bookmarking its unbookmarked
+/// segments (with `--per-commit`, its unbookmarked commits); otherwise the
+/// interactive selection.
+async fn select_bookmarks(
+    args: &SubmitArgs,
+    jj: &Jj<RealJjRunner>,
+    change_graph: &ChangeGraph,
+) -> Result<Option<select::SelectionResult>, StakkError> {
+    if !args.auto_bookmark && !args.per_commit {
+        return select::resolve_bookmark_interactively(
+            change_graph,
+            args.bookmark_command.as_deref(),
+            args.auto_prefix.as_deref(),
+        );
+    }
+
+    let segments = if let Some(name) = &args.bookmark {
+        segment_test::segments_up_to(change_graph, name).ok_or_else(|| {
+            submit::SubmitError::BookmarkNotFound {
+                bookmark: name.clone(),
+            }
+        })?
+    } else {
+        let working_copy = jj.get_working_copy().await?;
+        let (stack, segment_idx) = locate_working_copy(change_graph, &working_copy)?;
+        &stack.segments[..=segment_idx]
+    };
+    let segments = if args.per_commit {
+        graph::split_into_commits(segments)
+    } else {
+        segments.to_vec()
+    };
+    let selection = auto_bookmark::assign_bookmarks(
+        &segments,
+        &args.auto_bookmark_pattern,
+        &bookmark_names(change_graph),
+    )?;
+    Ok(Some(selection))
+}
+
+/// Every bookmark in the graph, including the ones left out of its stacks.
+fn bookmark_names(change_graph: &ChangeGraph) -> HashSet<String> {
+    change_graph
+        .segments
+        .values()
+        .flat_map(|seg| seg.bookmark_names.iter().cloned())
+        .chain(change_graph.tainted_bookmarks.iter().cloned())
+        .chain(change_graph.private_bookmarks.keys().cloned())
+        .chain(change_graph.conflicted_bookmarks.iter().cloned())
+        .collect()
+}
+
+/// Carry out the rest of the last submit's plan, which stopped partway.
+async fn continue_submission(args: &SubmitArgs) -> Result<(), StakkError> {
+    let jj = graph_jj(&args.graph, Some(&args.remote));
+    let root = jj.get_workspace_root().await?;
+    let journal = journal::Journal::load(&journal::Journal::path(&root))?;
+    let Some(mut plan) = journal.plan else {
+        println!("Nothing to continue: the last submit did not stop partway.");
+        return Ok(());
+    };
+    plan.skip_done(&journal.undo);
+
+    let login = auth::resolve_login().await?;
+    let mut forge = forge::github::GitHubForge::new(
+        &login.credentials,
+        plan.repo.owner.clone(),
+        plan.repo.repo.clone(),
+    )?;
+    if plan.head_repo != plan.repo {
+        forge = forge.with_head_repo(plan.head_repo.owner.clone(), plan.head_repo.repo.clone());
+    }
+
+    println!("Continuing the last submit.\n{}", plan_view::render(&plan));
+    execute_plan(args, &jj, &forge, &plan, journal.undo).await
+}
+
+/// Phases 3 and 4 of a submit: execute `plan`, whose earlier run already
+/// made the changes in `done`, then record the outcome.
+async fn execute_plan(
+    args: &SubmitArgs,
+    jj: &Jj<RealJjRunner>,
+    forge: &forge::github::GitHubForge,
+    plan: &submit::SubmissionPlan,
+    done: Vec<journal::SubmitAction>,
+) -> Result<(), StakkError> {
+    let comment_env = load_comment_env(
+        args.template.as_deref(),
+        args.comment_style,
+        args.comment_footer.as_deref(),
+    )?;
 
     // Phase 3: Execute.
-    let result =
-        submit::execute_submission_plan(&plan, &jj, &forge, &comment_env, args.stack_placement)
-            .await?;
+    let spinner = Spinner::new();
+    let result = submit::execute_submission_plan(
+        plan,
+        jj,
+        forge,
+        &comment_env,
+        args.stack_placement(),
+        args.link_prs,
+        &spinner,
+    )
+    .await;
+    spinner.finish_and_clear();
+    let workspace_root = jj.get_workspace_root().await?;
+    let journal_path = journal::Journal::path(&workspace_root);
+    let result = match result {
+        Err(submit::SubmitError::Incomplete { source, actions }) => {
+            let actions = done.into_iter().chain(actions).collect();
+            return Err(
+                stopped_partway(forge, plan, &journal_path, *source, actions, args.atomic).await,
+            );
+        }
+        result => result?,
+    };
+
+    // Remember the submitted PRs for `stakk jj-template`.
+    update_pr_cache(&workspace_root, |cache| {
+        cache.record_stack(&result.stack_entries);
+    });
+
+    // Record this run's failed steps for `stakk retry`, replacing the last
+    // run's.
+    journal::Journal {
+        repo: Some(plan.repo.clone()),
+        steps: result.steps.journal_steps(),
+        undo: Vec::new(),
+        plan: None,
+    }
+    .save(&journal_path)?;
 
     println!("\nSubmitted {} bookmark(s).", result.stack_entries.len());
 
+    if let Some(error) = result.steps.to_error() {
+        print!("\n{}", result.steps);
+        return Err(error.into());
+    }
+
+    if args.pr_trailer {
+        add_pr_trailers(args, jj, plan, &result.stack_entries).await?;
+    }
+
+    if args.umbrella {
+        let (issue, created) = submit::update_umbrella(forge, plan, &result.stack_entries).await?;
+        let verb = if created { "Opened" } else { "Updated" };
+        println!(
+            "{verb} tracking issue #{}: {}",
+            issue.number, issue.html_url
+        );
+    }
+
+    if args.link_issues {
+        let linked = submit::link_issues(forge, plan, &result.stack_entries).await?;
+        if linked > 0 {
+            println!("Linked {linked} PR(s) to the issues their commits reference.");
+        }
+    }
+
+    if args.auto_merge {
+        let enabled =
+            submit::enable_auto_merge(forge, plan, &result.stack_entries, args.merge_method)
+                .await?;
+        println!(
+            "Enabled auto-merge ({}) on {} PR(s) based on {}.",
+            args.merge_method,
+            enabled.len(),
+            plan.default_branch
+        );
+        let waiting = result.stack_entries.len() - enabled.len();
+        if waiting > 0 {
+            println!(
+                "{waiting} PR(s) stacked above them get auto-merge once they are based on {}: \
+                 after each merge, run `stakk sync` and submit with --auto-merge again.",
+                plan.default_branch
+            );
+        }
+    }
+
+    // Phase 4: Optionally block until CI has finished.
+    if args.wait_checks {
+        let bookmarks: Vec<String> = result
+            .stack_entries
+            .iter()
+            .map(|entry| entry.bookmark_name.clone())
+            .collect();
+        let pb = Spinner::new();
+        pb.set_message("Waiting for CI checks...");
+        let outcome = submit::wait_for_checks(
+            forge,
+            &bookmarks,
+            &submit::checks_schedule(std::time::Duration::from_secs(args.checks_timeout)),
+            |pending| pb.set_message(format!("Waiting for {pending} CI check(s)...")),
+        )
+        .await;
+        pb.finish_and_clear();
+        outcome?;
+        println!("All CI checks passed.");
+    }
+
+    Ok(())
+}
+
+/// Link each submitted change to its PR with a `PR:` trailer, and push the
+/// bookmarks the rewrite moved.
+async fn add_pr_trailers(
+    args: &SubmitArgs,
+    jj: &Jj<RealJjRunner>,
+    plan: &submit::SubmissionPlan,
+    entries: &[forge::markup::StackEntry],
+) -> Result<(), StakkError> {
+    let change_graph = build_graph(jj, &args.graph).await?;
+    let edits = submit::pr_trailer_edits(&change_graph, entries);
+    if edits.is_empty() {
+        return Ok(());
+    }
+
+    let pb = Spinner::new();
+    pb.set_message("Linking changes to their PRs...");
+    for (change_id, description) in &edits {
+        jj.describe(change_id, description).await?;
+    }
+    // Describing a change rewrites the ones above it too, so the whole
+    // stack is pushed again; pushes of unmoved bookmarks do nothing.
+    for entry in entries {
+        pb.set_message(format!("Pushing {}...", entry.bookmark_name));
+        jj.push_bookmark(&entry.bookmark_name, &plan.remote, plan.push_options)
+            .await?;
+    }
+    pb.finish_and_clear();
+    println!("Linked {} change(s) to their PRs.", edits.len());
     Ok(())
 }
 
 /// Resolve the GitHub remote from jj's remote list.
 ///
 /// If `preferred` is given, looks for that specific remote name. Otherwise,
-/// falls back to the first remote with a GitHub URL.
+/// falls back to `origin` if it is on GitHub, then to the first remote with a
+/// GitHub URL.
 ///
 /// Returns the remote name and parsed `GitHubRepo`.
 async fn resolve_github_remote(
     preferred: Option<&str>,
-) -> Result<(String, jj::remote::GitHubRepo), StakkError> {
-    let jj = Jj::new(RealJjRunner);
+) -> Result<(String, stakk_core::jj::remote::GitHubRepo), StakkError> {
+    let jj = new_jj(RealJjRunner::default());
     let remotes = jj.get_git_remote_list().await?;
 
     if let Some(name) = preferred {
@@ -307,7 +1260,12 @@ async fn resolve_github_remote(
         });
     }
 
-    for remote in &remotes {
+    // jj lists remotes by name, so a fork's remote can sort before origin.
+    let origin_first = remotes
+        .iter()
+        .filter(|r| r.name == "origin")
+        .chain(remotes.iter().filter(|r| r.name != "origin"));
+    for remote in origin_first {
         if let Some(repo) = parse_github_url(&remote.url) {
             return Ok((remote.name.clone(), repo));
         }
@@ -316,20 +1274,309 @@ async fn resolve_github_remote(
     Err(StakkError::NoGithubRemote)
 }
 
-async fn show_status(args: &ShowArgs) -> Result<(), StakkError> {
-    let pb = indicatif::ProgressBar::new_spinner();
-    pb.enable_steady_tick(std::time::Duration::from_millis(120));
-    pb.set_message("Loading repository status...");
-
-    let jj = Jj::new(RealJjRunner);
-
-    let default_branch = jj.get_default_branch().await?;
-
-    let remotes = jj.get_git_remote_list().await?;
-
-    let change_graph =
-        graph::build_change_graph(&jj, &args.graph.bookmarks_revset, &args.graph.heads_revset)
-            .await?;
+/// Resolve the remote to push to and a forge for the repository PRs are
+/// opened on.
+///
+/// PRs go to the repository of `pr_remote` when it is given, and head
+/// branches are looked up in the repository of `remote`. With a fork as
+/// `remote` and its parent as `pr_remote`, PRs are opened upstream from
+/// `fork-owner:branch`.
+///
+/// Without `pr_remote`, a `remote` that is a fork of another remote's
+/// repository opens its PRs on that parent, as GitHub itself suggests.
+async fn resolve_forge(
+    credentials: &forge::github::Credentials,
+    remote: &str,
+    pr_remote: Option<&str>,
+) -> Result<(String, forge::github::GitHubForge), StakkError> {
+    let (remote_name, head_repo) = resolve_github_remote(Some(remote)).await?;
+    let forge = forge::github::GitHubForge::new(
+        credentials,
+        head_repo.owner.clone(),
+        head_repo.repo.clone(),
+    )?;
+    let pr_repo = match pr_remote {
+        Some(name) if name != remote_name => resolve_github_remote(Some(name)).await?.1,
+        Some(_) => head_repo.clone(),
+        None => fork_parent_remote(&forge, &remote_name)
+            .await?
+            .unwrap_or_else(|| head_repo.clone()),
+    };
+    if pr_repo == head_repo {
+        return Ok((remote_name, forge));
+    }
+    let forge = forge::github::GitHubForge::new(credentials, pr_repo.owner, pr_repo.repo)?
+        .with_head_repo(head_repo.owner, head_repo.repo);
+    Ok((remote_name, forge))
+}
+
+/// The repository of another GitHub remote that `forge`'s repository was
+/// forked from, if any.
+///
+/// Only asks GitHub when there is another GitHub remote to match, so
+/// single-remote repositories pay no extra request.
+async fn fork_parent_remote(
+    forge: &forge::github::GitHubForge,
+    remote_name: &str,
+) -> Result<Option<stakk_core::jj::remote::GitHubRepo>, StakkError> {
+    let others: Vec<_> = new_jj(RealJjRunner::default())
+        .get_git_remote_list()
+        .await?
+        .into_iter()
+        .filter(|r| r.name != remote_name)
+        .filter_map(|r| parse_github_url(&r.url))
+        .collect();
+    if others.is_empty() {
+        return Ok(None);
+    }
+    let Some(parent) = forge.fork_parent().await? else {
+        return Ok(None);
+    };
+    Ok(others.into_iter().find(|repo| {
+        repo.owner.eq_ignore_ascii_case(&parent.owner)
+            && repo.repo.eq_ignore_ascii_case(&parent.repo)
+    }))
+}
+
+/// Print the stacks' bookmarks in dependency order, one per line.
+async fn print_bookmark_order(args: &OrderArgs) -> Result<(), StakkError> {
+    let jj = cached_jj(&args.graph, None).await?;
+    let change_graph = build_graph(&jj, &args.graph).await?;
+    save_query_cache(&jj)?;
+
+    for bookmark in graph::bookmark_order(&change_graph, args.roots_first) {
+        println!("{bookmark}");
+    }
+    Ok(())
+}
+
+async fn test_stack(args: &TestArgs) -> Result<(), StakkError> {
+    let command = args.command.as_deref().ok_or(StakkError::NoTestCommand)?;
+    let jj = cached_jj(&args.graph, None).await?;
+    let change_graph = build_graph(&jj, &args.graph).await?;
+    save_query_cache(&jj)?;
+
+    let segments = if let Some(bookmark) = &args.bookmark {
+        segment_test::segments_up_to(&change_graph, bookmark).ok_or_else(|| {
+            StakkError::BookmarkNotInStack {
+                bookmark: bookmark.clone(),
+            }
+        })?
+    } else {
+        let working_copy = jj.get_working_copy().await?;
+        let (stack, _) = std::iter::once(&working_copy.commit_id)
+            .chain(&working_copy.parents)
+            .find_map(|id| graph::locate_commit(&change_graph, id))
+            .ok_or_else(|| StakkError::WorkingCopyNotInStack {
+                change_id: working_copy.short_change_id.clone(),
+            })?;
+        &stack.segments[..]
+    };
+
+    let root = jj.get_workspace_root().await?;
+    let prs = cache::PrCache::load(&cache::PrCache::path(&root))?;
+    let mut report = segment_test::TestReport::default();
+    for (i, segment) in segments.iter().enumerate() {
+        let label = segment_test::label(segment);
+        eprintln!("==> Testing {label} ({}/{})", i + 1, segments.len());
+        let failure = segment_test::run_segment(&jj, segment, command).await?;
+        let failed = failure.is_some();
+        report.outcomes.push(segment_test::SegmentOutcome {
+            pr_number: segment
+                .bookmark_names
+                .first()
+                .and_then(|b| prs.prs.get(b))
+                .map(|pr| pr.number),
+            label,
+            failure,
+        });
+        if failed && args.fail_fast {
+            break;
+        }
+    }
+
+    println!();
+    print!("{report}");
+    match report.failed() {
+        0 => Ok(()),
+        failed => Err(StakkError::SegmentTestsFailed {
+            failed,
+            total: report.outcomes.len(),
+        }),
+    }
+}
+
+/// Show the diff of a segment, or of every segment up to it with
+/// `--stack`, against the commit its PR is based on: the parent of the
+/// segment's oldest commit, as GitHub computes the PR's diff.
+async fn review_segments(args: &ReviewArgs) -> Result<(), StakkError> {
+    let jj = cached_jj(&args.graph, None).await?;
+    let change_graph = build_graph(&jj, &args.graph).await?;
+    save_query_cache(&jj)?;
+
+    let up_to = if let Some(bookmark) = &args.bookmark {
+        segment_test::segments_up_to(&change_graph, bookmark).ok_or_else(|| {
+            StakkError::BookmarkNotInStack {
+                bookmark: bookmark.clone(),
+            }
+        })?
+    } else {
+        let working_copy = jj.get_working_copy().await?;
+        let (stack, segment_idx) = locate_working_copy(&change_graph, &working_copy)?;
+        &stack.segments[..=segment_idx]
+    };
+    let segments = if args.stack {
+        up_to
+    } else {
+        &up_to[up_to.len() - 1..]
+    };
+
+    for (i, segment) in segments.iter().enumerate() {
+        let (Some(newest), Some(oldest)) = (segment.commits.first(), segment.commits.last()) else {
+            continue;
+        };
+        if segments.len() > 1 {
+            eprintln!(
+                "==> Reviewing {} ({}/{})",
+                segment_test::label(segment),
+                i + 1,
+                segments.len()
+            );
+        }
+        jj.diff_interactively(
+            &format!("{}-", oldest.commit_id),
+            &newest.commit_id,
+            args.tool.as_deref(),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// List the stacks with PRs that request a review from the user.
+async fn list_review_requests(args: &ReviewQueueArgs) -> Result<(), StakkError> {
+    let pb = Spinner::new();
+    pb.set_message("Looking up review requests...");
+    let login = auth::resolve_login().await?;
+    let (_, forge) =
+        resolve_forge(&login.credentials, &args.remote, args.pr_remote.as_deref()).await?;
+    let requested = forge.list_review_requests().await?;
+    let comments = futures::future::join_all(
+        requested
+            .iter()
+            .map(|details| forge.list_comments(details.pr.number)),
+    )
+    .await;
+    let mut with_stacks = Vec::with_capacity(requested.len());
+    for (details, comments) in requested.into_iter().zip(comments) {
+        let stack = open_stack::stack_of(&details.pr, &comments?);
+        with_stacks.push((details.pr, stack));
+    }
+    pb.finish_and_clear();
+
+    print!("{}", review_queue::ReviewQueue::group(with_stacks));
+    Ok(())
+}
+
+async fn edit_note(args: &NoteArgs) -> Result<(), StakkError> {
+    let jj = cached_jj(&args.graph, None).await?;
+    let change_graph = build_graph(&jj, &args.graph).await?;
+    save_query_cache(&jj)?;
+
+    let segment = segment_test::segments_up_to(&change_graph, &args.bookmark)
+        .and_then(<[_]>::last)
+        .ok_or_else(|| StakkError::BookmarkNotInStack {
+            bookmark: args.bookmark.clone(),
+        })?;
+    let root = jj.get_workspace_root().await?;
+    let path = notes::Notes::path(&root);
+    let mut notes = notes::Notes::load(&path)?;
+    let current = notes.get(&segment.change_id).unwrap_or_default();
+
+    if args.print {
+        print!("{current}");
+        return Ok(());
+    }
+
+    let scratch = root.join(".jj").join("stakk").join("NOTE_EDITMSG.md");
+    let edited = notes::edit_in_editor(&scratch, current).await?;
+    notes.set(&segment.change_id, &edited);
+    notes.save(&path)?;
+    if notes.get(&segment.change_id).is_some() {
+        eprintln!("Saved the note on {}.", args.bookmark);
+    } else {
+        eprintln!("{} has no note.", args.bookmark);
+    }
+    Ok(())
+}
+
+async fn show_status(args: &ShowArgs) -> Result<(), StakkError> {
+    let pb = Spinner::new();
+    pb.set_message("Loading repository status...");
+
+    let jj = cached_jj(&args.graph, Some(&args.remote)).await?;
+
+    let default_branch = jj.get_default_branch().await?;
+
+    let remotes = jj.get_git_remote_list().await?;
+
+    let change_graph = build_graph(&jj, &args.graph).await?;
+
+    let synced = jj
+        .get_synced_bookmarks(&args.graph.bookmarks_revset, &args.remote)
+        .await?;
+    let remote_targets = jj.get_remote_targets(&args.remote).await?;
+    let root = jj.get_workspace_root().await?;
+    let prs = cache::PrCache::load(&cache::PrCache::path(&root))?;
+    save_query_cache(&jj)?;
+
+    let forge = if args.reviews || args.checks {
+        let login = auth::resolve_login().await?;
+        let (_, forge) = resolve_forge(&login.credentials, &args.remote, None).await?;
+        Some(forge)
+    } else {
+        None
+    };
+
+    let mut reviews = HashMap::new();
+    if let Some(forge) = forge.as_ref().filter(|_| args.reviews) {
+        pb.set_message("Fetching reviews...");
+        let shown = change_graph
+            .segments
+            .values()
+            .filter_map(|seg| seg.bookmark_names.first())
+            .filter_map(|name| Some((name, prs.prs.get(name)?.number)));
+        let lookups = shown.map(|(name, number)| async move {
+            Ok::<_, StakkError>((name.clone(), forge.get_reviews(number).await?))
+        });
+        reviews = futures::future::try_join_all(lookups)
+            .await?
+            .into_iter()
+            .collect();
+    }
+
+    let mut checks = HashMap::new();
+    if let Some(forge) = forge.as_ref().filter(|_| args.checks) {
+        pb.set_message("Fetching CI checks...");
+        let shown = change_graph
+            .segments
+            .values()
+            .filter_map(|seg| seg.bookmark_names.first())
+            .filter(|name| prs.prs.contains_key(*name));
+        let lookups = shown.map(|name| async move {
+            let runs = forge.list_check_runs(name).await.map_err(|source| {
+                submit::SubmitError::ChecksLookupFailed {
+                    bookmark: name.clone(),
+                    source,
+                }
+            })?;
+            Ok::<_, StakkError>((name.clone(), runs))
+        });
+        checks = futures::future::try_join_all(lookups)
+            .await?
+            .into_iter()
+            .collect();
+    }
 
     pb.finish_and_clear();
 
@@ -341,33 +1588,1376 @@ async fn show_status(args: &ShowArgs) -> Result<(), StakkError> {
         println!("Remote: {} {}{}", remote.name, remote.url, github);
     }
 
+    let view = show::GraphView {
+        graph: &change_graph,
+        default_branch: &default_branch,
+        synced: &synced,
+        remote_targets: &remote_targets,
+        prs: &prs,
+        reviews: &reviews,
+        checks: &checks,
+    };
+    print!("\n{view}");
+
+    Ok(())
+}
+
+/// Print the detailed status of the stack containing `args.bookmark`.
+///
+/// When the bookmark is in several stacks (a shared base), the stack it is
+/// the leaf of wins, then the first in display order.
+async fn stack_status(args: &StatusArgs) -> Result<(), StakkError> {
+    let pb = Spinner::new();
+    pb.set_message("Building change graph...");
+
+    let jj = cached_jj(&args.graph, Some(&args.remote)).await?;
+    let login = auth::resolve_login().await?;
+    let (remote_name, forge) =
+        resolve_forge(&login.credentials, &args.remote, args.pr_remote.as_deref()).await?;
+    let change_graph = build_graph(&jj, &args.graph).await?;
+    let default_branch = match graph::trunk_branch_of(&change_graph, &args.bookmark) {
+        Some(branch) => branch.to_string(),
+        None => jj.get_default_branch().await?,
+    };
+
+    let has_bookmark =
+        |seg: &graph::types::BookmarkSegment| seg.bookmark_names.contains(&args.bookmark);
+    let stack = change_graph
+        .stacks
+        .iter()
+        .find(|stack| stack.segments.last().is_some_and(has_bookmark))
+        .or_else(|| {
+            change_graph
+                .stacks
+                .iter()
+                .find(|stack| stack.segments.iter().any(has_bookmark))
+        })
+        .ok_or_else(|| StakkError::BookmarkNotInStack {
+            bookmark: args.bookmark.clone(),
+        })?;
+
+    pb.set_message("Looking up pull requests, reviews, and checks...");
+    let statuses =
+        status::collect_stack_status(&jj, &forge, stack, &default_branch, &remote_name).await?;
+    save_query_cache(&jj)?;
+    if let Some(cache) = jj.query_cache() {
+        prompt::record_failed_checks(
+            &cache,
+            statuses
+                .iter()
+                .filter(|seg| seg.divergence.is_some())
+                .map(|seg| (seg.bookmark.as_str(), seg.checks.as_slice())),
+        )?;
+    }
+    pb.finish_and_clear();
+
+    println!(
+        "Stack of {} ({} segment(s) on {default_branch}):",
+        args.bookmark,
+        statuses.len()
+    );
+    for segment in &statuses {
+        print!("\n{segment}");
+    }
+    let conflicted = graph::conflicted_in(&change_graph, &stack.segments);
+    if !conflicted.is_empty() {
+        eprintln!(
+            "\nWarning: conflicted bookmark(s) in this stack: {}",
+            conflicted.join(", ")
+        );
+        eprintln!(
+            "  Resolve each with `jj bookmark set <name> -r <revision>` or `stakk \
+             resolve-bookmark <name>`."
+        );
+    }
+
+    Ok(())
+}
+
+/// The `stakk prompt` line, or `None` when there are no stacks.
+async fn prompt_line(args: &PromptArgs) -> Result<Option<String>, StakkError> {
+    let jj = cached_jj(&args.graph, Some(&args.remote)).await?;
+    let change_graph = build_graph(&jj, &args.graph).await?;
     if change_graph.stacks.is_empty() {
-        println!("\nNo bookmark stacks found.");
+        save_query_cache(&jj)?;
+        return Ok(None);
+    }
+    let summary = prompt::summarize(
+        &jj,
+        &change_graph,
+        &args.graph.bookmarks_revset,
+        &args.remote,
+    )
+    .await?;
+    save_query_cache(&jj)?;
+    Ok(Some(summary.to_string()))
+}
+
+/// Resolve a conflicted bookmark by pointing it at one of its targets, so it
+/// re-enters the change graph.
+///
+/// Lists the targets and prompts for one unless `pick` (1-based) is given.
+async fn resolve_bookmark(name: &str, pick: Option<usize>) -> Result<(), StakkError> {
+    let jj = new_jj(RealJjRunner::default());
+    let bookmark = jj
+        .get_conflicted_bookmarks()
+        .await?
+        .into_iter()
+        .find(|b| b.name == name)
+        .ok_or_else(|| StakkError::BookmarkNotConflicted {
+            name: name.to_string(),
+        })?;
+
+    let short_id = |id: &str| id[..id.len().min(12)].to_string();
+    println!(
+        "Bookmark {name} points to {} commits:",
+        bookmark.targets.len()
+    );
+    for (i, target) in bookmark.targets.iter().enumerate() {
+        let desc = target
+            .description
+            .lines()
+            .next()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .unwrap_or("(no description)");
+        println!("  {}) {} {desc}", i + 1, short_id(&target.change_id));
+    }
+
+    let choice = if let Some(n) = pick {
+        n.to_string()
     } else {
-        println!("\nStacks ({} found):", change_graph.stacks.len());
-        for (i, stack) in change_graph.stacks.iter().enumerate() {
-            println!("  Stack {}:", i + 1);
-            for segment in &stack.segments {
-                let names = segment.bookmark_names.join(", ");
-                let commit_count = segment.commits.len();
-                let desc = segment
-                    .commits
-                    .first()
-                    .and_then(|c| c.description.lines().next())
-                    .map(str::trim)
-                    .filter(|l| !l.is_empty())
-                    .unwrap_or("(no description)");
-                println!("    {names} ({commit_count} commit(s)): {desc}");
+        let term = console::Term::stderr();
+        if !term.is_term() {
+            return Err(StakkError::NotInteractive);
+        }
+        eprint!("Keep which target? [1-{}]: ", bookmark.targets.len());
+        term.read_line()?
+    };
+    let target = choice
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_sub(1))
+        .and_then(|i| bookmark.targets.get(i))
+        .ok_or_else(|| StakkError::InvalidTargetChoice {
+            choice: choice.trim().to_string(),
+            count: bookmark.targets.len(),
+        })?;
+
+    jj.set_bookmark(name, &target.commit_id).await?;
+    println!("Moved {name} to {}.", short_id(&target.change_id));
+
+    Ok(())
+}
+
+/// A [`graph_jj`] that answers read-only queries from the
+/// `.jj/stakk-cache/` entries of earlier invocations, unless `--no-cache`
+/// was passed.
+async fn cached_jj(
+    graph_args: &GraphArgs,
+    remote: Option<&str>,
+) -> Result<Jj<RealJjRunner>, StakkError> {
+    let jj = graph_jj(graph_args, remote);
+    if graph_args.no_cache {
+        return Ok(jj);
+    }
+    let root = jj.get_workspace_root().await?;
+    let key = jj.query_cache_key().await?;
+    Ok(jj.with_query_cache(QueryCache::load(&QueryCache::<String>::jj_path(&root), key)))
+}
+
+/// Build the change graph from the configured revsets, checking its
+/// invariants with `--paranoid`.
+async fn build_graph(
+    jj: &Jj<RealJjRunner>,
+    graph_args: &GraphArgs,
+) -> Result<ChangeGraph, StakkError> {
+    let mut change_graph = graph::build_change_graph(
+        jj,
+        &graph_args.bookmarks_revset,
+        &graph_args.heads_revset,
+        graph_args.private_revset.as_deref(),
+    )
+    .await?;
+    if jj.snapshot_skipped() {
+        eprintln!(
+            "Warning: jj could not snapshot the working copy; stakk read the repository as of the \
+             last snapshot, so recent edits in the working copy are not included."
+        );
+    }
+    if graph_args.paranoid {
+        let bookmarks = jj.get_my_bookmarks(&graph_args.bookmarks_revset).await?;
+        graph::invariants::check_invariants(&change_graph, &bookmarks)?;
+    }
+    if !graph_args.paths.is_empty() {
+        graph::retain_stacks_touching(&mut change_graph, &graph_args.paths);
+    }
+    Ok(change_graph)
+}
+
+/// Apply `update` to the PR cache of the workspace at `workspace_root`.
+/// The cache only feeds displays, so failing to read or write it is a
+/// warning: the command's real work is already done.
+fn update_pr_cache(workspace_root: &Path, update: impl FnOnce(&mut cache::PrCache)) {
+    let path = cache::PrCache::path(workspace_root);
+    let result = cache::PrCache::load(&path).and_then(|mut cache| {
+        update(&mut cache);
+        cache.save(&path)
+    });
+    if let Err(error) = result {
+        eprintln!("Warning: could not update the PR cache: {error}");
+    }
+}
+
+/// Write the queries made through `jj` back to the cache, unless caching is
+/// off or a command has changed the repository since.
+fn save_query_cache(jj: &Jj<RealJjRunner>) -> Result<(), StakkError> {
+    if let Some(cache) = jj.query_cache() {
+        cache.save()?;
+    }
+    Ok(())
+}
+
+/// Bookmarks of the working-copy change's stack segment, or of its whole
+/// stack (trunk-to-leaf) with `whole_stack`.
+///
+/// The working copy is located in the change graph by its own commit, or by
+/// its parent when `@` is an empty change on top of a stack (the usual state
+/// after `jj new`).
+async fn working_copy_bookmarks(
+    jj: &Jj<RealJjRunner>,
+    graph_args: &GraphArgs,
+    whole_stack: bool,
+) -> Result<Vec<String>, StakkError> {
+    let working_copy = jj.get_working_copy().await?;
+    let change_graph = build_graph(jj, graph_args).await?;
+
+    let (stack, segment_idx) = locate_working_copy(&change_graph, &working_copy)?;
+
+    let segments = if whole_stack {
+        &stack.segments[..]
+    } else {
+        &stack.segments[segment_idx..=segment_idx]
+    };
+    let bookmarks: Vec<String> = segments
+        .iter()
+        .filter_map(|seg| seg.bookmark_names.first().cloned())
+        .collect();
+    if bookmarks.is_empty() {
+        return Err(StakkError::WorkingCopyUnbookmarked {
+            change_id: working_copy.short_change_id,
+        });
+    }
+    Ok(bookmarks)
+}
+
+/// Open the compare view of the working copy's segment (every segment of
+/// its stack with `--all`) against its base: the bookmark below it, or
+/// trunk.
+async fn open_compare_views<F: Forge>(
+    args: &PrOpenArgs,
+    jj: &Jj<RealJjRunner>,
+    forge: &F,
+) -> Result<(), StakkError> {
+    let working_copy = jj.get_working_copy().await?;
+    let change_graph = build_graph(jj, &args.graph).await?;
+    save_query_cache(jj)?;
+    let (stack, segment_idx) = locate_working_copy(&change_graph, &working_copy)?;
+    let stack_trunk = stack
+        .segments
+        .iter()
+        .find_map(|seg| seg.bookmark_names.first())
+        .and_then(|name| graph::trunk_branch_of(&change_graph, name));
+    let default_branch = match stack_trunk {
+        Some(branch) => branch.to_string(),
+        None => jj.get_default_branch().await?,
+    };
+    let indices = if args.all {
+        0..stack.segments.len()
+    } else {
+        segment_idx..segment_idx + 1
+    };
+
+    let urls = forge.urls();
+    let head_repo = forge.head_repo_ref();
+    let mut opened = 0;
+    for i in indices {
+        let Some(head) = stack.segments[i].bookmark_names.first() else {
+            continue;
+        };
+        let base = stack.segments[..i]
+            .iter()
+            .rev()
+            .find_map(|segment| segment.bookmark_names.first())
+            .unwrap_or(&default_branch);
+        let url = urls.compare(base, &head_repo, head);
+        println!("Opening {head} against {base}: {url}");
+        browser::open_url(&url)
+            .await
+            .map_err(|source| StakkError::BrowserOpenFailed {
+                url: url.clone(),
+                source,
+            })?;
+        opened += 1;
+    }
+    if opened == 0 {
+        return Err(StakkError::WorkingCopyUnbookmarked {
+            change_id: working_copy.short_change_id,
+        });
+    }
+    Ok(())
+}
+
+/// The stack and segment containing the working copy, or its parent when
+/// `@` is an empty change on top of a stack.
+fn locate_working_copy<'a>(
+    change_graph: &'a ChangeGraph,
+    working_copy: &LogEntry,
+) -> Result<(&'a BranchStack, usize), StakkError> {
+    std::iter::once(&working_copy.commit_id)
+        .chain(&working_copy.parents)
+        .find_map(|id| graph::locate_commit(change_graph, id))
+        .ok_or_else(|| StakkError::WorkingCopyNotInStack {
+            change_id: working_copy.short_change_id.clone(),
+        })
+}
+
+/// Open the pull request(s) for the working-copy change in the browser.
+///
+/// Without `--all`, only the PR for the containing segment is
+/// opened; with it, every PR in the stack is opened trunk-to-leaf.
+async fn pr_open(args: &PrOpenArgs) -> Result<(), StakkError> {
+    let pb = Spinner::new();
+    pb.set_message("Resolving working copy...");
+
+    let jj = cached_jj(&args.graph, Some(&args.remote)).await?;
+    let login = auth::resolve_login().await?;
+    let (_, forge) =
+        resolve_forge(&login.credentials, &args.remote, args.pr_remote.as_deref()).await?;
+
+    pb.set_message("Building change graph...");
+    if args.compare {
+        pb.finish_and_clear();
+        return open_compare_views(args, &jj, &forge).await;
+    }
+    let bookmarks = working_copy_bookmarks(&jj, &args.graph, args.all).await?;
+    save_query_cache(&jj)?;
+
+    pb.set_message("Looking up pull requests...");
+    let mut pr_links = jj.query_cache().map(|cache| cache.pr_links());
+    let lookups = futures::future::join_all(bookmarks.iter().map(|bookmark| {
+        let cached = pr_links
+            .as_ref()
+            .and_then(|links| links.get(bookmark))
+            .cloned();
+        let forge = &forge;
+        async move {
+            match cached {
+                Some(link) => Ok::<_, forge::ForgeError>(Some(link)),
+                None => Ok(forge.find_pr_for_branch(bookmark).await?.map(|pr| PrLink {
+                    number: pr.number,
+                    html_url: pr.html_url,
+                })),
+            }
+        }
+    }))
+    .await
+    .into_iter()
+    .collect::<Result<Vec<_>, _>>()?;
+    pb.finish_and_clear();
+
+    if let Some(links) = &mut pr_links {
+        for (bookmark, pr) in bookmarks.iter().zip(&lookups) {
+            if let Some(pr) = pr {
+                links.insert(bookmark.clone(), pr.clone());
+            }
+        }
+        links.save()?;
+    }
+
+    let mut opened = 0;
+    for (bookmark, lookup) in bookmarks.iter().zip(lookups) {
+        match lookup {
+            Some(pr) => {
+                println!("Opening PR #{} ({bookmark}): {}", pr.number, pr.html_url);
+                browser::open_url(&pr.html_url).await.map_err(|source| {
+                    StakkError::BrowserOpenFailed {
+                        url: pr.html_url.clone(),
+                        source,
+                    }
+                })?;
+                opened += 1;
+            }
+            None if args.all => println!("No open PR for {bookmark}, skipping."),
+            None => {
+                return Err(StakkError::NoPullRequest {
+                    bookmark: bookmark.clone(),
+                });
+            }
+        }
+    }
+
+    if opened == 0 {
+        // Only reachable with --all: report the leaf, which `stakk submit`
+        // would submit together with everything below it.
+        return Err(StakkError::NoPullRequest {
+            bookmark: bookmarks.last().cloned().unwrap_or_default(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Show the CI checks of every PR in the working-copy change's stack and,
+/// with `--wait`, block until they have all finished.
+async fn pr_checks(args: &PrChecksArgs) -> Result<(), StakkError> {
+    let pb = Spinner::new();
+    pb.set_message("Building change graph...");
+
+    let jj = cached_jj(&args.graph, Some(&args.remote)).await?;
+    let login = auth::resolve_login().await?;
+    let (_, forge) =
+        resolve_forge(&login.credentials, &args.remote, args.pr_remote.as_deref()).await?;
+    let bookmarks = working_copy_bookmarks(&jj, &args.graph, true).await?;
+    save_query_cache(&jj)?;
+
+    pb.set_message("Looking up CI checks...");
+    let lookups = futures::future::join_all(
+        bookmarks
+            .iter()
+            .map(|bookmark| forge.list_check_runs(bookmark)),
+    )
+    .await;
+    pb.finish_and_clear();
+    let mut all_runs = Vec::with_capacity(bookmarks.len());
+    for (bookmark, lookup) in bookmarks.iter().zip(lookups) {
+        let runs = lookup.map_err(|source| submit::SubmitError::ChecksLookupFailed {
+            bookmark: bookmark.clone(),
+            source,
+        })?;
+        println!("{bookmark}: {}", submit::describe_checks(&runs));
+        all_runs.push(runs);
+    }
+    if let Some(cache) = jj.query_cache() {
+        prompt::record_failed_checks(
+            &cache,
+            bookmarks
+                .iter()
+                .map(String::as_str)
+                .zip(all_runs.iter().map(Vec::as_slice)),
+        )?;
+    }
+
+    if args.wait {
+        let pb = Spinner::new();
+        pb.set_message("Waiting for CI checks...");
+        let outcome = submit::wait_for_checks(
+            &forge,
+            &bookmarks,
+            &submit::checks_schedule(std::time::Duration::from_secs(args.checks_timeout)),
+            |pending| pb.set_message(format!("Waiting for {pending} CI check(s)...")),
+        )
+        .await;
+        pb.finish_and_clear();
+        outcome?;
+        println!("All CI checks passed.");
+    }
+
+    Ok(())
+}
+
+/// Edit the title, body, labels, or draft state of a bookmark's PR in
+/// place, and with `--sync-description` the description of its change.
+async fn amend_pr(args: &AmendTitleArgs) -> Result<(), StakkError> {
+    let pb = Spinner::new();
+    pb.set_message("Looking up pull request...");
+    let login = auth::resolve_login().await?;
+    let (_, forge) =
+        resolve_forge(&login.credentials, &args.remote, args.pr_remote.as_deref()).await?;
+    let pr = forge
+        .find_pr_for_branch(&args.bookmark)
+        .await?
+        .ok_or_else(|| StakkError::NoPullRequest {
+            bookmark: args.bookmark.clone(),
+        })?;
+
+    pb.set_message(format!("Updating #{}...", pr.number));
+    let body = args.body.as_deref().map(|body| {
+        let existing = pr.body.as_deref().unwrap_or_default();
+        forge::markup::MANAGED_REGIONS
+            .iter()
+            .fold(body.trim().to_string(), |body, region| {
+                region.reinsert(existing, &body)
+            })
+    });
+    if args.title.is_some() || body.is_some() {
+        forge
+            .update_pr(pr.number, args.title.as_deref(), body.as_deref())
+            .await?;
+    }
+    if !args.labels.is_empty() {
+        forge.add_labels(pr.number, &args.labels).await?;
+    }
+    if args.draft && !pr.draft {
+        forge.convert_to_draft(pr.number).await?;
+    }
+    if args.ready && pr.draft {
+        forge.mark_ready_for_review(pr.number).await?;
+    }
+    pb.finish_and_clear();
+    println!(
+        "Updated #{} ({}): {}",
+        pr.number, args.bookmark, pr.html_url
+    );
+
+    if args.sync_description && (args.title.is_some() || args.body.is_some()) {
+        let jj = cached_jj(&args.graph, Some(&args.remote)).await?;
+        let change_graph = build_graph(&jj, &args.graph).await?;
+        let tip = segment_test::segments_up_to(&change_graph, &args.bookmark)
+            .and_then(<[_]>::last)
+            .and_then(|segment| segment.commits.first())
+            .ok_or_else(|| StakkError::BookmarkNotInStack {
+                bookmark: args.bookmark.clone(),
+            })?;
+        let description = submit::amended_description(
+            &tip.description,
+            args.title.as_deref(),
+            args.body.as_deref(),
+        );
+        if description != tip.description {
+            jj.describe(&tip.change_id, &description).await?;
+            println!(
+                "Described {}; `stakk submit` pushes the change.",
+                tip.short_change_id
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Check out the stack of a PR: fetch and track the bookmarks its stack
+/// metadata lists, and start a new change on the leaf.
+async fn open_stack(args: &OpenStackArgs) -> Result<(), StakkError> {
+    let reference =
+        open_stack::parse_pr_reference(&args.pr).ok_or_else(|| StakkError::InvalidPrReference {
+            reference: args.pr.clone(),
+        })?;
+
+    let pb = Spinner::new();
+    pb.set_message("Looking up pull request...");
+    let login = auth::resolve_login().await?;
+    let (remote_name, forge) =
+        resolve_forge(&login.credentials, &args.remote, args.pr_remote.as_deref()).await?;
+    let repo = forge.repo_ref();
+    if reference.repo.as_ref().is_some_and(|r| *r != repo) {
+        return Err(StakkError::PrInOtherRepo {
+            reference: args.pr.clone(),
+            repo: repo.to_string(),
+        });
+    }
+    let pr = forge.get_pr(reference.number).await?;
+    let comments = forge.list_comments(pr.number).await?;
+    let (stack, elsewhere): (Vec<_>, Vec<_>) = open_stack::stack_of(&pr, &comments)
+        .into_iter()
+        .partition(|entry| entry.repo.as_ref().is_none_or(|r| *r == repo));
+
+    pb.set_message(format!("Fetching {} branch(es)...", stack.len()));
+    let jj = new_jj(RealJjRunner::default());
+    let names: Vec<&str> = stack.iter().map(|e| e.bookmark_name.as_str()).collect();
+    jj.fetch_branches(&remote_name, &names).await?;
+    let mut tracked = Vec::new();
+    for name in names {
+        if jj.has_remote_bookmark(name, &remote_name).await? {
+            jj.track_bookmark(name, &remote_name).await?;
+            tracked.push(name);
+        }
+    }
+    let leaf = *tracked
+        .last()
+        .ok_or(StakkError::StackBranchesGone { number: pr.number })?;
+    jj.new_on(leaf).await?;
+    pb.finish_and_clear();
+
+    println!(
+        "Checked out the stack of #{} ({} bookmark(s)), on top of '{leaf}':",
+        pr.number,
+        tracked.len()
+    );
+    for entry in &stack {
+        let status = if tracked.contains(&entry.bookmark_name.as_str()) {
+            ""
+        } else {
+            " (gone from the remote)"
+        };
+        println!("  #{} {}{status}", entry.pr_number, entry.bookmark_name);
+    }
+    for entry in &elsewhere {
+        println!(
+            "  {} {} (in another repository, not fetched)",
+            entry.pr_ref(&repo),
+            entry.bookmark_name
+        );
+    }
+    Ok(())
+}
+
+/// Hand the stack up to a bookmark over to another user: assign its PRs to
+/// them, comment how to check it out, and with `--release` forget the local
+/// bookmarks.
+async fn hand_off(args: &HandoffArgs) -> Result<(), StakkError> {
+    let owner = args.to.trim_start_matches('@');
+    let pb = Spinner::new();
+    pb.set_message("Building change graph...");
+    let jj = cached_jj(&args.graph, Some(&args.remote)).await?;
+    let change_graph = build_graph(&jj, &args.graph).await?;
+    save_query_cache(&jj)?;
+    let bookmarks: Vec<&str> = segment_test::segments_up_to(&change_graph, &args.bookmark)
+        .ok_or_else(|| StakkError::BookmarkNotInStack {
+            bookmark: args.bookmark.clone(),
+        })?
+        .iter()
+        .filter_map(|segment| segment.bookmark_names.first())
+        .map(String::as_str)
+        .collect();
+
+    pb.set_message("Looking up pull requests...");
+    let login = auth::resolve_login().await?;
+    let (remote_name, forge) =
+        resolve_forge(&login.credentials, &args.remote, args.pr_remote.as_deref()).await?;
+    let found = futures::future::join_all(bookmarks.iter().map(|b| forge.find_pr_for_branch(b)))
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+    let prs: Vec<(&str, forge::PullRequest)> = bookmarks
+        .iter()
+        .zip(found)
+        .filter_map(|(bookmark, pr)| Some((*bookmark, pr?)))
+        .collect();
+    let Some((_, leaf)) = prs.last() else {
+        return Err(StakkError::NoPullRequest {
+            bookmark: args.bookmark.clone(),
+        });
+    };
+
+    let comment = handoff::handoff_comment(owner, leaf.number);
+    let owners = [owner.to_string()];
+    for (bookmark, pr) in &prs {
+        pb.set_message(format!("Handing over #{}...", pr.number));
+        forge.add_assignees(pr.number, &owners).await?;
+        if args.reviewer {
+            forge.request_reviewers(pr.number, &owners).await?;
+        }
+        let comments = forge.list_comments(pr.number).await?;
+        match handoff::comment_write(&comments, &comment) {
+            handoff::CommentWrite::Create => {
+                forge.create_comment(pr.number, &comment).await?;
             }
+            handoff::CommentWrite::Update(id) => forge.update_comment(id, &comment).await?,
+            handoff::CommentWrite::Keep => {}
+        }
+        if args.release {
+            jj.untrack_bookmark(bookmark, &remote_name).await?;
         }
+    }
+    pb.finish_and_clear();
 
-        if change_graph.excluded_bookmark_count > 0 {
+    println!("Handed {} PR(s) over to @{owner}:", prs.len());
+    for (bookmark, pr) in &prs {
+        println!("  #{} {bookmark}", pr.number);
+    }
+    if args.release {
+        println!(
+            "Stopped tracking the branches on {remote_name}; your local bookmarks no longer push \
+             to them."
+        );
+    }
+    Ok(())
+}
+
+/// Re-render the stack comments of a stack's PRs from the change graph and
+/// the PRs on GitHub, without pushing or changing any base.
+async fn comment_refresh(args: &CommentRefreshArgs) -> Result<(), StakkError> {
+    let pb = Spinner::new();
+    pb.set_message("Building change graph...");
+
+    let jj = cached_jj(&args.graph, Some(&args.remote)).await?;
+    let login = auth::resolve_login().await?;
+    let (remote_name, forge) =
+        resolve_forge(&login.credentials, &args.remote, args.pr_remote.as_deref()).await?;
+    let change_graph = build_graph(&jj, &args.graph).await?;
+
+    let stack = match &args.bookmark {
+        Some(name) => change_graph
+            .stacks
+            .iter()
+            .find(|s| {
+                s.segments
+                    .iter()
+                    .any(|seg| seg.bookmark_names.contains(name))
+            })
+            .ok_or_else(|| submit::SubmitError::BookmarkNotFound {
+                bookmark: name.clone(),
+            })?,
+        None => locate_working_copy(&change_graph, &jj.get_working_copy().await?)?.0,
+    };
+    let bookmarks: Vec<String> = stack
+        .segments
+        .iter()
+        .filter_map(|seg| seg.bookmark_names.first().cloned())
+        .collect();
+    let leaf = bookmarks.last().expect("every stack segment is bookmarked");
+    let default_branch = match graph::trunk_branch_of(&change_graph, leaf) {
+        Some(branch) => branch.to_string(),
+        None => jj.get_default_branch().await?,
+    };
+    save_query_cache(&jj)?;
+
+    pb.set_message("Looking up pull requests...");
+    let analysis = submit::analyze_submission(
+        leaf,
+        &change_graph,
+        &default_branch,
+        &bookmarks.iter().cloned().collect(),
+    )?;
+    let mut plan = submit::create_submission_plan(
+        &analysis,
+        &forge,
+        &remote_name,
+        submit::options::PrMode::default(),
+        submit::options::SyncPrContent::None,
+        submit::options::TrailerHandling::default(),
+        &submit::content::PrTemplates::default(),
+        &HashSet::new(),
+        &HashMap::new(),
+    )
+    .await?;
+    let left_out = plan.comments_only();
+    if plan.bookmark_plans.is_empty() {
+        return Err(StakkError::NoPullRequest {
+            bookmark: leaf.clone(),
+        });
+    }
+
+    let comment_env = load_comment_env(
+        args.template.as_deref(),
+        args.comment_style,
+        args.comment_footer.as_deref(),
+    )?;
+    let result = submit::execute_submission_plan(
+        &plan,
+        &jj,
+        &forge,
+        &comment_env,
+        args.stack_placement,
+        false,
+        &pb,
+    )
+    .await;
+    pb.finish_and_clear();
+    let result = result?;
+
+    for bookmark in &left_out {
+        println!("Left out {bookmark}: it has no open PR.");
+    }
+    println!(
+        "Refreshed the stack on {} PR(s).",
+        result.stack_entries.len()
+    );
+    if let Some(error) = result.steps.to_error() {
+        print!("\n{}", result.steps);
+        return Err(error.into());
+    }
+    Ok(())
+}
+
+/// Check the change graph for internal consistency and, with
+/// `--against-remote`, that GitHub matches each stack, printing the command
+/// that fixes each stack that differs.
+async fn graph_check(args: &GraphCheckArgs) -> Result<(), StakkError> {
+    let pb = Spinner::new();
+    pb.set_message("Building change graph...");
+
+    let jj = cached_jj(&args.graph, Some(&args.remote)).await?;
+    let change_graph = build_graph(&jj, &args.graph).await?;
+    let bookmarks = jj.get_my_bookmarks(&args.graph.bookmarks_revset).await?;
+    graph::invariants::check_invariants(&change_graph, &bookmarks)?;
+    let stacks: Vec<&BranchStack> = change_graph
+        .stacks
+        .iter()
+        .filter(|s| {
+            args.bookmark.as_ref().is_none_or(|name| {
+                s.segments
+                    .iter()
+                    .any(|seg| seg.bookmark_names.contains(name))
+            })
+        })
+        .collect();
+    if let Some(name) = &args.bookmark
+        && stacks.is_empty()
+    {
+        return Err(submit::SubmitError::BookmarkNotFound {
+            bookmark: name.clone(),
+        }
+        .into());
+    }
+    if !args.against_remote {
+        pb.finish_and_clear();
+        save_query_cache(&jj)?;
+        println!(
+            "{} change graph: {} stack(s), consistent",
+            output::symbol("✓", "ok"),
+            change_graph.stacks.len()
+        );
+        return Ok(());
+    }
+
+    let login = auth::resolve_login().await?;
+    let (remote_name, forge) =
+        resolve_forge(&login.credentials, &args.remote, args.pr_remote.as_deref()).await?;
+    let trunk = jj.get_default_branch().await?;
+    let synced = jj
+        .get_synced_bookmarks(&args.graph.bookmarks_revset, &remote_name)
+        .await?;
+    let known: HashSet<&str> = change_graph
+        .stacks
+        .iter()
+        .flat_map(|s| &s.segments)
+        .flat_map(|seg| &seg.bookmark_names)
+        .map(String::as_str)
+        .collect();
+
+    pb.set_message("Looking up pull requests...");
+    let check_stacks = args.stack_placement != forge::markup::StackPlacement::None;
+    let mut audits = Vec::new();
+    for stack in stacks {
+        let names: Vec<String> = stack
+            .segments
+            .iter()
+            .filter_map(|seg| seg.bookmark_names.first().cloned())
+            .collect();
+        let default_branch = names
+            .first()
+            .and_then(|name| graph::trunk_branch_of(&change_graph, name))
+            .unwrap_or(&trunk);
+        let details = forge.find_prs_for_branches(&names).await?;
+        let views =
+            futures::future::try_join_all(names.iter().zip(details).map(|(name, details)| {
+                let (forge, synced) = (&forge, &synced);
+                async move {
+                    let pr = details.map(|d| d.pr);
+                    let listed = match (&pr, args.stack_placement) {
+                        (Some(pr), forge::markup::StackPlacement::Comment) => {
+                            let comments = forge.list_comments(pr.number).await?;
+                            forge::markup::find_stack_comment(&comments)
+                                .and_then(|c| forge::markup::parse_stack_comment(&c.body))
+                        }
+                        (Some(pr), forge::markup::StackPlacement::Body) => pr
+                            .body
+                            .as_deref()
+                            .and_then(forge::markup::parse_stack_in_body),
+                        _ => None,
+                    };
+                    Ok::<_, forge::ForgeError>(audit::RemoteView {
+                        pr,
+                        in_sync: synced.contains(name),
+                        listed: listed
+                            .map(|data| data.stack.into_iter().map(|e| e.bookmark_name).collect()),
+                    })
+                }
+            }))
+            .await?;
+        audits.push(audit::audit_stack(
+            &names,
+            default_branch,
+            &views,
+            &known,
+            check_stacks,
+        ));
+    }
+    pb.finish_and_clear();
+    save_query_cache(&jj)?;
+
+    let mut fixes = Vec::new();
+    for stack in &audits {
+        let Some(fix) = stack.fix() else {
             println!(
-                "\n  ({} bookmark(s) excluded due to merge commits)",
-                change_graph.excluded_bookmark_count,
+                "{} {}: matches {remote_name}",
+                output::symbol("✓", "ok"),
+                stack.leaf
             );
+            continue;
+        };
+        println!("{} {}:", output::symbol("✗", "FAIL"), stack.leaf);
+        for problem in &stack.problems {
+            println!("    {problem}");
+        }
+        fixes.push(fix);
+    }
+    if fixes.is_empty() {
+        return Ok(());
+    }
+    println!("\nTo fix:");
+    for fix in &fixes {
+        println!("  {fix}");
+    }
+    Err(StakkError::GraphDrift {
+        stacks: fixes.len(),
+    })
+}
+
+/// Replay the failed steps recorded by the last submit.
+async fn retry_failed_steps() -> Result<(), StakkError> {
+    let root = new_jj(RealJjRunner::default()).get_workspace_root().await?;
+    let path = journal::Journal::path(&root);
+    let journal = journal::Journal::load(&path)?;
+    let Some(repo) = journal.repo.filter(|_| !journal.steps.is_empty()) else {
+        println!("Nothing to retry: the last submit completed every step.");
+        return Ok(());
+    };
+
+    let login = auth::resolve_login().await?;
+    let forge =
+        forge::github::GitHubForge::new(&login.credentials, repo.owner.clone(), repo.repo.clone())?;
+
+    let pb = Spinner::new();
+    pb.set_message(format!("Retrying {} step(s)...", journal.steps.len()));
+    let report = submit::retry_journal_steps(&forge, &journal.steps).await;
+    pb.finish_and_clear();
+
+    journal::Journal {
+        repo: Some(repo),
+        steps: report.journal_steps(),
+        undo: Vec::new(),
+        plan: None,
+    }
+    .save(&path)?;
+
+    print!("{report}");
+    match report.to_error() {
+        Some(error) => Err(error.into()),
+        None => Ok(()),
+    }
+}
+
+/// Handle a submit that failed with `cause` after making `actions`: undo
+/// them with `atomic`, else record them for `stakk undo`. Returns the error
+/// to exit with.
+async fn stopped_partway<F: Forge>(
+    forge: &F,
+    plan: &submit::SubmissionPlan,
+    journal_path: &Path,
+    cause: submit::SubmitError,
+    actions: Vec<journal::SubmitAction>,
+    atomic: bool,
+) -> StakkError {
+    let undo = if atomic {
+        let pb = Spinner::new();
+        pb.set_message("Undoing the changes made so far...");
+        let report = submit::undo_actions(forge, &actions).await;
+        pb.finish_and_clear();
+        eprint!("Submission failed; undid the changes made so far:\n{report}");
+        report.failed_actions()
+    } else {
+        eprintln!("Submission stopped partway. Done so far:");
+        for action in &actions {
+            eprintln!("  {}", action.describe());
+        }
+        eprintln!(
+            "Run `stakk submit --continue` to finish, or `stakk undo` to close the created PRs \
+             and restore changed bases."
+        );
+        actions.clone()
+    };
+
+    let saved = journal::Journal {
+        repo: Some(plan.repo.clone()),
+        steps: Vec::new(),
+        undo,
+        plan: (!atomic).then(|| plan.clone()),
+    }
+    .save(journal_path);
+    match saved {
+        Err(e) => e.into(),
+        Ok(()) if atomic => cause.into(),
+        Ok(()) => submit::SubmitError::Incomplete {
+            source: Box::new(cause),
+            actions,
+        }
+        .into(),
+    }
+}
+
+/// Undo the changes of the last submit, if it stopped partway.
+async fn undo_stopped_submit() -> Result<(), StakkError> {
+    let root = new_jj(RealJjRunner::default()).get_workspace_root().await?;
+    let path = journal::Journal::path(&root);
+    let journal = journal::Journal::load(&path)?;
+    let Some(repo) = journal.repo.filter(|_| !journal.undo.is_empty()) else {
+        println!("Nothing to undo: the last submit did not stop partway.");
+        return Ok(());
+    };
+
+    let login = auth::resolve_login().await?;
+    let forge =
+        forge::github::GitHubForge::new(&login.credentials, repo.owner.clone(), repo.repo.clone())?;
+
+    let pb = Spinner::new();
+    pb.set_message(format!("Undoing {} change(s)...", journal.undo.len()));
+    let report = submit::undo_actions(&forge, &journal.undo).await;
+    pb.finish_and_clear();
+
+    journal::Journal {
+        repo: Some(repo),
+        steps: journal.steps,
+        undo: report.failed_actions(),
+        plan: None,
+    }
+    .save(&path)?;
+
+    print!("{report}");
+    match report.to_error() {
+        Some(error) => Err(error.into()),
+        None => Ok(()),
+    }
+}
+
+/// Delete a bookmark and propagate the deletion to its PR and stack. With
+/// `abandon`, the bookmark's changes are abandoned as well.
+async fn delete_bookmark(args: &DeleteArgs, abandon: bool) -> Result<(), StakkError> {
+    let pb = Spinner::new();
+    pb.set_message("Building change graph...");
+
+    let jj = graph_jj(&args.graph, Some(&args.remote));
+    let login = auth::resolve_login().await?;
+    let (remote_name, forge) =
+        resolve_forge(&login.credentials, &args.remote, args.pr_remote.as_deref()).await?;
+
+    let change_graph = build_graph(&jj, &args.graph).await?;
+    let default_branch = match graph::trunk_branch_of(&change_graph, &args.bookmark) {
+        Some(branch) => branch.to_string(),
+        None => jj.get_default_branch().await?,
+    };
+
+    pb.set_message("Checking for affected pull requests...");
+    let plan = delete::create_delete_plan(
+        &args.bookmark,
+        &change_graph,
+        &default_branch,
+        &remote_name,
+        &forge,
+        abandon,
+    )
+    .await?;
+    pb.finish_and_clear();
+
+    if args.dry_run {
+        println!(
+            "DRY RUN {} no changes will be made.\n",
+            output::symbol("—", "-")
+        );
+    }
+    println!("{plan}");
+    if args.dry_run {
+        return Ok(());
+    }
+
+    let comment_env = load_comment_env(
+        args.template.as_deref(),
+        args.comment_style,
+        args.comment_footer.as_deref(),
+    )?;
+
+    delete::execute_delete_plan(&plan, &jj, &forge, &comment_env).await?;
+    update_pr_cache(&jj.get_workspace_root().await?, |cache| {
+        cache.forget([plan.bookmark.as_str()]);
+    });
+    if abandon {
+        println!("Abandoned bookmark '{}'.", plan.bookmark);
+    } else {
+        println!("Deleted bookmark '{}'.", plan.bookmark);
+    }
+
+    Ok(())
+}
+
+/// Put the working copy on a bookmark's segment. With `--submit`, wait for
+/// the working copy to leave the edited change, then submit every stack
+/// through the bookmark with the given submit options.
+async fn edit_segment(mut args: EditArgs) -> Result<(), StakkError> {
+    let bookmark = args
+        .submit_args
+        .bookmark
+        .clone()
+        .expect("clap requires a bookmark for edit");
+    let jj = graph_jj(&args.submit_args.graph, Some(&args.submit_args.remote));
+    let change_graph = build_graph(&jj, &args.submit_args.graph).await?;
+    if edit::affected_leaves(&change_graph, &bookmark).is_empty() {
+        return Err(StakkError::BookmarkNotInStack { bookmark });
+    }
+
+    if args.new {
+        jj.new_after(&bookmark).await?;
+        println!("Started a new change after '{bookmark}'.");
+    } else {
+        jj.edit(&bookmark).await?;
+        println!("Editing '{bookmark}'.");
+    }
+    if !args.submit {
+        return Ok(());
+    }
+
+    let (operation, change_id) = jj.watch_working_copy().await?;
+    eprintln!(
+        "Waiting for the working copy to leave this change (e.g. `jj new`); the stacks through \
+         '{bookmark}' are submitted then. Press Ctrl-C to stop without submitting."
+    );
+    let watch = RefCell::new(edit::EditWatch::new(change_id, operation));
+    let schedule = Schedule::fixed(std::time::Duration::from_secs(args.poll_interval));
+    let outcome = poll::poll_until(
+        &schedule,
+        std::future::pending(),
+        |_| {
+            let (jj, watch) = (&jj, &watch);
+            async move {
+                let (operation, change_id) = jj.watch_working_copy().await?;
+                Ok::<_, JjError>(watch.borrow_mut().observe(operation, &change_id))
+            }
+        },
+        |()| {},
+    )
+    .await?;
+    if !matches!(outcome, Polled::Done(_)) {
+        return Ok(());
+    }
+
+    let change_graph = build_graph(&jj, &args.submit_args.graph).await?;
+    let leaves = edit::affected_leaves(&change_graph, &bookmark);
+    if leaves.is_empty() {
+        return Err(StakkError::BookmarkNotInStack { bookmark });
+    }
+    for leaf in leaves {
+        eprintln!("==> Submitting {leaf}");
+        args.submit_args.bookmark = Some(leaf);
+        submit_bookmark(&args.submit_args).await?;
+    }
+    Ok(())
+}
+
+async fn restack(mut args: RebaseArgs) -> Result<(), StakkError> {
+    let jj = graph_jj(&args.submit_args.graph, Some(&args.submit_args.remote));
+    let change_graph = build_graph(&jj, &args.submit_args.graph).await?;
+    let bookmark = if let Some(bookmark) = args.submit_args.bookmark.clone() {
+        bookmark
+    } else {
+        let working_copy = jj.get_working_copy().await?;
+        let (stack, segment_idx) = locate_working_copy(&change_graph, &working_copy)?;
+        stack.segments[segment_idx]
+            .bookmark_names
+            .first()
+            .cloned()
+            .ok_or(StakkError::WorkingCopyUnbookmarked {
+                change_id: working_copy.short_change_id,
+            })?
+    };
+    let plan = rebase::plan_restacks(&change_graph, &bookmark).ok_or_else(|| {
+        StakkError::BookmarkNotInStack {
+            bookmark: bookmark.clone(),
         }
+    })?;
+
+    print!("{plan}");
+    if args.submit_args.dry_run || plan.restacks.is_empty() {
+        return Ok(());
+    }
+    for restack in &plan.restacks {
+        jj.rebase(&restack.source, &bookmark).await?;
+    }
+    println!("Rebased {} branch(es).", plan.restacks.len());
+    if !args.submit {
+        return Ok(());
+    }
+
+    let change_graph = build_graph(&jj, &args.submit_args.graph).await?;
+    for leaf in edit::affected_leaves(&change_graph, &bookmark) {
+        eprintln!("==> Submitting {leaf}");
+        args.submit_args.bookmark = Some(leaf);
+        submit_bookmark(&args.submit_args).await?;
     }
+    Ok(())
+}
+
+async fn sync_stacks(args: &SyncArgs) -> Result<(), StakkError> {
+    let pb = Spinner::new();
+    let jj = graph_jj(&args.graph, Some(&args.remote));
+    let login = auth::resolve_login().await?;
+    let (remote_name, forge) =
+        resolve_forge(&login.credentials, &args.remote, args.pr_remote.as_deref()).await?;
 
+    if !args.dry_run {
+        pb.set_message(format!("Fetching from {remote_name}..."));
+        jj.fetch_branches(&remote_name, &[]).await?;
+    }
+
+    pb.set_message("Building change graph...");
+    let default_branch = jj.get_default_branch().await?;
+    let change_graph = build_graph(&jj, &args.graph).await?;
+
+    pb.set_message("Checking for merged pull requests...");
+    let merged = sync::find_merged_prs(&change_graph, &forge).await?;
+    let plan = sync::plan_sync(&change_graph, &merged, &default_branch);
+    pb.finish_and_clear();
+
+    if args.dry_run {
+        println!(
+            "DRY RUN {} no changes will be made.\n",
+            output::symbol("—", "-")
+        );
+    }
+    println!("{plan}");
+    if args.dry_run || plan.is_empty() {
+        return Ok(());
+    }
+
+    let operation = jj.current_operation().await?;
+    let conflicted_before = triage::conflicted_change_ids(&jj).await?;
+    sync::execute_sync_plan(&plan, &jj).await?;
+    update_pr_cache(&jj.get_workspace_root().await?, |cache| {
+        cache.forget(
+            plan.merged
+                .iter()
+                .flat_map(|seg| &seg.bookmarks)
+                .map(String::as_str),
+        );
+    });
+
+    let conflicts = triage::new_conflicts(&jj, &conflicted_before).await?;
+    if !conflicts.is_empty() {
+        println!("The rebase left {} conflicted change(s):", conflicts.len());
+        for change in &conflicts {
+            print!("{change}");
+        }
+        match triage::triage(&jj, &conflicts, &operation).await? {
+            triage::Outcome::Aborted => {
+                println!("Restored the repository to before the sync.");
+                return Ok(());
+            }
+            triage::Outcome::Done { remaining: 0 } => {}
+            triage::Outcome::Done { remaining } => {
+                println!(
+                    "Synced, leaving {remaining} conflicted change(s). Resolve them with `jj \
+                     resolve` before submitting, or undo the sync with `jj op restore \
+                     {operation}`."
+                );
+                return Ok(());
+            }
+        }
+    }
+    println!("Synced. Run `stakk submit` to update the remaining PRs' bases and stack comments.");
+
+    Ok(())
+}
+
+async fn backport_stack(args: &BackportArgs) -> Result<(), StakkError> {
+    let pb = Spinner::new();
+    let jj = graph_jj(&args.graph, Some(&args.remote));
+    let login = auth::resolve_login().await?;
+    let (remote_name, forge) =
+        resolve_forge(&login.credentials, &args.remote, args.pr_remote.as_deref()).await?;
+
+    if !args.dry_run {
+        pb.set_message(format!("Fetching {} from {remote_name}...", args.onto));
+        jj.fetch_branches(&remote_name, &[&args.onto]).await?;
+    }
+
+    pb.set_message("Building change graph...");
+    let default_branch = jj.get_default_branch().await?;
+    let change_graph = build_graph(&jj, &args.graph).await?;
+    let analysis = submit::analyze_submission(
+        &args.bookmark,
+        &change_graph,
+        &default_branch,
+        &HashSet::from([args.bookmark.clone()]),
+    )?;
+    let mut segments = analysis.segments;
+    if args.only {
+        segments.drain(..segments.len() - 1);
+    }
+
+    pb.set_message("Looking up pull requests...");
+    let prs = backport::find_original_prs(&segments, &forge).await?;
+    let plan = backport::plan_backport(
+        segments,
+        &args.onto,
+        &remote_name,
+        &bookmark_names(&change_graph),
+        &prs,
+    )?;
+    pb.finish_and_clear();
+
+    if args.dry_run {
+        println!(
+            "DRY RUN {} no changes will be made.\n",
+            output::symbol("—", "-")
+        );
+        println!("{plan}");
+        return Ok(());
+    }
+    println!("{plan}");
+
+    let pb = Spinner::new();
+    pb.set_message(format!("Copying the stack onto {}...", args.onto));
+    let copies = backport::duplicate_stack(&plan, &jj).await?;
+    let remote_targets = jj.get_remote_targets(&remote_name).await?;
+    let analysis = submit::SubmissionAnalysis {
+        segments: copies,
+        default_branch: args.onto.clone(),
+        stack_memberships: HashMap::new(),
+    };
+    let mut submission = submit::create_submission_plan(
+        &analysis,
+        &forge,
+        &remote_name,
+        submit::options::PrMode::default(),
+        submit::options::SyncPrContent::default(),
+        submit::options::TrailerHandling::default(),
+        &submit::content::PrTemplates::default(),
+        &HashSet::new(),
+        &remote_targets,
+    )
+    .await?;
+    backport::link_originals(&mut submission, &plan);
+    pb.finish_and_clear();
+    println!("{submission}");
+
+    let comment_env =
+        forge::markup::build_comment_env(None, forge::markup::CommentStyle::default())?;
+    let spinner = Spinner::new();
+    let result = submit::execute_submission_plan(
+        &submission,
+        &jj,
+        &forge,
+        &comment_env,
+        forge::markup::StackPlacement::default(),
+        false,
+        &spinner,
+    )
+    .await;
+    spinner.finish_and_clear();
+    let result = result?;
+
+    update_pr_cache(&jj.get_workspace_root().await?, |cache| {
+        cache.record_stack(&result.stack_entries);
+    });
+
+    println!(
+        "\nBackported {} bookmark(s) onto {}.",
+        result.stack_entries.len(),
+        args.onto
+    );
+    if let Some(error) = result.steps.to_error() {
+        print!("\n{}", result.steps);
+        return Err(error.into());
+    }
     Ok(())
 }
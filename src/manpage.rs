@@ -0,0 +1,71 @@
+//! `stakk completions --man`: one manual page for stakk and every
+//! subcommand, generated from the clap definitions.
+//!
+//! clap_mangen renders a page per command. stakk's page is that top-level
+//! page followed by a section per subcommand, `STAKK SUBMIT` and so on, with
+//! the subcommand's own synopsis, description, and options as subsections.
+
+use std::io;
+use std::io::Write;
+
+use clap::Command;
+use clap_mangen::Man;
+
+/// Write the manual page for `cmd` and all its subcommands to `out`.
+pub fn render(cmd: Command, out: &mut dyn Write) -> io::Result<()> {
+    let mut cmd = cmd.disable_help_subcommand(true);
+    cmd.build();
+    Man::new(cmd.clone()).render(out)?;
+    render_subcommands(&cmd, out)
+}
+
+fn render_subcommands(cmd: &Command, out: &mut dyn Write) -> io::Result<()> {
+    for sub in cmd.get_subcommands().filter(|sub| !sub.is_hide_set()) {
+        let name = sub.get_bin_name().unwrap_or_else(|| sub.get_name());
+        writeln!(out, ".SH \"{}\"", name.to_uppercase())?;
+
+        let man = Man::new(sub.clone());
+        let mut sections = Vec::new();
+        man.render_synopsis_section(&mut sections)?;
+        man.render_description_section(&mut sections)?;
+        if sub.get_arguments().any(|arg| !arg.is_hide_set()) {
+            man.render_options_section(&mut sections)?;
+        }
+        // Each section repeats the page preamble defining `\*(Aq`; the main
+        // page already has it. The sections themselves become subsections.
+        for line in String::from_utf8_lossy(&sections).lines() {
+            if line.starts_with(".ie \\n(.g .ds Aq") || line.starts_with(".el .ds Aq") {
+                continue;
+            }
+            match line.strip_prefix(".SH ") {
+                Some(title) => writeln!(out, ".SS {title}")?,
+                None => writeln!(out, "{line}")?,
+            }
+        }
+
+        render_subcommands(sub, out)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+    use crate::cli::Cli;
+
+    #[test]
+    fn page_covers_subcommands_and_their_flags() {
+        let mut out = Vec::new();
+        render(Cli::command(), &mut out).unwrap();
+        let page = String::from_utf8(out).unwrap();
+
+        assert!(page.starts_with(".ie \\n(.g .ds Aq \\(aq\n.el .ds Aq '\n.TH stakk 1"));
+
+        assert!(page.contains("\n.SH \"STAKK SUBMIT\"\n.SS SYNOPSIS\n"));
+        assert!(page.contains("\n.SH \"STAKK PR CHECKS\"\n"));
+        assert!(page.contains("\\-\\-auto\\-bookmark\\-pattern"));
+        assert_eq!(page.matches(".ds Aq \\(aq").count(), 1);
+    }
+}
@@ -0,0 +1,205 @@
+//! Free-form notes on a bookmark's change, kept next to the PR cache.
+//!
+//! `stakk note <bookmark>` edits the note of the bookmark's segment in
+//! `$VISUAL` / `$EDITOR`. Notes live in `.jj/stakk/notes.json` under the
+//! workspace root, keyed by change ID so they survive rebases and bookmark
+//! renames. With `--include-notes`, `stakk submit` writes each note into a
+//! section of its PR body.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use miette::Diagnostic;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+
+/// Errors from reading, writing, or editing notes.
+#[derive(Debug, Error, Diagnostic)]
+pub enum NotesError {
+    #[error("failed to read notes {path}")]
+    #[diagnostic(code(stakk::notes::read_failed), help("check file permissions"))]
+    Read {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("failed to parse notes {path}")]
+    #[diagnostic(
+        code(stakk::notes::parse_failed),
+        help("fix the JSON by hand, or delete the file to drop every note")
+    )]
+    Parse {
+        path: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error("failed to write notes {path}")]
+    #[diagnostic(code(stakk::notes::write_failed), help("check file permissions"))]
+    Write {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("editor `{editor}` failed")]
+    #[diagnostic(
+        code(stakk::notes::editor_failed),
+        help("set $VISUAL or $EDITOR to an editor that waits until the file is closed")
+    )]
+    Editor {
+        editor: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+/// Notes keyed by change ID.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Notes {
+    pub notes: BTreeMap<String, String>,
+}
+
+impl Notes {
+    /// Path of the notes file in the workspace rooted at `workspace_root`.
+    pub fn path(workspace_root: &Path) -> PathBuf {
+        workspace_root.join(".jj").join("stakk").join("notes.json")
+    }
+
+    /// Load the notes, or none if the file does not exist yet.
+    pub fn load(path: &Path) -> Result<Self, NotesError> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(source) => {
+                return Err(NotesError::Read {
+                    path: path.display().to_string(),
+                    source,
+                });
+            }
+        };
+        serde_json::from_str(&contents).map_err(|source| NotesError::Parse {
+            path: path.display().to_string(),
+            source,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), NotesError> {
+        let write_failed = |source| NotesError::Write {
+            path: path.display().to_string(),
+            source,
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(write_failed)?;
+        }
+        let json = serde_json::to_string_pretty(self).expect("Notes serializes to JSON");
+        std::fs::write(path, json).map_err(write_failed)
+    }
+
+    /// The note on `change_id`, if it has one.
+    pub fn get(&self, change_id: &str) -> Option<&str> {
+        self.notes.get(change_id).map(String::as_str)
+    }
+
+    /// Set the note on `change_id`. Text that is blank once trimmed removes
+    /// the note.
+    pub fn set(&mut self, change_id: &str, text: &str) {
+        let text = text.trim();
+        if text.is_empty() {
+            self.notes.remove(change_id);
+        } else {
+            self.notes
+                .insert(change_id.to_string(), format!("{text}\n"));
+        }
+    }
+}
+
+/// The editor to edit notes with: `$VISUAL`, then `$EDITOR`, then `vi`.
+fn editor_command() -> String {
+    ["VISUAL", "EDITOR"]
+        .into_iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+/// Let the user edit `text` in their editor, through a scratch file at
+/// `scratch`, and return the result.
+///
+/// The editor command is split on whitespace, so `code --wait` works; the
+/// scratch file is passed as its last argument.
+pub async fn edit_in_editor(scratch: &Path, text: &str) -> Result<String, NotesError> {
+    let write_failed = |source| NotesError::Write {
+        path: scratch.display().to_string(),
+        source,
+    };
+    if let Some(dir) = scratch.parent() {
+        std::fs::create_dir_all(dir).map_err(write_failed)?;
+    }
+    std::fs::write(scratch, text).map_err(write_failed)?;
+
+    let editor = editor_command();
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let status = tokio::process::Command::new(program)
+        .args(words)
+        .arg(scratch)
+        .status()
+        .await
+        .map_err(|source| NotesError::Editor {
+            editor: editor.clone(),
+            source,
+        })?;
+    if !status.success() {
+        return Err(NotesError::Editor {
+            source: std::io::Error::other(format!("{program} exited with {status}")),
+            editor,
+        });
+    }
+
+    let edited = std::fs::read_to_string(scratch).map_err(|source| NotesError::Read {
+        path: scratch.display().to_string(),
+        source,
+    })?;
+    // The scratch file is only a vehicle for the editor; a leftover one is
+    // overwritten next time.
+    let _ = std::fs::remove_file(scratch);
+    Ok(edited)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_the_file() {
+        let dir = std::env::temp_dir().join(format!("stakk-notes-test-{}", std::process::id()));
+        let path = Notes::path(&dir);
+        assert_eq!(Notes::load(&path).unwrap(), Notes::default());
+
+        let mut notes = Notes::default();
+        notes.set("zzzz", "Waiting on the schema migration.");
+        notes.save(&path).unwrap();
+
+        let loaded = Notes::load(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            loaded.get("zzzz"),
+            Some("Waiting on the schema migration.\n")
+        );
+        assert_eq!(loaded.get("yyyy"), None);
+    }
+
+    #[test]
+    fn blank_text_removes_the_note() {
+        let mut notes = Notes::default();
+        notes.set("zzzz", "  context  \n\n");
+        assert_eq!(notes.get("zzzz"), Some("context\n"));
+        notes.set("zzzz", " \n");
+        assert_eq!(notes.get("zzzz"), None);
+    }
+}
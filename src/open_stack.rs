@@ -0,0 +1,146 @@
+//! `stakk open-stack`: check out a stack someone else submitted, from one of
+//! its PRs.
+//!
+//! The stack comment (or the stack section of the PR body) records every
+//! bookmark of the stack with its PR, bottom first. Their branches are
+//! fetched and tracked as local bookmarks, and a new working-copy change is
+//! started on the leaf. A PR without stack metadata is checked out on its
+//! own.
+
+use stakk_core::forge::Comment;
+use stakk_core::forge::ForgeRepoRef;
+use stakk_core::forge::PullRequest;
+use stakk_core::forge::markup;
+use stakk_core::forge::markup::StackEntry;
+
+/// A PR named on the command line: `12`, `#12`, or the PR's web URL.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PrReference {
+    /// The repository named by a URL; `None` for a bare number.
+    pub repo: Option<ForgeRepoRef>,
+    pub number: u64,
+}
+
+/// Parse `reference`, or `None` if it names no PR. URLs are taken from any
+/// host, so GitHub Enterprise links work too; anything after the number
+/// (`/files`, a fragment) is ignored.
+pub fn parse_pr_reference(reference: &str) -> Option<PrReference> {
+    let reference = reference.trim();
+    if let Ok(number) = reference.trim_start_matches('#').parse() {
+        return Some(PrReference { repo: None, number });
+    }
+    let path = reference.split_once("://")?.1;
+    let segments: Vec<&str> = path.split(['/', '#', '?']).collect();
+    let pull = segments.iter().position(|s| *s == "pull")?;
+    let number = segments.get(pull + 1)?.parse().ok()?;
+    // `host/owner/repo/pull/N`: the owner and repo come right before
+    // `pull`, after the host.
+    let (owner, repo) = match pull.checked_sub(2)? {
+        0 => return None,
+        start => (segments[start], segments[start + 1]),
+    };
+    Some(PrReference {
+        repo: Some(ForgeRepoRef::new(owner, repo)),
+        number,
+    })
+}
+
+/// The stack `pr` belongs to, bottom first, from its stack comment or the
+/// stack section of its body. A PR without stack metadata is a stack of
+/// one.
+pub fn stack_of(pr: &PullRequest, comments: &[Comment]) -> Vec<StackEntry> {
+    let data = markup::find_stack_comment(comments)
+        .and_then(|comment| markup::parse_stack_comment(&comment.body))
+        .or_else(|| pr.body.as_deref().and_then(markup::parse_stack_in_body));
+    match data {
+        Some(data) => data.stack,
+        None => vec![StackEntry {
+            bookmark_name: pr.head_ref.clone(),
+            pr_url: pr.html_url.clone(),
+            pr_number: pr.number,
+            repo: None,
+        }],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use stakk_core::forge::PrState;
+    use stakk_core::forge::markup::CommentStyle;
+    use stakk_core::forge::markup::STAKK_REPO_URL;
+    use stakk_core::forge::markup::StackCommentContext;
+    use stakk_core::forge::markup::StackCommentData;
+
+    use super::*;
+
+    #[test]
+    fn references_are_numbers_or_urls() {
+        let number = |number| PrReference { repo: None, number };
+        assert_eq!(parse_pr_reference("12"), Some(number(12)));
+        assert_eq!(parse_pr_reference("#12"), Some(number(12)));
+        assert_eq!(
+            parse_pr_reference("https://github.com/o/r/pull/12/files#diff"),
+            Some(PrReference {
+                repo: Some(ForgeRepoRef::new("o", "r")),
+                number: 12,
+            })
+        );
+        assert_eq!(
+            parse_pr_reference("https://ghe.example.com/o/r/pull/7")
+                .unwrap()
+                .repo,
+            Some(ForgeRepoRef::new("o", "r"))
+        );
+        assert_eq!(parse_pr_reference("https://github.com/o/r/issues/12"), None);
+        assert_eq!(parse_pr_reference("https://github.com/pull/12"), None);
+        assert_eq!(parse_pr_reference("feat"), None);
+    }
+
+    fn pr(body: Option<String>) -> PullRequest {
+        PullRequest {
+            number: 2,
+            html_url: "https://github.com/o/r/pull/2".to_string(),
+            title: "B".to_string(),
+            head_ref: "b".to_string(),
+            base_ref: "a".to_string(),
+            state: PrState::Open,
+            body,
+            draft: false,
+        }
+    }
+
+    fn entry(bookmark: &str, number: u64) -> StackEntry {
+        StackEntry {
+            bookmark_name: bookmark.to_string(),
+            pr_url: format!("https://github.com/o/r/pull/{number}"),
+            pr_number: number,
+            repo: None,
+        }
+    }
+
+    #[test]
+    fn stack_comes_from_the_body_section() {
+        let data = StackCommentData {
+            version: 1,
+            stack: vec![entry("a", 1), entry("b", 2), entry("c", 3)],
+        };
+        let ctx = StackCommentContext {
+            stack: vec![],
+            stack_size: 3,
+            default_branch: "main".to_string(),
+            current_bookmark: "b".to_string(),
+            stakk_url: STAKK_REPO_URL.to_string(),
+        };
+        let env = markup::build_comment_env(None, CommentStyle::Default).unwrap();
+        let template = env.get_template("stack_comment").unwrap();
+        let section = markup::format_stack_comment(&data, &ctx, &template).unwrap();
+        let body = markup::splice_stack_into_body("Details.", &section);
+
+        assert_eq!(stack_of(&pr(Some(body)), &[]), data.stack);
+    }
+
+    #[test]
+    fn a_pr_without_metadata_is_a_stack_of_one() {
+        assert_eq!(stack_of(&pr(None), &[]), [entry("b", 2)]);
+    }
+}
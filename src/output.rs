@@ -0,0 +1,131 @@
+//! Terminal output style.
+//!
+//! By default stakk decorates its output with Unicode symbols and animated
+//! spinners. With `--plain` it sticks to ASCII text and writes progress as
+//! ordinary lines, with no control sequences, for screen readers and dumb
+//! terminals. The style is set once at startup and read wherever output is
+//! produced.
+//!
+//! Colors follow `--color`: by default they are used on terminals unless
+//! `NO_COLOR` is set, and never in plain mode.
+
+use std::borrow::Cow;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+use stakk_core::submit::events::SubmitEvent;
+use stakk_core::submit::events::SubmitObserver;
+
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// Switch to plain output for the rest of the process.
+pub fn set_plain(plain: bool) {
+    PLAIN.store(plain, Ordering::Relaxed);
+}
+
+pub fn is_plain() -> bool {
+    PLAIN.load(Ordering::Relaxed)
+}
+
+/// When to color output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorChoice {
+    /// On terminals, unless `NO_COLOR` is set or in plain mode.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Color output as `choice` says for the rest of the process. Call after
+/// [`set_plain`].
+pub fn set_color(choice: ColorChoice) {
+    let enabled = match choice {
+        ColorChoice::Auto if is_plain() => false,
+        ColorChoice::Auto => return,
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    };
+    console::set_colors_enabled(enabled);
+    console::set_colors_enabled_stderr(enabled);
+}
+
+/// `decorated`, or its ASCII equivalent `plain` in plain mode.
+pub fn symbol(decorated: &'static str, plain: &'static str) -> &'static str {
+    if is_plain() { plain } else { decorated }
+}
+
+/// The ASCII stand-in for a Unicode symbol drawn by the selection screens,
+/// if it has one.
+pub fn ascii_symbol(symbol: &str) -> Option<&'static str> {
+    Some(match symbol {
+        "\u{25cb}" => "o",                                        // ○
+        "\u{25c6}" => "*",                                        // ◆
+        "\u{2502}" => "|",                                        // │
+        "\u{251c}" | "\u{256f}" | "\u{2570}" | "\u{253c}" => "+", // ├ ╯ ╰ ┼
+        "\u{2500}" | "\u{2014}" => "-",                           // ─ —
+        "\u{2190}" => "<",                                        // ←
+        "\u{2192}" => ">",                                        // →
+        "\u{2191}" => "^",                                        // ↑
+        "\u{2193}" => "v",                                        // ↓
+        "\u{2026}" => ".",                                        // …
+        // Braille spinner frames all become one mark, so nothing animates.
+        s if s.starts_with(|c| ('\u{2800}'..='\u{28ff}').contains(&c)) => "*",
+        _ => return None,
+    })
+}
+
+/// An animated spinner, or in plain mode one stderr line per message.
+pub struct Spinner(Option<indicatif::ProgressBar>);
+
+impl Spinner {
+    pub fn new() -> Self {
+        if is_plain() {
+            return Self(None);
+        }
+        let pb = indicatif::ProgressBar::new_spinner();
+        pb.enable_steady_tick(std::time::Duration::from_millis(120));
+        Self(Some(pb))
+    }
+
+    pub fn set_message(&self, message: impl Into<Cow<'static, str>>) {
+        match &self.0 {
+            Some(pb) => pb.set_message(message),
+            None => eprintln!("{}", message.into()),
+        }
+    }
+
+    pub fn finish_and_clear(&self) {
+        if let Some(pb) = &self.0 {
+            pb.finish_and_clear();
+        }
+    }
+}
+
+/// Shows running steps as the spinner message and prints outcomes below it.
+impl SubmitObserver for Spinner {
+    fn on_event(&self, event: SubmitEvent) {
+        if event.is_step() {
+            self.set_message(event.to_string());
+            return;
+        }
+        match &self.0 {
+            Some(pb) => pb.println(format!("  {event}")),
+            None => println!("  {event}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_symbol_covers_graph_and_spinner() {
+        assert_eq!(ascii_symbol("\u{25cb}"), Some("o"));
+        assert_eq!(ascii_symbol("\u{256f}"), Some("+"));
+        assert_eq!(ascii_symbol("\u{280b}"), Some("*"));
+        assert_eq!(ascii_symbol("a"), None);
+        assert_eq!(ascii_symbol(" "), None);
+    }
+}
@@ -0,0 +1,259 @@
+//! The submission plan as shown before a submit: one aligned line per
+//! bookmark with its base, each step under it marked like a diff (`+` for
+//! PRs created, `~` for changes, `=` for what is left alone) and colored by
+//! kind, and a closing line counting the PRs of each kind.
+//!
+//! Colors come from `console`, which leaves them out when stdout is not a
+//! terminal, `NO_COLOR` is set, or `--color=never` is given.
+
+use std::fmt::Write as _;
+
+use console::Style;
+use stakk_core::submit::PlanStep;
+use stakk_core::submit::PlanStepKind;
+use stakk_core::submit::SubmissionPlan;
+
+use crate::output;
+
+/// Render `plan` for the terminal.
+pub fn render(plan: &SubmissionPlan) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "Submission plan ({}):", plan.description());
+
+    let name_width = plan
+        .bookmark_plans
+        .iter()
+        .map(|bp| console::measure_text_width(&bp.bookmark_name))
+        .max()
+        .unwrap_or(0);
+    let base_width = plan
+        .bookmark_plans
+        .iter()
+        .map(|bp| console::measure_text_width(&bp.base))
+        .max()
+        .unwrap_or(0);
+    let arrow = output::symbol("→", ">");
+
+    let mut counts = [0usize; 4];
+    for bp in &plan.bookmark_plans {
+        let steps = plan.steps(bp);
+        // A bookmark counts as the weightiest of its steps.
+        let kind = steps
+            .iter()
+            .map(|step| step.kind)
+            .max()
+            .unwrap_or(PlanStepKind::UpToDate);
+        counts[kind as usize] += 1;
+
+        let name = console::pad_str(
+            &bp.bookmark_name,
+            name_width,
+            console::Alignment::Left,
+            None,
+        );
+        let reviews = bp
+            .reviews
+            .filter(|r| !r.is_empty())
+            .map(|r| format!("  [{r}]"))
+            .unwrap_or_default();
+        let checks = bp
+            .checks_summary()
+            .map(|c| format!("  [{c}]"))
+            .unwrap_or_default();
+        let base = format!("{arrow} {}", bp.base);
+        let base = console::pad_str(&base, base_width + 2, console::Alignment::Left, None);
+        let header = format!("{name}  {base}{reviews}{checks}");
+        let header = if kind == PlanStepKind::UpToDate {
+            style(kind).apply_to(header.trim_end())
+        } else {
+            Style::new().bold().apply_to(header.trim_end())
+        };
+        let _ = writeln!(out, "  {header}");
+        for step in &steps {
+            write_step(&mut out, step);
+        }
+    }
+
+    let retargets = plan.retarget_steps();
+    if !retargets.is_empty() {
+        let _ = writeln!(out, "  Stacked PRs outside this submission:");
+        for step in &retargets {
+            write_step(&mut out, step);
+        }
+        counts[PlanStepKind::Retarget as usize] += retargets.len();
+    }
+
+    let _ = writeln!(out, "{}", summary(&counts));
+    out
+}
+
+/// One step line, marked and colored by its kind.
+fn write_step(out: &mut String, step: &PlanStep) {
+    let marker = match step.kind {
+        PlanStepKind::Create => '+',
+        PlanStepKind::Retarget | PlanStepKind::Update => '~',
+        PlanStepKind::UpToDate => '=',
+    };
+    let line = format!("{marker} {}", step.text);
+    let _ = writeln!(out, "    {}", style(step.kind).apply_to(line));
+}
+
+fn style(kind: PlanStepKind) -> Style {
+    match kind {
+        PlanStepKind::Create => Style::new().green(),
+        PlanStepKind::Retarget => Style::new().yellow(),
+        PlanStepKind::Update => Style::new().cyan(),
+        PlanStepKind::UpToDate => Style::new().dim(),
+    }
+}
+
+/// The closing line: how many PRs the plan creates, retargets, and
+/// otherwise updates, and how many it leaves alone. `counts` is indexed by
+/// [`PlanStepKind`].
+fn summary(counts: &[usize; 4]) -> String {
+    let parts: Vec<String> = [
+        (PlanStepKind::Create, "to create"),
+        (PlanStepKind::Retarget, "to retarget"),
+        (PlanStepKind::Update, "to update"),
+        (PlanStepKind::UpToDate, "up to date"),
+    ]
+    .into_iter()
+    .filter(|(kind, _)| counts[*kind as usize] > 0)
+    .map(|(kind, label)| {
+        style(kind)
+            .apply_to(format!("{} {label}", counts[kind as usize]))
+            .to_string()
+    })
+    .collect();
+    if parts.is_empty() {
+        "Nothing to submit.".to_string()
+    } else {
+        format!("{}.", parts.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use stakk_core::forge::CheckRun;
+    use stakk_core::forge::CheckState;
+    use stakk_core::forge::ForgeRepoRef;
+    use stakk_core::forge::PrState;
+    use stakk_core::forge::PullRequest;
+    use stakk_core::jj::PushOptions;
+    use stakk_core::submit::BookmarkPlan;
+    use stakk_core::submit::options::PrMode;
+    use stakk_core::submit::options::PrOptions;
+
+    use super::*;
+
+    fn bookmark(name: &str, base: &str, pr: Option<u64>) -> BookmarkPlan {
+        BookmarkPlan {
+            bookmark_name: name.to_string(),
+            base: base.to_string(),
+            title: format!("Add {name}"),
+            body: None,
+            existing_pr: pr.map(|number| PullRequest {
+                number,
+                html_url: format!("https://github.com/o/r/pull/{number}"),
+                title: format!("Add {name}"),
+                head_ref: name.to_string(),
+                base_ref: base.to_string(),
+                state: PrState::Open,
+                body: None,
+                draft: false,
+            }),
+            needs_push: false,
+            expected_remote_target: None,
+            needs_create: pr.is_none(),
+            needs_base_update: false,
+            needs_title_sync: false,
+            needs_body_sync: false,
+            pr_options: PrOptions::default(),
+            title_source: None,
+            needs_mark_ready: false,
+            change_id: None,
+            renamed_from: None,
+            reviews: None,
+            issues: Vec::new(),
+            push_size: None,
+            checks: None,
+        }
+    }
+
+    #[test]
+    fn steps_are_marked_aligned_and_summed_up() {
+        console::set_colors_enabled(false);
+        let mut retargeted = bookmark("feature-b", "a", Some(2));
+        retargeted.needs_base_update = true;
+        retargeted.needs_push = true;
+        if let Some(pr) = &mut retargeted.existing_pr {
+            pr.base_ref = "main".to_string();
+        }
+        let plan = SubmissionPlan {
+            bookmark_plans: vec![
+                bookmark("a", "main", Some(1)),
+                retargeted,
+                bookmark("c", "feature-b", None),
+            ],
+            retargets: vec![],
+            repo: ForgeRepoRef::new("o", "r"),
+            head_repo: ForgeRepoRef::new("o", "r"),
+            remote: "origin".to_string(),
+            pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+        };
+
+        let arrow = output::symbol("→", ">");
+        assert_eq!(
+            render(&plan),
+            format!(
+                "Submission plan (3 bookmark(s), remote: origin):\n  a          {arrow} main\n    \
+                 = PR #1 up to date\n  feature-b  {arrow} a\n    ~ push bookmark to origin\n    ~ \
+                 retarget PR #2 from main to a\n  c          {arrow} feature-b\n    + create PR: \
+                 \"Add c\"\n1 to create, 1 to retarget, 1 up to date.\n"
+            )
+        );
+    }
+
+    #[test]
+    fn existing_prs_show_their_checks() {
+        console::set_colors_enabled(false);
+        let mut checked = bookmark("a", "main", Some(1));
+        checked.checks = Some(vec![
+            CheckRun {
+                name: "build".to_string(),
+                state: CheckState::Passed,
+            },
+            CheckRun {
+                name: "test".to_string(),
+                state: CheckState::Pending,
+            },
+        ]);
+        let mut unchecked = bookmark("b", "a", Some(2));
+        unchecked.checks = Some(vec![]);
+        let plan = SubmissionPlan {
+            bookmark_plans: vec![checked, unchecked],
+            retargets: vec![],
+            repo: ForgeRepoRef::new("o", "r"),
+            head_repo: ForgeRepoRef::new("o", "r"),
+            remote: "origin".to_string(),
+            pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+        };
+
+        let arrow = output::symbol("→", ">");
+        let out = render(&plan);
+        assert!(out.contains(&format!("  a  {arrow} main  [CI: 1 passed, 1 pending]\n")));
+        assert!(out.contains(&format!("  b  {arrow} a\n")));
+    }
+}
@@ -0,0 +1,256 @@
+//! Throttled polling of remote state.
+//!
+//! Waiting on the forge (CI checks, merges) is a loop of "ask, then sleep".
+//! [`poll_until`] owns that loop: it spaces attempts according to a
+//! [`Schedule`] (fixed interval or exponential backoff, with optional jitter so
+//! concurrent stakk processes don't hit the API in lockstep), stops at the
+//! schedule's timeout, and gives up early when a cancellation future resolves.
+
+use std::hash::BuildHasher;
+use std::hash::RandomState;
+use std::ops::ControlFlow;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// How often, and for how long, to poll.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Schedule {
+    interval: Duration,
+    max_interval: Duration,
+    factor: u32,
+    jitter: f64,
+    timeout: Option<Duration>,
+}
+
+impl Schedule {
+    /// Poll every `interval`, without jitter or timeout.
+    pub const fn fixed(interval: Duration) -> Self {
+        Self {
+            interval,
+            max_interval: interval,
+            factor: 1,
+            jitter: 0.0,
+            timeout: None,
+        }
+    }
+
+    /// Multiply the interval by `factor` after every attempt, up to
+    /// `max_interval`.
+    #[must_use]
+    pub const fn with_backoff(mut self, factor: u32, max_interval: Duration) -> Self {
+        self.factor = factor;
+        self.max_interval = max_interval;
+        self
+    }
+
+    /// Randomly stretch or shrink each delay by up to `fraction` of itself
+    /// (clamped to `0.0..=1.0`).
+    #[must_use]
+    pub const fn with_jitter(mut self, fraction: f64) -> Self {
+        self.jitter = fraction.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Stop polling once `timeout` has elapsed since the first attempt.
+    #[must_use]
+    pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub const fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Delay after the `attempt`-th (0-based) attempt, before jitter.
+    fn base_delay(&self, attempt: u32) -> Duration {
+        let grown = self
+            .interval
+            .saturating_mul(self.factor.saturating_pow(attempt));
+        if self.factor > 1 {
+            grown.min(self.max_interval.max(self.interval))
+        } else {
+            grown
+        }
+    }
+
+    /// Delay after the `attempt`-th attempt, with jitter applied using
+    /// `unit`, a value in `-1.0..=1.0`.
+    fn jittered_delay(&self, attempt: u32, unit: f64) -> Duration {
+        self.base_delay(attempt)
+            .mul_f64((1.0 + self.jitter * unit.clamp(-1.0, 1.0)).max(0.0))
+    }
+
+    /// Delay after the `attempt`-th attempt.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.jittered_delay(attempt, random_unit())
+    }
+}
+
+/// A pseudo-random value in `-1.0..=1.0`, drawn from the per-process random
+/// keys of the standard hasher to avoid a dependency on `rand`.
+fn random_unit() -> f64 {
+    #[expect(
+        clippy::cast_precision_loss,
+        reason = "only the high bits matter for jitter"
+    )]
+    let fraction = RandomState::new().hash_one(Instant::now()) as f64 / u64::MAX as f64;
+    fraction.mul_add(2.0, -1.0)
+}
+
+/// How a [`poll_until`] loop ended.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Polled<T, P> {
+    /// An attempt reported completion.
+    Done(T),
+    /// The schedule's timeout elapsed; carries the last pending state.
+    TimedOut(P),
+    /// The cancellation future resolved; carries the last pending state.
+    Cancelled(P),
+}
+
+/// Call `attempt` until it breaks with a result, the schedule times out, or
+/// `cancel` resolves.
+///
+/// `attempt` receives the time elapsed since polling started and returns
+/// `Continue(pending)` to keep waiting or `Break(done)` to stop. Errors end
+/// polling immediately. `on_pending` sees each pending state before the loop
+/// sleeps. Pass [`std::future::pending`] as `cancel` when polling cannot be
+/// interrupted.
+pub async fn poll_until<T, P, E, Fut>(
+    schedule: &Schedule,
+    cancel: impl Future<Output = ()>,
+    mut attempt: impl FnMut(Duration) -> Fut,
+    mut on_pending: impl FnMut(&P),
+) -> Result<Polled<T, P>, E>
+where
+    Fut: Future<Output = Result<ControlFlow<T, P>, E>>,
+{
+    let start = Instant::now();
+    let mut cancel = std::pin::pin!(cancel);
+    for n in 0_u32.. {
+        let elapsed = start.elapsed();
+        let pending = match attempt(elapsed).await? {
+            ControlFlow::Break(done) => return Ok(Polled::Done(done)),
+            ControlFlow::Continue(pending) => pending,
+        };
+
+        let mut delay = schedule.delay(n);
+        if let Some(timeout) = schedule.timeout {
+            let Some(remaining) = timeout
+                .checked_sub(start.elapsed())
+                .filter(|r| !r.is_zero())
+            else {
+                return Ok(Polled::TimedOut(pending));
+            };
+            delay = delay.min(remaining);
+        }
+
+        on_pending(&pending);
+        tokio::select! {
+            () = tokio::time::sleep(delay) => {}
+            () = &mut cancel => return Ok(Polled::Cancelled(pending)),
+        }
+    }
+    unreachable!("polling stops long before u32::MAX attempts")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::*;
+
+    #[test]
+    fn fixed_schedule_keeps_interval() {
+        let schedule = Schedule::fixed(Duration::from_secs(5));
+        assert_eq!(schedule.delay(0), Duration::from_secs(5));
+        assert_eq!(schedule.delay(10), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn backoff_grows_until_capped() {
+        let schedule =
+            Schedule::fixed(Duration::from_secs(2)).with_backoff(2, Duration::from_secs(10));
+        let delays: Vec<_> = (0..5).map(|n| schedule.delay(n).as_secs()).collect();
+        assert_eq!(delays, [2, 4, 8, 10, 10]);
+    }
+
+    #[test]
+    fn jitter_stays_within_fraction() {
+        let schedule = Schedule::fixed(Duration::from_secs(10)).with_jitter(0.2);
+        assert_eq!(schedule.jittered_delay(0, -1.0), Duration::from_secs(8));
+        assert_eq!(schedule.jittered_delay(0, 1.0), Duration::from_secs(12));
+        for n in 0..20 {
+            let delay = schedule.delay(n);
+            assert!(delay >= Duration::from_secs(8) && delay <= Duration::from_secs(12));
+        }
+    }
+
+    #[tokio::test]
+    async fn poll_until_returns_when_attempt_breaks() {
+        let mut calls = 0;
+        let mut pending_seen = Vec::new();
+        let outcome = poll_until(
+            &Schedule::fixed(Duration::ZERO),
+            std::future::pending(),
+            |_| {
+                calls += 1;
+                let result = if calls < 3 {
+                    ControlFlow::Continue(calls)
+                } else {
+                    ControlFlow::Break("done")
+                };
+                async move { Ok::<_, Infallible>(result) }
+            },
+            |pending| pending_seen.push(*pending),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(outcome, Polled::Done("done"));
+        assert_eq!(pending_seen, [1, 2]);
+    }
+
+    #[tokio::test]
+    async fn poll_until_times_out_with_last_pending_state() {
+        let outcome = poll_until(
+            &Schedule::fixed(Duration::ZERO).with_timeout(Duration::ZERO),
+            std::future::pending(),
+            |_| async { Ok::<ControlFlow<(), _>, Infallible>(ControlFlow::Continue("running")) },
+            |_| {},
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(outcome, Polled::TimedOut("running"));
+    }
+
+    #[tokio::test]
+    async fn poll_until_stops_on_cancel() {
+        let outcome = poll_until(
+            &Schedule::fixed(Duration::from_hours(1)),
+            std::future::ready(()),
+            |_| async { Ok::<ControlFlow<(), _>, Infallible>(ControlFlow::Continue("running")) },
+            |_| {},
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(outcome, Polled::Cancelled("running"));
+    }
+
+    #[tokio::test]
+    async fn poll_until_propagates_errors() {
+        let outcome: Result<Polled<(), u32>, &str> = poll_until(
+            &Schedule::fixed(Duration::ZERO),
+            std::future::pending(),
+            |_| async { Err("boom") },
+            |_| {},
+        )
+        .await;
+
+        assert_eq!(outcome, Err("boom"));
+    }
+}
@@ -0,0 +1,89 @@
+//! `stakk submit --preview-bodies`: the PR bodies, issue bodies, and stack
+//! comments a submit would write, rendered as Markdown in the terminal.
+//!
+//! The texts come from running the plan against a recording forge, so they
+//! are the ones the submit then writes. HTML comments (where stakk keeps its
+//! markers) are left out, as the forge's web page leaves them out.
+
+use std::fmt::Write as _;
+
+use stakk_core::forge::recording::Mutation;
+
+use crate::output;
+
+/// Render the text of every write in `mutations` under its headline. In
+/// plain mode, or when stdout is not a terminal, the Markdown is shown as
+/// is.
+pub fn render(mutations: &[Mutation]) -> String {
+    render_styled(
+        mutations,
+        !output::is_plain() && console::Term::stdout().is_term(),
+    )
+}
+
+/// Render `mutations` as [`render`] does, styling the Markdown if `styled`.
+fn render_styled(mutations: &[Mutation], styled: bool) -> String {
+    let skin = termimad::MadSkin::default();
+    let mut out = String::new();
+    for mutation in mutations {
+        let Some(markdown) = mutation.markdown() else {
+            continue;
+        };
+        let headline = mutation.to_string();
+        let headline = headline.lines().next().unwrap_or_default();
+        let visible = visible_markdown(markdown);
+        let rule = output::symbol("─", "-").repeat(40);
+        let _ = writeln!(out, "\n{rule}\n{headline}\n{rule}");
+        if styled {
+            let _ = write!(out, "{}", skin.term_text(&visible));
+        } else {
+            let _ = writeln!(out, "{}", visible.trim_end());
+        }
+    }
+    out
+}
+
+/// `markdown` without its HTML comments.
+fn visible_markdown(markdown: &str) -> String {
+    let mut visible = String::with_capacity(markdown.len());
+    let mut rest = markdown;
+    while let Some(start) = rest.find("<!--") {
+        visible.push_str(&rest[..start]);
+        rest = rest[start..]
+            .find("-->")
+            .map_or("", |end| &rest[start + end + "-->".len()..]);
+    }
+    visible.push_str(rest);
+    visible
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_comments_are_left_out() {
+        assert_eq!(
+            visible_markdown("<!-- stakk:stack -->\n# Stack\n- #1 <!--x-->👈\n"),
+            "\n# Stack\n- #1 👈\n"
+        );
+        assert_eq!(visible_markdown("text <!-- unclosed"), "text ");
+    }
+
+    #[test]
+    fn writes_without_text_are_skipped() {
+        let rendered = render_styled(
+            &[
+                Mutation::ClosePr { pr_number: 3 },
+                Mutation::CreateComment {
+                    pr_number: 4,
+                    body: "**Stack**\n".to_string(),
+                },
+            ],
+            false,
+        );
+        assert!(!rendered.contains("#3"));
+        assert!(rendered.contains("would comment on #4:\n"));
+        assert!(rendered.ends_with("\n**Stack**\n"));
+    }
+}
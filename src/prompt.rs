@@ -0,0 +1,116 @@
+//! `stakk prompt`: a one-line stack summary for shell prompts.
+//!
+//! Prompts run before every command, so the summary never talks to the
+//! forge. Stacks and sync state come from jj through the query cache, which
+//! answers from `.jj/stakk-cache/` until the next jj operation, and failing
+//! checks are those recorded by the last `stakk pr checks` or `stakk status`
+//! under the same operation.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use stakk_core::forge::CheckRun;
+use stakk_core::forge::CheckState;
+use stakk_core::graph::types::ChangeGraph;
+use stakk_core::jj::Jj;
+use stakk_core::jj::runner::JjRunner;
+use stakk_core::query_cache::QueryCache;
+
+use crate::error::StakkError;
+
+/// What the prompt line reports.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PromptSummary {
+    pub stacks: usize,
+    /// Stack bookmarks whose local target is not on the remote.
+    pub unsynced: usize,
+    /// Failed check runs last seen on the stack bookmarks; `None` if no
+    /// checks were recorded under the current operation.
+    pub failing_checks: Option<usize>,
+}
+
+/// Summarize the stacks of `graph`.
+pub async fn summarize<R: JjRunner>(
+    jj: &Jj<R>,
+    graph: &ChangeGraph,
+    bookmarks_revset: &str,
+    remote: &str,
+) -> Result<PromptSummary, StakkError> {
+    let bookmarks: BTreeSet<&str> = graph
+        .stacks
+        .iter()
+        .flat_map(|stack| &stack.segments)
+        .filter_map(|seg| seg.bookmark_names.first().map(String::as_str))
+        .collect();
+    let synced = jj.get_synced_bookmarks(bookmarks_revset, remote).await?;
+
+    let failing_checks = jj.query_cache().and_then(|cache| {
+        let checks = cache.failed_checks();
+        let recorded: Vec<usize> = bookmarks
+            .iter()
+            .filter_map(|b| checks.get(b).copied())
+            .collect();
+        (!recorded.is_empty()).then(|| recorded.iter().sum())
+    });
+
+    Ok(PromptSummary {
+        stacks: graph.stacks.len(),
+        unsynced: bookmarks.iter().filter(|b| !synced.contains(**b)).count(),
+        failing_checks,
+    })
+}
+
+/// Record how many checks failed on each bookmark, for later prompts.
+pub fn record_failed_checks<'a>(
+    cache: &QueryCache<String>,
+    runs: impl IntoIterator<Item = (&'a str, &'a [CheckRun])>,
+) -> Result<(), StakkError> {
+    let mut checks = cache.failed_checks();
+    for (bookmark, runs) in runs {
+        let failed = runs
+            .iter()
+            .filter(|r| r.state == CheckState::Failed)
+            .count();
+        checks.insert(bookmark.to_string(), failed);
+    }
+    Ok(checks.save()?)
+}
+
+impl fmt::Display for PromptSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let noun = if self.stacks == 1 { "stack" } else { "stacks" };
+        write!(f, "{} {noun}", self.stacks)?;
+        if self.unsynced > 0 {
+            write!(f, ", {} unsynced", self.unsynced)?;
+        }
+        if let Some(failing) = self.failing_checks.filter(|n| *n > 0) {
+            write!(f, ", {failing} failing")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_omits_zero_counts() {
+        let summary = PromptSummary {
+            stacks: 1,
+            unsynced: 0,
+            failing_checks: Some(0),
+        };
+        assert_eq!(summary.to_string(), "1 stack");
+    }
+
+    #[test]
+    fn display_all_counts() {
+        let summary = PromptSummary {
+            stacks: 3,
+            unsynced: 2,
+            failing_checks: Some(1),
+        };
+        assert_eq!(summary.to_string(), "3 stacks, 2 unsynced, 1 failing");
+    }
+}
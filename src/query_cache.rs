@@ -0,0 +1,237 @@
+//! On-disk cache of jj queries and PR lookups between invocations.
+//!
+//! Entries live under `.jj/stakk-cache/` in the workspace root and are keyed
+//! by the head of the jj operation log and the commits the local bookmarks
+//! point to. Any new operation (a working-copy snapshot, commit, fetch, or
+//! bookmark move) changes the key, and a cache loaded under a different key
+//! starts out empty, so stale entries are never served.
+//!
+//! PR links and failed checks come from the forge, where they change without
+//! a jj operation (a PR is opened elsewhere, CI finishes), so those caches
+//! are also dropped `FORGE_TTL` after their first entry was written.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use miette::Diagnostic;
+use serde::Deserialize;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+/// Errors from writing the query cache.
+#[derive(Debug, Error, Diagnostic)]
+pub enum QueryCacheError {
+    #[error("failed to write query cache {path}")]
+    #[diagnostic(
+        code(stakk::query_cache::write_failed),
+        help("check file permissions, or pass --no-cache")
+    )]
+    Write {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+/// The repository state a cache was written in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheKey {
+    /// ID of the jj operation at the head of the op log.
+    pub operation_id: String,
+    /// Local bookmark name → target commit ID (`conflicted` when it has
+    /// several).
+    pub bookmark_targets: BTreeMap<String, String>,
+}
+
+/// How long the forge-derived caches are served under an unchanged key.
+const FORGE_TTL: Duration = Duration::from_mins(5);
+
+/// A PR found for a bookmark, as cached by `stakk pr open`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrLink {
+    pub number: u64,
+    pub html_url: String,
+}
+
+/// Query results keyed by query, valid only for `key`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryCache<V> {
+    #[serde(skip)]
+    path: PathBuf,
+    key: CacheKey,
+    /// Seconds since the Unix epoch when the oldest entry was cached.
+    #[serde(default)]
+    since: u64,
+    entries: BTreeMap<String, V>,
+}
+
+impl<V: Serialize + DeserializeOwned> QueryCache<V> {
+    /// Path of the cache of jj query output.
+    pub fn jj_path(workspace_root: &Path) -> PathBuf {
+        workspace_root
+            .join(".jj")
+            .join("stakk-cache")
+            .join("jj.json")
+    }
+
+    /// Load the entries cached under `key`.
+    ///
+    /// A missing or unreadable file, or one written under another key, gives
+    /// an empty cache: the cache only ever saves work, so it never fails a
+    /// command.
+    pub fn load(path: &Path, key: CacheKey) -> Self {
+        Self::load_within(path, key, None)
+    }
+
+    /// Like [`QueryCache::load`], but also empty once `max_age` has passed
+    /// since the oldest entry was cached.
+    fn load_within(path: &Path, key: CacheKey, max_age: Option<Duration>) -> Self {
+        let now = unix_now();
+        let (since, entries) = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Self>(&contents).ok())
+            .filter(|cached| cached.key == key)
+            .filter(|cached| {
+                max_age.is_none_or(|age| now.saturating_sub(cached.since) <= age.as_secs())
+            })
+            .map_or((now, BTreeMap::new()), |cached| {
+                (cached.since, cached.entries)
+            });
+        Self {
+            path: path.to_path_buf(),
+            key,
+            since,
+            entries,
+        }
+    }
+
+    pub fn get(&self, query: &str) -> Option<&V> {
+        self.entries.get(query)
+    }
+
+    pub fn insert(&mut self, query: String, value: V) {
+        self.entries.insert(query, value);
+    }
+
+    pub fn save(&self) -> Result<(), QueryCacheError> {
+        let write_failed = |source| QueryCacheError::Write {
+            path: self.path.display().to_string(),
+            source,
+        };
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(write_failed)?;
+        }
+        let json = serde_json::to_string(self).expect("QueryCache serializes to JSON");
+        std::fs::write(&self.path, json).map_err(write_failed)
+    }
+}
+
+impl QueryCache<String> {
+    /// The PR lookups cached next to these jj queries, under the same key.
+    pub fn pr_links(&self) -> QueryCache<PrLink> {
+        QueryCache::load_within(
+            &self.path.with_file_name("prs.json"),
+            self.key.clone(),
+            Some(FORGE_TTL),
+        )
+    }
+
+    /// Failed check runs per bookmark, as last seen by `stakk pr checks` or
+    /// `stakk status` under the same key.
+    pub fn failed_checks(&self) -> QueryCache<usize> {
+        QueryCache::load_within(
+            &self.path.with_file_name("checks.json"),
+            self.key.clone(),
+            Some(FORGE_TTL),
+        )
+    }
+}
+
+/// Seconds since the Unix epoch.
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(operation_id: &str) -> CacheKey {
+        CacheKey {
+            operation_id: operation_id.to_string(),
+            bookmark_targets: BTreeMap::from([("feat".to_string(), "abc".to_string())]),
+        }
+    }
+
+    #[test]
+    fn save_and_load_under_same_key() {
+        let dir = std::env::temp_dir().join(format!("stakk-query-cache-{}", std::process::id()));
+        let path = QueryCache::<String>::jj_path(&dir);
+        let mut cache = QueryCache::load(&path, key("op1"));
+        cache.insert("log".to_string(), "output".to_string());
+        cache.save().unwrap();
+
+        let same = QueryCache::<String>::load(&path, key("op1"));
+        let moved = QueryCache::<String>::load(&path, key("op2"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(same.get("log").map(String::as_str), Some("output"));
+        assert!(moved.get("log").is_none());
+    }
+
+    #[test]
+    fn pr_links_share_the_key() {
+        let dir =
+            std::env::temp_dir().join(format!("stakk-query-cache-prs-{}", std::process::id()));
+        let jj_cache = QueryCache::<String>::load(&QueryCache::<String>::jj_path(&dir), key("op1"));
+        let mut links = jj_cache.pr_links();
+        let link = PrLink {
+            number: 7,
+            html_url: "https://github.com/o/r/pull/7".to_string(),
+        };
+        links.insert("feat".to_string(), link.clone());
+        links.save().unwrap();
+
+        let reloaded = jj_cache.pr_links();
+        let moved =
+            QueryCache::<String>::load(&QueryCache::<String>::jj_path(&dir), key("op2")).pr_links();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(reloaded.get("feat"), Some(&link));
+        assert!(moved.get("feat").is_none());
+    }
+
+    #[test]
+    fn forge_entries_expire_under_the_same_key() {
+        let dir =
+            std::env::temp_dir().join(format!("stakk-query-cache-ttl-{}", std::process::id()));
+        let jj_cache = QueryCache::<String>::load(&QueryCache::<String>::jj_path(&dir), key("op1"));
+        let mut checks = jj_cache.failed_checks();
+        checks.insert("feat".to_string(), 2);
+        checks.since -= FORGE_TTL.as_secs() + 1;
+        checks.save().unwrap();
+
+        let expired = jj_cache.failed_checks();
+        let path = checks.path.clone();
+        let unexpired = QueryCache::<usize>::load(&path, key("op1"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(expired.get("feat").is_none());
+        assert_eq!(unexpired.get("feat"), Some(&2));
+    }
+
+    #[test]
+    fn load_missing_file_is_empty() {
+        let cache = QueryCache::<String>::load(
+            Path::new("/nonexistent/.jj/stakk-cache/jj.json"),
+            key("op"),
+        );
+        assert!(cache.get("log").is_none());
+    }
+}
@@ -0,0 +1,180 @@
+//! `stakk rebase`: put the branches left behind by a moved bookmark back on
+//! top of it.
+//!
+//! jj rebases descendants when a change is rewritten, but not when a
+//! bookmark moves on to new commits (`jj new` on a bookmark, then `jj
+//! bookmark move`). What was stacked on the bookmark's old commit then forks
+//! off inside the bookmark's segment: its segment shares commits with the
+//! bookmark's. Each such branch is rebased onto the bookmark, starting at
+//! its oldest commit of its own.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use stakk_core::graph::types::BookmarkSegment;
+use stakk_core::graph::types::ChangeGraph;
+
+use crate::segment_test::label;
+
+/// One branch to move onto the bookmark.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Restack {
+    /// The first bookmark or short change ID of the segment being moved.
+    pub label: String,
+    /// The oldest commit of the segment that is not the bookmark's.
+    pub source: String,
+}
+
+/// What `stakk rebase` will do.
+#[derive(Debug)]
+pub struct RestackPlan {
+    /// The bookmark the branches are moved onto.
+    pub onto: String,
+    pub restacks: Vec<Restack>,
+}
+
+/// Plan moving every branch that forks off inside `bookmark`'s segment
+/// onto `bookmark`. `None` if the bookmark is not in the graph.
+pub fn plan_restacks(graph: &ChangeGraph, bookmark: &str) -> Option<RestackPlan> {
+    let segments = || graph.stacks.iter().flat_map(|stack| &stack.segments);
+    let target = segments().find(|segment| segment.bookmark_names.iter().any(|b| b == bookmark))?;
+    // Below its tip, the bookmark's commits are what it used to point to.
+    let behind: HashSet<&str> = target
+        .commits
+        .iter()
+        .skip(1)
+        .map(|commit| commit.commit_id.as_str())
+        .collect();
+
+    let mut seen = HashSet::new();
+    let restacks = segments()
+        .filter(|segment| segment.change_id != target.change_id)
+        .filter(|segment| seen.insert(segment.change_id.as_str()))
+        .filter_map(|segment| left_behind(segment, &behind))
+        .collect();
+    Some(RestackPlan {
+        onto: bookmark.to_string(),
+        restacks,
+    })
+}
+
+/// The move for `segment` if it forks off one of the `behind` commits.
+fn left_behind(segment: &BookmarkSegment, behind: &HashSet<&str>) -> Option<Restack> {
+    let shared = segment
+        .commits
+        .iter()
+        .position(|commit| behind.contains(commit.commit_id.as_str()))?;
+    let source = segment.commits[..shared].last()?;
+    Some(Restack {
+        label: label(segment),
+        source: source.commit_id.clone(),
+    })
+}
+
+impl fmt::Display for RestackPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.restacks.is_empty() {
+            return writeln!(f, "Nothing is left behind by '{}'.", self.onto);
+        }
+        writeln!(
+            f,
+            "Restacking {} branch(es) onto '{}':",
+            self.restacks.len(),
+            self.onto
+        )?;
+        for restack in &self.restacks {
+            writeln!(f, "  - rebase '{}' and its descendants", restack.label)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use stakk_core::graph::types::BranchStack;
+    use stakk_core::graph::types::SegmentCommit;
+    use stakk_core::jj::types::Signature;
+
+    use super::*;
+
+    fn commit(commit_id: &str) -> SegmentCommit {
+        let sig = Signature {
+            name: "Test".to_string(),
+            email: "test@example.com".to_string(),
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+        };
+        SegmentCommit {
+            commit_id: commit_id.to_string(),
+            change_id: format!("ch_{commit_id}"),
+            description: String::new(),
+            author: sig.clone(),
+            committer: sig,
+            short_change_id: commit_id.to_string(),
+            files: vec![],
+            is_immutable: false,
+            local_bookmark_names: vec![],
+        }
+    }
+
+    /// A segment `name` with the given commits, tip first.
+    fn segment(name: &str, commits: &[&str]) -> BookmarkSegment {
+        BookmarkSegment {
+            bookmark_names: vec![name.to_string()],
+            change_id: format!("ch_{name}"),
+            commits: commits.iter().map(|c| commit(c)).collect(),
+        }
+    }
+
+    fn graph(stacks: Vec<Vec<BookmarkSegment>>) -> ChangeGraph {
+        ChangeGraph {
+            adjacency_list: HashMap::new(),
+            stack_leaves: HashSet::new(),
+            stack_roots: HashSet::new(),
+            segments: HashMap::new(),
+            tainted_change_ids: HashSet::new(),
+            excluded_bookmark_count: 0,
+            tainted_bookmarks: vec![],
+            private_bookmarks: HashMap::new(),
+            conflicted_bookmarks: vec![],
+            root_trunks: HashMap::new(),
+            stacks: stacks
+                .into_iter()
+                .map(|segments| BranchStack { segments })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn branches_on_the_old_commit_move_onto_the_bookmark() {
+        // `a` moved from a0 to a1; `b` (with `c` on it) still sits on a0.
+        let graph = graph(vec![
+            vec![segment("a", &["a1", "a0"])],
+            vec![segment("b", &["b1", "b0", "a0"]), segment("c", &["c0"])],
+            vec![segment("d", &["d0"])],
+        ]);
+
+        let plan = plan_restacks(&graph, "a").unwrap();
+        assert_eq!(
+            plan.restacks,
+            [Restack {
+                label: "b".to_string(),
+                source: "b0".to_string(),
+            }]
+        );
+        assert!(plan_restacks(&graph, "gone").is_none());
+    }
+
+    #[test]
+    fn stacked_branches_are_left_alone() {
+        let graph = graph(vec![vec![
+            segment("a", &["a1", "a0"]),
+            segment("b", &["b0"]),
+        ]]);
+
+        let plan = plan_restacks(&graph, "a").unwrap();
+        assert!(plan.restacks.is_empty());
+        assert_eq!(plan.to_string(), "Nothing is left behind by 'a'.\n");
+    }
+}
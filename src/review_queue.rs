@@ -0,0 +1,139 @@
+//! `stakk review-queue`: the stacks with PRs waiting for my review.
+//!
+//! The forge lists the open PRs that request a review from the
+//! authenticated user. Each PR's stack comment (or body section) names the
+//! stack it belongs to, so PRs of one stack are shown together, in
+//! dependency order, with the command that checks the stack out.
+
+use std::fmt;
+
+use stakk_core::forge::PullRequest;
+use stakk_core::forge::markup::StackEntry;
+
+/// A stack with at least one PR that requests my review.
+#[derive(Debug)]
+pub struct QueuedStack {
+    /// Every PR of the stack, bottom first.
+    pub entries: Vec<StackEntry>,
+    /// The PRs of the stack that request my review.
+    pub requested: Vec<PullRequest>,
+}
+
+/// Stacks waiting for my review.
+#[derive(Debug)]
+pub struct ReviewQueue {
+    pub stacks: Vec<QueuedStack>,
+}
+
+impl ReviewQueue {
+    /// Group requested PRs, each with the stack its metadata names, by
+    /// stack. Stacks that share a PR are one stack (metadata of PRs in the
+    /// same stack may be of different ages); the longest listing wins.
+    pub fn group(requested: Vec<(PullRequest, Vec<StackEntry>)>) -> Self {
+        let mut stacks: Vec<QueuedStack> = Vec::new();
+        for (pr, entries) in requested {
+            let shares = |stack: &QueuedStack| {
+                stack
+                    .entries
+                    .iter()
+                    .any(|e| entries.iter().any(|o| o.pr_number == e.pr_number))
+            };
+            match stacks.iter_mut().find(|stack| shares(stack)) {
+                Some(stack) => {
+                    if entries.len() > stack.entries.len() {
+                        stack.entries = entries;
+                    }
+                    stack.requested.push(pr);
+                }
+                None => stacks.push(QueuedStack {
+                    entries,
+                    requested: vec![pr],
+                }),
+            }
+        }
+        Self { stacks }
+    }
+}
+
+impl fmt::Display for ReviewQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.stacks.is_empty() {
+            return writeln!(f, "No pull requests are waiting for your review.");
+        }
+        for (i, stack) in self.stacks.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            let leaf = stack.entries.last().map_or(0, |e| e.pr_number);
+            writeln!(
+                f,
+                "Stack of {} PR(s); check out with `stakk open-stack {leaf}`:",
+                stack.entries.len()
+            )?;
+            for entry in &stack.entries {
+                let requested = stack
+                    .requested
+                    .iter()
+                    .find(|pr| pr.number == entry.pr_number);
+                match requested {
+                    Some(pr) => writeln!(
+                        f,
+                        "  #{} {}: \"{}\" (review requested)",
+                        entry.pr_number, entry.bookmark_name, pr.title
+                    )?,
+                    None => writeln!(f, "  #{} {}", entry.pr_number, entry.bookmark_name)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use stakk_core::forge::PrState;
+
+    use super::*;
+
+    fn pr(number: u64, title: &str) -> PullRequest {
+        PullRequest {
+            number,
+            html_url: format!("https://github.com/o/r/pull/{number}"),
+            title: title.to_string(),
+            head_ref: format!("b{number}"),
+            base_ref: "main".to_string(),
+            state: PrState::Open,
+            body: None,
+            draft: false,
+        }
+    }
+
+    fn stack(numbers: &[u64]) -> Vec<StackEntry> {
+        numbers
+            .iter()
+            .map(|&number| StackEntry {
+                bookmark_name: format!("b{number}"),
+                pr_url: format!("https://github.com/o/r/pull/{number}"),
+                pr_number: number,
+                repo: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn prs_of_one_stack_are_grouped() {
+        let queue = ReviewQueue::group(vec![
+            (pr(2, "Two"), stack(&[1, 2])),
+            (pr(9, "Nine"), stack(&[9])),
+            (pr(3, "Three"), stack(&[1, 2, 3])),
+        ]);
+
+        assert_eq!(queue.stacks.len(), 2);
+        assert_eq!(
+            queue.to_string(),
+            "Stack of 3 PR(s); check out with `stakk open-stack 3`:\n  #1 b1\n  #2 b2: \"Two\" \
+             (review requested)\n  #3 b3: \"Three\" (review requested)\n\nStack of 1 PR(s); check \
+             out with `stakk open-stack 9`:\n  #9 b9: \"Nine\" (review requested)\n"
+        );
+    }
+}
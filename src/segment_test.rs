@@ -0,0 +1,227 @@
+//! `stakk test`: run a command on each segment of a stack.
+//!
+//! Every segment is checked out into a [`TempWorkspace`] of its own, on a
+//! new empty change on top of its bookmarked commit, so the command sees
+//! exactly the tree that segment's PR would merge and the user's working copy
+//! is left alone. This catches stacks that only build at the tip before a
+//! reviewer does.
+
+use std::fmt;
+use std::process::ExitStatus;
+
+use stakk_core::graph::types::BookmarkSegment;
+use stakk_core::graph::types::ChangeGraph;
+use stakk_core::jj::Jj;
+use stakk_core::jj::runner::JjRunner;
+use stakk_core::jj::workspace::TempWorkspace;
+
+use crate::error::StakkError;
+
+/// The segments of the stack ending at `bookmark`, trunk first, up to and
+/// including the bookmark's segment. Prefers a stack whose leaf is the
+/// bookmark, like `stakk status`, else the first stack containing it.
+pub fn segments_up_to<'a>(graph: &'a ChangeGraph, bookmark: &str) -> Option<&'a [BookmarkSegment]> {
+    let has_bookmark = |seg: &BookmarkSegment| seg.bookmark_names.iter().any(|b| b == bookmark);
+    let stack = graph
+        .stacks
+        .iter()
+        .find(|stack| stack.segments.last().is_some_and(has_bookmark))
+        .or_else(|| {
+            graph
+                .stacks
+                .iter()
+                .find(|stack| stack.segments.iter().any(has_bookmark))
+        })?;
+    let end = stack.segments.iter().position(has_bookmark)?;
+    Some(&stack.segments[..=end])
+}
+
+/// The segment's first bookmark, or its short change ID if it has none.
+pub fn label(segment: &BookmarkSegment) -> String {
+    segment.bookmark_names.first().cloned().unwrap_or_else(|| {
+        segment
+            .commits
+            .first()
+            .map(|c| c.short_change_id.clone())
+            .unwrap_or_default()
+    })
+}
+
+/// Run `command` in a temporary workspace on `segment`'s bookmarked commit.
+///
+/// Returns `None` if the command exited successfully, or how it failed. The
+/// workspace is forgotten and its directory removed whatever the outcome.
+pub async fn run_segment<R: JjRunner>(
+    jj: &Jj<R>,
+    segment: &BookmarkSegment,
+    command: &str,
+) -> Result<Option<String>, StakkError> {
+    let Some(tip) = segment.commits.first() else {
+        return Ok(None);
+    };
+    let workspace = TempWorkspace::create(jj, &tip.commit_id).await?;
+    let status = run_command(command, workspace.path(), segment).await;
+    workspace.destroy().await?;
+
+    Ok(describe_failure(status?))
+}
+
+async fn run_command(
+    command: &str,
+    dir: &std::path::Path,
+    segment: &BookmarkSegment,
+) -> Result<ExitStatus, StakkError> {
+    use tokio::process::Command;
+
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    };
+    cmd.current_dir(dir)
+        .env(
+            "STAKK_BOOKMARK",
+            segment.bookmark_names.first().map_or("", String::as_str),
+        )
+        .env("STAKK_CHANGE_ID", &segment.change_id)
+        .status()
+        .await
+        .map_err(|source| StakkError::TestCommandFailed {
+            command: command.to_string(),
+            source,
+        })
+}
+
+fn describe_failure(status: ExitStatus) -> Option<String> {
+    if status.success() {
+        None
+    } else {
+        Some(status.code().map_or_else(
+            || "killed by a signal".to_string(),
+            |code| format!("exit code {code}"),
+        ))
+    }
+}
+
+/// How the command went on one segment.
+pub struct SegmentOutcome {
+    pub label: String,
+    pub pr_number: Option<u64>,
+    /// `None` if the command passed.
+    pub failure: Option<String>,
+}
+
+/// The outcome on each tested segment, trunk first.
+#[derive(Default)]
+pub struct TestReport {
+    pub outcomes: Vec<SegmentOutcome>,
+}
+
+impl TestReport {
+    pub fn failed(&self) -> usize {
+        self.outcomes.iter().filter(|o| o.failure.is_some()).count()
+    }
+}
+
+impl fmt::Display for TestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for outcome in &self.outcomes {
+            let verdict = if outcome.failure.is_some() {
+                "FAILED"
+            } else {
+                "ok"
+            };
+            write!(f, "  {verdict:<6}  {}", outcome.label)?;
+            if let Some(number) = outcome.pr_number {
+                write!(f, " #{number}")?;
+            }
+            if let Some(ref failure) = outcome.failure {
+                write!(f, " ({failure})")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::collections::HashSet;
+
+    use stakk_core::graph::types::BranchStack;
+
+    use super::*;
+
+    fn segment(name: &str) -> BookmarkSegment {
+        BookmarkSegment {
+            bookmark_names: vec![name.to_string()],
+            change_id: format!("ch_{name}"),
+            commits: vec![],
+        }
+    }
+
+    fn graph_of(stacks: &[&[&str]]) -> ChangeGraph {
+        ChangeGraph {
+            adjacency_list: HashMap::new(),
+            stack_leaves: HashSet::new(),
+            stack_roots: HashSet::new(),
+            segments: HashMap::new(),
+            tainted_change_ids: HashSet::new(),
+            excluded_bookmark_count: 0,
+            tainted_bookmarks: vec![],
+            private_bookmarks: HashMap::new(),
+            conflicted_bookmarks: vec![],
+            root_trunks: HashMap::new(),
+            stacks: stacks
+                .iter()
+                .map(|names| BranchStack {
+                    segments: names.iter().map(|name| segment(name)).collect(),
+                })
+                .collect(),
+        }
+    }
+
+    fn labels(segments: &[BookmarkSegment]) -> Vec<String> {
+        segments.iter().map(label).collect()
+    }
+
+    #[test]
+    fn segments_up_to_prefers_stack_ending_at_bookmark() {
+        let graph = graph_of(&[&["a", "b", "c"], &["a", "b"]]);
+        assert_eq!(labels(segments_up_to(&graph, "b").unwrap()), ["a", "b"]);
+        assert_eq!(
+            labels(segments_up_to(&graph, "c").unwrap()),
+            ["a", "b", "c"]
+        );
+        assert_eq!(labels(segments_up_to(&graph, "a").unwrap()), ["a"]);
+        assert!(segments_up_to(&graph, "missing").is_none());
+    }
+
+    #[test]
+    fn report_lists_verdict_per_segment() {
+        let report = TestReport {
+            outcomes: vec![
+                SegmentOutcome {
+                    label: "feat-a".to_string(),
+                    pr_number: Some(12),
+                    failure: None,
+                },
+                SegmentOutcome {
+                    label: "feat-b".to_string(),
+                    pr_number: None,
+                    failure: Some("exit code 101".to_string()),
+                },
+            ],
+        };
+        assert_eq!(report.failed(), 1);
+        assert_eq!(
+            report.to_string(),
+            "  ok      feat-a #12\n  FAILED  feat-b (exit code 101)\n"
+        );
+    }
+}
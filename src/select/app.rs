@@ -20,6 +20,7 @@ use ratatui::style::Style;
 use ratatui::text::Line;
 use ratatui::text::Span;
 use ratatui::widgets::Widget;
+use stakk_core::graph::types::ChangeGraph;
 
 use super::SelectionResult;
 use super::bookmark_gen;
@@ -48,7 +49,7 @@ use super::graph_widget::display_line_count;
 use super::graph_widget::graph_help_line;
 use crate::error::StakkError::Interrupted;
 use crate::error::StakkError::{self};
-use crate::graph::types::ChangeGraph;
+use crate::output;
 
 /// Which screen is currently active.
 enum Screen {
@@ -210,6 +211,14 @@ fn run_event_loop(
                     }
                 }
             }
+
+            if output::is_plain() {
+                for cell in &mut frame.buffer_mut().content {
+                    if let Some(ascii) = output::ascii_symbol(cell.symbol()) {
+                        cell.set_symbol(ascii);
+                    }
+                }
+            }
         })?;
 
         // Poll for input with a short timeout so we can re-render spinner
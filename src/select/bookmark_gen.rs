@@ -372,8 +372,9 @@ pub fn dynamic_segment_commits(rows: &[BookmarkRow], row_idx: usize) -> Vec<&Boo
 
 #[cfg(test)]
 mod tests {
+    use stakk_core::jj::types::Signature;
+
     use super::*;
-    use crate::jj::types::Signature;
     use crate::select::bookmark_widget::RowState;
 
     #[test]
@@ -13,12 +13,12 @@ use ratatui::style::Style;
 use ratatui::text::Line;
 use ratatui::text::Span;
 use ratatui::widgets::Widget;
+use stakk_core::jj::types::Signature;
 
 use super::BookmarkAssignment;
 use super::bookmark_gen;
 use super::graph_layout::LayoutNode;
 use super::tfidf;
-use crate::jj::types::Signature;
 
 /// Whether the user-input row is in normal mode or edit (typing) mode.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -1087,7 +1087,7 @@ mod tests {
             is_leaf,
             stack_index: 0,
             short_change_id: change_id[..4.min(change_id.len())].to_string(),
-            author: crate::jj::types::Signature {
+            author: stakk_core::jj::types::Signature {
                 name: "Test".to_string(),
                 email: "test@test.com".to_string(),
                 timestamp: "T".to_string(),
@@ -1482,7 +1482,7 @@ mod tests {
             is_trunk: false,
             is_immutable: false,
             excluded_bookmarks: vec![],
-            author: crate::jj::types::Signature {
+            author: stakk_core::jj::types::Signature {
                 name: "Test".to_string(),
                 email: "test@test.com".to_string(),
                 timestamp: "T".to_string(),
@@ -363,11 +363,12 @@ mod tests {
     use std::collections::HashMap;
     use std::collections::HashSet;
 
+    use stakk_core::graph::types::BookmarkSegment;
+    use stakk_core::graph::types::BranchStack;
+    use stakk_core::graph::types::ChangeGraph;
+    use stakk_core::graph::types::SegmentCommit;
+
     use super::*;
-    use crate::graph::types::BookmarkSegment;
-    use crate::graph::types::BranchStack;
-    use crate::graph::types::ChangeGraph;
-    use crate::graph::types::SegmentCommit;
     use crate::select::graph_layout::build_layout;
 
     fn make_graph(stacks: Vec<BranchStack>) -> ChangeGraph {
@@ -378,6 +379,10 @@ mod tests {
             segments: HashMap::new(),
             tainted_change_ids: HashSet::new(),
             excluded_bookmark_count: 0,
+            tainted_bookmarks: vec![],
+            private_bookmarks: HashMap::new(),
+            conflicted_bookmarks: vec![],
+            root_trunks: HashMap::new(),
             stacks,
         }
     }
@@ -393,12 +398,12 @@ mod tests {
                     commit_id: format!("c_{change_id}_{i}"),
                     change_id: change_id.to_string(),
                     description: desc.to_string(),
-                    author: crate::jj::types::Signature {
+                    author: stakk_core::jj::types::Signature {
                         name: "Test".to_string(),
                         email: "test@test.com".to_string(),
                         timestamp: "T".to_string(),
                     },
-                    committer: crate::jj::types::Signature {
+                    committer: stakk_core::jj::types::Signature {
                         name: "Test".to_string(),
                         email: "test@test.com".to_string(),
                         timestamp: "T".to_string(),
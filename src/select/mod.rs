@@ -13,8 +13,9 @@ mod tfidf;
 
 use std::io::IsTerminal;
 
+use stakk_core::graph::types::ChangeGraph;
+
 use crate::error::StakkError;
-use crate::graph::types::ChangeGraph;
 
 /// A bookmark assignment for a commit in the submission stack.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -67,8 +68,9 @@ mod tests {
     use std::collections::HashMap;
     use std::collections::HashSet;
 
+    use stakk_core::graph::types::ChangeGraph;
+
     use super::*;
-    use crate::graph::types::ChangeGraph;
 
     fn make_graph_empty() -> ChangeGraph {
         ChangeGraph {
@@ -78,6 +80,10 @@ mod tests {
             segments: HashMap::new(),
             tainted_change_ids: HashSet::new(),
             excluded_bookmark_count: 0,
+            tainted_bookmarks: vec![],
+            private_bookmarks: HashMap::new(),
+            conflicted_bookmarks: vec![],
+            root_trunks: HashMap::new(),
             stacks: vec![],
         }
     }
@@ -0,0 +1,395 @@
+//! `stakk show`: the stacks as a graph, like `jj log` scoped to them.
+//!
+//! One node per segment, leaves at the top and trunk at the bottom. Each node
+//! carries the segment's bookmarks, its PR number from the PR cache, and how
+//! the bookmark compares to its branch on the remote. Bookmarks left out of
+//! the stacks are listed below the graph with the reason. With `--reviews`,
+//! PR nodes also show their approvals and change requests, and with
+//! `--checks` a summary of their CI checks, both looked up on the forge.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+
+use stakk_core::forge::CheckRun;
+use stakk_core::forge::ReviewSummary;
+use stakk_core::graph::topological_sort;
+use stakk_core::graph::types::BookmarkSegment;
+use stakk_core::graph::types::ChangeGraph;
+use stakk_core::submit::describe_checks;
+
+use crate::cache::PrCache;
+use crate::output;
+
+/// Everything `stakk show` draws, read from jj and the PR cache only.
+pub struct GraphView<'a> {
+    pub graph: &'a ChangeGraph,
+    pub default_branch: &'a str,
+    /// Bookmarks whose local target is already on the remote.
+    pub synced: &'a HashSet<String>,
+    /// The commit each bookmark on the remote points to, by name.
+    pub remote_targets: &'a HashMap<String, String>,
+    pub prs: &'a PrCache,
+    /// Review counts of the PRs, by bookmark; empty without `--reviews`.
+    pub reviews: &'a HashMap<String, ReviewSummary>,
+    /// CI check runs of the PRs, by bookmark; empty without `--checks`.
+    pub checks: &'a HashMap<String, Vec<CheckRun>>,
+}
+
+impl GraphView<'_> {
+    /// Write the text after a segment's node symbol.
+    fn write_label(&self, f: &mut fmt::Formatter<'_>, segment: &BookmarkSegment) -> fmt::Result {
+        match segment.bookmark_names.first() {
+            None => write!(f, "(no bookmark)")?,
+            Some(bookmark) => {
+                write!(f, "{}", segment.bookmark_names.join(", "))?;
+                if let Some(pr) = self.prs.prs.get(bookmark) {
+                    write!(f, " #{}", pr.number)?;
+                }
+                if let Some(reviews) = self.reviews.get(bookmark) {
+                    if reviews.approved > 0 {
+                        write!(f, " {}{}", output::symbol("✓", "+"), reviews.approved)?;
+                    }
+                    if reviews.changes_requested > 0 {
+                        write!(
+                            f,
+                            " {}{}",
+                            output::symbol("✗", "x"),
+                            reviews.changes_requested
+                        )?;
+                    }
+                }
+                if let Some(runs) = self.checks.get(bookmark) {
+                    write!(f, " [CI: {}]", describe_checks(runs))?;
+                }
+                let sync = if self.synced.contains(bookmark) {
+                    "synced"
+                } else if self.remote_targets.contains_key(bookmark) {
+                    "needs push"
+                } else {
+                    "not pushed"
+                };
+                write!(f, " [{sync}]")?;
+            }
+        }
+
+        if let Some(commit) = segment.commits.first() {
+            let summary = commit.description.lines().next().map_or("", str::trim);
+            let summary = if summary.is_empty() {
+                "(no description)"
+            } else {
+                summary
+            };
+            write!(f, "  {} {summary}", commit.short_change_id)?;
+        }
+        if segment.commits.len() > 1 {
+            write!(f, " ({} commits)", segment.commits.len())?;
+        }
+        Ok(())
+    }
+
+    /// Draw the segments, leaves first, with each segment's second and later
+    /// children branching off one column to the right and merging back in
+    /// below their subtree.
+    fn fmt_graph(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let order = topological_sort(self.graph);
+        let position: HashMap<&str, usize> =
+            order.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+        let parent = |id: &str| self.graph.adjacency_list.get(id).map(String::as_str);
+
+        // A segment's first child (the one listed first) continues its
+        // column; every other child branches into the next one.
+        let mut first_child: HashMap<Option<&str>, &str> = HashMap::new();
+        for id in &order {
+            first_child
+                .entry(parent(id))
+                .and_modify(|first| {
+                    if position[id] < position[first] {
+                        *first = id;
+                    }
+                })
+                .or_insert(id);
+        }
+        let mut columns: HashMap<Option<&str>, usize> = HashMap::from([(None, 0)]);
+        for id in order.iter().rev() {
+            let base = columns[&parent(id)];
+            let column = if first_child[&parent(id)] == *id {
+                base
+            } else {
+                base + 1
+            };
+            columns.insert(Some(id), column);
+        }
+
+        let pipe = output::symbol("│ ", "| ");
+        for id in &order {
+            let column = columns[&Some(*id)];
+            write!(f, "{}{}  ", pipe.repeat(column), output::symbol("○", "o"))?;
+            if let Some(segment) = self.graph.segments.get(*id) {
+                self.write_label(f, segment)?;
+            }
+            writeln!(f)?;
+            if first_child[&parent(id)] != *id {
+                writeln!(
+                    f,
+                    "{}{}",
+                    pipe.repeat(column - 1),
+                    output::symbol("├─╯", "|-'")
+                )?;
+            }
+        }
+        writeln!(f, "{}  {}", output::symbol("◆", "*"), self.default_branch)
+    }
+}
+
+impl fmt::Display for GraphView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.graph.stacks.is_empty() {
+            writeln!(f, "No bookmark stacks found.")?;
+        } else {
+            self.fmt_graph(f)?;
+        }
+
+        let mut private: Vec<(&String, &String)> = self.graph.private_bookmarks.iter().collect();
+        private.sort();
+        let unnamed_tainted = self
+            .graph
+            .excluded_bookmark_count
+            .saturating_sub(self.graph.tainted_bookmarks.len());
+        if self.graph.tainted_bookmarks.is_empty()
+            && private.is_empty()
+            && unnamed_tainted == 0
+            && self.graph.conflicted_bookmarks.is_empty()
+        {
+            return Ok(());
+        }
+
+        writeln!(f, "\nExcluded from the stacks:")?;
+        for bookmark in &self.graph.tainted_bookmarks {
+            writeln!(f, "  {bookmark}: a merge commit is in its history")?;
+        }
+        if unnamed_tainted > 0 {
+            writeln!(
+                f,
+                "  {unnamed_tainted} unbookmarked head(s): a merge commit is in their history"
+            )?;
+        }
+        for (bookmark, change_id) in private {
+            writeln!(
+                f,
+                "  {bookmark}: private change {} is at or below it",
+                &change_id[..change_id.len().min(12)]
+            )?;
+        }
+        for bookmark in &self.graph.conflicted_bookmarks {
+            writeln!(
+                f,
+                "  {bookmark}: conflicted, it points to several commits; resolve with `jj \
+                 bookmark set {bookmark} -r <revision>` or `stakk resolve-bookmark {bookmark}`"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use stakk_core::forge::CheckState;
+    use stakk_core::graph::types::BranchStack;
+    use stakk_core::graph::types::SegmentCommit;
+    use stakk_core::jj::types::Signature;
+
+    use super::*;
+    use crate::cache::CachedPr;
+
+    fn segment(name: &str, commits: usize) -> BookmarkSegment {
+        let signature = Signature {
+            name: String::new(),
+            email: String::new(),
+            timestamp: String::new(),
+        };
+        BookmarkSegment {
+            bookmark_names: vec![name.to_string()],
+            change_id: format!("ch_{name}"),
+            commits: (0..commits)
+                .map(|i| SegmentCommit {
+                    commit_id: format!("c_{name}_{i}"),
+                    change_id: format!("ch_{name}"),
+                    description: format!("add {name}\n"),
+                    author: signature.clone(),
+                    committer: signature.clone(),
+                    short_change_id: format!("{name}{i}"),
+                    files: vec![],
+                    is_immutable: false,
+                    local_bookmark_names: vec![],
+                })
+                .collect(),
+        }
+    }
+
+    /// A graph from `stacks` of bookmark names, trunk to leaf.
+    fn graph_of(stacks: &[&[&str]]) -> ChangeGraph {
+        let mut graph = ChangeGraph {
+            adjacency_list: HashMap::new(),
+            stack_leaves: HashSet::new(),
+            stack_roots: HashSet::new(),
+            segments: HashMap::new(),
+            tainted_change_ids: HashSet::new(),
+            excluded_bookmark_count: 0,
+            tainted_bookmarks: vec![],
+            private_bookmarks: HashMap::new(),
+            conflicted_bookmarks: vec![],
+            root_trunks: HashMap::new(),
+            stacks: vec![],
+        };
+        for names in stacks {
+            let segments: Vec<BookmarkSegment> =
+                names.iter().map(|name| segment(name, 1)).collect();
+            for pair in segments.windows(2) {
+                graph
+                    .adjacency_list
+                    .insert(pair[1].change_id.clone(), pair[0].change_id.clone());
+            }
+            for seg in &segments {
+                graph.segments.insert(seg.change_id.clone(), seg.clone());
+            }
+            graph.stacks.push(BranchStack { segments });
+        }
+        graph
+    }
+
+    fn render(
+        graph: &ChangeGraph,
+        synced: &[&str],
+        pushed: &[&str],
+        prs: &[(&str, u64)],
+    ) -> String {
+        render_with_lookups(graph, synced, pushed, prs, &HashMap::new(), &HashMap::new())
+    }
+
+    fn render_with_lookups(
+        graph: &ChangeGraph,
+        synced: &[&str],
+        pushed: &[&str],
+        prs: &[(&str, u64)],
+        reviews: &HashMap<String, ReviewSummary>,
+        checks: &HashMap<String, Vec<CheckRun>>,
+    ) -> String {
+        let synced = synced.iter().map(ToString::to_string).collect();
+        let remote_targets = pushed
+            .iter()
+            .map(|name| ((*name).to_string(), "remote".to_string()))
+            .collect();
+        let prs = PrCache {
+            prs: prs
+                .iter()
+                .map(|(name, number)| {
+                    let pr = CachedPr {
+                        number: *number,
+                        url: String::new(),
+                        position: 1,
+                        stack_size: 1,
+                    };
+                    ((*name).to_string(), pr)
+                })
+                .collect(),
+        };
+        GraphView {
+            graph,
+            default_branch: "main",
+            synced: &synced,
+            remote_targets: &remote_targets,
+            prs: &prs,
+            reviews,
+            checks,
+        }
+        .to_string()
+    }
+
+    #[test]
+    fn branching_stacks_merge_back_into_their_base() {
+        let graph = graph_of(&[&["a", "b"], &["a", "c", "d"], &["e"]]);
+        let out = render(&graph, &["a"], &["a", "b"], &[("a", 11), ("b", 12)]);
+        assert_eq!(
+            out,
+            "○  b #12 [needs push]  b0 add b\n│ ○  d [not pushed]  d0 add d\n│ ○  c [not pushed]  \
+             c0 add c\n├─╯\n○  a #11 [synced]  a0 add a\n│ ○  e [not pushed]  e0 add e\n├─╯\n◆  \
+             main\n"
+        );
+    }
+
+    #[test]
+    fn reviews_follow_the_pr_number() {
+        let graph = graph_of(&[&["a", "b"]]);
+        let reviews = HashMap::from([
+            (
+                "a".to_string(),
+                ReviewSummary {
+                    approved: 2,
+                    changes_requested: 1,
+                },
+            ),
+            ("b".to_string(), ReviewSummary::default()),
+        ]);
+        let out = render_with_lookups(
+            &graph,
+            &[],
+            &[],
+            &[("a", 11), ("b", 12)],
+            &reviews,
+            &HashMap::new(),
+        );
+        assert!(out.contains("○  a #11 ✓2 ✗1 [not pushed]  a0 add a\n"));
+        assert!(out.contains("○  b #12 [not pushed]  b0 add b\n"));
+    }
+
+    #[test]
+    fn checks_are_summed_up_after_the_reviews() {
+        let graph = graph_of(&[&["a", "b"]]);
+        let run = |name: &str, state| CheckRun {
+            name: name.to_string(),
+            state,
+        };
+        let checks = HashMap::from([
+            (
+                "a".to_string(),
+                vec![
+                    run("build", CheckState::Passed),
+                    run("test", CheckState::Failed),
+                ],
+            ),
+            ("b".to_string(), vec![]),
+        ]);
+        let out = render_with_lookups(
+            &graph,
+            &[],
+            &[],
+            &[("a", 11), ("b", 12)],
+            &HashMap::new(),
+            &checks,
+        );
+        assert!(out.contains("○  a #11 [CI: 1 passed, 1 failed] [not pushed]  a0 add a\n"));
+        assert!(out.contains("○  b #12 [CI: no checks] [not pushed]  b0 add b\n"));
+    }
+
+    #[test]
+    fn excluded_bookmarks_listed_with_reason() {
+        let mut graph = graph_of(&[&["a"]]);
+        graph.segments.get_mut("ch_a").unwrap().commits = segment("a", 3).commits;
+        graph.excluded_bookmark_count = 2;
+        graph.tainted_bookmarks = vec!["merged".to_string()];
+        graph
+            .private_bookmarks
+            .insert("secret".to_string(), "qpvuntsmwlqt1234".to_string());
+        graph.conflicted_bookmarks = vec!["split".to_string()];
+        let out = render(&graph, &[], &[], &[]);
+        assert!(out.starts_with("○  a [not pushed]  a0 add a (3 commits)\n"));
+        assert!(out.ends_with(
+            "\nExcluded from the stacks:\n  merged: a merge commit is in its history\n  1 \
+             unbookmarked head(s): a merge commit is in their history\n  secret: private change \
+             qpvuntsmwlqt is at or below it\n  split: conflicted, it points to several commits; \
+             resolve with `jj bookmark set split -r <revision>` or `stakk resolve-bookmark \
+             split`\n"
+        ));
+    }
+}
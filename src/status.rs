@@ -0,0 +1,270 @@
+//! `stakk status`: everything about one stack in one place.
+//!
+//! For each segment from trunk to leaf, gathers its commits, how the local
+//! bookmark compares to its remote branch, the open PR and whether its base
+//! is right, each reviewer's latest verdict, and the CI checks on the branch.
+
+use std::fmt;
+
+use stakk_core::forge::CheckRun;
+use stakk_core::forge::Forge;
+use stakk_core::forge::PrDetails;
+use stakk_core::forge::PrState;
+use stakk_core::forge::PullRequest;
+use stakk_core::forge::Review;
+use stakk_core::forge::ReviewState;
+use stakk_core::forge::latest_verdicts;
+use stakk_core::graph::types::BookmarkSegment;
+use stakk_core::graph::types::BranchStack;
+use stakk_core::jj::Jj;
+use stakk_core::jj::runner::JjRunner;
+use stakk_core::submit::describe_checks;
+
+use crate::error::StakkError;
+use crate::output;
+
+/// The state of one segment of a stack.
+#[derive(Debug)]
+pub struct SegmentStatus {
+    pub bookmark: String,
+    /// `(short change ID, summary)` of each commit, oldest first.
+    pub commits: Vec<(String, String)>,
+    /// The branch the segment's PR should target.
+    pub expected_base: String,
+    pub remote: String,
+    /// Commits only on the local bookmark and only on the remote branch;
+    /// `None` if the bookmark was never pushed.
+    pub divergence: Option<(usize, usize)>,
+    pub pr: Option<PullRequest>,
+    pub reviews: Vec<Review>,
+    /// Check runs on the remote branch; empty if it was never pushed.
+    pub checks: Vec<CheckRun>,
+}
+
+/// Gather the status of every segment of `stack`, trunk to leaf.
+pub async fn collect_stack_status<R: JjRunner, F: Forge>(
+    jj: &Jj<R>,
+    forge: &F,
+    stack: &BranchStack,
+    default_branch: &str,
+    remote: &str,
+) -> Result<Vec<SegmentStatus>, StakkError> {
+    let bookmarks: Vec<String> = stack
+        .segments
+        .iter()
+        .map(|seg| seg.bookmark_names.first().cloned().unwrap_or_default())
+        .collect();
+    let prs = forge.find_prs_for_branches(&bookmarks).await?;
+    let bases = std::iter::once(default_branch).chain(bookmarks.iter().map(String::as_str));
+    futures::future::try_join_all(
+        stack
+            .segments
+            .iter()
+            .zip(bases)
+            .zip(prs)
+            .map(|((segment, base), pr)| segment_status(jj, forge, segment, base, remote, pr)),
+    )
+    .await
+}
+
+async fn segment_status<R: JjRunner, F: Forge>(
+    jj: &Jj<R>,
+    forge: &F,
+    segment: &BookmarkSegment,
+    expected_base: &str,
+    remote: &str,
+    pr: Option<PrDetails>,
+) -> Result<SegmentStatus, StakkError> {
+    let bookmark = segment.bookmark_names.first().cloned().unwrap_or_default();
+    let divergence = jj.get_remote_divergence(&bookmark, remote).await?;
+    // A pushed branch without a PR can still have checks.
+    let (pr, reviews, checks) = match pr {
+        Some(details) => (Some(details.pr), details.reviews, details.checks),
+        None if divergence.is_some() => (None, Vec::new(), forge.list_check_runs(&bookmark).await?),
+        None => (None, Vec::new(), Vec::new()),
+    };
+
+    Ok(SegmentStatus {
+        commits: segment
+            .commits
+            .iter()
+            .rev()
+            .map(|c| {
+                let summary = c.description.lines().next().map_or("", str::trim);
+                let summary = if summary.is_empty() {
+                    "(no description)"
+                } else {
+                    summary
+                };
+                (c.short_change_id.clone(), summary.to_string())
+            })
+            .collect(),
+        bookmark,
+        expected_base: expected_base.to_string(),
+        remote: remote.to_string(),
+        divergence,
+        pr,
+        reviews,
+        checks,
+    })
+}
+
+/// Each reviewer's latest verdict (see [`latest_verdicts`]), grouped by
+/// verdict: "approved by alice; changes requested by bob".
+pub fn summarize_reviews(reviews: &[Review]) -> String {
+    let latest = latest_verdicts(reviews);
+
+    let groups: Vec<String> = [
+        (ReviewState::Approved, "approved"),
+        (ReviewState::ChangesRequested, "changes requested"),
+        (ReviewState::Dismissed, "dismissed"),
+        (ReviewState::Commented, "commented"),
+    ]
+    .into_iter()
+    .filter_map(|(wanted, label)| {
+        let authors: Vec<&str> = latest
+            .iter()
+            .filter(|(_, state)| *state == wanted)
+            .map(|(author, _)| *author)
+            .collect();
+        (!authors.is_empty()).then(|| format!("{label} by {}", authors.join(", ")))
+    })
+    .collect();
+
+    if groups.is_empty() {
+        "none".to_string()
+    } else {
+        groups.join("; ")
+    }
+}
+
+/// How the local bookmark compares to its branch on `remote`.
+fn describe_divergence(divergence: Option<(usize, usize)>, remote: &str) -> String {
+    match divergence {
+        None => format!("not pushed to {remote}"),
+        Some((0, 0)) => format!("in sync with {remote}"),
+        Some((ahead, 0)) => format!("{ahead} commit(s) ahead of {remote}"),
+        Some((0, behind)) => format!("{behind} commit(s) behind {remote}"),
+        Some((ahead, behind)) => {
+            format!("diverged from {remote}: {ahead} ahead, {behind} behind")
+        }
+    }
+}
+
+impl fmt::Display for SegmentStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.bookmark)?;
+        writeln!(f, "  Commits:  {}", self.commits.len())?;
+        for (change_id, summary) in &self.commits {
+            writeln!(f, "    {change_id}  {summary}")?;
+        }
+        writeln!(
+            f,
+            "  Remote:   {}",
+            describe_divergence(self.divergence, &self.remote)
+        )?;
+
+        let Some(pr) = &self.pr else {
+            return writeln!(f, "  PR:       none (run `stakk submit {}`)", self.bookmark);
+        };
+        let state = match pr.state {
+            PrState::Open => "open",
+            PrState::Closed => "closed",
+            PrState::Merged => "merged",
+        };
+        writeln!(f, "  PR:       #{} ({state}) {}", pr.number, pr.html_url)?;
+        if pr.base_ref == self.expected_base {
+            writeln!(f, "  Base:     {}", pr.base_ref)?;
+        } else {
+            writeln!(
+                f,
+                "  Base:     {} {} expected {} (run `stakk submit` to retarget)",
+                pr.base_ref,
+                output::symbol("—", "-"),
+                self.expected_base
+            )?;
+        }
+        writeln!(f, "  Reviews:  {}", summarize_reviews(&self.reviews))?;
+        if self.divergence.is_some() {
+            writeln!(f, "  CI:       {}", describe_checks(&self.checks))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn review(author: &str, state: ReviewState) -> Review {
+        Review {
+            author: author.to_string(),
+            state,
+        }
+    }
+
+    #[test]
+    fn summarize_reviews_keeps_latest_verdict_per_reviewer() {
+        let reviews = [
+            review("alice", ReviewState::ChangesRequested),
+            review("bob", ReviewState::Commented),
+            review("alice", ReviewState::Approved),
+            review("carol", ReviewState::ChangesRequested),
+            review("carol", ReviewState::Commented),
+            review("dave", ReviewState::Pending),
+        ];
+        assert_eq!(
+            summarize_reviews(&reviews),
+            "approved by alice; changes requested by carol; commented by bob"
+        );
+    }
+
+    #[test]
+    fn summarize_reviews_none() {
+        assert_eq!(summarize_reviews(&[]), "none");
+    }
+
+    #[test]
+    fn describe_divergence_cases() {
+        assert_eq!(describe_divergence(None, "origin"), "not pushed to origin");
+        assert_eq!(
+            describe_divergence(Some((0, 0)), "origin"),
+            "in sync with origin"
+        );
+        assert_eq!(
+            describe_divergence(Some((2, 0)), "origin"),
+            "2 commit(s) ahead of origin"
+        );
+        assert_eq!(
+            describe_divergence(Some((1, 3)), "origin"),
+            "diverged from origin: 1 ahead, 3 behind"
+        );
+    }
+
+    #[test]
+    fn display_flags_wrong_base() {
+        let status = SegmentStatus {
+            bookmark: "feat-b".to_string(),
+            commits: vec![("kx".to_string(), "add api".to_string())],
+            expected_base: "feat-a".to_string(),
+            remote: "origin".to_string(),
+            divergence: Some((0, 0)),
+            pr: Some(PullRequest {
+                number: 7,
+                html_url: "https://github.com/o/r/pull/7".to_string(),
+                title: "add api".to_string(),
+                head_ref: "feat-b".to_string(),
+                base_ref: "main".to_string(),
+                state: PrState::Open,
+                body: None,
+                draft: false,
+            }),
+            reviews: vec![],
+            checks: vec![],
+        };
+        let out = status.to_string();
+        assert!(out.contains("  PR:       #7 (open) https://github.com/o/r/pull/7\n"));
+        assert!(out.contains("  Base:     main — expected feat-a"));
+        assert!(out.contains("  CI:       no checks\n"));
+    }
+}
@@ -0,0 +1,122 @@
+//! Which branch each PR of a stack merges into.
+//!
+//! A stack's bookmarks are listed trunk to leaf, but not every bookmark
+//! gets a PR: one left out of the submission, or one whose PR already
+//! merged, passes its place to the bookmark above it. A [`BaseChain`]
+//! records each bookmark's role and works out the bases from that, so that
+//! the base of a PR never depends on where its segment sits in a list.
+//! A `Stakk-Base` trailer overrides the base of one PR without moving the
+//! ones above it.
+
+use std::collections::HashMap;
+
+/// The part a bookmark plays in a [`BaseChain`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Link {
+    /// The bookmark has a PR, and the next PR up is based on it.
+    Submitted,
+    /// The bookmark has no PR of its own: it was not selected, has no
+    /// changes of its own, or its PR already merged. The next PR up takes
+    /// the base this one would have had.
+    Skipped,
+}
+
+/// The bookmarks of one stack, trunk to leaf, with what each contributes
+/// to the bases of the others.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BaseChain<'a> {
+    default_branch: &'a str,
+    links: Vec<(&'a str, Link)>,
+    /// Bases set explicitly, ahead of the chain's own.
+    overrides: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> BaseChain<'a> {
+    /// An empty chain that starts at `default_branch`.
+    pub fn new(default_branch: &'a str) -> Self {
+        Self {
+            default_branch,
+            links: Vec::new(),
+            overrides: HashMap::new(),
+        }
+    }
+
+    /// A chain in which every one of `bookmarks` is submitted.
+    pub fn submitted(
+        default_branch: &'a str,
+        bookmarks: impl IntoIterator<Item = &'a str>,
+    ) -> Self {
+        let mut chain = Self::new(default_branch);
+        for bookmark in bookmarks {
+            chain.push(bookmark, Link::Submitted);
+        }
+        chain
+    }
+
+    /// Add the next bookmark up the stack.
+    pub fn push(&mut self, bookmark: &'a str, link: Link) {
+        self.links.push((bookmark, link));
+    }
+
+    /// Base `bookmark`'s PR on `base` whatever precedes it. The PRs above it
+    /// are still based on it.
+    pub fn override_base(&mut self, bookmark: &'a str, base: &'a str) {
+        self.overrides.insert(bookmark, base);
+    }
+
+    /// Each submitted bookmark with the branch its PR merges into, trunk
+    /// first.
+    pub fn bases(&self) -> Vec<(&'a str, &'a str)> {
+        let mut base = self.default_branch;
+        let mut bases = Vec::new();
+        for &(bookmark, link) in &self.links {
+            if link == Link::Submitted {
+                bases.push((
+                    bookmark,
+                    self.overrides.get(bookmark).copied().unwrap_or(base),
+                ));
+                base = bookmark;
+            }
+        }
+        bases
+    }
+
+    /// The branch `bookmark`'s PR merges into, if it is submitted.
+    pub fn base_of(&self, bookmark: &str) -> Option<&'a str> {
+        self.bases()
+            .into_iter()
+            .find(|(name, _)| *name == bookmark)
+            .map(|(_, base)| base)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skipped_bookmarks_pass_their_base_up() {
+        let mut chain = BaseChain::new("main");
+        chain.push("a", Link::Skipped);
+        chain.push("b", Link::Submitted);
+        chain.push("c", Link::Skipped);
+        chain.push("d", Link::Skipped);
+        chain.push("e", Link::Submitted);
+        assert_eq!(chain.bases(), [("b", "main"), ("e", "b")]);
+        assert_eq!(chain.base_of("a"), None);
+    }
+
+    #[test]
+    fn overrides_move_one_base_and_keep_the_chain_above() {
+        let mut chain = BaseChain::submitted("main", ["a", "b", "c"]);
+        chain.override_base("b", "release");
+        assert_eq!(chain.bases(), [("a", "main"), ("b", "release"), ("c", "b")]);
+        assert_eq!(chain.base_of("c"), Some("b"));
+    }
+
+    #[test]
+    fn submitted_chains_stack_each_pr_on_the_one_below() {
+        let chain = BaseChain::submitted("main", ["a", "b", "c"]);
+        assert_eq!(chain.bases(), [("a", "main"), ("b", "a"), ("c", "b")]);
+    }
+}
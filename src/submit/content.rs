@@ -0,0 +1,493 @@
+//! User templates for PR titles and bodies.
+//!
+//! When `--title-template` or `--body-template` is set, the planning phase
+//! renders the PR title/body from a minijinja template instead of using the
+//! commit-derived defaults directly. The defaults are still available to the
+//! template as `default_title` / `default_body`.
+//!
+//! With `--pr-template`, the resulting body is then merged into the
+//! repository's own pull request template (see [`RepoPrTemplate`]).
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use minijinja::Environment;
+use serde::Serialize;
+
+use super::SubmitError;
+use crate::graph::types::SegmentCommit;
+use crate::submit::options::PrTemplateMerge;
+use crate::submit::options::TitleSource;
+
+const TITLE_TEMPLATE: &str = "pr_title";
+const BODY_TEMPLATE: &str = "pr_body";
+
+/// Template rendering context for one PR's title and body.
+#[derive(Debug, Clone, Serialize)]
+pub struct PrContentContext {
+    /// The bookmark (head branch) of the PR.
+    pub bookmark: String,
+    /// The PR's base branch.
+    pub base: String,
+    /// Number of commits in the segment.
+    pub commit_count: usize,
+    /// The bookmarked (newest) commit — the one the default title comes
+    /// from.
+    pub first_commit: CommitContext,
+    /// All commits in the segment, oldest first.
+    pub commits: Vec<CommitContext>,
+    /// The title stakk would use without a template.
+    pub default_title: String,
+    /// The body stakk would use without a template (empty if none).
+    pub default_body: String,
+}
+
+/// Template rendering context for a single commit.
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitContext {
+    /// First line of the description.
+    pub summary: String,
+    /// Description without the summary line, trimmed.
+    pub body: String,
+    /// Full description, trimmed.
+    pub description: String,
+    pub change_id: String,
+    pub short_change_id: String,
+    pub commit_id: String,
+    pub author_name: String,
+    pub author_email: String,
+}
+
+impl CommitContext {
+    fn from_commit(commit: &SegmentCommit) -> Self {
+        let description = commit.description.trim();
+        let mut lines = description.splitn(2, '\n');
+        let summary = lines.next().unwrap_or_default().trim().to_string();
+        let body = lines.next().unwrap_or_default().trim().to_string();
+        Self {
+            summary,
+            body,
+            description: description.to_string(),
+            change_id: commit.change_id.clone(),
+            short_change_id: commit.short_change_id.clone(),
+            commit_id: commit.commit_id.clone(),
+            author_name: commit.author.name.clone(),
+            author_email: commit.author.email.clone(),
+        }
+    }
+}
+
+impl PrContentContext {
+    /// Build the context for a segment. `commits` is newest-first, as stored
+    /// on `BookmarkSegment`.
+    pub fn new(
+        bookmark: &str,
+        base: &str,
+        commits: &[SegmentCommit],
+        default_title: &str,
+        default_body: Option<&str>,
+    ) -> Self {
+        let contexts: Vec<CommitContext> = commits
+            .iter()
+            .rev()
+            .map(CommitContext::from_commit)
+            .collect();
+        Self {
+            bookmark: bookmark.to_string(),
+            base: base.to_string(),
+            commit_count: contexts.len(),
+            first_commit: contexts
+                .last()
+                .cloned()
+                .unwrap_or_else(|| CommitContext::empty(bookmark)),
+            commits: contexts,
+            default_title: default_title.to_string(),
+            default_body: default_body.unwrap_or_default().to_string(),
+        }
+    }
+}
+
+impl CommitContext {
+    /// Placeholder for segments without commits (never produced by the graph,
+    /// but keeps rendering total).
+    fn empty(bookmark: &str) -> Self {
+        Self {
+            summary: bookmark.to_string(),
+            body: String::new(),
+            description: String::new(),
+            change_id: String::new(),
+            short_change_id: String::new(),
+            commit_id: String::new(),
+            author_name: String::new(),
+            author_email: String::new(),
+        }
+    }
+}
+
+/// A repository's pull request template, and how generated bodies are
+/// merged into it.
+#[derive(Debug, Clone)]
+pub struct RepoPrTemplate {
+    source: String,
+    merge: PrTemplateMerge,
+    section: String,
+}
+
+impl RepoPrTemplate {
+    /// Where GitHub looks for a single pull request template, relative to the
+    /// repository root, in its order of precedence.
+    const LOCATIONS: [&str; 6] = [
+        ".github/pull_request_template.md",
+        ".github/PULL_REQUEST_TEMPLATE.md",
+        "pull_request_template.md",
+        "PULL_REQUEST_TEMPLATE.md",
+        "docs/pull_request_template.md",
+        "docs/PULL_REQUEST_TEMPLATE.md",
+    ];
+
+    pub fn new(source: String, merge: PrTemplateMerge, section: &str) -> Self {
+        Self {
+            source,
+            merge,
+            section: section.trim().to_string(),
+        }
+    }
+
+    /// Path of the repository's PR template under `workspace_root`, if it
+    /// has one.
+    pub fn find(workspace_root: &Path) -> Option<PathBuf> {
+        Self::LOCATIONS
+            .iter()
+            .map(|location| workspace_root.join(location))
+            .find(|path| path.is_file())
+    }
+
+    /// Merge a generated body into the template. Without a generated body,
+    /// the template is used as-is.
+    pub fn apply(&self, generated: Option<&str>) -> String {
+        let template = self.source.trim();
+        let Some(generated) = generated.map(str::trim).filter(|g| !g.is_empty()) else {
+            return template.to_string();
+        };
+        match self.merge {
+            PrTemplateMerge::Ignore => generated.to_string(),
+            PrTemplateMerge::Prepend => format!("{generated}\n\n{template}"),
+            PrTemplateMerge::Append => format!("{template}\n\n{generated}"),
+            PrTemplateMerge::Fill => fill_section(template, &self.section, generated)
+                .unwrap_or_else(|| format!("{template}\n\n{generated}")),
+        }
+    }
+}
+
+/// Replace the contents of the Markdown section headed `section` (up to the
+/// next heading of the same or a higher level) with `content`. `None` if the
+/// template has no such section.
+fn fill_section(template: &str, section: &str, content: &str) -> Option<String> {
+    let lines: Vec<&str> = template.lines().collect();
+    let (start, level) = lines.iter().enumerate().find_map(|(i, line)| {
+        let (level, title) = parse_heading(line)?;
+        title.eq_ignore_ascii_case(section).then_some((i, level))
+    })?;
+    let end = lines[start + 1..]
+        .iter()
+        .position(|line| parse_heading(line).is_some_and(|(l, _)| l <= level))
+        .map_or(lines.len(), |offset| start + 1 + offset);
+
+    let mut filled = lines[..=start].join("\n");
+    filled.push_str("\n\n");
+    filled.push_str(content);
+    if end < lines.len() {
+        filled.push_str("\n\n");
+        filled.push_str(&lines[end..].join("\n"));
+    }
+    Some(filled)
+}
+
+/// Level and title of an ATX Markdown heading (`## Title`).
+fn parse_heading(line: &str) -> Option<(usize, &str)> {
+    let line = line.trim_start();
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let rest = &line[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    Some((level, rest.trim().trim_end_matches('#').trim_end()))
+}
+
+/// Compiled PR title/body templates. Either may be absent, in which case the
+/// commit-derived default is used for that field.
+#[derive(Debug, Default)]
+pub struct PrTemplates {
+    env: Environment<'static>,
+    has_title: bool,
+    has_body: bool,
+    repo_template: Option<RepoPrTemplate>,
+    title_source: TitleSource,
+}
+
+impl PrTemplates {
+    /// Compile the given template sources.
+    pub fn new(title: Option<&str>, body: Option<&str>) -> Result<Self, SubmitError> {
+        let mut env = Environment::new();
+        for (name, source) in [(TITLE_TEMPLATE, title), (BODY_TEMPLATE, body)] {
+            if let Some(source) = source {
+                env.add_template_owned(name, source.to_string())
+                    .map_err(|e| SubmitError::TemplateRenderFailed {
+                        message: format!("failed to compile {name} template: {e}"),
+                    })?;
+            }
+        }
+        Ok(Self {
+            env,
+            has_title: title.is_some(),
+            has_body: body.is_some(),
+            repo_template: None,
+            title_source: TitleSource::default(),
+        })
+    }
+
+    /// Take default titles from `source` instead of the tip commit.
+    #[must_use]
+    pub fn with_title_source(mut self, source: TitleSource) -> Self {
+        self.title_source = source;
+        self
+    }
+
+    /// Where default titles come from.
+    pub fn title_source(&self) -> TitleSource {
+        self.title_source
+    }
+
+    /// Whether titles are rendered from a title template.
+    pub fn has_title_template(&self) -> bool {
+        self.has_title
+    }
+
+    /// Merge every rendered body into the repository's PR template.
+    #[must_use]
+    pub fn with_repo_template(mut self, repo_template: Option<RepoPrTemplate>) -> Self {
+        self.repo_template = repo_template;
+        self
+    }
+
+    /// Render the PR title. Falls back to `ctx.default_title` when no title
+    /// template is set or the template renders to whitespace — GitHub rejects
+    /// empty titles.
+    pub fn render_title(&self, ctx: &PrContentContext) -> Result<String, SubmitError> {
+        if !self.has_title {
+            return Ok(ctx.default_title.clone());
+        }
+        let rendered = self.render(TITLE_TEMPLATE, ctx)?;
+        // Titles are single-line; join any lines a template produced.
+        let title = rendered
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if title.is_empty() {
+            Ok(ctx.default_title.clone())
+        } else {
+            Ok(title)
+        }
+    }
+
+    /// Render the PR body. Returns `default_body` when no body template is
+    /// set, and `None` when the result is empty. The repository's PR
+    /// template, if any, is applied last.
+    pub fn render_body(
+        &self,
+        ctx: &PrContentContext,
+        default_body: Option<String>,
+    ) -> Result<Option<String>, SubmitError> {
+        let body = if self.has_body {
+            let rendered = self.render(BODY_TEMPLATE, ctx)?;
+            let body = rendered.trim();
+            if body.is_empty() {
+                None
+            } else {
+                Some(body.to_string())
+            }
+        } else {
+            default_body
+        };
+        Ok(match &self.repo_template {
+            Some(repo_template) => {
+                Some(repo_template.apply(body.as_deref())).filter(|b| !b.is_empty())
+            }
+            None => body,
+        })
+    }
+
+    fn render(&self, name: &str, ctx: &PrContentContext) -> Result<String, SubmitError> {
+        self.env
+            .get_template(name)
+            .and_then(|t| t.render(ctx))
+            .map_err(|e| SubmitError::TemplateRenderFailed {
+                message: format!("{name}: {e}"),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jj::types::Signature;
+
+    fn commit(change_id: &str, description: &str) -> SegmentCommit {
+        let sig = Signature {
+            name: "Jo".to_string(),
+            email: "jo@example.com".to_string(),
+            timestamp: "T".to_string(),
+        };
+        SegmentCommit {
+            commit_id: format!("c_{change_id}"),
+            change_id: change_id.to_string(),
+            description: description.to_string(),
+            author: sig.clone(),
+            committer: sig,
+            short_change_id: change_id.to_string(),
+            files: vec![],
+            is_immutable: false,
+            local_bookmark_names: vec![],
+        }
+    }
+
+    fn context() -> PrContentContext {
+        // Newest first, as on BookmarkSegment.
+        let commits = [
+            commit("bbb", "style login form\n\nTweak spacing."),
+            commit("aaa", "add login page"),
+        ];
+        PrContentContext::new(
+            "login",
+            "main",
+            &commits,
+            "style login form",
+            Some("default body"),
+        )
+    }
+
+    #[test]
+    fn context_orders_commits_oldest_first() {
+        let ctx = context();
+        assert_eq!(ctx.commit_count, 2);
+        assert_eq!(ctx.commits[0].summary, "add login page");
+        assert_eq!(ctx.first_commit.summary, "style login form");
+        assert_eq!(ctx.first_commit.body, "Tweak spacing.");
+    }
+
+    #[test]
+    fn no_templates_use_defaults() {
+        let templates = PrTemplates::new(None, None).unwrap();
+        let ctx = context();
+        assert_eq!(templates.render_title(&ctx).unwrap(), "style login form");
+        assert_eq!(
+            templates
+                .render_body(&ctx, Some("default body".to_string()))
+                .unwrap()
+                .as_deref(),
+            Some("default body")
+        );
+    }
+
+    #[test]
+    fn title_template_renders_variables() {
+        let templates = PrTemplates::new(
+            Some("[{{ bookmark }}] {{ first_commit.summary }} ({{ commit_count }})"),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            templates.render_title(&context()).unwrap(),
+            "[login] style login form (2)"
+        );
+    }
+
+    #[test]
+    fn empty_title_falls_back_to_default() {
+        let templates = PrTemplates::new(Some("{{ '' }}"), None).unwrap();
+        assert_eq!(
+            templates.render_title(&context()).unwrap(),
+            "style login form"
+        );
+    }
+
+    #[test]
+    fn body_template_can_list_commits() {
+        let templates = PrTemplates::new(
+            None,
+            Some("{{ commits | map(attribute='summary') | join(', ') }}\n\n{{ default_body }}"),
+        )
+        .unwrap();
+        let body = templates.render_body(&context(), None).unwrap().unwrap();
+        assert_eq!(body, "add login page, style login form\n\ndefault body");
+    }
+
+    #[test]
+    fn empty_body_template_is_none() {
+        let templates = PrTemplates::new(None, Some("  ")).unwrap();
+        assert!(templates.render_body(&context(), None).unwrap().is_none());
+    }
+
+    const REPO_TEMPLATE: &str =
+        "## Description\n\n<!-- What does this change? -->\n\n## Checklist\n\n- [ ] Tests\n";
+
+    #[test]
+    fn repo_template_fill_replaces_section_placeholder() {
+        let template = RepoPrTemplate::new(
+            REPO_TEMPLATE.to_string(),
+            PrTemplateMerge::Fill,
+            "description",
+        );
+        assert_eq!(
+            template.apply(Some("Adds login.")),
+            "## Description\n\nAdds login.\n\n## Checklist\n\n- [ ] Tests"
+        );
+    }
+
+    #[test]
+    fn repo_template_fill_without_section_appends() {
+        let template =
+            RepoPrTemplate::new(REPO_TEMPLATE.to_string(), PrTemplateMerge::Fill, "Summary");
+        assert_eq!(
+            template.apply(Some("Adds login.")),
+            format!("{}\n\nAdds login.", REPO_TEMPLATE.trim())
+        );
+    }
+
+    #[test]
+    fn repo_template_prepend_and_append() {
+        let prepend = RepoPrTemplate::new("T".to_string(), PrTemplateMerge::Prepend, "");
+        let append = RepoPrTemplate::new("T".to_string(), PrTemplateMerge::Append, "");
+        assert_eq!(prepend.apply(Some("body")), "body\n\nT");
+        assert_eq!(append.apply(Some("body")), "T\n\nbody");
+        assert_eq!(append.apply(None), "T");
+    }
+
+    #[test]
+    fn repo_template_applies_after_body_template() {
+        let templates = PrTemplates::new(None, Some("{{ bookmark }} body"))
+            .unwrap()
+            .with_repo_template(Some(RepoPrTemplate::new(
+                REPO_TEMPLATE.to_string(),
+                PrTemplateMerge::Fill,
+                "Description",
+            )));
+        let body = templates.render_body(&context(), None).unwrap().unwrap();
+        assert!(body.starts_with("## Description\n\nlogin body\n\n## Checklist"));
+    }
+
+    #[test]
+    fn parse_heading_levels() {
+        assert_eq!(parse_heading("## Description"), Some((2, "Description")));
+        assert_eq!(parse_heading("# Title #"), Some((1, "Title")));
+        assert_eq!(parse_heading("#hashtag"), None);
+        assert_eq!(parse_heading("plain"), None);
+    }
+
+    #[test]
+    fn invalid_template_is_error() {
+        assert!(PrTemplates::new(Some("{{ unclosed"), None).is_err());
+    }
+}
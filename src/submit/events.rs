@@ -0,0 +1,117 @@
+//! Events reported while a submission plan executes.
+//!
+//! The executor never writes to the terminal. It reports each step as a
+//! [`SubmitEvent`] to a [`SubmitObserver`], and the caller renders them: the
+//! `stakk` binary drives a spinner, while a TUI or JSON output can read them
+//! from a channel.
+
+use std::fmt;
+use std::sync::mpsc::Sender;
+
+/// Something that happened while executing a submission plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubmitEvent {
+    /// A bookmark is being pushed to the remote.
+    Pushing { bookmark: String },
+    /// The head branch of a renamed bookmark's PR is being renamed to match.
+    RenamingBranch { from: String, to: String },
+    /// The base branch of a PR in the stack is being changed.
+    UpdatingBase { pr_number: u64 },
+    /// A PR's title and/or body is being synced from its commits.
+    SyncingContent { pr_number: u64 },
+    /// A bookmark already has a PR.
+    ExistingPr {
+        bookmark: String,
+        pr_number: u64,
+        url: String,
+    },
+    /// The head and base branches of a new PR are being checked on the forge.
+    CheckingBranches { bookmark: String },
+    /// A PR is being created.
+    CreatingPr { bookmark: String, title: String },
+    /// A PR was created.
+    CreatedPr {
+        bookmark: String,
+        pr_number: u64,
+        url: String,
+    },
+    /// A PR stacked outside the submission is being retargeted.
+    Retargeting { pr_number: u64 },
+    /// A PR stacked outside the submission was retargeted.
+    Retargeted {
+        pr_number: u64,
+        bookmark: String,
+        from: String,
+        to: String,
+    },
+    /// The stack comments or body fences are being written.
+    UpdatingStackComments,
+    /// Cleaning up stale stack info failed; the submission carries on.
+    Warning { message: String },
+}
+
+impl SubmitEvent {
+    /// Whether the event starts a step that is still running, as opposed to
+    /// reporting an outcome.
+    pub fn is_step(&self) -> bool {
+        matches!(
+            self,
+            Self::Pushing { .. }
+                | Self::RenamingBranch { .. }
+                | Self::UpdatingBase { .. }
+                | Self::SyncingContent { .. }
+                | Self::CheckingBranches { .. }
+                | Self::CreatingPr { .. }
+                | Self::Retargeting { .. }
+                | Self::UpdatingStackComments
+        )
+    }
+}
+
+impl fmt::Display for SubmitEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pushing { bookmark } => write!(f, "Pushing bookmark: {bookmark}"),
+            Self::RenamingBranch { from, to } => write!(f, "Renaming branch {from} to {to}..."),
+            Self::UpdatingBase { pr_number } => write!(f, "Updating PR #{pr_number} base..."),
+            Self::SyncingContent { pr_number } => {
+                write!(f, "Syncing PR #{pr_number} title/body...")
+            }
+            Self::ExistingPr { pr_number, url, .. } => {
+                write!(f, "Existing PR #{pr_number}: {url}")
+            }
+            Self::CheckingBranches { bookmark } => {
+                write!(f, "Checking branches for {bookmark}...")
+            }
+            Self::CreatingPr { title, .. } => write!(f, "Creating PR: {title}"),
+            Self::CreatedPr { pr_number, url, .. } => write!(f, "Created PR #{pr_number}: {url}"),
+            Self::Retargeting { pr_number } => write!(f, "Retargeting PR #{pr_number}..."),
+            Self::Retargeted {
+                pr_number,
+                bookmark,
+                from,
+                to,
+            } => write!(f, "Retargeted PR #{pr_number} ({bookmark}): {from} -> {to}"),
+            Self::UpdatingStackComments => write!(f, "Updating stack comments..."),
+            Self::Warning { message } => write!(f, "Warning: {message}"),
+        }
+    }
+}
+
+/// Receives the events of a submission as they happen.
+pub trait SubmitObserver: Sync {
+    fn on_event(&self, event: SubmitEvent);
+}
+
+/// Ignores all events.
+impl SubmitObserver for () {
+    fn on_event(&self, _event: SubmitEvent) {}
+}
+
+/// Forwards events to a channel. Events sent after the receiver is dropped
+/// are discarded.
+impl SubmitObserver for Sender<SubmitEvent> {
+    fn on_event(&self, event: SubmitEvent) {
+        let _ = self.send(event);
+    }
+}
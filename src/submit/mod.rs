@@ -3,41 +3,90 @@
 //! Takes a change graph and forge implementation and submits bookmarks as
 //! stacked pull requests, updating existing PRs idempotently.
 
+pub mod base_chain;
+pub mod content;
+pub mod events;
+pub mod options;
 mod trailers;
 mod unwrap;
 
+use std::collections::BTreeMap;
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fmt;
+use std::ops::ControlFlow;
+use std::time::Duration;
 
+use futures::StreamExt;
 use miette::Diagnostic;
+use serde::Deserialize;
+use serde::Serialize;
 use thiserror::Error;
 
-use crate::cli::submit::PrMode;
-use crate::cli::submit::SyncPrContent;
-use crate::cli::submit::TrailerHandling;
+use crate::forge::CheckRun;
+use crate::forge::CheckState;
+use crate::forge::CommentEdit;
 use crate::forge::CreatePrParams;
 use crate::forge::Forge;
 use crate::forge::ForgeError;
+use crate::forge::ForgeRepoRef;
+use crate::forge::Issue;
+use crate::forge::MergeMethod;
+use crate::forge::PrState;
 use crate::forge::PullRequest;
-use crate::forge::comment::STAKK_REPO_URL;
-use crate::forge::comment::StackCommentContext;
-use crate::forge::comment::StackCommentData;
-use crate::forge::comment::StackEntry;
-use crate::forge::comment::StackEntryContext;
-use crate::forge::comment::StackPlacement;
-use crate::forge::comment::find_stack_comment;
-use crate::forge::comment::find_stack_in_body;
-use crate::forge::comment::format_stack_comment;
-use crate::forge::comment::splice_stack_into_body;
-use crate::forge::comment::strip_stack_from_body;
-use crate::forge::comment::with_comment_preamble;
+use crate::forge::ReviewSummary;
+use crate::forge::markup::LINKS_REGION;
+use crate::forge::markup::NOTES_REGION;
+use crate::forge::markup::STAKK_REPO_URL;
+use crate::forge::markup::StackCommentContext;
+use crate::forge::markup::StackCommentData;
+use crate::forge::markup::StackEntry;
+use crate::forge::markup::StackEntryContext;
+use crate::forge::markup::StackPlacement;
+use crate::forge::markup::find_stack_comment;
+use crate::forge::markup::find_stack_in_body;
+use crate::forge::markup::format_pr_links;
+use crate::forge::markup::format_stack_comment;
+use crate::forge::markup::format_umbrella_body;
+use crate::forge::markup::parse_followed_by;
+use crate::forge::markup::parse_stack_comment;
+use crate::forge::markup::parse_stack_in_body;
+use crate::forge::markup::parse_umbrella_body;
+use crate::forge::markup::splice_change_id_into_body;
+use crate::forge::markup::splice_links_into_body;
+use crate::forge::markup::splice_stack_into_body;
+use crate::forge::markup::strip_managed_regions;
+use crate::forge::markup::strip_stack_from_body;
+use crate::forge::markup::with_comment_preamble;
+use crate::forge::recording::RecordingForge;
 use crate::graph::types::BookmarkSegment;
 use crate::graph::types::ChangeGraph;
 use crate::graph::types::SegmentCommit;
 use crate::jj::Jj;
 use crate::jj::JjError;
+use crate::jj::PushOptions;
+use crate::jj::PushSize;
 use crate::jj::runner::JjRunner;
+use crate::journal::JournalStep;
+use crate::journal::SubmitAction;
+use crate::poll;
+use crate::poll::Polled;
+use crate::poll::Schedule;
+use crate::submit::base_chain::BaseChain;
+use crate::submit::base_chain::Link;
+use crate::submit::content::PrContentContext;
+use crate::submit::content::PrTemplates;
+use crate::submit::events::SubmitEvent;
+use crate::submit::events::SubmitObserver;
+use crate::submit::options::PrMode;
+use crate::submit::options::PrOptions;
+use crate::submit::options::SyncPrContent;
+use crate::submit::options::TitleSource;
+use crate::submit::options::TrailerHandling;
+use crate::submit::trailers::has_signoff;
+use crate::submit::trailers::parse_pr_options;
 use crate::submit::trailers::split_trailers;
+use crate::submit::trailers::without_stakk_trailers;
 use crate::submit::unwrap::unwrap_markdown;
 
 /// Errors from the submission pipeline.
@@ -51,6 +100,69 @@ pub enum SubmitError {
     )]
     BookmarkNotFound { bookmark: String },
 
+    /// A private change lies at or below the target bookmark.
+    #[error("bookmark '{bookmark}' is on top of private change {change_id}")]
+    #[diagnostic(
+        code(stakk::submit::private_change),
+        help(
+            "private changes must never be pushed; rebase the stack off the private change (e.g. \
+             `jj rebase -s <first change above it> -d trunk()`), or adjust --private-revset"
+        )
+    )]
+    PrivateChange { bookmark: String, change_id: String },
+
+    /// A commit in the segment has a `Stakk-*` trailer stakk can't apply.
+    #[error("invalid trailer in a commit of '{bookmark}': {trailer}")]
+    #[diagnostic(
+        code(stakk::submit::invalid_pr_trailer),
+        help(
+            "supported trailers are Stakk-Draft (true or false), Stakk-Reviewer, Stakk-Label, and \
+             Stakk-Base; fix the description with `jj describe`"
+        )
+    )]
+    InvalidPrTrailer { bookmark: String, trailer: String },
+
+    /// Sign-offs are required and some commits lack one.
+    #[error("{count} commit(s) lack a Signed-off-by trailer for their author: {changes}")]
+    #[diagnostic(
+        code(stakk::submit::missing_signoff),
+        help("add the sign-offs with `jj describe`, or submit with --fix-signoff to append them")
+    )]
+    MissingSignoff { count: usize, changes: String },
+
+    /// The stack being submitted contains bookmarks that point to several
+    /// commits.
+    #[error("the stack contains conflicted bookmarks: {bookmarks}")]
+    #[diagnostic(
+        code(stakk::submit::conflicted_bookmarks),
+        help(
+            "resolve each with `jj bookmark set <name> -r <revision>` or `stakk resolve-bookmark \
+             <name>`, or submit with --force to leave them behind"
+        )
+    )]
+    ConflictedBookmarks { bookmarks: String },
+
+    /// A submitted bookmark's branch would collide on the remote with the
+    /// branch of another bookmark in the graph.
+    #[error(
+        "bookmark '{bookmark}' (change {change_id}) would collide on the remote with \
+         '{other_bookmark}' (change {other_change_id})"
+    )]
+    #[diagnostic(
+        code(stakk::submit::branch_name_collision),
+        help(
+            "branch names that differ only in case clash on case-insensitive file systems, and a \
+             branch cannot coexist with one nested under it (`feat` and `feat/login`); rename one \
+             of them with `jj bookmark rename`"
+        )
+    )]
+    BranchNameCollision {
+        bookmark: String,
+        change_id: String,
+        other_bookmark: String,
+        other_change_id: String,
+    },
+
     /// Selected bookmarks were never consumed by any segment in the target
     /// stack — typically because their commits are immutable in jj, so the
     /// bookmarks revset excluded them from the change graph.
@@ -98,6 +210,64 @@ pub enum SubmitError {
         source: ForgeError,
     },
 
+    /// Failed to look up the existing PRs of the submitted bookmarks.
+    #[error("failed to check for existing PRs")]
+    #[diagnostic(
+        code(stakk::submit::prs_lookup_failed),
+        help("check your network connection and GitHub token permissions")
+    )]
+    PrsLookupFailed {
+        #[source]
+        source: ForgeError,
+    },
+
+    /// Failed to rename the head branch of a renamed bookmark's PR.
+    #[error("failed to rename branch '{from}' to '{to}'")]
+    #[diagnostic(
+        code(stakk::submit::branch_rename_failed),
+        help(
+            "the PR for the bookmark's change was opened from '{from}'; rename the bookmark back \
+             to keep it, or check your token permissions"
+        )
+    )]
+    BranchRenameFailed {
+        from: String,
+        to: String,
+        #[source]
+        source: ForgeError,
+    },
+
+    /// The branch was renamed on the forge but jj could not pick it up.
+    #[error("failed to track the renamed branch '{bookmark}'")]
+    #[diagnostic(
+        code(stakk::submit::rename_track_failed),
+        help(
+            "run `jj git fetch`, `jj bookmark track {bookmark}@<remote>`, and move the bookmark \
+             back to its change, then re-run stakk submit"
+        )
+    )]
+    RenameTrackFailed {
+        bookmark: String,
+        #[source]
+        source: JjError,
+    },
+
+    /// Failed to track a newly pushed branch.
+    #[error("failed to track the pushed branch '{bookmark}@{remote}'")]
+    #[diagnostic(
+        code(stakk::submit::track_failed),
+        help(
+            "run `jj bookmark track {bookmark}@{remote}`, or pass --no-track to leave tracking to \
+             jj"
+        )
+    )]
+    TrackFailed {
+        bookmark: String,
+        remote: String,
+        #[source]
+        source: JjError,
+    },
+
     /// Failed to push a bookmark to the remote.
     #[error("failed to push bookmark '{bookmark}'")]
     #[diagnostic(
@@ -110,6 +280,46 @@ pub enum SubmitError {
         source: JjError,
     },
 
+    /// The remote branch moved between planning and pushing.
+    #[error(
+        "'{bookmark}' on the remote moved since the submission was planned (expected {}, now {})",
+        short_target(expected.as_deref()),
+        short_target(actual.as_deref())
+    )]
+    #[diagnostic(
+        code(stakk::submit::remote_moved),
+        help(
+            "someone else may have pushed to the branch; inspect it with `jj log -r \
+             '{bookmark}@{remote}'`, then re-run stakk submit to plan against it"
+        )
+    )]
+    RemoteMoved {
+        bookmark: String,
+        remote: String,
+        expected: Option<String>,
+        actual: Option<String>,
+    },
+
+    /// Pushing would overwrite commits on the remote branch that the local
+    /// bookmark has no version of.
+    #[error(
+        "pushing '{bookmark}' would drop {count} commit(s) from its branch on {remote} that are \
+         not in the local bookmark: {changes}"
+    )]
+    #[diagnostic(
+        code(stakk::submit::would_clobber_remote),
+        help(
+            "someone else may have pushed to the branch; rebase onto `{bookmark}@{remote}` or \
+             squash its commits in to keep them, or pass --force-push to overwrite them"
+        )
+    )]
+    WouldClobberRemote {
+        bookmark: String,
+        remote: String,
+        count: usize,
+        changes: String,
+    },
+
     /// Failed to update the base branch of an existing PR.
     #[error("failed to update PR base for '{bookmark}'")]
     #[diagnostic(
@@ -124,11 +334,40 @@ pub enum SubmitError {
         source: ForgeError,
     },
 
+    /// A new PR's head branch is not on the remote.
+    #[error("head branch '{bookmark}' does not exist on the remote")]
+    #[diagnostic(code(stakk::submit::head_branch_missing), help("{cause}"))]
+    HeadBranchMissing { bookmark: String, cause: String },
+
+    /// A new PR's base branch is not on the remote.
+    #[error("base branch '{base}' for '{bookmark}' does not exist on the remote")]
+    #[diagnostic(code(stakk::submit::base_branch_missing), help("{cause}"))]
+    BaseBranchMissing {
+        bookmark: String,
+        base: String,
+        cause: String,
+    },
+
+    /// Failed to check whether a branch exists on the remote.
+    #[error("failed to check whether branch '{branch}' exists on the remote")]
+    #[diagnostic(
+        code(stakk::submit::branch_lookup_failed),
+        help("check your network connection and GitHub token permissions")
+    )]
+    BranchLookupFailed {
+        branch: String,
+        #[source]
+        source: ForgeError,
+    },
+
     /// Failed to create a new PR.
     #[error("failed to create PR for '{bookmark}'")]
     #[diagnostic(
         code(stakk::submit::pr_create_failed),
-        help("check your token permissions and that the head branch exists on the remote")
+        help(
+            "check your token permissions, then re-run `stakk submit` — stack comments are only \
+             written once every PR in the stack exists"
+        )
     )]
     PrCreateFailed {
         bookmark: String,
@@ -148,6 +387,18 @@ pub enum SubmitError {
         source: ForgeError,
     },
 
+    /// A batched update of several stack comments failed.
+    #[error("failed to update {count} stack comments in one batch")]
+    #[diagnostic(
+        code(stakk::submit::comment_batch_failed),
+        help("check your token permissions for commenting on PRs")
+    )]
+    CommentBatchFailed {
+        count: usize,
+        #[source]
+        source: ForgeError,
+    },
+
     /// Failed to render a stack comment template.
     #[error("template rendering failed: {message}")]
     #[diagnostic(
@@ -168,31 +419,176 @@ pub enum SubmitError {
         source: ForgeError,
     },
 
-    /// Failed to sync the title of an existing PR.
-    #[error("failed to sync title of PR #{pr_number} for '{bookmark}'")]
+    /// Failed to request reviews on a new PR.
+    #[error("failed to request reviewers on PR #{pr_number}")]
+    #[diagnostic(
+        code(stakk::submit::review_request_failed),
+        help("check that each Stakk-Reviewer is a collaborator on the repository")
+    )]
+    ReviewRequestFailed {
+        pr_number: u64,
+        #[source]
+        source: ForgeError,
+    },
+
+    /// Failed to add labels to a new PR.
+    #[error("failed to add labels to PR #{pr_number}")]
+    #[diagnostic(
+        code(stakk::submit::label_failed),
+        help("check your token permissions for labeling pull requests")
+    )]
+    LabelFailed {
+        pr_number: u64,
+        #[source]
+        source: ForgeError,
+    },
+
+    /// Failed to mark a draft PR ready for review.
+    #[error("failed to mark PR #{pr_number} ready for review")]
+    #[diagnostic(
+        code(stakk::submit::mark_ready_failed),
+        help("mark it ready on the forge, or run `stakk retry`")
+    )]
+    MarkReadyFailed {
+        pr_number: u64,
+        #[source]
+        source: ForgeError,
+    },
+
+    /// Failed to sync the title and/or body of an existing PR.
+    #[error("failed to sync title/body of PR #{pr_number} for '{bookmark}'")]
+    #[diagnostic(
+        code(stakk::submit::content_sync_failed),
+        help(
+            "the PR exists but its title/body could not be updated — check your token permissions"
+        )
+    )]
+    ContentSyncFailed {
+        pr_number: u64,
+        bookmark: String,
+        #[source]
+        source: ForgeError,
+    },
+
+    /// Every PR exists, but some retargets or stack comment writes failed.
+    #[error("{failed} of {total} post-submit step(s) failed")]
+    #[diagnostic(
+        code(stakk::submit::steps_failed),
+        help(
+            "the PRs themselves are in place; run `stakk retry` to replay the failed steps, or \
+             re-run `stakk submit`"
+        )
+    )]
+    StepsFailed { failed: usize, total: usize },
+
+    /// A push, base update, or PR creation failed after earlier ones went
+    /// through, leaving the stack half-submitted.
+    #[error("submission stopped partway, after {} change(s)", actions.len())]
+    #[diagnostic(
+        code(stakk::submit::incomplete),
+        help(
+            "fix the cause and run `stakk submit --continue` to finish, or run `stakk undo` to \
+             close the PRs it created and restore the bases it changed"
+        )
+    )]
+    Incomplete {
+        #[source]
+        source: Box<SubmitError>,
+        /// What had been done before the failure, oldest first.
+        actions: Vec<SubmitAction>,
+    },
+
+    /// `stakk undo` could not close a PR created by the stopped submit.
+    #[error("failed to close PR #{pr_number} ('{bookmark}')")]
     #[diagnostic(
-        code(stakk::submit::title_sync_failed),
-        help("the PR exists but its title could not be updated — check your token permissions")
+        code(stakk::submit::close_failed),
+        help("check your token permissions, then re-run `stakk undo` or close the PR by hand")
     )]
-    TitleSyncFailed {
+    ClosePrFailed {
         pr_number: u64,
         bookmark: String,
         #[source]
         source: ForgeError,
     },
 
-    /// Failed to sync the body of an existing PR.
-    #[error("failed to sync body of PR #{pr_number} for '{bookmark}'")]
+    /// Some changes of a stopped submit could not be undone.
+    #[error("{failed} of {total} change(s) could not be undone")]
+    #[diagnostic(
+        code(stakk::submit::undo_failed),
+        help("run `stakk undo` again to retry the changes that are left")
+    )]
+    UndoFailed { failed: usize, total: usize },
+
+    /// The forge refused to turn on auto-merge for a submitted PR.
+    #[error("failed to enable auto-merge on PR #{pr_number} ('{bookmark}')")]
+    #[diagnostic(
+        code(stakk::submit::auto_merge_failed),
+        help(
+            "auto-merge must be allowed in the repository settings, and the PR needs branch \
+             protection with required reviews or checks; PRs after this one were left unchanged"
+        )
+    )]
+    AutoMergeFailed {
+        bookmark: String,
+        pr_number: u64,
+        #[source]
+        source: ForgeError,
+    },
+
+    /// Failed to create or update the stack's tracking issue.
+    #[error("failed to update the tracking issue of the stack")]
+    #[diagnostic(
+        code(stakk::submit::umbrella_failed),
+        help("the PRs were submitted; check that issues are enabled for the repository")
+    )]
+    UmbrellaFailed {
+        #[source]
+        source: ForgeError,
+    },
+
+    /// Failed to link a submitted PR to an issue its commits reference.
+    #[error("failed to link PR #{pr_number} to issue #{issue}")]
     #[diagnostic(
-        code(stakk::submit::body_sync_failed),
-        help("the PR exists but its body could not be updated — check your token permissions")
+        code(stakk::submit::issue_link_failed),
+        help("the PRs were submitted; check that #{issue} is an issue of this repository")
     )]
-    BodySyncFailed {
+    IssueLinkFailed {
+        issue: u64,
         pr_number: u64,
+        #[source]
+        source: ForgeError,
+    },
+
+    /// Failed to list the CI check runs of a submitted bookmark.
+    #[error("failed to list CI checks for '{bookmark}'")]
+    #[diagnostic(
+        code(stakk::submit::checks_lookup_failed),
+        help("check your network connection and that your token can read checks")
+    )]
+    ChecksLookupFailed {
         bookmark: String,
         #[source]
         source: ForgeError,
     },
+
+    /// At least one CI check on a submitted bookmark failed.
+    #[error("CI checks failed: {}", .failed.join(", "))]
+    #[diagnostic(
+        code(stakk::submit::checks_failed),
+        help("the PRs were submitted — inspect the failing checks on GitHub")
+    )]
+    ChecksFailed { failed: Vec<String> },
+
+    /// CI checks were still running when `--checks-timeout` ran out.
+    #[error("CI checks still pending after {timeout_secs}s: {}", .pending.join(", "))]
+    #[diagnostic(
+        code(stakk::submit::checks_timed_out),
+        help("the PRs were submitted — raise --checks-timeout to wait longer")
+    )]
+    ChecksTimedOut {
+        timeout_secs: u64,
+        pending: Vec<String>,
+    },
 }
 
 // ---------------------------------------------------------------------------
@@ -207,10 +603,25 @@ pub struct SubmissionAnalysis {
     pub segments: Vec<BookmarkSegment>,
     /// The default branch name (e.g., "main").
     pub default_branch: String,
+    /// For each submitted bookmark, every bookmark of every stack passing
+    /// through it (see [`StackMember`]).
+    pub stack_memberships: HashMap<String, Vec<StackMember>>,
+}
+
+/// A bookmark that shares a stack with a submitted bookmark.
+///
+/// A PR shared by several stacks (e.g. a common root) lists the members of
+/// all of them in its stack comment, in a canonical order, so submitting
+/// either stack renders the same comment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StackMember {
+    pub bookmark_name: String,
+    /// Base branch of the member's PR.
+    pub base: String,
 }
 
 /// One bookmark's planned actions.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[expect(
     clippy::struct_excessive_bools,
     reason = "these are independent action flags, not a state machine"
@@ -228,6 +639,10 @@ pub struct BookmarkPlan {
     pub existing_pr: Option<PullRequest>,
     /// Whether the bookmark needs pushing.
     pub needs_push: bool,
+    /// The commit the bookmark's remote-tracking bookmark pointed to when
+    /// the plan was made; `None` if it had never been pushed. The push is
+    /// aborted if the remote-tracking bookmark has moved since.
+    pub expected_remote_target: Option<String>,
     /// Whether a new PR must be created.
     pub needs_create: bool,
     /// Whether the existing PR's base needs updating.
@@ -236,19 +651,119 @@ pub struct BookmarkPlan {
     pub needs_title_sync: bool,
     /// Whether the existing PR's body should be synced from commits.
     pub needs_body_sync: bool,
+    /// Options from the segment's `Stakk-*` trailers, applied when the PR is
+    /// created.
+    pub pr_options: PrOptions,
+    /// Where the title came from, shown in the plan. `None` when a title
+    /// template produced it.
+    #[serde(default)]
+    pub title_source: Option<TitleSource>,
+    /// Whether the existing PR is a draft that `draft-above-first` wants
+    /// ready for review, now that it is at the bottom of the stack.
+    #[serde(default)]
+    pub needs_mark_ready: bool,
+    /// The change ID of the bookmarked commit, recorded in every PR body
+    /// stakk writes so the PR can be found after the bookmark is renamed.
+    #[serde(default)]
+    pub change_id: Option<String>,
+    /// The head branch of the existing PR, found by change ID, when the
+    /// bookmark has been renamed since. The branch is renamed to the
+    /// bookmark before pushing.
+    #[serde(default)]
+    pub renamed_from: Option<String>,
+    /// Approvals and change requests on the existing PR, shown in the
+    /// plan; `None` without a PR or if the reviews could not be fetched.
+    #[serde(default)]
+    pub reviews: Option<ReviewSummary>,
+    /// Issues the segment's commits reference as `#N`, which
+    /// `--link-issues` links the PR to.
+    #[serde(default)]
+    pub issues: Vec<u64>,
+    /// What the push does to the remote, shown in the plan; `None` if not
+    /// looked up.
+    #[serde(default)]
+    pub push_size: Option<PushSize>,
+    /// CI check runs on the existing PR's head, shown in the plan; `None`
+    /// without a PR or when the PR was found by change ID.
+    #[serde(default)]
+    pub checks: Option<Vec<CheckRun>>,
+}
+
+impl BookmarkPlan {
+    /// The existing PR's CI checks for the plan display, e.g.
+    /// `CI: 2 passed, 1 failed`; `None` when there are none to show.
+    pub fn checks_summary(&self) -> Option<String> {
+        self.checks
+            .as_deref()
+            .filter(|runs| !runs.is_empty())
+            .map(|runs| format!("CI: {}", describe_checks(runs)))
+    }
+    /// Whether the PR is (or will be) created as a draft: its
+    /// `Stakk-Draft` trailer, else `pr_mode`. Under `draft-above-first`
+    /// only PRs not based on `default_branch` are drafts.
+    pub fn is_draft(&self, pr_mode: PrMode, default_branch: &str) -> bool {
+        self.pr_options.draft.unwrap_or(match pr_mode {
+            PrMode::Regular => false,
+            PrMode::Draft => true,
+            PrMode::DraftAboveFirst => self.base != default_branch,
+        })
+    }
+
+    /// `body` with the plan's change ID recorded in it.
+    fn with_change_id(&self, body: Option<String>) -> Option<String> {
+        match &self.change_id {
+            Some(id) => Some(splice_change_id_into_body(
+                body.as_deref().unwrap_or(""),
+                id,
+            )),
+            None => body,
+        }
+    }
+}
+
+/// A base change for the PR of a stack member outside the submitted range,
+/// e.g. a bookmark stacked above the target whose PR still points at its
+/// pre-reorder parent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Retarget {
+    pub bookmark_name: String,
+    pub pr_number: u64,
+    /// The PR's current base.
+    pub from: String,
+    /// The bookmark's parent in the stack.
+    pub to: String,
 }
 
 /// Phase 2 output: the full submission plan.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SubmissionPlan {
     /// Per-bookmark plans, ordered trunk-to-leaf.
     pub bookmark_plans: Vec<BookmarkPlan>,
+    /// PRs outside the submitted range whose base no longer matches the
+    /// stack, applied after all bookmarks are pushed.
+    pub retargets: Vec<Retarget>,
+    /// The repository the PRs are opened in.
+    pub repo: ForgeRepoRef,
+    /// The repository the bookmarks are pushed to: a fork of `repo` in the
+    /// fork workflow, else `repo`.
+    pub head_repo: ForgeRepoRef,
     /// The remote name to push to.
     pub remote: String,
     /// Whether to create PRs as regular or draft.
     pub pr_mode: PrMode,
+    /// How bookmarks are pushed.
+    #[serde(default)]
+    pub push_options: PushOptions,
+    /// Title of the milestone new PRs are put in.
+    #[serde(default)]
+    pub milestone: Option<String>,
+    /// Number of the GitHub Project new PRs are added to.
+    #[serde(default)]
+    pub project: Option<u64>,
     /// The default branch name (e.g., "main").
     pub default_branch: String,
+    /// Carried over from [`SubmissionAnalysis::stack_memberships`].
+    pub stack_memberships: HashMap<String, Vec<StackMember>>,
 }
 
 /// Phase 3 output: what was actually done.
@@ -256,12 +771,187 @@ pub struct SubmissionPlan {
 pub struct SubmissionResult {
     /// Stack entries for all submitted bookmarks.
     pub stack_entries: Vec<StackEntry>,
+    /// Retargets and stack comment writes, with any that failed.
+    pub steps: StepReport,
+}
+
+/// Outcome of one step that runs once every PR exists.
+#[derive(Debug)]
+pub struct StepOutcome {
+    pub description: String,
+    pub error: Option<SubmitError>,
+    /// Forge writes that replay this step with `stakk retry`. Empty for
+    /// steps that cannot be replayed (e.g. stack cleanup).
+    pub retry: Vec<JournalStep>,
+}
+
+/// Steps run after every PR in the stack exists: retargets and stack
+/// comment writes. A failure there leaves the PRs intact, so execution
+/// records it and carries on with the remaining steps instead of aborting.
+#[derive(Debug, Default)]
+pub struct StepReport {
+    pub steps: Vec<StepOutcome>,
+}
+
+impl StepReport {
+    fn record(
+        &mut self,
+        description: impl Into<String>,
+        retry: Vec<JournalStep>,
+        result: Result<(), SubmitError>,
+    ) {
+        self.steps.push(StepOutcome {
+            description: description.into(),
+            error: result.err(),
+            retry,
+        });
+    }
+
+    fn record_step(&mut self, step: JournalStep, result: Result<(), SubmitError>) {
+        self.record(step.describe(), vec![step], result);
+    }
+
+    pub fn failed(&self) -> usize {
+        self.steps.iter().filter(|s| s.error.is_some()).count()
+    }
+
+    /// The replayable steps of every failed outcome, for the journal.
+    pub fn journal_steps(&self) -> Vec<JournalStep> {
+        self.steps
+            .iter()
+            .filter(|s| s.error.is_some())
+            .flat_map(|s| s.retry.iter().cloned())
+            .collect()
+    }
+
+    /// The error to exit with when any step failed.
+    pub fn to_error(&self) -> Option<SubmitError> {
+        let failed = self.failed();
+        (failed > 0).then(|| SubmitError::StepsFailed {
+            failed,
+            total: self.steps.len(),
+        })
+    }
+}
+
+impl fmt::Display for StepReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failed = self.failed();
+        writeln!(
+            f,
+            "Steps: {} succeeded, {failed} failed",
+            self.steps.len() - failed
+        )?;
+        for step in &self.steps {
+            match &step.error {
+                None => writeln!(f, "  ok      {}", step.description)?,
+                Some(error) => {
+                    let hint = if error.is_retriable() && !step.retry.is_empty() {
+                        "retriable"
+                    } else {
+                        "not retriable"
+                    };
+                    writeln!(
+                        f,
+                        "  FAILED  {} ({hint}): {}",
+                        step.description,
+                        error_chain(error)
+                    )?;
+                }
+            }
+        }
+        if failed > 0 && !self.journal_steps().is_empty() {
+            writeln!(f, "Run `stakk retry` to replay the failed steps.")?;
+        }
+        Ok(())
+    }
+}
+
+/// `error` followed by its immediate cause. Deeper sources (HTTP client
+/// errors) tend to repeat the forge error's message.
+fn error_chain(error: &dyn std::error::Error) -> String {
+    match error.source() {
+        Some(cause) => format!("{error}: {cause}"),
+        None => error.to_string(),
+    }
+}
+
+impl SubmitError {
+    /// `self`, wrapped in [`SubmitError::Incomplete`] if `actions` include
+    /// any that `stakk undo` could reverse.
+    fn after(self, actions: Vec<SubmitAction>) -> Self {
+        if actions.iter().any(SubmitAction::is_reversible) {
+            Self::Incomplete {
+                source: Box::new(self),
+                actions,
+            }
+        } else {
+            self
+        }
+    }
+
+    /// Whether re-running the step may succeed: API errors (rate limits,
+    /// timeouts, server errors) are, while authentication failures, malformed
+    /// responses, and template errors need a fix first.
+    pub fn is_retriable(&self) -> bool {
+        match self {
+            Self::BaseUpdateFailed { source, .. }
+            | Self::CommentFailed { source, .. }
+            | Self::CommentBatchFailed { source, .. }
+            | Self::BodyUpdateFailed { source, .. }
+            | Self::ReviewRequestFailed { source, .. }
+            | Self::LabelFailed { source, .. }
+            | Self::MarkReadyFailed { source, .. } => matches!(source, ForgeError::Api { .. }),
+            _ => false,
+        }
+    }
 }
 
 // ---------------------------------------------------------------------------
 // Phase 1: Analysis
 // ---------------------------------------------------------------------------
 
+/// Whether the branches for bookmarks `a` and `b` would clash on the remote:
+/// the names differ only in ASCII case, or one is nested under the other like
+/// a directory (`feat` and `feat/login`), which git refs cannot both hold.
+fn branch_names_collide(a: &str, b: &str) -> bool {
+    let (a, b) = (a.to_ascii_lowercase(), b.to_ascii_lowercase());
+    a == b || a.starts_with(&format!("{b}/")) || b.starts_with(&format!("{a}/"))
+}
+
+/// The first submitted bookmark whose branch would collide with the branch of
+/// a differently named bookmark anywhere in the change graph.
+fn find_branch_collision(
+    segments: &[BookmarkSegment],
+    change_graph: &ChangeGraph,
+) -> Option<SubmitError> {
+    let mut others: Vec<(&str, &str)> = change_graph
+        .stacks
+        .iter()
+        .flat_map(|stack| &stack.segments)
+        .flat_map(|seg| {
+            seg.bookmark_names
+                .iter()
+                .map(|name| (name.as_str(), seg.change_id.as_str()))
+        })
+        .collect();
+    others.sort_unstable();
+    others.dedup();
+
+    segments.iter().find_map(|seg| {
+        let bookmark = seg.bookmark_names.first()?;
+        let (other_bookmark, other_change_id) = others
+            .iter()
+            .find(|(other, _)| *other != bookmark && branch_names_collide(bookmark, other))?;
+        Some(SubmitError::BranchNameCollision {
+            bookmark: bookmark.clone(),
+            change_id: seg.change_id.clone(),
+            other_bookmark: (*other_bookmark).to_string(),
+            other_change_id: (*other_change_id).to_string(),
+        })
+    })
+}
+
 /// Find the segments relevant to submitting the target bookmark.
 ///
 /// Locates the stack containing `target_bookmark` in the change graph and
@@ -272,6 +962,13 @@ pub fn analyze_submission(
     default_branch: &str,
     selected_bookmarks: &HashSet<String>,
 ) -> Result<SubmissionAnalysis, SubmitError> {
+    if let Some(change_id) = change_graph.private_bookmarks.get(target_bookmark) {
+        return Err(SubmitError::PrivateChange {
+            bookmark: target_bookmark.to_string(),
+            change_id: change_id.clone(),
+        });
+    }
+
     let stack = change_graph
         .stacks
         .iter()
@@ -346,16 +1043,207 @@ pub fn analyze_submission(
         return Err(SubmitError::SelectedBookmarksExcluded { missing, immutable });
     }
 
-    Ok(SubmissionAnalysis {
-        segments,
+    if let Some(collision) = find_branch_collision(&segments, change_graph) {
+        return Err(collision);
+    }
+
+    let skipped: HashSet<&str> = stack.segments[..=target_index]
+        .iter()
+        .filter(|seg| {
+            !seg.bookmark_names
+                .iter()
+                .any(|name| selected_bookmarks.contains(name))
+        })
+        .flat_map(|seg| &seg.bookmark_names)
+        .map(String::as_str)
+        .collect();
+    let stack_memberships = segments
+        .iter()
+        .filter_map(|seg| {
+            let name = seg.bookmark_names.first()?;
+            let members = stack_members(change_graph, &seg.change_id, &skipped, default_branch);
+            Some((name.clone(), members))
+        })
+        .collect();
+
+    Ok(SubmissionAnalysis {
+        segments,
         default_branch: default_branch.to_string(),
+        stack_memberships,
+    })
+}
+
+/// The commits of `analysis` without a `Signed-off-by` line for their
+/// author's email, oldest first.
+pub fn unsigned_commits(analysis: &SubmissionAnalysis) -> Vec<&SegmentCommit> {
+    analysis
+        .segments
+        .iter()
+        .flat_map(|segment| segment.commits.iter().rev())
+        .filter(|commit| !has_signoff(&commit.description, &commit.author.email))
+        .collect()
+}
+
+/// Fail with [`SubmitError::MissingSignoff`] if any of `commits` is listed.
+pub fn require_signoffs(commits: &[&SegmentCommit]) -> Result<(), SubmitError> {
+    if commits.is_empty() {
+        return Ok(());
+    }
+    Err(SubmitError::MissingSignoff {
+        count: commits.len(),
+        changes: commits
+            .iter()
+            .map(|commit| commit.short_change_id.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+    })
+}
+
+/// Fail with [`SubmitError::ConflictedBookmarks`] if any of `conflicted`,
+/// the conflicted bookmarks in the submitted segments, is listed.
+pub fn require_resolved_bookmarks(conflicted: &[&str]) -> Result<(), SubmitError> {
+    if conflicted.is_empty() {
+        return Ok(());
+    }
+    Err(SubmitError::ConflictedBookmarks {
+        bookmarks: conflicted.join(", "),
     })
 }
 
+/// `commit`'s description with a sign-off by its author appended.
+pub fn signed_off_description(commit: &SegmentCommit) -> String {
+    trailers::with_signoff(
+        &commit.description,
+        &commit.author.name,
+        &commit.author.email,
+    )
+}
+
+/// The descriptions that link the bookmarked commits of `entries` to their
+/// PRs with a `PR:` trailer, keyed by change ID, bottom of the stack first.
+/// Commits that already link their PR, and immutable ones, are left out.
+pub fn pr_trailer_edits(
+    change_graph: &ChangeGraph,
+    entries: &[StackEntry],
+) -> Vec<(String, String)> {
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let tip = change_graph
+                .stacks
+                .iter()
+                .flat_map(|stack| &stack.segments)
+                .find(|seg| seg.bookmark_names.contains(&entry.bookmark_name))?
+                .commits
+                .first()
+                .filter(|commit| !commit.is_immutable)?;
+            let description = trailers::with_pr_trailer(&tip.description, &entry.pr_url)?;
+            Some((tip.change_id.clone(), description))
+        })
+        .collect()
+}
+
+/// `description` with its subject replaced by `title` and the text below
+/// it by `body`, keeping its trailers, for editing a PR's title and body
+/// into the change it was submitted from.
+pub fn amended_description(description: &str, title: Option<&str>, body: Option<&str>) -> String {
+    trailers::amend_description(description, title, body)
+}
+
+/// Collect the members of every stack that passes through the segment with
+/// `change_id`, skipping bookmarks folded into other segments.
+///
+/// Stacks are visited in order of their bookmark names rather than graph
+/// order (which follows commit recency), so the result does not change when
+/// one of the stacks is amended.
+fn stack_members(
+    change_graph: &ChangeGraph,
+    change_id: &str,
+    skipped: &HashSet<&str>,
+    default_branch: &str,
+) -> Vec<StackMember> {
+    let mut paths: Vec<Vec<(&str, &str)>> = change_graph
+        .stacks
+        .iter()
+        .filter(|s| s.segments.iter().any(|seg| seg.change_id == change_id))
+        .map(|s| {
+            let mut chain = BaseChain::new(default_branch);
+            for name in s
+                .segments
+                .iter()
+                .filter_map(|seg| seg.bookmark_names.first())
+            {
+                let link = if skipped.contains(name.as_str()) {
+                    Link::Skipped
+                } else {
+                    Link::Submitted
+                };
+                chain.push(name, link);
+            }
+            chain.bases()
+        })
+        .collect();
+    paths.sort_unstable();
+
+    let mut seen = HashSet::new();
+    let mut members = Vec::new();
+    for (name, base) in paths.into_iter().flatten() {
+        if seen.insert(name) {
+            members.push(StackMember {
+                bookmark_name: name.to_string(),
+                base: base.to_string(),
+            });
+        }
+    }
+    members
+}
+
 // ---------------------------------------------------------------------------
 // Helpers
 // ---------------------------------------------------------------------------
 
+/// The title of a segment's PR without a title template, and the source it
+/// was actually taken from: a segment without commits falls back to its
+/// bookmark name.
+fn default_title(
+    segment: &BookmarkSegment,
+    bookmark_name: &str,
+    source: TitleSource,
+) -> (String, TitleSource) {
+    let summary = |c: &SegmentCommit| {
+        c.description
+            .lines()
+            .next()
+            .unwrap_or(&c.description)
+            .to_string()
+    };
+    let commit = match source {
+        TitleSource::Tip => segment.commits.first(),
+        TitleSource::Root => segment.commits.last(),
+        TitleSource::Bookmark => None,
+    };
+    match commit {
+        Some(c) => (summary(c), source),
+        None if source == TitleSource::Bookmark => {
+            (prettify_bookmark(bookmark_name), TitleSource::Bookmark)
+        }
+        // Segments always have commits; keep the raw name if one does not.
+        None => (bookmark_name.to_string(), TitleSource::Bookmark),
+    }
+}
+
+/// A bookmark name as a title: the last `/` component, with `-` and `_` as
+/// spaces and the first letter capitalized.
+fn prettify_bookmark(name: &str) -> String {
+    let last = name.rsplit('/').next().unwrap_or(name);
+    let words = last.replace(['-', '_'], " ");
+    let mut chars = words.trim().chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => name.to_string(),
+    }
+}
+
 /// Build a PR body from segment commit descriptions.
 ///
 /// - Single commit: lines after the first (the title line) become the body.
@@ -365,7 +1253,8 @@ pub fn analyze_submission(
 /// Trailer blocks (Signed-off-by, Co-authored-by, Refs, etc.) are
 /// removed from the unwrap pass and either dropped (`Strip`) or
 /// reattached verbatim after unwrapping (`Keep`), so multi-line
-/// trailer blocks survive intact.
+/// trailer blocks survive intact. `Stakk-*` trailers configure the PR
+/// rather than describe it, and are always dropped.
 fn build_pr_body(commits: &[SegmentCommit], trailers: TrailerHandling) -> Option<String> {
     if commits.is_empty() {
         return None;
@@ -384,12 +1273,12 @@ fn build_pr_body(commits: &[SegmentCommit], trailers: TrailerHandling) -> Option
             };
             let unwrapped = unwrap_markdown(body_text.trim());
             let kept_trailers = match trailers {
-                TrailerHandling::Keep => trailer_block,
+                TrailerHandling::Keep => trailer_block.and_then(without_stakk_trailers),
                 TrailerHandling::Strip => None,
             };
             match (unwrapped.is_empty(), kept_trailers) {
                 (true, None) => None,
-                (true, Some(tb)) => Some(tb.to_string()),
+                (true, Some(tb)) => Some(tb),
                 (false, None) => Some(unwrapped),
                 (false, Some(tb)) => Some(format!("{unwrapped}\n\n{tb}")),
             }
@@ -411,7 +1300,14 @@ fn build_pr_body(commits: &[SegmentCommit], trailers: TrailerHandling) -> Option
 /// Query the forge to determine what actions are needed for each bookmark.
 ///
 /// For each segment in the analysis, checks the forge for existing PRs and
-/// determines whether to push, create, or update.
+/// determines whether to push, create, or update. Bookmarks in
+/// `synced_bookmarks` already match the remote and are not pushed. A
+/// bookmark whose PR merged at the segment's tip is left out of the plan,
+/// and the PR above it takes its base.
+#[expect(
+    clippy::too_many_arguments,
+    reason = "plan inputs are independent settings resolved by the caller"
+)]
 pub async fn create_submission_plan<F: Forge>(
     analysis: &SubmissionAnalysis,
     forge: &F,
@@ -419,6 +1315,9 @@ pub async fn create_submission_plan<F: Forge>(
     pr_mode: PrMode,
     sync: SyncPrContent,
     trailers: TrailerHandling,
+    templates: &PrTemplates,
+    synced_bookmarks: &HashSet<String>,
+    remote_targets: &HashMap<String, String>,
 ) -> Result<SubmissionPlan, SubmitError> {
     // Collect bookmark names for concurrent PR lookup.
     let bookmark_names: Vec<String> = analysis
@@ -434,45 +1333,150 @@ pub async fn create_submission_plan<F: Forge>(
         })
         .collect::<Result<_, _>>()?;
 
-    // Concurrently check for existing PRs for all bookmarks.
-    let pr_futures: Vec<_> = bookmark_names
+    // Check for existing PRs for all bookmarks at once, with their reviews.
+    let pr_details = forge
+        .find_prs_for_branches(&bookmark_names)
+        .await
+        .map_err(|source| SubmitError::PrsLookupFailed { source })?;
+
+    // A bookmark without a PR may have been renamed since its PR was opened:
+    // look for an open PR recording its change ID. A PR from a branch that
+    // is still one of the submitted bookmarks belongs to that bookmark.
+    let is_submitted = |head: &str| {
+        analysis
+            .segments
+            .iter()
+            .any(|seg| seg.bookmark_names.iter().any(|b| b == head))
+    };
+    let change_futures: Vec<_> = analysis
+        .segments
         .iter()
-        .map(|name| forge.find_pr_for_branch(name))
+        .zip(&pr_details)
+        .map(|(segment, details)| async move {
+            if details.is_some() {
+                return Ok(None);
+            }
+            let pr = forge.find_pr_for_change(&segment.change_id).await?;
+            Ok(pr.filter(|pr| !is_submitted(&pr.head_ref)))
+        })
         .collect();
-    let pr_results = futures::future::join_all(pr_futures).await;
+    let renamed_prs = futures::future::join_all(change_futures).await;
 
-    let mut bookmark_plans = Vec::new();
+    // A bookmark with no open PR may be one whose PR has merged while the
+    // local stack still holds it: one merged with the segment's tip as its
+    // head is a merged ancestor, not a PR to open again.
+    let merged_futures: Vec<_> = analysis
+        .segments
+        .iter()
+        .zip(&bookmark_names)
+        .zip(pr_details.iter().zip(&renamed_prs))
+        .map(|((segment, name), (details, renamed_pr))| async move {
+            if details.is_some() || !matches!(renamed_pr, Ok(None)) {
+                return Ok(false);
+            }
+            let merged = forge
+                .find_merged_pr_for_branch(name)
+                .await
+                .map_err(|source| SubmitError::PrLookupFailed {
+                    bookmark: name.clone(),
+                    source,
+                })?;
+            Ok(merged.is_some_and(|merged| {
+                segment
+                    .commits
+                    .first()
+                    .is_some_and(|tip| tip.commit_id == merged.head_sha)
+            }))
+        })
+        .collect();
+    let merged: Vec<bool> = futures::future::join_all(merged_futures)
+        .await
+        .into_iter()
+        .collect::<Result<_, SubmitError>>()?;
 
-    for (i, (segment, pr_result)) in analysis.segments.iter().zip(pr_results).enumerate() {
-        let bookmark_name = bookmark_names[i].clone();
+    let pr_options: Vec<PrOptions> = analysis
+        .segments
+        .iter()
+        .zip(&bookmark_names)
+        .map(|(segment, name)| {
+            parse_pr_options(segment.commits.iter().map(|c| c.description.as_str())).map_err(
+                |trailer| SubmitError::InvalidPrTrailer {
+                    bookmark: name.clone(),
+                    trailer,
+                },
+            )
+        })
+        .collect::<Result<_, _>>()?;
+
+    let mut chain = BaseChain::new(&analysis.default_branch);
+    for ((name, &merged), options) in bookmark_names.iter().zip(&merged).zip(&pr_options) {
+        chain.push(
+            name,
+            if merged {
+                Link::Skipped
+            } else {
+                Link::Submitted
+            },
+        );
+        if let Some(base) = &options.base {
+            chain.override_base(name, base);
+        }
+    }
 
-        let base = if i == 0 {
-            analysis.default_branch.clone()
+    let mut bookmark_plans = Vec::new();
+
+    for ((((segment, details), renamed_pr), pr_options), bookmark_name) in analysis
+        .segments
+        .iter()
+        .zip(pr_details)
+        .zip(renamed_prs)
+        .zip(&pr_options)
+        .zip(bookmark_names.iter().cloned())
+    {
+        let Some(base) = chain.base_of(&bookmark_name) else {
+            tracing::debug!(bookmark = %bookmark_name, "skipping bookmark with a merged PR");
+            continue;
+        };
+        let base_reason = if pr_options.base.is_some() {
+            "Stakk-Base trailer"
+        } else if base == analysis.default_branch {
+            "bottom of the stack"
         } else {
-            bookmark_names[i - 1].clone()
+            "bookmark below"
         };
+        let base = base.to_string();
 
-        let title = segment.commits.first().map_or_else(
-            || bookmark_name.clone(),
-            |c| {
-                c.description
-                    .lines()
-                    .next()
-                    .unwrap_or(&c.description)
-                    .to_string()
-            },
-        );
+        let (default_title, title_source) =
+            default_title(segment, &bookmark_name, templates.title_source());
 
-        let existing_pr = pr_result.map_err(|source| SubmitError::PrLookupFailed {
-            bookmark: bookmark_name.clone(),
-            source,
-        })?;
+        let (existing_pr, renamed_from, reviews, checks) = if let Some(details) = details {
+            let reviews = ReviewSummary::from_reviews(&details.reviews);
+            (Some(details.pr), None, Some(reviews), Some(details.checks))
+        } else {
+            let pr = renamed_pr.map_err(|source| SubmitError::PrLookupFailed {
+                bookmark: bookmark_name.clone(),
+                source,
+            })?;
+            let from = pr.as_ref().map(|pr| pr.head_ref.clone());
+            (pr, from, None, None)
+        };
 
         let needs_base_update = existing_pr.as_ref().is_some_and(|pr| pr.base_ref != base);
 
         let needs_create = existing_pr.is_none();
 
-        let body = build_pr_body(&segment.commits, trailers);
+        let default_body = build_pr_body(&segment.commits, trailers);
+        let content_ctx = PrContentContext::new(
+            &bookmark_name,
+            &base,
+            &segment.commits,
+            &default_title,
+            default_body.as_deref(),
+        );
+        let title = templates.render_title(&content_ctx)?;
+        let title_source =
+            (!templates.has_title_template() || title == default_title).then_some(title_source);
+        let body = templates.render_body(&content_ctx, default_body)?;
 
         let wants_title = matches!(sync, SyncPrContent::Title | SyncPrContent::All);
         let wants_body = matches!(sync, SyncPrContent::Body | SyncPrContent::All);
@@ -486,7 +1490,7 @@ pub async fn create_submission_plan<F: Forge>(
                 let existing_user_body = pr
                     .body
                     .as_deref()
-                    .map(strip_stack_from_body)
+                    .map(strip_managed_regions)
                     .unwrap_or_default();
                 let normalized_existing = unwrap_markdown(existing_user_body.trim());
                 let normalized_new = body
@@ -496,217 +1500,926 @@ pub async fn create_submission_plan<F: Forge>(
                 normalized_new != normalized_existing
             });
 
-        bookmark_plans.push(BookmarkPlan {
+        let needs_push = !synced_bookmarks.contains(&bookmark_name);
+        // A renamed branch keeps its commit: the push moves it on from there.
+        let expected_remote_target = remote_targets
+            .get(renamed_from.as_ref().unwrap_or(&bookmark_name))
+            .cloned();
+
+        let mut plan = BookmarkPlan {
             bookmark_name,
             base,
             title,
             body,
             existing_pr,
-            needs_push: true,
+            needs_push,
+            expected_remote_target,
             needs_create,
             needs_base_update,
             needs_title_sync,
             needs_body_sync,
-        });
+            pr_options: pr_options.clone(),
+            title_source,
+            needs_mark_ready: false,
+            change_id: Some(segment.change_id.clone()),
+            renamed_from,
+            reviews,
+            issues: trailers::issue_references(
+                segment.commits.iter().map(|c| c.description.as_str()),
+            ),
+            push_size: None,
+            checks,
+        };
+        plan.needs_mark_ready = pr_mode == PrMode::DraftAboveFirst
+            && plan.existing_pr.as_ref().is_some_and(|pr| pr.draft)
+            && !plan.is_draft(pr_mode, &analysis.default_branch);
+        tracing::debug!(
+            bookmark = %plan.bookmark_name,
+            base = %plan.base,
+            base_reason,
+            pr = ?plan.existing_pr.as_ref().map(|pr| pr.number),
+            pr_base = ?plan.existing_pr.as_ref().map(|pr| &pr.base_ref),
+            renamed_from = ?plan.renamed_from,
+            plan.needs_push,
+            plan.needs_create,
+            plan.needs_base_update,
+            plan.needs_title_sync,
+            plan.needs_body_sync,
+            plan.needs_mark_ready,
+            "planned bookmark"
+        );
+        bookmark_plans.push(plan);
+    }
+
+    // The PRs of renamed bookmarks were found by change ID, without their
+    // reviews. Reviews only inform the plan display, so a failed lookup is
+    // left out rather than failing the submission.
+    let review_futures = bookmark_plans.iter().map(|bp| async move {
+        match &bp.existing_pr {
+            Some(pr) if bp.reviews.is_none() => forge.get_reviews(pr.number).await.ok(),
+            _ => bp.reviews,
+        }
+    });
+    let reviews = futures::future::join_all(review_futures).await;
+    for (bp, reviews) in bookmark_plans.iter_mut().zip(reviews) {
+        bp.reviews = reviews;
     }
 
+    let retargets = plan_member_retargets(forge, analysis, &bookmark_names).await?;
+
     Ok(SubmissionPlan {
         bookmark_plans,
+        retargets,
+        repo: forge.repo_ref(),
+        head_repo: forge.head_repo_ref(),
         remote: remote.to_string(),
         pr_mode,
+        push_options: PushOptions::default(),
+        milestone: None,
+        project: None,
         default_branch: analysis.default_branch.clone(),
+        stack_memberships: analysis.stack_memberships.clone(),
     })
 }
 
+/// Compare the PR bases of every stack member outside the submitted range
+/// against the full expected chain.
+///
+/// After `jj rebase` reorders a stack, bookmarks above the submitted one can
+/// keep PRs based on their old parents. Such a PR is retargeted when its new
+/// base exists on the remote after this submission: the default branch, a
+/// submitted bookmark, or a member that has a PR of its own.
+async fn plan_member_retargets<F: Forge>(
+    forge: &F,
+    analysis: &SubmissionAnalysis,
+    submitted: &[String],
+) -> Result<Vec<Retarget>, SubmitError> {
+    let mut seen: HashSet<&str> = submitted.iter().map(String::as_str).collect();
+    let mut outside: Vec<&StackMember> = Vec::new();
+    // Iterate in submission order so the result is deterministic.
+    for name in submitted {
+        for member in analysis.stack_memberships.get(name).into_iter().flatten() {
+            if seen.insert(&member.bookmark_name) {
+                outside.push(member);
+            }
+        }
+    }
+    if outside.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let lookups = futures::future::join_all(
+        outside
+            .iter()
+            .map(|m| forge.find_pr_for_branch(&m.bookmark_name)),
+    )
+    .await;
+    let mut prs = Vec::new();
+    for (member, lookup) in outside.into_iter().zip(lookups) {
+        let pr = lookup.map_err(|source| SubmitError::PrLookupFailed {
+            bookmark: member.bookmark_name.clone(),
+            source,
+        })?;
+        if let Some(pr) = pr {
+            prs.push((member, pr));
+        }
+    }
+
+    let on_remote = |branch: &str| {
+        branch == analysis.default_branch
+            || submitted.iter().any(|name| name == branch)
+            || prs.iter().any(|(m, _)| m.bookmark_name == branch)
+    };
+    for (member, pr) in &prs {
+        if pr.base_ref != member.base {
+            tracing::debug!(
+                bookmark = %member.bookmark_name,
+                pr = pr.number,
+                pr_base = %pr.base_ref,
+                stack_base = %member.base,
+                retarget = on_remote(&member.base),
+                "stack member's PR base differs from the stack"
+            );
+        }
+    }
+    Ok(prs
+        .iter()
+        .filter(|(member, pr)| pr.base_ref != member.base && on_remote(&member.base))
+        .map(|(member, pr)| Retarget {
+            bookmark_name: member.bookmark_name.clone(),
+            pr_number: pr.number,
+            from: pr.base_ref.clone(),
+            to: member.base.clone(),
+        })
+        .collect())
+}
+
 // ---------------------------------------------------------------------------
 // Phase 2: Display (for --dry-run)
 // ---------------------------------------------------------------------------
 
-impl fmt::Display for SubmissionPlan {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let draft_label = if self.pr_mode == PrMode::Draft {
-            ", draft"
-        } else {
-            ""
-        };
-        writeln!(
-            f,
-            "Submission plan ({} bookmark(s), remote: {}{draft_label}):",
-            self.bookmark_plans.len(),
-            self.remote,
-        )?;
-
-        for bp in &self.bookmark_plans {
-            writeln!(f, "  {} (base: {})", bp.bookmark_name, bp.base)?;
-            if bp.needs_push {
-                writeln!(f, "    - push bookmark to {}", self.remote)?;
-            }
-            if bp.needs_create {
-                writeln!(f, "    - create PR: \"{}\"", bp.title)?;
-            }
-            if bp.needs_base_update
-                && let Some(pr) = &bp.existing_pr
-            {
-                writeln!(
-                    f,
-                    "    - update PR #{} base: {} -> {}",
-                    pr.number, pr.base_ref, bp.base,
-                )?;
-            }
-            if bp.needs_title_sync
-                && let Some(pr) = &bp.existing_pr
-            {
-                writeln!(f, "    - sync PR #{} title from commits", pr.number)?;
-            }
-            if bp.needs_body_sync
-                && let Some(pr) = &bp.existing_pr
-            {
-                writeln!(f, "    - sync PR #{} body from commits", pr.number)?;
-            }
-            if !bp.needs_create
-                && !bp.needs_base_update
-                && !bp.needs_title_sync
-                && !bp.needs_body_sync
-                && let Some(pr) = &bp.existing_pr
-            {
-                writeln!(f, "    - PR #{} up to date", pr.number)?;
+impl SubmissionPlan {
+    /// Mark the steps an earlier run of this plan completed before it
+    /// stopped (`done`) as done, so executing the plan again performs only
+    /// the rest.
+    ///
+    /// PRs that run created become existing PRs but keep `needs_create`, so
+    /// the reviewers and labels from their trailers are still applied.
+    pub fn skip_done(&mut self, done: &[SubmitAction]) {
+        for action in done {
+            let bookmark = match action {
+                SubmitAction::Pushed { bookmark, .. }
+                | SubmitAction::UpdatedBase { bookmark, .. }
+                | SubmitAction::CreatedPr { bookmark, .. } => bookmark,
+            };
+            let Some(bp) = self
+                .bookmark_plans
+                .iter_mut()
+                .find(|bp| bp.bookmark_name == *bookmark)
+            else {
+                continue;
+            };
+            match action {
+                SubmitAction::Pushed { .. } => bp.needs_push = false,
+                SubmitAction::UpdatedBase { to, .. } => {
+                    bp.needs_base_update = false;
+                    if let Some(pr) = &mut bp.existing_pr {
+                        pr.base_ref.clone_from(to);
+                    }
+                }
+                SubmitAction::CreatedPr { pr_number, url, .. } => {
+                    bp.existing_pr = Some(PullRequest {
+                        number: *pr_number,
+                        html_url: url.clone(),
+                        title: bp.title.clone(),
+                        head_ref: bp.bookmark_name.clone(),
+                        base_ref: bp.base.clone(),
+                        state: PrState::Open,
+                        body: bp.with_change_id(bp.body.clone()),
+                        draft: bp.is_draft(self.pr_mode, &self.default_branch),
+                    });
+                }
             }
         }
+    }
 
-        Ok(())
+    /// Reduce the plan to rewriting stack comments, for `stakk comment
+    /// refresh`: bookmarks without an open PR are dropped, and nothing is
+    /// pushed, renamed, created, retargeted, or synced. Each PR is listed
+    /// with the base and title it has on the forge.
+    ///
+    /// Returns the dropped bookmarks.
+    pub fn comments_only(&mut self) -> Vec<String> {
+        let (kept, dropped): (Vec<_>, Vec<_>) = std::mem::take(&mut self.bookmark_plans)
+            .into_iter()
+            .partition(|bp| bp.existing_pr.is_some());
+        self.bookmark_plans = kept;
+        self.retargets.clear();
+        for bp in &mut self.bookmark_plans {
+            if let Some(pr) = &bp.existing_pr {
+                bp.base.clone_from(&pr.base_ref);
+                bp.title.clone_from(&pr.title);
+            }
+            bp.needs_push = false;
+            bp.needs_create = false;
+            bp.needs_base_update = false;
+            bp.needs_title_sync = false;
+            bp.needs_body_sync = false;
+            bp.needs_mark_ready = false;
+            bp.renamed_from = None;
+            bp.issues.clear();
+        }
+        dropped.into_iter().map(|bp| bp.bookmark_name).collect()
     }
 }
 
-// ---------------------------------------------------------------------------
-// Phase 3: Execution
-// ---------------------------------------------------------------------------
+/// How a step of the plan changes the forge, for renderers that tell the
+/// kinds apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PlanStepKind {
+    /// Nothing changes.
+    UpToDate,
+    /// A branch is pushed or renamed, or an existing PR is edited.
+    Update,
+    /// A PR is moved onto another base.
+    Retarget,
+    /// A PR is created, or set up after its creation.
+    Create,
+}
 
-/// Execute the submission plan: push, create PRs, update bases, manage
-/// comments.
-pub async fn execute_submission_plan<R: JjRunner, F: Forge>(
-    plan: &SubmissionPlan,
-    jj: &Jj<R>,
-    forge: &F,
-    comment_env: &minijinja::Environment<'_>,
-    placement: StackPlacement,
-) -> Result<SubmissionResult, SubmitError> {
-    let pb = indicatif::ProgressBar::new_spinner();
-    pb.enable_steady_tick(std::time::Duration::from_millis(120));
+/// One line of the plan display under a bookmark.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanStep {
+    pub kind: PlanStepKind,
+    pub text: String,
+}
 
-    let mut stack_entries = Vec::new();
+impl PlanStep {
+    fn new(kind: PlanStepKind, text: String) -> Self {
+        Self { kind, text }
+    }
+}
 
-    // Returns the body that is currently live on GitHub for this bookmark:
-    // the commit-derived body if we just synced or created it, otherwise the
-    // body fetched during planning.
-    let effective_body = |bp: &BookmarkPlan| -> Option<String> {
-        if bp.needs_create || bp.needs_body_sync {
-            bp.body.clone()
+impl SubmissionPlan {
+    /// The plan's settings as shown in its headline: the bookmark count,
+    /// the remote, and the fork and draft modes.
+    pub fn description(&self) -> String {
+        let draft_label = match self.pr_mode {
+            PrMode::Regular => "",
+            PrMode::Draft => ", draft",
+            PrMode::DraftAboveFirst => ", draft above the first PR",
+        };
+        let fork_label = if self.head_repo == self.repo {
+            String::new()
         } else {
-            bp.existing_pr.as_ref().and_then(|pr| pr.body.clone())
-        }
-    };
+            format!(", PRs on {} from {}", self.repo, self.head_repo)
+        };
+        format!(
+            "{} bookmark(s), remote: {}{fork_label}{draft_label}",
+            self.bookmark_plans.len(),
+            self.remote,
+        )
+    }
 
-    // Process each bookmark trunk-to-leaf: push, update base, create PR.
-    // Each bookmark must be fully processed before the next is pushed to
-    // prevent transient empty diffs that trigger GitHub auto-close (#35).
-    for bp in &plan.bookmark_plans {
+    /// What the plan does for `bp`, one step per line of the display.
+    pub fn steps(&self, bp: &BookmarkPlan) -> Vec<PlanStep> {
+        use PlanStepKind::Create;
+        use PlanStepKind::Retarget;
+        use PlanStepKind::UpToDate;
+        use PlanStepKind::Update;
+
+        let mut steps = Vec::new();
+        if let Some(from) = &bp.renamed_from
+            && let Some(pr) = &bp.existing_pr
+        {
+            steps.push(PlanStep::new(
+                Update,
+                format!(
+                    "rename branch {from} to {} (PR #{} is for the same change)",
+                    bp.bookmark_name, pr.number,
+                ),
+            ));
+        }
         if bp.needs_push {
-            pb.set_message(format!("Pushing bookmark: {}", bp.bookmark_name));
-            jj.push_bookmark(&bp.bookmark_name, &plan.remote)
-                .await
-                .map_err(|source| SubmitError::PushFailed {
-                    bookmark: bp.bookmark_name.clone(),
-                    source,
-                })?;
+            let size = match bp.push_size {
+                Some(PushSize {
+                    new_commits,
+                    replaced_commits: 0,
+                }) => format!(" ({new_commits} new commit(s))"),
+                Some(PushSize {
+                    new_commits,
+                    replaced_commits,
+                }) => format!(
+                    " ({new_commits} new commit(s), replacing {replaced_commits} on the remote)"
+                ),
+                None => String::new(),
+            };
+            steps.push(PlanStep::new(
+                Update,
+                format!("push bookmark to {}{size}", self.remote),
+            ));
+        }
+        if bp.needs_create {
+            let draft = if bp.is_draft(self.pr_mode, &self.default_branch) {
+                " (draft)"
+            } else {
+                ""
+            };
+            let source = bp
+                .title_source
+                .map(|s| format!(" (title from {})", s.describe()))
+                .unwrap_or_default();
+            if let Some(pr) = &bp.existing_pr {
+                // Created by an earlier run (see `skip_done`).
+                steps.push(PlanStep::new(
+                    UpToDate,
+                    format!("PR #{} already created", pr.number),
+                ));
+            } else {
+                steps.push(PlanStep::new(
+                    Create,
+                    format!("create PR{draft}: \"{}\"{source}", bp.title),
+                ));
+                if let Some(milestone) = &self.milestone {
+                    steps.push(PlanStep::new(Create, format!("set milestone {milestone}")));
+                }
+                if let Some(project) = self.project {
+                    steps.push(PlanStep::new(Create, format!("add to project #{project}")));
+                }
+            }
+            if !bp.pr_options.reviewers.is_empty() {
+                steps.push(PlanStep::new(
+                    Create,
+                    format!(
+                        "request reviews from {}",
+                        bp.pr_options.reviewers.join(", ")
+                    ),
+                ));
+            }
+            if !bp.pr_options.labels.is_empty() {
+                steps.push(PlanStep::new(
+                    Create,
+                    format!("add labels {}", bp.pr_options.labels.join(", ")),
+                ));
+            }
         }
-
         if bp.needs_base_update
             && let Some(pr) = &bp.existing_pr
         {
-            pb.set_message(format!("Updating PR #{} base...", pr.number));
-            forge
-                .update_pr_base(pr.number, &bp.base)
-                .await
-                .map_err(|source| SubmitError::BaseUpdateFailed {
-                    bookmark: bp.bookmark_name.clone(),
-                    source,
-                })?;
+            steps.push(PlanStep::new(
+                Retarget,
+                format!(
+                    "retarget PR #{} from {} to {}",
+                    pr.number, pr.base_ref, bp.base,
+                ),
+            ));
         }
-
         if bp.needs_title_sync
             && let Some(pr) = &bp.existing_pr
         {
-            pb.set_message(format!("Syncing PR #{} title...", pr.number));
-            forge
-                .update_pr_title(pr.number, &bp.title)
-                .await
-                .map_err(|source| SubmitError::TitleSyncFailed {
-                    pr_number: pr.number,
-                    bookmark: bp.bookmark_name.clone(),
-                    source,
-                })?;
+            let source = bp.title_source.map_or("commits", TitleSource::describe);
+            steps.push(PlanStep::new(
+                Update,
+                format!("sync PR #{} title from {source}", pr.number),
+            ));
         }
-
-        // Body sync: skip when body-mode stacking is active — the
-        // body-mode stack phase will splice the fence onto bp.body,
-        // combining both updates into a single API call.
         if bp.needs_body_sync
-            && placement != StackPlacement::Body
             && let Some(pr) = &bp.existing_pr
         {
-            let new_body = bp.body.as_deref().unwrap_or("");
-            pb.set_message(format!("Syncing PR #{} body...", pr.number));
-            forge
-                .update_pr_body(pr.number, new_body)
-                .await
-                .map_err(|source| SubmitError::BodySyncFailed {
-                    pr_number: pr.number,
-                    bookmark: bp.bookmark_name.clone(),
-                    source,
-                })?;
+            steps.push(PlanStep::new(
+                Update,
+                format!("sync PR #{} body from commits", pr.number),
+            ));
         }
-
-        let pr = if let Some(existing) = &bp.existing_pr {
-            pb.println(format!(
-                "  Existing PR #{}: {}",
-                existing.number, existing.html_url,
+        if bp.needs_mark_ready
+            && let Some(pr) = &bp.existing_pr
+        {
+            steps.push(PlanStep::new(
+                Update,
+                format!("mark PR #{} ready for review", pr.number),
             ));
-            existing.clone()
-        } else {
-            pb.set_message(format!("Creating PR: {}", bp.title));
-            let pr = forge
-                .create_pr(CreatePrParams {
-                    title: bp.title.clone(),
-                    head: bp.bookmark_name.clone(),
-                    base: bp.base.clone(),
-                    body: bp.body.clone(),
-                    draft: plan.pr_mode == PrMode::Draft,
+        }
+        if !bp.needs_create
+            && !bp.needs_base_update
+            && !bp.needs_title_sync
+            && !bp.needs_body_sync
+            && !bp.needs_mark_ready
+            && let Some(pr) = &bp.existing_pr
+        {
+            steps.push(PlanStep::new(
+                UpToDate,
+                format!("PR #{} up to date", pr.number),
+            ));
+        }
+        steps
+    }
+
+    /// The steps for stacked PRs outside this submission.
+    pub fn retarget_steps(&self) -> Vec<PlanStep> {
+        self.retargets
+            .iter()
+            .map(|rt| {
+                PlanStep::new(
+                    PlanStepKind::Retarget,
+                    format!(
+                        "retarget PR #{} ({}) from {} to {}",
+                        rt.pr_number, rt.bookmark_name, rt.from, rt.to,
+                    ),
+                )
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for SubmissionPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Submission plan ({}):", self.description())?;
+
+        for bp in &self.bookmark_plans {
+            let reviews = bp
+                .reviews
+                .filter(|r| !r.is_empty())
+                .map(|r| format!(" [{r}]"))
+                .unwrap_or_default();
+            let checks = bp
+                .checks_summary()
+                .map(|c| format!(" [{c}]"))
+                .unwrap_or_default();
+            writeln!(
+                f,
+                "  {} (base: {}){reviews}{checks}",
+                bp.bookmark_name, bp.base
+            )?;
+            for step in self.steps(bp) {
+                writeln!(f, "    - {}", step.text)?;
+            }
+        }
+
+        if !self.retargets.is_empty() {
+            writeln!(f, "  Stacked PRs outside this submission:")?;
+            for step in self.retarget_steps() {
+                writeln!(f, "    - {}", step.text)?;
+            }
+        }
+
+        let structural = self
+            .bookmark_plans
+            .iter()
+            .filter(|bp| bp.needs_base_update)
+            .count()
+            + self.retargets.len();
+        if structural > 0 {
+            writeln!(
+                f,
+                "Stack structure changed: {structural} PR(s) will be retargeted."
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Build the stack comment data and rendering context for the PR at `index`.
+///
+/// Entries recorded on the PR's previous stack comment are kept when they are
+/// still members of a stack passing through the PR, so a PR shared by
+/// several stacks keeps listing all of them instead of flipping to whichever
+/// stack was submitted last. Falls back to this run's stack alone when the
+/// memberships do not cover it.
+fn stack_for_pr(
+    plan: &SubmissionPlan,
+    stack_entries: &[StackEntry],
+    entry_contexts: &[StackEntryContext],
+    index: usize,
+    previous: Option<&StackCommentData>,
+) -> (StackCommentData, StackCommentContext) {
+    let current = &stack_entries[index];
+    let this_run = || {
+        stack_entries
+            .iter()
+            .cloned()
+            .zip(entry_contexts.iter().cloned())
+            .collect::<Vec<_>>()
+    };
+
+    let merged = plan
+        .stack_memberships
+        .get(&current.bookmark_name)
+        .map(|members| {
+            members
+                .iter()
+                .filter_map(|m| {
+                    if let Some(j) = stack_entries
+                        .iter()
+                        .position(|e| e.bookmark_name == m.bookmark_name)
+                    {
+                        return Some((stack_entries[j].clone(), entry_contexts[j].clone()));
+                    }
+                    let entry = previous?
+                        .stack
+                        .iter()
+                        .find(|e| e.bookmark_name == m.bookmark_name)?;
+                    let ctx = StackEntryContext {
+                        bookmark_name: entry.bookmark_name.clone(),
+                        pr_url: entry.pr_url.clone(),
+                        pr_number: entry.pr_number,
+                        pr_ref: entry.pr_ref(&plan.repo),
+                        // Titles are not recorded in the comment metadata.
+                        title: entry.bookmark_name.clone(),
+                        base: m.base.clone(),
+                        is_draft: false,
+                        position: 0,
+                        is_current: false,
+                    };
+                    Some((entry.clone(), ctx))
                 })
-                .await
-                .map_err(|source| SubmitError::PrCreateFailed {
-                    bookmark: bp.bookmark_name.clone(),
-                    source,
-                })?;
-            pb.println(format!("  Created PR #{}: {}", pr.number, pr.html_url));
-            pr
+                .collect::<Vec<_>>()
+        });
+    let pairs = match merged {
+        Some(pairs)
+            if stack_entries.iter().all(|e| {
+                pairs
+                    .iter()
+                    .any(|(m, _)| m.bookmark_name == e.bookmark_name)
+            }) =>
+        {
+            pairs
+        }
+        _ => this_run(),
+    };
+
+    let (stack, mut contexts): (Vec<_>, Vec<_>) = pairs.into_iter().unzip();
+    for (i, ctx) in contexts.iter_mut().enumerate() {
+        ctx.position = i + 1;
+        ctx.is_current = ctx.bookmark_name == current.bookmark_name;
+    }
+    (
+        StackCommentData { version: 0, stack },
+        StackCommentContext {
+            stack_size: contexts.len(),
+            current_bookmark: current.bookmark_name.clone(),
+            default_branch: plan.default_branch.clone(),
+            stakk_url: STAKK_REPO_URL.to_string(),
+            stack: contexts,
+        },
+    )
+}
+
+// ---------------------------------------------------------------------------
+// Phase 3: Execution
+// ---------------------------------------------------------------------------
+
+/// The dependency links for the PR at `index`: the PR it is stacked on and
+/// the PR stacked on it. A PR whose child was not submitted this time keeps
+/// the "Followed by" link already in `existing_body`.
+fn pr_links(
+    plan: &SubmissionPlan,
+    stack_entries: &[StackEntry],
+    index: usize,
+    existing_body: &str,
+) -> String {
+    let parent = index
+        .checked_sub(1)
+        .map(|j| stack_entries[j].pr_ref(&plan.repo));
+    let child = stack_entries
+        .get(index + 1)
+        .map(|e| e.pr_ref(&plan.repo))
+        .or_else(|| parse_followed_by(existing_body).map(ToString::to_string));
+    format_pr_links(parent.as_deref(), child.as_deref())
+}
+
+/// Maximum number of chains of new PRs created at once.
+const MAX_CONCURRENT_CREATES: usize = 4;
+
+/// The PRs to create, each with its index in the plan, grouped into chains
+/// that are created one PR after another. A PR whose base is also created in
+/// this run joins its base's chain, so GitHub numbers a stack's new PRs
+/// bottom-up; a PR based on trunk or on an existing PR starts a chain of its
+/// own, which is created alongside the others.
+fn creation_chains(plan: &SubmissionPlan) -> Vec<Vec<(usize, &BookmarkPlan)>> {
+    let mut chains: Vec<Vec<(usize, &BookmarkPlan)>> = Vec::new();
+    let mut chain_of: HashMap<&str, usize> = HashMap::new();
+    for (index, bp) in plan.bookmark_plans.iter().enumerate() {
+        if bp.existing_pr.is_some() {
+            continue;
+        }
+        let chain = if let Some(&chain) = chain_of.get(bp.base.as_str()) {
+            chain
+        } else {
+            chains.push(Vec::new());
+            chains.len() - 1
         };
+        chains[chain].push((index, bp));
+        chain_of.insert(&bp.bookmark_name, chain);
+    }
+    chains
+}
 
-        stack_entries.push(StackEntry {
-            bookmark_name: bp.bookmark_name.clone(),
-            pr_url: pr.html_url.clone(),
-            pr_number: pr.number,
+/// Execute the submission plan: push, create PRs, update bases, manage
+/// comments. With `link_prs`, each PR body also gets a section linking the
+/// PRs it depends on and that follow it. Each step and its outcome are
+/// reported to `observer`.
+pub async fn execute_submission_plan<R: JjRunner, F: Forge, O: SubmitObserver>(
+    plan: &SubmissionPlan,
+    jj: &Jj<R>,
+    forge: &F,
+    comment_env: &minijinja::Environment<'_>,
+    placement: StackPlacement,
+    link_prs: bool,
+    observer: &O,
+) -> Result<SubmissionResult, SubmitError> {
+    let mut stack_entries = Vec::new();
+    // Reviewer requests and labels from trailers, applied to new PRs once
+    // the whole stack exists.
+    let mut option_steps = Vec::new();
+
+    // Returns the body that is currently live on GitHub for this bookmark:
+    // the commit-derived body if we just created it, that body with the
+    // links section (and notes, unless new ones were attached) carried over
+    // if we just synced it, otherwise the body fetched during planning.
+    let effective_body = |bp: &BookmarkPlan| -> Option<String> {
+        let existing = bp.existing_pr.as_ref().and_then(|pr| pr.body.clone());
+        if bp.needs_create {
+            bp.with_change_id(bp.body.clone())
+        } else if bp.needs_body_sync {
+            let existing = existing.as_deref().unwrap_or("");
+            let mut body = bp.with_change_id(bp.body.clone()).unwrap_or_default();
+            if NOTES_REGION.find(&body).is_none() {
+                body = NOTES_REGION.reinsert(existing, &body);
+            }
+            Some(LINKS_REGION.reinsert(existing, &body))
+        } else {
+            existing
+        }
+    };
+
+    // Process each bookmark trunk-to-leaf: push, update base, sync content.
+    // Each bookmark must be fully processed before the next is pushed to
+    // prevent transient empty diffs that trigger GitHub auto-close (#35).
+    // New PRs are created afterwards, when every head and base branch
+    // exists on the remote (see `creation_chains`).
+    // Everything done so far, so that a failure can report it for
+    // `stakk undo`.
+    let mut actions = Vec::new();
+    let processed = async {
+        for bp in &plan.bookmark_plans {
+            if let Some(from) = &bp.renamed_from {
+                observer.on_event(SubmitEvent::RenamingBranch {
+                    from: from.clone(),
+                    to: bp.bookmark_name.clone(),
+                });
+                rename_branch(jj, forge, bp, from, plan).await?;
+            }
+
+            if bp.needs_push {
+                observer.on_event(SubmitEvent::Pushing {
+                    bookmark: bp.bookmark_name.clone(),
+                });
+                if !plan.push_options.force {
+                    check_push_lease(jj, bp, &plan.remote).await?;
+                }
+                jj.push_bookmark(&bp.bookmark_name, &plan.remote, plan.push_options)
+                    .await
+                    .map_err(|source| SubmitError::PushFailed {
+                        bookmark: bp.bookmark_name.clone(),
+                        source,
+                    })?;
+                // A first push leaves the branch untracked under some jj
+                // configurations, and an untracked branch never reads as
+                // synced. A dry run pushed nothing to track.
+                if plan.push_options.track
+                    && !plan.push_options.dry_run
+                    && bp.expected_remote_target.is_none()
+                {
+                    jj.track_bookmark(&bp.bookmark_name, &plan.remote)
+                        .await
+                        .map_err(|source| SubmitError::TrackFailed {
+                            bookmark: bp.bookmark_name.clone(),
+                            remote: plan.remote.clone(),
+                            source,
+                        })?;
+                }
+                actions.push(SubmitAction::Pushed {
+                    bookmark: bp.bookmark_name.clone(),
+                    remote: plan.remote.clone(),
+                });
+            }
+
+            // Base updates are deliberately not batched: each must land before
+            // the next bookmark is pushed (see above).
+            if bp.needs_base_update
+                && let Some(pr) = &bp.existing_pr
+            {
+                observer.on_event(SubmitEvent::UpdatingBase {
+                    pr_number: pr.number,
+                });
+                forge
+                    .update_pr_base(pr.number, &bp.base)
+                    .await
+                    .map_err(|source| SubmitError::BaseUpdateFailed {
+                        bookmark: bp.bookmark_name.clone(),
+                        source,
+                    })?;
+                actions.push(SubmitAction::UpdatedBase {
+                    bookmark: bp.bookmark_name.clone(),
+                    pr_number: pr.number,
+                    from: pr.base_ref.clone(),
+                    to: bp.base.clone(),
+                });
+            }
+
+            // Title and body sync share one API call. Body sync is left out
+            // when body-mode stacking is active — the body-mode stack phase will
+            // splice the fence onto bp.body, combining both updates into a
+            // single API call.
+            let sync_title = bp.needs_title_sync.then_some(bp.title.as_str());
+            let sync_body = (bp.needs_body_sync && placement != StackPlacement::Body)
+                .then(|| effective_body(bp).unwrap_or_default());
+            if (sync_title.is_some() || sync_body.is_some())
+                && let Some(pr) = &bp.existing_pr
+            {
+                observer.on_event(SubmitEvent::SyncingContent {
+                    pr_number: pr.number,
+                });
+                forge
+                    .update_pr(pr.number, sync_title, sync_body.as_deref())
+                    .await
+                    .map_err(|source| SubmitError::ContentSyncFailed {
+                        pr_number: pr.number,
+                        bookmark: bp.bookmark_name.clone(),
+                        source,
+                    })?;
+            }
+
+            if let Some(existing) = &bp.existing_pr {
+                observer.on_event(SubmitEvent::ExistingPr {
+                    bookmark: bp.bookmark_name.clone(),
+                    pr_number: existing.number,
+                    url: existing.html_url.clone(),
+                });
+            }
+        }
+
+        let mut results: Vec<_> = futures::stream::iter(creation_chains(plan))
+            .map(|chain| async move {
+                let mut results = Vec::with_capacity(chain.len());
+                for (index, bp) in chain {
+                    results.push((index, bp, create_pr(forge, plan, bp, observer).await));
+                }
+                results
+            })
+            .buffer_unordered(MAX_CONCURRENT_CREATES)
+            .concat()
+            .await;
+        results.sort_by_key(|(index, _, _)| *index);
+        // Every PR that was created is recorded before the first failure is
+        // returned, so that `stakk undo` can close it.
+        let mut created = HashMap::new();
+        let mut first_error = None;
+        for (_, bp, result) in results {
+            match result {
+                Ok(pr) => {
+                    actions.push(SubmitAction::CreatedPr {
+                        bookmark: bp.bookmark_name.clone(),
+                        pr_number: pr.number,
+                        url: pr.html_url.clone(),
+                    });
+                    created.insert(bp.bookmark_name.as_str(), pr);
+                }
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        if let Some(e) = first_error {
+            return Err(e);
+        }
+
+        for bp in &plan.bookmark_plans {
+            let pr = match &bp.existing_pr {
+                Some(existing) => existing,
+                None => &created[bp.bookmark_name.as_str()],
+            };
+
+            if bp.needs_mark_ready {
+                option_steps.push(JournalStep::MarkReady {
+                    pr_number: pr.number,
+                });
+            }
+
+            // A PR created by an earlier, stopped run of this plan still
+            // counts as new here (see `SubmissionPlan::skip_done`).
+            if bp.needs_create {
+                if !bp.pr_options.reviewers.is_empty() {
+                    option_steps.push(JournalStep::Reviewers {
+                        pr_number: pr.number,
+                        reviewers: bp.pr_options.reviewers.clone(),
+                    });
+                }
+                if !bp.pr_options.labels.is_empty() {
+                    option_steps.push(JournalStep::Labels {
+                        pr_number: pr.number,
+                        labels: bp.pr_options.labels.clone(),
+                    });
+                }
+            }
+
+            stack_entries.push(StackEntry {
+                bookmark_name: bp.bookmark_name.clone(),
+                pr_url: pr.html_url.clone(),
+                pr_number: pr.number,
+                repo: Some(plan.repo.clone()),
+            });
+        }
+        Ok::<(), SubmitError>(())
+    }
+    .await;
+    if let Err(e) = processed {
+        return Err(e.after(actions));
+    }
+
+    // From here on every PR exists, so failures are recorded in `steps` and
+    // the remaining steps still run.
+    let mut steps = StepReport::default();
+
+    // Retarget PRs stacked outside the submitted range, now that every base
+    // they move onto has been pushed.
+    for rt in &plan.retargets {
+        observer.on_event(SubmitEvent::Retargeting {
+            pr_number: rt.pr_number,
+        });
+        let result = forge
+            .update_pr_base(rt.pr_number, &rt.to)
+            .await
+            .map_err(|source| SubmitError::BaseUpdateFailed {
+                bookmark: rt.bookmark_name.clone(),
+                source,
+            });
+        if result.is_ok() {
+            observer.on_event(SubmitEvent::Retargeted {
+                pr_number: rt.pr_number,
+                bookmark: rt.bookmark_name.clone(),
+                from: rt.from.clone(),
+                to: rt.to.clone(),
+            });
+        }
+        steps.record_step(
+            JournalStep::Retarget {
+                bookmark: rt.bookmark_name.clone(),
+                pr_number: rt.pr_number,
+                base: rt.to.clone(),
+            },
+            result,
+        );
+    }
+
+    for step in option_steps {
+        let result = replay_step(forge, &step).await;
+        steps.record_step(step, result);
+    }
+
+    // The body each PR has on the forge, kept current as the steps below
+    // rewrite bodies.
+    let mut live_bodies: Vec<Option<String>> =
+        plan.bookmark_plans.iter().map(effective_body).collect();
+
+    // Dependency links, unless the body-mode stack section below writes
+    // them in the same update.
+    let links_with_stack = placement == StackPlacement::Body && stack_entries.len() > 1;
+    if link_prs && !links_with_stack {
+        for (i, entry) in stack_entries.iter().enumerate() {
+            let pr_number = entry.pr_number;
+            let existing_body = live_bodies[i].clone().unwrap_or_default();
+            let links = pr_links(plan, &stack_entries, i, &existing_body);
+            let body = splice_links_into_body(&existing_body, &links);
+            if body == existing_body {
+                continue;
+            }
+            let result = forge
+                .update_pr_body(pr_number, &body)
+                .await
+                .map_err(|source| SubmitError::BodyUpdateFailed { pr_number, source });
+            if result.is_ok() {
+                live_bodies[i] = Some(body.clone());
+            }
+            steps.record_step(JournalStep::PrBody { pr_number, body }, result);
+        }
+    }
+
+    if placement == StackPlacement::None {
+        return Ok(SubmissionResult {
+            stack_entries,
+            steps,
         });
     }
 
     // Step 3: Concurrently create/update stack comments on all PRs.
+    // Only reached once every PR in the range exists: a creation failure
+    // above returns early, so no PR is left listing a partial stack.
     // For single-bookmark submissions, skip stack info entirely and just
     // clean up any stale stack artifacts from a previously larger stack.
     if stack_entries.len() > 1 {
-        pb.set_message("Updating stack comments...");
-        let comment_data = StackCommentData {
-            version: 0,
-            stack: stack_entries.clone(),
-        };
+        observer.on_event(SubmitEvent::UpdatingStackComments);
 
         let template = comment_env.get_template("stack_comment").map_err(|e| {
             SubmitError::TemplateRenderFailed {
@@ -724,149 +2437,232 @@ pub async fn execute_submission_plan<R: JjRunner, F: Forge>(
                     bookmark_name: entry.bookmark_name.clone(),
                     pr_url: entry.pr_url.clone(),
                     pr_number: entry.pr_number,
+                    pr_ref: entry.pr_ref(&plan.repo),
                     title: bp.title.clone(),
                     base: bp.base.clone(),
-                    is_draft: plan.pr_mode == PrMode::Draft && bp.needs_create,
+                    is_draft: bp.needs_create && bp.is_draft(plan.pr_mode, &plan.default_branch),
                     position: i + 1,
-                    is_current: false, // set per-PR below
+                    is_current: false, // set per-PR in stack_for_pr
                 }
             })
             .collect();
 
         match placement {
             StackPlacement::Comment => {
-                let comment_futures: Vec<_> = stack_entries
+                // Look up each PR's existing stack comment concurrently, then
+                // render against it and split into creates and edits so the
+                // edits can be batched. Comments already up to date are not
+                // written at all.
+                let lookup_futures: Vec<_> = stack_entries
                     .iter()
                     .enumerate()
                     .map(|(i, entry)| {
-                        let mut entries = entry_contexts.clone();
-                        entries[i].is_current = true;
-                        let ctx = StackCommentContext {
-                            stack_size: entries.len(),
-                            current_bookmark: entry.bookmark_name.clone(),
-                            default_branch: plan.default_branch.clone(),
-                            stakk_url: STAKK_REPO_URL.to_string(),
-                            stack: entries,
-                        };
-
-                        let rendered = format_stack_comment(&comment_data, &ctx, &template)
-                            .map(|s| with_comment_preamble(&s));
                         let pr_number = entry.pr_number;
-                        let existing_body = effective_body(&plan.bookmark_plans[i]);
-                        let pb = &pb;
                         async move {
-                            let rendered = rendered?;
-                            let existing_comments =
-                                forge.list_comments(pr_number).await.map_err(|source| {
-                                    SubmitError::CommentFailed { pr_number, source }
-                                })?;
-
-                            if let Some(existing) = find_stack_comment(&existing_comments) {
-                                forge.update_comment(existing.id, &rendered).await.map_err(
-                                    |source| SubmitError::CommentFailed { pr_number, source },
-                                )?;
-                            } else {
-                                forge.create_comment(pr_number, &rendered).await.map_err(
-                                    |source| SubmitError::CommentFailed { pr_number, source },
-                                )?;
+                            let lookup = forge
+                                .list_comments(pr_number)
+                                .await
+                                .map(|comments| find_stack_comment(&comments).cloned())
+                                .map_err(|source| SubmitError::CommentFailed { pr_number, source });
+                            (i, lookup)
+                        }
+                    })
+                    .collect();
+                let lookups = futures::future::join_all(lookup_futures).await;
+
+                let mut edits = Vec::new();
+                let mut creates = Vec::new();
+                let render = |i: usize, previous: Option<&StackCommentData>| {
+                    let (data, ctx) =
+                        stack_for_pr(plan, &stack_entries, &entry_contexts, i, previous);
+                    format_stack_comment(&data, &ctx, &template)
+                        .map(|rendered| with_comment_preamble(&rendered))
+                };
+                for (i, lookup) in lookups {
+                    let pr_number = stack_entries[i].pr_number;
+                    let existing = match lookup {
+                        Ok(existing) => existing,
+                        Err(e) => {
+                            // Replay without the previous comment's metadata,
+                            // which could not be read.
+                            let retry = render(i, None)
+                                .map(|body| JournalStep::StackComment { pr_number, body })
+                                .into_iter()
+                                .collect();
+                            steps.record(
+                                format!("stack comment on PR #{pr_number}"),
+                                retry,
+                                Err(e),
+                            );
+                            continue;
+                        }
+                    };
+                    let previous = existing.as_ref().and_then(|c| parse_stack_comment(&c.body));
+                    let rendered = match render(i, previous.as_ref()) {
+                        Ok(rendered) => rendered,
+                        Err(e) => {
+                            steps.record(
+                                format!("stack comment on PR #{pr_number}"),
+                                Vec::new(),
+                                Err(e),
+                            );
+                            continue;
+                        }
+                    };
+                    match existing {
+                        Some(comment) if comment.body.trim_end() == rendered.trim_end() => {}
+                        Some(comment) => edits.push((
+                            pr_number,
+                            CommentEdit {
+                                comment_id: comment.id,
+                                node_id: comment.node_id,
+                                body: rendered,
+                            },
+                        )),
+                        None => creates.push((i, pr_number, rendered)),
+                    }
+                }
 
-                                // Migration: if switching from body mode, strip
-                                // the fenced section from the PR body.
-                                if let Some(body) = &existing_body
-                                    && find_stack_in_body(body).is_some()
-                                {
-                                    let stripped = strip_stack_from_body(body);
-                                    if let Err(e) = forge.update_pr_body(pr_number, &stripped).await
-                                    {
-                                        pb.println(format!(
-                                            "  Warning: failed to strip stack from PR \
-                                             #{pr_number} body during migration: {e}"
-                                        ));
-                                    }
+                let create_futures: Vec<_> = creates
+                    .into_iter()
+                    .map(|(i, pr_number, rendered)| {
+                        let existing_body = live_bodies[i].clone();
+                        async move {
+                            if let Err(source) = forge.create_comment(pr_number, &rendered).await {
+                                return (
+                                    pr_number,
+                                    rendered,
+                                    Err(SubmitError::CommentFailed { pr_number, source }),
+                                );
+                            }
+
+                            // Migration: if switching from body mode, strip
+                            // the fenced section from the PR body.
+                            if let Some(body) = &existing_body
+                                && find_stack_in_body(body).is_some()
+                            {
+                                let stripped = strip_stack_from_body(body);
+                                if let Err(e) = forge.update_pr_body(pr_number, &stripped).await {
+                                    observer.on_event(SubmitEvent::Warning {
+                                        message: format!(
+                                            "failed to strip stack from PR #{pr_number} body \
+                                             during migration: {e}"
+                                        ),
+                                    });
                                 }
                             }
-                            Ok::<(), SubmitError>(())
+                            (pr_number, rendered, Ok(()))
                         }
                     })
                     .collect();
-                let comment_results = futures::future::join_all(comment_futures).await;
-                for result in comment_results {
-                    result?;
+                for (pr_number, body, result) in futures::future::join_all(create_futures).await {
+                    steps.record_step(JournalStep::StackComment { pr_number, body }, result);
                 }
+
+                apply_comment_edits(forge, &edits, &mut steps).await;
             }
             StackPlacement::Body => {
-                let body_futures: Vec<_> =
-                    stack_entries
-                        .iter()
-                        .enumerate()
-                        .map(|(i, entry)| {
-                            let mut entries = entry_contexts.clone();
-                            entries[i].is_current = true;
-                            let ctx = StackCommentContext {
-                                stack_size: entries.len(),
-                                current_bookmark: entry.bookmark_name.clone(),
-                                default_branch: plan.default_branch.clone(),
-                                stakk_url: STAKK_REPO_URL.to_string(),
-                                stack: entries,
-                            };
-
-                            let rendered = format_stack_comment(&comment_data, &ctx, &template);
-                            let pr_number = entry.pr_number;
-                            let bp = &plan.bookmark_plans[i];
-                            let existing_body = effective_body(bp).unwrap_or_default();
-                            let had_fence = find_stack_in_body(&existing_body).is_some();
-                            let pb = &pb;
-                            async move {
-                                let rendered = rendered?;
-                                let new_body = splice_stack_into_body(&existing_body, &rendered);
+                let body_futures: Vec<_> = stack_entries
+                    .iter()
+                    .enumerate()
+                    .map(|(i, entry)| {
+                        let pr_number = entry.pr_number;
+                        let existing_body = live_bodies[i].clone().unwrap_or_default();
+                        let had_fence = find_stack_in_body(&existing_body).is_some();
+                        let previous = parse_stack_in_body(&existing_body);
+                        let (data, ctx) = stack_for_pr(
+                            plan,
+                            &stack_entries,
+                            &entry_contexts,
+                            i,
+                            previous.as_ref(),
+                        );
+                        let new_body =
+                            format_stack_comment(&data, &ctx, &template).map(|rendered| {
+                                let body = splice_stack_into_body(&existing_body, &rendered);
+                                if link_prs {
+                                    let links = pr_links(plan, &stack_entries, i, &existing_body);
+                                    splice_links_into_body(&body, &links)
+                                } else {
+                                    body
+                                }
+                            });
+                        let retry: Vec<JournalStep> = new_body
+                            .as_ref()
+                            .ok()
+                            .filter(|body| **body != existing_body)
+                            .map(|body| JournalStep::PrBody {
+                                pr_number,
+                                body: body.clone(),
+                            })
+                            .into_iter()
+                            .collect();
+                        let update = async move {
+                            let new_body = new_body?;
+                            if new_body != existing_body {
                                 forge.update_pr_body(pr_number, &new_body).await.map_err(
                                     |source| SubmitError::BodyUpdateFailed { pr_number, source },
                                 )?;
+                            }
 
-                                // Migration: if no existing fenced section was found,
-                                // check for an old stack comment and delete it.
-                                if !had_fence {
-                                    let comments =
-                                        forge.list_comments(pr_number).await.map_err(|source| {
-                                            SubmitError::CommentFailed { pr_number, source }
-                                        })?;
-                                    if let Some(old) = find_stack_comment(&comments)
-                                        && let Err(e) = forge.delete_comment(old.id).await
-                                    {
-                                        pb.println(format!(
-                                            "  Warning: failed to delete old stack comment on PR \
+                            // Migration: if no existing fenced section was found,
+                            // check for an old stack comment and delete it.
+                            if !had_fence {
+                                let comments =
+                                    forge.list_comments(pr_number).await.map_err(|source| {
+                                        SubmitError::CommentFailed { pr_number, source }
+                                    })?;
+                                if let Some(old) = find_stack_comment(&comments)
+                                    && let Err(e) = forge.delete_comment(old.id).await
+                                {
+                                    observer.on_event(SubmitEvent::Warning {
+                                        message: format!(
+                                            "failed to delete old stack comment on PR \
                                              #{pr_number} during migration: {e}"
-                                        ));
-                                    }
+                                        ),
+                                    });
                                 }
-                                Ok::<(), SubmitError>(())
                             }
-                        })
-                        .collect();
-                let body_results = futures::future::join_all(body_futures).await;
-                for result in body_results {
-                    result?;
+                            Ok::<(), SubmitError>(())
+                        };
+                        async move { (pr_number, retry, update.await) }
+                    })
+                    .collect();
+                for (pr_number, retry, result) in futures::future::join_all(body_futures).await {
+                    steps.record(
+                        format!("stack section in body of PR #{pr_number}"),
+                        retry,
+                        result,
+                    );
                 }
             }
+            StackPlacement::None => unreachable!("returned before writing stack info"),
         }
     } else if stack_entries.len() == 1 {
         // Single bookmark — not a stack. Clean up any stale stack artifacts
         // from when this PR was part of a larger stack.
         let entry = &stack_entries[0];
         let pr_number = entry.pr_number;
-        let existing_body = effective_body(&plan.bookmark_plans[0]);
+        let existing_body = live_bodies[0].clone();
 
         // Clean up old stack comment (from either comment mode or pre-migration).
-        let comments = forge
-            .list_comments(pr_number)
-            .await
-            .map_err(|source| SubmitError::CommentFailed { pr_number, source })?;
+        let comments = match forge.list_comments(pr_number).await {
+            Ok(comments) => comments,
+            Err(source) => {
+                steps.record(
+                    format!("stack cleanup on PR #{pr_number}"),
+                    Vec::new(),
+                    Err(SubmitError::CommentFailed { pr_number, source }),
+                );
+                Vec::new()
+            }
+        };
         if let Some(old) = find_stack_comment(&comments)
             && let Err(e) = forge.delete_comment(old.id).await
         {
-            pb.println(format!(
-                "  Warning: failed to clean up old stack comment on PR #{pr_number}: {e}"
-            ));
+            observer.on_event(SubmitEvent::Warning {
+                message: format!("failed to clean up old stack comment on PR #{pr_number}: {e}"),
+            });
         }
 
         // Clean up old body fence (from body mode).
@@ -875,52 +2671,885 @@ pub async fn execute_submission_plan<R: JjRunner, F: Forge>(
         {
             let stripped = strip_stack_from_body(body);
             if let Err(e) = forge.update_pr_body(pr_number, &stripped).await {
-                pb.println(format!(
-                    "  Warning: failed to strip stack from PR #{pr_number} body: {e}"
-                ));
+                observer.on_event(SubmitEvent::Warning {
+                    message: format!("failed to strip stack from PR #{pr_number} body: {e}"),
+                });
             }
         }
     }
 
-    pb.finish_and_clear();
-
-    Ok(SubmissionResult { stack_entries })
+    Ok(SubmissionResult {
+        stack_entries,
+        steps,
+    })
 }
 
-// ---------------------------------------------------------------------------
-// Tests
-// ---------------------------------------------------------------------------
+/// A forge for running `plan` as a dry run: it reads through `forge` and
+/// records every write. The branches the plan pushes count as pushed, new
+/// PRs are numbered after the plan's highest existing one, and body updates
+/// are diffed against the bodies the plan fetched.
+pub fn recording_forge<'a, F: Forge>(forge: &'a F, plan: &SubmissionPlan) -> RecordingForge<'a, F> {
+    let existing = || {
+        plan.bookmark_plans
+            .iter()
+            .filter_map(|bp| bp.existing_pr.as_ref())
+    };
+    let first_new_number = existing().map(|pr| pr.number).max().unwrap_or(0) + 1;
+    RecordingForge::new(forge, first_new_number)
+        .with_pushed_branches(
+            plan.bookmark_plans
+                .iter()
+                .filter(|bp| bp.needs_push)
+                .map(|bp| bp.bookmark_name.clone()),
+        )
+        .with_pr_bodies(existing().filter_map(|pr| Some((pr.number, pr.body.clone()?))))
+}
 
-#[cfg(test)]
-mod tests {
-    use std::collections::HashMap;
-    use std::sync::Arc;
-    use std::sync::Mutex;
+/// Create the PR for `bp` once its branches are confirmed on the remote.
+async fn create_pr<F: Forge, O: SubmitObserver>(
+    forge: &F,
+    plan: &SubmissionPlan,
+    bp: &BookmarkPlan,
+    observer: &O,
+) -> Result<PullRequest, SubmitError> {
+    observer.on_event(SubmitEvent::CheckingBranches {
+        bookmark: bp.bookmark_name.clone(),
+    });
+    verify_pr_branches(forge, plan, bp).await?;
+
+    observer.on_event(SubmitEvent::CreatingPr {
+        bookmark: bp.bookmark_name.clone(),
+        title: bp.title.clone(),
+    });
+    let pr = forge
+        .create_pr(CreatePrParams {
+            title: bp.title.clone(),
+            head: bp.bookmark_name.clone(),
+            base: bp.base.clone(),
+            body: bp.with_change_id(bp.body.clone()),
+            draft: bp.is_draft(plan.pr_mode, &plan.default_branch),
+            milestone: plan.milestone.clone(),
+            project: plan.project,
+        })
+        .await
+        .map_err(|source| SubmitError::PrCreateFailed {
+            bookmark: bp.bookmark_name.clone(),
+            source,
+        })?;
+    observer.on_event(SubmitEvent::CreatedPr {
+        bookmark: bp.bookmark_name.clone(),
+        pr_number: pr.number,
+        url: pr.html_url.clone(),
+    });
+    Ok(pr)
+}
 
-    use super::*;
-    use crate::forge::Comment;
-    use crate::forge::ForgeError;
-    use crate::forge::PrState;
-    use crate::forge::comment::build_comment_env;
-    use crate::graph::types::BranchStack;
-    use crate::graph::types::SegmentCommit;
-    use crate::jj::JjError;
+/// Check that the head and base branches of a PR about to be created exist on
+/// the remote. A push the remote silently rejected would otherwise surface as
+/// an opaque PR creation error; this names the missing branch and the step
+/// that should have created it.
+async fn verify_pr_branches<F: Forge>(
+    forge: &F,
+    plan: &SubmissionPlan,
+    bp: &BookmarkPlan,
+) -> Result<(), SubmitError> {
+    let head_exists = forge
+        .head_branch_exists(&bp.bookmark_name)
+        .await
+        .map_err(|source| SubmitError::BranchLookupFailed {
+            branch: bp.bookmark_name.clone(),
+            source,
+        })?;
+    if !head_exists {
+        let cause = if bp.needs_push {
+            format!(
+                "pushing '{}' to '{}' should have created it — the remote may have rejected the \
+                 push (e.g. a branch protection rule); check `jj git push` output",
+                bp.bookmark_name, plan.remote
+            )
+        } else {
+            format!(
+                "it was not pushed because jj reported it in sync with '{}' — run `jj git fetch` \
+                 and submit again",
+                plan.remote
+            )
+        };
+        return Err(SubmitError::HeadBranchMissing {
+            bookmark: bp.bookmark_name.clone(),
+            cause,
+        });
+    }
 
-    // -- Shared operation log for ordering tests --
+    if !branch_exists(forge, &bp.base).await? {
+        let cause = if bp.base == plan.default_branch {
+            format!(
+                "'{}' is the repository's default branch — check that it exists on GitHub and \
+                 that --remote points at the right repository",
+                bp.base
+            )
+        } else if plan.head_repo != plan.repo {
+            format!(
+                "'{}' was pushed to the fork {}, but PRs on {} can only target branches of {}; \
+                 push the lower bookmarks of the stack to {} as well, or submit them one at a \
+                 time as each lands",
+                bp.base, plan.head_repo, plan.repo, plan.repo, plan.repo
+            )
+        } else {
+            format!(
+                "'{}' is the previous bookmark in this stack; pushing it to '{}' earlier in this \
+                 submission should have created it",
+                bp.base, plan.remote
+            )
+        };
+        return Err(SubmitError::BaseBranchMissing {
+            bookmark: bp.bookmark_name.clone(),
+            base: bp.base.clone(),
+            cause,
+        });
+    }
 
-    type OpLog = Arc<Mutex<Vec<Op>>>;
+    Ok(())
+}
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
-    enum Op {
-        Push(String),
-        BaseUpdate(u64),
-        CreatePr(String),
+/// Compare-and-swap guard for a push, akin to `git push --force-with-lease`:
+/// fail if the remote-tracking bookmark no longer points where it did when
+/// the plan was made.
+///
+/// `jj git push` already refuses to overwrite a remote branch that moved
+/// since the last fetch. This also catches a fetch in between (e.g. from
+/// another terminal while the plan awaited confirmation), after which jj
+/// would push over commits the plan never saw.
+/// Rename the head branch of a renamed bookmark's PR from `from` to the
+/// bookmark, then have jj track the renamed branch with the bookmark still on
+/// its change, so the push that follows updates it. A dry run leaves jj
+/// alone, as no branch was renamed.
+async fn rename_branch<R: JjRunner, F: Forge>(
+    jj: &Jj<R>,
+    forge: &F,
+    bp: &BookmarkPlan,
+    from: &str,
+    plan: &SubmissionPlan,
+) -> Result<(), SubmitError> {
+    let to = bp.bookmark_name.as_str();
+    forge
+        .rename_branch(from, to)
+        .await
+        .map_err(|source| SubmitError::BranchRenameFailed {
+            from: from.to_string(),
+            to: to.to_string(),
+            source,
+        })?;
+    if plan.push_options.dry_run {
+        return Ok(());
     }
 
-    // -- Test helpers --
-
-    fn test_comment_env() -> minijinja::Environment<'static> {
-        build_comment_env(None).unwrap()
+    let remote = plan.remote.as_str();
+    let track_failed = |source| SubmitError::RenameTrackFailed {
+        bookmark: to.to_string(),
+        source,
+    };
+    jj.fetch_branches(remote, &[from, to])
+        .await
+        .map_err(track_failed)?;
+    jj.track_bookmark(to, remote).await.map_err(track_failed)?;
+    if let Some(change_id) = &bp.change_id {
+        jj.set_bookmark(to, change_id).await.map_err(track_failed)?;
+    }
+    Ok(())
+}
+
+/// Fail with [`SubmitError::WouldClobberRemote`] if pushing a bookmark of
+/// `plan` would drop commits from its remote branch that only the remote
+/// has, like `git push --force-with-lease` refusing to lose others' work.
+pub async fn check_remote_clobbers<R: JjRunner>(
+    jj: &Jj<R>,
+    plan: &SubmissionPlan,
+) -> Result<(), SubmitError> {
+    for bp in &plan.bookmark_plans {
+        if !bp.needs_push || bp.expected_remote_target.is_none() {
+            continue;
+        }
+        let clobbered = jj
+            .get_clobbered_commits(&bp.bookmark_name, &plan.remote)
+            .await
+            .map_err(|source| SubmitError::PushFailed {
+                bookmark: bp.bookmark_name.clone(),
+                source,
+            })?;
+        if !clobbered.is_empty() {
+            return Err(SubmitError::WouldClobberRemote {
+                bookmark: bp.bookmark_name.clone(),
+                remote: plan.remote.clone(),
+                count: clobbered.len(),
+                changes: clobbered
+                    .iter()
+                    .map(|entry| entry.short_change_id.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Prefix each PR title with the bookmark's position in its stack, as
+/// `[2/4] Title`. Positions are recounted on every submit, so existing PRs
+/// whose stack grew or shrank are marked for a title sync; the rest of
+/// their title is kept unless it is being synced anyway.
+///
+/// A bookmark's position is its depth above the default branch, and the
+/// stack's size the depth of the deepest bookmark stacked with it.
+pub fn number_titles(plan: &mut SubmissionPlan) {
+    let fallback_size = plan.bookmark_plans.len();
+    for (i, bp) in plan.bookmark_plans.iter_mut().enumerate() {
+        let (position, size) = plan
+            .stack_memberships
+            .get(&bp.bookmark_name)
+            .and_then(|members| stack_position(members, &bp.bookmark_name))
+            .unwrap_or((i + 1, fallback_size));
+        let text = match &bp.existing_pr {
+            Some(pr) if !bp.needs_title_sync => &pr.title,
+            _ => &bp.title,
+        };
+        let title = format!("[{position}/{size}] {}", strip_position(text));
+        if let Some(pr) = &bp.existing_pr {
+            bp.needs_title_sync = pr.title != title;
+        }
+        bp.title = title;
+    }
+}
+
+/// The position of `bookmark` among `members` and the number of PRs in its
+/// stack, both counted from the default branch.
+fn stack_position(members: &[StackMember], bookmark: &str) -> Option<(usize, usize)> {
+    let depth = |name: &str| {
+        let mut depth = 0;
+        let mut current = members.iter().find(|m| m.bookmark_name == name);
+        while let Some(member) = current {
+            depth += 1;
+            // Members form a tree, but a bound keeps a malformed list finite.
+            if depth > members.len() {
+                break;
+            }
+            current = members.iter().find(|m| m.bookmark_name == member.base);
+        }
+        depth
+    };
+    let position = depth(bookmark);
+    let size = members.iter().map(|m| depth(&m.bookmark_name)).max()?;
+    (position > 0).then_some((position, size))
+}
+
+/// `title` without a leading `[i/n] ` stack position.
+fn strip_position(title: &str) -> &str {
+    let Some((position, rest)) = title
+        .strip_prefix('[')
+        .and_then(|title| title.split_once("] "))
+    else {
+        return title;
+    };
+    let is_position = position.split_once('/').is_some_and(|(i, n)| {
+        [i, n]
+            .iter()
+            .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+    });
+    if is_position { rest } else { title }
+}
+
+/// Write each change's note from `notes` (keyed by change ID) into its PR
+/// body. New PRs and bodies being synced anyway get the notes section
+/// added; other existing PRs are marked for a body sync if their notes
+/// section differs, with the rest of their body kept as is.
+pub fn attach_notes(plan: &mut SubmissionPlan, notes: &BTreeMap<String, String>) {
+    for bp in &mut plan.bookmark_plans {
+        let Some(note) = bp.change_id.as_ref().and_then(|id| notes.get(id)) else {
+            continue;
+        };
+        if bp.needs_create || bp.needs_body_sync {
+            bp.body = Some(NOTES_REGION.replace(bp.body.as_deref().unwrap_or(""), note));
+            continue;
+        }
+        let existing = bp
+            .existing_pr
+            .as_ref()
+            .and_then(|pr| pr.body.as_deref())
+            .unwrap_or("");
+        if NOTES_REGION.extract(existing) != Some(note.as_str()) {
+            bp.body = Some(NOTES_REGION.replace(existing, note));
+            bp.needs_body_sync = true;
+        }
+    }
+}
+
+async fn check_push_lease<R: JjRunner>(
+    jj: &Jj<R>,
+    bp: &BookmarkPlan,
+    remote: &str,
+) -> Result<(), SubmitError> {
+    let actual = jj
+        .get_current_remote_target(&bp.bookmark_name, remote)
+        .await
+        .map_err(|source| SubmitError::PushFailed {
+            bookmark: bp.bookmark_name.clone(),
+            source,
+        })?;
+    if actual != bp.expected_remote_target {
+        return Err(SubmitError::RemoteMoved {
+            bookmark: bp.bookmark_name.clone(),
+            remote: remote.to_string(),
+            expected: bp.expected_remote_target.clone(),
+            actual,
+        });
+    }
+    Ok(())
+}
+
+/// A remote target for messages: a short commit ID, or "absent".
+fn short_target(target: Option<&str>) -> &str {
+    target.map_or("absent", |id| &id[..id.len().min(12)])
+}
+
+async fn branch_exists<F: Forge>(forge: &F, branch: &str) -> Result<bool, SubmitError> {
+    forge
+        .branch_exists(branch)
+        .await
+        .map_err(|source| SubmitError::BranchLookupFailed {
+            branch: branch.to_string(),
+            source,
+        })
+}
+
+/// Apply stack comment edits, batched when the forge supports it, recording
+/// the outcome in `steps`.
+///
+/// Falls back to one concurrent `update_comment` call per edit when the forge
+/// has no batch API, when there is only one edit, or when an edit lacks the
+/// node ID the batch API needs.
+async fn apply_comment_edits<F: Forge>(
+    forge: &F,
+    edits: &[(u64, CommentEdit)],
+    steps: &mut StepReport,
+) {
+    let batchable = forge.supports_batch_mutations()
+        && edits.len() > 1
+        && edits.iter().all(|(_, edit)| edit.node_id.is_some());
+    if batchable {
+        let batch: Vec<CommentEdit> = edits.iter().map(|(_, edit)| edit.clone()).collect();
+        let result = forge.update_comments_batch(&batch).await.map_err(|source| {
+            SubmitError::CommentBatchFailed {
+                count: batch.len(),
+                source,
+            }
+        });
+        let retry = edits
+            .iter()
+            .map(|(pr_number, edit)| JournalStep::StackComment {
+                pr_number: *pr_number,
+                body: edit.body.clone(),
+            })
+            .collect();
+        steps.record(
+            format!("stack comments on {} PRs (batched)", batch.len()),
+            retry,
+            result,
+        );
+        return;
+    }
+
+    let edit_futures: Vec<_> = edits
+        .iter()
+        .map(|(pr_number, edit)| async move {
+            let result = forge
+                .update_comment(edit.comment_id, &edit.body)
+                .await
+                .map_err(|source| SubmitError::CommentFailed {
+                    pr_number: *pr_number,
+                    source,
+                });
+            (*pr_number, result)
+        })
+        .collect();
+    for ((pr_number, edit), (_, result)) in edits
+        .iter()
+        .zip(futures::future::join_all(edit_futures).await)
+    {
+        steps.record_step(
+            JournalStep::StackComment {
+                pr_number: *pr_number,
+                body: edit.body.clone(),
+            },
+            result,
+        );
+    }
+}
+
+/// Replay journaled steps one by one, recording each outcome.
+pub async fn retry_journal_steps<F: Forge>(forge: &F, journal: &[JournalStep]) -> StepReport {
+    let mut steps = StepReport::default();
+    for step in journal {
+        let result = replay_step(forge, step).await;
+        steps.record_step(step.clone(), result);
+    }
+    steps
+}
+
+async fn replay_step<F: Forge>(forge: &F, step: &JournalStep) -> Result<(), SubmitError> {
+    match step {
+        JournalStep::Retarget {
+            bookmark,
+            pr_number,
+            base,
+        } => forge
+            .update_pr_base(*pr_number, base)
+            .await
+            .map_err(|source| SubmitError::BaseUpdateFailed {
+                bookmark: bookmark.clone(),
+                source,
+            }),
+        JournalStep::StackComment { pr_number, body } => {
+            let pr_number = *pr_number;
+            let comment_failed = |source| SubmitError::CommentFailed { pr_number, source };
+            // The failed request may have gone through before erroring, or a
+            // later submit may have written the comment: update it if so.
+            let comments = forge
+                .list_comments(pr_number)
+                .await
+                .map_err(comment_failed)?;
+            match find_stack_comment(&comments) {
+                Some(existing) => forge.update_comment(existing.id, body).await,
+                None => forge.create_comment(pr_number, body).await.map(|_| ()),
+            }
+            .map_err(comment_failed)
+        }
+        JournalStep::PrBody { pr_number, body } => forge
+            .update_pr_body(*pr_number, body)
+            .await
+            .map_err(|source| SubmitError::BodyUpdateFailed {
+                pr_number: *pr_number,
+                source,
+            }),
+        JournalStep::Reviewers {
+            pr_number,
+            reviewers,
+        } => forge
+            .request_reviewers(*pr_number, reviewers)
+            .await
+            .map_err(|source| SubmitError::ReviewRequestFailed {
+                pr_number: *pr_number,
+                source,
+            }),
+        JournalStep::Labels { pr_number, labels } => forge
+            .add_labels(*pr_number, labels)
+            .await
+            .map_err(|source| SubmitError::LabelFailed {
+                pr_number: *pr_number,
+                source,
+            }),
+        JournalStep::MarkReady { pr_number } => forge
+            .mark_ready_for_review(*pr_number)
+            .await
+            .map_err(|source| SubmitError::MarkReadyFailed {
+                pr_number: *pr_number,
+                source,
+            }),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Undo
+// ---------------------------------------------------------------------------
+
+/// Outcome of undoing a stopped submit.
+#[derive(Debug, Default)]
+pub struct UndoReport {
+    /// Reversed actions, newest first, with the error of any that failed.
+    pub reverted: Vec<(SubmitAction, Option<SubmitError>)>,
+    /// Actions that undo leaves in place, such as pushed branches.
+    pub remaining: Vec<SubmitAction>,
+}
+
+impl UndoReport {
+    /// The actions still to undo, oldest first, for the journal.
+    pub fn failed_actions(&self) -> Vec<SubmitAction> {
+        self.reverted
+            .iter()
+            .rev()
+            .filter(|(_, error)| error.is_some())
+            .map(|(action, _)| action.clone())
+            .collect()
+    }
+
+    /// The error to exit with when any action could not be undone.
+    pub fn to_error(&self) -> Option<SubmitError> {
+        let failed = self.failed_actions().len();
+        (failed > 0).then(|| SubmitError::UndoFailed {
+            failed,
+            total: self.reverted.len(),
+        })
+    }
+}
+
+impl fmt::Display for UndoReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (action, error) in &self.reverted {
+            match error {
+                None => writeln!(f, "  ok      {}", action.describe_undo())?,
+                Some(error) => writeln!(
+                    f,
+                    "  FAILED  {}: {}",
+                    action.describe_undo(),
+                    error_chain(error)
+                )?,
+            }
+        }
+        if !self.remaining.is_empty() {
+            writeln!(f, "Left in place:")?;
+            for action in &self.remaining {
+                writeln!(f, "  {}", action.describe_undo())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reverse the actions of a stopped submit, newest first: close the PRs it
+/// created and restore the bases it changed. Pushes are not reverted; they
+/// are reported in [`UndoReport::remaining`].
+pub async fn undo_actions<F: Forge>(forge: &F, actions: &[SubmitAction]) -> UndoReport {
+    let mut report = UndoReport::default();
+    for action in actions.iter().rev() {
+        let result = match action {
+            SubmitAction::Pushed { .. } => {
+                report.remaining.push(action.clone());
+                continue;
+            }
+            SubmitAction::UpdatedBase {
+                bookmark,
+                pr_number,
+                from,
+                ..
+            } => forge
+                .update_pr_base(*pr_number, from)
+                .await
+                .map_err(|source| SubmitError::BaseUpdateFailed {
+                    bookmark: bookmark.clone(),
+                    source,
+                }),
+            SubmitAction::CreatedPr {
+                bookmark,
+                pr_number,
+                ..
+            } => forge
+                .close_pr(*pr_number)
+                .await
+                .map_err(|source| SubmitError::ClosePrFailed {
+                    pr_number: *pr_number,
+                    bookmark: bookmark.clone(),
+                    source,
+                }),
+        };
+        report.reverted.push((action.clone(), result.err()));
+    }
+    report
+}
+
+// ---------------------------------------------------------------------------
+// Auto-merge
+// ---------------------------------------------------------------------------
+
+/// Turn on auto-merge for the PRs of `stack` that `plan` bases on the
+/// default branch, returning their numbers.
+///
+/// A PR based on the bookmark below it would merge into that bookmark's
+/// branch, not the default branch, so it is left alone: it gets auto-merge
+/// from a later submit, once the PR below has merged and it is based on the
+/// default branch itself.
+pub async fn enable_auto_merge<F: Forge>(
+    forge: &F,
+    plan: &SubmissionPlan,
+    stack: &[StackEntry],
+    method: MergeMethod,
+) -> Result<Vec<u64>, SubmitError> {
+    let on_trunk = |entry: &&StackEntry| {
+        plan.bookmark_plans
+            .iter()
+            .any(|bp| bp.bookmark_name == entry.bookmark_name && bp.base == plan.default_branch)
+    };
+    let mut enabled = Vec::new();
+    for entry in stack.iter().filter(on_trunk) {
+        forge
+            .enable_auto_merge(entry.pr_number, method)
+            .await
+            .map_err(|source| SubmitError::AutoMergeFailed {
+                bookmark: entry.bookmark_name.clone(),
+                pr_number: entry.pr_number,
+                source,
+            })?;
+        enabled.push(entry.pr_number);
+    }
+    Ok(enabled)
+}
+
+// ---------------------------------------------------------------------------
+// Tracking issue
+// ---------------------------------------------------------------------------
+
+/// Create or update the tracking issue summarizing `stack`, the submitted
+/// PRs of `plan`. An open issue of the authenticated user that tracks any of
+/// the PRs is updated; otherwise a new one is opened. Returns the issue and
+/// whether it was created.
+pub async fn update_umbrella<F: Forge>(
+    forge: &F,
+    plan: &SubmissionPlan,
+    stack: &[StackEntry],
+) -> Result<(Issue, bool), SubmitError> {
+    let failed = |source| SubmitError::UmbrellaFailed { source };
+    let data = StackCommentData {
+        version: 0,
+        stack: stack.to_vec(),
+    };
+    let titles: Vec<String> = plan
+        .bookmark_plans
+        .iter()
+        .map(|bp| bp.title.clone())
+        .collect();
+    let body = format_umbrella_body(&data, &titles, &plan.repo, &plan.default_branch);
+
+    let issues = forge.list_own_issues().await.map_err(failed)?;
+    let tracking = issues.into_iter().find(|issue| {
+        issue
+            .body
+            .as_deref()
+            .and_then(parse_umbrella_body)
+            .is_some_and(|tracked| {
+                tracked
+                    .stack
+                    .iter()
+                    .any(|t| stack.iter().any(|e| e.pr_number == t.pr_number))
+            })
+    });
+    if let Some(issue) = tracking {
+        if issue.body.as_deref() != Some(body.as_str()) {
+            forge
+                .update_issue_body(issue.number, &body)
+                .await
+                .map_err(failed)?;
+        }
+        return Ok((issue, false));
+    }
+    let title = format!("Stack: {}", titles.last().map_or("", String::as_str));
+    let issue = forge.create_issue(&title, &body).await.map_err(failed)?;
+    Ok((issue, true))
+}
+
+// ---------------------------------------------------------------------------
+// Issue links
+// ---------------------------------------------------------------------------
+
+/// Link each PR of `stack` to the issues its commits reference (see
+/// [`BookmarkPlan::issues`]), skipping references to PRs of the stack
+/// itself. Returns the number of links made; those already in place are
+/// not counted.
+pub async fn link_issues<F: Forge>(
+    forge: &F,
+    plan: &SubmissionPlan,
+    stack: &[StackEntry],
+) -> Result<usize, SubmitError> {
+    let stack_prs: HashSet<u64> = stack.iter().map(|entry| entry.pr_number).collect();
+    let mut linked = 0;
+    // One link at a time: PRs that reference the same issue update the same
+    // record of it.
+    for entry in stack {
+        let Some(bp) = plan
+            .bookmark_plans
+            .iter()
+            .find(|bp| bp.bookmark_name == entry.bookmark_name)
+        else {
+            continue;
+        };
+        for &issue in bp.issues.iter().filter(|i| !stack_prs.contains(i)) {
+            let added = forge
+                .link_pr_to_issue(issue, entry.pr_number)
+                .await
+                .map_err(|source| SubmitError::IssueLinkFailed {
+                    issue,
+                    pr_number: entry.pr_number,
+                    source,
+                })?;
+            linked += usize::from(added);
+        }
+    }
+    Ok(linked)
+}
+
+// ---------------------------------------------------------------------------
+// Waiting for CI checks
+// ---------------------------------------------------------------------------
+
+/// Polling schedule for `wait_for_checks`: start at 5 seconds and back off to
+/// 30, so quick CI is noticed promptly without hammering the API on long runs.
+pub fn checks_schedule(timeout: Duration) -> Schedule {
+    Schedule::fixed(Duration::from_secs(5))
+        .with_backoff(2, Duration::from_secs(30))
+        .with_jitter(0.1)
+        .with_timeout(timeout)
+}
+
+/// How long a freshly pushed branch may report no check runs before it is
+/// treated as having no CI at all. CI providers take a moment to register
+/// their runs after a push.
+const CHECKS_REGISTER_GRACE: Duration = Duration::from_mins(1);
+
+/// Combined state of the check runs across all submitted bookmarks.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ChecksStatus {
+    Passed,
+    /// `bookmark: check` labels of the checks still running. A bookmark with
+    /// no runs registered yet is listed by name alone.
+    Pending(Vec<String>),
+    /// `bookmark: check` labels of the failed checks.
+    Failed(Vec<String>),
+}
+
+/// Summarize the check runs of each bookmark. Any failure wins over pending
+/// checks so callers can stop waiting early.
+///
+/// While `awaiting_registration` is set, a bookmark without any check runs
+/// counts as pending rather than passed.
+pub fn summarize_checks(
+    runs: &[(String, Vec<CheckRun>)],
+    awaiting_registration: bool,
+) -> ChecksStatus {
+    let mut pending = Vec::new();
+    let mut failed = Vec::new();
+    for (bookmark, checks) in runs {
+        if checks.is_empty() && awaiting_registration {
+            pending.push(bookmark.clone());
+        }
+        for check in checks {
+            let label = format!("{bookmark}: {}", check.name);
+            match check.state {
+                CheckState::Passed => {}
+                CheckState::Pending => pending.push(label),
+                CheckState::Failed => failed.push(label),
+            }
+        }
+    }
+
+    if !failed.is_empty() {
+        ChecksStatus::Failed(failed)
+    } else if !pending.is_empty() {
+        ChecksStatus::Pending(pending)
+    } else {
+        ChecksStatus::Passed
+    }
+}
+
+/// One-line summary of a branch's check runs, e.g. `2 passed, 1 failed`.
+pub fn describe_checks(runs: &[CheckRun]) -> String {
+    if runs.is_empty() {
+        return "no checks".to_string();
+    }
+    let count = |state| runs.iter().filter(|r| r.state == state).count();
+    [
+        (count(CheckState::Passed), "passed"),
+        (count(CheckState::Failed), "failed"),
+        (count(CheckState::Pending), "pending"),
+    ]
+    .iter()
+    .filter(|(n, _)| *n > 0)
+    .map(|(n, label)| format!("{n} {label}"))
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+/// Poll the check runs of `bookmarks` until they all pass, any of them
+/// fails, or the schedule's timeout elapses.
+///
+/// `on_pending` is called with the number of pending checks before each
+/// wait.
+pub async fn wait_for_checks<F: Forge>(
+    forge: &F,
+    bookmarks: &[String],
+    schedule: &Schedule,
+    mut on_pending: impl FnMut(usize),
+) -> Result<(), SubmitError> {
+    let outcome = poll::poll_until(
+        schedule,
+        std::future::pending(),
+        |elapsed| async move {
+            let mut runs = Vec::with_capacity(bookmarks.len());
+            for bookmark in bookmarks {
+                let checks = forge.list_check_runs(bookmark).await.map_err(|source| {
+                    SubmitError::ChecksLookupFailed {
+                        bookmark: bookmark.clone(),
+                        source,
+                    }
+                })?;
+                runs.push((bookmark.clone(), checks));
+            }
+            match summarize_checks(&runs, elapsed < CHECKS_REGISTER_GRACE) {
+                ChecksStatus::Passed => Ok(ControlFlow::Break(())),
+                ChecksStatus::Failed(failed) => Err(SubmitError::ChecksFailed { failed }),
+                ChecksStatus::Pending(pending) => Ok(ControlFlow::Continue(pending)),
+            }
+        },
+        |pending| on_pending(pending.len()),
+    )
+    .await?;
+
+    match outcome {
+        Polled::Done(()) => Ok(()),
+        Polled::TimedOut(pending) | Polled::Cancelled(pending) => {
+            Err(SubmitError::ChecksTimedOut {
+                timeout_secs: schedule.timeout().unwrap_or_default().as_secs(),
+                pending,
+            })
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::forge::Comment;
+    use crate::forge::ForgeError;
+    use crate::forge::MergedPullRequest;
+    use crate::forge::PrDetails;
+    use crate::forge::PrState;
+    use crate::forge::Review;
+    use crate::forge::ReviewState;
+    use crate::forge::markup::CommentStyle;
+    use crate::forge::markup::build_comment_env;
+    use crate::forge::markup::parse_change_id;
+    use crate::forge::recording::Mutation;
+    use crate::graph::types::BranchStack;
+    use crate::graph::types::SegmentCommit;
+    use crate::jj::JjError;
+
+    // -- Shared operation log for ordering tests --
+
+    type OpLog = Arc<Mutex<Vec<Op>>>;
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    enum Op {
+        Push(String),
+        BaseUpdate(u64),
+        CreatePr(String),
+    }
+
+    // -- Test helpers --
+
+    fn test_comment_env() -> minijinja::Environment<'static> {
+        build_comment_env(None, CommentStyle::Default).unwrap()
     }
 
     fn make_segment(names: &[&str], change_id: &str, desc: &str) -> BookmarkSegment {
@@ -957,6 +3586,10 @@ mod tests {
             segments: HashMap::new(),
             tainted_change_ids: std::collections::HashSet::new(),
             excluded_bookmark_count: 0,
+            tainted_bookmarks: vec![],
+            private_bookmarks: HashMap::new(),
+            conflicted_bookmarks: vec![],
+            root_trunks: HashMap::new(),
             stacks,
         }
     }
@@ -970,6 +3603,7 @@ mod tests {
             base_ref: base.to_string(),
             state: PrState::Open,
             body: None,
+            draft: false,
         }
     }
 
@@ -982,6 +3616,7 @@ mod tests {
             base_ref: base.to_string(),
             state: PrState::Open,
             body: Some(body.to_string()),
+            draft: false,
         }
     }
 
@@ -989,31 +3624,67 @@ mod tests {
 
     struct MockForge {
         existing_prs: HashMap<String, PullRequest>,
+        merged_prs: HashMap<String, MergedPullRequest>,
         created_prs: Mutex<Vec<CreatePrParams>>,
         created_comments: Mutex<Vec<(u64, String)>>,
         updated_comments: Mutex<Vec<(u64, String)>>,
         updated_bases: Mutex<Vec<(u64, String)>>,
         updated_titles: Mutex<Vec<(u64, String)>>,
         updated_bodies: Mutex<Vec<(u64, String)>>,
+        pr_updates: Mutex<Vec<u64>>,
         deleted_comments: Mutex<Vec<u64>>,
+        closed_prs: Mutex<Vec<u64>>,
         existing_comments: HashMap<u64, Vec<Comment>>,
+        batched_edits: Mutex<Vec<Vec<u64>>>,
+        check_runs: HashMap<String, Vec<CheckRun>>,
+        auto_merged: Mutex<Vec<(u64, MergeMethod)>>,
+        marked_ready: Mutex<Vec<u64>>,
+        renamed_branches: Mutex<Vec<(String, String)>>,
+        requested_reviewers: Mutex<Vec<(u64, Vec<String>)>>,
+        added_labels: Mutex<Vec<(u64, Vec<String>)>>,
+        reviews: HashMap<u64, Vec<Review>>,
+        failing_creates: HashSet<String>,
+        failing_comments: HashSet<u64>,
+        missing_branches: HashSet<String>,
+        supports_batch: bool,
         next_pr_number: Mutex<u64>,
         ops: Option<OpLog>,
+        issues: Mutex<Vec<Issue>>,
+        created_issues: Mutex<Vec<String>>,
+        issue_links: Mutex<Vec<(u64, u64)>>,
     }
 
     impl MockForge {
         fn new() -> Self {
             Self {
                 existing_prs: HashMap::new(),
+                merged_prs: HashMap::new(),
                 created_prs: Mutex::new(Vec::new()),
                 created_comments: Mutex::new(Vec::new()),
                 updated_comments: Mutex::new(Vec::new()),
                 updated_bases: Mutex::new(Vec::new()),
                 updated_titles: Mutex::new(Vec::new()),
                 updated_bodies: Mutex::new(Vec::new()),
+                pr_updates: Mutex::new(Vec::new()),
                 deleted_comments: Mutex::new(Vec::new()),
+                closed_prs: Mutex::new(Vec::new()),
                 existing_comments: HashMap::new(),
+                batched_edits: Mutex::new(Vec::new()),
+                check_runs: HashMap::new(),
+                auto_merged: Mutex::new(Vec::new()),
+                marked_ready: Mutex::new(Vec::new()),
+                renamed_branches: Mutex::new(Vec::new()),
+                requested_reviewers: Mutex::new(Vec::new()),
+                added_labels: Mutex::new(Vec::new()),
+                reviews: HashMap::new(),
+                failing_creates: HashSet::new(),
+                failing_comments: HashSet::new(),
+                missing_branches: HashSet::new(),
+                supports_batch: false,
                 next_pr_number: Mutex::new(100),
+                issues: Mutex::new(Vec::new()),
+                created_issues: Mutex::new(Vec::new()),
+                issue_links: Mutex::new(Vec::new()),
                 ops: None,
             }
         }
@@ -1028,17 +3699,66 @@ mod tests {
             self
         }
 
+        fn with_merged_pr(mut self, head: &str, pr: PullRequest, head_sha: &str) -> Self {
+            self.merged_prs.insert(
+                head.to_string(),
+                MergedPullRequest {
+                    pr,
+                    head_sha: head_sha.to_string(),
+                },
+            );
+            self
+        }
+
+        fn with_reviews(mut self, pr_number: u64, reviews: Vec<Review>) -> Self {
+            self.reviews.insert(pr_number, reviews);
+            self
+        }
+
         fn with_existing_comments(mut self, pr_number: u64, comments: Vec<Comment>) -> Self {
             self.existing_comments.insert(pr_number, comments);
             self
         }
+
+        fn with_failing_create(mut self, head: &str) -> Self {
+            self.failing_creates.insert(head.to_string());
+            self
+        }
+
+        fn with_failing_comment(mut self, pr_number: u64) -> Self {
+            self.failing_comments.insert(pr_number);
+            self
+        }
+
+        fn with_missing_branch(mut self, branch: &str) -> Self {
+            self.missing_branches.insert(branch.to_string());
+            self
+        }
+
+        fn with_check_runs(mut self, branch: &str, runs: Vec<CheckRun>) -> Self {
+            self.check_runs.insert(branch.to_string(), runs);
+            self
+        }
+
+        fn with_batch_support(mut self) -> Self {
+            self.supports_batch = true;
+            self
+        }
     }
 
     impl Forge for MockForge {
+        fn repo_ref(&self) -> ForgeRepoRef {
+            ForgeRepoRef::new("test", "repo")
+        }
+
         async fn get_authenticated_user(&self) -> Result<String, ForgeError> {
             Ok("test-user".to_string())
         }
 
+        async fn get_user_emails(&self) -> Result<Vec<String>, ForgeError> {
+            Ok(vec![])
+        }
+
         fn find_pr_for_branch(
             &self,
             head: &str,
@@ -1048,14 +3768,63 @@ mod tests {
             async move { Ok(result) }
         }
 
-        fn create_pr(
+        async fn find_merged_pr_for_branch(
             &self,
-            params: CreatePrParams,
-        ) -> impl std::future::Future<Output = Result<PullRequest, ForgeError>> + Send {
-            let mut counter = self.next_pr_number.lock().unwrap();
-            let number = *counter;
-            *counter += 1;
-            let pr = PullRequest {
+            head: &str,
+        ) -> Result<Option<MergedPullRequest>, ForgeError> {
+            Ok(self.merged_prs.get(head).cloned())
+        }
+
+        async fn get_pr(&self, _number: u64) -> Result<PullRequest, ForgeError> {
+            unreachable!("submit never looks up PRs by number")
+        }
+
+        async fn list_review_requests(&self) -> Result<Vec<PrDetails>, ForgeError> {
+            unreachable!("submit never lists review requests")
+        }
+
+        async fn add_assignees(
+            &self,
+            _pr_number: u64,
+            _assignees: &[String],
+        ) -> Result<(), ForgeError> {
+            unreachable!("submit never assigns PRs")
+        }
+
+        fn find_pr_for_change(
+            &self,
+            change_id: &str,
+        ) -> impl std::future::Future<Output = Result<Option<PullRequest>, ForgeError>> + Send
+        {
+            let result = self
+                .existing_prs
+                .values()
+                .find(|pr| pr.body.as_deref().and_then(parse_change_id) == Some(change_id))
+                .cloned();
+            async move { Ok(result) }
+        }
+
+        fn rename_branch(
+            &self,
+            from: &str,
+            to: &str,
+        ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send {
+            self.renamed_branches
+                .lock()
+                .unwrap()
+                .push((from.to_string(), to.to_string()));
+            async { Ok(()) }
+        }
+
+        fn create_pr(
+            &self,
+            params: CreatePrParams,
+        ) -> impl std::future::Future<Output = Result<PullRequest, ForgeError>> + Send {
+            let fail = self.failing_creates.contains(&params.head);
+            let mut counter = self.next_pr_number.lock().unwrap();
+            let number = *counter;
+            *counter += 1;
+            let pr = PullRequest {
                 number,
                 html_url: format!("https://github.com/test/repo/pull/{number}"),
                 title: params.title.clone(),
@@ -1063,12 +3832,18 @@ mod tests {
                 base_ref: params.base.clone(),
                 state: PrState::Open,
                 body: params.body.clone(),
+                draft: false,
             };
             if let Some(ops) = &self.ops {
                 ops.lock().unwrap().push(Op::CreatePr(params.head.clone()));
             }
             self.created_prs.lock().unwrap().push(params);
-            async move { Ok(pr) }
+            async move {
+                if fail {
+                    return Err(ForgeError::MalformedResponse { field: "number" });
+                }
+                Ok(pr)
+            }
         }
 
         fn update_pr_base(
@@ -1086,18 +3861,83 @@ mod tests {
             async { Ok(()) }
         }
 
-        fn update_pr_title(
+        fn update_pr(
             &self,
             pr_number: u64,
+            title: Option<&str>,
+            body: Option<&str>,
+        ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send {
+            self.pr_updates.lock().unwrap().push(pr_number);
+            if let Some(title) = title {
+                self.updated_titles
+                    .lock()
+                    .unwrap()
+                    .push((pr_number, title.to_string()));
+            }
+            if let Some(body) = body {
+                self.updated_bodies
+                    .lock()
+                    .unwrap()
+                    .push((pr_number, body.to_string()));
+            }
+            async { Ok(()) }
+        }
+
+        async fn close_pr(&self, pr_number: u64) -> Result<(), ForgeError> {
+            self.closed_prs.lock().unwrap().push(pr_number);
+            Ok(())
+        }
+
+        fn list_own_issues(
+            &self,
+        ) -> impl std::future::Future<Output = Result<Vec<Issue>, ForgeError>> + Send {
+            let issues = self.issues.lock().unwrap().clone();
+            async move { Ok(issues) }
+        }
+
+        fn create_issue(
+            &self,
             title: &str,
+            body: &str,
+        ) -> impl std::future::Future<Output = Result<Issue, ForgeError>> + Send {
+            let mut issues = self.issues.lock().unwrap();
+            let number = 900 + issues.len() as u64;
+            let issue = Issue {
+                number,
+                html_url: format!("https://github.com/test/repo/issues/{number}"),
+                body: Some(body.to_string()),
+            };
+            issues.push(issue.clone());
+            self.created_issues.lock().unwrap().push(title.to_string());
+            async move { Ok(issue) }
+        }
+
+        fn update_issue_body(
+            &self,
+            number: u64,
+            body: &str,
         ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send {
-            self.updated_titles
-                .lock()
-                .unwrap()
-                .push((pr_number, title.to_string()));
+            for issue in self.issues.lock().unwrap().iter_mut() {
+                if issue.number == number {
+                    issue.body = Some(body.to_string());
+                }
+            }
             async { Ok(()) }
         }
 
+        fn link_pr_to_issue(
+            &self,
+            issue_number: u64,
+            pr_number: u64,
+        ) -> impl std::future::Future<Output = Result<bool, ForgeError>> + Send {
+            let mut links = self.issue_links.lock().unwrap();
+            let added = !links.contains(&(issue_number, pr_number));
+            if added {
+                links.push((issue_number, pr_number));
+            }
+            async move { Ok(added) }
+        }
+
         fn list_comments(
             &self,
             pr_number: u64,
@@ -1115,15 +3955,27 @@ mod tests {
             pr_number: u64,
             body: &str,
         ) -> impl std::future::Future<Output = Result<Comment, ForgeError>> + Send {
+            let fail = self.failing_comments.contains(&pr_number);
             let comment = Comment {
                 id: pr_number * 1000,
+                node_id: None,
                 body: body.to_string(),
             };
-            self.created_comments
-                .lock()
-                .unwrap()
-                .push((pr_number, body.to_string()));
-            async move { Ok(comment) }
+            if !fail {
+                self.created_comments
+                    .lock()
+                    .unwrap()
+                    .push((pr_number, body.to_string()));
+            }
+            async move {
+                if fail {
+                    return Err(ForgeError::Api {
+                        message: "rate limited".to_string(),
+                        source: "rate limited".into(),
+                    });
+                }
+                Ok(comment)
+            }
         }
 
         fn update_comment(
@@ -1157,6 +4009,90 @@ mod tests {
             self.deleted_comments.lock().unwrap().push(comment_id);
             async { Ok(()) }
         }
+
+        fn branch_exists(
+            &self,
+            branch: &str,
+        ) -> impl std::future::Future<Output = Result<bool, ForgeError>> + Send {
+            let exists = !self.missing_branches.contains(branch);
+            async move { Ok(exists) }
+        }
+
+        fn list_check_runs(
+            &self,
+            branch: &str,
+        ) -> impl std::future::Future<Output = Result<Vec<CheckRun>, ForgeError>> + Send {
+            let runs = self.check_runs.get(branch).cloned().unwrap_or_default();
+            async move { Ok(runs) }
+        }
+
+        fn list_reviews(
+            &self,
+            pr_number: u64,
+        ) -> impl std::future::Future<Output = Result<Vec<Review>, ForgeError>> + Send {
+            let reviews = self.reviews.get(&pr_number).cloned().unwrap_or_default();
+            async move { Ok(reviews) }
+        }
+
+        fn request_reviewers(
+            &self,
+            pr_number: u64,
+            reviewers: &[String],
+        ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send {
+            self.requested_reviewers
+                .lock()
+                .unwrap()
+                .push((pr_number, reviewers.to_vec()));
+            async { Ok(()) }
+        }
+
+        fn add_labels(
+            &self,
+            pr_number: u64,
+            labels: &[String],
+        ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send {
+            self.added_labels
+                .lock()
+                .unwrap()
+                .push((pr_number, labels.to_vec()));
+            async { Ok(()) }
+        }
+
+        fn enable_auto_merge(
+            &self,
+            pr_number: u64,
+            method: MergeMethod,
+        ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send {
+            self.auto_merged.lock().unwrap().push((pr_number, method));
+            async { Ok(()) }
+        }
+
+        fn mark_ready_for_review(
+            &self,
+            pr_number: u64,
+        ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send {
+            self.marked_ready.lock().unwrap().push(pr_number);
+            async { Ok(()) }
+        }
+
+        async fn convert_to_draft(&self, _pr_number: u64) -> Result<(), ForgeError> {
+            Ok(())
+        }
+
+        fn supports_batch_mutations(&self) -> bool {
+            self.supports_batch
+        }
+
+        fn update_comments_batch(
+            &self,
+            edits: &[crate::forge::CommentEdit],
+        ) -> impl std::future::Future<Output = Result<(), ForgeError>> + Send {
+            self.batched_edits
+                .lock()
+                .unwrap()
+                .push(edits.iter().map(|e| e.comment_id).collect());
+            async { Ok(()) }
+        }
     }
 
     // -- Mock JjRunner --
@@ -1166,6 +4102,8 @@ mod tests {
     struct MockJjRunner {
         push_calls: PushLog,
         ops: Option<OpLog>,
+        /// The `name@remote` of every `jj bookmark track`.
+        tracks: Option<Arc<Mutex<Vec<String>>>>,
     }
 
     impl MockJjRunner {
@@ -1175,17 +4113,26 @@ mod tests {
                 Self {
                     push_calls: Arc::clone(&calls),
                     ops: None,
+                    tracks: None,
                 },
                 calls,
             )
         }
 
+        fn with_tracks(self, tracks: &Arc<Mutex<Vec<String>>>) -> Self {
+            Self {
+                tracks: Some(Arc::clone(tracks)),
+                ..self
+            }
+        }
+
         fn new_with_ops(ops: OpLog) -> (Self, PushLog) {
             let calls: PushLog = Arc::new(Mutex::new(Vec::new()));
             (
                 Self {
                     push_calls: Arc::clone(&calls),
                     ops: Some(ops),
+                    tracks: None,
                 },
                 calls,
             )
@@ -1197,8 +4144,8 @@ mod tests {
             &self,
             args: &[&str],
         ) -> impl std::future::Future<Output = Result<String, JjError>> + Send {
-            // Only handle push commands.
-            if args[0] == "git" && args[1] == "push" {
+            // Only handle push commands that push.
+            if args.windows(2).any(|w| w == ["git", "push"]) && !args.contains(&"--dry-run") {
                 let bookmark = args
                     .iter()
                     .position(|a| *a == "--bookmark")
@@ -1214,6 +4161,9 @@ mod tests {
                 }
                 self.push_calls.lock().unwrap().push((bookmark, remote));
             }
+            if let (Some(tracks), ["bookmark", "track", remote_bookmark]) = (&self.tracks, args) {
+                tracks.lock().unwrap().push((*remote_bookmark).to_string());
+            }
             async { Ok(String::new()) }
         }
     }
@@ -1257,6 +4207,64 @@ mod tests {
         assert_eq!(result.segments[1].bookmark_names, vec!["feat-b"]);
     }
 
+    #[test]
+    fn unsigned_commits_are_listed_oldest_first() {
+        let seg_a = make_segment(&["feat-a"], "ch_a", "feature a");
+        let seg_b = make_segment(
+            &["feat-b"],
+            "ch_b",
+            "feature b\n\nSigned-off-by: Test <test@test.com>",
+        );
+        let seg_c = make_segment(&["feat-c"], "ch_c", "feature c");
+        let graph = make_graph(vec![BranchStack {
+            segments: vec![seg_a, seg_b, seg_c],
+        }]);
+
+        let all = HashSet::from([
+            "feat-a".to_string(),
+            "feat-b".to_string(),
+            "feat-c".to_string(),
+        ]);
+        let analysis = analyze_submission("feat-c", &graph, "main", &all).unwrap();
+        let unsigned = unsigned_commits(&analysis);
+        let err = require_signoffs(&unsigned).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "2 commit(s) lack a Signed-off-by trailer for their author: ch_a, ch_c"
+        );
+        assert_eq!(
+            signed_off_description(unsigned[0]),
+            "feature a\n\nSigned-off-by: Test <test@test.com>\n"
+        );
+    }
+
+    #[test]
+    fn pr_trailers_skip_linked_and_immutable_changes() {
+        let url = |n: u64| format!("https://github.com/test/repo/pull/{n}");
+        let seg_a = make_segment(&["feat-a"], "ch_a", "feature a");
+        let seg_b = make_segment(&["feat-b"], "ch_b", &format!("feature b\n\nPR: {}", url(2)));
+        let mut seg_c = make_segment(&["feat-c"], "ch_c", "feature c");
+        seg_c.commits[0].is_immutable = true;
+        let graph = make_graph(vec![BranchStack {
+            segments: vec![seg_a, seg_b, seg_c],
+        }]);
+        let entries: Vec<StackEntry> = ["feat-a", "feat-b", "feat-c"]
+            .into_iter()
+            .zip(1..)
+            .map(|(name, n)| StackEntry {
+                bookmark_name: name.to_string(),
+                pr_url: url(n),
+                pr_number: n,
+                repo: None,
+            })
+            .collect();
+
+        assert_eq!(
+            pr_trailer_edits(&graph, &entries),
+            [("ch_a".to_string(), format!("feature a\n\nPR: {}\n", url(1)))]
+        );
+    }
+
     #[test]
     fn analyze_leaf_of_stack() {
         let seg_a = make_segment(&["feat-a"], "ch_a", "feature a");
@@ -1287,6 +4295,54 @@ mod tests {
         );
     }
 
+    #[test]
+    fn analyze_bookmark_on_private_change_is_error() {
+        let mut graph = make_graph(vec![]);
+        graph
+            .private_bookmarks
+            .insert("feat-a".to_string(), "ch_private".to_string());
+
+        let all = HashSet::from(["feat-a".to_string()]);
+        let result = analyze_submission("feat-a", &graph, "main", &all);
+        assert!(matches!(
+            result,
+            Err(SubmitError::PrivateChange { ref change_id, .. }) if change_id == "ch_private"
+        ));
+    }
+
+    #[test]
+    fn analyze_detects_branch_collision_across_stacks() {
+        let graph = make_graph(vec![
+            BranchStack {
+                segments: vec![make_segment(&["feat"], "ch_feat", "feat")],
+            },
+            BranchStack {
+                segments: vec![make_segment(&["feat/login"], "ch_login", "login")],
+            },
+        ]);
+
+        let all = HashSet::from(["feat/login".to_string()]);
+        let result = analyze_submission("feat/login", &graph, "main", &all);
+        assert!(matches!(
+            result,
+            Err(SubmitError::BranchNameCollision {
+                ref change_id,
+                ref other_bookmark,
+                ref other_change_id,
+                ..
+            }) if change_id == "ch_login" && other_bookmark == "feat" && other_change_id == "ch_feat"
+        ));
+    }
+
+    #[test]
+    fn branch_names_collide_cases() {
+        assert!(branch_names_collide("Feat-A", "feat-a"));
+        assert!(branch_names_collide("feat", "feat/login"));
+        assert!(branch_names_collide("team/x/y", "Team/X"));
+        assert!(!branch_names_collide("feat", "feature"));
+        assert!(!branch_names_collide("feat-a", "feat-b"));
+    }
+
     #[test]
     fn analyze_multiple_stacks_finds_correct_one() {
         let stack1 = BranchStack {
@@ -1307,6 +4363,40 @@ mod tests {
         assert_eq!(result.segments[1].bookmark_names, vec!["gamma"]);
     }
 
+    #[test]
+    fn analyze_memberships_merge_stacks_sharing_a_root() {
+        let root = make_segment(&["root"], "ch_root", "root");
+        // Graph order follows recency; the b-stack comes first here.
+        let graph = make_graph(vec![
+            BranchStack {
+                segments: vec![root.clone(), make_segment(&["b1"], "ch_b1", "b1")],
+            },
+            BranchStack {
+                segments: vec![root, make_segment(&["a1"], "ch_a1", "a1")],
+            },
+        ]);
+
+        let all = HashSet::from(["root".to_string(), "a1".to_string()]);
+        let result = analyze_submission("a1", &graph, "main", &all).unwrap();
+
+        let member = |name: &str, base: &str| StackMember {
+            bookmark_name: name.to_string(),
+            base: base.to_string(),
+        };
+        assert_eq!(
+            result.stack_memberships["root"],
+            vec![
+                member("root", "main"),
+                member("a1", "root"),
+                member("b1", "root"),
+            ]
+        );
+        assert_eq!(
+            result.stack_memberships["a1"],
+            vec![member("root", "main"), member("a1", "root")]
+        );
+    }
+
     #[test]
     fn analyze_filters_unselected_bookmarks() {
         let seg_a = make_segment(&["feat-a"], "ch_a", "feature a");
@@ -1419,6 +4509,7 @@ mod tests {
             ],
 
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
         };
 
         let forge = MockForge::new();
@@ -1429,6 +4520,9 @@ mod tests {
             PrMode::Regular,
             SyncPrContent::None,
             TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
         )
         .await
         .unwrap();
@@ -1445,15 +4539,22 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn plan_existing_pr_correct_base() {
+    async fn plan_skips_a_merged_middle_pr() {
         let analysis = SubmissionAnalysis {
-            segments: vec![make_segment(&["feat-a"], "ch_a", "feature a")],
-
+            segments: vec![
+                make_segment(&["feat-a"], "ch_a", "feature a"),
+                make_segment(&["feat-b"], "ch_b", "feature b"),
+                make_segment(&["feat-c"], "ch_c", "feature c"),
+            ],
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
         };
-
-        let forge = MockForge::new().with_existing_pr("feat-a", make_pr(42, "feat-a", "main"));
-
+        // feat-b merged at its current tip, and feat-c's PR still points at
+        // it; feat-a merged too, but before its change was rewritten.
+        let forge = MockForge::new()
+            .with_merged_pr("feat-a", make_pr(6, "feat-a", "main"), "c_old")
+            .with_merged_pr("feat-b", make_pr(7, "feat-b", "feat-a"), "c_ch_b")
+            .with_existing_pr("feat-c", make_pr(8, "feat-c", "feat-b"));
         let plan = create_submission_plan(
             &analysis,
             &forge,
@@ -1461,33 +4562,35 @@ mod tests {
             PrMode::Regular,
             SyncPrContent::None,
             TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
         )
         .await
         .unwrap();
 
-        assert!(!plan.bookmark_plans[0].needs_create);
-        assert!(!plan.bookmark_plans[0].needs_base_update);
-        assert_eq!(
-            plan.bookmark_plans[0].existing_pr.as_ref().unwrap().number,
-            42
-        );
+        let bases: Vec<(&str, &str)> = plan
+            .bookmark_plans
+            .iter()
+            .map(|bp| (bp.bookmark_name.as_str(), bp.base.as_str()))
+            .collect();
+        assert_eq!(bases, [("feat-a", "main"), ("feat-c", "feat-a")]);
+        assert!(plan.bookmark_plans[0].needs_create);
+        assert!(plan.bookmark_plans[1].needs_base_update);
     }
 
     #[tokio::test]
-    async fn plan_existing_pr_wrong_base() {
+    async fn plan_bases_a_pr_on_its_stakk_base_trailer() {
         let analysis = SubmissionAnalysis {
             segments: vec![
                 make_segment(&["feat-a"], "ch_a", "feature a"),
-                make_segment(&["feat-b"], "ch_b", "feature b"),
+                make_segment(&["feat-b"], "ch_b", "feature b\n\nStakk-Base: release"),
+                make_segment(&["feat-c"], "ch_c", "feature c"),
             ],
-
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
         };
-
-        let forge = MockForge::new()
-            .with_existing_pr("feat-a", make_pr(10, "feat-a", "main"))
-            .with_existing_pr("feat-b", make_pr(11, "feat-b", "main"));
-
+        let forge = MockForge::new().with_existing_pr("feat-b", make_pr(7, "feat-b", "feat-a"));
         let plan = create_submission_plan(
             &analysis,
             &forge,
@@ -1495,32 +4598,36 @@ mod tests {
             PrMode::Regular,
             SyncPrContent::None,
             TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
         )
         .await
         .unwrap();
 
-        // feat-a: base is "main", existing PR base is "main" -> no update
-        assert!(!plan.bookmark_plans[0].needs_base_update);
-
-        // feat-b: base should be "feat-a", existing PR base is "main" ->
-        // needs update
+        let bases: Vec<&str> = plan
+            .bookmark_plans
+            .iter()
+            .map(|bp| bp.base.as_str())
+            .collect();
+        assert_eq!(bases, ["main", "release", "feat-b"]);
         assert!(plan.bookmark_plans[1].needs_base_update);
-        assert_eq!(plan.bookmark_plans[1].base, "feat-a");
     }
 
     #[tokio::test]
-    async fn plan_mixed_existing_and_new() {
+    async fn plan_reads_pr_options_from_trailers() {
         let analysis = SubmissionAnalysis {
-            segments: vec![
-                make_segment(&["feat-a"], "ch_a", "feature a"),
-                make_segment(&["feat-b"], "ch_b", "feature b"),
-            ],
-
+            segments: vec![make_segment(
+                &["feat-a"],
+                "ch_a",
+                "feature a\n\nDetails.\n\nRefs: X-1\nStakk-Draft: true\nStakk-Reviewer: \
+                 alice\nStakk-Label: backend",
+            )],
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
         };
 
-        let forge = MockForge::new().with_existing_pr("feat-a", make_pr(10, "feat-a", "main"));
-
+        let forge = MockForge::new();
         let plan = create_submission_plan(
             &analysis,
             &forge,
@@ -1528,127 +4635,458 @@ mod tests {
             PrMode::Regular,
             SyncPrContent::None,
             TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
         )
         .await
         .unwrap();
 
-        assert!(!plan.bookmark_plans[0].needs_create);
-        assert!(plan.bookmark_plans[1].needs_create);
+        let bp = &plan.bookmark_plans[0];
+        assert!(bp.is_draft(plan.pr_mode, &plan.default_branch));
+        assert_eq!(bp.pr_options.reviewers, ["alice"]);
+        assert_eq!(bp.pr_options.labels, ["backend"]);
+        assert_eq!(bp.body.as_deref(), Some("Details.\n\nRefs: X-1"));
     }
 
     #[tokio::test]
-    async fn plan_sync_title_detects_change() {
+    async fn plan_renders_title_and_body_templates() {
         let analysis = SubmissionAnalysis {
-            // Commit title "feature a" differs from PR title "PR for feat-a".
-            segments: vec![make_segment(&["feat-a"], "ch_a", "feature a")],
+            segments: vec![
+                make_segment(&["feat-a"], "ch_a", "feature a"),
+                make_segment(&["feat-b"], "ch_b", "feature b"),
+            ],
+
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
         };
 
-        let forge = MockForge::new().with_existing_pr("feat-a", make_pr(42, "feat-a", "main"));
-
+        let forge = MockForge::new();
+        let templates = PrTemplates::new(
+            Some("{{ bookmark }}: {{ first_commit.summary }}"),
+            Some("Based on {{ base }} ({{ commit_count }} commit)"),
+        )
+        .unwrap();
         let plan = create_submission_plan(
             &analysis,
             &forge,
             "origin",
             PrMode::Regular,
-            SyncPrContent::All,
+            SyncPrContent::None,
             TrailerHandling::Keep,
+            &templates,
+            &HashSet::new(),
+            &HashMap::new(),
         )
         .await
         .unwrap();
 
-        assert!(plan.bookmark_plans[0].needs_title_sync);
-        // Body is None for both commit and PR — no body sync needed.
-        assert!(!plan.bookmark_plans[0].needs_body_sync);
+        assert_eq!(plan.bookmark_plans[0].title, "feat-a: feature a");
+        assert_eq!(plan.bookmark_plans[1].title, "feat-b: feature b");
+        assert_eq!(
+            plan.bookmark_plans[1].body.as_deref(),
+            Some("Based on feat-a (1 commit)")
+        );
     }
 
     #[tokio::test]
-    async fn plan_sync_title_skips_when_same() {
+    async fn plan_takes_titles_from_title_source() {
+        let mut segment = make_segment(&["feat/login-page"], "ch_a", "tip line\n\nbody");
+        let mut root = segment.commits[0].clone();
+        root.description = "root line".to_string();
+        segment.commits.push(root);
         let analysis = SubmissionAnalysis {
-            segments: vec![make_segment(&["feat-a"], "ch_a", "PR for feat-a")],
+            segments: vec![segment],
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
         };
 
-        let forge = MockForge::new().with_existing_pr("feat-a", make_pr(42, "feat-a", "main"));
+        let mut titles = Vec::new();
+        for source in [TitleSource::Tip, TitleSource::Root, TitleSource::Bookmark] {
+            let templates = PrTemplates::default().with_title_source(source);
+            let plan = create_submission_plan(
+                &analysis,
+                &MockForge::new(),
+                "origin",
+                PrMode::Regular,
+                SyncPrContent::None,
+                TrailerHandling::Keep,
+                &templates,
+                &HashSet::new(),
+                &HashMap::new(),
+            )
+            .await
+            .unwrap();
+            assert_eq!(plan.bookmark_plans[0].title_source, Some(source));
+            titles.push(plan.bookmark_plans[0].title.clone());
+            if source == TitleSource::Bookmark {
+                assert!(
+                    plan.to_string()
+                        .contains("create PR: \"Login page\" (title from the bookmark name)")
+                );
+            }
+        }
+        assert_eq!(titles, ["tip line", "root line", "Login page"]);
+    }
+
+    #[tokio::test]
+    async fn renamed_bookmark_keeps_its_pr() {
+        let analysis = SubmissionAnalysis {
+            segments: vec![make_segment(&["feat-login"], "ch_a", "feature a")],
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+        };
+        let body = splice_change_id_into_body("feature a body", "ch_a");
+        let forge = MockForge::new()
+            .with_existing_pr("feat-a", make_pr_with_body(7, "feat-a", "main", &body));
 
         let plan = create_submission_plan(
             &analysis,
             &forge,
             "origin",
             PrMode::Regular,
-            SyncPrContent::All,
+            SyncPrContent::None,
             TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::from([("feat-a".to_string(), "c_old".to_string())]),
+        )
+        .await
+        .unwrap();
+        let bp = &plan.bookmark_plans[0];
+        assert_eq!(bp.renamed_from.as_deref(), Some("feat-a"));
+        assert_eq!(bp.existing_pr.as_ref().map(|pr| pr.number), Some(7));
+        assert_eq!(bp.expected_remote_target.as_deref(), Some("c_old"));
+        assert!(!bp.needs_create);
+        assert!(
+            plan.to_string().contains(
+                "    - rename branch feat-a to feat-login (PR #7 is for the same change)\n"
+            )
+        );
+
+        let (runner, push_calls) = MockJjRunner::new();
+        let jj = Jj::new(runner);
+        let mut plan = plan;
+        // The mock runner knows no remote bookmarks.
+        plan.bookmark_plans[0].expected_remote_target = None;
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &test_comment_env(),
+            StackPlacement::None,
+            false,
+            &(),
         )
         .await
         .unwrap();
 
-        assert!(!plan.bookmark_plans[0].needs_title_sync);
+        assert_eq!(
+            *forge.renamed_branches.lock().unwrap(),
+            [("feat-a".to_string(), "feat-login".to_string())]
+        );
+        assert_eq!(push_calls.lock().unwrap().len(), 1);
+        assert!(forge.created_prs.lock().unwrap().is_empty());
     }
 
     #[tokio::test]
-    async fn plan_sync_body_detects_change() {
+    async fn comments_only_keeps_prs_as_they_are_on_the_forge() {
         let analysis = SubmissionAnalysis {
-            segments: vec![make_segment(
-                &["feat-a"],
-                "ch_a",
-                "feature a\n\nnew body text",
-            )],
+            segments: vec![
+                make_segment(&["feat-a"], "ch_a", "feature a"),
+                make_segment(&["feat-b"], "ch_b", "feature b"),
+                make_segment(&["feat-c"], "ch_c", "feature c"),
+            ],
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
         };
+        // feat-b's PR merged by hand: feat-c's PR was retargeted onto main.
+        let mut pr_c = make_pr(9, "feat-c", "main");
+        pr_c.title = "Hand-edited title".to_string();
+        let forge = MockForge::new()
+            .with_existing_pr("feat-a", make_pr(7, "feat-a", "main"))
+            .with_existing_pr("feat-c", pr_c);
+        let mut plan = create_submission_plan(
+            &analysis,
+            &forge,
+            "origin",
+            PrMode::Regular,
+            SyncPrContent::All,
+            TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
+        )
+        .await
+        .unwrap();
+        assert!(plan.bookmark_plans[2].needs_base_update);
 
-        let forge = MockForge::new().with_existing_pr(
-            "feat-a",
-            make_pr_with_body(42, "feat-a", "main", "old body"),
-        );
+        assert_eq!(plan.comments_only(), ["feat-b"]);
+        let bookmarks: Vec<&str> = plan
+            .bookmark_plans
+            .iter()
+            .map(|bp| bp.bookmark_name.as_str())
+            .collect();
+        assert_eq!(bookmarks, ["feat-a", "feat-c"]);
+        let c = &plan.bookmark_plans[1];
+        assert_eq!(c.base, "main");
+        assert_eq!(c.title, "Hand-edited title");
+        assert!(plan.bookmark_plans.iter().all(|bp| {
+            !bp.needs_push && !bp.needs_base_update && !bp.needs_title_sync && !bp.needs_body_sync
+        }));
+        assert!(plan.retargets.is_empty());
+    }
 
+    #[tokio::test]
+    async fn dry_run_records_forge_writes_without_making_them() {
+        let analysis = SubmissionAnalysis {
+            segments: vec![
+                make_segment(&["feat-a"], "ch_a", "feature a"),
+                make_segment(&["feat-b"], "ch_b", "feature b"),
+            ],
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+        };
+        let forge = MockForge::new().with_existing_pr("feat-a", make_pr(7, "feat-a", "develop"));
         let plan = create_submission_plan(
             &analysis,
             &forge,
             "origin",
             PrMode::Regular,
-            SyncPrContent::All,
+            SyncPrContent::None,
             TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
         )
         .await
         .unwrap();
+        let plan = SubmissionPlan {
+            push_options: plan.push_options.dry_run(),
+            ..plan
+        };
 
-        assert!(plan.bookmark_plans[0].needs_body_sync);
+        let tracks = Arc::new(Mutex::new(Vec::new()));
+        let (runner, _push_calls) = MockJjRunner::new();
+        let jj = Jj::new(runner.with_tracks(&tracks));
+        let recording = recording_forge(&forge, &plan);
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &recording,
+            &test_comment_env(),
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
+
+        assert!(forge.updated_bases.lock().unwrap().is_empty());
+        assert!(forge.created_prs.lock().unwrap().is_empty());
+        assert!(forge.created_comments.lock().unwrap().is_empty());
+        assert!(tracks.lock().unwrap().is_empty());
+
+        let mutations = recording.mutations();
+        assert!(mutations.iter().any(|m| matches!(
+            m,
+            Mutation::UpdateBase { pr_number: 7, base } if base == "main"
+        )));
+        assert!(mutations.iter().any(|m| matches!(
+            m,
+            Mutation::CreatePr { number: 8, params } if params.base == "feat-a"
+        )));
+        let commented: Vec<u64> = mutations
+            .iter()
+            .filter_map(|m| match m {
+                Mutation::CreateComment { pr_number, body } => {
+                    assert!(body.contains("2. test/repo#8"));
+                    Some(*pr_number)
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(commented, [7, 8]);
     }
 
     #[tokio::test]
-    async fn plan_sync_body_ignores_fenced_section() {
-        let fenced_body =
-            "old body\n\n<!-- STAKK_BODY_START -->\nstack info\n<!-- STAKK_BODY_END -->";
+    async fn created_pr_body_records_change_id() {
         let analysis = SubmissionAnalysis {
-            // Commit body = "old body" matches the non-fenced portion.
-            segments: vec![make_segment(&["feat-a"], "ch_a", "feature a\n\nold body")],
+            segments: vec![make_segment(&["feat-a"], "ch_a", "feature a\n\nDetails.")],
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
         };
+        let forge = MockForge::new();
+        let plan = create_submission_plan(
+            &analysis,
+            &forge,
+            "origin",
+            PrMode::Regular,
+            SyncPrContent::None,
+            TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
+        )
+        .await
+        .unwrap();
+        let (runner, _push_calls) = MockJjRunner::new();
+        execute_submission_plan(
+            &plan,
+            &Jj::new(runner),
+            &forge,
+            &test_comment_env(),
+            StackPlacement::None,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
 
-        let forge = MockForge::new().with_existing_pr(
-            "feat-a",
-            make_pr_with_body(42, "feat-a", "main", fenced_body),
+        let created = forge.created_prs.lock().unwrap();
+        let body = created[0].body.as_deref().unwrap();
+        assert_eq!(parse_change_id(body), Some("ch_a"));
+        assert_eq!(strip_managed_regions(body), "Details.");
+    }
+
+    #[tokio::test]
+    async fn new_prs_get_the_milestone_and_project() {
+        let analysis = SubmissionAnalysis {
+            segments: vec![
+                make_segment(&["feat-a"], "ch_a", "feature a"),
+                make_segment(&["feat-b"], "ch_b", "feature b"),
+            ],
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+        };
+        let forge = MockForge::new().with_existing_pr("feat-a", make_pr(1, "feat-a", "main"));
+        let mut plan = create_submission_plan(
+            &analysis,
+            &forge,
+            "origin",
+            PrMode::Regular,
+            SyncPrContent::None,
+            TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
+        )
+        .await
+        .unwrap();
+        plan.milestone = Some("Sprint 42".to_string());
+        plan.project = Some(7);
+        let output = plan.to_string();
+        assert!(
+            output.ends_with(
+                "    - create PR: \"feature b\" (title from the tip commit)\n    - set milestone \
+                 Sprint 42\n    - add to project #7\n"
+            ),
+            "{output}"
         );
 
+        let (runner, _push_calls) = MockJjRunner::new();
+        execute_submission_plan(
+            &plan,
+            &Jj::new(runner),
+            &forge,
+            &test_comment_env(),
+            StackPlacement::None,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
+        let created = forge.created_prs.lock().unwrap();
+        assert_eq!(created.len(), 1);
+        assert_eq!(created[0].milestone.as_deref(), Some("Sprint 42"));
+        assert_eq!(created[0].project, Some(7));
+    }
+
+    #[tokio::test]
+    async fn draft_above_first_readies_the_bottom_pr() {
+        // feat-a's parent merged, leaving its draft PR at the bottom.
+        let analysis = SubmissionAnalysis {
+            segments: vec![
+                make_segment(&["feat-a"], "ch_a", "feature a"),
+                make_segment(&["feat-b"], "ch_b", "feature b"),
+                make_segment(&["feat-c"], "ch_c", "feature c"),
+            ],
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+        };
+        let draft_pr = |number, head: &str, base: &str| PullRequest {
+            draft: true,
+            ..make_pr(number, head, base)
+        };
+        let forge = MockForge::new()
+            .with_existing_pr("feat-a", draft_pr(1, "feat-a", "main"))
+            .with_existing_pr("feat-b", draft_pr(2, "feat-b", "feat-a"));
+
         let plan = create_submission_plan(
             &analysis,
             &forge,
             "origin",
-            PrMode::Regular,
-            SyncPrContent::All,
+            PrMode::DraftAboveFirst,
+            SyncPrContent::None,
             TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
+        )
+        .await
+        .unwrap();
+        let ready: Vec<bool> = plan
+            .bookmark_plans
+            .iter()
+            .map(|bp| bp.needs_mark_ready)
+            .collect();
+        assert_eq!(ready, [true, false, false]);
+        let output = plan.to_string();
+        assert!(
+            output.contains("    - mark PR #1 ready for review\n"),
+            "{output}"
+        );
+        assert!(
+            output.contains("    - create PR (draft): \"feature c\""),
+            "{output}"
+        );
+
+        let (runner, _push_calls) = MockJjRunner::new();
+        let jj = Jj::new(runner);
+        let result = execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &test_comment_env(),
+            StackPlacement::None,
+            false,
+            &(),
         )
         .await
         .unwrap();
 
-        assert!(!plan.bookmark_plans[0].needs_body_sync);
+        assert_eq!(*forge.marked_ready.lock().unwrap(), [1]);
+        assert!(forge.created_prs.lock().unwrap()[0].draft);
+        assert_eq!(result.steps.failed(), 0);
+    }
+
+    #[test]
+    fn prettify_bookmark_names() {
+        assert_eq!(
+            prettify_bookmark("gb-caching_database"),
+            "Gb caching database"
+        );
+        assert_eq!(prettify_bookmark("user/feat/x"), "X");
+        assert_eq!(prettify_bookmark("---"), "---");
     }
 
     #[tokio::test]
-    async fn plan_sync_disabled_does_not_set_flags() {
+    async fn plan_existing_pr_correct_base() {
         let analysis = SubmissionAnalysis {
             segments: vec![make_segment(&["feat-a"], "ch_a", "feature a")],
+
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
         };
 
         let forge = MockForge::new().with_existing_pr("feat-a", make_pr(42, "feat-a", "main"));
@@ -1660,94 +5098,715 @@ mod tests {
             PrMode::Regular,
             SyncPrContent::None,
             TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
         )
         .await
         .unwrap();
 
-        assert!(!plan.bookmark_plans[0].needs_title_sync);
-        assert!(!plan.bookmark_plans[0].needs_body_sync);
+        assert!(!plan.bookmark_plans[0].needs_create);
+        assert!(!plan.bookmark_plans[0].needs_base_update);
+        assert_eq!(
+            plan.bookmark_plans[0].existing_pr.as_ref().unwrap().number,
+            42
+        );
     }
 
     #[tokio::test]
-    async fn plan_sync_new_pr_does_not_set_flags() {
+    async fn plan_shows_review_summary() {
         let analysis = SubmissionAnalysis {
-            segments: vec![make_segment(&["feat-a"], "ch_a", "feature a")],
+            segments: vec![
+                make_segment(&["feat-a"], "ch_a", "feature a"),
+                make_segment(&["feat-b"], "ch_b", "feature b"),
+            ],
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
         };
-
-        let forge = MockForge::new();
+        let review = |author: &str, state| Review {
+            author: author.to_string(),
+            state,
+        };
+        let forge = MockForge::new()
+            .with_existing_pr("feat-a", make_pr(42, "feat-a", "main"))
+            .with_reviews(
+                42,
+                vec![
+                    review("alice", ReviewState::ChangesRequested),
+                    review("alice", ReviewState::Approved),
+                    review("bob", ReviewState::Approved),
+                    review("carol", ReviewState::ChangesRequested),
+                    review("dave", ReviewState::Commented),
+                ],
+            );
 
         let plan = create_submission_plan(
             &analysis,
             &forge,
             "origin",
             PrMode::Regular,
-            SyncPrContent::All,
+            SyncPrContent::None,
             TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
         )
         .await
         .unwrap();
 
-        assert!(!plan.bookmark_plans[0].needs_title_sync);
-        assert!(!plan.bookmark_plans[0].needs_body_sync);
+        assert_eq!(
+            plan.bookmark_plans[0].reviews,
+            Some(ReviewSummary {
+                approved: 2,
+                changes_requested: 1
+            })
+        );
+        assert_eq!(plan.bookmark_plans[1].reviews, None);
+        let output = plan.to_string();
+        assert!(output.contains("  feat-a (base: main) [\u{2713}2 \u{2717}1]\n"));
+        assert!(output.contains("  feat-b (base: feat-a)\n"));
     }
 
     #[tokio::test]
-    async fn plan_sync_title_only_does_not_set_body_flag() {
+    async fn plan_skips_push_for_synced_bookmarks() {
         let analysis = SubmissionAnalysis {
-            segments: vec![make_segment(&["feat-a"], "ch_a", "feature a\n\nnew body")],
+            segments: vec![
+                make_segment(&["feat-a"], "ch_a", "feature a"),
+                make_segment(&["feat-b"], "ch_b", "feature b"),
+            ],
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
         };
 
-        let forge = MockForge::new().with_existing_pr(
-            "feat-a",
-            make_pr_with_body(42, "feat-a", "main", "old body"),
-        );
+        let forge = MockForge::new();
 
         let plan = create_submission_plan(
             &analysis,
             &forge,
             "origin",
             PrMode::Regular,
-            SyncPrContent::Title,
+            SyncPrContent::None,
             TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::from(["feat-a".to_string()]),
+            &HashMap::new(),
         )
         .await
         .unwrap();
 
-        assert!(plan.bookmark_plans[0].needs_title_sync);
-        assert!(!plan.bookmark_plans[0].needs_body_sync);
+        assert!(!plan.bookmark_plans[0].needs_push);
+        assert!(plan.bookmark_plans[1].needs_push);
+        assert_eq!(plan.to_string().matches("push bookmark").count(), 1);
     }
 
     #[tokio::test]
-    async fn plan_sync_body_only_does_not_set_title_flag() {
+    async fn plan_retargets_stale_prs_above_submitted_range() {
+        // After `jj rebase`, the stack reads main <- c <- a <- b. b's PR still
+        // targets c, and e sits on an unpushed bookmark x.
+        let member = |name: &str, base: &str| StackMember {
+            bookmark_name: name.to_string(),
+            base: base.to_string(),
+        };
+        let members = vec![
+            member("c", "main"),
+            member("a", "c"),
+            member("b", "a"),
+            member("x", "b"),
+            member("e", "x"),
+        ];
         let analysis = SubmissionAnalysis {
-            segments: vec![make_segment(&["feat-a"], "ch_a", "feature a\n\nnew body")],
+            segments: vec![
+                make_segment(&["c"], "ch_c", "feature c"),
+                make_segment(&["a"], "ch_a", "feature a"),
+            ],
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::from([
+                ("c".to_string(), members.clone()),
+                ("a".to_string(), members),
+            ]),
         };
+        let forge = MockForge::new()
+            .with_existing_pr("a", make_pr(1, "a", "main"))
+            .with_existing_pr("c", make_pr(3, "c", "a"))
+            .with_existing_pr("b", make_pr(2, "b", "c"))
+            .with_existing_pr("e", make_pr(5, "e", "b"));
 
-        let forge = MockForge::new().with_existing_pr(
-            "feat-a",
-            make_pr_with_body(42, "feat-a", "main", "old body"),
+        let plan = create_submission_plan(
+            &analysis,
+            &forge,
+            "origin",
+            PrMode::Regular,
+            SyncPrContent::None,
+            TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            plan.retargets,
+            vec![Retarget {
+                bookmark_name: "b".to_string(),
+                pr_number: 2,
+                from: "c".to_string(),
+                to: "a".to_string(),
+            }]
         );
+        let output = plan.to_string();
+        assert!(output.contains("retarget PR #3 from a to main"));
+        assert!(output.contains("retarget PR #1 from main to c"));
+        assert!(output.contains("retarget PR #2 (b) from c to a"));
+        assert!(output.contains("3 PR(s) will be retargeted"));
+    }
+
+    #[tokio::test]
+    async fn plan_existing_pr_wrong_base() {
+        let analysis = SubmissionAnalysis {
+            segments: vec![
+                make_segment(&["feat-a"], "ch_a", "feature a"),
+                make_segment(&["feat-b"], "ch_b", "feature b"),
+            ],
+
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+        };
+
+        let forge = MockForge::new()
+            .with_existing_pr("feat-a", make_pr(10, "feat-a", "main"))
+            .with_existing_pr("feat-b", make_pr(11, "feat-b", "main"));
 
         let plan = create_submission_plan(
             &analysis,
             &forge,
             "origin",
             PrMode::Regular,
-            SyncPrContent::Body,
+            SyncPrContent::None,
             TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
         )
         .await
         .unwrap();
 
-        assert!(!plan.bookmark_plans[0].needs_title_sync);
-        assert!(plan.bookmark_plans[0].needs_body_sync);
-    }
+        // feat-a: base is "main", existing PR base is "main" -> no update
+        assert!(!plan.bookmark_plans[0].needs_base_update);
 
-    #[test]
+        // feat-b: base should be "feat-a", existing PR base is "main" ->
+        // needs update
+        assert!(plan.bookmark_plans[1].needs_base_update);
+        assert_eq!(plan.bookmark_plans[1].base, "feat-a");
+    }
+
+    #[tokio::test]
+    async fn plan_mixed_existing_and_new() {
+        let analysis = SubmissionAnalysis {
+            segments: vec![
+                make_segment(&["feat-a"], "ch_a", "feature a"),
+                make_segment(&["feat-b"], "ch_b", "feature b"),
+            ],
+
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+        };
+
+        let forge = MockForge::new().with_existing_pr("feat-a", make_pr(10, "feat-a", "main"));
+
+        let plan = create_submission_plan(
+            &analysis,
+            &forge,
+            "origin",
+            PrMode::Regular,
+            SyncPrContent::None,
+            TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
+        )
+        .await
+        .unwrap();
+
+        assert!(!plan.bookmark_plans[0].needs_create);
+        assert!(plan.bookmark_plans[1].needs_create);
+    }
+
+    #[tokio::test]
+    async fn plan_sync_title_detects_change() {
+        let analysis = SubmissionAnalysis {
+            // Commit title "feature a" differs from PR title "PR for feat-a".
+            segments: vec![make_segment(&["feat-a"], "ch_a", "feature a")],
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+        };
+
+        let forge = MockForge::new().with_existing_pr("feat-a", make_pr(42, "feat-a", "main"));
+
+        let plan = create_submission_plan(
+            &analysis,
+            &forge,
+            "origin",
+            PrMode::Regular,
+            SyncPrContent::All,
+            TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
+        )
+        .await
+        .unwrap();
+
+        assert!(plan.bookmark_plans[0].needs_title_sync);
+        // Body is None for both commit and PR — no body sync needed.
+        assert!(!plan.bookmark_plans[0].needs_body_sync);
+    }
+
+    #[tokio::test]
+    async fn plan_sync_title_skips_when_same() {
+        let analysis = SubmissionAnalysis {
+            segments: vec![make_segment(&["feat-a"], "ch_a", "PR for feat-a")],
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+        };
+
+        let forge = MockForge::new().with_existing_pr("feat-a", make_pr(42, "feat-a", "main"));
+
+        let plan = create_submission_plan(
+            &analysis,
+            &forge,
+            "origin",
+            PrMode::Regular,
+            SyncPrContent::All,
+            TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
+        )
+        .await
+        .unwrap();
+
+        assert!(!plan.bookmark_plans[0].needs_title_sync);
+    }
+
+    #[tokio::test]
+    async fn plan_sync_body_detects_change() {
+        let analysis = SubmissionAnalysis {
+            segments: vec![make_segment(
+                &["feat-a"],
+                "ch_a",
+                "feature a\n\nnew body text",
+            )],
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+        };
+
+        let forge = MockForge::new().with_existing_pr(
+            "feat-a",
+            make_pr_with_body(42, "feat-a", "main", "old body"),
+        );
+
+        let plan = create_submission_plan(
+            &analysis,
+            &forge,
+            "origin",
+            PrMode::Regular,
+            SyncPrContent::All,
+            TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
+        )
+        .await
+        .unwrap();
+
+        assert!(plan.bookmark_plans[0].needs_body_sync);
+    }
+
+    #[tokio::test]
+    async fn plan_sync_body_ignores_fenced_section() {
+        let fenced_body =
+            "old body\n\n<!-- STAKK_BODY_START -->\nstack info\n<!-- STAKK_BODY_END -->";
+        let analysis = SubmissionAnalysis {
+            // Commit body = "old body" matches the non-fenced portion.
+            segments: vec![make_segment(&["feat-a"], "ch_a", "feature a\n\nold body")],
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+        };
+
+        let forge = MockForge::new().with_existing_pr(
+            "feat-a",
+            make_pr_with_body(42, "feat-a", "main", fenced_body),
+        );
+
+        let plan = create_submission_plan(
+            &analysis,
+            &forge,
+            "origin",
+            PrMode::Regular,
+            SyncPrContent::All,
+            TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
+        )
+        .await
+        .unwrap();
+
+        assert!(!plan.bookmark_plans[0].needs_body_sync);
+    }
+
+    #[tokio::test]
+    async fn plan_sync_body_ignores_pr_trailer_added_after_create() {
+        // A submit with `--pr-trailer` wrote the PR link into the commit.
+        let description = trailers::with_pr_trailer(
+            "feature a\n\nold body\n\nRefs: X",
+            "https://github.com/o/r/pull/42",
+        )
+        .unwrap();
+        let analysis = SubmissionAnalysis {
+            segments: vec![make_segment(&["feat-a"], "ch_a", &description)],
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+        };
+
+        let forge = MockForge::new().with_existing_pr(
+            "feat-a",
+            make_pr_with_body(42, "feat-a", "main", "old body\n\nRefs: X"),
+        );
+
+        let plan = create_submission_plan(
+            &analysis,
+            &forge,
+            "origin",
+            PrMode::Regular,
+            SyncPrContent::All,
+            TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
+        )
+        .await
+        .unwrap();
+
+        assert!(!plan.bookmark_plans[0].needs_body_sync);
+    }
+
+    #[tokio::test]
+    async fn plan_sync_disabled_does_not_set_flags() {
+        let analysis = SubmissionAnalysis {
+            segments: vec![make_segment(&["feat-a"], "ch_a", "feature a")],
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+        };
+
+        let forge = MockForge::new().with_existing_pr("feat-a", make_pr(42, "feat-a", "main"));
+
+        let plan = create_submission_plan(
+            &analysis,
+            &forge,
+            "origin",
+            PrMode::Regular,
+            SyncPrContent::None,
+            TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
+        )
+        .await
+        .unwrap();
+
+        assert!(!plan.bookmark_plans[0].needs_title_sync);
+        assert!(!plan.bookmark_plans[0].needs_body_sync);
+    }
+
+    #[tokio::test]
+    async fn plan_sync_new_pr_does_not_set_flags() {
+        let analysis = SubmissionAnalysis {
+            segments: vec![make_segment(&["feat-a"], "ch_a", "feature a")],
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+        };
+
+        let forge = MockForge::new();
+
+        let plan = create_submission_plan(
+            &analysis,
+            &forge,
+            "origin",
+            PrMode::Regular,
+            SyncPrContent::All,
+            TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
+        )
+        .await
+        .unwrap();
+
+        assert!(!plan.bookmark_plans[0].needs_title_sync);
+        assert!(!plan.bookmark_plans[0].needs_body_sync);
+    }
+
+    #[tokio::test]
+    async fn plan_sync_title_only_does_not_set_body_flag() {
+        let analysis = SubmissionAnalysis {
+            segments: vec![make_segment(&["feat-a"], "ch_a", "feature a\n\nnew body")],
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+        };
+
+        let forge = MockForge::new().with_existing_pr(
+            "feat-a",
+            make_pr_with_body(42, "feat-a", "main", "old body"),
+        );
+
+        let plan = create_submission_plan(
+            &analysis,
+            &forge,
+            "origin",
+            PrMode::Regular,
+            SyncPrContent::Title,
+            TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
+        )
+        .await
+        .unwrap();
+
+        assert!(plan.bookmark_plans[0].needs_title_sync);
+        assert!(!plan.bookmark_plans[0].needs_body_sync);
+    }
+
+    #[tokio::test]
+    async fn plan_sync_body_only_does_not_set_title_flag() {
+        let analysis = SubmissionAnalysis {
+            segments: vec![make_segment(&["feat-a"], "ch_a", "feature a\n\nnew body")],
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+        };
+
+        let forge = MockForge::new().with_existing_pr(
+            "feat-a",
+            make_pr_with_body(42, "feat-a", "main", "old body"),
+        );
+
+        let plan = create_submission_plan(
+            &analysis,
+            &forge,
+            "origin",
+            PrMode::Regular,
+            SyncPrContent::Body,
+            TrailerHandling::Keep,
+            &PrTemplates::default(),
+            &HashSet::new(),
+            &HashMap::new(),
+        )
+        .await
+        .unwrap();
+
+        assert!(!plan.bookmark_plans[0].needs_title_sync);
+        assert!(plan.bookmark_plans[0].needs_body_sync);
+    }
+
+    #[test]
     fn plan_display_dry_run() {
+        let mut plan = SubmissionPlan {
+            bookmark_plans: vec![
+                BookmarkPlan {
+                    bookmark_name: "feat-a".to_string(),
+                    base: "main".to_string(),
+                    title: "feature a".to_string(),
+                    body: None,
+                    existing_pr: None,
+                    needs_push: true,
+                    expected_remote_target: None,
+                    needs_create: true,
+                    needs_base_update: false,
+                    needs_title_sync: false,
+                    needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
+                },
+                BookmarkPlan {
+                    bookmark_name: "feat-b".to_string(),
+                    base: "feat-a".to_string(),
+                    title: "feature b".to_string(),
+                    body: None,
+                    existing_pr: Some(make_pr(42, "feat-b", "main")),
+                    needs_push: true,
+                    expected_remote_target: None,
+                    needs_create: false,
+                    needs_base_update: true,
+                    needs_title_sync: false,
+                    needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
+                },
+            ],
+            remote: "origin".to_string(),
+            pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
+        };
+
+        let output = plan.to_string();
+        assert!(output.contains("2 bookmark(s)"));
+        assert!(output.contains("feat-a (base: main)"));
+        assert!(output.contains("create PR: \"feature a\""));
+        assert!(output.contains("push bookmark to origin\n"));
+        assert!(output.contains("retarget PR #42 from main to feat-a"));
+
+        plan.bookmark_plans[0].push_size = Some(PushSize {
+            new_commits: 3,
+            replaced_commits: 0,
+        });
+        plan.bookmark_plans[1].push_size = Some(PushSize {
+            new_commits: 1,
+            replaced_commits: 2,
+        });
+        let output = plan.to_string();
+        assert!(output.contains("push bookmark to origin (3 new commit(s))\n"));
+        assert!(
+            output
+                .contains("push bookmark to origin (1 new commit(s), replacing 2 on the remote)\n")
+        );
+    }
+
+    #[test]
+    fn plan_display_shows_sync_lines() {
+        let plan = SubmissionPlan {
+            bookmark_plans: vec![BookmarkPlan {
+                bookmark_name: "feat-a".to_string(),
+                base: "main".to_string(),
+                title: "feature a".to_string(),
+                body: Some("body text".to_string()),
+                existing_pr: Some(make_pr(42, "feat-a", "main")),
+                needs_push: true,
+                expected_remote_target: None,
+                needs_create: false,
+                needs_base_update: false,
+                needs_title_sync: true,
+                needs_body_sync: true,
+                pr_options: PrOptions::default(),
+                title_source: None,
+                needs_mark_ready: false,
+                change_id: None,
+                renamed_from: None,
+                issues: Vec::new(),
+                push_size: None,
+                checks: None,
+                reviews: None,
+            }],
+            remote: "origin".to_string(),
+            pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
+        };
+
+        let output = plan.to_string();
+        assert!(output.contains("sync PR #42 title from commits"));
+        assert!(output.contains("sync PR #42 body from commits"));
+        assert!(!output.contains("up to date"));
+    }
+
+    // -----------------------------------------------------------------------
+    // Phase 3 tests
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn execute_applies_pr_options_to_new_prs() {
+        let plan = SubmissionPlan {
+            bookmark_plans: vec![BookmarkPlan {
+                bookmark_name: "feat-a".to_string(),
+                base: "main".to_string(),
+                title: "feature a".to_string(),
+                body: None,
+                existing_pr: None,
+                needs_push: true,
+                expected_remote_target: None,
+                needs_create: true,
+                needs_base_update: false,
+                needs_title_sync: false,
+                needs_body_sync: false,
+                pr_options: PrOptions {
+                    draft: Some(false),
+                    reviewers: vec!["alice".to_string()],
+                    labels: vec!["backend".to_string()],
+                    base: None,
+                },
+                title_source: None,
+                needs_mark_ready: false,
+                change_id: None,
+                renamed_from: None,
+                issues: Vec::new(),
+                push_size: None,
+                checks: None,
+                reviews: None,
+            }],
+            remote: "origin".to_string(),
+            pr_mode: PrMode::Draft,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
+        };
+
+        let (runner, _push_calls) = MockJjRunner::new();
+        let jj = Jj::new(runner);
+        let forge = MockForge::new();
+        let env = test_comment_env();
+
+        let result =
+            execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::None, false, &())
+                .await
+                .unwrap();
+
+        assert_eq!(result.steps.failed(), 0);
+        assert!(!forge.created_prs.lock().unwrap()[0].draft);
+        assert_eq!(
+            *forge.requested_reviewers.lock().unwrap(),
+            [(100, vec!["alice".to_string()])]
+        );
+        assert_eq!(
+            *forge.added_labels.lock().unwrap(),
+            [(100, vec!["backend".to_string()])]
+        );
+    }
+
+    #[tokio::test]
+    async fn execute_creates_new_prs() {
         let plan = SubmissionPlan {
             bookmark_plans: vec![
                 BookmarkPlan {
@@ -1757,118 +5816,857 @@ mod tests {
                     body: None,
                     existing_pr: None,
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: true,
                     needs_base_update: false,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
                 BookmarkPlan {
                     bookmark_name: "feat-b".to_string(),
                     base: "feat-a".to_string(),
                     title: "feature b".to_string(),
                     body: None,
-                    existing_pr: Some(make_pr(42, "feat-b", "main")),
+                    existing_pr: None,
                     needs_push: true,
-                    needs_create: false,
-                    needs_base_update: true,
+                    expected_remote_target: None,
+                    needs_create: true,
+                    needs_base_update: false,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
             ],
             remote: "origin".to_string(),
             pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
+        };
+
+        let (runner, _push_calls) = MockJjRunner::new();
+        let jj = Jj::new(runner);
+        let forge = MockForge::new();
+        let env = test_comment_env();
+        let (events_tx, events_rx) = std::sync::mpsc::channel();
+
+        let result = execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &events_tx,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.stack_entries.len(), 2);
+
+        let created = forge.created_prs.lock().unwrap();
+        assert_eq!(created.len(), 2);
+        assert_eq!(created[0].head, "feat-a");
+        assert_eq!(created[0].base, "main");
+        assert_eq!(created[1].head, "feat-b");
+        assert_eq!(created[1].base, "feat-a");
+
+        drop(events_tx);
+        let created_events: Vec<String> = events_rx
+            .iter()
+            .filter_map(|event| match event {
+                SubmitEvent::CreatedPr { bookmark, .. } => Some(bookmark),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(created_events, ["feat-a", "feat-b"]);
+    }
+
+    #[test]
+    fn creation_chains_follow_new_bases() {
+        let mut plan = single_create_plan(false);
+        let new = |name: &str, base: &str| BookmarkPlan {
+            bookmark_name: name.to_string(),
+            base: base.to_string(),
+            ..plan.bookmark_plans[0].clone()
+        };
+        let mut existing = new("a", "main");
+        existing.existing_pr = Some(make_pr(1, "a", "main"));
+        plan.bookmark_plans = vec![
+            existing,
+            new("b", "a"),
+            new("x", "main"),
+            new("c", "b"),
+            new("y", "x"),
+            new("d", "b"),
+        ];
+
+        let chains: Vec<Vec<(usize, &str)>> = creation_chains(&plan)
+            .into_iter()
+            .map(|chain| {
+                chain
+                    .into_iter()
+                    .map(|(index, bp)| (index, bp.bookmark_name.as_str()))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            chains,
+            [vec![(1, "b"), (3, "c"), (5, "d")], vec![(2, "x"), (4, "y")]]
+        );
+    }
+
+    fn single_create_plan(needs_push: bool) -> SubmissionPlan {
+        SubmissionPlan {
+            bookmark_plans: vec![BookmarkPlan {
+                bookmark_name: "feat-a".to_string(),
+                base: "main".to_string(),
+                title: "feature a".to_string(),
+                body: None,
+                existing_pr: None,
+                needs_push,
+                expected_remote_target: None,
+                needs_create: true,
+                needs_base_update: false,
+                needs_title_sync: false,
+                needs_body_sync: false,
+                pr_options: PrOptions::default(),
+                title_source: None,
+                needs_mark_ready: false,
+                change_id: None,
+                renamed_from: None,
+                issues: Vec::new(),
+                push_size: None,
+                checks: None,
+                reviews: None,
+            }],
+            remote: "origin".to_string(),
+            pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
+        }
+    }
+
+    #[test]
+    fn notes_are_attached_to_new_and_existing_prs() {
+        let notes = BTreeMap::from([
+            ("ch_a".to_string(), "Why A.\n".to_string()),
+            ("ch_b".to_string(), "Why B.\n".to_string()),
+        ]);
+        let mut plan = single_create_plan(false);
+        plan.bookmark_plans[0].change_id = Some("ch_a".to_string());
+        let mut existing = plan.bookmark_plans[0].clone();
+        existing.bookmark_name = "feat-b".to_string();
+        existing.change_id = Some("ch_b".to_string());
+        existing.needs_create = false;
+        existing.existing_pr = Some(make_pr_with_body(2, "feat-b", "main", "Edited by hand."));
+        let mut up_to_date = existing.clone();
+        up_to_date.bookmark_name = "feat-c".to_string();
+        up_to_date.existing_pr = Some(make_pr_with_body(
+            3,
+            "feat-c",
+            "main",
+            &NOTES_REGION.replace("Edited by hand.", "Why B.\n"),
+        ));
+        plan.bookmark_plans.extend([existing, up_to_date]);
+
+        attach_notes(&mut plan, &notes);
+
+        let [new, existing, up_to_date] = &plan.bookmark_plans[..] else {
+            panic!("expected three plans");
+        };
+        assert_eq!(
+            new.body
+                .as_deref()
+                .and_then(|body| NOTES_REGION.extract(body)),
+            Some("Why A.\n")
+        );
+        assert!(existing.needs_body_sync);
+        let body = existing.body.as_deref().unwrap();
+        assert!(body.starts_with("Edited by hand."));
+        assert_eq!(NOTES_REGION.extract(body), Some("Why B.\n"));
+        // Already up to date: no sync.
+        assert!(!up_to_date.needs_body_sync);
+    }
+
+    #[test]
+    fn titles_are_numbered_by_stack_position() {
+        let member = |name: &str, base: &str| StackMember {
+            bookmark_name: name.to_string(),
+            base: base.to_string(),
+        };
+        let mut plan = single_create_plan(false);
+        // The stack grew from two PRs to three: `feat-b` was `[2/2]`.
+        let mut renumbered = plan.bookmark_plans[0].clone();
+        renumbered.bookmark_name = "feat-b".to_string();
+        renumbered.needs_create = false;
+        renumbered.existing_pr = Some(PullRequest {
+            title: "[2/2] Edited by hand".to_string(),
+            ..make_pr(2, "feat-b", "feat-a")
+        });
+        let mut up_to_date = renumbered.clone();
+        up_to_date.bookmark_name = "feat-c".to_string();
+        up_to_date.existing_pr = Some(PullRequest {
+            title: "[3/3] PR for feat-c".to_string(),
+            ..make_pr(3, "feat-c", "feat-b")
+        });
+        plan.bookmark_plans.extend([renumbered, up_to_date]);
+        let stack = vec![
+            member("feat-a", "main"),
+            member("feat-b", "feat-a"),
+            member("feat-c", "feat-b"),
+        ];
+        for name in ["feat-a", "feat-b", "feat-c"] {
+            plan.stack_memberships
+                .insert(name.to_string(), stack.clone());
+        }
+
+        number_titles(&mut plan);
+
+        let titles: Vec<_> = plan.bookmark_plans.iter().map(|bp| &bp.title).collect();
+        assert_eq!(
+            titles,
+            [
+                "[1/3] feature a",
+                "[2/3] Edited by hand",
+                "[3/3] PR for feat-c"
+            ]
+        );
+        assert!(plan.bookmark_plans[1].needs_title_sync);
+        assert!(!plan.bookmark_plans[2].needs_title_sync);
+    }
+
+    #[test]
+    fn only_a_leading_position_is_stripped() {
+        assert_eq!(strip_position("[12/14] Fix"), "Fix");
+        assert_eq!(strip_position("[WIP] Fix"), "[WIP] Fix");
+        assert_eq!(strip_position("[1/] Fix"), "[1/] Fix");
+        assert_eq!(strip_position("Fix [1/2] later"), "Fix [1/2] later");
+    }
+
+    #[tokio::test]
+    async fn execute_aborts_push_when_remote_moved_since_planning() {
+        // The mock runner reports no remote bookmarks, as if feat-a were
+        // deleted on the remote and fetched after planning.
+        let (runner, push_calls) = MockJjRunner::new();
+        let jj = Jj::new(runner);
+        let forge = MockForge::new();
+        let env = test_comment_env();
+        let mut plan = single_create_plan(true);
+        plan.bookmark_plans[0].expected_remote_target = Some("abc123".to_string());
+
+        let result = execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await;
+
+        match result {
+            Err(SubmitError::RemoteMoved {
+                bookmark,
+                expected,
+                actual,
+                ..
+            }) => {
+                assert_eq!(bookmark, "feat-a");
+                assert_eq!(expected.as_deref(), Some("abc123"));
+                assert_eq!(actual, None);
+            }
+            other => panic!("expected RemoteMoved, got {other:?}"),
+        }
+        assert!(push_calls.lock().unwrap().is_empty());
+        assert!(forge.created_prs.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn execute_force_push_skips_remote_check() {
+        let (runner, push_calls) = MockJjRunner::new();
+        let jj = Jj::new(runner);
+        let forge = MockForge::new();
+        let mut plan = single_create_plan(true);
+        plan.bookmark_plans[0].expected_remote_target = Some("abc123".to_string());
+        plan.push_options.force = true;
+
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &test_comment_env(),
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(push_calls.lock().unwrap().len(), 1);
+        assert_eq!(forge.created_prs.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn execute_tracks_only_first_pushes() {
+        let tracks = Arc::new(Mutex::new(Vec::new()));
+        let (runner, push_calls) = MockJjRunner::new();
+        let jj = Jj::new(runner.with_tracks(&tracks));
+        let forge = MockForge::new();
+        let mut plan = single_create_plan(true);
+        let mut second = plan.bookmark_plans[0].clone();
+        second.bookmark_name = "feat-b".to_string();
+        second.base = "feat-a".to_string();
+        second.expected_remote_target = Some("abc123".to_string());
+        plan.bookmark_plans.push(second);
+        plan.push_options.force = true;
+
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &test_comment_env(),
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(push_calls.lock().unwrap().len(), 2);
+        assert_eq!(*tracks.lock().unwrap(), ["feat-a@origin"]);
+
+        tracks.lock().unwrap().clear();
+        plan.push_options.track = false;
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &test_comment_env(),
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
+        assert!(tracks.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn execute_missing_head_branch_blames_push() {
+        let (runner, _push_calls) = MockJjRunner::new();
+        let jj = Jj::new(runner);
+        let forge = MockForge::new().with_missing_branch("feat-a");
+        let env = test_comment_env();
+
+        let result = execute_submission_plan(
+            &single_create_plan(true),
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await;
+
+        match result {
+            Err(SubmitError::HeadBranchMissing { bookmark, cause }) => {
+                assert_eq!(bookmark, "feat-a");
+                assert!(cause.contains("pushing 'feat-a' to 'origin'"));
+            }
+            other => panic!("expected HeadBranchMissing, got {other:?}"),
+        }
+        assert!(forge.created_prs.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn execute_missing_head_branch_skipped_push_suggests_fetch() {
+        let (runner, _push_calls) = MockJjRunner::new();
+        let jj = Jj::new(runner);
+        let forge = MockForge::new().with_missing_branch("feat-a");
+        let env = test_comment_env();
+
+        let result = execute_submission_plan(
+            &single_create_plan(false),
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(SubmitError::HeadBranchMissing { ref cause, .. }) if cause.contains("jj git fetch")
+        ));
+    }
+
+    #[tokio::test]
+    async fn execute_missing_default_base_branch() {
+        let (runner, _push_calls) = MockJjRunner::new();
+        let jj = Jj::new(runner);
+        let forge = MockForge::new().with_missing_branch("main");
+        let env = test_comment_env();
+
+        let result = execute_submission_plan(
+            &single_create_plan(true),
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await;
+
+        match result {
+            Err(SubmitError::BaseBranchMissing { base, cause, .. }) => {
+                assert_eq!(base, "main");
+                assert!(cause.contains("default branch"));
+            }
+            other => panic!("expected BaseBranchMissing, got {other:?}"),
+        }
+        assert!(forge.created_prs.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn execute_stacked_base_missing_upstream_explains_fork() {
+        let (runner, _push_calls) = MockJjRunner::new();
+        let jj = Jj::new(runner);
+        let forge = MockForge::new().with_missing_branch("feat-a");
+        let env = test_comment_env();
+        let mut plan = single_create_plan(true);
+        plan.head_repo = ForgeRepoRef::new("me", "repo");
+        plan.bookmark_plans[0].bookmark_name = "feat-b".to_string();
+        plan.bookmark_plans[0].base = "feat-a".to_string();
+        assert!(
+            plan.to_string()
+                .contains("remote: origin, PRs on test/repo from me/repo")
+        );
+
+        let result = execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(SubmitError::BaseBranchMissing { ref cause, .. })
+                if cause.contains("pushed to the fork me/repo")
+        ));
+    }
+
+    #[tokio::test]
+    async fn execute_applies_retargets_after_pushes() {
+        let ops: OpLog = Arc::new(Mutex::new(Vec::new()));
+        let mut plan = single_create_plan(true);
+        plan.retargets = vec![Retarget {
+            bookmark_name: "feat-b".to_string(),
+            pr_number: 7,
+            from: "old".to_string(),
+            to: "feat-a".to_string(),
+        }];
+
+        let (runner, _push_calls) = MockJjRunner::new_with_ops(Arc::clone(&ops));
+        let jj = Jj::new(runner);
+        let forge = MockForge::new().with_ops(Arc::clone(&ops));
+        let env = test_comment_env();
+
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            *ops.lock().unwrap(),
+            vec![
+                Op::Push("feat-a".to_string()),
+                Op::CreatePr("feat-a".to_string()),
+                Op::BaseUpdate(7),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn execute_failed_create_writes_no_stack_comments() {
+        let mut plan = single_create_plan(true);
+        let mut second = plan.bookmark_plans[0].clone();
+        second.bookmark_name = "feat-b".to_string();
+        second.base = "feat-a".to_string();
+        plan.bookmark_plans.push(second);
+
+        let (runner, _push_calls) = MockJjRunner::new();
+        let jj = Jj::new(runner);
+        let forge = MockForge::new().with_failing_create("feat-b");
+        let env = test_comment_env();
+
+        let result = execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await;
+
+        let Err(SubmitError::Incomplete { source, actions }) = result else {
+            panic!("expected Incomplete, got {result:?}");
+        };
+        assert!(matches!(
+            *source,
+            SubmitError::PrCreateFailed { ref bookmark, .. } if bookmark == "feat-b"
+        ));
+        assert!(forge.created_comments.lock().unwrap().is_empty());
+        assert!(forge.updated_bodies.lock().unwrap().is_empty());
+
+        assert_eq!(
+            actions,
+            [
+                SubmitAction::Pushed {
+                    bookmark: "feat-a".to_string(),
+                    remote: "origin".to_string(),
+                },
+                SubmitAction::Pushed {
+                    bookmark: "feat-b".to_string(),
+                    remote: "origin".to_string(),
+                },
+                SubmitAction::CreatedPr {
+                    bookmark: "feat-a".to_string(),
+                    pr_number: 100,
+                    url: "https://github.com/test/repo/pull/100".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn execute_creates_prs_after_every_push() {
+        let ops: OpLog = Arc::new(Mutex::new(Vec::new()));
+        let mut plan = single_create_plan(true);
+        let mut second = plan.bookmark_plans[0].clone();
+        second.bookmark_name = "feat-b".to_string();
+        second.base = "feat-a".to_string();
+        plan.bookmark_plans.push(second);
+
+        let (runner, _push_calls) = MockJjRunner::new_with_ops(Arc::clone(&ops));
+        let jj = Jj::new(runner);
+        // The trunk PR fails, but the PR stacked on it does not wait for it.
+        let forge = MockForge::new()
+            .with_failing_create("feat-a")
+            .with_ops(Arc::clone(&ops));
+        let env = test_comment_env();
+
+        let result = execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await;
+
+        let Err(SubmitError::Incomplete { source, actions }) = result else {
+            panic!("expected Incomplete, got {result:?}");
+        };
+        assert!(matches!(
+            *source,
+            SubmitError::PrCreateFailed { ref bookmark, .. } if bookmark == "feat-a"
+        ));
+        assert!(actions.contains(&SubmitAction::CreatedPr {
+            bookmark: "feat-b".to_string(),
+            pr_number: 101,
+            url: "https://github.com/test/repo/pull/101".to_string(),
+        }));
+        assert_eq!(
+            ops.lock().unwrap()[..2],
+            [
+                Op::Push("feat-a".to_string()),
+                Op::Push("feat-b".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn continue_after_failure_runs_only_remaining_steps() {
+        let mut plan = single_create_plan(true);
+        let mut second = plan.bookmark_plans[0].clone();
+        second.bookmark_name = "feat-b".to_string();
+        second.base = "feat-a".to_string();
+        second.pr_options.labels = vec!["backend".to_string()];
+        plan.bookmark_plans.push(second);
+        let env = test_comment_env();
+
+        let (runner, _push_calls) = MockJjRunner::new();
+        let forge = MockForge::new().with_failing_create("feat-b");
+        let result = execute_submission_plan(
+            &plan,
+            &Jj::new(runner),
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await;
+        let Err(SubmitError::Incomplete { actions, .. }) = result else {
+            panic!("expected Incomplete, got {result:?}");
         };
 
-        let output = plan.to_string();
-        assert!(output.contains("2 bookmark(s)"));
-        assert!(output.contains("feat-a (base: main)"));
-        assert!(output.contains("create PR: \"feature a\""));
-        assert!(output.contains("push bookmark to origin"));
-        assert!(output.contains("update PR #42 base: main -> feat-a"));
+        // The plan survives the trip through the journal.
+        let json = serde_json::to_string(&plan).unwrap();
+        let mut plan: SubmissionPlan = serde_json::from_str(&json).unwrap();
+        plan.skip_done(&actions);
+        assert!(plan.to_string().contains("PR #100 already created"));
+
+        let (runner, push_calls) = MockJjRunner::new();
+        let forge = MockForge::new();
+        *forge.next_pr_number.lock().unwrap() = 101;
+        let result = execute_submission_plan(
+            &plan,
+            &Jj::new(runner),
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
+
+        assert!(push_calls.lock().unwrap().is_empty());
+        let created = forge.created_prs.lock().unwrap();
+        assert_eq!(created.len(), 1);
+        assert_eq!(created[0].head, "feat-b");
+        assert_eq!(
+            result
+                .stack_entries
+                .iter()
+                .map(|e| e.pr_url.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "https://github.com/test/repo/pull/100",
+                "https://github.com/test/repo/pull/101",
+            ]
+        );
+        assert_eq!(
+            *forge.added_labels.lock().unwrap(),
+            [(101, vec!["backend".to_string()])]
+        );
+    }
+
+    #[tokio::test]
+    async fn undo_closes_created_prs_and_restores_bases() {
+        let forge = MockForge::new();
+        let actions = [
+            SubmitAction::Pushed {
+                bookmark: "feat-a".to_string(),
+                remote: "origin".to_string(),
+            },
+            SubmitAction::UpdatedBase {
+                bookmark: "feat-a".to_string(),
+                pr_number: 5,
+                from: "old".to_string(),
+                to: "main".to_string(),
+            },
+            SubmitAction::CreatedPr {
+                bookmark: "feat-b".to_string(),
+                pr_number: 6,
+                url: "https://github.com/test/repo/pull/6".to_string(),
+            },
+        ];
+
+        let report = undo_actions(&forge, &actions).await;
+
+        assert!(report.to_error().is_none());
+        assert_eq!(*forge.closed_prs.lock().unwrap(), [6]);
+        assert_eq!(
+            *forge.updated_bases.lock().unwrap(),
+            [(5, "old".to_string())]
+        );
+        assert_eq!(report.remaining, actions[..1]);
+        assert_eq!(
+            report.to_string(),
+            "  ok      close PR #6 (feat-b)\n  ok      restore base of PR #5 to old\nLeft in \
+             place:\n  branch feat-a stays on origin\n"
+        );
     }
 
-    #[test]
-    fn plan_display_shows_sync_lines() {
-        let plan = SubmissionPlan {
-            bookmark_plans: vec![BookmarkPlan {
-                bookmark_name: "feat-a".to_string(),
-                base: "main".to_string(),
-                title: "feature a".to_string(),
-                body: Some("body text".to_string()),
-                existing_pr: Some(make_pr(42, "feat-a", "main")),
-                needs_push: true,
-                needs_create: false,
-                needs_base_update: false,
-                needs_title_sync: true,
-                needs_body_sync: true,
-            }],
-            remote: "origin".to_string(),
-            pr_mode: PrMode::Regular,
-            default_branch: "main".to_string(),
-        };
+    #[tokio::test]
+    async fn execute_failed_comment_continues_and_reports() {
+        let mut plan = single_create_plan(true);
+        for (name, base) in [("feat-b", "feat-a"), ("feat-c", "feat-b")] {
+            let mut next = plan.bookmark_plans[0].clone();
+            next.bookmark_name = name.to_string();
+            next.base = base.to_string();
+            plan.bookmark_plans.push(next);
+        }
 
-        let output = plan.to_string();
-        assert!(output.contains("sync PR #42 title from commits"));
-        assert!(output.contains("sync PR #42 body from commits"));
-        assert!(!output.contains("up to date"));
-    }
+        let (runner, _push_calls) = MockJjRunner::new();
+        let jj = Jj::new(runner);
+        // PRs are numbered from 100 in creation order; fail feat-b's comment.
+        let forge = MockForge::new().with_failing_comment(101);
+        let env = test_comment_env();
 
-    // -----------------------------------------------------------------------
-    // Phase 3 tests
-    // -----------------------------------------------------------------------
+        let result = execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
+
+        let commented: Vec<u64> = forge
+            .created_comments
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(pr, _)| *pr)
+            .collect();
+        assert_eq!(commented.len(), 2);
+        assert!(!commented.contains(&101));
+        assert!(matches!(
+            result.steps.to_error(),
+            Some(SubmitError::StepsFailed {
+                failed: 1,
+                total: 3
+            })
+        ));
+        let summary = result.steps.to_string();
+        assert!(summary.starts_with("Steps: 2 succeeded, 1 failed\n"));
+        assert!(summary.contains(
+            "FAILED  stack comment on PR #101 (retriable): failed to manage stack comment on PR \
+             #101: API error: rate limited"
+        ));
+        assert!(summary.ends_with("Run `stakk retry` to replay the failed steps.\n"));
+        assert!(matches!(
+            &result.steps.journal_steps()[..],
+            [JournalStep::StackComment { pr_number: 101, body }] if body.contains("STAKK_STACK")
+        ));
+    }
 
     #[tokio::test]
-    async fn execute_creates_new_prs() {
-        let plan = SubmissionPlan {
-            bookmark_plans: vec![
-                BookmarkPlan {
-                    bookmark_name: "feat-a".to_string(),
-                    base: "main".to_string(),
-                    title: "feature a".to_string(),
-                    body: None,
-                    existing_pr: None,
-                    needs_push: true,
-                    needs_create: true,
-                    needs_base_update: false,
-                    needs_title_sync: false,
-                    needs_body_sync: false,
-                },
-                BookmarkPlan {
-                    bookmark_name: "feat-b".to_string(),
-                    base: "feat-a".to_string(),
-                    title: "feature b".to_string(),
-                    body: None,
-                    existing_pr: None,
-                    needs_push: true,
-                    needs_create: true,
-                    needs_base_update: false,
-                    needs_title_sync: false,
-                    needs_body_sync: false,
-                },
-            ],
-            remote: "origin".to_string(),
-            pr_mode: PrMode::Regular,
-            default_branch: "main".to_string(),
+    async fn retry_journal_steps_updates_or_creates_stack_comments() {
+        let existing = Comment {
+            id: 900,
+            node_id: None,
+            body: "<!--- STAKK_STACK: e30= --->\nold".to_string(),
         };
+        let forge = MockForge::new()
+            .with_existing_comments(50, vec![existing])
+            .with_failing_comment(52);
+        let journal = [
+            JournalStep::StackComment {
+                pr_number: 50,
+                body: "updated".to_string(),
+            },
+            JournalStep::StackComment {
+                pr_number: 51,
+                body: "created".to_string(),
+            },
+            JournalStep::StackComment {
+                pr_number: 52,
+                body: "still failing".to_string(),
+            },
+            JournalStep::Retarget {
+                bookmark: "feat-c".to_string(),
+                pr_number: 53,
+                base: "main".to_string(),
+            },
+        ];
+
+        let report = retry_journal_steps(&forge, &journal).await;
+
+        assert_eq!(
+            *forge.updated_comments.lock().unwrap(),
+            [(900, "updated".to_string())]
+        );
+        assert_eq!(
+            *forge.created_comments.lock().unwrap(),
+            [(51, "created".to_string())]
+        );
+        assert_eq!(
+            *forge.updated_bases.lock().unwrap(),
+            [(53, "main".to_string())]
+        );
+        assert_eq!(report.failed(), 1);
+        assert_eq!(report.journal_steps(), [journal[2].clone()]);
+    }
+
+    #[tokio::test]
+    async fn execute_placement_none_writes_no_stack_info() {
+        let mut plan = single_create_plan(true);
+        let mut second = plan.bookmark_plans[0].clone();
+        second.bookmark_name = "feat-b".to_string();
+        second.base = "feat-a".to_string();
+        plan.bookmark_plans.push(second);
 
         let (runner, _push_calls) = MockJjRunner::new();
         let jj = Jj::new(runner);
         let forge = MockForge::new();
         let env = test_comment_env();
 
-        let result = execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Comment)
-            .await
-            .unwrap();
+        let result =
+            execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::None, false, &())
+                .await
+                .unwrap();
 
         assert_eq!(result.stack_entries.len(), 2);
-
-        let created = forge.created_prs.lock().unwrap();
-        assert_eq!(created.len(), 2);
-        assert_eq!(created[0].head, "feat-a");
-        assert_eq!(created[0].base, "main");
-        assert_eq!(created[1].head, "feat-b");
-        assert_eq!(created[1].base, "feat-a");
+        assert!(forge.created_comments.lock().unwrap().is_empty());
+        assert!(forge.updated_comments.lock().unwrap().is_empty());
+        assert!(forge.updated_bodies.lock().unwrap().is_empty());
     }
 
     #[tokio::test]
@@ -1881,14 +6679,31 @@ mod tests {
                 body: None,
                 existing_pr: Some(make_pr(42, "feat-a", "main")),
                 needs_push: true,
+                expected_remote_target: None,
                 needs_create: false,
                 needs_base_update: true,
                 needs_title_sync: false,
                 needs_body_sync: false,
+                pr_options: PrOptions::default(),
+                title_source: None,
+                needs_mark_ready: false,
+                change_id: None,
+                renamed_from: None,
+                issues: Vec::new(),
+                push_size: None,
+                checks: None,
+                reviews: None,
             }],
             remote: "origin".to_string(),
             pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
         };
 
         let (runner, _push_calls) = MockJjRunner::new();
@@ -1896,9 +6711,17 @@ mod tests {
         let forge = MockForge::new();
         let env = test_comment_env();
 
-        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Comment)
-            .await
-            .unwrap();
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
 
         let updated = forge.updated_bases.lock().unwrap();
         assert_eq!(updated.len(), 1);
@@ -1916,10 +6739,20 @@ mod tests {
                     body: None,
                     existing_pr: None,
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: true,
                     needs_base_update: false,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
                 BookmarkPlan {
                     bookmark_name: "feat-b".to_string(),
@@ -1928,15 +6761,32 @@ mod tests {
                     body: None,
                     existing_pr: None,
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: true,
                     needs_base_update: false,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
             ],
             remote: "origin".to_string(),
             pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
         };
 
         let (runner, _push_calls) = MockJjRunner::new();
@@ -1944,9 +6794,17 @@ mod tests {
         let forge = MockForge::new();
         let env = test_comment_env();
 
-        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Comment)
-            .await
-            .unwrap();
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
 
         let comments = forge.created_comments.lock().unwrap();
         // One stack comment per PR.
@@ -1967,6 +6825,7 @@ mod tests {
                     bookmark_name: "old".to_string(),
                     pr_url: "https://example.com/1".to_string(),
                     pr_number: 1,
+                    repo: None,
                 }],
             },
             &StackCommentContext {
@@ -1974,6 +6833,7 @@ mod tests {
                     bookmark_name: "old".to_string(),
                     pr_url: "https://example.com/1".to_string(),
                     pr_number: 1,
+                    pr_ref: "#1".to_string(),
                     title: "old feature".to_string(),
                     base: "main".to_string(),
                     is_draft: false,
@@ -1998,10 +6858,20 @@ mod tests {
                     body: None,
                     existing_pr: Some(make_pr(50, "feat-a", "main")),
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: false,
                     needs_base_update: false,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
                 BookmarkPlan {
                     bookmark_name: "feat-b".to_string(),
@@ -2010,39 +6880,337 @@ mod tests {
                     body: None,
                     existing_pr: None,
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: true,
                     needs_base_update: false,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
+                },
+            ],
+            remote: "origin".to_string(),
+            pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
+        };
+
+        let (runner, _push_calls) = MockJjRunner::new();
+        let jj = Jj::new(runner);
+        let forge = MockForge::new().with_existing_comments(
+            50,
+            vec![Comment {
+                id: 999,
+                node_id: None,
+                body: existing_comment_body,
+            }],
+        );
+
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
+
+        // Should have updated the existing comment on PR #50, not created a
+        // new one. A new comment is created for the second PR.
+        let created = forge.created_comments.lock().unwrap();
+        assert_eq!(created.len(), 1);
+
+        let updated = forge.updated_comments.lock().unwrap();
+        assert_eq!(updated.len(), 1);
+        assert_eq!(updated[0].0, 999);
+    }
+
+    /// Two existing PRs, each with a stack comment carrying a node ID.
+    fn two_pr_plan_with_comments() -> (SubmissionPlan, Vec<(u64, Comment)>) {
+        let plan = SubmissionPlan {
+            bookmark_plans: vec![
+                BookmarkPlan {
+                    bookmark_name: "feat-a".to_string(),
+                    base: "main".to_string(),
+                    title: "feature a".to_string(),
+                    body: None,
+                    existing_pr: Some(make_pr(50, "feat-a", "main")),
+                    needs_push: true,
+                    expected_remote_target: None,
+                    needs_create: false,
+                    needs_base_update: false,
+                    needs_title_sync: false,
+                    needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
+                },
+                BookmarkPlan {
+                    bookmark_name: "feat-b".to_string(),
+                    base: "feat-a".to_string(),
+                    title: "feature b".to_string(),
+                    body: None,
+                    existing_pr: Some(make_pr(51, "feat-b", "feat-a")),
+                    needs_push: true,
+                    expected_remote_target: None,
+                    needs_create: false,
+                    needs_base_update: false,
+                    needs_title_sync: false,
+                    needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
             ],
             remote: "origin".to_string(),
             pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
+        };
+        let comment = |id: u64| Comment {
+            id,
+            node_id: Some(format!("IC_{id}")),
+            body: "<!--- STAKK_STACK: e30= --->\nold".to_string(),
+        };
+        (plan, vec![(50, comment(900)), (51, comment(901))])
+    }
+
+    #[tokio::test]
+    async fn execute_batches_comment_edits_when_supported() {
+        let (plan, comments) = two_pr_plan_with_comments();
+        let (runner, _push_calls) = MockJjRunner::new();
+        let jj = Jj::new(runner);
+        let mut forge = MockForge::new().with_batch_support();
+        for (pr, comment) in comments {
+            forge = forge.with_existing_comments(pr, vec![comment]);
+        }
+        let env = test_comment_env();
+
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
+
+        let batches = forge.batched_edits.lock().unwrap();
+        assert_eq!(batches.len(), 1);
+        let mut ids = batches[0].clone();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![900, 901]);
+        assert!(forge.updated_comments.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn execute_falls_back_to_rest_without_batch_support() {
+        let (plan, comments) = two_pr_plan_with_comments();
+        let (runner, _push_calls) = MockJjRunner::new();
+        let jj = Jj::new(runner);
+        let mut forge = MockForge::new();
+        for (pr, comment) in comments {
+            forge = forge.with_existing_comments(pr, vec![comment]);
+        }
+        let env = test_comment_env();
+
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
+
+        assert!(forge.batched_edits.lock().unwrap().is_empty());
+        assert_eq!(forge.updated_comments.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn execute_shared_root_comment_keeps_other_stack() {
+        let (mut plan, _) = two_pr_plan_with_comments();
+        let member = |name: &str, base: &str| StackMember {
+            bookmark_name: name.to_string(),
+            base: base.to_string(),
+        };
+        plan.stack_memberships = HashMap::from([
+            (
+                "feat-a".to_string(),
+                vec![
+                    member("feat-a", "main"),
+                    member("feat-b", "feat-a"),
+                    member("feat-c", "feat-a"),
+                ],
+            ),
+            (
+                "feat-b".to_string(),
+                vec![member("feat-a", "main"), member("feat-b", "feat-a")],
+            ),
+        ]);
+
+        // A previous run submitted the sibling stack feat-a <- feat-c.
+        let previous = StackCommentData {
+            version: 0,
+            stack: vec![
+                StackEntry {
+                    bookmark_name: "feat-a".to_string(),
+                    pr_url: "https://github.com/test/repo/pull/50".to_string(),
+                    pr_number: 50,
+                    repo: None,
+                },
+                StackEntry {
+                    bookmark_name: "feat-c".to_string(),
+                    pr_url: "https://github.com/test/repo/pull/60".to_string(),
+                    pr_number: 60,
+                    repo: None,
+                },
+            ],
+        };
+        let env = test_comment_env();
+        let template = env.get_template("stack_comment").unwrap();
+        let ctx = StackCommentContext {
+            stack: vec![],
+            stack_size: 2,
             default_branch: "main".to_string(),
+            current_bookmark: "feat-a".to_string(),
+            stakk_url: STAKK_REPO_URL.to_string(),
         };
+        let old_body = format_stack_comment(&previous, &ctx, &template).unwrap();
+
+        let (runner, _push_calls) = MockJjRunner::new();
+        let jj = Jj::new(runner);
+        let forge = MockForge::new().with_existing_comments(
+            50,
+            vec![Comment {
+                id: 900,
+                node_id: None,
+                body: old_body,
+            }],
+        );
+
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
+
+        let updated = forge.updated_comments.lock().unwrap();
+        assert_eq!(updated.len(), 1);
+        let body = &updated[0].1;
+        let merged = parse_stack_comment(body).unwrap();
+        let names: Vec<_> = merged
+            .stack
+            .iter()
+            .map(|e| e.bookmark_name.as_str())
+            .collect();
+        assert_eq!(names, vec!["feat-a", "feat-b", "feat-c"]);
+        assert!(body.contains("pull/60"));
+
+        // feat-b is not on the sibling stack, so its comment lists only its
+        // own stack.
+        let created = forge.created_comments.lock().unwrap();
+        assert_eq!(created.len(), 1);
+        assert!(!created[0].1.contains("pull/60"));
+    }
+
+    #[tokio::test]
+    async fn execute_skips_unchanged_stack_comments() {
+        let (plan, _) = two_pr_plan_with_comments();
+        let env = test_comment_env();
+
+        // First run creates the comments.
+        let (runner, _push_calls) = MockJjRunner::new();
+        let jj = Jj::new(runner);
+        let forge = MockForge::new();
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
+        let created = forge.created_comments.lock().unwrap().clone();
+        assert_eq!(created.len(), 2);
 
+        // Second run finds identical comments and writes nothing.
         let (runner, _push_calls) = MockJjRunner::new();
         let jj = Jj::new(runner);
-        let forge = MockForge::new().with_existing_comments(
-            50,
-            vec![Comment {
-                id: 999,
-                body: existing_comment_body,
-            }],
-        );
-
-        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Comment)
-            .await
-            .unwrap();
-
-        // Should have updated the existing comment on PR #50, not created a
-        // new one. A new comment is created for the second PR.
-        let created = forge.created_comments.lock().unwrap();
-        assert_eq!(created.len(), 1);
+        let mut forge = MockForge::new();
+        for (i, (pr_number, body)) in created.into_iter().enumerate() {
+            forge = forge.with_existing_comments(
+                pr_number,
+                vec![Comment {
+                    id: 900 + i as u64,
+                    node_id: None,
+                    body,
+                }],
+            );
+        }
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
 
-        let updated = forge.updated_comments.lock().unwrap();
-        assert_eq!(updated.len(), 1);
-        assert_eq!(updated[0].0, 999);
+        assert!(forge.created_comments.lock().unwrap().is_empty());
+        assert!(forge.updated_comments.lock().unwrap().is_empty());
     }
 
     #[tokio::test]
@@ -2056,10 +7224,20 @@ mod tests {
                     body: None,
                     existing_pr: None,
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: true,
                     needs_base_update: false,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
                 BookmarkPlan {
                     bookmark_name: "feat-b".to_string(),
@@ -2068,15 +7246,32 @@ mod tests {
                     body: None,
                     existing_pr: None,
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: true,
                     needs_base_update: false,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
             ],
             remote: "my-remote".to_string(),
             pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
         };
 
         let (runner, push_calls) = MockJjRunner::new();
@@ -2084,9 +7279,17 @@ mod tests {
         let forge = MockForge::new();
         let env = test_comment_env();
 
-        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Comment)
-            .await
-            .unwrap();
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
 
         let calls = push_calls.lock().unwrap();
         assert_eq!(calls.len(), 2);
@@ -2104,14 +7307,31 @@ mod tests {
                 body: None,
                 existing_pr: None,
                 needs_push: true,
+                expected_remote_target: None,
                 needs_create: true,
                 needs_base_update: false,
                 needs_title_sync: false,
                 needs_body_sync: false,
+                pr_options: PrOptions::default(),
+                title_source: None,
+                needs_mark_ready: false,
+                change_id: None,
+                renamed_from: None,
+                issues: Vec::new(),
+                push_size: None,
+                checks: None,
+                reviews: None,
             }],
             remote: "origin".to_string(),
             pr_mode: PrMode::Draft,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
         };
 
         let output = plan.to_string();
@@ -2131,14 +7351,31 @@ mod tests {
                 body: None,
                 existing_pr: None,
                 needs_push: true,
+                expected_remote_target: None,
                 needs_create: true,
                 needs_base_update: false,
                 needs_title_sync: false,
                 needs_body_sync: false,
+                pr_options: PrOptions::default(),
+                title_source: None,
+                needs_mark_ready: false,
+                change_id: None,
+                renamed_from: None,
+                issues: Vec::new(),
+                push_size: None,
+                checks: None,
+                reviews: None,
             }],
             remote: "origin".to_string(),
             pr_mode: PrMode::Draft,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
         };
 
         let (runner, _push_calls) = MockJjRunner::new();
@@ -2146,9 +7383,17 @@ mod tests {
         let forge = MockForge::new();
         let env = test_comment_env();
 
-        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Comment)
-            .await
-            .unwrap();
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
 
         let created = forge.created_prs.lock().unwrap();
         assert_eq!(created.len(), 1);
@@ -2165,14 +7410,31 @@ mod tests {
                 body: Some("updated body".to_string()),
                 existing_pr: Some(make_pr(42, "feat-a", "main")),
                 needs_push: true,
+                expected_remote_target: None,
                 needs_create: false,
                 needs_base_update: false,
                 needs_title_sync: true,
                 needs_body_sync: true,
+                pr_options: PrOptions::default(),
+                title_source: None,
+                needs_mark_ready: false,
+                change_id: None,
+                renamed_from: None,
+                issues: Vec::new(),
+                push_size: None,
+                checks: None,
+                reviews: None,
             }],
             remote: "origin".to_string(),
             pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
         };
 
         let (runner, _push_calls) = MockJjRunner::new();
@@ -2180,9 +7442,17 @@ mod tests {
         let forge = MockForge::new();
         let env = test_comment_env();
 
-        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Comment)
-            .await
-            .unwrap();
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
 
         let updated_titles = forge.updated_titles.lock().unwrap();
         assert_eq!(updated_titles.len(), 1);
@@ -2191,6 +7461,67 @@ mod tests {
         let updated_bodies = forge.updated_bodies.lock().unwrap();
         assert_eq!(updated_bodies.len(), 1);
         assert_eq!(updated_bodies[0], (42, "updated body".to_string()));
+
+        // Title and body go out in a single request.
+        assert_eq!(*forge.pr_updates.lock().unwrap(), vec![42]);
+    }
+
+    #[tokio::test]
+    async fn execute_body_sync_keeps_links_section() {
+        let links = splice_links_into_body("old body", "- Depends on #41\n");
+        let plan = SubmissionPlan {
+            bookmark_plans: vec![BookmarkPlan {
+                bookmark_name: "feat-a".to_string(),
+                base: "main".to_string(),
+                title: "title".to_string(),
+                body: Some("updated body".to_string()),
+                existing_pr: Some(make_pr_with_body(42, "feat-a", "main", &links)),
+                needs_push: false,
+                expected_remote_target: None,
+                needs_create: false,
+                needs_base_update: false,
+                needs_title_sync: false,
+                needs_body_sync: true,
+                pr_options: PrOptions::default(),
+                title_source: None,
+                needs_mark_ready: false,
+                change_id: None,
+                renamed_from: None,
+                issues: Vec::new(),
+                push_size: None,
+                checks: None,
+                reviews: None,
+            }],
+            remote: "origin".to_string(),
+            pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
+        };
+
+        let (runner, _push_calls) = MockJjRunner::new();
+        let jj = Jj::new(runner);
+        let forge = MockForge::new();
+        let env = test_comment_env();
+
+        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::None, false, &())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *forge.updated_bodies.lock().unwrap(),
+            [(
+                42,
+                "updated body\n\n<!-- STAKK_LINKS_START -->\n- Depends on #41\n<!-- \
+                 STAKK_LINKS_END -->\n"
+                    .to_string()
+            )]
+        );
     }
 
     #[tokio::test]
@@ -2203,14 +7534,31 @@ mod tests {
                 body: None,
                 existing_pr: Some(make_pr(42, "feat-a", "main")),
                 needs_push: true,
+                expected_remote_target: None,
                 needs_create: false,
                 needs_base_update: false,
                 needs_title_sync: true,
                 needs_body_sync: true,
+                pr_options: PrOptions::default(),
+                title_source: None,
+                needs_mark_ready: false,
+                change_id: None,
+                renamed_from: None,
+                issues: Vec::new(),
+                push_size: None,
+                checks: None,
+                reviews: None,
             }],
             remote: "origin".to_string(),
             pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
         };
 
         let (runner, _push_calls) = MockJjRunner::new();
@@ -2218,9 +7566,17 @@ mod tests {
         let forge = MockForge::new();
         let env = test_comment_env();
 
-        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Comment)
-            .await
-            .unwrap();
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
 
         let updated_titles = forge.updated_titles.lock().unwrap();
         assert_eq!(updated_titles[0], (42, "title only".to_string()));
@@ -2240,14 +7596,31 @@ mod tests {
                 body: Some("body".to_string()),
                 existing_pr: Some(make_pr(42, "feat-a", "main")),
                 needs_push: true,
+                expected_remote_target: None,
                 needs_create: false,
                 needs_base_update: false,
                 needs_title_sync: false,
                 needs_body_sync: false,
+                pr_options: PrOptions::default(),
+                title_source: None,
+                needs_mark_ready: false,
+                change_id: None,
+                renamed_from: None,
+                issues: Vec::new(),
+                push_size: None,
+                checks: None,
+                reviews: None,
             }],
             remote: "origin".to_string(),
             pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
         };
 
         let (runner, _push_calls) = MockJjRunner::new();
@@ -2255,9 +7628,17 @@ mod tests {
         let forge = MockForge::new();
         let env = test_comment_env();
 
-        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Comment)
-            .await
-            .unwrap();
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
 
         let updated_titles = forge.updated_titles.lock().unwrap();
         assert!(updated_titles.is_empty());
@@ -2274,10 +7655,20 @@ mod tests {
                     body: Some("new commit body".to_string()),
                     existing_pr: Some(make_pr(10, "feat-a", "main")),
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: false,
                     needs_base_update: false,
                     needs_title_sync: false,
                     needs_body_sync: true,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
                 BookmarkPlan {
                     bookmark_name: "feat-b".to_string(),
@@ -2286,15 +7677,32 @@ mod tests {
                     body: Some("commit body b".to_string()),
                     existing_pr: Some(make_pr(11, "feat-b", "feat-a")),
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: false,
                     needs_base_update: false,
                     needs_title_sync: false,
                     needs_body_sync: true,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
             ],
             remote: "origin".to_string(),
             pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
         };
 
         let (runner, _push_calls) = MockJjRunner::new();
@@ -2302,7 +7710,7 @@ mod tests {
         let forge = MockForge::new();
         let env = test_comment_env();
 
-        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Body)
+        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Body, false, &())
             .await
             .unwrap();
 
@@ -2662,10 +8070,20 @@ mod tests {
                     body: None,
                     existing_pr: None,
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: true,
                     needs_base_update: false,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
                 BookmarkPlan {
                     bookmark_name: "feat-b".to_string(),
@@ -2674,15 +8092,32 @@ mod tests {
                     body: None,
                     existing_pr: None,
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: true,
                     needs_base_update: false,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
             ],
             remote: "origin".to_string(),
             pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
         };
 
         let (runner, _push_calls) = MockJjRunner::new();
@@ -2690,7 +8125,7 @@ mod tests {
         let forge = MockForge::new();
         let env = test_comment_env();
 
-        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Body)
+        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Body, false, &())
             .await
             .unwrap();
 
@@ -2712,9 +8147,108 @@ mod tests {
         assert_eq!(created_comments.len(), 0);
     }
 
+    fn two_new_prs_plan() -> SubmissionPlan {
+        let new_pr = |bookmark: &str, base: &str| BookmarkPlan {
+            bookmark_name: bookmark.to_string(),
+            base: base.to_string(),
+            title: format!("{bookmark} title"),
+            body: Some("user text".to_string()),
+            existing_pr: None,
+            needs_push: true,
+            expected_remote_target: None,
+            needs_create: true,
+            needs_base_update: false,
+            needs_title_sync: false,
+            needs_body_sync: false,
+            pr_options: PrOptions::default(),
+            title_source: None,
+            needs_mark_ready: false,
+            change_id: None,
+            renamed_from: None,
+            issues: Vec::new(),
+            push_size: None,
+            checks: None,
+            reviews: None,
+        };
+        SubmissionPlan {
+            bookmark_plans: vec![new_pr("feat-a", "main"), new_pr("feat-b", "feat-a")],
+            remote: "origin".to_string(),
+            pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
+            default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
+        }
+    }
+
+    #[tokio::test]
+    async fn execute_link_prs_writes_links_section() {
+        let plan = two_new_prs_plan();
+        let (runner, _push_calls) = MockJjRunner::new();
+        let jj = Jj::new(runner);
+        let forge = MockForge::new();
+        let env = test_comment_env();
+
+        let result =
+            execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Comment, true, &())
+                .await
+                .unwrap();
+
+        let a = result.stack_entries[0].pr_number;
+        let b = result.stack_entries[1].pr_number;
+        let updated_bodies = forge.updated_bodies.lock().unwrap();
+        assert_eq!(
+            *updated_bodies,
+            [
+                (
+                    a,
+                    format!(
+                        "user text\n\n<!-- STAKK_LINKS_START -->\n- Followed by #{b}\n<!-- \
+                         STAKK_LINKS_END -->\n"
+                    )
+                ),
+                (
+                    b,
+                    format!(
+                        "user text\n\n<!-- STAKK_LINKS_START -->\n- Depends on #{a}\n<!-- \
+                         STAKK_LINKS_END -->\n"
+                    )
+                ),
+            ]
+        );
+        assert_eq!(result.steps.failed(), 0);
+        // The stack comments are still written.
+        assert_eq!(forge.created_comments.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn execute_link_prs_in_body_mode_shares_the_stack_update() {
+        let plan = two_new_prs_plan();
+        let (runner, _push_calls) = MockJjRunner::new();
+        let jj = Jj::new(runner);
+        let forge = MockForge::new();
+        let env = test_comment_env();
+
+        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Body, true, &())
+            .await
+            .unwrap();
+
+        let updated_bodies = forge.updated_bodies.lock().unwrap();
+        assert_eq!(updated_bodies.len(), 2);
+        for (_, body) in updated_bodies.iter() {
+            assert!(body.starts_with("user text\n"), "{body}");
+            assert!(body.contains("STAKK_BODY_START"), "{body}");
+            assert!(body.contains("STAKK_LINKS_START"), "{body}");
+        }
+    }
+
     #[tokio::test]
     async fn execute_body_mode_updates_existing_fence() {
-        use crate::forge::comment::splice_stack_into_body;
+        use crate::forge::markup::splice_stack_into_body;
 
         let existing_body = splice_stack_into_body("Original PR body", "old stack content");
         let plan = SubmissionPlan {
@@ -2726,10 +8260,20 @@ mod tests {
                     body: None,
                     existing_pr: Some(make_pr_with_body(50, "feat-a", "main", &existing_body)),
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: false,
                     needs_base_update: false,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
                 BookmarkPlan {
                     bookmark_name: "feat-b".to_string(),
@@ -2738,15 +8282,32 @@ mod tests {
                     body: None,
                     existing_pr: None,
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: true,
                     needs_base_update: false,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
             ],
             remote: "origin".to_string(),
             pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
         };
 
         let (runner, _push_calls) = MockJjRunner::new();
@@ -2754,7 +8315,7 @@ mod tests {
         let forge = MockForge::new();
         let env = test_comment_env();
 
-        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Body)
+        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Body, false, &())
             .await
             .unwrap();
 
@@ -2787,6 +8348,7 @@ mod tests {
                     bookmark_name: "feat-a".to_string(),
                     pr_url: "https://example.com/1".to_string(),
                     pr_number: 50,
+                    repo: None,
                 }],
             },
             &StackCommentContext {
@@ -2794,6 +8356,7 @@ mod tests {
                     bookmark_name: "feat-a".to_string(),
                     pr_url: "https://example.com/1".to_string(),
                     pr_number: 50,
+                    pr_ref: "#50".to_string(),
                     title: "feature a".to_string(),
                     base: "main".to_string(),
                     is_draft: false,
@@ -2818,10 +8381,20 @@ mod tests {
                     body: None,
                     existing_pr: Some(make_pr_with_body(50, "feat-a", "main", "Plain body")),
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: false,
                     needs_base_update: false,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
                 BookmarkPlan {
                     bookmark_name: "feat-b".to_string(),
@@ -2830,15 +8403,32 @@ mod tests {
                     body: None,
                     existing_pr: None,
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: true,
                     needs_base_update: false,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
             ],
             remote: "origin".to_string(),
             pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
         };
 
         let (runner, _push_calls) = MockJjRunner::new();
@@ -2847,11 +8437,12 @@ mod tests {
             50,
             vec![Comment {
                 id: 999,
+                node_id: None,
                 body: old_comment_body,
             }],
         );
 
-        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Body)
+        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Body, false, &())
             .await
             .unwrap();
 
@@ -2868,7 +8459,7 @@ mod tests {
 
     #[tokio::test]
     async fn execute_comment_mode_migration_strips_body() {
-        use crate::forge::comment::splice_stack_into_body;
+        use crate::forge::markup::splice_stack_into_body;
 
         // PR has a fenced section in the body (from previous body mode).
         let body_with_fence = splice_stack_into_body("Original PR body", "old stack content");
@@ -2881,10 +8472,20 @@ mod tests {
                     body: None,
                     existing_pr: Some(make_pr_with_body(50, "feat-a", "main", &body_with_fence)),
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: false,
                     needs_base_update: false,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
                 BookmarkPlan {
                     bookmark_name: "feat-b".to_string(),
@@ -2893,15 +8494,32 @@ mod tests {
                     body: None,
                     existing_pr: None,
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: true,
                     needs_base_update: false,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
             ],
             remote: "origin".to_string(),
             pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
         };
 
         let (runner, _push_calls) = MockJjRunner::new();
@@ -2911,9 +8529,17 @@ mod tests {
         let forge = MockForge::new();
         let env = test_comment_env();
 
-        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Comment)
-            .await
-            .unwrap();
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
 
         // Should have created comments for both PRs.
         let created_comments = forge.created_comments.lock().unwrap();
@@ -2945,14 +8571,31 @@ mod tests {
                 body: None,
                 existing_pr: None,
                 needs_push: true,
+                expected_remote_target: None,
                 needs_create: true,
                 needs_base_update: false,
                 needs_title_sync: false,
                 needs_body_sync: false,
+                pr_options: PrOptions::default(),
+                title_source: None,
+                needs_mark_ready: false,
+                change_id: None,
+                renamed_from: None,
+                issues: Vec::new(),
+                push_size: None,
+                checks: None,
+                reviews: None,
             }],
             remote: "origin".to_string(),
             pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
         };
 
         let (runner, _push_calls) = MockJjRunner::new();
@@ -2960,9 +8603,17 @@ mod tests {
         let forge = MockForge::new();
         let env = test_comment_env();
 
-        let result = execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Comment)
-            .await
-            .unwrap();
+        let result = execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(result.stack_entries.len(), 1);
 
@@ -2990,6 +8641,7 @@ mod tests {
                     bookmark_name: "feat-a".to_string(),
                     pr_url: "https://example.com/1".to_string(),
                     pr_number: 50,
+                    repo: None,
                 }],
             },
             &StackCommentContext {
@@ -2997,6 +8649,7 @@ mod tests {
                     bookmark_name: "feat-a".to_string(),
                     pr_url: "https://example.com/1".to_string(),
                     pr_number: 50,
+                    pr_ref: "#50".to_string(),
                     title: "feature a".to_string(),
                     base: "main".to_string(),
                     is_draft: false,
@@ -3020,14 +8673,31 @@ mod tests {
                 body: None,
                 existing_pr: Some(make_pr(50, "feat-a", "main")),
                 needs_push: true,
+                expected_remote_target: None,
                 needs_create: false,
                 needs_base_update: false,
                 needs_title_sync: false,
                 needs_body_sync: false,
+                pr_options: PrOptions::default(),
+                title_source: None,
+                needs_mark_ready: false,
+                change_id: None,
+                renamed_from: None,
+                issues: Vec::new(),
+                push_size: None,
+                checks: None,
+                reviews: None,
             }],
             remote: "origin".to_string(),
             pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
         };
 
         let (runner, _push_calls) = MockJjRunner::new();
@@ -3036,13 +8706,22 @@ mod tests {
             50,
             vec![Comment {
                 id: 999,
+                node_id: None,
                 body: old_comment_body,
             }],
         );
 
-        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Comment)
-            .await
-            .unwrap();
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
 
         // Old stack comment should be deleted.
         let deleted = forge.deleted_comments.lock().unwrap();
@@ -3056,7 +8735,7 @@ mod tests {
 
     #[tokio::test]
     async fn execute_single_bookmark_cleans_up_old_body_fence() {
-        use crate::forge::comment::splice_stack_into_body;
+        use crate::forge::markup::splice_stack_into_body;
 
         let body_with_fence = splice_stack_into_body("Original PR body", "old stack content");
         let plan = SubmissionPlan {
@@ -3067,14 +8746,31 @@ mod tests {
                 body: None,
                 existing_pr: Some(make_pr_with_body(50, "feat-a", "main", &body_with_fence)),
                 needs_push: true,
+                expected_remote_target: None,
                 needs_create: false,
                 needs_base_update: false,
                 needs_title_sync: false,
                 needs_body_sync: false,
+                pr_options: PrOptions::default(),
+                title_source: None,
+                needs_mark_ready: false,
+                change_id: None,
+                renamed_from: None,
+                issues: Vec::new(),
+                push_size: None,
+                checks: None,
+                reviews: None,
             }],
             remote: "origin".to_string(),
             pr_mode: PrMode::Regular,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
         };
 
         let (runner, _push_calls) = MockJjRunner::new();
@@ -3082,7 +8778,7 @@ mod tests {
         let forge = MockForge::new();
         let env = test_comment_env();
 
-        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Body)
+        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Body, false, &())
             .await
             .unwrap();
 
@@ -3119,10 +8815,20 @@ mod tests {
                     body: None,
                     existing_pr: Some(make_pr(10, "feat-a", "feat-b")),
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: false,
                     needs_base_update: true,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
                 BookmarkPlan {
                     bookmark_name: "feat-b".to_string(),
@@ -3131,15 +8837,32 @@ mod tests {
                     body: None,
                     existing_pr: Some(make_pr(11, "feat-b", "main")),
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: false,
                     needs_base_update: true,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
             ],
             remote: "origin".to_string(),
             pr_mode: PrMode::Draft,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
         };
 
         let (runner, _push_calls) = MockJjRunner::new_with_ops(Arc::clone(&ops));
@@ -3150,9 +8873,17 @@ mod tests {
             .with_ops(Arc::clone(&ops));
         let env = test_comment_env();
 
-        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Comment)
-            .await
-            .unwrap();
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
 
         let ops = ops.lock().unwrap();
         assert_eq!(
@@ -3182,10 +8913,20 @@ mod tests {
                     body: None,
                     existing_pr: Some(make_pr(10, "feat-a", "feat-c")),
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: false,
                     needs_base_update: true,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
                 BookmarkPlan {
                     bookmark_name: "feat-b".to_string(),
@@ -3194,10 +8935,20 @@ mod tests {
                     body: None,
                     existing_pr: Some(make_pr(11, "feat-b", "main")),
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: false,
                     needs_base_update: true,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
                 BookmarkPlan {
                     bookmark_name: "feat-c".to_string(),
@@ -3206,15 +8957,32 @@ mod tests {
                     body: None,
                     existing_pr: Some(make_pr(12, "feat-c", "feat-a")),
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: false,
                     needs_base_update: true,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
             ],
             remote: "origin".to_string(),
             pr_mode: PrMode::Draft,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
         };
 
         let (runner, _push_calls) = MockJjRunner::new_with_ops(Arc::clone(&ops));
@@ -3226,9 +8994,17 @@ mod tests {
             .with_ops(Arc::clone(&ops));
         let env = test_comment_env();
 
-        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Comment)
-            .await
-            .unwrap();
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
 
         let ops = ops.lock().unwrap();
         assert_eq!(
@@ -3259,10 +9035,20 @@ mod tests {
                     body: None,
                     existing_pr: Some(make_pr(10, "feat-a", "feat-b")),
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: false,
                     needs_base_update: true,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
                 BookmarkPlan {
                     bookmark_name: "feat-b".to_string(),
@@ -3271,15 +9057,32 @@ mod tests {
                     body: None,
                     existing_pr: None,
                     needs_push: true,
+                    expected_remote_target: None,
                     needs_create: true,
                     needs_base_update: false,
                     needs_title_sync: false,
                     needs_body_sync: false,
+                    pr_options: PrOptions::default(),
+                    title_source: None,
+                    needs_mark_ready: false,
+                    change_id: None,
+                    renamed_from: None,
+                    issues: Vec::new(),
+                    push_size: None,
+                    checks: None,
+                    reviews: None,
                 },
             ],
             remote: "origin".to_string(),
             pr_mode: PrMode::Draft,
+            push_options: PushOptions::default(),
+            milestone: None,
+            project: None,
             default_branch: "main".to_string(),
+            stack_memberships: HashMap::new(),
+            retargets: vec![],
+            repo: ForgeRepoRef::new("test", "repo"),
+            head_repo: ForgeRepoRef::new("test", "repo"),
         };
 
         let (runner, _push_calls) = MockJjRunner::new_with_ops(Arc::clone(&ops));
@@ -3289,9 +9092,17 @@ mod tests {
             .with_ops(Arc::clone(&ops));
         let env = test_comment_env();
 
-        execute_submission_plan(&plan, &jj, &forge, &env, StackPlacement::Comment)
-            .await
-            .unwrap();
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
 
         let ops = ops.lock().unwrap();
         assert_eq!(
@@ -3305,4 +9116,234 @@ mod tests {
             "base update for feat-a must complete before feat-b is pushed"
         );
     }
+
+    #[tokio::test]
+    async fn execute_reorder_updates_bases_before_later_pushes_and_creates_last() {
+        // `feat-b` moved below `feat-a`, with a new `feat-n` between them:
+        // main <- feat-b <- feat-n <- feat-a, where `feat-b` was on `feat-a`.
+        let ops: OpLog = Arc::new(Mutex::new(Vec::new()));
+        let mut plan = single_create_plan(true);
+        let template = plan.bookmark_plans.remove(0);
+        let existing = |name: &str, base: &str, pr: PullRequest| BookmarkPlan {
+            bookmark_name: name.to_string(),
+            base: base.to_string(),
+            existing_pr: Some(pr),
+            needs_create: false,
+            needs_base_update: true,
+            ..template.clone()
+        };
+        plan.bookmark_plans = vec![
+            existing("feat-b", "main", make_pr(11, "feat-b", "feat-a")),
+            BookmarkPlan {
+                bookmark_name: "feat-n".to_string(),
+                base: "feat-b".to_string(),
+                ..template.clone()
+            },
+            existing("feat-a", "feat-n", make_pr(10, "feat-a", "main")),
+        ];
+
+        let (runner, _push_calls) = MockJjRunner::new_with_ops(Arc::clone(&ops));
+        let jj = Jj::new(runner);
+        let forge = MockForge::new()
+            .with_existing_pr("feat-a", make_pr(10, "feat-a", "main"))
+            .with_existing_pr("feat-b", make_pr(11, "feat-b", "feat-a"))
+            .with_ops(Arc::clone(&ops));
+        let env = test_comment_env();
+
+        execute_submission_plan(
+            &plan,
+            &jj,
+            &forge,
+            &env,
+            StackPlacement::Comment,
+            false,
+            &(),
+        )
+        .await
+        .unwrap();
+
+        let ops = ops.lock().unwrap();
+        assert_eq!(
+            *ops,
+            vec![
+                Op::Push("feat-b".to_string()),
+                Op::BaseUpdate(11),
+                Op::Push("feat-n".to_string()),
+                Op::Push("feat-a".to_string()),
+                Op::BaseUpdate(10),
+                Op::CreatePr("feat-n".to_string()),
+            ],
+            "each base update precedes the next push, and new PRs are created once every branch \
+             is pushed and every existing PR retargeted"
+        );
+    }
+
+    // -- CI checks --
+
+    fn check(name: &str, state: CheckState) -> CheckRun {
+        CheckRun {
+            name: name.to_string(),
+            state,
+        }
+    }
+
+    #[test]
+    fn summarize_checks_failure_wins_over_pending() {
+        let runs = vec![
+            (
+                "feat-a".to_string(),
+                vec![check("build", CheckState::Pending)],
+            ),
+            (
+                "feat-b".to_string(),
+                vec![
+                    check("build", CheckState::Passed),
+                    check("lint", CheckState::Failed),
+                ],
+            ),
+        ];
+        assert_eq!(
+            summarize_checks(&runs, false),
+            ChecksStatus::Failed(vec!["feat-b: lint".to_string()])
+        );
+    }
+
+    #[test]
+    fn describe_checks_counts_each_state() {
+        assert_eq!(describe_checks(&[]), "no checks");
+        let runs = [
+            check("build", CheckState::Passed),
+            check("lint", CheckState::Passed),
+            check("test", CheckState::Pending),
+        ];
+        assert_eq!(describe_checks(&runs), "2 passed, 1 pending");
+    }
+
+    #[test]
+    fn summarize_checks_without_runs_waits_for_registration() {
+        let runs = vec![("feat-a".to_string(), vec![])];
+        assert_eq!(
+            summarize_checks(&runs, true),
+            ChecksStatus::Pending(vec!["feat-a".to_string()])
+        );
+        assert_eq!(summarize_checks(&runs, false), ChecksStatus::Passed);
+    }
+
+    #[tokio::test]
+    async fn enable_auto_merge_only_on_prs_based_on_trunk() {
+        let forge = MockForge::new();
+        let plan = two_new_prs_plan();
+        let stack: Vec<StackEntry> = [("feat-a", 1), ("feat-b", 2)]
+            .into_iter()
+            .map(|(name, number)| StackEntry {
+                bookmark_name: name.to_string(),
+                pr_url: format!("https://github.com/test/repo/pull/{number}"),
+                pr_number: number,
+                repo: None,
+            })
+            .collect();
+
+        let enabled = enable_auto_merge(&forge, &plan, &stack, MergeMethod::Squash)
+            .await
+            .unwrap();
+
+        assert_eq!(enabled, [1]);
+        assert_eq!(
+            *forge.auto_merged.lock().unwrap(),
+            [(1, MergeMethod::Squash)]
+        );
+    }
+
+    #[tokio::test]
+    async fn umbrella_is_created_once_and_then_updated() {
+        let forge = MockForge::new();
+        let mut plan = single_create_plan(true);
+        let entry = |name: &str, number: u64| StackEntry {
+            bookmark_name: name.to_string(),
+            pr_url: format!("https://github.com/test/repo/pull/{number}"),
+            pr_number: number,
+            repo: None,
+        };
+
+        let (issue, created) = update_umbrella(&forge, &plan, &[entry("feat-a", 1)])
+            .await
+            .unwrap();
+        assert!(created);
+        assert_eq!(*forge.created_issues.lock().unwrap(), ["Stack: feature a"]);
+        assert!(issue.body.unwrap().contains("1. #1 feature a (`feat-a`)"));
+
+        let mut second = plan.bookmark_plans[0].clone();
+        second.bookmark_name = "feat-b".to_string();
+        second.title = "feature b".to_string();
+        plan.bookmark_plans.push(second);
+        let (issue, created) =
+            update_umbrella(&forge, &plan, &[entry("feat-a", 1), entry("feat-b", 2)])
+                .await
+                .unwrap();
+        assert!(!created);
+        assert_eq!(issue.number, 900);
+        let body = forge.issues.lock().unwrap()[0].body.clone().unwrap();
+        assert!(body.contains("stack of 2 pull requests"));
+        assert!(body.contains("2. #2 feature b (`feat-b`)"));
+        assert_eq!(forge.created_issues.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn issue_links_skip_stack_prs_and_existing_links() {
+        let forge = MockForge::new();
+        let mut plan = single_create_plan(true);
+        plan.bookmark_plans[0].issues = vec![2, 7];
+        let stack = [StackEntry {
+            bookmark_name: "feat-a".to_string(),
+            pr_url: "https://github.com/test/repo/pull/2".to_string(),
+            pr_number: 2,
+            repo: None,
+        }];
+
+        assert_eq!(link_issues(&forge, &plan, &stack).await.unwrap(), 1);
+        assert_eq!(link_issues(&forge, &plan, &stack).await.unwrap(), 0);
+        assert_eq!(*forge.issue_links.lock().unwrap(), [(7, 2)]);
+    }
+
+    fn zero_schedule() -> Schedule {
+        Schedule::fixed(Duration::ZERO).with_timeout(Duration::ZERO)
+    }
+
+    #[tokio::test]
+    async fn wait_for_checks_reports_failures() {
+        let forge = MockForge::new()
+            .with_check_runs("feat-a", vec![check("build", CheckState::Passed)])
+            .with_check_runs("feat-b", vec![check("test", CheckState::Failed)]);
+        let bookmarks = vec!["feat-a".to_string(), "feat-b".to_string()];
+
+        let result = wait_for_checks(&forge, &bookmarks, &zero_schedule(), |_| {}).await;
+
+        assert!(
+            matches!(result, Err(SubmitError::ChecksFailed { ref failed }) if failed == &["feat-b: test"])
+        );
+    }
+
+    #[tokio::test]
+    async fn wait_for_checks_times_out_on_pending() {
+        let forge =
+            MockForge::new().with_check_runs("feat-a", vec![check("build", CheckState::Pending)]);
+
+        let result =
+            wait_for_checks(&forge, &["feat-a".to_string()], &zero_schedule(), |_| {}).await;
+
+        assert!(matches!(
+            result,
+            Err(SubmitError::ChecksTimedOut { ref pending, .. }) if pending == &["feat-a: build"]
+        ));
+    }
+
+    #[tokio::test]
+    async fn wait_for_checks_passes_when_all_green() {
+        let forge =
+            MockForge::new().with_check_runs("feat-a", vec![check("build", CheckState::Passed)]);
+
+        wait_for_checks(&forge, &["feat-a".to_string()], &zero_schedule(), |_| {})
+            .await
+            .unwrap();
+    }
 }
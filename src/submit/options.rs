@@ -0,0 +1,177 @@
+//! Options that shape a submission, shared by the CLI and config file.
+
+use clap::ValueEnum;
+
+/// Whether new pull requests are created as regular or draft PRs.
+///
+/// This only affects newly created PRs. Existing PRs keep their
+/// current draft/ready state, except under `draft-above-first`, which marks
+/// a draft PR ready for review once it is at the bottom of its stack.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum PrMode {
+    /// Create pull requests as regular (non-draft) PRs.
+    #[default]
+    Regular,
+    /// Create pull requests as drafts.
+    Draft,
+    /// Create the bottom PR of the stack (the one based on the default
+    /// branch) as regular and every PR above it as a draft. As the PRs below
+    /// a draft merge and it moves to the bottom, it is marked ready for
+    /// review.
+    DraftAboveFirst,
+}
+
+impl std::fmt::Display for PrMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pv = self
+            .to_possible_value()
+            .expect("all variants have possible values");
+        f.write_str(pv.get_name())
+    }
+}
+
+/// Where a PR's title comes from when no title template is set.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum TitleSource {
+    /// The first line of the commit the bookmark points at, the newest in
+    /// its segment.
+    #[default]
+    Tip,
+    /// The first line of the oldest commit in the segment.
+    Root,
+    /// The bookmark name, prettified: `feat/login-page` becomes
+    /// `Login page`.
+    Bookmark,
+}
+
+impl TitleSource {
+    /// The source as named in the submission plan.
+    pub fn describe(self) -> &'static str {
+        match self {
+            Self::Tip => "the tip commit",
+            Self::Root => "the oldest commit",
+            Self::Bookmark => "the bookmark name",
+        }
+    }
+}
+
+impl std::fmt::Display for TitleSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pv = self
+            .to_possible_value()
+            .expect("all variants have possible values");
+        f.write_str(pv.get_name())
+    }
+}
+
+/// Controls whether existing PR titles and/or bodies are updated from
+/// commit descriptions on every submit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum SyncPrContent {
+    /// Do not sync. Title and body are only set on PR creation.
+    #[default]
+    None,
+    /// Sync only the PR title from the first line of the commit description.
+    Title,
+    /// Sync only the PR body from the commit description.
+    Body,
+    /// Sync both the PR title and body.
+    All,
+}
+
+impl std::fmt::Display for SyncPrContent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pv = self
+            .to_possible_value()
+            .expect("all variants have possible values");
+        f.write_str(pv.get_name())
+    }
+}
+
+/// Controls whether git commit trailers are stripped from PR bodies.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum TrailerHandling {
+    /// Leave trailers in the PR body verbatim.
+    #[default]
+    Keep,
+    /// Strip the trailer block (Signed-off-by, Co-authored-by, Refs, etc.)
+    /// from the PR body.
+    Strip,
+}
+
+impl std::fmt::Display for TrailerHandling {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pv = self
+            .to_possible_value()
+            .expect("all variants have possible values");
+        f.write_str(pv.get_name())
+    }
+}
+
+/// How generated PR bodies are combined with the repository's pull request
+/// template (`.github/pull_request_template.md` and the other locations
+/// GitHub recognizes).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum PrTemplateMerge {
+    /// Don't use the repository's PR template.
+    #[default]
+    Ignore,
+    /// Put the generated body in the template section named by
+    /// --pr-template-section, replacing its placeholder text.
+    Fill,
+    /// Put the generated body above the template.
+    Prepend,
+    /// Put the generated body below the template.
+    Append,
+}
+
+impl std::fmt::Display for PrTemplateMerge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pv = self
+            .to_possible_value()
+            .expect("all variants have possible values");
+        f.write_str(pv.get_name())
+    }
+}
+
+/// Options for one PR, read from `Stakk-*` trailers in the commit
+/// descriptions of its segment (`Stakk-Draft: true`, `Stakk-Reviewer: alice`,
+/// `Stakk-Label: backend`, `Stakk-Base: release`), so they travel with the
+/// change instead of the command line.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PrOptions {
+    /// Overrides `--pr-mode` when the PR is created.
+    pub draft: Option<bool>,
+    /// Users, or `org/team` slugs, asked to review the new PR.
+    pub reviewers: Vec<String>,
+    /// Labels added to the new PR.
+    pub labels: Vec<String>,
+    /// The branch the PR merges into, instead of the bookmark below it.
+    #[serde(default)]
+    pub base: Option<String>,
+}
@@ -1,3 +1,5 @@
+use crate::submit::options::PrOptions;
+
 /// Split a commit description into `(body, trailers)`.
 ///
 /// A trailer block is the last paragraph (separated from the rest by one
@@ -69,6 +71,194 @@ pub(crate) fn split_trailers(text: &str) -> (&str, Option<&str>) {
     (trimmed[..i].trim_end(), Some(last_paragraph))
 }
 
+/// Prefix of the trailers that set per-PR options.
+const STAKK_TRAILER_PREFIX: &str = "stakk-";
+
+/// Whether `line` is a `Stakk-*` trailer (the key is case-insensitive).
+pub(crate) fn is_stakk_trailer(line: &str) -> bool {
+    is_trailer_line(line)
+        && line
+            .get(..STAKK_TRAILER_PREFIX.len())
+            .is_some_and(|p| p.eq_ignore_ascii_case(STAKK_TRAILER_PREFIX))
+}
+
+/// `block` without the trailers stakk reads or writes itself (`Stakk-*`
+/// and `PR:`), or `None` if nothing else is left.
+pub(crate) fn without_stakk_trailers(block: &str) -> Option<String> {
+    let kept: Vec<&str> = block
+        .lines()
+        .filter(|l| !is_stakk_trailer(l) && !is_pr_trailer(l))
+        .collect();
+    if kept.iter().all(|l| l.trim().is_empty()) {
+        None
+    } else {
+        Some(kept.join("\n"))
+    }
+}
+
+/// Collect the `Stakk-*` trailers of `descriptions` into [`PrOptions`].
+///
+/// `Stakk-Reviewer` and `Stakk-Label` may repeat and take comma-separated
+/// values; duplicates are dropped. `Stakk-Draft` takes `true`/`false` (or
+/// `yes`/`no`) and `Stakk-Base` a branch name; for both the last one wins.
+/// Returns the offending line for an
+/// unknown `Stakk-*` key or an invalid draft value.
+pub(crate) fn parse_pr_options<'a>(
+    descriptions: impl IntoIterator<Item = &'a str>,
+) -> Result<PrOptions, String> {
+    let mut options = PrOptions::default();
+    for description in descriptions {
+        let Some(block) = split_trailers(description.trim()).1 else {
+            continue;
+        };
+        for line in block.lines().filter(|l| is_stakk_trailer(l)) {
+            let (key, value) = line.split_once(": ").expect("trailer lines contain ': '");
+            let value = value.trim();
+            let list = match key[STAKK_TRAILER_PREFIX.len()..]
+                .to_ascii_lowercase()
+                .as_str()
+            {
+                "draft" => {
+                    options.draft = Some(match value.to_ascii_lowercase().as_str() {
+                        "true" | "yes" => true,
+                        "false" | "no" => false,
+                        _ => return Err(line.to_string()),
+                    });
+                    continue;
+                }
+                "base" if !value.is_empty() => {
+                    options.base = Some(value.to_string());
+                    continue;
+                }
+                "reviewer" => &mut options.reviewers,
+                "label" => &mut options.labels,
+                _ => return Err(line.to_string()),
+            };
+            for item in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                if !list.iter().any(|existing| existing == item) {
+                    list.push(item.to_string());
+                }
+            }
+        }
+    }
+    Ok(options)
+}
+
+/// Whether `description` has a `Signed-off-by` line for `email`. Like the
+/// DCO checks it satisfies, any line counts, not just the trailer block;
+/// key and email compare case-insensitively.
+pub(crate) fn has_signoff(description: &str, email: &str) -> bool {
+    let email = format!("<{}>", email.to_ascii_lowercase());
+    description.lines().any(|line| {
+        line.split_once(": ").is_some_and(|(key, value)| {
+            key.eq_ignore_ascii_case("signed-off-by")
+                && value.trim_end().to_ascii_lowercase().ends_with(&email)
+        })
+    })
+}
+
+/// `description` with `Signed-off-by: {name} <{email}>` appended to its
+/// trailer block, or as a new one.
+pub(crate) fn with_signoff(description: &str, name: &str, email: &str) -> String {
+    let text = description.trim_end();
+    let signoff = format!("Signed-off-by: {name} <{email}>");
+    if text.is_empty() {
+        format!("{signoff}\n")
+    } else if split_trailers(text).1.is_some() {
+        format!("{text}\n{signoff}\n")
+    } else {
+        format!("{text}\n\n{signoff}\n")
+    }
+}
+
+/// Key of the trailer linking a change to its PR.
+const PR_TRAILER_KEY: &str = "PR";
+
+/// Whether `line` is a `PR:` trailer (the key is case-insensitive).
+fn is_pr_trailer(line: &str) -> bool {
+    line.split_once(": ")
+        .is_some_and(|(key, _)| key.eq_ignore_ascii_case(PR_TRAILER_KEY))
+}
+
+/// `description` with a `PR: {url}` trailer, or `None` if it already has
+/// one. A `PR:` trailer to another URL (a PR since closed and recreated) is
+/// replaced.
+pub(crate) fn with_pr_trailer(description: &str, url: &str) -> Option<String> {
+    let trailer = format!("{PR_TRAILER_KEY}: {url}");
+    let text = description.trim_end();
+    if text.lines().any(|line| line.trim_end() == trailer) {
+        return None;
+    }
+    let (body, block) = split_trailers(text);
+    let kept: Vec<&str> = block
+        .into_iter()
+        .flat_map(str::lines)
+        .filter(|line| !is_pr_trailer(line))
+        .collect();
+    Some(if text.is_empty() {
+        format!("{trailer}\n")
+    } else if kept.is_empty() {
+        format!("{body}\n\n{trailer}\n")
+    } else {
+        format!("{body}\n\n{}\n{trailer}\n", kept.join("\n"))
+    })
+}
+
+/// `description` with its subject line replaced by `title` and the text
+/// between the subject and the trailers by `body`. Parts passed as `None`
+/// are kept, and so are the trailers.
+pub(crate) fn amend_description(
+    description: &str,
+    title: Option<&str>,
+    body: Option<&str>,
+) -> String {
+    let (message, block) = split_trailers(description.trim_end());
+    let (subject, rest) = message.split_once('\n').unwrap_or((message, ""));
+    let subject = title.unwrap_or(subject).trim();
+    let rest = body.unwrap_or(rest).trim();
+    let mut amended = subject.to_string();
+    for part in [rest, block.unwrap_or_default()] {
+        if !part.is_empty() {
+            amended.push_str("\n\n");
+            amended.push_str(part);
+        }
+    }
+    amended.push('\n');
+    amended
+}
+
+/// The issue numbers `descriptions` reference as `#N`, sorted and
+/// deduplicated.
+///
+/// Like GitHub's autolinks, a reference must stand on its own: `#N` inside
+/// a word, an HTML entity (`&#8212;`), or another repository's reference
+/// (`owner/repo#N`) is not one.
+pub(crate) fn issue_references<'a>(descriptions: impl IntoIterator<Item = &'a str>) -> Vec<u64> {
+    let mut issues = Vec::new();
+    for description in descriptions {
+        for (i, _) in description.match_indices('#') {
+            let starts_word = description[..i]
+                .chars()
+                .next_back()
+                .is_none_or(|c| c.is_whitespace() || "([,:".contains(c));
+            let rest = &description[i + 1..];
+            let end = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let ends_word = !rest[end..].starts_with(|c: char| c.is_alphanumeric() || c == '_');
+            if starts_word
+                && ends_word
+                && let Ok(number) = rest[..end].parse()
+            {
+                issues.push(number);
+            }
+        }
+    }
+    issues.sort_unstable();
+    issues.dedup();
+    issues
+}
+
 /// Check if a line matches the git trailer format: `Key: value`.
 ///
 /// The key must start with an ASCII letter and contain only ASCII
@@ -196,4 +386,126 @@ mod tests {
         assert_eq!(body(input), "title\n\nbody");
         assert_eq!(block(input), Some("Refs: https://example.com:8080/path"));
     }
+
+    #[test]
+    fn parse_pr_options_across_commits() {
+        let options = parse_pr_options([
+            "feat: a\n\nStakk-Reviewer: alice, org/core\nstakk-label: backend",
+            "feat: b\n\nbody\n\nSigned-off-by: Bob <b@c>\nStakk-Draft: yes\nStakk-Reviewer: alice",
+            "feat: c\n\nStakk-Base: release",
+        ])
+        .unwrap();
+        assert_eq!(
+            options,
+            PrOptions {
+                draft: Some(true),
+                reviewers: vec!["alice".to_string(), "org/core".to_string()],
+                labels: vec!["backend".to_string()],
+                base: Some("release".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_pr_options_rejects_bad_values() {
+        assert_eq!(
+            parse_pr_options(["feat\n\nStakk-Draft: maybe"]),
+            Err("Stakk-Draft: maybe".to_string())
+        );
+        assert_eq!(
+            parse_pr_options(["feat\n\nStakk-Assignee: bob"]),
+            Err("Stakk-Assignee: bob".to_string())
+        );
+    }
+
+    #[test]
+    fn without_stakk_trailers_keeps_others() {
+        assert_eq!(
+            without_stakk_trailers("Refs: X\nStakk-Label: a\nPR: https://github.com/o/r/pull/7")
+                .as_deref(),
+            Some("Refs: X")
+        );
+        assert_eq!(without_stakk_trailers("Stakk-Label: a"), None);
+        assert_eq!(
+            without_stakk_trailers("pr: https://github.com/o/r/pull/7"),
+            None
+        );
+    }
+
+    #[test]
+    fn has_signoff_matches_the_email() {
+        let description = "Fix\n\nsigned-off-by: Me <Me@Example.com>\n";
+        assert!(has_signoff(description, "me@example.com"));
+        assert!(!has_signoff(description, "other@example.com"));
+        assert!(!has_signoff("Fix\n", "me@example.com"));
+    }
+
+    #[test]
+    fn with_signoff_joins_the_trailer_block() {
+        assert_eq!(
+            with_signoff("Fix\n\nRefs: X\n", "Me", "me@example.com"),
+            "Fix\n\nRefs: X\nSigned-off-by: Me <me@example.com>\n"
+        );
+        assert_eq!(
+            with_signoff("Fix\n", "Me", "me@example.com"),
+            "Fix\n\nSigned-off-by: Me <me@example.com>\n"
+        );
+        assert_eq!(
+            with_signoff("", "Me", "me@example.com"),
+            "Signed-off-by: Me <me@example.com>\n"
+        );
+    }
+
+    #[test]
+    fn pr_trailer_is_added_once() {
+        let url = "https://github.com/o/r/pull/7";
+        let added = with_pr_trailer("Fix\n\nRefs: X\n", url).unwrap();
+        assert_eq!(added, format!("Fix\n\nRefs: X\nPR: {url}\n"));
+        assert_eq!(with_pr_trailer(&added, url), None);
+        assert_eq!(
+            with_pr_trailer("Fix", url).unwrap(),
+            format!("Fix\n\nPR: {url}\n")
+        );
+        assert_eq!(with_pr_trailer("", url).unwrap(), format!("PR: {url}\n"));
+    }
+
+    #[test]
+    fn amending_keeps_what_is_not_replaced() {
+        let description = "Fix\n\nOld details.\n\nPR: https://github.com/o/r/pull/7\n";
+        assert_eq!(
+            amend_description(description, Some("Fix the parser"), None),
+            "Fix the parser\n\nOld details.\n\nPR: https://github.com/o/r/pull/7\n"
+        );
+        assert_eq!(
+            amend_description(description, None, Some("New details.\n")),
+            "Fix\n\nNew details.\n\nPR: https://github.com/o/r/pull/7\n"
+        );
+        assert_eq!(amend_description("Fix\n\nOld.", None, Some("")), "Fix\n");
+    }
+
+    #[test]
+    fn pr_trailer_to_another_pr_is_replaced() {
+        let old = "Fix\n\nPR: https://github.com/o/r/pull/3\nRefs: X\n";
+        assert_eq!(
+            with_pr_trailer(old, "https://github.com/o/r/pull/7").unwrap(),
+            "Fix\n\nRefs: X\nPR: https://github.com/o/r/pull/7\n"
+        );
+        assert_eq!(
+            with_pr_trailer(
+                "Fix\n\nPR: https://github.com/o/r/pull/3",
+                "https://github.com/o/r/pull/7"
+            )
+            .unwrap(),
+            "Fix\n\nPR: https://github.com/o/r/pull/7\n"
+        );
+    }
+
+    #[test]
+    fn issue_references_stand_alone() {
+        let descriptions = [
+            "fix: handle empty input (#12)\n\nFixes #7, see #12.",
+            "docs: em dash &#8212; and other/repo#3 and a#4 and #5b\n\nRefs: #9",
+        ];
+        assert_eq!(issue_references(descriptions), [7, 9, 12]);
+    }
 }
@@ -0,0 +1,589 @@
+//! Cleaning up after merged pull requests.
+//!
+//! When GitHub squash- or rebase-merges a PR, the commits that land on the
+//! base branch are new ones, so the local change never shows up as merged in
+//! jj. `stakk sync` asks the forge instead: a bookmark whose latest merged PR
+//! had the bookmark's current commit as its head was merged as-is. Its
+//! changes are abandoned, its local bookmark deleted, and whatever was stacked
+//! on it is rebased onto trunk.
+//!
+//! Only a run of merged segments starting at trunk is cleaned up. A merged PR
+//! above an unmerged one still has its changes under the unmerged PR, and a
+//! bookmark that moved after its PR merged holds work that has not been
+//! merged; both are reported and left alone.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+
+use miette::Diagnostic;
+use stakk_core::forge::Forge;
+use stakk_core::forge::ForgeError;
+use stakk_core::forge::MergedPullRequest;
+use stakk_core::forge::markup::parse_change_id;
+use stakk_core::graph::types::BookmarkSegment;
+use stakk_core::graph::types::ChangeGraph;
+use stakk_core::graph::types::TrunkBranch;
+use stakk_core::jj::Jj;
+use stakk_core::jj::JjError;
+use stakk_core::jj::runner::JjRunner;
+use thiserror::Error;
+
+use crate::segment_test::label;
+
+/// Errors from `stakk sync`.
+#[derive(Debug, Error, Diagnostic)]
+#[expect(
+    clippy::enum_variant_names,
+    reason = "every step of a sync can fail; names mirror DeleteError's"
+)]
+pub enum SyncError {
+    /// Failed to look up a bookmark's merged PR.
+    #[error("failed to check for a merged PR for '{bookmark}'")]
+    #[diagnostic(
+        code(stakk::sync::pr_lookup_failed),
+        help("check your network connection and GitHub token permissions")
+    )]
+    PrLookupFailed {
+        bookmark: String,
+        #[source]
+        source: ForgeError,
+    },
+
+    /// Failed to rebase the unmerged rest of a stack onto trunk.
+    #[error("failed to rebase '{label}' onto trunk")]
+    #[diagnostic(
+        code(stakk::sync::rebase_failed),
+        help("nothing has been abandoned yet — resolve the problem and re-run `stakk sync`")
+    )]
+    RebaseFailed {
+        label: String,
+        #[source]
+        source: JjError,
+    },
+
+    /// Failed to delete a merged segment's local bookmark.
+    #[error("failed to delete bookmark '{bookmark}'")]
+    #[diagnostic(
+        code(stakk::sync::bookmark_delete_failed),
+        help("the stacks are rebased; finish with `jj bookmark delete` and `jj abandon`")
+    )]
+    BookmarkDeleteFailed {
+        bookmark: String,
+        #[source]
+        source: JjError,
+    },
+
+    /// Failed to abandon the merged changes.
+    #[error("failed to abandon the merged changes")]
+    #[diagnostic(
+        code(stakk::sync::abandon_failed),
+        help("the stacks are rebased and the bookmarks deleted; finish with `jj abandon`")
+    )]
+    AbandonFailed {
+        #[source]
+        source: JjError,
+    },
+}
+
+/// A segment whose PR was merged as the segment stands.
+#[derive(Debug)]
+pub struct MergedSegment {
+    /// The segment's local bookmarks.
+    pub bookmarks: Vec<String>,
+    /// The merged PR.
+    pub pr_number: u64,
+    /// The segment's commits, newest first.
+    pub commits: Vec<String>,
+}
+
+/// The unmerged rest of a stack, rebased onto trunk.
+#[derive(Debug)]
+pub struct Rebase {
+    /// The first bookmark or short change ID of the lowest unmerged segment.
+    pub label: String,
+    /// The oldest commit of that segment.
+    pub source: String,
+    /// The trunk pattern branch the stack forks off, rebased onto instead
+    /// of the trunk.
+    pub onto: Option<TrunkBranch>,
+}
+
+/// Why a segment with a merged PR is left alone.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The bookmark moved to other commits after the PR merged.
+    ChangedSinceMerge,
+    /// A segment below it in the stack is not merged.
+    AboveUnmerged { below: String },
+}
+
+/// What `stakk sync` will do.
+#[derive(Debug)]
+pub struct SyncPlan {
+    /// Merged segments to abandon, trunk first.
+    pub merged: Vec<MergedSegment>,
+    /// Stack remainders to rebase onto trunk.
+    pub rebases: Vec<Rebase>,
+    /// Segments with a merged PR that stay, with their PR numbers.
+    pub skipped: Vec<(String, u64, SkipReason)>,
+    /// The default branch name (e.g., "main").
+    pub default_branch: String,
+}
+
+impl SyncPlan {
+    /// Whether there is nothing to clean up.
+    pub fn is_empty(&self) -> bool {
+        self.merged.is_empty()
+    }
+}
+
+/// Look up the latest merged PR of each bookmarked segment's first
+/// bookmark, keyed by bookmark.
+pub async fn find_merged_prs<F: Forge>(
+    change_graph: &ChangeGraph,
+    forge: &F,
+) -> Result<HashMap<String, MergedPullRequest>, SyncError> {
+    let mut names: Vec<&String> = change_graph
+        .segments
+        .values()
+        .filter_map(|seg| seg.bookmark_names.first())
+        .collect();
+    names.sort();
+    let lookups = names.into_iter().map(|name| async move {
+        let pr = forge
+            .find_merged_pr_for_branch(name)
+            .await
+            .map_err(|source| SyncError::PrLookupFailed {
+                bookmark: name.clone(),
+                source,
+            })?;
+        Ok::<_, SyncError>(pr.map(|pr| (name.clone(), pr)))
+    });
+    let mut merged = HashMap::new();
+    for result in futures::future::join_all(lookups).await {
+        merged.extend(result?);
+    }
+    Ok(merged)
+}
+
+/// How a segment relates to the merged PR of its bookmark.
+enum MergeState {
+    /// No merged PR, or one that belonged to other changes.
+    Unmerged,
+    /// The PR merged with the segment's tip as its head.
+    Merged(u64),
+    /// The PR merged this change, but the change has been rewritten since.
+    ChangedSinceMerge(u64),
+}
+
+fn merge_state(
+    segment: &BookmarkSegment,
+    merged: &HashMap<String, MergedPullRequest>,
+) -> MergeState {
+    let Some(pr) = segment
+        .bookmark_names
+        .first()
+        .and_then(|name| merged.get(name))
+    else {
+        return MergeState::Unmerged;
+    };
+    if segment
+        .commits
+        .first()
+        .is_some_and(|tip| tip.commit_id == pr.head_sha)
+    {
+        return MergeState::Merged(pr.pr.number);
+    }
+    // A bookmark name can be reused for a later change; only a PR that
+    // recorded this change's ID makes its rewrite worth mentioning.
+    if pr.pr.body.as_deref().and_then(parse_change_id) == Some(segment.change_id.as_str()) {
+        MergeState::ChangedSinceMerge(pr.pr.number)
+    } else {
+        MergeState::Unmerged
+    }
+}
+
+/// Plan the clean-up of `merged` PRs: in each stack, the merged segments
+/// from trunk up to the first unmerged one are abandoned, and the rest of
+/// the stack is rebased onto trunk.
+pub fn plan_sync(
+    change_graph: &ChangeGraph,
+    merged: &HashMap<String, MergedPullRequest>,
+    default_branch: &str,
+) -> SyncPlan {
+    let mut plan = SyncPlan {
+        merged: Vec::new(),
+        rebases: Vec::new(),
+        skipped: Vec::new(),
+        default_branch: default_branch.to_string(),
+    };
+    let mut seen = HashSet::new();
+    for stack in &change_graph.stacks {
+        let onto = stack
+            .segments
+            .first()
+            .and_then(|root| change_graph.root_trunks.get(&root.change_id));
+        let mut below_unmerged: Option<String> = None;
+        let mut any_merged = false;
+        for segment in &stack.segments {
+            let state = merge_state(segment, merged);
+            let first_visit = seen.insert(segment.change_id.as_str());
+            if let Some(below) = &below_unmerged {
+                if let MergeState::Merged(number) | MergeState::ChangedSinceMerge(number) = state
+                    && first_visit
+                {
+                    plan.skipped.push((
+                        label(segment),
+                        number,
+                        SkipReason::AboveUnmerged {
+                            below: below.clone(),
+                        },
+                    ));
+                }
+                continue;
+            }
+            match state {
+                MergeState::Merged(pr_number) => {
+                    any_merged = true;
+                    if first_visit {
+                        plan.merged.push(MergedSegment {
+                            bookmarks: segment.bookmark_names.clone(),
+                            pr_number,
+                            commits: segment
+                                .commits
+                                .iter()
+                                .map(|c| c.commit_id.clone())
+                                .collect(),
+                        });
+                    }
+                }
+                MergeState::ChangedSinceMerge(_) | MergeState::Unmerged => {
+                    if let MergeState::ChangedSinceMerge(number) = state
+                        && first_visit
+                    {
+                        plan.skipped
+                            .push((label(segment), number, SkipReason::ChangedSinceMerge));
+                    }
+                    if any_merged
+                        && first_visit
+                        && let Some(oldest) = segment.commits.last()
+                    {
+                        plan.rebases.push(Rebase {
+                            label: label(segment),
+                            source: oldest.commit_id.clone(),
+                            onto: onto.cloned(),
+                        });
+                    }
+                    below_unmerged = Some(label(segment));
+                }
+            }
+        }
+    }
+    plan
+}
+
+impl fmt::Display for SyncPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.merged.is_empty() {
+            writeln!(f, "Nothing to sync: no merged PRs at the base of a stack.")?;
+        } else {
+            writeln!(f, "Cleaning up {} merged PR(s):", self.merged.len())?;
+            for segment in &self.merged {
+                writeln!(
+                    f,
+                    "  - {} (#{}): abandon {} commit(s), delete the bookmark",
+                    segment.bookmarks.join(", "),
+                    segment.pr_number,
+                    segment.commits.len()
+                )?;
+            }
+        }
+        for rebase in &self.rebases {
+            writeln!(
+                f,
+                "  - rebase '{}' and its descendants onto trunk ({})",
+                rebase.label,
+                rebase
+                    .onto
+                    .as_ref()
+                    .map_or(self.default_branch.as_str(), |trunk| trunk.branch.as_str())
+            )?;
+        }
+        if !self.skipped.is_empty() {
+            writeln!(f, "Skipped:")?;
+            for (label, number, reason) in &self.skipped {
+                match reason {
+                    SkipReason::ChangedSinceMerge => {
+                        writeln!(f, "  - {label} (#{number}): changed since the PR merged")?;
+                    }
+                    SkipReason::AboveUnmerged { below } => {
+                        writeln!(f, "  - {label} (#{number}): stacked on unmerged '{below}'")?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Execute the sync plan: rebase the stack remainders first, so that
+/// abandoning the merged changes leaves nothing behind on them.
+pub async fn execute_sync_plan<R: JjRunner>(plan: &SyncPlan, jj: &Jj<R>) -> Result<(), SyncError> {
+    for rebase in &plan.rebases {
+        let onto = match &rebase.onto {
+            Some(trunk) => trunk.revset(),
+            None => jj.trunk().to_string(),
+        };
+        jj.rebase(&rebase.source, &onto)
+            .await
+            .map_err(|source| SyncError::RebaseFailed {
+                label: rebase.label.clone(),
+                source,
+            })?;
+    }
+
+    // The bookmarks go before the abandon: depending on the jj version,
+    // `jj abandon` either deletes them or moves them to the parent.
+    for bookmark in plan.merged.iter().flat_map(|seg| &seg.bookmarks) {
+        jj.delete_bookmark(bookmark)
+            .await
+            .map_err(|source| SyncError::BookmarkDeleteFailed {
+                bookmark: bookmark.clone(),
+                source,
+            })?;
+    }
+    let commits: Vec<String> = plan
+        .merged
+        .iter()
+        .flat_map(|seg| seg.commits.iter().cloned())
+        .collect();
+    if !commits.is_empty() {
+        jj.abandon(&commits)
+            .await
+            .map_err(|source| SyncError::AbandonFailed { source })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use stakk_core::forge::PrState;
+    use stakk_core::forge::PullRequest;
+    use stakk_core::forge::markup::splice_change_id_into_body;
+    use stakk_core::graph::types::BranchStack;
+    use stakk_core::graph::types::SegmentCommit;
+    use stakk_core::jj::types::Signature;
+
+    use super::*;
+
+    /// Records every jj invocation.
+    struct MockJjRunner {
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl JjRunner for MockJjRunner {
+        fn run_jj(
+            &self,
+            args: &[&str],
+        ) -> impl std::future::Future<Output = Result<String, JjError>> + Send {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("jj {}", args.join(" ")));
+            async { Ok(String::new()) }
+        }
+    }
+
+    fn commit(commit_id: &str) -> SegmentCommit {
+        let sig = Signature {
+            name: "Test".to_string(),
+            email: "test@example.com".to_string(),
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+        };
+        SegmentCommit {
+            commit_id: commit_id.to_string(),
+            change_id: format!("ch_{commit_id}"),
+            description: String::new(),
+            author: sig.clone(),
+            committer: sig,
+            short_change_id: commit_id.to_string(),
+            files: vec![],
+            is_immutable: false,
+            local_bookmark_names: vec![],
+        }
+    }
+
+    /// A segment `name` with commits `c_<name>` (tip) and `c_<name>_0`.
+    fn segment(name: &str) -> BookmarkSegment {
+        BookmarkSegment {
+            bookmark_names: vec![name.to_string()],
+            change_id: format!("ch_{name}"),
+            commits: vec![commit(&format!("c_{name}")), commit(&format!("c_{name}_0"))],
+        }
+    }
+
+    fn graph(stacks: &[&[&str]]) -> ChangeGraph {
+        let stacks: Vec<BranchStack> = stacks
+            .iter()
+            .map(|names| BranchStack {
+                segments: names.iter().map(|n| segment(n)).collect(),
+            })
+            .collect();
+        ChangeGraph {
+            adjacency_list: HashMap::new(),
+            stack_leaves: HashSet::new(),
+            stack_roots: HashSet::new(),
+            segments: stacks
+                .iter()
+                .flat_map(|stack| &stack.segments)
+                .map(|seg| (seg.change_id.clone(), seg.clone()))
+                .collect(),
+            tainted_change_ids: HashSet::new(),
+            excluded_bookmark_count: 0,
+            tainted_bookmarks: vec![],
+            private_bookmarks: HashMap::new(),
+            conflicted_bookmarks: vec![],
+            root_trunks: HashMap::new(),
+            stacks,
+        }
+    }
+
+    /// A merged PR for bookmark `name` whose head was `head_sha`.
+    fn merged_pr(number: u64, name: &str, head_sha: &str) -> (String, MergedPullRequest) {
+        let body = splice_change_id_into_body("Body.", &format!("ch_{name}"));
+        (
+            name.to_string(),
+            MergedPullRequest {
+                pr: PullRequest {
+                    number,
+                    html_url: format!("https://github.com/test/repo/pull/{number}"),
+                    title: format!("PR for {name}"),
+                    head_ref: name.to_string(),
+                    base_ref: "main".to_string(),
+                    state: PrState::Closed,
+                    body: Some(body),
+                    draft: false,
+                },
+                head_sha: head_sha.to_string(),
+            },
+        )
+    }
+
+    #[test]
+    fn merged_base_is_abandoned_and_rest_rebased() {
+        let graph = graph(&[&["a", "b", "c"], &["a", "d"]]);
+        let merged = HashMap::from([merged_pr(1, "a", "c_a")]);
+        let plan = plan_sync(&graph, &merged, "main");
+
+        assert_eq!(plan.merged.len(), 1);
+        assert_eq!(plan.merged[0].bookmarks, ["a"]);
+        assert_eq!(plan.merged[0].commits, ["c_a", "c_a_0"]);
+        let sources: Vec<(&str, &str)> = plan
+            .rebases
+            .iter()
+            .map(|r| (r.label.as_str(), r.source.as_str()))
+            .collect();
+        assert_eq!(sources, [("b", "c_b_0"), ("d", "c_d_0")]);
+        assert!(plan.skipped.is_empty());
+    }
+
+    #[test]
+    fn merged_pr_above_unmerged_is_skipped() {
+        let graph = graph(&[&["a", "b", "c"]]);
+        let merged = HashMap::from([merged_pr(1, "a", "c_a"), merged_pr(3, "c", "c_c")]);
+        let plan = plan_sync(&graph, &merged, "main");
+
+        assert_eq!(plan.merged.len(), 1);
+        assert_eq!(plan.rebases.len(), 1);
+        assert_eq!(
+            plan.skipped,
+            [(
+                "c".to_string(),
+                3,
+                SkipReason::AboveUnmerged {
+                    below: "b".to_string()
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn head_mismatch_is_not_merged() {
+        let graph = graph(&[&["a", "b"]]);
+        // `a` was rewritten after its PR merged; `b`'s name was reused.
+        let (_, mut reused) = merged_pr(2, "b", "old");
+        reused.pr.body = None;
+        let merged = HashMap::from([merged_pr(1, "a", "old"), ("b".to_string(), reused)]);
+        let plan = plan_sync(&graph, &merged, "main");
+
+        assert!(plan.is_empty());
+        assert!(plan.rebases.is_empty());
+        assert_eq!(
+            plan.skipped,
+            [("a".to_string(), 1, SkipReason::ChangedSinceMerge)]
+        );
+    }
+
+    #[test]
+    fn whole_stack_merged_needs_no_rebase() {
+        let graph = graph(&[&["a", "b"]]);
+        let merged = HashMap::from([merged_pr(1, "a", "c_a"), merged_pr(2, "b", "c_b")]);
+        let plan = plan_sync(&graph, &merged, "main");
+
+        assert_eq!(plan.merged.len(), 2);
+        assert!(plan.rebases.is_empty());
+        assert_eq!(
+            plan.to_string(),
+            "Cleaning up 2 merged PR(s):\n  - a (#1): abandon 2 commit(s), delete the bookmark\n  \
+             - b (#2): abandon 2 commit(s), delete the bookmark\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn execute_rebases_before_abandoning() {
+        let graph = graph(&[&["a", "b"]]);
+        let merged = HashMap::from([merged_pr(1, "a", "c_a")]);
+        let plan = plan_sync(&graph, &merged, "main");
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let jj = Jj::new(MockJjRunner { log: log.clone() });
+
+        execute_sync_plan(&plan, &jj).await.unwrap();
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            [
+                "jj rebase -s c_b_0 -d trunk()",
+                "jj bookmark delete a",
+                "jj abandon c_a c_a_0",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn stacks_on_a_pattern_branch_rebase_onto_its_remote_bookmark() {
+        let mut graph = graph(&[&["a", "b"]]);
+        graph.root_trunks.insert(
+            "ch_a".to_string(),
+            TrunkBranch {
+                branch: "develop".to_string(),
+                remote: "upstream".to_string(),
+            },
+        );
+        let merged = HashMap::from([merged_pr(1, "a", "c_a")]);
+        let plan = plan_sync(&graph, &merged, "main");
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let jj = Jj::new(MockJjRunner { log: log.clone() });
+
+        execute_sync_plan(&plan, &jj).await.unwrap();
+
+        assert_eq!(
+            log.lock().unwrap()[0],
+            r#"jj rebase -s c_b_0 -d "develop"@"upstream""#
+        );
+    }
+}
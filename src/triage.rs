@@ -0,0 +1,190 @@
+//! Triage of the conflicts a `stakk sync` rebase leaves behind.
+//!
+//! jj never stops a rebase halfway: a change that no longer applies cleanly
+//! is rebased anyway and recorded as conflicted. After a sync, stakk lists
+//! the changes the rebase newly conflicted, with their conflicted files, and
+//! offers for each to resolve it in the merge tool (`jj resolve`), leave it
+//! for later, or restore the repository to the operation before the sync.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use stakk_core::jj::Jj;
+use stakk_core::jj::JjError;
+use stakk_core::jj::runner::JjRunner;
+use stakk_core::jj::runner::RealJjRunner;
+
+use crate::error::StakkError;
+
+/// The commits a sync can conflict: everything jj lets it rewrite.
+const REWRITABLE: &str = "mutable()";
+
+/// A change with conflicts.
+#[derive(Debug, Clone)]
+pub struct ConflictedChange {
+    pub change_id: String,
+    pub short_change_id: String,
+    /// The first line of its description.
+    pub summary: String,
+    pub files: Vec<String>,
+}
+
+impl fmt::Display for ConflictedChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let summary = if self.summary.is_empty() {
+            "(no description)"
+        } else {
+            &self.summary
+        };
+        writeln!(f, "  {} {summary}", self.short_change_id)?;
+        for file in &self.files {
+            writeln!(f, "    {file}")?;
+        }
+        Ok(())
+    }
+}
+
+/// An answer to the triage prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Choice {
+    /// Run `jj resolve` on the change.
+    Resolve,
+    /// Leave the change conflicted.
+    Skip,
+    /// Restore the repository to before the sync.
+    Abort,
+}
+
+/// Parse an answer to the triage prompt: a choice or its first letter.
+pub fn parse_choice(input: &str) -> Option<Choice> {
+    match input.trim().to_ascii_lowercase().as_str() {
+        "r" | "resolve" => Some(Choice::Resolve),
+        "s" | "skip" => Some(Choice::Skip),
+        "a" | "abort" => Some(Choice::Abort),
+        _ => None,
+    }
+}
+
+/// How triage ended.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// Every change was resolved or skipped; `remaining` are still
+    /// conflicted.
+    Done { remaining: usize },
+    /// The repository was restored to before the sync.
+    Aborted,
+}
+
+/// The change IDs of the rewritable changes that have conflicts.
+pub async fn conflicted_change_ids<R: JjRunner>(jj: &Jj<R>) -> Result<HashSet<String>, JjError> {
+    Ok(jj
+        .get_conflicts(REWRITABLE)
+        .await?
+        .into_iter()
+        .map(|entry| entry.change_id)
+        .collect())
+}
+
+/// The conflicted changes not in `before`, oldest first: resolving a change
+/// can resolve the changes stacked on it too.
+pub async fn new_conflicts<R: JjRunner>(
+    jj: &Jj<R>,
+    before: &HashSet<String>,
+) -> Result<Vec<ConflictedChange>, JjError> {
+    let mut conflicts = Vec::new();
+    for entry in jj.get_conflicts(REWRITABLE).await?.into_iter().rev() {
+        if before.contains(&entry.change_id) {
+            continue;
+        }
+        conflicts.push(ConflictedChange {
+            files: jj.get_conflicted_files(&entry.commit_id).await?,
+            summary: entry.description.lines().next().unwrap_or("").to_string(),
+            change_id: entry.change_id,
+            short_change_id: entry.short_change_id,
+        });
+    }
+    Ok(conflicts)
+}
+
+/// Ask what to do with each of `conflicts`. Aborting restores `operation`.
+/// Without a terminal, nothing is asked and every change stays conflicted.
+pub async fn triage(
+    jj: &Jj<RealJjRunner>,
+    conflicts: &[ConflictedChange],
+    operation: &str,
+) -> Result<Outcome, StakkError> {
+    let term = console::Term::stderr();
+    if !term.is_term() {
+        return Ok(Outcome::Done {
+            remaining: conflicts.len(),
+        });
+    }
+
+    let mut remaining = 0;
+    for change in conflicts {
+        loop {
+            // A resolution further down the stack may have fixed it.
+            let files = if jj.get_conflicts(&change.change_id).await?.is_empty() {
+                Vec::new()
+            } else {
+                jj.get_conflicted_files(&change.change_id).await?
+            };
+            if files.is_empty() {
+                break;
+            }
+            eprint!(
+                "{}",
+                ConflictedChange {
+                    files,
+                    ..change.clone()
+                }
+            );
+            eprint!("[r]esolve, [s]kip, or [a]bort the sync? ");
+            match parse_choice(&term.read_line()?) {
+                Some(Choice::Resolve) => {
+                    if let Err(e) = jj.resolve_interactively(&change.change_id).await {
+                        eprintln!("{e}");
+                    }
+                }
+                Some(Choice::Skip) => {
+                    remaining += 1;
+                    break;
+                }
+                Some(Choice::Abort) => {
+                    jj.restore_operation(operation).await?;
+                    return Ok(Outcome::Aborted);
+                }
+                None => eprintln!("Answer r, s, or a."),
+            }
+        }
+    }
+    Ok(Outcome::Done { remaining })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn choices_accept_words_and_initials() {
+        assert_eq!(parse_choice("r\n"), Some(Choice::Resolve));
+        assert_eq!(parse_choice(" Skip "), Some(Choice::Skip));
+        assert_eq!(parse_choice("a"), Some(Choice::Abort));
+        assert_eq!(parse_choice(""), None);
+        assert_eq!(parse_choice("x"), None);
+    }
+
+    #[test]
+    fn change_lists_its_files() {
+        let change = ConflictedChange {
+            change_id: "qpvuntsmwlqt".to_string(),
+            short_change_id: "qpvu".to_string(),
+            summary: String::new(),
+            files: vec!["src/lib.rs".to_string(), "README.md".to_string()],
+        };
+        assert_eq!(
+            change.to_string(),
+            "  qpvu (no description)\n    src/lib.rs\n    README.md\n"
+        );
+    }
+}
@@ -0,0 +1,367 @@
+//! End-to-end tests against real jj repositories.
+//!
+//! Each test builds a repository in a temporary directory with the `jj` on
+//! PATH, pushes `main` to a second jj repository serving as the remote, and
+//! runs graph construction and submission analysis through `RealJjRunner`.
+//! Unlike the mock-runner unit tests, these catch template and CLI changes
+//! between jj versions.
+//!
+//! Run with `cargo nextest run --features e2e --test e2e`.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+use stakk_core::graph::DEFAULT_BOOKMARKS_REVSET;
+use stakk_core::graph::DEFAULT_HEADS_REVSET;
+use stakk_core::graph::build_change_graph;
+use stakk_core::graph::invariants::check_invariants;
+use stakk_core::graph::types::ChangeGraph;
+use stakk_core::jj::Jj;
+use stakk_core::jj::PushOptions;
+use stakk_core::jj::runner::RealJjRunner;
+use stakk_core::jj::templates;
+use stakk_core::jj::workspace::TempWorkspace;
+use stakk_core::submit::analyze_submission;
+use stakk_core::submit::signed_off_description;
+use stakk_core::submit::unsigned_commits;
+
+/// A jj repository with an `origin` remote, removed on drop.
+struct TestRepo {
+    root: PathBuf,
+}
+
+impl TestRepo {
+    /// A repository whose `main` (one commit) is pushed to `origin`, with an
+    /// empty working-copy change on top of it.
+    fn new(name: &str) -> Self {
+        let root = std::env::temp_dir().join(format!("stakk-e2e-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        let repo = Self { root };
+
+        // The backing git store of a non-colocated jj repo is bare, so it
+        // accepts pushes without `git` on PATH.
+        run(&repo.root, "jj", &["git", "init", "origin"]);
+        run(&repo.root, "jj", &["git", "init", "repo"]);
+        // Repo-level identity, so `mine()` matches in stakk's own jj calls.
+        repo.jj(&["config", "set", "--repo", "user.name", "stakk e2e"]);
+        repo.jj(&["config", "set", "--repo", "user.email", "e2e@stakk.invalid"]);
+        repo.jj(&["new", "root()"]);
+        repo.jj(&[
+            "git",
+            "remote",
+            "add",
+            "origin",
+            "../origin/.jj/repo/store/git",
+        ]);
+
+        repo.commit("initial", "README");
+        repo.jj(&["bookmark", "create", "main", "-r", "@-"]);
+        repo.jj(&[
+            "--config",
+            "git.push-new-bookmarks=true",
+            "git",
+            "push",
+            "--remote",
+            "origin",
+            "--bookmark",
+            "main",
+        ]);
+        repo
+    }
+
+    fn path(&self) -> PathBuf {
+        self.root.join("repo")
+    }
+
+    fn jj(&self, args: &[&str]) -> String {
+        run(&self.path(), "jj", args)
+    }
+
+    /// Write `file` and commit it as `message`, leaving an empty change on
+    /// top.
+    fn commit(&self, message: &str, file: &str) {
+        std::fs::write(self.path().join(file), message).unwrap();
+        self.jj(&["commit", "-m", message]);
+    }
+
+    fn bookmark(&self, name: &str) {
+        self.jj(&["bookmark", "create", name, "-r", "@-"]);
+    }
+
+    fn stakk_jj(&self) -> Jj<RealJjRunner> {
+        Jj::new(RealJjRunner::default().in_dir(self.path()))
+    }
+
+    async fn graph(&self) -> ChangeGraph {
+        build_change_graph(
+            &self.stakk_jj(),
+            DEFAULT_BOOKMARKS_REVSET,
+            DEFAULT_HEADS_REVSET,
+            None,
+        )
+        .await
+        .unwrap()
+    }
+}
+
+impl Drop for TestRepo {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+fn run(dir: &Path, program: &str, args: &[&str]) -> String {
+    let output = Command::new(program)
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap_or_else(|e| panic!("failed to run {program}: {e}"));
+    assert!(
+        output.status.success(),
+        "{program} {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+/// The first bookmark of each segment of each stack, trunk to leaf.
+fn stack_bookmarks(graph: &ChangeGraph) -> Vec<Vec<String>> {
+    let mut stacks: Vec<Vec<String>> = graph
+        .stacks
+        .iter()
+        .map(|stack| {
+            stack
+                .segments
+                .iter()
+                .map(|seg| seg.bookmark_names.first().cloned().unwrap_or_default())
+                .collect()
+        })
+        .collect();
+    stacks.sort();
+    stacks
+}
+
+#[tokio::test]
+async fn linear_stack() {
+    let repo = TestRepo::new("linear");
+    repo.commit("feat: a", "a");
+    repo.bookmark("feat-a");
+    repo.commit("feat: b", "b");
+    repo.bookmark("feat-b");
+
+    let jj = repo.stakk_jj();
+    assert_eq!(jj.get_default_branch().await.unwrap(), "main");
+
+    let graph = repo.graph().await;
+    assert_eq!(stack_bookmarks(&graph), [["feat-a", "feat-b"]]);
+    let leaf = &graph.stacks[0].segments[1];
+    assert_eq!(leaf.commits.len(), 1);
+    assert_eq!(leaf.commits[0].description.trim(), "feat: b");
+}
+
+/// Every template set written for the installed jj must produce the same
+/// graph as the newest one.
+#[tokio::test]
+async fn template_sets_agree() {
+    let repo = TestRepo::new("templates");
+    repo.commit("feat: a", "a");
+    repo.bookmark("feat-a");
+
+    let version = repo.stakk_jj().version().await.unwrap();
+    let expected = stack_bookmarks(&repo.graph().await);
+    for set in templates::candidates(version) {
+        let jj = repo.stakk_jj().with_templates(set);
+        assert_eq!(
+            jj.select_templates(Some(set.min_version)).await.ok(),
+            Some(set)
+        );
+        let graph = build_change_graph(&jj, DEFAULT_BOOKMARKS_REVSET, DEFAULT_HEADS_REVSET, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            stack_bookmarks(&graph),
+            expected,
+            "template set {}",
+            set.name
+        );
+    }
+}
+
+#[tokio::test]
+async fn branching_stacks_share_their_base() {
+    let repo = TestRepo::new("branching");
+    repo.commit("feat: base", "base");
+    repo.bookmark("base");
+    repo.commit("feat: left", "left");
+    repo.bookmark("left");
+    repo.jj(&["new", "base"]);
+    repo.commit("feat: right", "right");
+    repo.bookmark("right");
+
+    let graph = repo.graph().await;
+    assert_eq!(
+        stack_bookmarks(&graph),
+        [["base", "left"], ["base", "right"]]
+    );
+    let bookmarks = repo
+        .stakk_jj()
+        .get_my_bookmarks(DEFAULT_BOOKMARKS_REVSET)
+        .await
+        .unwrap();
+    check_invariants(&graph, &bookmarks).unwrap();
+}
+
+#[tokio::test]
+async fn unbookmarked_head_becomes_a_segment() {
+    let repo = TestRepo::new("heads");
+    repo.commit("feat: a", "a");
+    repo.bookmark("feat-a");
+    repo.commit("wip: no bookmark yet", "wip");
+
+    let graph = repo.graph().await;
+    assert_eq!(graph.stacks.len(), 1);
+    let segments = &graph.stacks[0].segments;
+    assert_eq!(segments.len(), 2);
+    assert_eq!(segments[0].bookmark_names, ["feat-a"]);
+    assert!(segments[1].bookmark_names.is_empty());
+}
+
+#[tokio::test]
+async fn analysis_and_sync_state_after_push() {
+    let repo = TestRepo::new("push");
+    repo.commit("feat: a", "a");
+    repo.bookmark("feat-a");
+    repo.commit("feat: b", "b");
+    repo.bookmark("feat-b");
+
+    let jj = repo.stakk_jj();
+    jj.push_bookmark("feat-a", "origin", PushOptions::default())
+        .await
+        .unwrap();
+    let synced = jj
+        .get_synced_bookmarks(DEFAULT_BOOKMARKS_REVSET, "origin")
+        .await
+        .unwrap();
+    assert_eq!(synced, HashSet::from(["feat-a".to_string()]));
+
+    let graph = repo.graph().await;
+    let analysis = analyze_submission(
+        "feat-b",
+        &graph,
+        "main",
+        &HashSet::from(["feat-b".to_string()]),
+    )
+    .unwrap();
+    let bookmarks: Vec<&str> = analysis
+        .segments
+        .iter()
+        .map(|seg| seg.bookmark_names[0].as_str())
+        .collect();
+    assert_eq!(bookmarks, ["feat-a", "feat-b"]);
+    assert_eq!(analysis.default_branch, "main");
+}
+
+/// The visible heads; a temporary workspace left behind shows up as an
+/// extra one.
+fn head_count(repo: &TestRepo) -> usize {
+    repo.jj(&[
+        "log",
+        "--no-graph",
+        "-r",
+        "heads(all())",
+        "-T",
+        "commit_id ++ \"\\n\"",
+    ])
+    .lines()
+    .count()
+}
+
+#[tokio::test]
+async fn temporary_workspace_leaves_no_change_behind() {
+    let repo = TestRepo::new("workspace");
+    repo.commit("feat: a", "a");
+    repo.bookmark("feat-a");
+
+    let jj = repo.stakk_jj();
+    let workspace = TempWorkspace::create(&jj, "feat-a").await.unwrap();
+    let path = workspace.path().to_path_buf();
+    assert!(path.join("a").exists());
+    workspace.destroy().await.unwrap();
+
+    assert!(!path.exists());
+    assert_eq!(head_count(&repo), 1);
+}
+
+#[tokio::test]
+async fn dropped_temporary_workspace_is_cleaned_up() {
+    let repo = TestRepo::new("workspace-drop");
+    repo.commit("feat: a", "a");
+    repo.bookmark("feat-a");
+
+    let jj = repo.stakk_jj();
+    let workspace = TempWorkspace::create(&jj, "feat-a").await.unwrap();
+    let path = workspace.path().to_path_buf();
+    drop(workspace);
+
+    assert!(!path.exists());
+    assert_eq!(head_count(&repo), 1);
+}
+
+#[tokio::test]
+async fn push_size_counts_new_and_replaced_commits() {
+    let repo = TestRepo::new("push-size");
+    repo.commit("feat: a", "a");
+    repo.commit("feat: b", "b");
+    repo.bookmark("feat-b");
+
+    let jj = repo.stakk_jj();
+    let size = jj.get_push_size("feat-b", "origin").await.unwrap();
+    assert_eq!((size.new_commits, size.replaced_commits), (2, 0));
+
+    jj.push_bookmark("feat-b", "origin", PushOptions::default())
+        .await
+        .unwrap();
+    repo.jj(&["describe", "-r", "feat-b", "-m", "feat: b, reworded"]);
+    let size = jj.get_push_size("feat-b", "origin").await.unwrap();
+    assert_eq!((size.new_commits, size.replaced_commits), (1, 1));
+}
+
+#[tokio::test]
+async fn operations_record_the_stakk_action() {
+    let repo = TestRepo::new("action");
+    repo.commit("feat: a", "a");
+
+    let jj = repo.stakk_jj().with_action("submit");
+    jj.create_bookmark("feat-a", "@-").await.unwrap();
+
+    let tags = repo.jj(&["op", "log", "--no-graph", "-n", "1", "-T", "tags"]);
+    assert!(tags.contains("stakk.action"), "tags: {tags}");
+}
+
+#[tokio::test]
+async fn signoff_fix_satisfies_the_check() {
+    let repo = TestRepo::new("signoff");
+    repo.commit("feat: a", "a");
+    repo.bookmark("feat-a");
+    repo.commit("feat: b", "b");
+    repo.bookmark("feat-b");
+
+    let selected = HashSet::from(["feat-a".to_string(), "feat-b".to_string()]);
+    let analysis = analyze_submission("feat-b", &repo.graph().await, "main", &selected).unwrap();
+    let unsigned = unsigned_commits(&analysis);
+    assert_eq!(unsigned.len(), 2);
+
+    let jj = repo.stakk_jj();
+    for commit in unsigned {
+        jj.describe(&commit.change_id, &signed_off_description(commit))
+            .await
+            .unwrap();
+    }
+
+    let analysis = analyze_submission("feat-b", &repo.graph().await, "main", &selected).unwrap();
+    assert!(unsigned_commits(&analysis).is_empty());
+}